@@ -145,6 +145,13 @@ async fn main() {
         }
     }
 
+    // 如果执行自检
+    if args.self_test {
+        let report = DoHServer::new(config, false).self_test().await;
+        println!("{}", report);
+        exit(if report.passed() { 0 } else { 1 });
+    }
+
     info!("Initializing Oxide WDNS server...");
     
     // 创建 DoHServer 实例，传入debug参数
@@ -9,10 +9,17 @@
 //! - 可定制 DNS 查询参数（域名、记录类型、DNSSEC 等）
 //! - 显示详细的请求和响应信息
 //! - 支持通过条件验证响应内容
+//! - 支持持续监控域名应答变化
+//! - 支持对比多个 DoH 服务器的应答 (compare 子命令)
+//! - 支持生成服务器配置模板 (gen-config 子命令)
 
 use mimalloc::MiMalloc;
 use clap::Parser;
-use oxide_wdns::client::{CliArgs, run_query, print_error};
+use oxide_wdns::client::{
+    CliArgs, CompareArgs, GenConfigArgs, run_query, run_watch, run_compare, display_compare_report,
+    run_gen_config, print_error, read_stdin_payload,
+};
+use oxide_wdns::common::consts::{COMPARE_EXIT_DISAGREE, COMPARE_SUBCOMMAND, GEN_CONFIG_SUBCOMMAND};
 
 // 使用 mimalloc 作为全局内存分配器
 #[global_allocator]
@@ -20,8 +27,18 @@ static GLOBAL: MiMalloc = MiMalloc;
 
 #[tokio::main]
 async fn main() {
+    // compare 子命令：并发对比多个 DoH 服务器的应答
+    if std::env::args().nth(1).as_deref() == Some(COMPARE_SUBCOMMAND) {
+        run_compare_command().await;
+    }
+    
+    // gen-config 子命令：生成服务器配置模板
+    if std::env::args().nth(1).as_deref() == Some(GEN_CONFIG_SUBCOMMAND) {
+        run_gen_config_command();
+    }
+    
     // 解析命令行参数
-    let args = CliArgs::parse();
+    let mut args = CliArgs::parse();
     
     // 初始化全局颜色控制
     colored::control::set_override(!args.no_color);
@@ -32,6 +49,23 @@ async fn main() {
         std::process::exit(1);
     }
     
+    // 从标准输入读取查询载荷 (--payload -)
+    if let Err(err) = read_stdin_payload(&mut args) {
+        print_error(&err);
+        std::process::exit(1);
+    }
+    
+    // 监控模式：持续查询并在应答变化时输出，退出码反映是否检测到变化
+    if args.watch.is_some() {
+        match run_watch(args).await {
+            Ok(summary) => std::process::exit(summary.exit_code()),
+            Err(err) => {
+                print_error(&err);
+                std::process::exit(1);
+            }
+        }
+    }
+    
     // 执行 DNS 查询
     match run_query(args).await {
         Ok(_) => {}
@@ -41,4 +75,43 @@ async fn main() {
             std::process::exit(1);
         }
     }
-} 
\ No newline at end of file
+}
+
+// 执行 compare 子命令，退出码: 0 表示全部一致，2 表示存在差异，1 表示参数或执行错误
+async fn run_compare_command() -> ! {
+    let args = CompareArgs::parse_from(std::env::args().skip(1));
+    
+    colored::control::set_override(!args.no_color);
+    
+    if let Err(err) = args.validate() {
+        eprintln!("Argument error: {}", err);
+        std::process::exit(1);
+    }
+    
+    let result = match run_compare(&args).await {
+        Ok(report) => display_compare_report(&report, args.json).map(|_| report.agree),
+        Err(err) => Err(err),
+    };
+    
+    match result {
+        Ok(true) => std::process::exit(0),
+        Ok(false) => std::process::exit(COMPARE_EXIT_DISAGREE),
+        Err(err) => {
+            print_error(&err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// 执行 gen-config 子命令，退出码: 0 表示成功，1 表示参数错误或生成的配置无效
+fn run_gen_config_command() -> ! {
+    let args = GenConfigArgs::parse_from(std::env::args().skip(1));
+    
+    match run_gen_config(args) {
+        Ok(()) => std::process::exit(0),
+        Err(err) => {
+            print_error(&err);
+            std::process::exit(1);
+        }
+    }
+}
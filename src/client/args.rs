@@ -7,9 +7,12 @@
 use anyhow::Result;
 use clap::{Parser, ValueEnum, ArgAction};
 use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use crate::common::consts::{default_listen_addr, DEFAULT_GEN_CONFIG_UPSTREAM, STDIN_PAYLOAD};
 
 // HTTP 格式支持的 DoH 请求
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum DohFormat {
     // Wire 格式 (application/dns-message)
     Wire,
@@ -62,6 +65,52 @@ impl fmt::Display for HttpVersion {
     }
 }
 
+// 从标准输入读取的查询载荷格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PayloadFormat {
+    // 十六进制文本（忽略空白字符）
+    Hex,
+    // 原始二进制 DNS 报文
+    Binary,
+}
+
+impl fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadFormat::Hex => write!(f, "hex"),
+            PayloadFormat::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+// 命令行指定的 EDNS 选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdnsOptionArg {
+    // 选项代码
+    pub code: u16,
+    // 选项数据
+    pub data: Vec<u8>,
+}
+
+// 解析 CODE:HEXVALUE 格式的 EDNS 选项
+fn parse_edns_option(value: &str) -> std::result::Result<EdnsOptionArg, String> {
+    let (code, data) = value
+        .split_once(':')
+        .ok_or_else(|| format!("EDNS option must be in CODE:HEXVALUE format: {}", value))?;
+
+    // 0 与 65535 为保留代码
+    let code = code
+        .trim()
+        .parse::<u16>()
+        .ok()
+        .filter(|code| *code != 0 && *code != u16::MAX)
+        .ok_or_else(|| format!("EDNS option code must be between 1 and 65534: {}", code))?;
+    let data = hex::decode(data.trim())
+        .map_err(|e| format!("Invalid hex value for EDNS option {}: {}", code, e))?;
+
+    Ok(EdnsOptionArg { code, data })
+}
+
 // Oxide WDNS DoH 客户端命令行工具
 #[derive(Parser, Debug)]
 #[command(
@@ -74,7 +123,9 @@ impl fmt::Display for HttpVersion {
              - DNSSEC validation requests\n\
              - GET/POST method selection (automatic or manual)\n\
              - HTTP/1.1 and HTTP/2 support\n\
-             - Response analysis and validation\n\n\
+             - Response analysis and validation\n\
+             - Multi-server answer comparison (owdns-cli compare --help)\n\
+             - Server configuration template generation (owdns-cli gen-config --help)\n\n\
              Author: shengyanli1982\n\
              Email: shengyanlee36@gmail.com\n\
              GitHub: https://github.com/shengyanli1982"
@@ -158,16 +209,49 @@ pub struct CliArgs {
     )]
     pub dnssec: bool,
 
+    // 请求服务器标识 (EDNS NSID)
+    //
+    // 在查询中附加空的 NSID 选项（RFC 5001），
+    // 并在详细输出中显示服务器返回的标识，便于排查多后端部署
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Request the server identifier (EDNS NSID) and show it in verbose output"
+    )]
+    pub nsid: bool,
+
+    // 附加任意 EDNS 选项（可重复）
+    //
+    // 格式为 CODE:HEXVALUE，例如 --edns-option 15:0017 或 --edns-option 65001:
+    // 选项附加到查询的 OPT 记录中，仅对 wire 格式生效，用于测试服务器对各类 EDNS 选项的处理
+    #[arg(
+        long = "edns-option",
+        value_name = "CODE:HEXVALUE",
+        value_parser = parse_edns_option,
+        help = "Attach an EDNS option to the query as CODE:HEXVALUE (repeatable, e.g., 15:0017)"
+    )]
+    pub edns_options: Vec<EdnsOptionArg>,
+
     // 发送原始 DNS 查询载荷 (十六进制编码)
     //
     // 提供一个原始的、十六进制编码的 DNS 消息作为查询载荷
     // 指定时，将覆盖域名和记录类型参数
     // 用于高级或边缘情况测试
+    // 指定为 - 时从标准输入读取载荷，格式由 --payload-format 决定
     #[arg(
         long,
-        help = "Send a raw, hex-encoded DNS query payload (overrides domain/type)"
+        help = "Send a raw, hex-encoded DNS query payload, or '-' to read it from stdin (overrides domain/type)"
     )]
     pub payload: Option<String>,
+
+    // 标准输入载荷的格式 (hex 或 binary)
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = PayloadFormat::Hex,
+        help = "Format of the payload read from stdin with '--payload -': 'hex' text or raw 'binary' wire format"
+    )]
+    pub payload_format: PayloadFormat,
     
     // 根据指定条件验证响应
     //
@@ -186,6 +270,39 @@ pub struct CliArgs {
     )]
     pub validate: Option<String>,
 
+    // 持续监控模式的查询间隔（秒）
+    //
+    // 按指定间隔重复查询，仅在应答集合发生变化时输出一行
+    // 与 --validate 一起使用时，应答不满足条件将输出告警
+    // 退出码: 0 表示检测到变化，2 表示未检测到变化，3 表示出现验证告警
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Re-query every SECONDS and print a line only when the answer set changes"
+    )]
+    pub watch: Option<u64>,
+
+    // 监控模式下检测到 N 次变化后停止
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "watch",
+        help = "Stop watching after N answer set changes (requires --watch)"
+    )]
+    pub watch_count: Option<usize>,
+
+    // 显示 CNAME 解析链
+    //
+    // 根据应答节中的 CNAME 记录输出完整的解析链，
+    // 例如 www.example.com. → CNAME → lb.example.net. → A → 1.2.3.4
+    // 与 --format json 一起使用时以有序 JSON 数组输出
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Show the resolution trace including any CNAME chain (a JSON array with --format json)"
+    )]
+    pub follow_cname: bool,
+
     // 跳过 TLS 证书验证
     //
     // 连接到服务器时禁用 TLS 证书验证
@@ -242,15 +359,30 @@ impl CliArgs {
             }
         }
 
-        // 如果提供了载荷，验证其是否为有效的十六进制字符串
+        // 如果提供了载荷，验证其是否为有效的十六进制字符串（- 表示从标准输入读取）
         if let Some(payload) = &self.payload {
-            if !payload.chars().all(|c| c.is_ascii_hexdigit()) {
+            if payload != STDIN_PAYLOAD && !payload.chars().all(|c| c.is_ascii_hexdigit()) {
                 return Err(anyhow::anyhow!(
                     "Payload must be a valid hex-encoded string"
                 ));
             }
         }
 
+        // 二进制载荷只能从标准输入读取
+        if self.payload_format == PayloadFormat::Binary && self.payload.as_deref() != Some(STDIN_PAYLOAD) {
+            return Err(anyhow::anyhow!(
+                "--payload-format binary requires --payload -"
+            ));
+        }
+
+        // 验证监控参数
+        if self.watch == Some(0) {
+            return Err(anyhow::anyhow!("Watch interval must be greater than 0"));
+        }
+        if self.watch_count == Some(0) {
+            return Err(anyhow::anyhow!("Watch count must be greater than 0"));
+        }
+
         // 验证记录类型
         if self.payload.is_none() {
             // 只有在未提供原始载荷时才验证记录类型
@@ -269,4 +401,189 @@ impl CliArgs {
 
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+// 多服务器对比子命令参数
+//
+// 用法: owdns-cli compare --servers <URL1,URL2,...> <DOMAIN>
+#[derive(Parser, Debug)]
+#[command(
+    name = "owdns-cli compare",
+    version,
+    about = "Query the same name against multiple DoH servers concurrently and compare the answer sets"
+)]
+pub struct CompareArgs {
+    // 参与对比的 DoH 服务器 URL 列表（逗号分隔）
+    #[arg(
+        long,
+        required = true,
+        value_delimiter = ',',
+        help = "Comma-separated DoH server endpoint URLs to compare"
+    )]
+    pub servers: Vec<String>,
+
+    // 要查询的域名
+    #[arg(required = true, help = "Domain name to query on every server (e.g., example.com)")]
+    pub domain: String,
+
+    // DNS 记录类型
+    #[arg(
+        short,
+        long = "record",
+        default_value = "A",
+        help = "DNS record type to query (e.g., A, AAAA, MX, TXT)"
+    )]
+    pub record_type: String,
+
+    // DoH 请求格式 (json 或 wire)
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = DohFormat::Wire,
+        help = "DoH request format: 'wire' (application/dns-message) or 'json' (application/dns-json)"
+    )]
+    pub format: DohFormat,
+
+    // HTTP 方法 (GET 或 POST)
+    #[arg(
+        short = 'X',
+        long,
+        value_enum,
+        help = "Force the HTTP method (GET or POST). Auto-selected if unspecified"
+    )]
+    pub method: Option<HttpMethod>,
+
+    // 在 DNS 查询中设置 DNSSEC OK (DO) 位
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Enable DNSSEC validation by setting the DNSSEC OK (DO) bit"
+    )]
+    pub dnssec: bool,
+
+    // 跳过 TLS 证书验证
+    #[arg(
+        short = 'k',
+        long,
+        action = ArgAction::SetTrue,
+        help = "Skip TLS certificate verification (use with caution)"
+    )]
+    pub insecure: bool,
+
+    // 以 JSON 格式输出对比结果
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Print the comparison report as JSON instead of a table"
+    )]
+    pub json: bool,
+
+    // 禁用终端中的彩色输出
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Disable colored output in the terminal"
+    )]
+    pub no_color: bool,
+}
+
+impl CompareArgs {
+    // 为指定服务器构建单次查询参数
+    pub fn query_args(&self, server_url: &str) -> CliArgs {
+        CliArgs {
+            server_url: server_url.to_string(),
+            domain: self.domain.clone(),
+            record_type: self.record_type.clone(),
+            format: self.format,
+            method: self.method,
+            http_version: None,
+            dnssec: self.dnssec,
+            nsid: false,
+            edns_options: Vec::new(),
+            payload: None,
+            payload_format: PayloadFormat::Hex,
+            validate: None,
+            insecure: self.insecure,
+            verbose: 0,
+            no_color: self.no_color,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
+        }
+    }
+
+    // 验证命令行参数
+    pub fn validate(&self) -> Result<()> {
+        if self.servers.len() < 2 {
+            return Err(anyhow::anyhow!("At least two servers are required for comparison"));
+        }
+
+        // 复用单次查询的参数校验（URL、记录类型）
+        for server in &self.servers {
+            self.query_args(server).validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+// 服务器配置模板生成子命令参数
+//
+// 用法: owdns-cli gen-config [--listen <ADDR>] [--upstream <URL1,URL2,...>] [--cache] [--routing] [-o <FILE>]
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "owdns-cli gen-config",
+    version,
+    about = "Generate a commented, validated owdns server configuration file"
+)]
+pub struct GenConfigArgs {
+    // 服务器监听地址
+    #[arg(
+        long,
+        default_value_t = default_listen_addr(),
+        help = "Address and port the server listens on"
+    )]
+    pub listen: SocketAddr,
+
+    // 上游 DoH 服务器 URL 列表（逗号分隔）
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = DEFAULT_GEN_CONFIG_UPSTREAM,
+        help = "Comma-separated upstream DoH endpoint URLs"
+    )]
+    pub upstream: Vec<String>,
+
+    // 启用 DNS 缓存
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Enable the DNS cache"
+    )]
+    pub cache: bool,
+
+    // 启用 DNS 分流（生成包含上游服务器的默认上游组）
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Enable DNS routing with a default upstream group built from the upstream URLs"
+    )]
+    pub routing: bool,
+
+    // 交互模式：逐项提示输入，直接回车保留命令行参数或默认值
+    #[arg(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        help = "Prompt for each option interactively (press Enter to keep the shown value)"
+    )]
+    pub interactive: bool,
+
+    // 输出文件路径（未指定时输出到标准输出）
+    #[arg(
+        short,
+        long,
+        help = "Write the configuration to this file instead of stdout"
+    )]
+    pub output: Option<PathBuf>,
+}
@@ -0,0 +1,174 @@
+// src/client/compare.rs
+
+// 该模块实现多服务器对比子命令 (`owdns-cli compare`)。
+//
+// 主要流程：
+// 1. 对每个服务器并发执行相同的 DoH 查询（复用 `core::execute_query`）。
+// 2. 将每个应答归一化为 `AnswerSnapshot`（忽略 TTL 与记录顺序）。
+// 3. 以多数服务器的应答集合为基准，标记存在差异或查询失败的服务器。
+// 4. 以表格或 JSON 格式输出对比结果与一致性汇总。
+
+use crate::client::args::CompareArgs;
+use crate::client::core::{build_http_client, execute_query};
+use crate::client::error::{ClientError, ClientResult};
+use crate::client::response::AnswerSnapshot;
+use colored::Colorize;
+use futures::future::join_all;
+use serde::Serialize;
+
+// 单个服务器的对比结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerAnswer {
+    // 服务器 URL
+    pub server: String,
+    // 响应码（查询失败时为空）
+    pub rcode: Option<String>,
+    // 应答记录，格式为 "类型 数据"
+    pub answers: Vec<String>,
+    // 查询错误信息
+    pub error: Option<String>,
+    // 查询耗时（毫秒）
+    pub duration_ms: u64,
+    // 是否与多数服务器的应答一致
+    pub matches_majority: bool,
+}
+
+// 多服务器对比报告
+#[derive(Debug, Clone, Serialize)]
+pub struct CompareReport {
+    // 查询的域名
+    pub domain: String,
+    // 查询的记录类型
+    pub record_type: String,
+    // 所有服务器是否均成功应答且应答一致
+    pub agree: bool,
+    // 不同应答集合的数量（不含失败的服务器）
+    pub distinct_answer_sets: usize,
+    // 各服务器的结果（按参数顺序）
+    pub results: Vec<ServerAnswer>,
+}
+
+// 并发查询所有服务器并生成对比报告
+pub async fn run_compare(args: &CompareArgs) -> ClientResult<CompareReport> {
+    let first_server = args.servers.first()
+        .ok_or_else(|| ClientError::InvalidArgument("No servers specified".to_string()))?;
+    let http_client = build_http_client(&args.query_args(first_server))?;
+
+    // 并发执行查询
+    let queries = args.servers.iter().map(|server| {
+        let query_args = args.query_args(server);
+        let http_client = &http_client;
+        async move {
+            let result = execute_query(&query_args, http_client).await;
+            (query_args.server_url, result)
+        }
+    });
+    // 将应答归一化为快照（忽略 TTL 与记录顺序）
+    let outcomes: Vec<_> = join_all(queries).await
+        .into_iter()
+        .map(|(server, result)| {
+            let answered = result.map(|r| (AnswerSnapshot::from_message(&r.message), r.duration));
+            (server, answered)
+        })
+        .collect();
+
+    // 统计各应答集合出现次数，出现最多者作为基准（次数相同时取先出现者）
+    let mut distinct: Vec<(&AnswerSnapshot, usize)> = Vec::new();
+    for (snapshot, _) in outcomes.iter().filter_map(|(_, answered)| answered.as_ref().ok()) {
+        match distinct.iter_mut().find(|(s, _)| *s == snapshot) {
+            Some((_, count)) => *count += 1,
+            None => distinct.push((snapshot, 1)),
+        }
+    }
+    let majority = distinct.iter()
+        .fold(None::<(&AnswerSnapshot, usize)>, |best, &(s, count)| match best {
+            Some((_, best_count)) if best_count >= count => best,
+            _ => Some((s, count)),
+        })
+        .map(|(s, _)| s);
+
+    let results: Vec<ServerAnswer> = outcomes.iter()
+        .map(|(server, answered)| match answered {
+            Ok((snapshot, duration)) => ServerAnswer {
+                server: server.clone(),
+                rcode: Some(snapshot.response_code.to_string()),
+                answers: snapshot.answers.iter().cloned().collect(),
+                error: None,
+                duration_ms: duration.as_millis() as u64,
+                matches_majority: majority == Some(snapshot),
+            },
+            Err(e) => ServerAnswer {
+                server: server.clone(),
+                rcode: None,
+                answers: Vec::new(),
+                error: Some(e.to_string()),
+                duration_ms: 0,
+                matches_majority: false,
+            },
+        })
+        .collect();
+
+    let agree = distinct.len() == 1 && results.iter().all(|r| r.error.is_none());
+
+    Ok(CompareReport {
+        domain: args.domain.clone(),
+        record_type: args.record_type.to_uppercase(),
+        agree,
+        distinct_answer_sets: distinct.len(),
+        results,
+    })
+}
+
+// 输出对比报告
+pub fn display_compare_report(report: &CompareReport, json: bool) -> ClientResult<()> {
+    if json {
+        let output = serde_json::to_string_pretty(report)
+            .map_err(|e| ClientError::Other(format!("Failed to serialize comparison report: {}", e)))?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    println!("{} {} {} across {} servers\n",
+             ";; Comparing".bold(),
+             report.domain,
+             report.record_type,
+             report.results.len());
+
+    let server_width = report.results.iter()
+        .map(|r| r.server.len())
+        .max()
+        .unwrap_or(0)
+        .max("SERVER".len());
+
+    println!("{:<6} {:<width$} {:>8}  {:<20} ANSWERS",
+             "MATCH", "SERVER", "TIME", "STATUS",
+             width = server_width);
+
+    for result in &report.results {
+        let (marker, status, answers) = match &result.error {
+            Some(error) => ("ERROR".red().bold(), "-".to_string(), error.red().to_string()),
+            None => {
+                let marker = if result.matches_majority { "OK".green().bold() } else { "DIFF".yellow().bold() };
+                let answers = if result.answers.is_empty() { "-".to_string() } else { result.answers.join(", ") };
+                (marker, result.rcode.clone().unwrap_or_default(), answers)
+            }
+        };
+
+        println!("{:<6} {:<width$} {:>6}ms  {:<20} {}",
+                 marker, result.server, result.duration_ms, status, answers,
+                 width = server_width);
+    }
+
+    println!();
+    if report.agree {
+        println!("{}", ";; Result: all servers agree".green().bold());
+    } else {
+        let failed = report.results.iter().filter(|r| r.error.is_some()).count();
+        println!("{} {} distinct answer set(s), {} server(s) failed",
+                 ";; Result: servers DISAGREE:".yellow().bold(),
+                 report.distinct_answer_sets,
+                 failed);
+    }
+
+    Ok(())
+}
@@ -13,16 +13,18 @@
 // 7. 调用 `response::display_response` 或类似函数来格式化并打印结果或错误信息。
 // 8. 处理整个流程中可能出现的 `error::ClientError`。
 
-use crate::client::args::CliArgs;
+use crate::client::args::{CliArgs, DohFormat};
 use crate::client::error::{ClientError, ClientResult};
 use crate::client::{request, response};
-use crate::client::response::DohResponse;
-use crate::common::consts::DEFAULT_HTTP_CLIENT_TIMEOUT;
+use crate::client::response::{AnswerSnapshot, DohResponse};
+use crate::common::consts::{
+    DEFAULT_HTTP_CLIENT_TIMEOUT, WATCH_EXIT_CHANGED, WATCH_EXIT_UNCHANGED, WATCH_EXIT_VALIDATION_ALERT,
+};
 use colored::Colorize;
 use regex::Regex;
 use reqwest::Client;
 use std::str::FromStr;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use hickory_proto::op::ResponseCode;
 use hickory_proto::rr::RecordType;
 
@@ -125,19 +127,157 @@ impl FromStr for ValidationCondition {
     }
 }
 
+// 监控模式结果汇总
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WatchSummary {
+    // 已执行的查询次数
+    pub queries: u64,
+    // 检测到的应答变化次数
+    pub changes: usize,
+    // 验证告警次数
+    pub alerts: usize,
+}
+
+impl WatchSummary {
+    // 监控模式的进程退出码：出现验证告警为 3，检测到变化为 0，否则为 2
+    pub fn exit_code(&self) -> i32 {
+        if self.alerts > 0 {
+            WATCH_EXIT_VALIDATION_ALERT
+        } else if self.changes > 0 {
+            WATCH_EXIT_CHANGED
+        } else {
+            WATCH_EXIT_UNCHANGED
+        }
+    }
+}
+
 // 执行 DoH 查询
 pub async fn run_query(args: CliArgs) -> ClientResult<()> {
     // 1. 初始化 HTTP 客户端
     let http_client = build_http_client(&args)?;
     
-    // 2. 构建请求
-    let request = request::build_doh_request(&args, &http_client).await?;
+    // 2-4. 构建、发送请求并解析响应
+    let doh_response = execute_query(&args, &http_client).await?;
+    
+    // 5. 显示结果
+    response::display_response(&doh_response, args.verbose);
+    
+    // 显示 CNAME 解析链
+    if args.follow_cname {
+        display_resolution_trace(&doh_response, args.format)?;
+    }
+    
+    // 6. 验证结果
+    if let Some(validation_conditions) = &args.validate {
+        println!("\n{}", ";; Validating Response:".bold());
+        
+        let parsed_conditions = parse_validation_conditions(validation_conditions)?;
+        validate_response(&doh_response, &parsed_conditions, true)?;
+        
+        // 如果验证通过，打印成功消息
+        println!("{}", "All validation conditions passed!".green().bold());
+    }
+    
+    Ok(())
+}
+
+// 打印解析链：JSON 格式时输出有序数组，否则输出单行轨迹
+fn display_resolution_trace(doh_response: &DohResponse, format: DohFormat) -> ClientResult<()> {
+    println!("\n{}", ";; RESOLUTION TRACE:".bold());
+    if format == DohFormat::Json {
+        let chain = response::resolution_chain(&doh_response.message);
+        println!("{}", serde_json::to_string_pretty(&chain)?);
+    } else {
+        println!("{}", response::format_resolution_chain(&doh_response.message));
+    }
+    Ok(())
+}
+
+// 持续监控域名，仅在应答集合变化时输出，直到达到 --watch-count 或收到 Ctrl+C
+pub async fn run_watch(args: CliArgs) -> ClientResult<WatchSummary> {
+    let interval_secs = args.watch
+        .ok_or_else(|| ClientError::InvalidArgument("Watch interval not specified".to_string()))?;
+    let http_client = build_http_client(&args)?;
+    let conditions = args.validate
+        .as_deref()
+        .map(parse_validation_conditions)
+        .transpose()?;
+    
+    println!("{} {} {} every {}s",
+             ";; Watching".bold(),
+             args.domain,
+             args.record_type.to_uppercase(),
+             interval_secs);
+    
+    let mut summary = WatchSummary::default();
+    let mut previous: Option<AnswerSnapshot> = None;
+    let mut validation_passing = true;
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {},
+            _ = tokio::signal::ctrl_c() => break,
+        }
+        
+        summary.queries += 1;
+        let doh_response = match execute_query(&args, &http_client).await {
+            Ok(response) => response,
+            Err(e) => {
+                // 查询失败不中断监控
+                eprintln!("{} {} {}", format_timestamp(SystemTime::now()), "ERROR".red().bold(), e);
+                continue;
+            }
+        };
+        let timestamp = format_timestamp(SystemTime::now());
+        
+        // 变化检测
+        let snapshot = AnswerSnapshot::from_message(&doh_response.message);
+        match &previous {
+            None => println!("{} {} {}", timestamp, "INITIAL".cyan().bold(), snapshot),
+            Some(old) if *old != snapshot => {
+                summary.changes += 1;
+                println!("{} {} {} -> {}", timestamp, "CHANGED".yellow().bold(), old, snapshot);
+            },
+            Some(_) => {}
+        }
+        previous = Some(snapshot);
+        
+        // 验证告警：仅在验证状态变化时输出
+        if let Some(conditions) = &conditions {
+            match validate_response(&doh_response, conditions, false) {
+                Err(e) if validation_passing => {
+                    summary.alerts += 1;
+                    validation_passing = false;
+                    println!("{} {} {}", timestamp, "ALERT".red().bold(), e);
+                },
+                Ok(()) if !validation_passing => {
+                    validation_passing = true;
+                    println!("{} {} All validation conditions passed", timestamp, "RECOVERED".green().bold());
+                },
+                _ => {}
+            }
+        }
+        
+        if args.watch_count.is_some_and(|count| summary.changes >= count) {
+            break;
+        }
+    }
+    
+    Ok(summary)
+}
+
+// 构建、发送 DoH 请求并解析响应
+pub async fn execute_query(args: &CliArgs, http_client: &Client) -> ClientResult<DohResponse> {
+    // 构建请求
+    let request = request::build_doh_request(args, http_client).await?;
     
     if args.verbose >= 3 {
         eprintln!("Sending request to {}...", args.server_url);
     }
     
-    // 3. 发送请求并计时
+    // 发送请求并计时
     let start_time = Instant::now();
     let http_response = http_client.execute(request).await?;
     let duration = start_time.elapsed();
@@ -146,25 +286,34 @@ pub async fn run_query(args: CliArgs) -> ClientResult<()> {
         eprintln!("Received response in {:?}.", duration);
     }
     
-    // 4. 解析响应
+    // 解析响应
     let mut doh_response = response::parse_doh_response(http_response).await?;
     doh_response.duration = duration; // 设置耗时
     
-    // 5. 显示结果
-    response::display_response(&doh_response, args.verbose);
+    Ok(doh_response)
+}
+
+// 将时间格式化为 RFC 3339 UTC 时间戳（精确到秒）
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
     
-    // 6. 验证结果
-    if let Some(validation_conditions) = &args.validate {
-        println!("\n{}", ";; Validating Response:".bold());
-        
-        let parsed_conditions = parse_validation_conditions(validation_conditions)?;
-        validate_response(&doh_response, &parsed_conditions)?;
-        
-        // 如果验证通过，打印成功消息
-        println!("{}", "All validation conditions passed!".green().bold());
-    }
+    // 由 Unix 纪元天数推算公历日期
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
     
-    Ok(())
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day,
+        secs_of_day / 3_600, (secs_of_day % 3_600) / 60, secs_of_day % 60
+    )
 }
 
 // 解析验证条件字符串
@@ -184,8 +333,8 @@ fn parse_validation_conditions(validation_str: &str) -> ClientResult<Vec<Validat
     Ok(conditions)
 }
 
-// 验证 DoH 响应是否符合指定条件
-fn validate_response(response: &DohResponse, conditions: &[ValidationCondition]) -> ClientResult<()> {
+// 验证 DoH 响应是否符合指定条件（report 为 true 时打印每项通过的条件）
+fn validate_response(response: &DohResponse, conditions: &[ValidationCondition], report: bool) -> ClientResult<()> {
     let message = &response.message;
     
     for condition in conditions {
@@ -197,7 +346,9 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
                         "Response code validation failed: expected {:?}, got {:?}", expected_rcode, actual_rcode
                     )));
                 }
-                println!("✓ {}: {:?}", "Response code".green(), expected_rcode);
+                if report {
+                    println!("✓ {}: {:?}", "Response code".green(), expected_rcode);
+                }
             },
             ValidationCondition::ContainsIP(expected_ip) => {
                 let mut found = false;
@@ -218,7 +369,9 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
                         "IP address validation failed: expected to find {}", expected_ip
                     )));
                 }
-                println!("✓ {}: {}", "Contains IP".green(), expected_ip);
+                if report {
+                    println!("✓ {}: {}", "Contains IP".green(), expected_ip);
+                }
             },
             ValidationCondition::MinTTL(min_ttl) => {
                 let mut all_above_min = true;
@@ -235,7 +388,9 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
                         "TTL validation failed: expected minimum TTL of {}", min_ttl
                     )));
                 }
-                println!("✓ {}: {}", "Minimum TTL".green(), min_ttl);
+                if report {
+                    println!("✓ {}: {}", "Minimum TTL".green(), min_ttl);
+                }
             },
             ValidationCondition::MinAnswers(min_count) => {
                 let actual_count = message.answers().len();
@@ -244,7 +399,9 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
                         "Answer count validation failed: expected at least {}, got {}", min_count, actual_count
                     )));
                 }
-                println!("✓ {}: {} (actual: {})", "Minimum answers".green(), min_count, actual_count);
+                if report {
+                    println!("✓ {}: {} (actual: {})", "Minimum answers".green(), min_count, actual_count);
+                }
             },
             ValidationCondition::HasRecordType(expected_type) => {
                 let mut found = false;
@@ -261,7 +418,9 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
                         "Record type validation failed: expected to find {:?} record", expected_type
                     )));
                 }
-                println!("✓ {}: {:?}", "Has record type".green(), expected_type);
+                if report {
+                    println!("✓ {}: {:?}", "Has record type".green(), expected_type);
+                }
             },
             ValidationCondition::ContainsText(expected_text) => {
                 let mut found = false;
@@ -290,7 +449,9 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
                         "Text content validation failed: expected to match pattern '{}'", expected_text
                     )));
                 }
-                println!("✓ {}: '{}'", "Contains text".green(), expected_text);
+                if report {
+                    println!("✓ {}: '{}'", "Contains text".green(), expected_text);
+                }
             },
             ValidationCondition::DnssecValidated => {
                 if !message.authentic_data() {
@@ -298,7 +459,9 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
                         "DNSSEC validation failed: AD bit not set in response".to_string()
                     ));
                 }
-                println!("✓ {}", "DNSSEC validated (AD bit set)".green());
+                if report {
+                    println!("✓ {}", "DNSSEC validated (AD bit set)".green());
+                }
             },
         }
     }
@@ -307,7 +470,7 @@ fn validate_response(response: &DohResponse, conditions: &[ValidationCondition])
 }
 
 // 构建配置好的 HTTP 客户端
-fn build_http_client(args: &CliArgs) -> ClientResult<Client> {
+pub fn build_http_client(args: &CliArgs) -> ClientResult<Client> {
     let mut client_builder = Client::builder()
         .timeout(Duration::from_secs(DEFAULT_HTTP_CLIENT_TIMEOUT));
     
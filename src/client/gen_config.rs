@@ -0,0 +1,161 @@
+// src/client/gen_config.rs
+
+// 该模块实现服务器配置模板生成子命令 (`owdns-cli gen-config`)。
+//
+// 主要流程：
+// 1. 交互模式下逐项提示输入（直接回车保留命令行参数或默认值）。
+// 2. 按参数渲染带注释的 YAML 配置。
+// 3. 以 `ServerConfig` 解析并验证生成的配置，确保服务器可直接加载。
+// 4. 输出到标准输出或 `--output` 指定的文件。
+
+use std::io::{self, BufRead, Write};
+use std::net::SocketAddr;
+use crate::client::args::GenConfigArgs;
+use crate::client::error::{ClientError, ClientResult};
+use crate::server::config::ServerConfig;
+
+// 启用路由时生成的上游组名称
+const GENERATED_UPSTREAM_GROUP: &str = "primary";
+
+// 执行 gen-config 子命令：生成并验证配置，写入文件或标准输出
+pub fn run_gen_config(mut args: GenConfigArgs) -> ClientResult<()> {
+    if args.interactive {
+        let stdin = io::stdin();
+        prompt_options(&mut args, &mut stdin.lock(), &mut io::stderr())?;
+    }
+
+    let config = generate_config(&args)?;
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, &config)?;
+            eprintln!("Configuration written to {}", path.display());
+        },
+        None => print!("{}", config),
+    }
+    Ok(())
+}
+
+// 逐项提示输入配置选项，空输入保留当前值
+pub fn prompt_options<R: BufRead, W: Write>(args: &mut GenConfigArgs, input: &mut R, output: &mut W) -> ClientResult<()> {
+    let listen = prompt(input, output, "Listen address", &args.listen.to_string())?;
+    args.listen = listen.parse::<SocketAddr>()
+        .map_err(|_| ClientError::InvalidArgument(format!("Invalid listen address: {}", listen)))?;
+
+    let upstreams = prompt(input, output, "Upstream DoH URLs (comma-separated)", &args.upstream.join(","))?;
+    args.upstream = upstreams.split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+
+    args.cache = prompt_bool(input, output, "Enable cache", args.cache)?;
+    args.routing = prompt_bool(input, output, "Enable routing", args.routing)?;
+    Ok(())
+}
+
+// 渲染配置并以 ServerConfig 解析与验证，返回配置文本
+pub fn generate_config(args: &GenConfigArgs) -> ClientResult<String> {
+    let config = render_config(args);
+    let parsed: ServerConfig = serde_yaml::from_str(&config)
+        .map_err(|e| ClientError::InvalidArgument(format!("Generated configuration cannot be parsed: {}", e)))?;
+    parsed.test()
+        .map_err(|e| ClientError::InvalidArgument(format!("Generated configuration is invalid: {}", e)))?;
+    Ok(config)
+}
+
+// 按参数渲染带注释的 YAML 配置
+pub fn render_config(args: &GenConfigArgs) -> String {
+    let resolvers: String = args.upstream.iter()
+        .map(|url| format!("      - address: {:?}\n        protocol: \"doh\"\n", url))
+        .collect();
+
+    let mut config = format!(
+        r#"# oxide-wdns 服务器配置（由 owdns-cli gen-config 生成）
+# 完整选项说明见 config.default.yaml
+
+# --- HTTP 服务器配置 ---
+http_server:
+  # 服务器监听地址和端口
+  listen_addr: "{listen}"
+  # 服务器连接超时（秒）
+  timeout: 120
+  # --- 速率限制配置 ---
+  rate_limit:
+    # 是否启用速率限制
+    enabled: true
+    # 每个 IP 每秒最大请求数
+    per_ip_rate: 100
+    # 单个 IP 的并发请求数限制
+    per_ip_concurrent: 10
+
+# --- DNS 解析器配置 ---
+dns_resolver:
+  # --- 上游 DNS 服务器配置 ---
+  upstream:
+    # 是否启用 DNSSEC 验证
+    enable_dnssec: true
+    # DNS 查询超时时间（秒）
+    query_timeout: 30
+    # 上游 DNS 解析器列表（DoH）
+    resolvers:
+{resolvers}
+  # --- DNS 缓存配置 ---
+  cache:
+    # 是否启用缓存
+    enabled: {cache}
+    # 缓存容量（条目数）
+    size: 10000
+"#,
+        listen = args.listen,
+        resolvers = resolvers,
+        cache = args.cache,
+    );
+
+    if args.routing {
+        config.push_str(&format!(
+            r#"
+  # --- DNS 分流配置 ---
+  routing:
+    # 是否启用 DNS 分流功能
+    enabled: true
+    # 上游 DNS 服务器组（默认与全局上游相同，可按需增加其他组）
+    upstream_groups:
+      - name: "{group}"
+        resolvers:
+{resolvers}
+    # 分流规则，按顺序匹配，例如：
+    # - match:
+    #     type: exact
+    #     values: ["example.com"]
+    #   upstream_group: "{group}"
+    rules: []
+    # 未匹配任何规则时使用的上游组
+    default_upstream_group: "{group}"
+"#,
+            group = GENERATED_UPSTREAM_GROUP,
+            resolvers = resolvers.lines().map(|line| format!("    {}\n", line)).collect::<String>(),
+        ));
+    }
+
+    config
+}
+
+// 提示输入文本，空输入时返回默认值
+fn prompt<R: BufRead, W: Write>(input: &mut R, output: &mut W, label: &str, default: &str) -> ClientResult<String> {
+    write!(output, "{} [{}]: ", label, default)?;
+    output.flush()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let line = line.trim();
+    Ok(if line.is_empty() { default.to_string() } else { line.to_string() })
+}
+
+// 提示输入是/否，空输入时返回默认值
+fn prompt_bool<R: BufRead, W: Write>(input: &mut R, output: &mut W, label: &str, default: bool) -> ClientResult<bool> {
+    let answer = prompt(input, output, &format!("{} (y/n)", label), if default { "y" } else { "n" })?;
+    match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Err(ClientError::InvalidArgument(format!("Expected y or n for '{}', got: {}", label, answer))),
+    }
+}
@@ -6,9 +6,14 @@ pub mod error;
 pub mod request;
 pub mod response;
 pub mod core;
+pub mod compare;
+pub mod gen_config;
 
 // 重新导出关键类型，方便外部使用
-pub use args::CliArgs;
+pub use args::{CliArgs, CompareArgs, GenConfigArgs};
 pub use error::{ClientError, ClientResult};
+pub use request::read_stdin_payload;
 pub use response::DohResponse;
-pub use core::{run_query, print_error}; 
\ No newline at end of file
+pub use core::{run_query, run_watch, print_error, WatchSummary};
+pub use compare::{run_compare, display_compare_report, CompareReport};
+pub use gen_config::{run_gen_config, generate_config}; 
\ No newline at end of file
@@ -6,7 +6,10 @@
 // 1. 根据命令行参数 (`args::CliArgs`) 创建 DNS 查询消息 (`trust_dns_proto::op::Message`)。
 //    - 设置查询的域名、记录类型。
 //    - 根据 `--dnssec` 参数设置 DNSSEC OK (DO) 位。
+//    - 根据 `--nsid` 参数附加 EDNS NSID 选项。
+//    - 根据 `--edns-option` 参数附加任意 EDNS 选项。
 //    - 如果提供了 `--payload`，则直接使用提供的十六进制编码报文，跳过域名/类型参数。
+//    - `--payload -` 时从标准输入读取载荷（十六进制文本或原始二进制，由 `--payload-format` 决定）。
 // 2. 将 DNS 消息编码为指定的格式：
 //    - Wireformat (`application/dns-message`)。
 //    - JSON (`application/dns-json`)，如果服务器支持 (需要确认实现细节或添加对 JSON 的支持)。
@@ -25,17 +28,19 @@
 
 // 依赖: reqwest, trust-dns-proto, base64, serde_json (如果支持 JSON)
 
-use crate::client::args::{CliArgs, DohFormat, HttpMethod, HttpVersion};
+use crate::client::args::{CliArgs, DohFormat, HttpMethod, HttpVersion, PayloadFormat};
 use crate::client::error::{ClientError, ClientResult};
-use crate::common::consts::{CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE};
+use crate::common::consts::{CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE, EDNS_NSID_OPTION_CODE, STDIN_PAYLOAD};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use reqwest::{self, Request, Url};
 use reqwest::header::{ACCEPT, CONTENT_TYPE};
 use serde::Serialize;
 use serde_json;
+use std::io::Read;
 use std::str::FromStr;
-use hickory_proto::op::{Message, MessageType, OpCode, Query};
+use hickory_proto::op::{Edns, Message, MessageType, OpCode, Query};
 use hickory_proto::rr::{Name, RecordType, DNSClass};
+use hickory_proto::rr::rdata::opt::EdnsOption;
 use hickory_proto::serialize::binary::{BinEncodable, BinEncoder};
 use rand::random;
 
@@ -96,6 +101,35 @@ pub async fn build_doh_request(args: &CliArgs, client: &reqwest::Client) -> Clie
     Ok(request_builder.build()?)
 }
 
+// 若 --payload 为 -，从标准输入读取一次载荷并替换为十六进制形式，后续（包括监控模式的重复查询）直接复用
+pub fn read_stdin_payload(args: &mut CliArgs) -> ClientResult<()> {
+    if args.payload.as_deref() != Some(STDIN_PAYLOAD) {
+        return Ok(());
+    }
+
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input)?;
+    let payload = decode_payload(&input, args.payload_format)?;
+    args.payload = Some(hex::encode(payload));
+    Ok(())
+}
+
+// 按指定格式解码载荷：十六进制文本忽略空白字符，二进制原样使用
+pub fn decode_payload(input: &[u8], format: PayloadFormat) -> ClientResult<Vec<u8>> {
+    let payload = match format {
+        PayloadFormat::Hex => {
+            let text: Vec<u8> = input.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+            hex::decode(text)?
+        },
+        PayloadFormat::Binary => input.to_vec(),
+    };
+
+    if payload.is_empty() {
+        return Err(ClientError::InvalidArgument("Payload read from stdin is empty".to_string()));
+    }
+    Ok(payload)
+}
+
 // 创建 DNS 查询消息
 fn create_dns_query(args: &CliArgs) -> ClientResult<Message> {
     // 如果提供了 payload 参数，直接使用
@@ -135,12 +169,29 @@ fn create_dns_query(args: &CliArgs) -> ClientResult<Message> {
     query.set_query_class(DNSClass::IN);
     
     // 6. 设置 DNSSEC OK 位 (如果启用)
+    let mut edns = None;
     if args.dnssec {
         message.set_checking_disabled(true);
         
         // 为 DNSSEC 添加 EDNS(0) 和 DO 位支持
-        let mut edns = hickory_proto::op::Edns::new();
-        edns.set_dnssec_ok(true);
+        edns.get_or_insert_with(Edns::new).set_dnssec_ok(true);
+    }
+    
+    // 7. 请求服务器标识 (如果启用)，NSID 请求携带空的选项数据
+    if args.nsid {
+        edns.get_or_insert_with(Edns::new)
+            .options_mut()
+            .insert(EdnsOption::Unknown(EDNS_NSID_OPTION_CODE, Vec::new()));
+    }
+    
+    // 8. 附加命令行指定的任意 EDNS 选项
+    for option in &args.edns_options {
+        edns.get_or_insert_with(Edns::new)
+            .options_mut()
+            .insert(EdnsOption::Unknown(option.code, option.data.clone()));
+    }
+    
+    if let Some(edns) = edns {
         message.set_edns(edns);
     }
     
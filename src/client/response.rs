@@ -15,15 +15,21 @@
 // 依赖: reqwest, trust-dns-proto, serde_json, colored (终端颜色支持)
 
 use crate::client::error::{ClientError, ClientResult};
-use crate::common::consts::{CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE};
+use crate::common::consts::{
+    CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE,
+    EDNS_EXTENDED_ERROR_OPTION_CODE, EDNS_NSID_OPTION_CODE,
+    HEADER_BACKEND, HEADER_BLOCK_REASON, HEADER_SERVER,
+};
 use colored::Colorize;
 use reqwest;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json;
-use std::fmt::Write;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Write};
 use std::time::Duration;
 use hickory_proto::op::{Message, MessageType, Query, ResponseCode};
 use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
 
 // DoH JSON 响应格式
 #[derive(Debug, Deserialize)]
@@ -92,6 +98,142 @@ pub struct DohJsonAnswer {
     pub data: String,
 }
 
+// EDNS 扩展错误信息（RFC 8914）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedErrorInfo {
+    // 信息码
+    pub info_code: u16,
+    // 附加文本
+    pub extra_text: String,
+}
+
+// 应答服务器的标识信息，用于排查负载均衡后的多后端部署
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerIdentity {
+    // Server 响应头
+    pub server: Option<String>,
+    // X-Backend 响应头
+    pub backend: Option<String>,
+    // EDNS NSID（RFC 5001），格式为十六进制，可打印时附带文本
+    pub nsid: Option<String>,
+}
+
+impl ServerIdentity {
+    // 是否未获取到任何标识
+    pub fn is_empty(&self) -> bool {
+        self.server.is_none() && self.backend.is_none() && self.nsid.is_none()
+    }
+}
+
+// 应答快照，用于监控模式下的变化检测（忽略 TTL 与记录顺序）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnswerSnapshot {
+    // 响应码
+    pub response_code: ResponseCode,
+    // 应答记录集合，格式为 "类型 数据"
+    pub answers: BTreeSet<String>,
+}
+
+impl AnswerSnapshot {
+    // 从 DNS 消息生成应答快照
+    pub fn from_message(message: &Message) -> Self {
+        let answers = message.answers()
+            .iter()
+            .map(|record| match record.data() {
+                Some(rdata) => format!("{} {}", record.record_type(), rdata),
+                None => record.record_type().to_string(),
+            })
+            .collect();
+
+        Self {
+            response_code: message.response_code(),
+            answers,
+        }
+    }
+}
+
+impl fmt::Display for AnswerSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let answers: Vec<&str> = self.answers.iter().map(String::as_str).collect();
+        write!(f, "{} [{}]", self.response_code, answers.join(", "))
+    }
+}
+
+// 解析链中的一步：名称经某类型记录指向的数据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolutionStep {
+    // 记录名称
+    pub name: String,
+    // 记录类型
+    #[serde(rename = "type")]
+    pub record_type: String,
+    // 记录 TTL
+    pub ttl: u32,
+    // 记录数据（CNAME 为目标名称）
+    pub data: String,
+}
+
+// 从应答节构建解析链：自查询名起沿 CNAME 记录前进，最后附加链末名称的查询类型记录
+pub fn resolution_chain(message: &Message) -> Vec<ResolutionStep> {
+    let Some(query) = message.queries().first() else {
+        return Vec::new();
+    };
+    let step = |record: &Record| ResolutionStep {
+        name: record.name().to_string(),
+        record_type: record.record_type().to_string(),
+        ttl: record.ttl(),
+        data: record.data().map(|data| data.to_string()).unwrap_or_default(),
+    };
+
+    let mut chain = Vec::new();
+    let mut current = query.name().clone();
+    let mut visited = HashSet::new();
+    // 跟随 CNAME，遇到环路时停止
+    while visited.insert(current.to_lowercase()) {
+        let Some((record, target)) = message.answers().iter().find_map(|record| match record.data() {
+            Some(RData::CNAME(cname)) if *record.name() == current => Some((record, cname.0.clone())),
+            _ => None,
+        }) else {
+            break;
+        };
+        chain.push(step(record));
+        current = target;
+    }
+
+    chain.extend(message.answers().iter()
+        .filter(|record| *record.name() == current && record.record_type() != RecordType::CNAME)
+        .map(step));
+    chain
+}
+
+// 将解析链格式化为可读的单行轨迹，例如 `www.example.com. → CNAME → lb.example.net. → A → 1.2.3.4`
+// 链末的多条同类型记录以逗号分隔
+pub fn format_resolution_chain(message: &Message) -> String {
+    let chain = resolution_chain(message);
+    let mut trace = match (chain.first(), message.queries().first()) {
+        (Some(first), _) => first.name.clone(),
+        (None, Some(query)) => query.name().to_string(),
+        (None, None) => return String::new(),
+    };
+
+    // CNAME 逐跳输出，链末记录按连续的同类型分组
+    let mut previous_type: Option<&str> = None;
+    for step in &chain {
+        if step.record_type != RecordType::CNAME.to_string() && previous_type == Some(step.record_type.as_str()) {
+            write!(trace, ", {}", step.data).unwrap();
+        } else {
+            write!(trace, " → {} → {}", step.record_type, step.data).unwrap();
+        }
+        previous_type = Some(step.record_type.as_str());
+    }
+
+    // 链末无记录时附加响应码（如 NXDOMAIN）
+    if chain.last().is_none_or(|step| step.record_type == RecordType::CNAME.to_string()) {
+        write!(trace, " → {}", message.response_code()).unwrap();
+    }
+    trace
+}
+
 // DoH 响应结构
 #[derive(Debug)]
 pub struct DohResponse {
@@ -187,6 +329,91 @@ pub async fn parse_doh_response(response: reqwest::Response) -> ClientResult<Doh
     })
 }
 
+// 获取服务器返回的阻止原因（X-Owdns-Block-Reason 响应头）
+pub fn block_reason(response: &DohResponse) -> Option<&str> {
+    response.headers
+        .get(HEADER_BLOCK_REASON)
+        .and_then(|value| value.to_str().ok())
+}
+
+// 从 DNS 消息中提取 EDNS 扩展错误
+pub fn extract_extended_error(message: &Message) -> Option<ExtendedErrorInfo> {
+    let edns = message.extensions().as_ref()?;
+    match edns.option(EdnsCode::from(EDNS_EXTENDED_ERROR_OPTION_CODE))? {
+        EdnsOption::Unknown(_, data) if data.len() >= 2 => Some(ExtendedErrorInfo {
+            info_code: u16::from_be_bytes([data[0], data[1]]),
+            extra_text: String::from_utf8_lossy(&data[2..])
+                .trim_end_matches('\0')
+                .to_string(),
+        }),
+        _ => None,
+    }
+}
+
+// 从 DNS 消息中提取 EDNS NSID，可打印时格式为 `hex ("text")`
+pub fn extract_nsid(message: &Message) -> Option<String> {
+    let edns = message.extensions().as_ref()?;
+    match edns.option(EdnsCode::from(EDNS_NSID_OPTION_CODE))? {
+        EdnsOption::Unknown(_, data) if !data.is_empty() => {
+            let hex = hex::encode(data);
+            if data.iter().all(|b| (32..=126).contains(b)) {
+                Some(format!("{} (\"{}\")", hex, String::from_utf8_lossy(data)))
+            } else {
+                Some(hex)
+            }
+        },
+        _ => None,
+    }
+}
+
+// 提取应答服务器的标识信息（Server / X-Backend 响应头与 EDNS NSID）
+pub fn extract_server_identity(response: &DohResponse) -> ServerIdentity {
+    let header_value = |name: &str| {
+        response.headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+
+    ServerIdentity {
+        server: header_value(HEADER_SERVER),
+        backend: header_value(HEADER_BACKEND),
+        nsid: extract_nsid(&response.message),
+    }
+}
+
+// 获取 EDE 信息码的名称（RFC 8914 第 4 节）
+pub fn ede_info_code_name(info_code: u16) -> &'static str {
+    match info_code {
+        0 => "Other Error",
+        1 => "Unsupported DNSKEY Algorithm",
+        2 => "Unsupported DS Digest Type",
+        3 => "Stale Answer",
+        4 => "Forged Answer",
+        5 => "DNSSEC Indeterminate",
+        6 => "DNSSEC Bogus",
+        7 => "Signature Expired",
+        8 => "Signature Not Yet Valid",
+        9 => "DNSKEY Missing",
+        10 => "RRSIGs Missing",
+        11 => "No Zone Key Bit Set",
+        12 => "NSEC Missing",
+        13 => "Cached Error",
+        14 => "Not Ready",
+        15 => "Blocked",
+        16 => "Censored",
+        17 => "Filtered",
+        18 => "Prohibited",
+        19 => "Stale NXDomain Answer",
+        20 => "Not Authoritative",
+        21 => "Not Supported",
+        22 => "No Reachable Authority",
+        23 => "Network Error",
+        24 => "Invalid Data",
+        _ => "Unknown",
+    }
+}
+
 // 将 JSON 格式的 DNS 数据转换为 DNS 消息
 fn json_to_message(json: &DohJsonResponse) -> ClientResult<Message> {
     let mut message = Message::new();
@@ -361,6 +588,18 @@ pub fn display_response(response: &DohResponse, verbose_level: u8) {
              message.name_servers().len(),
              message.additionals().len());
     
+    // 打印阻止原因和扩展错误，便于区分主动拦截与真实的 NXDOMAIN
+    if let Some(reason) = block_reason(response) {
+        println!("{} {}", ";; BLOCKED:".bold().red(), reason);
+    }
+    if let Some(ede) = extract_extended_error(message) {
+        if ede.extra_text.is_empty() {
+            println!("{} {} ({})", ";; EDE:".bold(), ede.info_code, ede_info_code_name(ede.info_code));
+        } else {
+            println!("{} {} ({}): {}", ";; EDE:".bold(), ede.info_code, ede_info_code_name(ede.info_code), ede.extra_text);
+        }
+    }
+    
     // 打印 Question Section
     if !message.queries().is_empty() {
         println!("\n{}", ";; QUESTION SECTION:".bold());
@@ -439,6 +678,21 @@ pub fn display_response(response: &DohResponse, verbose_level: u8) {
     if verbose_level > 0 {
         println!("\n{} (Level {})", ";; --- Verbose Output ---".bold(), verbose_level);
         
+        // 打印应答服务器标识
+        let identity = extract_server_identity(response);
+        if !identity.is_empty() {
+            println!("\n{}", ";; Server Identity:".bold());
+            if let Some(server) = &identity.server {
+                println!("Server: {}", server);
+            }
+            if let Some(backend) = &identity.backend {
+                println!("X-Backend: {}", backend);
+            }
+            if let Some(nsid) = &identity.nsid {
+                println!("NSID: {}", nsid);
+            }
+        }
+        
         // 打印 HTTP 响应头
         if verbose_level >= 1 {
             println!("\n{}", ";; HTTP Response Headers:".bold());
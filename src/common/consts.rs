@@ -3,7 +3,7 @@
 // 本文件包含项目中使用的所有全局常量
 
 // 标准库导入
-use std::net::SocketAddr;
+use std::net::{Ipv6Addr, SocketAddr};
 
 //
 // 通用常量
@@ -24,6 +24,9 @@ pub fn default_listen_addr() -> SocketAddr {
 // 默认服务器连接超时
 pub const DEFAULT_LISTEN_TIMEOUT: u64 = 120;
 
+// NSID 配置取值：使用本机主机名作为服务器标识
+pub const NSID_HOSTNAME: &str = "hostname";
+
 // 最大请求大小
 pub const MAX_REQUEST_SIZE: usize = 16 * 1024; // 16KB
 
@@ -31,6 +34,12 @@ pub const MAX_REQUEST_SIZE: usize = 16 * 1024; // 16KB
 // DNS 常量
 //
 
+// DNS 报文头部长度（字节）
+pub const DNS_HEADER_SIZE: usize = 12;
+
+// OPT 伪记录类型（RFC 6891）
+pub const DNS_RECORD_TYPE_OPT: u16 = 41;
+
 // 默认记录类型 (A 记录)
 pub const DNS_RECORD_TYPE_A: u16 = 1;
 
@@ -40,6 +49,72 @@ pub const DNS_CLASS_IN: u16 = 1;
 // DNS 分流特殊上游组名称 - 黑洞（阻止）
 pub const BLACKHOLE_UPSTREAM_GROUP_NAME: &str = "__blackhole__";
 
+// 黑洞 sinkhole 响应的默认 TTL（秒）
+pub const DEFAULT_BLACKHOLE_SINKHOLE_TTL: u32 = 60;
+
+// 黑洞响应的默认最大 TTL（秒）：负响应 SOA 的 TTL 与 MINIMUM，并作为 sinkhole 应答 TTL 的上限
+pub const DEFAULT_BLACKHOLE_TTL: u32 = 300;
+
+// 黑洞响应 TTL 的上限（秒），避免客户端长期缓存阻止结果
+pub const MAX_BLACKHOLE_TTL: u32 = 86400;
+
+// 黑洞负响应附加的合成 SOA 记录的主服务器名与管理员邮箱（使用保留的 .invalid 顶级域）
+pub const BLACKHOLE_SOA_MNAME: &str = "blocked.invalid.";
+pub const BLACKHOLE_SOA_RNAME: &str = "hostmaster.blocked.invalid.";
+
+//
+// EDNS 扩展错误 (EDE) 常量
+//
+
+// EDNS 扩展错误 Option Code（RFC 8914）
+pub const EDNS_EXTENDED_ERROR_OPTION_CODE: u16 = 15;
+
+// EDNS 名称服务器标识 Option Code（RFC 5001）
+pub const EDNS_NSID_OPTION_CODE: u16 = 3;
+
+// EDNS 填充 Option Code（RFC 7830）
+pub const EDNS_PADDING_OPTION_CODE: u16 = 12;
+
+// 应答填充的默认块大小（字节），采用 RFC 8467 推荐的应答块大小
+pub const DEFAULT_RESPONSE_PADDING_BLOCK_SIZE: u16 = 468;
+
+// 标识后端服务器的响应头名称
+pub const HEADER_SERVER: &str = "server";
+pub const HEADER_BACKEND: &str = "x-backend";
+
+// 转发环路标记请求头，携带发起转发的实例标识
+pub const HEADER_LOOP_MARKER: &str = "x-owdns-instance";
+
+// EDE 信息码：其他
+pub const EDE_INFO_CODE_OTHER: u16 = 0;
+
+// EDE 信息码：过期应答
+pub const EDE_INFO_CODE_STALE_ANSWER: u16 = 3;
+
+// EDE 信息码：已阻止
+pub const EDE_INFO_CODE_BLOCKED: u16 = 15;
+
+// EDE 信息码：已过滤
+pub const EDE_INFO_CODE_FILTERED: u16 = 17;
+
+// EDE 信息码：已禁止
+pub const EDE_INFO_CODE_PROHIBITED: u16 = 18;
+
+// EDE 信息码：无可达权威服务器
+pub const EDE_INFO_CODE_NO_REACHABLE_AUTHORITY: u16 = 22;
+
+// 阻止原因响应头名称
+pub const HEADER_BLOCK_REASON: &str = "x-owdns-block-reason";
+
+// 指定上游组的请求头名称（仅受信任客户端可用，用于测试分流配置）
+pub const HEADER_UPSTREAM_GROUP_OVERRIDE: &str = "x-owdns-upstream-group";
+
+// 阻止原因：命中分流规则
+pub const BLOCK_REASON_RULE_MATCH: &str = "rule_match";
+
+// 阻止原因：未匹配任何规则，按 default_action 拒绝
+pub const BLOCK_REASON_DEFAULT_ACTION: &str = "default_action";
+
 //
 // EDNS 客户端子网 (ECS) 常量
 //
@@ -75,6 +150,12 @@ pub const MAX_IPV6_PREFIX_LENGTH: u8 = 128;
 // 默认缓存大小（条目数）
 pub const DEFAULT_CACHE_SIZE: usize = 10000;
 
+// 默认缓存分片数（1 表示不分片）
+pub const DEFAULT_CACHE_SHARDS: usize = 1;
+
+// 缓存分片数上限
+pub const MAX_CACHE_SHARDS: usize = 256;
+
 // 默认最小 TTL（秒）
 pub const DEFAULT_MIN_TTL: u32 = 60;
 
@@ -84,11 +165,74 @@ pub const DEFAULT_MAX_TTL: u32 = 86400; // 1 天
 // 默认负缓存 TTL（秒）
 pub const DEFAULT_NEGATIVE_TTL: u32 = 300; // 5 分钟
 
+// 默认返回给客户端的记录 TTL 上限（秒）
+pub const DEFAULT_RESPONSE_MAX_TTL: u32 = 604800; // 7 天
+
+// 重复查询检测的默认时间窗口（秒）
+pub const DEFAULT_DUPLICATE_QUERY_WINDOW_SECS: u64 = 2;
+
+// 重复查询检测默认最多跟踪的查询数
+pub const DEFAULT_DUPLICATE_QUERY_MAX_TRACKED: u64 = 10000;
+
+// CNAME 展平默认最大追踪深度
+pub const DEFAULT_CNAME_FLATTEN_MAX_DEPTH: usize = 8;
+
+// 缓存条目默认空闲过期时间（秒），0 表示禁用
+pub const DEFAULT_CACHE_TIME_TO_IDLE_SECS: u64 = 300; // 5 分钟
+
+// 上游失败时返回过期缓存条目所使用的 TTL（秒）
+pub const DEFAULT_STALE_ON_ERROR_TTL: u32 = 30;
+
+// 上游失败时允许返回的过期缓存条目的最大过期时长（秒），参考 RFC 8767
+pub const DEFAULT_STALE_ON_ERROR_MAX_AGE: u64 = 86400; // 1 天
+
+// 上游 TTL=0 应答强制缓存时长的上限（秒）
+pub const MAX_OVERRIDE_ZERO_TTL_SECS: u32 = 300;
+
+// 自适应 TTL 缩短后的默认下限（秒）
+pub const DEFAULT_ADAPTIVE_TTL_MIN: u32 = 10;
+
+// 自适应 TTL 延长后的默认上限（秒）
+pub const DEFAULT_ADAPTIVE_TTL_MAX: u32 = 86400; // 1 天
+
+// 自适应 TTL 默认最多跟踪应答变化历史的缓存键数
+pub const DEFAULT_ADAPTIVE_TTL_MAX_TRACKED: u64 = 10000;
+
+// 自适应 TTL 的最大延长级数（每级 TTL 翻倍）
+pub const ADAPTIVE_TTL_MAX_GROWTH_STEPS: i32 = 4;
+
+// 自适应 TTL 的最大缩短级数（每级 TTL 减半）
+pub const ADAPTIVE_TTL_MAX_SHRINK_STEPS: i32 = 3;
+
+// 启用压缩存储时，触发压缩的最小线格式大小（字节）
+pub const DEFAULT_CACHE_COMPRESSION_MIN_BYTES: usize = 512;
+
 // 缓存文件魔数，用于识别缓存文件
 pub const CACHE_FILE_MAGIC: &str = "OXIDEWDNS_CACHE";
 
-// 缓存文件版本号
-pub const CACHE_FILE_VERSION: u64 = 1;
+// 缓存文件版本号（v2：缓存键增加 DO/CD 标志）
+pub const CACHE_FILE_VERSION: u64 = 2;
+
+// 可迁移的上一缓存文件版本号（v1：缓存键不含 DO/CD 标志）
+pub const CACHE_FILE_PREVIOUS_VERSION: u64 = 1;
+
+// 按上游组隔离缓存时的命名空间前缀
+pub const CACHE_NAMESPACE_GROUP_PREFIX: &str = "group:";
+
+// 损坏缓存文件的隔离后缀
+pub const CACHE_FILE_CORRUPT_SUFFIX: &str = ".corrupt";
+
+// 默认启动加载缓存的并发批次数
+pub const DEFAULT_CACHE_LOAD_CONCURRENCY: usize = 4;
+
+// 默认保存缓存时并发序列化的线程数
+pub const DEFAULT_CACHE_SAVE_CONCURRENCY: usize = 4;
+
+// 启动加载缓存时每个批次的条目数
+pub const CACHE_LOAD_BATCH_SIZE: usize = 1000;
+
+// 缓存导出时流式通道的容量
+pub const CACHE_DUMP_CHANNEL_CAPACITY: usize = 256;
 
 //
 // 速率限制常量
@@ -112,6 +256,15 @@ pub const MIN_PER_IP_CONCURRENT: u32 = 1;
 // 单个 IP 的并发请求数限制的最大值
 pub const MAX_PER_IP_CONCURRENT: u32 = 65535; 
 
+// 默认每日配额重置时刻（UTC 小时）
+pub const DEFAULT_QUOTA_RESET_HOUR: u8 = 0;
+
+// 每日配额重置时刻（UTC 小时）的最大值
+pub const MAX_QUOTA_RESET_HOUR: u8 = 23;
+
+// 默认每日配额最多跟踪的客户端 IP 数
+pub const DEFAULT_QUOTA_MAX_TRACKED_IPS: u64 = 100000;
+
 //
 // 上游服务器常量
 //
@@ -119,6 +272,9 @@ pub const MAX_PER_IP_CONCURRENT: u32 = 65535;
 // 默认查询超时时间（秒）
 pub const DEFAULT_QUERY_TIMEOUT: u64 = 30;
 
+// 默认上游连接超时时间（秒）
+pub const DEFAULT_CONNECT_TIMEOUT: u64 = 2;
+
 //
 // HTTP 相关常量
 //
@@ -132,6 +288,9 @@ pub const DEFAULT_HTTP_CLIENT_POOL_IDLE_TIMEOUT: u64 = 30;
 // 默认 HTTP 客户端连接池最大空闲连接数
 pub const DEFAULT_HTTP_CLIENT_POOL_MAX_IDLE_CONNECTIONS: u32 = 10;
 
+// 默认 HTTP 客户端最低 TLS 版本
+pub const DEFAULT_HTTP_CLIENT_TLS_MIN_VERSION: &str = "1.2";
+
 // 默认 HTTP 客户端 Agent
 pub const DEFAULT_HTTP_CLIENT_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Safari/537.36";
 
@@ -161,12 +320,34 @@ pub const DOH_JSON_API_PATH: &str = "/resolve";
 // DoH 标准请求路径 (RFC 8484)
 pub const DOH_STANDARD_PATH: &str = "/dns-query";
 
+// 协议回退链中各传输协议的标准端口
+pub const DNS_STANDARD_PORT: u16 = 53;
+pub const DOT_STANDARD_PORT: u16 = 853;
+pub const DOH_STANDARD_PORT: u16 = 443;
+
 // DoH JSON格式标识
 pub const DOH_FORMAT_JSON: &str = "json";
 
 // DoH 二进制格式标识
 pub const DOH_FORMAT_WIRE: &str = "wire"; 
 
+//
+// 管理接口常量
+//
+
+// 缓存导出路径
+pub const ADMIN_CACHE_DUMP_PATH: &str = "/admin/cache/dump";
+// 路由规则刷新路径
+pub const ADMIN_ROUTING_REFRESH_PATH: &str = "/admin/routing/refresh";
+// 当前生效配置（脱敏）路径
+pub const ADMIN_CONFIG_PATH: &str = "/admin/config";
+
+// 纯文本内容类型
+pub const CONTENT_TYPE_TEXT_PLAIN: &str = "text/plain; charset=utf-8";
+
+// NDJSON 内容类型
+pub const CONTENT_TYPE_NDJSON: &str = "application/x-ndjson";
+
 //
 // URL规则周期性更新常量
 //
@@ -179,3 +360,200 @@ pub const MIN_URL_RULE_UPDATE_INTERVAL_SECS: u64 = 30; // 30秒
 
 // URL规则更新间隔的最大值（秒）
 pub const MAX_URL_RULE_UPDATE_INTERVAL_SECS: u64 = 86400 * 7; // 7天
+
+// URL规则下载的默认最大字节数
+pub const DEFAULT_MAX_RULE_DOWNLOAD_BYTES: u64 = 50 * 1024 * 1024; // 50MB
+
+// 同时进行下载与编译的URL规则更新任务的默认上限
+pub const DEFAULT_MAX_CONCURRENT_URL_UPDATES: usize = 2;
+
+// 上游组未就绪时 wait 策略的默认最长等待时间（毫秒）
+pub const DEFAULT_NOT_READY_WAIT_MS: u64 = 500;
+
+// URL规则获取失败后的默认最大重试次数
+pub const DEFAULT_URL_RULE_RETRY_MAX_ATTEMPTS: u32 = 3;
+
+// URL规则重试的默认初始退避时间（秒）
+pub const DEFAULT_URL_RULE_RETRY_INITIAL_BACKOFF_SECS: u64 = 10;
+
+// URL规则重试的默认最大退避时间（秒）
+pub const DEFAULT_URL_RULE_RETRY_MAX_BACKOFF_SECS: u64 = 300; // 5分钟
+
+//
+// 特殊用途域名（RFC 6761）常量
+//
+
+// localhost 域名，解析为回环地址
+pub const SPECIAL_NAME_LOCALHOST: &str = "localhost.";
+
+// invalid 域名，始终返回 NXDOMAIN
+pub const SPECIAL_NAME_INVALID: &str = "invalid.";
+
+// 本地合成的特殊域名应答 TTL（秒）
+pub const SPECIAL_NAME_TTL: u32 = 86400;
+
+// ANY 查询的 HINFO 应答（RFC 8482）：CPU 字段取值与记录 TTL（秒）
+pub const ANY_HINFO_CPU: &str = "RFC8482";
+pub const ANY_HINFO_TTL: u32 = 3600;
+
+// EDNS 能力探测（无问题节的 EDNS 查询）应答中通告的 UDP 载荷大小（字节）与支持的最高 EDNS 版本
+pub const EDNS_PROBE_MAX_PAYLOAD: u16 = 1232;
+pub const EDNS_SUPPORTED_VERSION: u8 = 0;
+
+//
+// 本地静态记录常量
+//
+
+// 本地静态记录默认 TTL（秒）
+pub const DEFAULT_LOCAL_RECORDS_TTL: u32 = 300;
+
+// DNS64 合成默认使用的知名前缀 64:ff9b::/96（RFC 6052）
+pub const DEFAULT_DNS64_PREFIX: Ipv6Addr = Ipv6Addr::new(0x64, 0xff9b, 0, 0, 0, 0, 0, 0);
+
+//
+// 本地 DNSSEC 信任锚常量
+//
+
+// 信任锚记录未指定 TTL 时的默认 TTL（秒），与根区 DNSKEY 的 TTL 一致
+pub const DEFAULT_TRUST_ANCHOR_TTL: u32 = 172800;
+
+//
+// DDR（Discovery of Designated Resolvers，RFC 9462）常量
+//
+
+// DDR 查询名
+pub const DDR_RESOLVER_NAME: &str = "_dns.resolver.arpa.";
+
+// SVCB dohpath 参数键（RFC 9461）
+pub const SVCB_KEY_DOHPATH: u16 = 7;
+
+// DDR 默认 DoH 端口
+pub const DEFAULT_DDR_PORT: u16 = 443;
+
+// DDR 默认 ALPN 协议
+pub const DEFAULT_DDR_ALPN: &str = "h2";
+
+// DDR 默认 SVCB 优先级
+pub const DEFAULT_DDR_PRIORITY: u16 = 1;
+
+// DDR 记录默认 TTL（秒）
+pub const DEFAULT_DDR_TTL: u32 = 300;
+
+// dohpath 中 DNS 查询变量的 URI 模板
+pub const DDR_DOHPATH_VARIABLE: &str = "{?dns}";
+
+//
+// 健康探测常量
+//
+
+// 默认探测查询名
+pub const DEFAULT_HEALTH_PROBE_NAME: &str = "example.com.";
+
+// 默认探测间隔（秒）
+pub const DEFAULT_HEALTH_PROBE_INTERVAL_SECS: u64 = 30;
+
+// 默认探测超时（秒）
+pub const DEFAULT_HEALTH_PROBE_TIMEOUT_SECS: u64 = 5;
+
+//
+// 自检常量
+//
+
+// 自检等待上游组URL规则完成首次加载的最长时间（秒）
+pub const SELF_TEST_ROUTING_WAIT_SECS: u64 = 10;
+
+// 自检读取应答体的最大字节数
+pub const SELF_TEST_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+//
+// 查询日志常量
+//
+
+// 默认查询日志文件路径
+pub const DEFAULT_QUERY_LOG_PATH: &str = "./query.log";
+
+// 默认单个查询日志文件最大大小（MB）
+pub const DEFAULT_QUERY_LOG_MAX_SIZE_MB: u64 = 100;
+
+// 默认保留的轮转查询日志文件数
+pub const DEFAULT_QUERY_LOG_MAX_FILES: u32 = 5;
+
+// 默认查询日志记录队列容量，队列满时丢弃新记录
+pub const DEFAULT_QUERY_LOG_BUFFER_SIZE: usize = 4096;
+
+// 默认 dnstap 帧队列容量，队列满时丢弃新帧
+pub const DEFAULT_DNSTAP_BUFFER_SIZE: usize = 4096;
+
+// 默认插件脚本单次调用允许执行的最大操作数
+pub const DEFAULT_PLUGIN_MAX_OPERATIONS: u64 = 100000;
+
+// 默认调试详情日志采样率（记录所有查询）
+pub const DEFAULT_LOG_SAMPLE_RATE: f64 = 1.0;
+
+//
+// 查询限制常量
+//
+
+// 默认客户端查询最大字节数（宽松值，正常查询远小于此值）
+pub const DEFAULT_MAX_QUERY_SIZE: usize = 4096;
+
+// 默认 EDNS OPT 记录最大选项数
+pub const DEFAULT_MAX_EDNS_OPTIONS: usize = 16;
+
+// 默认查询最大问题数（多问题查询的每个问题都会拆分为独立的上游查询）
+pub const DEFAULT_MAX_QUESTIONS: usize = 8;
+
+// 多问题查询并发解析的最大问题数
+pub const MULTI_QUESTION_MAX_CONCURRENCY: usize = 4;
+
+// 默认 JSON API 请求（查询字符串）最大字节数
+pub const DEFAULT_MAX_JSON_REQUEST_SIZE: usize = 1024;
+
+// 域名文本形式的最大长度（不含结尾的点，RFC 1035）
+pub const MAX_DOMAIN_NAME_LENGTH: usize = 253;
+
+// 单个标签的最大长度（字节，RFC 1035）
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+// 线格式域名的最大长度（含长度字节与根标签，RFC 1035）
+pub const MAX_WIRE_NAME_LENGTH: usize = 255;
+
+//
+// 客户端监控模式常量
+//
+
+// 监控模式退出码：检测到应答变化
+pub const WATCH_EXIT_CHANGED: i32 = 0;
+
+// 监控模式退出码：未检测到应答变化
+pub const WATCH_EXIT_UNCHANGED: i32 = 2;
+
+// 监控模式退出码：出现验证告警
+pub const WATCH_EXIT_VALIDATION_ALERT: i32 = 3;
+
+//
+// 客户端查询载荷常量
+//
+
+// 表示从标准输入读取查询载荷的 --payload 取值
+pub const STDIN_PAYLOAD: &str = "-";
+
+//
+// 客户端对比模式常量
+//
+
+// 多服务器对比子命令名称
+pub const COMPARE_SUBCOMMAND: &str = "compare";
+
+// 对比模式退出码：服务器应答存在差异
+pub const COMPARE_EXIT_DISAGREE: i32 = 2;
+
+//
+// 客户端配置生成常量
+//
+
+// 服务器配置模板生成子命令名称
+pub const GEN_CONFIG_SUBCOMMAND: &str = "gen-config";
+
+// 生成配置时默认使用的上游 DoH 服务器
+pub const DEFAULT_GEN_CONFIG_UPSTREAM: &str = "https://cloudflare-dns.com/dns-query";
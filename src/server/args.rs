@@ -45,6 +45,14 @@ pub struct CliArgs {
     )]
     pub test_config: bool,
     
+    // 自检
+    #[arg(
+        long = "self-test",
+        action = ArgAction::SetTrue,
+        help = "Run an in-process end-to-end self-test (routing, upstream, cache) with the configuration and exit"
+    )]
+    pub self_test: bool,
+    
     // 启用调试日志
     #[arg(
         short = 'd',
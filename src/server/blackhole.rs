@@ -0,0 +1,188 @@
+// src/server/blackhole.rs
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use hickory_proto::rr::rdata::{A, AAAA, SOA};
+use crate::common::consts::{BLACKHOLE_SOA_MNAME, BLACKHOLE_SOA_RNAME};
+use crate::server::config::{BlackholeConfig, BlackholeResponseConfig, BlackholeResponseType};
+use crate::server::error::{Result, ServerError};
+
+// 客户端网段（CIDR）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    // 网络地址（已按前缀长度掩码）
+    network: IpAddr,
+    // 前缀长度
+    prefix_length: u8,
+}
+
+impl IpCidr {
+    // 检查地址是否位于该网段内（IPv4 映射的 IPv6 地址按 IPv4 处理）
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                mask_ipv4(ip, self.prefix_length) == network
+            },
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                mask_ipv6(ip, self.prefix_length) == network
+            },
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = ServerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || ServerError::Config(format!("Invalid CIDR '{}'", s));
+
+        // 未指定前缀长度时视为单个主机地址
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+
+        let ip = IpAddr::from_str(addr).map_err(|_| invalid())?;
+        let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+        let prefix_length = match prefix {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None => max_prefix,
+        };
+        if prefix_length > max_prefix {
+            return Err(invalid());
+        }
+
+        let network = match ip {
+            IpAddr::V4(v4) => IpAddr::V4(mask_ipv4(v4, prefix_length)),
+            IpAddr::V6(v6) => IpAddr::V6(mask_ipv6(v6, prefix_length)),
+        };
+
+        Ok(Self { network, prefix_length })
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_length)
+    }
+}
+
+// 黑洞响应生成器，根据客户端 IP 选择阻止响应
+#[derive(Debug, Clone, Default)]
+pub struct BlackholeResponder {
+    // 默认阻止响应
+    default_response: BlackholeResponseConfig,
+    // 按客户端网段覆盖的阻止响应（按配置顺序匹配）
+    client_responses: Vec<(Vec<IpCidr>, BlackholeResponseConfig)>,
+    // 阻止响应的最大 TTL（秒）
+    ttl: u32,
+}
+
+// 解析网段列表，任一网段无效时返回错误
+pub fn parse_cidrs(cidrs: &[String]) -> Result<Vec<IpCidr>> {
+    cidrs.iter().map(|cidr| IpCidr::from_str(cidr)).collect()
+}
+
+impl BlackholeResponder {
+    // 从配置创建黑洞响应生成器
+    pub fn new(config: &BlackholeConfig, ttl: u32) -> Result<Self> {
+        let mut client_responses = Vec::with_capacity(config.client_responses.len());
+        for entry in &config.client_responses {
+            client_responses.push((parse_cidrs(&entry.client_cidrs)?, entry.response.clone()));
+        }
+
+        Ok(Self {
+            default_response: config.response.clone(),
+            client_responses,
+            ttl,
+        })
+    }
+
+    // 选择适用于客户端的阻止响应：第一个包含客户端 IP 的网段条目优先，否则使用默认响应
+    pub fn response_for(&self, client_ip: IpAddr) -> &BlackholeResponseConfig {
+        self.client_responses
+            .iter()
+            .find(|(cidrs, _)| cidrs.iter().any(|cidr| cidr.contains(client_ip)))
+            .map(|(_, response)| response)
+            .unwrap_or(&self.default_response)
+    }
+
+    // 为被阻止的查询构建响应消息
+    pub fn build_response(&self, query_message: &Message, client_ip: IpAddr) -> Message {
+        let config = self.response_for(client_ip);
+
+        let response_code = match config.type_ {
+            BlackholeResponseType::Nxdomain => ResponseCode::NXDomain,
+            BlackholeResponseType::Refused => ResponseCode::Refused,
+            BlackholeResponseType::Sinkhole => ResponseCode::NoError,
+        };
+
+        let mut response = Message::new();
+        response.set_id(query_message.id())
+            .set_message_type(MessageType::Response)
+            .set_recursion_desired(query_message.recursion_desired())
+            .set_recursion_available(true)
+            .set_response_code(response_code);
+
+        // 复制查询部分
+        for q in query_message.queries() {
+            response.add_query(q.clone());
+        }
+
+        // sinkhole：A/AAAA 查询返回指定地址，其他类型返回空应答 (NODATA)
+        if config.type_ == BlackholeResponseType::Sinkhole {
+            for q in query_message.queries() {
+                let rdata = match q.query_type() {
+                    RecordType::A => RData::A(A(config.ipv4.unwrap_or(Ipv4Addr::UNSPECIFIED))),
+                    RecordType::AAAA => RData::AAAA(AAAA(config.ipv6.unwrap_or(Ipv6Addr::UNSPECIFIED))),
+                    _ => continue,
+                };
+                response.add_answer(Record::from_rdata(q.name().clone(), config.ttl.min(self.ttl), rdata));
+            }
+        }
+
+        // NXDOMAIN/NODATA 附加合成 SOA，客户端按其 TTL 负缓存阻止结果（RFC 2308）
+        if response_code == ResponseCode::NXDomain || (response_code == ResponseCode::NoError && response.answers().is_empty()) {
+            if let Some(q) = query_message.queries().first() {
+                response.add_name_server(self.soa_record(q.name().clone()));
+            }
+        }
+
+        response
+    }
+
+    // 构建负缓存 TTL 为最大 TTL 的合成 SOA 记录
+    fn soa_record(&self, name: Name) -> Record {
+        let soa = SOA::new(
+            Name::from_ascii(BLACKHOLE_SOA_MNAME).unwrap_or_else(|_| Name::root()),
+            Name::from_ascii(BLACKHOLE_SOA_RNAME).unwrap_or_else(|_| Name::root()),
+            1,
+            3600,
+            600,
+            86400,
+            self.ttl,
+        );
+        Record::from_rdata(name, self.ttl, RData::SOA(soa))
+    }
+}
+
+// 按前缀长度掩码 IPv4 地址
+fn mask_ipv4(ip: Ipv4Addr, prefix_length: u8) -> Ipv4Addr {
+    let mask = if prefix_length == 0 { 0 } else { !0u32 << (32 - prefix_length.min(32)) };
+    Ipv4Addr::from(u32::from(ip) & mask)
+}
+
+// 按前缀长度掩码 IPv6 地址
+fn mask_ipv6(ip: Ipv6Addr, prefix_length: u8) -> Ipv6Addr {
+    let mask = if prefix_length == 0 { 0 } else { !0u128 << (128 - prefix_length.min(128) as u32) };
+    Ipv6Addr::from(u128::from(ip) & mask)
+}
@@ -1,25 +1,32 @@
 // src/server/cache.rs
 
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::fs::{File, create_dir_all};
 use std::path::Path;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read};
 use std::io::Write;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use moka::future::Cache;
-use hickory_proto::op::{Message};
-use hickory_proto::rr::{DNSClass, Name, RecordType};
-use tokio::sync::RwLock;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use hickory_proto::op::{Message, ResponseCode};
+use hickory_proto::rr::{DNSClass, Name, RData, RecordType};
+use tokio::sync::{mpsc, RwLock, Semaphore};
+use tokio::task::JoinSet;
 use tokio::time::{interval, Instant};
 use tracing::{debug, warn, error, info};
 use serde::{Serialize, Deserialize};
 use tokio::task;
+use xxhash_rust::xxh64::Xxh64;
 use crate::server::error::{Result, ServerError};
-use crate::server::config::{CacheConfig, PersistenceCacheConfig};
+use crate::server::config::{CacheConfig, CorruptCachePolicy, PersistenceCacheConfig};
 use crate::server::ecs::{EcsData};
-use crate::common::consts::{CACHE_FILE_MAGIC, CACHE_FILE_VERSION};
+use crate::common::consts::{CACHE_FILE_MAGIC, CACHE_FILE_VERSION, CACHE_FILE_PREVIOUS_VERSION, CACHE_FILE_CORRUPT_SUFFIX, CACHE_LOAD_BATCH_SIZE, CACHE_DUMP_CHANNEL_CAPACITY, ADAPTIVE_TTL_MAX_GROWTH_STEPS, ADAPTIVE_TTL_MAX_SHRINK_STEPS};
 use crate::server::metrics::METRICS;
 
 // 缓存操作标签常量
@@ -37,6 +44,45 @@ const PERSIST_OP_SHUTDOWN_SAVE: &str = "shutdown_save";
 const PERSIST_OP_SHUTDOWN_SAVE_FAILED: &str = "shutdown_save_failed";
 const PERSIST_OP_SHUTDOWN_SAVE_TIMEOUT: &str = "shutdown_save_timeout";
 
+// 缓存导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheDumpFormat {
+    // 类 zone 文件的文本格式
+    Text,
+    // 每行一个 JSON 对象 (NDJSON)
+    Json,
+}
+
+// 缓存导出的 JSON 记录
+#[derive(Debug, Serialize)]
+struct CacheDumpEntry {
+    // 查询名
+    name: String,
+    // 查询类型
+    record_type: String,
+    // 查询类
+    record_class: String,
+    // 剩余 TTL（秒）
+    ttl_remaining: u64,
+    // 响应码
+    rcode: String,
+    // 应答记录（zone 文件格式）
+    answers: Vec<String>,
+    // ECS 网络地址（可选）
+    ecs_network: Option<String>,
+    // ECS 作用域前缀长度（可选）
+    ecs_scope_prefix_length: Option<u8>,
+    // 缓存命名空间（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    // 查询的 DO 标志
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dnssec_ok: bool,
+    // 查询的 CD 标志
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    checking_disabled: bool,
+}
+
 // 可序列化的缓存条目用于持久化
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PersistableCacheEntry {
@@ -65,6 +111,40 @@ struct PersistableCacheKey {
     ecs_network: Option<String>,
     // ECS 作用域前缀长度（可选）
     ecs_scope_prefix_length: Option<u8>,
+    // DNSSEC OK (DO) 标志
+    dnssec_ok: bool,
+    // Checking Disabled (CD) 标志
+    checking_disabled: bool,
+}
+
+// 上一版本（v1）缓存文件中的缓存键，不含 DO/CD 标志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PersistableCacheKeyV1 {
+    // 查询名
+    name: String,
+    // 查询类型
+    record_type: u16,
+    // 查询类
+    record_class: u16,
+    // ECS 网络地址（可选）
+    ecs_network: Option<String>,
+    // ECS 作用域前缀长度（可选）
+    ecs_scope_prefix_length: Option<u8>,
+}
+
+// v1 条目均由未区分 DO/CD 的查询写入，迁移为未设置 DO/CD 的键
+impl From<PersistableCacheKeyV1> for PersistableCacheKey {
+    fn from(key: PersistableCacheKeyV1) -> Self {
+        Self {
+            name: key.name,
+            record_type: key.record_type,
+            record_class: key.record_class,
+            ecs_network: key.ecs_network,
+            ecs_scope_prefix_length: key.ecs_scope_prefix_length,
+            dnssec_ok: false,
+            checking_disabled: false,
+        }
+    }
 }
 
 // 持久化文件版本信息
@@ -92,31 +172,227 @@ struct CacheItemForPersistence {
     last_accessed: u64,
 }
 
+// 缓存条目对应的 JSON API 应答序列化结果，首次 JSON 命中时填充，条目被替换或淘汰时随之失效
+pub type CachedJson = Arc<OnceLock<Arc<[u8]>>>;
+
 // 缓存条目
 #[derive(Debug, Clone)]
 pub struct CacheEntry {
-    // DNS 响应消息，使用Arc包装减少克隆成本
-    pub message: Arc<Message>,
+    // DNS 响应消息（已解析或线格式存储）
+    pub message: CachedMessage,
     // 过期时间（Unix 时间戳，秒）
     pub expires_at: u64,
+    // 存储时间（Unix 时间戳，秒）
+    pub stored_at: u64,
     // 访问次数，使用原子类型实现无锁更新
     pub access_count: Arc<AtomicU64>,
     // 最后访问时间（Unix 时间戳，秒），使用原子类型实现无锁更新
     pub last_accessed: Arc<AtomicU64>,
     // ECS 数据（可选）
     pub ecs_data: Option<EcsData>,
+    // 估算占用的内存字节数，插入时计算一次
+    pub size_bytes: u32,
+    // JSON API 应答序列化结果（启用 JSON 应答缓存时）
+    pub json: Option<CachedJson>,
+    // 刷新首次失败的时间（Unix 时间戳，秒），条目处于刷新失败宽限期内时设置
+    pub refresh_failed_at: Option<u64>,
+}
+
+impl CacheEntry {
+    // 估算条目内存占用：消息字节数 + 域名长度 + 键与条目结构体的固定开销
+    pub fn estimate_size(key: &CacheKey, cached: &CachedMessage, message: &Message) -> u32 {
+        let payload = cached
+            .stored_bytes()
+            .unwrap_or_else(|| message.to_vec().map(|wire| wire.len()).unwrap_or(0));
+        let overhead = std::mem::size_of::<CacheKey>() + std::mem::size_of::<CacheEntry>();
+        (payload + key.name.len() + overhead).min(u32::MAX as usize) as u32
+    }
+}
+
+// 自适应 TTL 跟踪的单个缓存键的应答变化历史
+#[derive(Debug, Clone, Copy)]
+struct AnswerHistory {
+    // 最近一次应答的记录指纹（忽略 TTL 与记录顺序）
+    fingerprint: u64,
+    // 调整级数：正数为连续未变化的刷新次数（每级 TTL 翻倍），负数为连续变化次数（每级 TTL 减半）
+    steps: i32,
+    // 按调整后 TTL 计算的过期时间（Unix 时间戳，秒）
+    expires_at: u64,
+}
+
+// 缓存中 DNS 消息的存储形式
+#[derive(Debug, Clone)]
+pub enum CachedMessage {
+    // 已解析的消息，使用Arc包装减少克隆成本
+    Parsed(Arc<Message>),
+    // 线格式字节，命中时重新解析
+    Wire(Arc<[u8]>),
+    // DEFLATE 压缩的线格式字节，命中时解压并重新解析
+    Compressed(Arc<[u8]>),
+}
+
+impl CachedMessage {
+    // 根据缓存配置选择存储形式：启用压缩存储且线格式达到阈值时以字节形式存储
+    pub fn encode(message: &Message, config: &CacheConfig) -> Self {
+        if !config.store_compressed {
+            return Self::Parsed(Arc::new(message.clone()));
+        }
+        
+        let wire = match message.to_vec() {
+            Ok(wire) => wire,
+            Err(e) => {
+                warn!("Failed to serialize message for compressed storage: {}", e);
+                return Self::Parsed(Arc::new(message.clone()));
+            }
+        };
+        
+        // 小条目压缩收益有限，直接保存线格式
+        if wire.len() < config.compression_min_bytes {
+            return Self::Wire(wire.into());
+        }
+        
+        let mut encoder = DeflateEncoder::new(Vec::with_capacity(wire.len() / 2), Compression::fast());
+        match encoder.write_all(&wire).and_then(|_| encoder.finish()) {
+            // 仅在压缩后更小时使用压缩形式
+            Ok(compressed) if compressed.len() < wire.len() => Self::Compressed(compressed.into()),
+            Ok(_) => Self::Wire(wire.into()),
+            Err(e) => {
+                warn!("Failed to compress cache entry: {}", e);
+                Self::Wire(wire.into())
+            }
+        }
+    }
+    
+    // 还原 DNS 消息
+    pub fn to_message(&self) -> Result<Message> {
+        match self {
+            Self::Parsed(message) => Ok(message.as_ref().clone()),
+            Self::Wire(wire) => Ok(Message::from_vec(wire)?),
+            Self::Compressed(_) => Ok(Message::from_vec(&self.to_wire()?)?),
+        }
+    }
+    
+    // 获取线格式字节
+    pub fn to_wire(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Parsed(message) => Ok(message.to_vec()?),
+            Self::Wire(wire) => Ok(wire.to_vec()),
+            Self::Compressed(compressed) => {
+                let mut wire = Vec::with_capacity(compressed.len() * 2);
+                DeflateDecoder::new(compressed.as_ref())
+                    .read_to_end(&mut wire)
+                    .map_err(|e| ServerError::Cache(format!("Failed to decompress cache entry: {}", e)))?;
+                Ok(wire)
+            }
+        }
+    }
+    
+    // 存储占用的字节数（仅线格式/压缩形式）
+    pub fn stored_bytes(&self) -> Option<usize> {
+        match self {
+            Self::Parsed(_) => None,
+            Self::Wire(bytes) | Self::Compressed(bytes) => Some(bytes.len()),
+        }
+    }
+}
+
+// 分片缓存：按缓存键哈希将条目分布到多个 Moka 实例，降低高并发下的内部锁竞争
+#[derive(Clone)]
+struct ShardedCache {
+    // 各分片的 Moka 缓存
+    shards: Arc<[Cache<CacheKey, CacheEntry>]>,
+}
+
+impl ShardedCache {
+    // 创建分片缓存，容量（条目数或字节数）在分片间平均分配
+    fn new(config: &CacheConfig) -> Self {
+        let shard_count = config.shards.max(1);
+        let shards = (0..shard_count).map(|_| {
+            // 配置了内存上限时按条目估算字节数加权，容量以字节计，取代按条目数的限制
+            let mut builder = if config.max_memory_bytes > 0 {
+                Cache::builder()
+                    .weigher(|_key: &CacheKey, entry: &CacheEntry| entry.size_bytes)
+                    .max_capacity(config.max_memory_bytes.div_ceil(shard_count as u64))
+            } else {
+                Cache::builder().max_capacity(config.size.div_ceil(shard_count) as u64)
+            };
+            
+            // 超过空闲时间未被访问的条目将被移除（0 表示禁用）
+            if config.time_to_idle_secs > 0 {
+                builder = builder.time_to_idle(std::time::Duration::from_secs(config.time_to_idle_secs));
+            }
+            builder.build()
+        }).collect();
+        
+        Self { shards }
+    }
+    
+    // 创建容量为 0 的单分片缓存
+    fn empty() -> Self {
+        Self { shards: Arc::new([Cache::builder().max_capacity(0).build()]) }
+    }
+    
+    // 按缓存键选择分片
+    fn shard(&self, key: &CacheKey) -> &Cache<CacheKey, CacheEntry> {
+        if self.shards.len() == 1 {
+            return &self.shards[0];
+        }
+        
+        let mut hasher = Xxh64::new(0);
+        key.hash(&mut hasher);
+        &self.shards[(hasher.finish() % self.shards.len() as u64) as usize]
+    }
+    
+    async fn get(&self, key: &CacheKey) -> Option<CacheEntry> {
+        self.shard(key).get(key).await
+    }
+    
+    async fn insert(&self, key: CacheKey, entry: CacheEntry) {
+        self.shard(&key).insert(key, entry).await
+    }
+    
+    async fn invalidate(&self, key: &CacheKey) {
+        self.shard(key).invalidate(key).await
+    }
+    
+    fn invalidate_all(&self) {
+        self.shards.iter().for_each(|shard| shard.invalidate_all());
+    }
+    
+    async fn run_pending_tasks(&self) {
+        for shard in self.shards.iter() {
+            shard.run_pending_tasks().await;
+        }
+    }
+    
+    fn entry_count(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.entry_count()).sum()
+    }
+    
+    fn weighted_size(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.weighted_size()).sum()
+    }
+    
+    // 依次遍历所有分片的条目
+    fn iter(&self) -> impl Iterator<Item = (Arc<CacheKey>, CacheEntry)> + '_ {
+        self.shards.iter().flat_map(|shard| shard.iter())
+    }
 }
 
 // DNS 响应缓存
 pub struct DnsCache {
-    // 内部 Moka LRU 缓存
-    cache: Cache<CacheKey, CacheEntry>,
+    // 内部 Moka LRU 缓存（按配置分片）
+    cache: ShardedCache,
     // 缓存配置
     config: CacheConfig,
     // 周期性保存任务取消标记
     periodic_save_cancel: Option<Arc<RwLock<bool>>>,
     // 周期性缓存条目计数任务取消标记
     metrics_task_cancel: Option<Arc<RwLock<bool>>>,
+    // 只读基线缓存（启动时加载，从不写入）
+    baseline: Arc<HashMap<CacheKey, CacheEntry>>,
+    // 自适应 TTL 的应答变化历史（启用时）
+    answer_history: Option<Cache<CacheKey, AnswerHistory>>,
 }
 
 // 缓存键
@@ -132,19 +408,26 @@ pub struct CacheKey {
     pub ecs_network: Option<Arc<String>>,
     // ECS 作用域前缀长度（可选）
     pub ecs_scope_prefix_length: Option<u8>,
+    // 缓存命名空间（如路由配置档案），不同命名空间的条目互不共享
+    pub namespace: Option<Arc<String>>,
+    // 查询的 DNSSEC OK (DO) 标志，签名与未签名应答分开缓存
+    pub dnssec_ok: bool,
+    // 查询的 Checking Disabled (CD) 标志
+    pub checking_disabled: bool,
 }
 
 impl CacheKey {
     // 创建新的缓存键
     pub fn new(name: Name, record_type: RecordType, record_class: DNSClass) -> Self {
-        // 直接从Name获取内部的utf8字符串，避免to_string()的间接转换开销
-        let name_str = name.to_utf8();
         Self {
-            name: Arc::new(name_str),
+            name: Arc::new(Self::normalize_name(&name)),
             record_type: record_type.into(),
             record_class: record_class.into(),
             ecs_network: None,
             ecs_scope_prefix_length: None,
+            namespace: None,
+            dnssec_ok: false,
+            checking_disabled: false,
         }
     }
     
@@ -157,8 +440,7 @@ impl CacheKey {
     ) -> Self {
         // 预先格式化ECS网络地址，避免重复计算
         // 使用预先估计的容量初始化字符串，避免多次重新分配
-        let name_str = name.to_utf8();
-        
+
         // 提前计算网络字符串的大致长度 (IP + '/' + prefix数字)
         let ecs_str_len = ecs_data.address.to_string().len() + 1 + 3;
         let mut network_str = String::with_capacity(ecs_str_len);
@@ -167,14 +449,36 @@ impl CacheKey {
         network_str.push_str(&ecs_data.scope_prefix_length.to_string());
         
         Self {
-            name: Arc::new(name_str),
+            name: Arc::new(Self::normalize_name(&name)),
             record_type: record_type.into(),
             record_class: record_class.into(),
             ecs_network: Some(Arc::new(network_str)),
             ecs_scope_prefix_length: Some(ecs_data.scope_prefix_length),
+            namespace: None,
+            dnssec_ok: false,
+            checking_disabled: false,
         }
     }
     
+    // 规范化查询名：统一使用小写 A-label（punycode）形式，Unicode 与 punycode 查询、
+    // 以及大小写不同的查询（如 DNS 0x20）共享缓存
+    fn normalize_name(name: &Name) -> String {
+        name.to_lowercase().to_ascii()
+    }
+    
+    // 设置缓存命名空间
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(Arc::new(namespace.to_string()));
+        self
+    }
+    
+    // 设置查询的 DO/CD 标志
+    pub fn with_dnssec_flags(mut self, dnssec_ok: bool, checking_disabled: bool) -> Self {
+        self.dnssec_ok = dnssec_ok;
+        self.checking_disabled = checking_disabled;
+        self
+    }
+    
     // 创建缓存查找键，用于匹配客户端查询
     pub fn create_lookup_key(
         name: Name, 
@@ -189,7 +493,7 @@ impl CacheKey {
         }
     }
     
-    // 获取基础键（不包含 ECS 信息）
+    // 获取基础键（不包含 ECS 信息，保留命名空间和 DO/CD 标志）
     pub fn get_base_key(&self) -> Self {
         Self {
             name: Arc::clone(&self.name),
@@ -197,6 +501,9 @@ impl CacheKey {
             record_class: self.record_class,
             ecs_network: None,
             ecs_scope_prefix_length: None,
+            namespace: self.namespace.clone(),
+            dnssec_ok: self.dnssec_ok,
+            checking_disabled: self.checking_disabled,
         }
     }
     
@@ -205,7 +512,10 @@ impl CacheKey {
         // 基本字段必须匹配
         if self.name != query_key.name || 
            self.record_type != query_key.record_type || 
-           self.record_class != query_key.record_class {
+           self.record_class != query_key.record_class ||
+           self.namespace != query_key.namespace ||
+           self.dnssec_ok != query_key.dnssec_ok ||
+           self.checking_disabled != query_key.checking_disabled {
             return false;
         }
         
@@ -360,36 +670,77 @@ impl CacheKey {
 }
 
 impl DnsCache {
-    // 创建新的 DNS 缓存
-    pub fn new(config: CacheConfig) -> Self {
+    // 创建新的 DNS 缓存，启动加载失败时始终以空缓存继续
+    pub fn new(mut config: CacheConfig) -> Self {
+        // on_corrupt 为 fail 或路径不可写即失败时仅由 try_new 报告错误，此处降级为继续运行
+        if config.persistence.on_corrupt == CorruptCachePolicy::Fail {
+            config.persistence.on_corrupt = CorruptCachePolicy::Ignore;
+        }
+        config.persistence.fail_on_unwritable = false;
+        
+        match Self::try_new(config) {
+            Ok(cache) => cache,
+            Err(e) => unreachable!("DnsCache creation only fails when on_corrupt is 'fail' or fail_on_unwritable is set: {}", e),
+        }
+    }
+    
+    // 创建禁用的缓存：不记录缓存指标、不启动后台任务，用于需要绕过缓存的内部查询
+    pub fn disabled() -> Self {
+        Self {
+            cache: ShardedCache::empty(),
+            config: CacheConfig::default(),
+            periodic_save_cancel: None,
+            metrics_task_cancel: None,
+            baseline: Arc::new(HashMap::new()),
+            answer_history: None,
+        }
+    }
+    
+    // 创建新的 DNS 缓存，缓存文件损坏且 on_corrupt 为 fail、或持久化路径不可写且 fail_on_unwritable 启用时返回错误
+    pub fn try_new(config: CacheConfig) -> Result<Self> {
         // 创建 Moka 缓存，设置最大容量
-        let cache = Cache::builder()
-            .max_capacity(config.size as u64)
-            .time_to_idle(std::time::Duration::from_secs(300)) // 5分钟内未使用的条目将被移除
-            .build();
+        let cache = ShardedCache::new(&config);
+        if config.shards > 1 {
+            debug!(shards = config.shards, "DNS cache sharding enabled");
+        }
         
         let mut dns_cache = DnsCache { 
             cache, 
             config: config.clone(), 
             periodic_save_cancel: None,
             metrics_task_cancel: None,
+            baseline: Arc::new(Self::load_baseline(&config)),
+            answer_history: (config.enabled && config.adaptive_ttl.enabled)
+                .then(|| Cache::builder().max_capacity(config.adaptive_ttl.max_tracked).build()),
         };
         
+        // 强制缓存 TTL=0 应答违背上游意图，启用时明确告警
+        if config.enabled && config.override_zero_ttl_secs > 0 {
+            warn!(
+                override_zero_ttl_secs = config.override_zero_ttl_secs,
+                "Cache override_zero_ttl_secs is enabled: upstream answers with TTL=0 will be cached despite the upstream asking not to"
+            );
+        }
+        
         // 记录缓存初始状态指标
         METRICS.cache_capacity().set(config.size as i64);
         METRICS.cache_entries().set(0);
+        METRICS.cache_memory_bytes().set(0);
         
         // 如果启用了持久化缓存且配置了启动时加载
         if dns_cache.config.persistence.enabled && dns_cache.config.persistence.load_on_startup {
             let config_clone = dns_cache.config.clone();
             let cache_clone = dns_cache.cache.clone();
+            let load_concurrency = dns_cache.config.persistence.load_concurrency;
+            let load_rate = dns_cache.config.persistence.load_rate;
             
             // 记录加载开始时间
             let load_start = Instant::now();
             
             // 使用阻塞任务加载缓存文件（这是在启动时一次性操作）
             match task::block_in_place(move || {
-                Self::load_cache_from_file(&config_clone.persistence)
+                let persistence = &config_clone.persistence;
+                Self::load_cache_from_file(&config_clone, &persistence.path, persistence.skip_expired_on_load)
             }) {
                 Ok((keys, entries)) => {
                     // 记录加载持续时间
@@ -397,32 +748,22 @@ impl DnsCache {
                     METRICS.cache_persist_operations_total().with_label_values(&[PERSIST_OP_LOAD]).inc();
                     METRICS.cache_persist_duration_seconds().with_label_values(&[PERSIST_OP_LOAD]).observe(load_duration.as_secs_f64());
                     
-                    // 将加载的条目导入到缓存
-                    let load_fut = async move {
-                        let entry_count = entries.len();
-                        
-                        for (i, (key, entry)) in keys.into_iter().zip(entries.into_iter()).enumerate() {
-                            cache_clone.insert(key, entry).await;
-                            
-                            // 更新缓存条目计数指标
-                            if (i + 1) % 1000 == 0 {
-                                METRICS.cache_entries().set((i + 1) as i64);
-                            }
-                            
-                            if i > 0 && i % 1000 == 0 {
-                                debug!("Loaded {} cache entries so far", i);
-                            }
-                        }
-                        
-                        METRICS.cache_entries().set(entry_count as i64);
-                        METRICS.cache_operations_total().with_label_values(&[CACHE_OP_INSERT]).inc_by(entry_count as u64);
-                        
-                        info!("Successfully loaded all cache entries from disk");
-                    };
+                    // 将加载的条目分批、限流地导入到缓存
+                    let load_fut = Self::warm_cache(
+                        cache_clone,
+                        keys,
+                        entries,
+                        load_concurrency,
+                        load_rate,
+                    );
                     
                     // 在后台执行缓存加载
                     tokio::spawn(load_fut);
                 }
+                Err(ServerError::Cache(reason)) => {
+                    METRICS.cache_persist_operations_total().with_label_values(&[PERSIST_OP_LOAD_FAILED]).inc();
+                    Self::handle_corrupt_cache_file(&dns_cache.config.persistence, &reason)?;
+                }
                 Err(e) => {
                     warn!("Failed to load cache from file: {}", e);
                     METRICS.cache_persist_operations_total().with_label_values(&[PERSIST_OP_LOAD_FAILED]).inc();
@@ -430,6 +771,24 @@ impl DnsCache {
             }
         }
         
+        // 启动时检查持久化路径是否可写，不可写时仅报告一次并禁用本次运行的持久化保存
+        if dns_cache.config.persistence.enabled {
+            if let Err(e) = Self::check_persistence_writable(&dns_cache.config.persistence.path) {
+                if dns_cache.config.persistence.fail_on_unwritable {
+                    return Err(ServerError::Cache(format!(
+                        "Cache persistence path {} is not writable: {}",
+                        dns_cache.config.persistence.path, e
+                    )));
+                }
+                error!(
+                    path = %dns_cache.config.persistence.path,
+                    error = %e,
+                    "Cache persistence path is not writable, persistence disabled for this session"
+                );
+                dns_cache.config.persistence.enabled = false;
+            }
+        }
+        
         // 如果启用了持久化缓存并启用了周期性保存
         if dns_cache.config.persistence.enabled && dns_cache.config.persistence.periodic.enabled {
             let config_clone = dns_cache.config.clone();
@@ -488,6 +847,7 @@ impl DnsCache {
         
         // 克隆缓存对象以避免移动问题
         let cache_clone = dns_cache.cache.clone();
+        let weighted = dns_cache.config.max_memory_bytes > 0;
         
         tokio::spawn(async move {
             let interval_duration = std::time::Duration::from_secs(15); // 15秒间隔
@@ -507,12 +867,40 @@ impl DnsCache {
                 cache_clone.run_pending_tasks().await;
                 let cache_size = cache_clone.entry_count();
                 METRICS.cache_entries().set(cache_size as i64);
+                METRICS.cache_memory_bytes().set(Self::estimated_memory(&cache_clone, weighted) as i64);
             }
         });
         
         dns_cache.metrics_task_cancel = Some(metrics_cancel_flag_clone);
         
-        dns_cache
+        Ok(dns_cache)
+    }
+    
+    // 按 on_corrupt 策略处理损坏的缓存文件
+    fn handle_corrupt_cache_file(config: &PersistenceCacheConfig, reason: &str) -> Result<()> {
+        match config.on_corrupt {
+            CorruptCachePolicy::Fail => Err(ServerError::Cache(format!(
+                "Cache file {} is corrupt: {}", config.path, reason
+            ))),
+            CorruptCachePolicy::Ignore => {
+                warn!("Cache file {} is corrupt, starting with an empty cache: {}", config.path, reason);
+                Ok(())
+            },
+            CorruptCachePolicy::Quarantine => {
+                let quarantine_path = format!("{}{}", config.path, CACHE_FILE_CORRUPT_SUFFIX);
+                match std::fs::rename(&config.path, &quarantine_path) {
+                    Ok(()) => warn!(
+                        "Cache file {} is corrupt, moved to {} and starting with an empty cache: {}",
+                        config.path, quarantine_path, reason
+                    ),
+                    Err(e) => warn!(
+                        "Cache file {} is corrupt and could not be moved to {} ({}), starting with an empty cache: {}",
+                        config.path, quarantine_path, e, reason
+                    ),
+                }
+                Ok(())
+            },
+        }
     }
     
     // 获取当前系统时间（秒）
@@ -525,7 +913,12 @@ impl DnsCache {
     }
     
     // 基于客户端 ECS 信息查找缓存条目
-    pub async fn get_with_ecs(&self, key: &CacheKey, _client_ecs: Option<&EcsData>) -> Option<Message> {
+    pub async fn get_with_ecs(&self, key: &CacheKey, client_ecs: Option<&EcsData>) -> Option<Message> {
+        self.get_entry_with_ecs(key, client_ecs).await.map(|(message, _)| message)
+    }
+    
+    // 基于客户端 ECS 信息查找缓存条目，同时返回条目的 JSON 应答缓存槽位（启用时）
+    pub async fn get_entry_with_ecs(&self, key: &CacheKey, _client_ecs: Option<&EcsData>) -> Option<(Message, Option<CachedJson>)> {
         // 检查缓存是否启用
         if !self.is_enabled() {
             return None;
@@ -540,14 +933,17 @@ impl DnsCache {
             
             // 检查是否过期
             if Self::get_system_time_secs() <= entry.expires_at {
-                // 缓存命中，记录指标
-                METRICS
-                    .cache_operations_total()
-                    .with_label_values(&[CACHE_OP_HIT])
-                    .inc();
-                    
-                debug!("Cache hit for key: {:?}", key);
-                return Some(entry.message.as_ref().clone());
+                if let Some(message) = self.decode_entry(key, &entry).await {
+                    // 缓存命中，记录指标
+                    METRICS
+                        .cache_operations_total()
+                        .with_label_values(&[CACHE_OP_HIT])
+                        .inc();
+                    Self::record_hit_age(&entry);
+                        
+                    debug!("Cache hit for key: {:?}", key);
+                    return Some((message, entry.json.clone()));
+                }
             }
         }
         
@@ -565,14 +961,17 @@ impl DnsCache {
                     // 尝试使用基础缓存（无ECS）匹配
                     // 只有当消息中没有ECS选项时才使用
                     if base_entry.ecs_data.is_none() {
-                        // 缓存命中，记录指标
-                        METRICS
-                            .cache_operations_total()
-                            .with_label_values(&[CACHE_OP_HIT])
-                            .inc();
-                        
-                        debug!("Cache hit for base key (non-ECS): {:?}", base_key);
-                        return Some(base_entry.message.as_ref().clone());
+                        if let Some(message) = self.decode_entry(&base_key, &base_entry).await {
+                            // 缓存命中，记录指标
+                            METRICS
+                                .cache_operations_total()
+                                .with_label_values(&[CACHE_OP_HIT])
+                                .inc();
+                            Self::record_hit_age(&base_entry);
+                            
+                            debug!("Cache hit for base key (non-ECS): {:?}", base_key);
+                            return Some((message, base_entry.json.clone()));
+                        }
                     }
                 }
             }
@@ -587,6 +986,122 @@ impl DnsCache {
         None
     }
     
+    // 查找只读基线缓存中未过期的条目（主缓存未命中且路由已确定使用上游时调用）
+    // 基线文件不含命名空间，路由配置档案与隔离上游组的查询不使用基线；ECS 查询使用基础键
+    pub fn get_baseline(&self, key: &CacheKey) -> Option<Message> {
+        if self.baseline.is_empty() || key.namespace.is_some() {
+            return None;
+        }
+        
+        let base_key = key.get_base_key();
+        let entry = self.baseline.get(&base_key)?;
+        if Self::get_system_time_secs() > entry.expires_at {
+            return None;
+        }
+        let message = entry.message.to_message().ok()?;
+        
+        METRICS.cache_baseline_hits_total().inc();
+        debug!("Baseline cache hit for key: {:?}", base_key);
+        Some(message)
+    }
+    
+    // 记录命中条目自存储以来的时长
+    fn record_hit_age(entry: &CacheEntry) {
+        let age = Self::get_system_time_secs().saturating_sub(entry.stored_at);
+        METRICS.cache_hit_age_seconds()
+            .with_label_values(&[])
+            .observe(age as f64);
+    }
+    
+    // 还原缓存条目中的消息，失败时移除该条目并视为未命中
+    async fn decode_entry(&self, key: &CacheKey, entry: &CacheEntry) -> Option<Message> {
+        match entry.message.to_message() {
+            Ok(message) => Some(message),
+            Err(e) => {
+                warn!("Failed to decode cached message for key {:?}: {}", key, e);
+                self.cache.invalidate(key).await;
+                None
+            }
+        }
+    }
+    
+    // 查找已过期但仍在允许时长内的缓存条目（仅在上游失败时使用），应答 TTL 统一改写为 stale_on_error_ttl
+    pub async fn get_stale(&self, key: &CacheKey) -> Option<Message> {
+        if !self.is_enabled() || !self.config.stale_on_error {
+            return None;
+        }
+        
+        let now = Self::get_system_time_secs();
+        
+        // 先尝试完全匹配，再尝试基础键（无 ECS）
+        let base_key = key.ecs_network.is_some().then(|| key.get_base_key());
+        for candidate in std::iter::once(key).chain(base_key.as_ref()) {
+            let Some(entry) = self.cache.get(candidate).await else {
+                continue;
+            };
+            
+            // 过期过久的条目不再返回
+            if now.saturating_sub(entry.expires_at) > self.config.stale_on_error_max_age {
+                continue;
+            }
+            
+            // 基础键仅在条目不含 ECS 数据时可用
+            if candidate.ecs_network.is_none() && key.ecs_network.is_some() && entry.ecs_data.is_some() {
+                continue;
+            }
+            
+            if let Some(mut message) = self.decode_entry(candidate, &entry).await {
+                let ttl = self.config.stale_on_error_ttl;
+                for record in message.answers_mut() {
+                    record.set_ttl(ttl);
+                }
+                for record in message.name_servers_mut() {
+                    record.set_ttl(ttl);
+                }
+                
+                debug!("Serving stale cache entry for key: {:?}", candidate);
+                return Some(message);
+            }
+        }
+        
+        None
+    }
+    
+    // 过期条目刷新失败时保留该条目：自首次失败起 refresh_failure_grace_secs 内继续作为缓存命中返回，
+    // 避免上游抖动时条目反复失效与重建；仅保留过期时长不超过宽限期的条目，成功刷新后重新计时
+    pub async fn retain_on_refresh_failure(&self, key: &CacheKey) -> Option<Message> {
+        let grace = self.config.refresh_failure_grace_secs;
+        if !self.is_enabled() || grace == 0 {
+            return None;
+        }
+        
+        let entry = self.cache.get(key).await?;
+        let now = Self::get_system_time_secs();
+        let failed_at = match entry.refresh_failed_at {
+            Some(failed_at) => failed_at,
+            None if now.saturating_sub(entry.expires_at) <= grace => now,
+            None => return None,
+        };
+        
+        // 宽限期已结束，不再保留
+        let retain_until = failed_at + grace;
+        if now > retain_until {
+            return None;
+        }
+        
+        let message = self.decode_entry(key, &entry).await?;
+        let retained = CacheEntry {
+            expires_at: retain_until,
+            last_accessed: Arc::new(AtomicU64::new(now)),
+            refresh_failed_at: Some(failed_at),
+            ..entry
+        };
+        self.cache.insert(key.clone(), retained).await;
+        
+        debug!("Retaining cache entry after refresh failure until {} for key: {:?}", retain_until, key);
+        Some(message)
+    }
+    
     // 查找缓存条目
     pub async fn get(&self, key: &CacheKey) -> Option<Message> {
         // 直接调用 get_with_ecs，不带 ECS 信息
@@ -607,12 +1122,17 @@ impl DnsCache {
         let expires_at = now + ttl as u64;
         
         // 创建缓存条目（尽量减少克隆操作）
+        let cached = CachedMessage::encode(message, &self.config);
         let entry = CacheEntry {
-            message: Arc::new(message.clone()),
+            size_bytes: CacheEntry::estimate_size(key, &cached, message),
+            message: cached,
             expires_at,
+            stored_at: now,
             access_count: Arc::new(AtomicU64::new(1)),
             last_accessed: Arc::new(AtomicU64::new(now)),
             ecs_data: client_ecs.cloned(),
+            json: self.config.json_answer_cache.then(CachedJson::default),
+            refresh_failed_at: None,
         };
         
         // 记录缓存插入
@@ -636,19 +1156,25 @@ impl DnsCache {
     
     // 使用自动 TTL 存储缓存条目
     pub async fn put_with_auto_ttl(&self, key: &CacheKey, message: &Message) -> Result<()> {
-        let ttl = self.calculate_ttl(message);
-        
-        // 记录缓存TTL分布
-        METRICS.cache_ttl_seconds()
-            .with_label_values(&[])
-            .observe(ttl as f64);
-            
-        self.put(key, message, ttl).await
+        self.put_with_auto_ttl_and_ecs(key, message, None).await
     }
     
     // 使用自动 TTL 存储缓存条目，支持 ECS
     pub async fn put_with_auto_ttl_and_ecs(&self, key: &CacheKey, message: &Message, client_ecs: Option<&EcsData>) -> Result<()> {
-        let ttl = self.calculate_ttl(message);
+        self.put_with_ttl_bounds_and_ecs(key, message, self.config.ttl.min, self.config.ttl.max, client_ecs).await
+    }
+    
+    // 以指定的 TTL 范围（如上游组覆盖值）计算 TTL 并存储缓存条目，支持 ECS
+    pub async fn put_with_ttl_bounds_and_ecs(
+        &self,
+        key: &CacheKey,
+        message: &Message,
+        min_ttl: u32,
+        max_ttl: u32,
+        client_ecs: Option<&EcsData>,
+    ) -> Result<()> {
+        let ttl = self.calculate_ttl_within(message, min_ttl, max_ttl);
+        let ttl = self.adapt_ttl(key, message, ttl).await;
         
         // 记录缓存TTL分布
         METRICS.cache_ttl_seconds()
@@ -660,7 +1186,12 @@ impl DnsCache {
     
     // 计算缓存条目的 TTL
     pub fn calculate_ttl(&self, message: &Message) -> u32 {
-        let mut min_ttl = self.config.ttl.max;
+        self.calculate_ttl_within(message, self.config.ttl.min, self.config.ttl.max)
+    }
+    
+    // 计算缓存条目的 TTL，限制在给定的最小/最大 TTL 之间
+    pub fn calculate_ttl_within(&self, message: &Message, ttl_min: u32, ttl_max: u32) -> u32 {
+        let mut min_ttl = ttl_max;
         
         // 遍历所有记录，找出最小的 TTL
         for record in message.answers() {
@@ -677,20 +1208,98 @@ impl DnsCache {
         
         // 如果没有找到任何记录，使用最小 TTL
         if message.answer_count() == 0 {
-            min_ttl = self.config.ttl.min;
+            min_ttl = ttl_min;
+        } else if min_ttl == 0 && self.config.override_zero_ttl_secs > 0 {
+            // 上游 TTL=0 的应答按配置的时长强制缓存，不受最小/最大 TTL 限制
+            return self.config.override_zero_ttl_secs;
         }
         
         // 应用配置的最小/最大 TTL 限制
-        min_ttl = min_ttl.max(self.config.ttl.min).min(self.config.ttl.max);
+        min_ttl = min_ttl.max(ttl_min).min(ttl_max);
         
         min_ttl
     }
     
+    // 按应答变化历史调整缓存 TTL（启用自适应 TTL 时）：
+    // 应答在上次缓存过期后刷新且未变化时延长一级，应答变化时缩短一级，调整后的 TTL 不超出配置的上下限
+    async fn adapt_ttl(&self, key: &CacheKey, message: &Message, ttl: u32) -> u32 {
+        let Some(history) = &self.answer_history else {
+            return ttl;
+        };
+        if message.response_code() != ResponseCode::NoError {
+            return ttl;
+        }
+        let Some(fingerprint) = Self::answer_fingerprint(message) else {
+            return ttl;
+        };
+        
+        let now = Self::get_system_time_secs();
+        let steps = match history.get(key).await {
+            // 上次缓存过期前的重复写入（如并发未命中）不计为一次刷新
+            Some(previous) if previous.fingerprint == fingerprint && now < previous.expires_at => previous.steps,
+            Some(previous) if previous.fingerprint == fingerprint => (previous.steps + 1).min(ADAPTIVE_TTL_MAX_GROWTH_STEPS),
+            Some(previous) => (previous.steps.min(0) - 1).max(-ADAPTIVE_TTL_MAX_SHRINK_STEPS),
+            None => 0,
+        };
+        
+        let adaptive = &self.config.adaptive_ttl;
+        let adapted = match steps.cmp(&0) {
+            std::cmp::Ordering::Greater => ttl.saturating_mul(1 << steps).min(adaptive.max).max(ttl),
+            std::cmp::Ordering::Less => (ttl >> -steps).max(adaptive.min).min(ttl),
+            std::cmp::Ordering::Equal => ttl,
+        };
+        if adapted != ttl {
+            debug!(key = ?key, steps, ttl, adapted, "Adaptive TTL adjusted cache TTL");
+        }
+        
+        history.insert(key.clone(), AnswerHistory { fingerprint, steps, expires_at: now + adapted as u64 }).await;
+        adapted
+    }
+    
+    // 计算应答节记录的指纹（忽略 TTL 与记录顺序），无应答记录时返回 None
+    fn answer_fingerprint(message: &Message) -> Option<u64> {
+        let mut records: Vec<String> = message.answers().iter()
+            .filter(|record| record.record_type() != RecordType::OPT)
+            .map(|record| format!(
+                "{} {} {} {}",
+                record.name().to_lowercase(),
+                record.dns_class(),
+                record.record_type(),
+                record.data().map(|data| data.to_string()).unwrap_or_default(),
+            ))
+            .collect();
+        if records.is_empty() {
+            return None;
+        }
+        records.sort_unstable();
+        
+        let mut hasher = Xxh64::new(0);
+        for record in &records {
+            hasher.update(record.as_bytes());
+            hasher.update(b"\n");
+        }
+        Some(hasher.digest())
+    }
+    
     // 获取负缓存TTL
     pub fn negative_ttl(&self) -> u32 {
         self.config.ttl.negative
     }
     
+    // 计算负响应（NXDOMAIN/NODATA）的缓存 TTL（RFC 2308）
+    // 取授权段 SOA 的 TTL 与 MINIMUM 中较小者，且不超过配置的负缓存 TTL；无 SOA 时使用配置值
+    pub fn negative_ttl_for(&self, message: &Message) -> u32 {
+        let negative_ttl = self.config.ttl.negative;
+        message
+            .name_servers()
+            .iter()
+            .find_map(|record| match record.data() {
+                Some(RData::SOA(soa)) => Some(record.ttl().min(soa.minimum())),
+                _ => None,
+            })
+            .map_or(negative_ttl, |soa_ttl| soa_ttl.min(negative_ttl))
+    }
+    
     // 检查缓存是否启用
     pub fn is_enabled(&self) -> bool {
         self.config.enabled
@@ -703,6 +1312,7 @@ impl DnsCache {
         
         // 记录缓存清空
         METRICS.cache_entries().set(0);
+        METRICS.cache_memory_bytes().set(0);
         METRICS.cache_operations_total().with_label_values(&[CACHE_OP_CLEAR]).inc();
     }
     
@@ -718,6 +1328,26 @@ impl DnsCache {
         count
     }
     
+    // 获取当前缓存条目估算占用的内存字节数
+    pub async fn memory_bytes(&self) -> u64 {
+        self.cache.run_pending_tasks().await;
+        let bytes = Self::estimated_memory(&self.cache, self.config.max_memory_bytes > 0);
+        
+        // 更新缓存内存指标
+        METRICS.cache_memory_bytes().set(bytes as i64);
+        
+        bytes
+    }
+    
+    // 启用加权时直接读取加权总量，否则逐条累加估算值
+    fn estimated_memory(cache: &ShardedCache, weighted: bool) -> u64 {
+        if weighted {
+            cache.weighted_size()
+        } else {
+            cache.iter().map(|(_, entry)| entry.size_bytes as u64).sum()
+        }
+    }
+    
     // 检查缓存是否为空
     pub async fn is_empty(&self) -> bool {
         self.len().await == 0
@@ -749,10 +1379,24 @@ impl DnsCache {
         result
     }
     
+    // 检查持久化路径是否可写：创建父目录并写入、删除探测文件
+    fn check_persistence_writable(path: &str) -> std::io::Result<()> {
+        if let Some(parent) = Path::new(path).parent() {
+            if !parent.as_os_str().is_empty() {
+                create_dir_all(parent)?;
+            }
+        }
+        
+        let probe_path = format!("{}.probe", path);
+        let result = File::create(&probe_path).and_then(|mut file| file.write_all(b"owdns"));
+        let _ = std::fs::remove_file(&probe_path);
+        result
+    }
+    
     // 实际执行缓存保存的内部方法
     async fn save_cache_to_file(
         config: &PersistenceCacheConfig, 
-        cache: &Cache<CacheKey, CacheEntry>
+        cache: &ShardedCache
     ) -> Result<usize> {
         // 确保目录存在
         if let Some(parent) = Path::new(&config.path).parent() {
@@ -776,6 +1420,11 @@ impl DnsCache {
         // 使用快照方式获取所有缓存条目
         let iter = cache.iter();
         for (key, entry) in iter {
+            // 带命名空间的条目不持久化，避免旧格式文件加载后跨命名空间共享
+            if key.namespace.is_some() {
+                continue;
+            }
+            
             if entry.expires_at > now {  // 只保存未过期的条目
                 // 预先获取计数器的值，避免后续多次原子读取
                 let access_count = entry.access_count.load(Ordering::Relaxed);
@@ -812,43 +1461,22 @@ impl DnsCache {
         
         // 复制临时路径
         let temp_path_clone = temp_path.clone();
+        let save_concurrency = config.save_concurrency.max(1);
         
-        // 在后台线程中执行IO操作
+        // 在后台线程中执行序列化与IO操作
         let saved_count = task::spawn_blocking(move || -> Result<usize> {
-            // 准备序列化数据
-            let mut persistable_keys = Vec::with_capacity(all_items.len());
-            let mut persistable_entries = Vec::with_capacity(all_items.len());
-            
-            for item in all_items {
-                // 将消息序列化为字节
-                let message_bytes = match item.entry.message.to_vec() {
-                    Ok(bytes) => bytes,
-                    Err(e) => {
-                        warn!("Failed to serialize message: {}", e);
-                        continue;
-                    }
-                };
-                
-                // 从缓存键创建可序列化键，需要解引用 Arc 包装的字符串
-                let persistable_key = PersistableCacheKey {
-                    name: (*item.key.name).clone(),
-                    record_type: item.key.record_type,
-                    record_class: item.key.record_class,
-                    ecs_network: item.key.ecs_network.as_ref().map(|s| (**s).clone()),
-                    ecs_scope_prefix_length: item.key.ecs_scope_prefix_length,
-                };
-                
-                let persistable_entry = PersistableCacheEntry {
-                    message_bytes,
-                    expires_at: item.entry.expires_at,
-                    stored_at: now,
-                    access_count: item.access_count,
-                    last_accessed: item.last_accessed,
-                };
-                
-                persistable_keys.push(persistable_key);
-                persistable_entries.push(persistable_entry);
-            }
+            // 按并发数分块并行序列化，拼接结果保持保存顺序
+            let chunk_size = all_items.len().div_ceil(save_concurrency).max(1);
+            let chunks = std::thread::scope(|scope| {
+                let handles: Vec<_> = all_items.chunks(chunk_size)
+                    .map(|chunk| scope.spawn(move || chunk.iter().filter_map(Self::to_persistable).collect::<Vec<_>>()))
+                    .collect();
+                handles.into_iter()
+                    .map(|handle| handle.join()
+                        .map_err(|_| ServerError::Other("Cache serialization thread panicked".to_string())))
+                    .collect::<Result<Vec<_>>>()
+            })?;
+            let (persistable_keys, persistable_entries): (Vec<_>, Vec<_>) = chunks.into_iter().flatten().unzip();
             
             // 打开临时文件用于写入
             let file = File::create(&temp_path_clone)
@@ -886,13 +1514,81 @@ impl DnsCache {
         Ok(saved_count)
     }
     
+    // 将缓存项转换为可持久化的键与条目，消息序列化失败时跳过
+    fn to_persistable(item: &CacheItemForPersistence) -> Option<(PersistableCacheKey, PersistableCacheEntry)> {
+        // 将消息序列化为字节
+        let message_bytes = match item.entry.message.to_wire() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!("Failed to serialize message: {}", e);
+                return None;
+            }
+        };
+        
+        // 从缓存键创建可序列化键，需要解引用 Arc 包装的字符串
+        let persistable_key = PersistableCacheKey {
+            name: (*item.key.name).clone(),
+            record_type: item.key.record_type,
+            record_class: item.key.record_class,
+            ecs_network: item.key.ecs_network.as_ref().map(|s| (**s).clone()),
+            ecs_scope_prefix_length: item.key.ecs_scope_prefix_length,
+            dnssec_ok: item.key.dnssec_ok,
+            checking_disabled: item.key.checking_disabled,
+        };
+        
+        let persistable_entry = PersistableCacheEntry {
+            message_bytes,
+            expires_at: item.entry.expires_at,
+            stored_at: item.entry.stored_at,
+            access_count: item.access_count,
+            last_accessed: item.last_accessed,
+        };
+        
+        Some((persistable_key, persistable_entry))
+    }
+    
+    // 加载只读基线缓存文件，加载失败时记录警告并以空基线继续
+    // 基线文件通常在发布前生成，条目按原始 TTL（生成时的 expires_at - stored_at）从加载时起重新计算过期时间
+    fn load_baseline(cache_config: &CacheConfig) -> HashMap<CacheKey, CacheEntry> {
+        let path = &cache_config.persistence.baseline_path;
+        if path.is_empty() || !cache_config.enabled {
+            return HashMap::new();
+        }
+        
+        match task::block_in_place(|| Self::load_cache_from_file(cache_config, path, false)) {
+            Ok((keys, entries)) => {
+                let now = Self::get_system_time_secs();
+                let baseline: HashMap<CacheKey, CacheEntry> = keys.into_iter()
+                    .zip(entries)
+                    .filter_map(|(key, mut entry)| {
+                        let ttl = entry.expires_at.saturating_sub(entry.stored_at);
+                        if ttl == 0 {
+                            return None;
+                        }
+                        entry.stored_at = now;
+                        entry.expires_at = now + ttl;
+                        Some((key, entry))
+                    })
+                    .collect();
+                info!(path = %path, entries = baseline.len(), "Baseline cache loaded");
+                baseline
+            },
+            Err(e) => {
+                warn!(path = %path, error = %e, "Failed to load baseline cache file, continuing without baseline");
+                HashMap::new()
+            },
+        }
+    }
+    
     // 从文件加载缓存
     fn load_cache_from_file(
-        config: &PersistenceCacheConfig
+        cache_config: &CacheConfig,
+        cache_path: &str,
+        skip_expired: bool,
     ) -> Result<(Vec<CacheKey>, Vec<CacheEntry>)> {
-        let path = Path::new(&config.path);
+        let path = Path::new(cache_path);
         if !path.exists() {
-            debug!("Cache file does not exist: {}", config.path);
+            debug!("Cache file does not exist: {}", cache_path);
             return Ok((Vec::new(), Vec::new()));
         }
         
@@ -906,38 +1602,45 @@ impl DnsCache {
         
         let mut reader = BufReader::new(file);
         
-        // 读取并验证文件头
+        // 读取并验证文件头（文件内容无效时返回 ServerError::Cache，按 on_corrupt 策略处理）
         let header: CacheFileHeader = match bincode::deserialize_from(&mut reader) {
             Ok(h) => h,
             Err(e) => {
-                return Err(ServerError::Other(format!("Failed to deserialize cache header: {}", e)));
+                return Err(ServerError::Cache(format!("Failed to deserialize cache header: {}", e)));
             }
         };
         
         // 验证魔数和版本
         if header.magic != CACHE_FILE_MAGIC {
-            return Err(ServerError::Other("Invalid cache file format".to_string()));
-        }
-        
-        if header.version != CACHE_FILE_VERSION {
-            return Err(ServerError::Other(format!(
-                "Unsupported cache file version: {}, expected: {}", 
-                header.version, CACHE_FILE_VERSION
-            )));
+            return Err(ServerError::Cache("Invalid cache file format".to_string()));
         }
         
         // 获取当前时间
         let now = Self::get_system_time_secs();
         
-        // 读取所有缓存条目
+        // 读取所有缓存条目，上一版本的文件按旧格式读取后迁移，避免升级后缓存全部失效
         let (persistable_keys, persistable_entries): (
             Vec<PersistableCacheKey>, 
             Vec<PersistableCacheEntry>
-        ) = match bincode::deserialize_from(&mut reader) {
-            Ok(data) => data,
-            Err(e) => {
-                return Err(ServerError::Other(format!("Failed to deserialize cache data: {}", e)));
-            }
+        ) = match header.version {
+            CACHE_FILE_VERSION => bincode::deserialize_from(&mut reader)
+                .map_err(|e| ServerError::Cache(format!("Failed to deserialize cache data: {}", e)))?,
+            CACHE_FILE_PREVIOUS_VERSION => {
+                let (keys, entries): (Vec<PersistableCacheKeyV1>, Vec<PersistableCacheEntry>) =
+                    bincode::deserialize_from(&mut reader)
+                        .map_err(|e| ServerError::Cache(format!("Failed to deserialize cache data: {}", e)))?;
+                info!(
+                    "Migrating {} entries from cache file version {} to version {}",
+                    keys.len(), header.version, CACHE_FILE_VERSION
+                );
+                (keys.into_iter().map(PersistableCacheKey::from).collect(), entries)
+            },
+            version => {
+                return Err(ServerError::Cache(format!(
+                    "Unsupported cache file version: {}, expected: {}", 
+                    version, CACHE_FILE_VERSION
+                )));
+            },
         };
         
         // 转换为内部格式
@@ -945,10 +1648,10 @@ impl DnsCache {
         let mut entries = Vec::with_capacity(persistable_entries.len());
         
         for (persistable_key, persistable_entry) in persistable_keys.into_iter()
-            .zip(persistable_entries.into_iter()) 
+            .zip(persistable_entries) 
         {
             // 检查是否过期
-            if config.skip_expired_on_load && persistable_entry.expires_at <= now {
+            if skip_expired && persistable_entry.expires_at <= now {
                 continue;
             }
             
@@ -968,14 +1671,22 @@ impl DnsCache {
                 record_class: persistable_key.record_class,
                 ecs_network: persistable_key.ecs_network.map(Arc::new),
                 ecs_scope_prefix_length: persistable_key.ecs_scope_prefix_length,
+                namespace: None,
+                dnssec_ok: persistable_key.dnssec_ok,
+                checking_disabled: persistable_key.checking_disabled,
             };
             
+            let cached = CachedMessage::encode(&message, cache_config);
             let entry = CacheEntry {
-                message: Arc::new(message),
+                size_bytes: CacheEntry::estimate_size(&key, &cached, &message),
+                message: cached,
                 expires_at: persistable_entry.expires_at,
+                stored_at: persistable_entry.stored_at,
                 access_count: Arc::new(AtomicU64::new(persistable_entry.access_count)),
                 last_accessed: Arc::new(AtomicU64::new(persistable_entry.last_accessed)),
                 ecs_data: None,
+                json: cache_config.json_answer_cache.then(CachedJson::default),
+                refresh_failed_at: None,
             };
             
             keys.push(key);
@@ -992,6 +1703,191 @@ impl DnsCache {
         Ok((keys, entries))
     }
     
+    // 将从文件加载的条目分批并发插入缓存，可选按速率限流
+    async fn warm_cache(
+        cache: ShardedCache,
+        keys: Vec<CacheKey>,
+        entries: Vec<CacheEntry>,
+        concurrency: usize,
+        rate: u64,
+    ) {
+        let entry_count = entries.len();
+        let warm_start = Instant::now();
+        
+        // 记录加载进度指标
+        METRICS.cache_load_total_entries().set(entry_count as i64);
+        METRICS.cache_load_loaded_entries().set(0);
+        
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        let loaded = Arc::new(AtomicU64::new(0));
+        let mut tasks = JoinSet::new();
+        let mut items = keys.into_iter().zip(entries);
+        let mut dispatched: u64 = 0;
+        
+        loop {
+            let batch: Vec<(CacheKey, CacheEntry)> = items.by_ref().take(CACHE_LOAD_BATCH_SIZE).collect();
+            if batch.is_empty() {
+                break;
+            }
+            
+            // 按配置的速率限流：已派发条目数不超过 rate * 已用时间
+            if rate > 0 {
+                let expected = std::time::Duration::from_secs_f64(dispatched as f64 / rate as f64);
+                let elapsed = warm_start.elapsed();
+                if expected > elapsed {
+                    tokio::time::sleep(expected - elapsed).await;
+                }
+            }
+            dispatched += batch.len() as u64;
+            
+            // 获取并发许可，限制同时进行的批次数
+            let permit = match semaphore.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => break,
+            };
+            
+            let cache = cache.clone();
+            let loaded = loaded.clone();
+            tasks.spawn(async move {
+                let batch_len = batch.len() as u64;
+                for (key, entry) in batch {
+                    cache.insert(key, entry).await;
+                }
+                drop(permit);
+                
+                // 更新加载进度
+                let total_loaded = loaded.fetch_add(batch_len, Ordering::Relaxed) + batch_len;
+                METRICS.cache_load_loaded_entries().set(total_loaded as i64);
+                METRICS.cache_entries().set(total_loaded as i64);
+                debug!("Loaded {} cache entries so far", total_loaded);
+            });
+        }
+        
+        // 等待所有批次完成
+        while tasks.join_next().await.is_some() {}
+        
+        METRICS.cache_load_loaded_entries().set(entry_count as i64);
+        METRICS.cache_entries().set(entry_count as i64);
+        METRICS.cache_operations_total().with_label_values(&[CACHE_OP_INSERT]).inc_by(entry_count as u64);
+        
+        info!(
+            "Successfully loaded all {} cache entries from disk in {:?}",
+            entry_count,
+            warm_start.elapsed()
+        );
+    }
+    
+    // 导出当前缓存内容，逐行通过通道流式返回，避免一次性缓冲全部条目
+    pub fn dump(&self, format: CacheDumpFormat) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel(CACHE_DUMP_CHANNEL_CAPACITY);
+        let cache = self.cache.clone();
+        
+        // 缓存迭代为同步操作，放入阻塞线程执行，并通过有界通道实现背压
+        task::spawn_blocking(move || {
+            let now = Self::get_system_time_secs();
+            
+            if format == CacheDumpFormat::Text
+                && tx.blocking_send("; name\tttl\tclass\ttype\tdata\n".to_string()).is_err()
+            {
+                return;
+            }
+            
+            for (key, entry) in cache.iter() {
+                // 跳过已过期的条目
+                if entry.expires_at <= now {
+                    continue;
+                }
+                
+                // 跳过无法还原的条目
+                let chunk = match Self::format_dump_entry(&key, &entry, now, format) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                };
+                if tx.blocking_send(chunk).is_err() {
+                    // 接收端已关闭（客户端断开）
+                    debug!("Cache dump receiver closed, stopping iteration");
+                    break;
+                }
+            }
+        });
+        
+        rx
+    }
+    
+    // 格式化单个缓存条目用于导出
+    fn format_dump_entry(key: &CacheKey, entry: &CacheEntry, now: u64, format: CacheDumpFormat) -> Option<String> {
+        let ttl_remaining = entry.expires_at.saturating_sub(now);
+        let record_type = RecordType::from(key.record_type);
+        let record_class = DNSClass::from(key.record_class);
+        let message = entry.message.to_message().ok()?;
+        let rcode = message.response_code();
+        
+        // 应答记录的 TTL 以剩余时间为上限
+        let answers: Vec<String> = message.answers().iter()
+            .map(|record| {
+                let ttl = (record.ttl() as u64).min(ttl_remaining);
+                let data = record.data().map(|d| d.to_string()).unwrap_or_default();
+                format!("{}\t{}\t{}\t{}\t{}", record.name(), ttl, record.dns_class(), record.record_type(), data)
+            })
+            .collect();
+        
+        let chunk = match format {
+            CacheDumpFormat::Text => {
+                let mut ecs_comment = match (&key.ecs_network, key.ecs_scope_prefix_length) {
+                    (Some(network), Some(scope)) => format!("\t; ecs={} scope={}", network, scope),
+                    (Some(network), None) => format!("\t; ecs={}", network),
+                    _ => String::new(),
+                };
+                if let Some(namespace) = &key.namespace {
+                    ecs_comment.push_str(&format!("\t; namespace={}", namespace));
+                }
+                if key.dnssec_ok {
+                    ecs_comment.push_str("\t; do");
+                }
+                if key.checking_disabled {
+                    ecs_comment.push_str("\t; cd");
+                }
+                
+                if answers.is_empty() {
+                    // 负缓存或无应答条目以注释形式输出
+                    format!(
+                        "; {}\t{}\t{}\t{}\t{}{}\n",
+                        key.name, ttl_remaining, record_class, record_type, rcode, ecs_comment
+                    )
+                } else {
+                    let mut chunk = String::new();
+                    for answer in &answers {
+                        chunk.push_str(answer);
+                        chunk.push_str(&ecs_comment);
+                        chunk.push('\n');
+                    }
+                    chunk
+                }
+            },
+            CacheDumpFormat::Json => {
+                let dump_entry = CacheDumpEntry {
+                    name: key.name.to_string(),
+                    record_type: record_type.to_string(),
+                    record_class: record_class.to_string(),
+                    ttl_remaining,
+                    rcode: rcode.to_string(),
+                    answers,
+                    ecs_network: key.ecs_network.as_ref().map(|n| n.to_string()),
+                    ecs_scope_prefix_length: key.ecs_scope_prefix_length,
+                    namespace: key.namespace.as_ref().map(|n| n.to_string()),
+                    dnssec_ok: key.dnssec_ok,
+                    checking_disabled: key.checking_disabled,
+                };
+                
+                let mut line = serde_json::to_string(&dump_entry).unwrap_or_default();
+                line.push('\n');
+                line
+            },
+        };
+        
+        Some(chunk)
+    }
+    
     // 关闭缓存，执行清理操作
     pub async fn shutdown(&self) -> Result<()> {
         // 取消周期性保存任务
@@ -1052,11 +1948,14 @@ impl From<&Message> for CacheKey {
         // 仅使用第一个查询作为缓存键
         if let Some(query) = message.queries().first() {
             CacheKey {
-                name: Arc::new(query.name().to_string()),
+                name: Arc::new(query.name().to_ascii()),
                 record_type: query.query_type().into(),
                 record_class: query.query_class().into(),
                 ecs_network: None,
                 ecs_scope_prefix_length: None,
+                namespace: None,
+                dnssec_ok: message.extensions().as_ref().is_some_and(|edns| edns.dnssec_ok()),
+                checking_disabled: message.checking_disabled(),
             }
         } else {
             // 创建一个空键，实际上不应该发生
@@ -1066,6 +1965,9 @@ impl From<&Message> for CacheKey {
                 record_class: 0,
                 ecs_network: None,
                 ecs_scope_prefix_length: None,
+                namespace: None,
+                dnssec_ok: false,
+                checking_disabled: false,
             }
         }
     }
@@ -0,0 +1,143 @@
+// src/server/cname.rs
+
+use std::net::IpAddr;
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use tracing::debug;
+use crate::server::ecs::EcsData;
+use crate::server::error::Result;
+use crate::server::upstream::{UpstreamManager, UpstreamSelection};
+
+// CNAME 展平：追踪 CNAME 链，并将最终的 A/AAAA 记录改写到查询名下
+// 无法展平（非 A/AAAA 查询、无 CNAME、链不完整或超出深度）时原样返回响应
+pub async fn flatten_cname(
+    upstream: &UpstreamManager,
+    query_message: &Message,
+    response: Message,
+    selection: &UpstreamSelection,
+    client_ip: IpAddr,
+    client_ecs: Option<&EcsData>,
+    max_depth: usize,
+) -> Message {
+    let Some(query) = query_message.queries().first() else {
+        return response;
+    };
+    let query_type = query.query_type();
+    if !matches!(query_type, RecordType::A | RecordType::AAAA)
+        || response.response_code() != ResponseCode::NoError
+    {
+        return response;
+    }
+
+    let query_name = query.name().clone();
+    let mut answers = response.answers().to_vec();
+    let mut current = query_name.clone();
+    let mut chain_ttl = u32::MAX;
+    let mut hops = 0;
+
+    loop {
+        // 沿 CNAME 链前进，链上最小 TTL 作为展平后记录的 TTL 上限
+        if let Some((target, ttl)) = find_cname(&answers, &current) {
+            hops += 1;
+            if hops > max_depth {
+                debug!(name = %query_name, max_depth, "CNAME chain exceeds max depth, skipping flattening");
+                return response;
+            }
+            chain_ttl = chain_ttl.min(ttl);
+            current = target;
+            continue;
+        }
+
+        // 无 CNAME 或已拿到最终记录
+        if hops == 0 || has_records(&answers, &current, query_type) {
+            break;
+        }
+
+        // 链不完整，向同一上游追踪目标
+        let chased = match chase_target(upstream, query_message, &current, query_type, selection, client_ip, client_ecs).await {
+            Ok(chased) if chased.response_code() == ResponseCode::NoError => chased,
+            Ok(chased) => {
+                debug!(target = %current, rcode = ?chased.response_code(), "CNAME target did not resolve, skipping flattening");
+                return response;
+            },
+            Err(e) => {
+                debug!(target = %current, error = %e, "Failed to chase CNAME target, skipping flattening");
+                return response;
+            },
+        };
+
+        answers = chased.answers().to_vec();
+        if find_cname(&answers, &current).is_none() && !has_records(&answers, &current, query_type) {
+            debug!(target = %current, "CNAME target has no usable answers, skipping flattening");
+            return response;
+        }
+    }
+
+    if hops == 0 {
+        return response;
+    }
+
+    // 以查询名改写最终记录
+    let flattened: Vec<Record> = answers
+        .iter()
+        .filter(|record| record.record_type() == query_type && record.name() == &current)
+        .map(|record| {
+            let mut record = record.clone();
+            let ttl = record.ttl().min(chain_ttl);
+            record.set_name(query_name.clone()).set_ttl(ttl);
+            record
+        })
+        .collect();
+
+    debug!(name = %query_name, target = %current, hops, records = flattened.len(), "CNAME chain flattened");
+
+    let mut response = response;
+    response.take_answers();
+    response.add_answers(flattened);
+    response
+}
+
+// 查找以 name 为所有者的 CNAME 记录，返回目标名与 TTL
+fn find_cname(answers: &[Record], name: &Name) -> Option<(Name, u32)> {
+    answers.iter().find_map(|record| match record.data() {
+        Some(RData::CNAME(cname)) if record.name() == name => Some((cname.0.clone(), record.ttl())),
+        _ => None,
+    })
+}
+
+// 判断是否存在以 name 为所有者的指定类型记录
+fn has_records(answers: &[Record], name: &Name, record_type: RecordType) -> bool {
+    answers
+        .iter()
+        .any(|record| record.record_type() == record_type && record.name() == name)
+}
+
+// 构建针对 CNAME 目标的查询并发送至上游
+async fn chase_target(
+    upstream: &UpstreamManager,
+    query_message: &Message,
+    target: &Name,
+    query_type: RecordType,
+    selection: &UpstreamSelection,
+    client_ip: IpAddr,
+    client_ecs: Option<&EcsData>,
+) -> Result<Message> {
+    let mut query = Query::query(target.clone(), query_type);
+    if let Some(original) = query_message.queries().first() {
+        query.set_query_class(original.query_class());
+    }
+
+    let mut chase = Message::new();
+    chase.set_id(fastrand::u16(..))
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true)
+        .set_checking_disabled(query_message.checking_disabled())
+        .add_query(query);
+
+    if let Some(edns) = query_message.extensions() {
+        chase.set_edns(edns.clone());
+    }
+
+    upstream.resolve(&chase, selection.clone(), Some(client_ip), client_ecs).await
+}
@@ -1,26 +1,53 @@
 // src/server/config.rs
 
 use std::fs;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
+use axum::http::HeaderValue;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use hickory_proto::rr::{Name, RecordType};
+use crate::server::blackhole::IpCidr;
 use crate::server::error::{ServerError, Result};
+use crate::server::routing::check_regex_syntax;
 use crate::common::consts::{
     // 服务器配置相关常量
-    default_listen_addr, DEFAULT_LISTEN_TIMEOUT,
+    default_listen_addr, DEFAULT_LISTEN_TIMEOUT, NSID_HOSTNAME,
     // 上游服务器相关常量
-    DEFAULT_QUERY_TIMEOUT,
+    DEFAULT_QUERY_TIMEOUT, DEFAULT_CONNECT_TIMEOUT,
     // 缓存相关常量
-    DEFAULT_CACHE_SIZE, DEFAULT_MIN_TTL, 
-    DEFAULT_MAX_TTL, DEFAULT_NEGATIVE_TTL,
+    DEFAULT_CACHE_SIZE, DEFAULT_CACHE_SHARDS, MAX_CACHE_SHARDS, DEFAULT_MIN_TTL, 
+    DEFAULT_MAX_TTL, DEFAULT_NEGATIVE_TTL, DEFAULT_RESPONSE_MAX_TTL,
+    DEFAULT_CACHE_LOAD_CONCURRENCY, DEFAULT_CACHE_SAVE_CONCURRENCY, DEFAULT_CACHE_COMPRESSION_MIN_BYTES,
+    DEFAULT_DUPLICATE_QUERY_WINDOW_SECS, DEFAULT_DUPLICATE_QUERY_MAX_TRACKED,
+    DEFAULT_CNAME_FLATTEN_MAX_DEPTH,
+    DEFAULT_STALE_ON_ERROR_TTL, DEFAULT_STALE_ON_ERROR_MAX_AGE, MAX_OVERRIDE_ZERO_TTL_SECS,
+    DEFAULT_ADAPTIVE_TTL_MIN, DEFAULT_ADAPTIVE_TTL_MAX, DEFAULT_ADAPTIVE_TTL_MAX_TRACKED,
+    DEFAULT_CACHE_TIME_TO_IDLE_SECS,
+    // 查询日志相关常量
+    DEFAULT_QUERY_LOG_PATH, DEFAULT_QUERY_LOG_MAX_SIZE_MB, DEFAULT_QUERY_LOG_MAX_FILES, DEFAULT_QUERY_LOG_BUFFER_SIZE, DEFAULT_DNSTAP_BUFFER_SIZE,
+    DEFAULT_LOG_SAMPLE_RATE, DEFAULT_PLUGIN_MAX_OPERATIONS,
+    // 查询限制相关常量
+    DEFAULT_MAX_QUERY_SIZE, DEFAULT_MAX_EDNS_OPTIONS, DEFAULT_MAX_QUESTIONS, DEFAULT_MAX_JSON_REQUEST_SIZE, MAX_REQUEST_SIZE, DNS_HEADER_SIZE,
+    // DDR 相关常量
+    DEFAULT_LOCAL_RECORDS_TTL, DEFAULT_DNS64_PREFIX, DEFAULT_TRUST_ANCHOR_TTL,
+    DEFAULT_DDR_PORT, DEFAULT_DDR_ALPN, DEFAULT_DDR_PRIORITY, DEFAULT_DDR_TTL, DDR_DOHPATH_VARIABLE,
+    // 健康探测相关常量
+    DEFAULT_HEALTH_PROBE_NAME, DEFAULT_HEALTH_PROBE_INTERVAL_SECS, DEFAULT_HEALTH_PROBE_TIMEOUT_SECS,
     // 速率限制相关常量
     DEFAULT_PER_IP_RATE, DEFAULT_PER_IP_CONCURRENT,
+    DEFAULT_QUOTA_RESET_HOUR, MAX_QUOTA_RESET_HOUR, DEFAULT_QUOTA_MAX_TRACKED_IPS,
     // HTTP 客户端相关常量
     DEFAULT_HTTP_CLIENT_TIMEOUT, DEFAULT_HTTP_CLIENT_POOL_IDLE_TIMEOUT,
     DEFAULT_HTTP_CLIENT_POOL_MAX_IDLE_CONNECTIONS, DEFAULT_HTTP_CLIENT_AGENT,
+    DEFAULT_HTTP_CLIENT_TLS_MIN_VERSION,
     // 分流相关常量
-    BLACKHOLE_UPSTREAM_GROUP_NAME,
+    BLACKHOLE_UPSTREAM_GROUP_NAME, DEFAULT_BLACKHOLE_SINKHOLE_TTL, DEFAULT_BLACKHOLE_TTL, MAX_BLACKHOLE_TTL,
+    DEFAULT_RESPONSE_PADDING_BLOCK_SIZE,
+    DOH_STANDARD_PATH, DOH_JSON_API_PATH,
     // ECS 相关常量
     ECS_POLICY_STRIP, ECS_POLICY_FORWARD, ECS_POLICY_ANONYMIZE,
     DEFAULT_IPV4_PREFIX_LENGTH, DEFAULT_IPV6_PREFIX_LENGTH,
@@ -34,6 +61,10 @@ use crate::common::consts::{
     DEFAULT_URL_RULE_UPDATE_INTERVAL_SECS,
     MIN_URL_RULE_UPDATE_INTERVAL_SECS,
     MAX_URL_RULE_UPDATE_INTERVAL_SECS,
+    DEFAULT_MAX_RULE_DOWNLOAD_BYTES, DEFAULT_MAX_CONCURRENT_URL_UPDATES, DEFAULT_NOT_READY_WAIT_MS,
+    DEFAULT_URL_RULE_RETRY_MAX_ATTEMPTS,
+    DEFAULT_URL_RULE_RETRY_INITIAL_BACKOFF_SECS,
+    DEFAULT_URL_RULE_RETRY_MAX_BACKOFF_SECS,
 };
 
 // 服务器配置
@@ -62,6 +93,109 @@ pub struct HttpServerConfig {
     // 速率限制配置
     #[serde(default)]
     pub rate_limit: RateLimitConfig,
+    
+    // 管理接口配置
+    #[serde(default)]
+    pub admin: AdminConfig,
+    
+    // NSID 服务器标识（RFC 5001）：空字符串表示禁用，"hostname" 表示使用本机主机名
+    #[serde(default)]
+    pub nsid: String,
+    
+    // 所有路由的公共路径前缀（例如 "/owdns"），空字符串表示挂载在根路径
+    #[serde(default)]
+    pub base_path: String,
+    
+    // RFC 8484 GET 请求的 Accept 头不接受 application/dns-message 时的处理策略
+    #[serde(default)]
+    pub get_accept: GetAcceptPolicy,
+    
+    // DoH 应答携带的 Alt-Svc 头（例如 h3=":443"; ma=86400），空字符串表示不通告
+    // 用于由前置 TLS 代理提供 HTTP/3 的部署，引导客户端后续请求升级至 HTTP/3
+    #[serde(default)]
+    pub advertise_alt_svc: String,
+    
+    // 健康检查配置
+    #[serde(default)]
+    pub health: HealthConfig,
+}
+
+// RFC 8484 GET 请求（/dns-query?dns=...）的 Accept 头不接受 application/dns-message 时的处理策略
+// Accept 缺失或包含 application/dns-message、application/*、*/* 时始终返回 wire 格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GetAcceptPolicy {
+    // 忽略 Accept，始终返回 wire 格式（请求路径优先）
+    #[default]
+    Ignore,
+    // Accept 接受 application/dns-json 时返回 JSON 格式，否则返回 wire 格式
+    Negotiate,
+    // 返回 406 Not Acceptable
+    Reject,
+}
+
+impl HttpServerConfig {
+    // 获取 NSID 服务器标识，未配置（或无法获取主机名）时返回 None
+    pub fn nsid_identifier(&self) -> Option<&str> {
+        match self.nsid.as_str() {
+            "" => None,
+            NSID_HOSTNAME => LOCAL_HOSTNAME.as_deref(),
+            nsid => Some(nsid),
+        }
+    }
+}
+
+// 本机主机名，仅在首次使用时获取
+static LOCAL_HOSTNAME: Lazy<Option<String>> = Lazy::new(local_hostname);
+
+#[cfg(unix)]
+fn local_hostname() -> Option<String> {
+    nix::unistd::gethostname().ok()?.into_string().ok()
+}
+
+#[cfg(not(unix))]
+fn local_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+// 健康检查配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthConfig {
+    // 端到端合成探测配置
+    #[serde(default)]
+    pub probe: HealthProbeConfig,
+}
+
+// 端到端合成探测配置：周期性地经完整处理流程（路由、上游）解析探测域名，结果用于就绪检查
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthProbeConfig {
+    // 是否启用探测
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+    
+    // 探测查询名
+    #[serde(default = "default_health_probe_name")]
+    pub name: String,
+    
+    // 探测间隔（秒）
+    #[serde(default = "default_health_probe_interval_secs")]
+    pub interval_secs: u64,
+    
+    // 单次探测超时（秒）
+    #[serde(default = "default_health_probe_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+// 管理接口配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdminConfig {
+    // 是否启用管理接口
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+    
+    // 管理接口访问令牌（通过 Authorization: Bearer <token> 传递）
+    #[serde(default)]
+    pub token: String,
 }
 
 // DNS 解析器配置
@@ -85,6 +219,365 @@ pub struct DnsResolverConfig {
     // EDNS 客户端子网配置
     #[serde(default)]
     pub ecs_policy: EcsPolicyConfig,
+    
+    // 是否在响应中附加 EDNS 扩展错误（RFC 8914）
+    #[serde(default)]
+    pub emit_ede: bool,
+    
+    // 是否为被阻止的查询（规则阻止或 default_action 拒绝）附加 X-Owdns-Block-Reason 响应头
+    #[serde(default)]
+    pub block_reason_header: bool,
+
+    // 重复查询（客户端重传）检测配置
+    #[serde(default)]
+    pub duplicate_detection: DuplicateDetectionConfig,
+
+    // CNAME 展平配置
+    #[serde(default)]
+    pub cname_flatten: CnameFlattenConfig,
+
+    // 是否统一响应头标志：RA 始终置位，AA 除本地静态记录外始终清除（缓存命中、黑洞及上游应答一致）
+    #[serde(default = "default_enable")]
+    pub normalize_response_flags: bool,
+
+    // 客户端查询未携带 OPT 记录时是否移除应答中的 OPT 记录（RFC 6891：不向非 EDNS 客户端发送 EDNS）
+    #[serde(default = "default_disable")]
+    pub strip_opt_for_non_edns_clients: bool,
+
+    // 应答中地址记录的排序偏好（A 在前或 AAAA 在前）
+    #[serde(default)]
+    pub answer_sort: AnswerSort,
+
+    // 是否移除上游应答节中重复的记录（名称、类型、类别与数据均相同），保留首次出现的顺序
+    #[serde(default = "default_disable")]
+    pub dedup_answers: bool,
+
+    // 返回给客户端的应答记录数上限（不含 RRSIG），超出部分被截去；已签名的 RRset 不被拆分。0 表示不限制
+    #[serde(default)]
+    pub max_answer_records: usize,
+
+    // 应答记录 TTL 下限：TTL 低于下限的应答记录按策略改写或丢弃
+    #[serde(default)]
+    pub answer_ttl_floor: AnswerTtlFloorConfig,
+
+    // 应答填充（RFC 8467）：客户端请求填充时将线格式应答填充至块大小的整数倍
+    #[serde(default)]
+    pub response_padding: ResponsePaddingConfig,
+
+    // 查询日志配置（审计用，独立于运行日志）
+    #[serde(default)]
+    pub query_log: QueryLogConfig,
+
+    // dnstap 查询/应答日志配置
+    #[serde(default)]
+    pub dnstap: DnstapConfig,
+
+    // 应答修改插件配置
+    #[serde(default)]
+    pub plugins: PluginConfig,
+
+    // 调试级别记录详情日志的采样率（0.0 - 1.0），1.0 表示每个查询都记录
+    #[serde(default = "default_log_sample_rate")]
+    pub log_sample_rate: f64,
+    
+    // 是否记录被本服务修改（阻止、过滤、改写）的应答，修改次数始终计入指标
+    #[serde(default = "default_disable")]
+    pub log_modified_responses: bool,
+    
+    // 客户端查询设置了 TC 位时的处理策略
+    #[serde(default)]
+    pub truncated_query_policy: TruncatedQueryPolicy,
+    
+    // ANY 查询的处理策略（RFC 8482），避免被用于放大攻击
+    #[serde(default)]
+    pub any_query_policy: AnyQueryPolicy,
+    
+    // 无问题节但携带 OPT 记录的查询（EDNS 能力探测）的处理策略
+    #[serde(default)]
+    pub edns_probe_policy: EdnsProbePolicy,
+    
+    // 客户端查询大小与 EDNS OPT 选项限制
+    #[serde(default)]
+    pub query_limits: QueryLimitsConfig,
+    
+    // 是否支持多问题查询（非标准）：启用后各问题拆分为独立查询并发解析，应答合并为一个响应
+    #[serde(default = "default_disable")]
+    pub allow_multi_question: bool,
+    
+    // 并发子查询（多问题查询的各问题）部分失败时是否按部分结果应答：响应码取自成功的子查询而非 SERVFAIL
+    #[serde(default = "default_disable")]
+    pub partial_results: bool,
+    
+    // DDR（RFC 9462）指定解析器发现配置
+    #[serde(default)]
+    pub ddr: DdrConfig,
+    
+    // 特殊用途域名（RFC 6761）本地应答配置
+    #[serde(default)]
+    pub special_names: SpecialNamesConfig,
+    
+    // 转发环路防护配置
+    #[serde(default)]
+    pub loop_protection: LoopProtectionConfig,
+    
+    // 本地静态记录配置
+    #[serde(default)]
+    pub local_records: LocalRecordsConfig,
+    
+    // DNSSEC 相关配置
+    #[serde(default)]
+    pub dnssec: DnssecConfig,
+}
+
+// DNSSEC 相关配置
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DnssecConfig {
+    // 本地信任锚：由本服务直接应答信任锚文件中名称的 DS/DNSKEY 查询
+    #[serde(default)]
+    pub local_trust_anchor: LocalTrustAnchorConfig,
+}
+
+// 本地信任锚配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalTrustAnchorConfig {
+    // 是否启用本地信任锚
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+
+    // 信任锚文件路径（区文件格式的 DS/DNSKEY 记录，例如根区信任锚 root.key）
+    #[serde(default)]
+    pub file_path: String,
+
+    // 文件中记录未指定 TTL 时使用的 TTL（秒）
+    #[serde(default = "default_trust_anchor_ttl")]
+    pub ttl: u32,
+}
+
+// 本地静态记录配置：命中的查询由本服务直接应答，不经过缓存与上游
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalRecordsConfig {
+    // 静态记录列表
+    #[serde(default)]
+    pub records: Vec<LocalRecordEntry>,
+
+    // 本地应答的 TTL（秒）
+    #[serde(default = "default_local_records_ttl")]
+    pub ttl: u32,
+
+    // 记录仅定义 IPv4 地址时 AAAA 查询的处理策略
+    #[serde(default)]
+    pub ipv4_only_aaaa: Ipv4OnlyAaaaPolicy,
+
+    // DNS64 合成使用的 /96 前缀
+    #[serde(default = "default_dns64_prefix")]
+    pub dns64_prefix: Ipv6Addr,
+}
+
+// 本地静态记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalRecordEntry {
+    // 域名（精确匹配，不区分大小写）
+    pub name: String,
+
+    // 地址列表（IPv4 与 IPv6 可混合）
+    pub addresses: Vec<IpAddr>,
+}
+
+// 记录仅定义 IPv4 地址时 AAAA 查询的处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Ipv4OnlyAaaaPolicy {
+    // 返回 NODATA（NOERROR 且无应答记录）
+    #[default]
+    Nodata,
+    // 以 DNS64 前缀嵌入 IPv4 地址合成 AAAA 记录（RFC 6052）
+    Synthesize,
+}
+
+// 转发环路防护配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopProtectionConfig {
+    // 是否启用：发往 DoH 上游的请求携带本实例标识，收到携带本实例标识的请求时返回 508
+    #[serde(default = "default_enable")]
+    pub enabled: bool,
+
+    // 本服务自身的主机名（如 DoH 证书中的主机名），对其的查询由本服务直接应答，不转发至上游
+    #[serde(default)]
+    pub self_names: Vec<String>,
+
+    // 本服务自身的地址，作为 self_names 的 A/AAAA 应答；为空时对 self_names 的查询返回 REFUSED
+    #[serde(default)]
+    pub self_addresses: Vec<IpAddr>,
+}
+
+// 特殊用途域名（RFC 6761）本地应答配置，命中的查询不经过缓存与上游
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecialNamesConfig {
+    // localhost 及其子域解析为回环地址（A: 127.0.0.1，AAAA: ::1）
+    #[serde(default = "default_enable")]
+    pub localhost: bool,
+
+    // invalid 及其子域直接返回 NXDOMAIN
+    #[serde(default = "default_enable")]
+    pub invalid: bool,
+}
+
+// DDR 配置：为 _dns.resolver.arpa 的 SVCB 查询合成指向本服务 DoH 端点的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DdrConfig {
+    // 是否启用 DDR 应答
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+
+    // SVCB 目标名（本服务 TLS 证书中的主机名）
+    #[serde(default)]
+    pub target: String,
+
+    // DoH 服务端口
+    #[serde(default = "default_ddr_port")]
+    pub port: u16,
+
+    // 支持的 ALPN 协议
+    #[serde(default = "default_ddr_alpn")]
+    pub alpn: Vec<String>,
+
+    // DoH URI 模板路径，为空时使用 base_path + /dns-query{?dns}
+    #[serde(default)]
+    pub dohpath: String,
+
+    // SVCB 优先级
+    #[serde(default = "default_ddr_priority")]
+    pub priority: u16,
+
+    // 合成记录的 TTL（秒）
+    #[serde(default = "default_ddr_ttl")]
+    pub ttl: u32,
+}
+
+// 查询日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryLogConfig {
+    // 是否启用查询日志
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+
+    // 查询日志文件路径
+    #[serde(default = "default_query_log_path")]
+    pub path: String,
+
+    // 记录格式
+    #[serde(default)]
+    pub format: QueryLogFormat,
+
+    // 单个日志文件的最大大小（MB），超过后轮转，0 表示不轮转
+    #[serde(default = "default_query_log_max_size_mb")]
+    pub max_size_mb: u64,
+
+    // 保留的轮转文件数
+    #[serde(default = "default_query_log_max_files")]
+    pub max_files: u32,
+
+    // 记录队列容量，队列满（磁盘写入过慢）时丢弃新记录
+    #[serde(default = "default_query_log_buffer_size")]
+    pub buffer_size: usize,
+}
+
+// dnstap 查询/应答日志配置（Frame Streams 格式）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnstapConfig {
+    // 是否启用 dnstap 输出
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+
+    // 输出文件路径（单向模式），与 socket_path 二选一
+    #[serde(default)]
+    pub file_path: String,
+
+    // 输出 Unix 套接字路径（双向模式），与 file_path 二选一
+    #[serde(default)]
+    pub socket_path: String,
+
+    // 写入每帧的服务器标识，为空时不写入
+    #[serde(default)]
+    pub identity: String,
+
+    // 帧队列容量，队列满（输出过慢或断开）时丢弃新帧
+    #[serde(default = "default_dnstap_buffer_size")]
+    pub buffer_size: usize,
+}
+
+// 应答修改插件配置：返回客户端前调用 Rhai 脚本修改应答（需启用 plugins 编译特性）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginConfig {
+    // 是否启用插件
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+
+    // 脚本文件路径，脚本需定义 on_response(query, response) 函数
+    #[serde(default)]
+    pub script_path: String,
+
+    // 单次调用允许执行的最大操作数，超出时中止脚本并返回原应答
+    #[serde(default = "default_plugin_max_operations")]
+    pub max_operations: u64,
+}
+
+// 查询日志记录格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryLogFormat {
+    // 每行一个 JSON 对象
+    #[default]
+    Json,
+    // 以空格分隔的文本行
+    Text,
+}
+
+// CNAME 展平配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CnameFlattenConfig {
+    // 是否启用 CNAME 展平
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+
+    // 需要展平的区域，查询名为该区域或其子域时生效
+    #[serde(default)]
+    pub zones: Vec<String>,
+
+    // CNAME 链的最大追踪深度
+    #[serde(default = "default_cname_flatten_max_depth")]
+    pub max_depth: usize,
+}
+
+impl CnameFlattenConfig {
+    // 判断域名是否位于需要展平的区域内
+    pub fn matches(&self, domain: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        self.zones.iter().any(|zone| {
+            let zone = zone.trim_end_matches('.').to_ascii_lowercase();
+            domain == zone || domain.ends_with(&format!(".{}", zone))
+        })
+    }
+}
+
+// 重复查询检测配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateDetectionConfig {
+    // 是否启用重复查询检测
+    #[serde(default = "default_enable")]
+    pub enabled: bool,
+
+    // 检测时间窗口（秒），窗口内同一客户端 IP、查询名、类型和 ID 的查询视为重复
+    #[serde(default = "default_duplicate_query_window_secs")]
+    pub window_secs: u64,
+
+    // 最多跟踪的查询数，用于限制内存占用
+    #[serde(default = "default_duplicate_query_max_tracked")]
+    pub max_tracked: u64,
 }
 
 // 上游 DNS 服务器配置
@@ -100,6 +593,33 @@ pub struct UpstreamConfig {
     // 查询超时时间（秒）
     #[serde(default = "default_query_timeout")]
     pub query_timeout: u64,
+    
+    // 上游连接超时时间（秒），仅覆盖建立连接阶段（TCP/DoT 连接、DoH 连接），使不可达的解析器尽快失败
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    
+    // UDP 查询是否为每次查询随机选择源端口（关闭时由操作系统分配临时端口）
+    #[serde(default = "default_enable")]
+    pub udp_source_port_randomization: bool,
+    
+    // 上游应答的问题节缺失或与查询不一致时的处理策略
+    #[serde(default)]
+    pub question_mismatch_policy: QuestionMismatchPolicy,
+    
+    // 转发前是否将客户端查询精简为仅含问题节与自身 OPT 记录（保留 DO/CD 位与 ECS）的新查询
+    #[serde(default = "default_enable")]
+    pub minimize_query: bool,
+}
+
+// 上游应答问题节缺失或与查询不一致时的处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QuestionMismatchPolicy {
+    // 以原始查询的问题节替换应答的问题节
+    #[default]
+    Fixup,
+    // 返回 SERVFAIL
+    Servfail,
 }
 
 // DNS 解析器配置
@@ -111,10 +631,22 @@ pub struct ResolverConfig {
     // 解析器协议类型
     #[serde(default = "default_resolver_protocol")]
     pub protocol: ResolverProtocol,
+
+    // 协议回退链（按顺序尝试，设置后 address 为 IP 或 IP:端口，忽略 protocol）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocols: Option<Vec<ResolverProtocol>>,
+
+    // DoT 使用的 TLS 服务器名称（仅用于协议回退链，默认使用 IP）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_name: Option<String>,
+
+    // 启用 DNS 0x20 编码：随机化查询名大小写并拒绝大小写不一致的应答（仅用于 UDP 解析器）
+    #[serde(default)]
+    pub dns_0x20: bool,
 }
 
 // DNS 解析器协议类型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ResolverProtocol {
     // UDP 协议
@@ -138,6 +670,10 @@ pub struct CacheConfig {
     #[serde(default = "default_cache_size")]
     pub size: usize,
     
+    // 缓存分片数：按缓存键哈希分布到多个独立实例以降低锁竞争，容量在分片间平均分配，1 表示不分片
+    #[serde(default = "default_cache_shards")]
+    pub shards: usize,
+    
     // TTL 配置
     #[serde(default)]
     pub ttl: TtlConfig,
@@ -145,6 +681,63 @@ pub struct CacheConfig {
     // 持久化缓存配置
     #[serde(default)]
     pub persistence: PersistenceCacheConfig,
+
+    // 是否以压缩的线格式存储较大的缓存条目（以 CPU 换取内存）
+    #[serde(default = "default_disable")]
+    pub store_compressed: bool,
+
+    // 触发压缩存储的最小线格式大小（字节）
+    #[serde(default = "default_cache_compression_min_bytes")]
+    pub compression_min_bytes: usize,
+
+    // 上游失败（SERVFAIL 或超时）时是否返回已过期的缓存条目
+    #[serde(default = "default_disable")]
+    pub stale_on_error: bool,
+
+    // 返回过期条目时使用的 TTL（秒）
+    #[serde(default = "default_stale_on_error_ttl")]
+    pub stale_on_error_ttl: u32,
+
+    // 允许返回的过期条目的最大过期时长（秒）
+    #[serde(default = "default_stale_on_error_max_age")]
+    pub stale_on_error_max_age: u64,
+
+    // 刷新失败宽限期（秒）：过期条目刷新失败时，自首次失败起在此时长内继续作为缓存命中返回，0 表示禁用
+    #[serde(default)]
+    pub refresh_failure_grace_secs: u64,
+
+    // 上游应答 TTL 为 0（不缓存）时仍强制缓存的时长（秒），0 表示禁用；启用后不受 ttl.min 影响
+    #[serde(default)]
+    pub override_zero_ttl_secs: u32,
+    
+    // A 或 AAAA 查询由上游解析后，在后台解析同名的另一地址类型并写入缓存，降低客户端随后查询的延迟
+    #[serde(default = "default_disable")]
+    pub companion_prefetch: bool,
+
+    // 自适应 TTL 配置
+    #[serde(default)]
+    pub adaptive_ttl: AdaptiveTtlConfig,
+
+    // 优先新鲜应答的查询类型（如 DS、DNSKEY、CAA）：无论 TTL 长短，每次查询都向上游重新验证，
+    // 仅在上游失败时返回未过期的缓存条目
+    #[serde(default)]
+    pub revalidate_types: Vec<String>,
+
+    // 条目在多长时间内未被访问即被移除（秒），0 表示禁用，仅按容量淘汰
+    #[serde(default = "default_cache_time_to_idle_secs")]
+    pub time_to_idle_secs: u64,
+
+    // 缓存估算内存上限（字节），0 表示禁用，启用后取代按条目数的容量限制
+    #[serde(default)]
+    pub max_memory_bytes: u64,
+
+    // 是否按上游组隔离缓存：启用后查询先路由，再以上游组作为缓存命名空间，避免不同组的应答互相污染
+    #[serde(default = "default_disable")]
+    pub isolate_upstream_groups: bool,
+
+    // 是否为缓存条目额外缓存 JSON API 的序列化结果（以内存换取 CPU），重复的 JSON 命中无需重新转换
+    #[serde(default = "default_disable")]
+    pub json_answer_cache: bool,
 }
 
 // TTL 配置
@@ -161,6 +754,31 @@ pub struct TtlConfig {
     // 负缓存 TTL（秒）
     #[serde(default = "default_negative_ttl")]
     pub negative: u32,
+    
+    // 返回给客户端的记录 TTL 上限（秒），与缓存内部的最大 TTL 相互独立，0 表示不限制
+    #[serde(default = "default_response_max_ttl")]
+    pub response_max: u32,
+}
+
+// 自适应 TTL 配置：跟踪每个缓存键的应答在刷新间是否变化，
+// 未变化的应答逐级延长缓存 TTL，频繁变化的应答逐级缩短缓存 TTL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveTtlConfig {
+    // 是否启用自适应 TTL
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+
+    // 缩短后的 TTL 下限（秒）
+    #[serde(default = "default_adaptive_ttl_min")]
+    pub min: u32,
+
+    // 延长后的 TTL 上限（秒）
+    #[serde(default = "default_adaptive_ttl_max")]
+    pub max: u32,
+
+    // 最多跟踪应答变化历史的缓存键数
+    #[serde(default = "default_adaptive_ttl_max_tracked")]
+    pub max_tracked: u64,
 }
 
 // 速率限制配置
@@ -177,8 +795,24 @@ pub struct RateLimitConfig {
     // 单个 IP 的并发请求数限制
     #[serde(default = "default_per_ip_concurrent")]
     pub per_ip_concurrent: u32,
-}
-
+    
+    // 每个 IP 每日最大查询数，超出后返回 REFUSED，0 表示不限制
+    #[serde(default)]
+    pub per_ip_daily_quota: u64,
+    
+    // 每日配额的重置时刻（UTC 小时，0 - 23）
+    #[serde(default = "default_quota_reset_hour")]
+    pub quota_reset_hour: u8,
+    
+    // 每日配额最多跟踪的客户端 IP 数，超出时淘汰最少使用的 IP
+    #[serde(default = "default_quota_max_tracked_ips")]
+    pub quota_max_tracked_ips: u64,
+    
+    // 允许通过 X-Forwarded-For 等请求头传递客户端地址的反向代理网段，其他连接按源地址计算每日配额
+    #[serde(default)]
+    pub quota_trusted_proxies: Vec<String>,
+}
+
 // HTTP 客户端配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpClientConfig {
@@ -193,6 +827,77 @@ pub struct HttpClientConfig {
     // HTTP 请求相关配置
     #[serde(default)]
     pub request: RequestConfig,
+    
+    // TLS 策略配置
+    #[serde(default)]
+    pub tls: TlsPolicyConfig,
+}
+
+// TLS 策略配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsPolicyConfig {
+    // 允许的最低 TLS 版本："1.0"、"1.1"、"1.2" 或 "1.3"
+    #[serde(default = "default_http_client_tls_min_version")]
+    pub min_version: String,
+    
+    // 允许的加密套件（rustls 名称，如 "TLS13_AES_256_GCM_SHA384"），为空时使用默认套件
+    #[serde(default)]
+    pub ciphers: Vec<String>,
+}
+
+impl TlsPolicyConfig {
+    // 解析允许的 TLS 版本
+    // rustls 仅实现 TLS 1.2 与 1.3，因此 "1.0"、"1.1" 与 "1.2" 等效
+    fn protocol_versions(&self) -> Result<&'static [&'static rustls::SupportedProtocolVersion]> {
+        const TLS13_ONLY: &[&rustls::SupportedProtocolVersion] = &[&rustls::version::TLS13];
+        match self.min_version.trim() {
+            "1.0" | "1.1" | "1.2" => Ok(rustls::ALL_VERSIONS),
+            "1.3" => Ok(TLS13_ONLY),
+            other => Err(ServerError::Config(format!(
+                "Invalid TLS min_version: {} (expected \"1.0\", \"1.1\", \"1.2\" or \"1.3\")", other
+            ))),
+        }
+    }
+    
+    // 按名称（不区分大小写）选取加密套件，未配置时使用默认套件
+    fn cipher_suites(&self) -> Result<Vec<rustls::SupportedCipherSuite>> {
+        if self.ciphers.is_empty() {
+            return Ok(rustls::crypto::ring::DEFAULT_CIPHER_SUITES.to_vec());
+        }
+        
+        self.ciphers.iter()
+            .map(|name| {
+                rustls::crypto::ring::ALL_CIPHER_SUITES.iter()
+                    .find(|suite| format!("{:?}", suite.suite()).eq_ignore_ascii_case(name.trim()))
+                    .copied()
+                    .ok_or_else(|| ServerError::Config(format!("Unknown TLS cipher suite in ciphers: {}", name)))
+            })
+            .collect()
+    }
+    
+    // 构建上游 HTTPS 连接的 rustls 客户端配置（最低版本、加密套件与内置根证书）
+    pub fn client_config(&self) -> Result<rustls::ClientConfig> {
+        let provider = rustls::crypto::CryptoProvider {
+            cipher_suites: self.cipher_suites()?,
+            ..rustls::crypto::ring::default_provider()
+        };
+        let versions = self.protocol_versions()?;
+        
+        let mut roots = rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        
+        let mut config = rustls::ClientConfig::builder_with_provider(Arc::new(provider))
+            .with_protocol_versions(versions)
+            .map_err(|e| ServerError::Config(format!(
+                "TLS ciphers contain no cipher suite usable with min_version {}: {}", self.min_version, e
+            )))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        // 上游客户端仅使用 HTTP/1.1
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        
+        Ok(config)
+    }
 }
 
 // 连接池配置
@@ -221,7 +926,6 @@ pub struct RequestConfig {
 
 // 路由配置（DNS分流）
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[derive(Default)]
 pub struct RoutingConfig {
     // 是否启用DNS分流
     #[serde(default = "default_disable")]
@@ -238,6 +942,279 @@ pub struct RoutingConfig {
     // 默认上游组名称（如果未匹配任何规则）
     #[serde(default)]
     pub default_upstream_group: Option<String>,
+    
+    // 未匹配任何规则且没有默认上游组时的处理方式
+    #[serde(default)]
+    pub default_action: DefaultRouteAction,
+    
+    // 路由配置档案：按请求路径前缀选择不同的默认上游组
+    #[serde(default)]
+    pub profiles: Vec<RoutingProfile>,
+    
+    // 黑洞响应配置
+    #[serde(default)]
+    pub blackhole: BlackholeConfig,
+    
+    // 黑洞响应的最大 TTL（秒）：NXDOMAIN/NODATA 附加以此为 TTL 的 SOA 记录，sinkhole 应答 TTL 不超过此值
+    #[serde(default = "default_blackhole_ttl")]
+    pub blackhole_ttl: u32,
+    
+    // URL规则下载的最大字节数，超出时中止下载
+    #[serde(default = "default_max_rule_download_bytes")]
+    pub max_rule_download_bytes: u64,
+    
+    // 同时进行下载与编译的URL规则更新任务上限
+    #[serde(default = "default_max_concurrent_url_updates")]
+    pub max_concurrent_url_updates: usize,
+    
+    // 延迟编译正则规则（内联及文件规则）：启动时不编译，规则首次成为匹配候选时编译并缓存
+    #[serde(default = "default_disable")]
+    pub lazy_regex: bool,
+    
+    // 上游组未就绪（其URL规则尚未完成首次加载）时的处理方式
+    #[serde(default)]
+    pub not_ready_policy: GroupNotReadyPolicy,
+    
+    // wait 策略下等待上游组就绪的最长时间（毫秒），超时后退回全局上游
+    #[serde(default = "default_not_ready_wait_ms")]
+    pub not_ready_wait_ms: u64,
+    
+    // URL规则完成首次加载前按 fail_closed_action 应答所有查询，避免屏蔽列表未加载时查询泄露至上游
+    #[serde(default = "default_disable")]
+    pub fail_closed_until_loaded: bool,
+    
+    // fail_closed_until_loaded 启用时，规则加载完成前查询的应答方式
+    #[serde(default)]
+    pub fail_closed_action: FailClosedAction,
+    
+    // 按请求头指定上游组（用于测试分流配置）
+    #[serde(default)]
+    pub group_override: GroupOverrideConfig,
+}
+
+// 按请求头指定上游组配置：受信任客户端可通过 X-Owdns-Upstream-Group 请求头跳过路由规则，
+// 客户端需位于 trusted_cidrs 内（按连接源地址判断），或携带有效的管理接口令牌
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GroupOverrideConfig {
+    // 是否启用
+    #[serde(default = "default_disable")]
+    pub enabled: bool,
+    
+    // 允许指定上游组的客户端网段
+    #[serde(default)]
+    pub trusted_cidrs: Vec<String>,
+}
+
+// 未匹配任何规则且没有默认上游组时的处理方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultRouteAction {
+    // 使用全局上游解析
+    #[default]
+    Global,
+    // 返回 REFUSED，仅解析显式路由的域名
+    Refuse,
+    // 按黑洞配置阻止查询
+    Blackhole,
+}
+
+// 上游组未就绪时的处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupNotReadyPolicy {
+    // 命中未就绪上游组的查询改用全局上游
+    #[default]
+    Global,
+    // 短暂等待所有URL规则完成首次加载后再匹配，超时后退回全局上游
+    Wait,
+}
+
+// URL规则完成首次加载前查询的应答方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FailClosedAction {
+    // 返回 REFUSED
+    #[default]
+    Refuse,
+    // 按黑洞配置阻止查询
+    Blackhole,
+}
+
+// 路由配置档案
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingProfile {
+    // 档案名称
+    pub name: String,
+    
+    // 请求路径前缀，例如 "/internal" 对应 "/internal/dns-query" 和 "/internal/resolve"
+    pub path_prefix: String,
+    
+    // 未匹配任何规则时使用的上游组，替代 default_upstream_group
+    pub default_upstream_group: String,
+}
+
+// 黑洞响应配置
+// 优先级：按顺序匹配 client_responses 中第一个包含客户端 IP 的条目，
+// 未命中时使用 response，均未配置时返回 NXDOMAIN
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BlackholeConfig {
+    // 默认阻止响应
+    #[serde(default)]
+    pub response: BlackholeResponseConfig,
+    
+    // 按客户端网段覆盖的阻止响应
+    #[serde(default)]
+    pub client_responses: Vec<ClientBlackholeResponse>,
+}
+
+// 按客户端网段定制的阻止响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientBlackholeResponse {
+    // 客户端网段列表（CIDR，如 192.168.100.0/24）
+    pub client_cidrs: Vec<String>,
+    
+    // 命中网段时使用的阻止响应
+    pub response: BlackholeResponseConfig,
+}
+
+// 阻止响应内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlackholeResponseConfig {
+    // 响应类型
+    #[serde(rename = "type", default)]
+    pub type_: BlackholeResponseType,
+    
+    // sinkhole 类型下 A 查询返回的地址（默认 0.0.0.0）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv4: Option<Ipv4Addr>,
+    
+    // sinkhole 类型下 AAAA 查询返回的地址（默认 ::）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<Ipv6Addr>,
+    
+    // sinkhole 应答记录的 TTL（秒）
+    #[serde(default = "default_blackhole_sinkhole_ttl")]
+    pub ttl: u32,
+}
+
+// 阻止响应类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BlackholeResponseType {
+    // 返回 NXDOMAIN
+    #[default]
+    Nxdomain,
+    // 返回 REFUSED
+    Refused,
+    // 返回指定地址（如拦截页或强制门户）
+    Sinkhole,
+}
+
+// 应答地址记录的排序偏好
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerSort {
+    // 保持上游顺序
+    #[default]
+    None,
+    // A 记录排在 AAAA 记录之前
+    V4First,
+    // AAAA 记录排在 A 记录之前
+    V6First,
+}
+
+// 应答记录 TTL 下限配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnswerTtlFloorConfig {
+    // TTL 下限（秒），0 表示禁用
+    #[serde(default)]
+    pub min_ttl: u32,
+
+    // 低于下限的记录的处理方式
+    #[serde(default)]
+    pub action: TtlFloorAction,
+}
+
+// 应答填充配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponsePaddingConfig {
+    // 是否启用应答填充
+    #[serde(default)]
+    pub enabled: bool,
+
+    // 填充块大小（字节），应答线格式长度填充至其整数倍
+    #[serde(default = "default_response_padding_block_size")]
+    pub block_size: u16,
+}
+
+// TTL 低于下限的应答记录的处理方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TtlFloorAction {
+    // 将 TTL 提升至下限
+    #[default]
+    Rewrite,
+    // 丢弃该记录，保留其他记录；所有记录均低于下限时改为提升 TTL，避免应答被清空
+    Drop,
+}
+
+// 客户端查询设置了 TC 位时的处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TruncatedQueryPolicy {
+    // 清除 TC 位后继续处理
+    #[default]
+    Clear,
+    // 返回 FORMERR
+    Formerr,
+}
+
+// ANY 查询的处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnyQueryPolicy {
+    // 转发至上游
+    #[default]
+    Forward,
+    // 返回单条 HINFO 记录（RFC 8482）
+    Hinfo,
+    // 返回 REFUSED
+    Refuse,
+}
+
+// EDNS 能力探测的处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EdnsProbePolicy {
+    // 与无问题节的查询一样视为无效查询
+    #[default]
+    Reject,
+    // 以携带 OPT 记录的 NOERROR 应答（EDNS 版本不受支持时为 BADVERS）
+    Answer,
+}
+
+// 客户端查询限制配置，超出限制的查询返回 FORMERR
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryLimitsConfig {
+    // 是否启用查询限制
+    #[serde(default = "default_enable")]
+    pub enabled: bool,
+
+    // 线格式查询最大字节数
+    #[serde(default = "default_max_query_size")]
+    pub max_query_size: usize,
+
+    // EDNS OPT 记录最大选项数（按线格式计数，重复选项分别计入）
+    #[serde(default = "default_max_edns_options")]
+    pub max_edns_options: usize,
+
+    // 查询最大问题数（按报文头 QDCOUNT 计数）
+    #[serde(default = "default_max_questions")]
+    pub max_questions: usize,
+
+    // JSON API 请求（查询字符串）最大字节数
+    #[serde(default = "default_max_json_request_size")]
+    pub max_json_request_size: usize,
 }
 
 // 上游DNS服务器组
@@ -258,6 +1235,26 @@ pub struct UpstreamGroup {
     // 上游组级别的 ECS 策略配置（覆盖全局设置）
     #[serde(default)]
     pub ecs_policy: Option<EcsPolicyConfig>,
+    
+    // 是否缓存该组的应答，false 时该组的应答不写入缓存（未设置时沿用全局缓存设置）
+    #[serde(default)]
+    pub cache: Option<bool>,
+    
+    // 该组应答的缓存最小 TTL（秒，覆盖全局设置）
+    #[serde(default)]
+    pub cache_min_ttl: Option<u32>,
+    
+    // 该组应答的缓存最大 TTL（秒，覆盖全局设置）
+    #[serde(default)]
+    pub cache_max_ttl: Option<u32>,
+    
+    // 该组 DoH 上游连接池的空闲超时（秒，覆盖全局设置），设置连接池参数的组使用独立的 HTTP 客户端
+    #[serde(default)]
+    pub pool_idle_timeout: Option<u64>,
+    
+    // 该组 DoH 上游连接池每个主机的最大空闲连接数（覆盖全局设置）
+    #[serde(default)]
+    pub pool_max_idle_connections: Option<u32>,
 }
 
 // 分流规则
@@ -293,6 +1290,10 @@ pub struct MatchCondition {
     // 周期性更新配置（用于url类型）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub periodic: Option<PeriodicUpdateConfig>,
+    
+    // 查询类型过滤（如 A、AAAA、MX），为空时匹配所有类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_types: Option<Vec<String>>,
 }
 
 // 匹配类型
@@ -340,11 +1341,48 @@ pub struct PersistenceCacheConfig {
     #[serde(default = "default_cache_shutdown_save_timeout")]
     pub shutdown_save_timeout_secs: u64,
     
+    // 启动加载时并发插入的批次数
+    #[serde(default = "default_cache_load_concurrency")]
+    pub load_concurrency: usize,
+    
+    // 保存缓存时并发序列化条目的线程数
+    #[serde(default = "default_cache_save_concurrency")]
+    pub save_concurrency: usize,
+    
+    // 启动加载时每秒最多插入的条目数（0 表示不限制）
+    #[serde(default)]
+    pub load_rate: u64,
+    
+    // 缓存文件损坏（文件头无效或数据截断）时的处理策略
+    #[serde(default)]
+    pub on_corrupt: CorruptCachePolicy,
+    
+    // 持久化路径不可写时是否启动失败（否则记录错误并在本次运行中禁用持久化）
+    #[serde(default)]
+    pub fail_on_unwritable: bool,
+    
+    // 只读基线缓存文件路径（与缓存文件格式相同），主缓存未命中时查找，从不写入；为空表示禁用
+    #[serde(default)]
+    pub baseline_path: String,
+    
     // 周期性保存配置
     #[serde(default)]
     pub periodic: PeriodicSaveConfig,
 }
 
+// 缓存文件损坏时的处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CorruptCachePolicy {
+    // 记录警告并以空缓存继续
+    #[default]
+    Ignore,
+    // 启动失败
+    Fail,
+    // 将损坏文件重命名为 .corrupt 后以空缓存继续
+    Quarantine,
+}
+
 // 周期性保存配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeriodicSaveConfig {
@@ -371,6 +1409,10 @@ pub struct EcsPolicyConfig {
     // 匿名化配置
     #[serde(default)]
     pub anonymization: EcsAnonymizationConfig,
+    
+    // 是否在返回客户端前移除上游响应中的 ECS 选项（缓存仍按 ECS 作用域存储），避免泄露子网作用域
+    #[serde(default = "default_disable")]
+    pub strip_from_response: bool,
 }
 
 // EDNS 客户端子网匿名化配置
@@ -395,6 +1437,26 @@ pub struct PeriodicUpdateConfig {
     // 更新间隔（秒）
     #[serde(default = "default_url_rule_update_interval")]
     pub interval_secs: u64,
+    
+    // 获取失败后的重试配置
+    #[serde(default)]
+    pub retry: UrlRuleRetryConfig,
+}
+
+// URL规则获取失败重试配置（独立于周期性更新间隔）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlRuleRetryConfig {
+    // 最大重试次数（0 表示不重试，直接等待下一个周期）
+    #[serde(default = "default_url_rule_retry_max_attempts")]
+    pub max_attempts: u32,
+    
+    // 初始退避时间（秒），每次重试后翻倍
+    #[serde(default = "default_url_rule_retry_initial_backoff")]
+    pub initial_backoff_secs: u64,
+    
+    // 最大退避时间（秒）
+    #[serde(default = "default_url_rule_retry_max_backoff")]
+    pub max_backoff_secs: u64,
 }
 
 // 默认值函数 - 使用 consts 中定义的常量
@@ -402,18 +1464,126 @@ fn default_resolver_protocol() -> ResolverProtocol {
     ResolverProtocol::Udp
 }
 
+fn default_health_probe_name() -> String {
+    DEFAULT_HEALTH_PROBE_NAME.to_string()
+}
+
+fn default_health_probe_interval_secs() -> u64 {
+    DEFAULT_HEALTH_PROBE_INTERVAL_SECS
+}
+
+fn default_health_probe_timeout_secs() -> u64 {
+    DEFAULT_HEALTH_PROBE_TIMEOUT_SECS
+}
+
+fn default_connect_timeout() -> u64 {
+    DEFAULT_CONNECT_TIMEOUT
+}
+
 fn default_query_timeout() -> u64 {
     DEFAULT_QUERY_TIMEOUT
 }
 
+fn default_log_sample_rate() -> f64 {
+    DEFAULT_LOG_SAMPLE_RATE
+}
+
+fn default_max_query_size() -> usize {
+    DEFAULT_MAX_QUERY_SIZE
+}
+
+fn default_max_edns_options() -> usize {
+    DEFAULT_MAX_EDNS_OPTIONS
+}
+
+fn default_max_questions() -> usize {
+    DEFAULT_MAX_QUESTIONS
+}
+
+fn default_max_json_request_size() -> usize {
+    DEFAULT_MAX_JSON_REQUEST_SIZE
+}
+
+fn default_query_log_path() -> String {
+    DEFAULT_QUERY_LOG_PATH.to_string()
+}
+
+fn default_query_log_max_size_mb() -> u64 {
+    DEFAULT_QUERY_LOG_MAX_SIZE_MB
+}
+
+fn default_query_log_max_files() -> u32 {
+    DEFAULT_QUERY_LOG_MAX_FILES
+}
+
+fn default_query_log_buffer_size() -> usize {
+    DEFAULT_QUERY_LOG_BUFFER_SIZE
+}
+
+fn default_dnstap_buffer_size() -> usize {
+    DEFAULT_DNSTAP_BUFFER_SIZE
+}
+
+fn default_plugin_max_operations() -> u64 {
+    DEFAULT_PLUGIN_MAX_OPERATIONS
+}
+
 fn default_disable() -> bool {
     false
 }
 
+fn default_enable() -> bool {
+    true
+}
+
 fn default_cache_size() -> usize {
     DEFAULT_CACHE_SIZE
 }
 
+fn default_cache_shards() -> usize {
+    DEFAULT_CACHE_SHARDS
+}
+
+fn default_duplicate_query_window_secs() -> u64 {
+    DEFAULT_DUPLICATE_QUERY_WINDOW_SECS
+}
+
+fn default_duplicate_query_max_tracked() -> u64 {
+    DEFAULT_DUPLICATE_QUERY_MAX_TRACKED
+}
+
+fn default_cname_flatten_max_depth() -> usize {
+    DEFAULT_CNAME_FLATTEN_MAX_DEPTH
+}
+
+fn default_cache_time_to_idle_secs() -> u64 {
+    DEFAULT_CACHE_TIME_TO_IDLE_SECS
+}
+
+fn default_stale_on_error_ttl() -> u32 {
+    DEFAULT_STALE_ON_ERROR_TTL
+}
+
+fn default_stale_on_error_max_age() -> u64 {
+    DEFAULT_STALE_ON_ERROR_MAX_AGE
+}
+
+fn default_adaptive_ttl_min() -> u32 {
+    DEFAULT_ADAPTIVE_TTL_MIN
+}
+
+fn default_adaptive_ttl_max() -> u32 {
+    DEFAULT_ADAPTIVE_TTL_MAX
+}
+
+fn default_adaptive_ttl_max_tracked() -> u64 {
+    DEFAULT_ADAPTIVE_TTL_MAX_TRACKED
+}
+
+fn default_cache_compression_min_bytes() -> usize {
+    DEFAULT_CACHE_COMPRESSION_MIN_BYTES
+}
+
 fn default_min_ttl() -> u32 {
     DEFAULT_MIN_TTL
 }
@@ -426,6 +1596,10 @@ fn default_negative_ttl() -> u32 {
     DEFAULT_NEGATIVE_TTL
 }
 
+fn default_response_max_ttl() -> u32 {
+    DEFAULT_RESPONSE_MAX_TTL
+}
+
 fn default_per_ip_rate() -> u32 {
     DEFAULT_PER_IP_RATE
 }
@@ -434,6 +1608,14 @@ fn default_per_ip_concurrent() -> u32 {
     DEFAULT_PER_IP_CONCURRENT
 }
 
+fn default_quota_reset_hour() -> u8 {
+    DEFAULT_QUOTA_RESET_HOUR
+}
+
+fn default_quota_max_tracked_ips() -> u64 {
+    DEFAULT_QUOTA_MAX_TRACKED_IPS
+}
+
 fn default_listen_timeout() -> u64 {
     DEFAULT_LISTEN_TIMEOUT
 }
@@ -450,6 +1632,10 @@ fn default_http_client_pool_max_idle_connections() -> u32 {
     DEFAULT_HTTP_CLIENT_POOL_MAX_IDLE_CONNECTIONS
 }
 
+fn default_http_client_tls_min_version() -> String {
+    DEFAULT_HTTP_CLIENT_TLS_MIN_VERSION.to_string()
+}
+
 fn default_http_client_agent() -> String {
     DEFAULT_HTTP_CLIENT_AGENT.to_string()
 }
@@ -463,6 +1649,41 @@ fn default_cache_persistence_path() -> String {
     "./cache.dat".to_string()
 }
 
+// 默认本地静态记录 TTL
+fn default_local_records_ttl() -> u32 {
+    DEFAULT_LOCAL_RECORDS_TTL
+}
+
+// 默认信任锚记录 TTL
+fn default_trust_anchor_ttl() -> u32 {
+    DEFAULT_TRUST_ANCHOR_TTL
+}
+
+// 默认 DNS64 前缀
+fn default_dns64_prefix() -> Ipv6Addr {
+    DEFAULT_DNS64_PREFIX
+}
+
+// 默认 DDR DoH 端口
+fn default_ddr_port() -> u16 {
+    DEFAULT_DDR_PORT
+}
+
+// 默认 DDR ALPN 协议
+fn default_ddr_alpn() -> Vec<String> {
+    vec![DEFAULT_DDR_ALPN.to_string()]
+}
+
+// 默认 DDR SVCB 优先级
+fn default_ddr_priority() -> u16 {
+    DEFAULT_DDR_PRIORITY
+}
+
+// 默认 DDR 记录 TTL
+fn default_ddr_ttl() -> u32 {
+    DEFAULT_DDR_TTL
+}
+
 // 默认启动时加载缓存
 fn default_cache_load_on_startup() -> bool {
     true
@@ -483,6 +1704,16 @@ fn default_cache_shutdown_save_timeout() -> u64 {
     30  // 30秒
 }
 
+// 默认启动加载缓存的并发批次数
+fn default_cache_load_concurrency() -> usize {
+    DEFAULT_CACHE_LOAD_CONCURRENCY
+}
+
+// 默认保存缓存时的并发序列化线程数
+fn default_cache_save_concurrency() -> usize {
+    DEFAULT_CACHE_SAVE_CONCURRENCY
+}
+
 // 默认 ECS 策略为剥离
 fn default_ecs_strategy() -> String {
     ECS_POLICY_STRIP.to_string()
@@ -503,15 +1734,57 @@ fn default_url_rule_update_interval() -> u64 {
     DEFAULT_URL_RULE_UPDATE_INTERVAL_SECS
 }
 
-impl ServerConfig {
-    // 从配置文件加载配置
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let config_str = fs::read_to_string(path)
-            .map_err(|e| ServerError::Config(format!("Failed to read config file: {}", e)))?;
-            
-        let config: ServerConfig = serde_yaml::from_str(&config_str)
-            .map_err(|e| ServerError::Config(format!("Failed to parse config: {}", e)))?;
-            
+// 默认URL规则最大下载字节数
+fn default_max_rule_download_bytes() -> u64 {
+    DEFAULT_MAX_RULE_DOWNLOAD_BYTES
+}
+
+// 默认URL规则并发更新上限
+fn default_max_concurrent_url_updates() -> usize {
+    DEFAULT_MAX_CONCURRENT_URL_UPDATES
+}
+
+fn default_not_ready_wait_ms() -> u64 {
+    DEFAULT_NOT_READY_WAIT_MS
+}
+
+// 默认URL规则最大重试次数
+fn default_url_rule_retry_max_attempts() -> u32 {
+    DEFAULT_URL_RULE_RETRY_MAX_ATTEMPTS
+}
+
+// 默认URL规则重试初始退避时间
+fn default_url_rule_retry_initial_backoff() -> u64 {
+    DEFAULT_URL_RULE_RETRY_INITIAL_BACKOFF_SECS
+}
+
+// 默认URL规则重试最大退避时间
+fn default_url_rule_retry_max_backoff() -> u64 {
+    DEFAULT_URL_RULE_RETRY_MAX_BACKOFF_SECS
+}
+
+// 默认黑洞 sinkhole TTL
+fn default_blackhole_sinkhole_ttl() -> u32 {
+    DEFAULT_BLACKHOLE_SINKHOLE_TTL
+}
+
+fn default_blackhole_ttl() -> u32 {
+    DEFAULT_BLACKHOLE_TTL
+}
+
+fn default_response_padding_block_size() -> u16 {
+    DEFAULT_RESPONSE_PADDING_BLOCK_SIZE
+}
+
+impl ServerConfig {
+    // 从配置文件加载配置
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config_str = fs::read_to_string(path)
+            .map_err(|e| ServerError::Config(format!("Failed to read config file: {}", e)))?;
+            
+        let config: ServerConfig = serde_yaml::from_str(&config_str)
+            .map_err(|e| ServerError::Config(format!("Failed to parse config: {}", e)))?;
+            
         // 验证配置
         config.test()?;
         
@@ -528,6 +1801,11 @@ impl ServerConfig {
         Duration::from_secs(self.dns.upstream.query_timeout)
     }
     
+    // 获取上游连接超时时间
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.dns.upstream.connect_timeout)
+    }
+    
     // 获取 HTTP 客户端超时时间
     pub fn http_client_timeout(&self) -> Duration {
         Duration::from_secs(self.dns.http_client.timeout)
@@ -592,17 +1870,78 @@ impl ServerConfig {
         Ok(self.dns.ecs_policy.clone())
     }
     
+    // 上游组的应答是否写入缓存，未知组沿用全局缓存设置
+    pub fn group_cache_enabled(&self, group_name: &str) -> bool {
+        self.dns.routing.upstream_groups
+            .iter()
+            .find(|g| g.name == group_name)
+            .and_then(|g| g.cache)
+            .unwrap_or(true)
+    }
+    
+    // 该查询类型是否需要每次向上游重新验证（不直接使用缓存应答）
+    pub fn cache_revalidates(&self, record_type: RecordType) -> bool {
+        let record_type = record_type.to_string();
+        self.dns.cache.revalidate_types.iter().any(|t| t.eq_ignore_ascii_case(&record_type))
+    }
+    
+    // 获取上游组应答的有效缓存 TTL 范围（最小值，最大值），未覆盖时使用全局设置
+    pub fn get_effective_cache_ttl(&self, group_name: &str) -> (u32, u32) {
+        let ttl = &self.dns.cache.ttl;
+        match self.dns.routing.upstream_groups.iter().find(|g| g.name == group_name) {
+            Some(group) => (group.cache_min_ttl.unwrap_or(ttl.min), group.cache_max_ttl.unwrap_or(ttl.max)),
+            None => (ttl.min, ttl.max),
+        }
+    }
+    
+    // 获取上游组的有效连接池配置，组未覆盖连接池参数时返回 None（使用共享的 HTTP 客户端）
+    pub fn get_group_pool_config(&self, group_name: &str) -> Option<PoolConfig> {
+        let group = self.dns.routing.upstream_groups.iter().find(|g| g.name == group_name)?;
+        if group.pool_idle_timeout.is_none() && group.pool_max_idle_connections.is_none() {
+            return None;
+        }
+        
+        let pool = &self.dns.http_client.pool;
+        Some(PoolConfig {
+            idle_timeout: group.pool_idle_timeout.unwrap_or(pool.idle_timeout),
+            max_idle_connections: group.pool_max_idle_connections.unwrap_or(pool.max_idle_connections),
+        })
+    }
+    
     // 验证配置有效性
     pub fn test(&self) -> Result<()> {
         // 验证速率限制配置
         self.validate_rate_limit()?;
         
+        // 验证管理接口配置
+        self.validate_admin()?;
+        
+        // 验证公共路径前缀
+        self.validate_base_path()?;
+        
+        // 验证 Alt-Svc 通告值
+        if HeaderValue::from_str(&self.http.advertise_alt_svc).is_err() {
+            return Err(ServerError::Config(format!(
+                "http_server.advertise_alt_svc is not a valid header value: {}", self.http.advertise_alt_svc
+            )));
+        }
+        
+        // 验证健康探测配置
+        self.validate_health_probe()?;
+        
         // 验证缓存持久化依赖链
         self.validate_cache_dependencies()?;
         
         // 验证全局解析器地址
         self.validate_resolvers(&self.dns.upstream.resolvers)?;
         
+        // 验证上游连接超时
+        if self.dns.upstream.connect_timeout == 0 {
+            return Err(ServerError::Config(
+                "upstream.connect_timeout must be greater than 0".to_string()
+            ));
+        }
+        
         // 验证上游组 ECS 策略与路由功能的依赖关系
         self.validate_routing_ecs_dependencies()?;
         
@@ -612,44 +1951,529 @@ impl ServerConfig {
         // 验证 ECS 策略配置
         self.validate_ecs_policy()?;
         
-        Ok(())
-    }
-    
-    // 验证速率限制配置
-    fn validate_rate_limit(&self) -> Result<()> {
-        if self.http.rate_limit.enabled {
-            // 验证每个 IP 每秒最大请求数
-            if self.http.rate_limit.per_ip_rate < MIN_PER_IP_RATE || self.http.rate_limit.per_ip_rate > MAX_PER_IP_RATE {
-                return Err(ServerError::Config(format!(
-                    "Invalid per_ip_rate: {} (must be between {} and {})",
-                    self.http.rate_limit.per_ip_rate, MIN_PER_IP_RATE, MAX_PER_IP_RATE
-                )));
+        // 验证重复查询检测配置
+        self.validate_duplicate_detection()?;
+        
+        // 验证 CNAME 展平配置
+        self.validate_cname_flatten()?;
+        
+        // 验证 DDR 配置
+        self.validate_ddr()?;
+        
+        // 验证转发环路防护配置
+        self.validate_loop_protection()?;
+        
+        // 验证本地静态记录配置
+        self.validate_local_records()?;
+        
+        // 验证本地信任锚配置
+        self.validate_trust_anchor()?;
+        
+        // 验证 TLS 策略配置
+        self.dns.http_client.tls.client_config()?;
+        
+        // 验证查询日志配置
+        self.validate_query_log()?;
+        
+        // 验证 dnstap 配置
+        self.validate_dnstap()?;
+        
+        // 验证插件配置
+        self.validate_plugins()?;
+        
+        // 验证查询限制配置
+        self.validate_query_limits()?;
+        
+        // 验证应答填充块大小
+        if self.dns.response_padding.enabled && self.dns.response_padding.block_size == 0 {
+            return Err(ServerError::Config(
+                "response_padding.block_size must be greater than 0".to_string()
+            ));
+        }
+        
+        // 验证详情日志采样率
+        if !(0.0..=1.0).contains(&self.dns.log_sample_rate) {
+            return Err(ServerError::Config(format!(
+                "log_sample_rate must be between 0.0 and 1.0, got {}", self.dns.log_sample_rate
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证健康探测配置
+    fn validate_health_probe(&self) -> Result<()> {
+        let config = &self.http.health.probe;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if Name::from_utf8(&config.name).is_err() || config.name.trim().is_empty() {
+            return Err(ServerError::Config(format!(
+                "Invalid health probe name: '{}'", config.name
+            )));
+        }
+        
+        if config.interval_secs == 0 || config.timeout_secs == 0 {
+            return Err(ServerError::Config(
+                "Health probe interval_secs and timeout_secs must be greater than 0".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证查询限制配置
+    fn validate_query_limits(&self) -> Result<()> {
+        let config = &self.dns.query_limits;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if !(DNS_HEADER_SIZE..=MAX_REQUEST_SIZE).contains(&config.max_query_size) {
+            return Err(ServerError::Config(format!(
+                "query_limits.max_query_size must be between {} and {}, got {}",
+                DNS_HEADER_SIZE, MAX_REQUEST_SIZE, config.max_query_size
+            )));
+        }
+        
+        if config.max_edns_options == 0 {
+            return Err(ServerError::Config(
+                "query_limits.max_edns_options must be greater than 0".to_string()
+            ));
+        }
+        
+        if config.max_questions == 0 {
+            return Err(ServerError::Config(
+                "query_limits.max_questions must be greater than 0".to_string()
+            ));
+        }
+        
+        if !(1..=MAX_REQUEST_SIZE).contains(&config.max_json_request_size) {
+            return Err(ServerError::Config(format!(
+                "query_limits.max_json_request_size must be between 1 and {}, got {}",
+                MAX_REQUEST_SIZE, config.max_json_request_size
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证查询日志配置
+    fn validate_query_log(&self) -> Result<()> {
+        let config = &self.dns.query_log;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if config.path.trim().is_empty() {
+            return Err(ServerError::Config(
+                "Query log is enabled but no path is configured".to_string()
+            ));
+        }
+        
+        if config.max_size_mb > 0 && config.max_files == 0 {
+            return Err(ServerError::Config(
+                "Query log rotation requires max_files greater than 0".to_string()
+            ));
+        }
+        
+        if config.buffer_size == 0 {
+            return Err(ServerError::Config(
+                "Query log buffer_size must be greater than 0".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证 dnstap 配置
+    fn validate_dnstap(&self) -> Result<()> {
+        let config = &self.dns.dnstap;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if config.file_path.trim().is_empty() == config.socket_path.trim().is_empty() {
+            return Err(ServerError::Config(
+                "dnstap is enabled but exactly one of file_path or socket_path must be configured".to_string()
+            ));
+        }
+        
+        if cfg!(not(unix)) && !config.socket_path.trim().is_empty() {
+            return Err(ServerError::Config(
+                "dnstap socket_path is only supported on Unix platforms".to_string()
+            ));
+        }
+        
+        if config.buffer_size == 0 {
+            return Err(ServerError::Config(
+                "dnstap buffer_size must be greater than 0".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证插件配置
+    fn validate_plugins(&self) -> Result<()> {
+        let config = &self.dns.plugins;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if cfg!(not(feature = "plugins")) {
+            return Err(ServerError::Config(
+                "plugins are enabled but this build does not include the 'plugins' feature".to_string()
+            ));
+        }
+        
+        if config.script_path.trim().is_empty() {
+            return Err(ServerError::Config(
+                "plugins are enabled but script_path is not configured".to_string()
+            ));
+        }
+        
+        if !Path::new(&config.script_path).is_file() {
+            return Err(ServerError::Config(format!(
+                "Plugin script not found: {}", config.script_path
+            )));
+        }
+        
+        if config.max_operations == 0 {
+            return Err(ServerError::Config(
+                "plugins max_operations must be greater than 0".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证本地静态记录配置
+    fn validate_local_records(&self) -> Result<()> {
+        let config = &self.dns.local_records;
+        for entry in &config.records {
+            if entry.name.trim().is_empty() || Name::from_ascii(&entry.name).is_err() {
+                return Err(ServerError::Config(format!(
+                    "Invalid local record name: '{}'", entry.name
+                )));
+            }
+            
+            if entry.addresses.is_empty() {
+                return Err(ServerError::Config(format!(
+                    "Local record '{}' must define at least one address", entry.name
+                )));
+            }
+        }
+        
+        // 仅支持 /96 前缀，低 32 位用于嵌入 IPv4 地址
+        if config.dns64_prefix.octets()[12..].iter().any(|&octet| octet != 0) {
+            return Err(ServerError::Config(format!(
+                "local_records dns64_prefix must be a /96 prefix with the low 32 bits zero: {}", config.dns64_prefix
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证本地信任锚配置（文件内容在启动时解析）
+    fn validate_trust_anchor(&self) -> Result<()> {
+        let config = &self.dns.dnssec.local_trust_anchor;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if config.file_path.trim().is_empty() {
+            return Err(ServerError::Config(
+                "local_trust_anchor is enabled but file_path is not configured".to_string()
+            ));
+        }
+        
+        if !Path::new(&config.file_path).is_file() {
+            return Err(ServerError::Config(format!(
+                "Trust anchor file not found: {}", config.file_path
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证转发环路防护配置
+    fn validate_loop_protection(&self) -> Result<()> {
+        for name in &self.dns.loop_protection.self_names {
+            if name.trim().is_empty() || Name::from_ascii(name).is_err() {
+                return Err(ServerError::Config(format!(
+                    "Invalid loop_protection self name: '{}'", name
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // 验证 CNAME 展平配置
+    fn validate_cname_flatten(&self) -> Result<()> {
+        let config = &self.dns.cname_flatten;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if config.zones.is_empty() {
+            return Err(ServerError::Config(
+                "CNAME flattening is enabled but no zones are configured".to_string()
+            ));
+        }
+        
+        if config.max_depth == 0 {
+            return Err(ServerError::Config(
+                "CNAME flattening max_depth must be greater than 0".to_string()
+            ));
+        }
+        
+        for zone in &config.zones {
+            if Name::from_ascii(zone).is_err() {
+                return Err(ServerError::Config(format!(
+                    "Invalid CNAME flattening zone: {}", zone
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // 验证 DDR 配置
+    fn validate_ddr(&self) -> Result<()> {
+        let config = &self.dns.ddr;
+        if !config.enabled {
+            return Ok(());
+        }
+        
+        if config.target.trim().is_empty() || Name::from_ascii(&config.target).is_err() {
+            return Err(ServerError::Config(format!(
+                "DDR is enabled but target is not a valid host name: '{}'", config.target
+            )));
+        }
+        
+        if config.alpn.is_empty() || config.alpn.iter().any(|alpn| alpn.is_empty() || alpn.len() > 255) {
+            return Err(ServerError::Config(
+                "DDR alpn must contain at least one protocol of 1-255 bytes".to_string()
+            ));
+        }
+        
+        let dohpath = self.ddr_dohpath();
+        if !dohpath.starts_with('/') || !dohpath.contains(DDR_DOHPATH_VARIABLE) {
+            return Err(ServerError::Config(format!(
+                "DDR dohpath must start with '/' and contain '{}': {}", DDR_DOHPATH_VARIABLE, dohpath
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    // 获取 DDR 公布的 dohpath，未配置时由 base_path 与标准 DoH 路径推导
+    pub fn ddr_dohpath(&self) -> String {
+        if self.dns.ddr.dohpath.is_empty() {
+            format!("{}{}{}", self.http.base_path, DOH_STANDARD_PATH, DDR_DOHPATH_VARIABLE)
+        } else {
+            self.dns.ddr.dohpath.clone()
+        }
+    }
+    
+    // 验证重复查询检测配置
+    fn validate_duplicate_detection(&self) -> Result<()> {
+        let config = &self.dns.duplicate_detection;
+        if config.enabled && (config.window_secs == 0 || config.max_tracked == 0) {
+            return Err(ServerError::Config(
+                "Duplicate query detection requires window_secs and max_tracked greater than 0".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证管理接口配置
+    fn validate_admin(&self) -> Result<()> {
+        // 启用管理接口时必须配置访问令牌
+        if self.http.admin.enabled && self.http.admin.token.trim().is_empty() {
+            return Err(ServerError::Config(
+                "Admin API is enabled but no token is configured".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证公共路径前缀：必须以 '/' 开头、不以 '/' 结尾，且仅包含合法的路径字符
+    fn validate_base_path(&self) -> Result<()> {
+        let base_path = self.http.base_path.as_str();
+        if base_path.is_empty() {
+            return Ok(());
+        }
+        
+        let valid_chars = base_path
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.' | '~'));
+        if !base_path.starts_with('/') || base_path.len() < 2 || base_path.ends_with('/')
+            || base_path.contains("//") || !valid_chars
+        {
+            return Err(ServerError::Config(format!(
+                "http_server.base_path is invalid: {}", base_path
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证速率限制配置
+    fn validate_rate_limit(&self) -> Result<()> {
+        if self.http.rate_limit.enabled {
+            // 验证每个 IP 每秒最大请求数
+            if self.http.rate_limit.per_ip_rate < MIN_PER_IP_RATE || self.http.rate_limit.per_ip_rate > MAX_PER_IP_RATE {
+                return Err(ServerError::Config(format!(
+                    "Invalid per_ip_rate: {} (must be between {} and {})",
+                    self.http.rate_limit.per_ip_rate, MIN_PER_IP_RATE, MAX_PER_IP_RATE
+                )));
+            }
+            
+            // 验证单个 IP 的并发请求数限制
+            if self.http.rate_limit.per_ip_concurrent < MIN_PER_IP_CONCURRENT || self.http.rate_limit.per_ip_concurrent > MAX_PER_IP_CONCURRENT {
+                return Err(ServerError::Config(format!(
+                    "Invalid per_ip_concurrent: {} (must be between {} and {})",
+                    self.http.rate_limit.per_ip_concurrent, MIN_PER_IP_CONCURRENT, MAX_PER_IP_CONCURRENT
+                )));
+            }
+            
+            // 验证每日配额设置
+            if self.http.rate_limit.quota_reset_hour > MAX_QUOTA_RESET_HOUR {
+                return Err(ServerError::Config(format!(
+                    "Invalid quota_reset_hour: {} (must be between 0 and {})",
+                    self.http.rate_limit.quota_reset_hour, MAX_QUOTA_RESET_HOUR
+                )));
+            }
+            if self.http.rate_limit.per_ip_daily_quota > 0 && self.http.rate_limit.quota_max_tracked_ips == 0 {
+                return Err(ServerError::Config(
+                    "quota_max_tracked_ips must be greater than 0 when per_ip_daily_quota is set".to_string()
+                ));
+            }
+            for cidr in &self.http.rate_limit.quota_trusted_proxies {
+                IpCidr::from_str(cidr).map_err(|e| ServerError::Config(format!(
+                    "http_server.rate_limit.quota_trusted_proxies: {}", e
+                )))?;
+            }
+        }
+        Ok(())
+    }
+    
+    // 验证缓存持久化依赖链
+    fn validate_cache_dependencies(&self) -> Result<()> {
+        // 验证缓存分片数
+        if self.dns.cache.shards == 0 || self.dns.cache.shards > MAX_CACHE_SHARDS {
+            return Err(ServerError::Config(format!(
+                "Cache shards must be between 1 and {}", MAX_CACHE_SHARDS
+            )));
+        }
+        
+        // 验证持久化缓存依赖于缓存本身
+        if self.dns.cache.persistence.enabled && !self.dns.cache.enabled {
+            return Err(ServerError::Config(
+                "Cache persistence is enabled but cache itself is disabled. Enable cache first.".to_string()
+            ));
+        }
+        
+        // 验证周期性保存依赖于持久化缓存
+        if !self.dns.cache.persistence.baseline_path.is_empty() && !self.dns.cache.enabled {
+            return Err(ServerError::Config(
+                "Baseline cache file is configured but cache itself is disabled. Enable cache first.".to_string()
+            ));
+        }
+        
+        if self.dns.cache.persistence.periodic.enabled && !self.dns.cache.persistence.enabled {
+            return Err(ServerError::Config(
+                "Periodic cache persistence is enabled but persistence itself is disabled. Enable persistence first.".to_string()
+            ));
+        }
+        
+        // 验证过期应答依赖于缓存本身
+        if self.dns.cache.stale_on_error && !self.dns.cache.enabled {
+            return Err(ServerError::Config(
+                "Cache stale_on_error is enabled but cache itself is disabled. Enable cache first.".to_string()
+            ));
+        }
+        
+        // 验证 TTL=0 强制缓存依赖于缓存本身，且时长有上限
+        if self.dns.cache.override_zero_ttl_secs > 0 && !self.dns.cache.enabled {
+            return Err(ServerError::Config(
+                "Cache override_zero_ttl_secs is set but cache itself is disabled. Enable cache first.".to_string()
+            ));
+        }
+        
+        if self.dns.cache.override_zero_ttl_secs > MAX_OVERRIDE_ZERO_TTL_SECS {
+            return Err(ServerError::Config(format!(
+                "Cache override_zero_ttl_secs must not exceed {} seconds", MAX_OVERRIDE_ZERO_TTL_SECS
+            )));
+        }
+        
+        // 验证伴随预取依赖于缓存本身
+        if self.dns.cache.companion_prefetch && !self.dns.cache.enabled {
+            return Err(ServerError::Config(
+                "Cache companion_prefetch is enabled but cache itself is disabled. Enable cache first.".to_string()
+            ));
+        }
+        
+        // 验证自适应 TTL 依赖于缓存本身，且上下限有效
+        let adaptive_ttl = &self.dns.cache.adaptive_ttl;
+        if adaptive_ttl.enabled {
+            if !self.dns.cache.enabled {
+                return Err(ServerError::Config(
+                    "Cache adaptive_ttl is enabled but cache itself is disabled. Enable cache first.".to_string()
+                ));
             }
-            
-            // 验证单个 IP 的并发请求数限制
-            if self.http.rate_limit.per_ip_concurrent < MIN_PER_IP_CONCURRENT || self.http.rate_limit.per_ip_concurrent > MAX_PER_IP_CONCURRENT {
+            if adaptive_ttl.min > adaptive_ttl.max {
                 return Err(ServerError::Config(format!(
-                    "Invalid per_ip_concurrent: {} (must be between {} and {})",
-                    self.http.rate_limit.per_ip_concurrent, MIN_PER_IP_CONCURRENT, MAX_PER_IP_CONCURRENT
+                    "Cache adaptive_ttl min ({}) must not exceed max ({})", adaptive_ttl.min, adaptive_ttl.max
                 )));
             }
+            if adaptive_ttl.max_tracked == 0 {
+                return Err(ServerError::Config(
+                    "Cache adaptive_ttl max_tracked must be at least 1".to_string()
+                ));
+            }
         }
-        Ok(())
-    }
-    
-    // 验证缓存持久化依赖链
-    fn validate_cache_dependencies(&self) -> Result<()> {
-        // 验证持久化缓存依赖于缓存本身
-        if self.dns.cache.persistence.enabled && !self.dns.cache.enabled {
+        
+        // 验证重新验证的查询类型依赖于缓存本身，且类型名称有效
+        if !self.dns.cache.revalidate_types.is_empty() {
+            if !self.dns.cache.enabled {
+                return Err(ServerError::Config(
+                    "Cache revalidate_types is set but cache itself is disabled. Enable cache first.".to_string()
+                ));
+            }
+            for record_type in &self.dns.cache.revalidate_types {
+                if RecordType::from_str(&record_type.to_uppercase()).is_err() {
+                    return Err(ServerError::Config(format!(
+                        "Cache revalidate_types: Unknown record type '{}'", record_type
+                    )));
+                }
+            }
+        }
+        
+        // 验证刷新失败宽限期依赖于缓存本身
+        if self.dns.cache.refresh_failure_grace_secs > 0 && !self.dns.cache.enabled {
             return Err(ServerError::Config(
-                "Cache persistence is enabled but cache itself is disabled. Enable cache first.".to_string()
+                "Cache refresh_failure_grace_secs is set but cache itself is disabled. Enable cache first.".to_string()
             ));
         }
         
-        // 验证周期性保存依赖于持久化缓存
-        if self.dns.cache.persistence.periodic.enabled && !self.dns.cache.persistence.enabled {
+        // 验证加载并发数
+        if self.dns.cache.persistence.load_concurrency == 0 {
             return Err(ServerError::Config(
-                "Periodic cache persistence is enabled but persistence itself is disabled. Enable persistence first.".to_string()
+                "Cache persistence load_concurrency must be at least 1".to_string()
+            ));
+        }
+        
+        // 验证保存并发数
+        if self.dns.cache.persistence.save_concurrency == 0 {
+            return Err(ServerError::Config(
+                "Cache persistence save_concurrency must be at least 1".to_string()
             ));
         }
         
@@ -659,6 +2483,41 @@ impl ServerConfig {
     // 验证解析器地址配置
     fn validate_resolvers(&self, resolvers: &[ResolverConfig]) -> Result<()> {
         for resolver in resolvers {
+            // DNS 0x20 编码仅适用于单一协议的 UDP 解析器
+            if resolver.dns_0x20 && (resolver.protocols.is_some() || resolver.protocol != ResolverProtocol::Udp) {
+                return Err(ServerError::Config(format!(
+                    "'dns_0x20' is only supported for UDP resolvers without a protocol fallback list: {}",
+                    resolver.address
+                )));
+            }
+            
+            // 协议回退链：地址必须为 IP 或 IP:端口，各协议使用其标准端点
+            if let Some(protocols) = &resolver.protocols {
+                if protocols.is_empty() {
+                    return Err(ServerError::Config(format!(
+                        "Resolver protocol fallback list must not be empty: {}",
+                        resolver.address
+                    )));
+                }
+                for (index, protocol) in protocols.iter().enumerate() {
+                    if protocols[..index].contains(protocol) {
+                        return Err(ServerError::Config(format!(
+                            "Duplicate protocol {:?} in fallback list of resolver: {}",
+                            protocol, resolver.address
+                        )));
+                    }
+                }
+                if resolver.address.parse::<IpAddr>().is_err()
+                    && resolver.address.parse::<SocketAddr>().is_err()
+                {
+                    return Err(ServerError::Config(format!(
+                        "Resolver with protocol fallback list must use 'ip' or 'ip:port' address: {}",
+                        resolver.address
+                    )));
+                }
+                continue;
+            }
+
             match resolver.protocol {
                 ResolverProtocol::Doh => {
                     // 验证 DoH 地址是有效的 URL
@@ -715,8 +2574,18 @@ impl ServerConfig {
     
     // 验证路由配置
     fn validate_routing(&self) -> Result<()> {
-        // 如果路由功能未启用，则直接返回
+        // 如果路由功能未启用，则直接返回（配置档案依赖上游组，需启用路由）
         if !self.dns.routing.enabled {
+            if !self.dns.routing.profiles.is_empty() {
+                return Err(ServerError::Config(
+                    "Routing profiles require routing to be enabled".to_string()
+                ));
+            }
+            if self.dns.routing.group_override.enabled {
+                return Err(ServerError::Config(
+                    "routing.group_override requires routing to be enabled".to_string()
+                ));
+            }
             return Ok(());
         }
         
@@ -729,6 +2598,85 @@ impl ServerConfig {
         // 验证默认上游组
         self.validate_default_upstream_group(&group_names)?;
         
+        // 验证路由配置档案
+        self.validate_routing_profiles(&group_names)?;
+        
+        // 验证黑洞响应配置
+        self.validate_blackhole()?;
+        
+        // 验证上游组覆盖配置
+        self.validate_group_override()?;
+        
+        // 验证URL规则下载大小限制
+        if self.dns.routing.max_rule_download_bytes == 0 {
+            return Err(ServerError::Config(
+                "routing.max_rule_download_bytes must be greater than 0".to_string()
+            ));
+        }
+        
+        // 验证URL规则并发更新上限
+        if self.dns.routing.max_concurrent_url_updates == 0 {
+            return Err(ServerError::Config(
+                "routing.max_concurrent_url_updates must be greater than 0".to_string()
+            ));
+        }
+        
+        // 验证未就绪等待时间
+        if self.dns.routing.not_ready_policy == GroupNotReadyPolicy::Wait && self.dns.routing.not_ready_wait_ms == 0 {
+            return Err(ServerError::Config(
+                "routing.not_ready_wait_ms must be greater than 0 when not_ready_policy is 'wait'".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // 验证黑洞响应配置
+    fn validate_blackhole(&self) -> Result<()> {
+        let blackhole_ttl = self.dns.routing.blackhole_ttl;
+        if blackhole_ttl == 0 || blackhole_ttl > MAX_BLACKHOLE_TTL {
+            return Err(ServerError::Config(format!(
+                "routing.blackhole_ttl must be between 1 and {} seconds, got {}",
+                MAX_BLACKHOLE_TTL, blackhole_ttl
+            )));
+        }
+        
+        for (i, entry) in self.dns.routing.blackhole.client_responses.iter().enumerate() {
+            if entry.client_cidrs.is_empty() {
+                return Err(ServerError::Config(format!(
+                    "Blackhole client response [{}]: 'client_cidrs' must not be empty",
+                    i + 1
+                )));
+            }
+            for cidr in &entry.client_cidrs {
+                IpCidr::from_str(cidr).map_err(|e| ServerError::Config(format!(
+                    "Blackhole client response [{}]: {}",
+                    i + 1, e
+                )))?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // 验证上游组覆盖配置：需配置受信任网段或启用管理接口令牌
+    fn validate_group_override(&self) -> Result<()> {
+        let group_override = &self.dns.routing.group_override;
+        if !group_override.enabled {
+            return Ok(());
+        }
+        
+        if group_override.trusted_cidrs.is_empty() && !self.http.admin.enabled {
+            return Err(ServerError::Config(
+                "routing.group_override requires trusted_cidrs or http_server.admin to be enabled".to_string()
+            ));
+        }
+        for cidr in &group_override.trusted_cidrs {
+            IpCidr::from_str(cidr).map_err(|e| ServerError::Config(format!(
+                "routing.group_override.trusted_cidrs: {}", e
+            )))?;
+        }
+        
         Ok(())
     }
     
@@ -760,6 +2708,15 @@ impl ServerConfig {
             
             // 验证解析器配置
             self.validate_resolvers(&group.resolvers)?;
+            
+            // 检查组级别的缓存 TTL 范围
+            let (min_ttl, max_ttl) = self.get_effective_cache_ttl(&group.name);
+            if min_ttl > max_ttl {
+                return Err(ServerError::Config(format!(
+                    "Upstream group '{}' cache_min_ttl ({}) cannot be greater than cache_max_ttl ({})",
+                    group.name, min_ttl, max_ttl
+                )));
+            }
         }
         
         Ok(group_names)
@@ -789,6 +2746,24 @@ impl ServerConfig {
     
     // 验证匹配条件
     fn validate_match_condition(&self, match_: &MatchCondition, rule_index: usize) -> Result<()> {
+        // 验证查询类型过滤
+        if let Some(ref record_types) = match_.record_types {
+            if record_types.is_empty() {
+                return Err(ServerError::Config(format!(
+                    "Rule [{}]: 'record_types' must not be empty when specified",
+                    rule_index
+                )));
+            }
+            for record_type in record_types {
+                if RecordType::from_str(&record_type.to_uppercase()).is_err() {
+                    return Err(ServerError::Config(format!(
+                        "Rule [{}]: Unknown record type '{}'",
+                        rule_index, record_type
+                    )));
+                }
+            }
+        }
+        
         match match_.type_ {
             MatchType::Exact => {
                 if match_.values.is_none() {
@@ -805,10 +2780,15 @@ impl ServerConfig {
                         rule_index
                     )));
                 }
-                // 尝试编译正则表达式，验证其有效性
-                if let Some(ref values) = match_.values {
+                // 尝试编译正则表达式，验证其有效性（延迟编译时仅校验语法，编译推迟到首次匹配）
+                if let Some(values) = match_.values.as_ref() {
                     for (i, pattern) in values.iter().enumerate() {
-                        if let Err(e) = regex::Regex::new(pattern) {
+                        let result = if self.dns.routing.lazy_regex {
+                            check_regex_syntax(pattern)
+                        } else {
+                            regex::Regex::new(pattern).map(|_| ()).map_err(|e| e.to_string())
+                        };
+                        if let Err(e) = result {
                             return Err(ServerError::Config(format!(
                                 "Rule [{}]: Regex pattern [{}] '{}' is invalid: {}",
                                 rule_index, i, pattern, e
@@ -889,6 +2869,23 @@ impl ServerConfig {
                                 rule_index, periodic.interval_secs, MAX_URL_RULE_UPDATE_INTERVAL_SECS
                             )));
                         }
+                        
+                        // 验证重试退避配置
+                        let retry = &periodic.retry;
+                        if retry.max_attempts > 0 {
+                            if retry.initial_backoff_secs == 0 {
+                                return Err(ServerError::Config(format!(
+                                    "Rule [{}]: Url type retry initial backoff must be greater than 0 seconds",
+                                    rule_index
+                                )));
+                            }
+                            if retry.max_backoff_secs < retry.initial_backoff_secs {
+                                return Err(ServerError::Config(format!(
+                                    "Rule [{}]: Url type retry max backoff {} seconds is less than initial backoff {} seconds",
+                                    rule_index, retry.max_backoff_secs, retry.initial_backoff_secs
+                                )));
+                            }
+                        }
                     }
                 }
             }
@@ -906,6 +2903,54 @@ impl ServerConfig {
                     default_group
                 )));
             }
+            
+            // 默认上游组存在时未匹配的查询总会使用该组，default_action 不会生效
+            if self.dns.routing.default_action != DefaultRouteAction::Global {
+                return Err(ServerError::Config(
+                    "routing.default_action cannot be combined with default_upstream_group".to_string()
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // 验证路由配置档案
+    fn validate_routing_profiles(&self, group_names: &std::collections::HashSet<String>) -> Result<()> {
+        let mut names = std::collections::HashSet::new();
+        let mut prefixes = std::collections::HashSet::new();
+        
+        for profile in &self.dns.routing.profiles {
+            if profile.name.trim().is_empty() {
+                return Err(ServerError::Config("Routing profile name cannot be empty".to_string()));
+            }
+            if !names.insert(profile.name.as_str()) {
+                return Err(ServerError::Config(format!(
+                    "Duplicate routing profile name: {}", profile.name
+                )));
+            }
+            
+            // 路径前缀必须以 '/' 开头、不以 '/' 结尾，且不能与标准 DoH 路径冲突
+            let prefix = profile.path_prefix.as_str();
+            if !prefix.starts_with('/') || prefix.len() < 2 || prefix.ends_with('/')
+                || prefix == DOH_STANDARD_PATH || prefix == DOH_JSON_API_PATH
+            {
+                return Err(ServerError::Config(format!(
+                    "Routing profile '{}' has an invalid path_prefix: {}", profile.name, prefix
+                )));
+            }
+            if !prefixes.insert(prefix) {
+                return Err(ServerError::Config(format!(
+                    "Duplicate routing profile path_prefix: {}", prefix
+                )));
+            }
+            
+            if !group_names.contains(&profile.default_upstream_group) {
+                return Err(ServerError::Config(format!(
+                    "Routing profile '{}' references non-existent upstream group: {}",
+                    profile.name, profile.default_upstream_group
+                )));
+            }
         }
         
         Ok(())
@@ -968,6 +3013,52 @@ impl Default for TtlConfig {
             min: DEFAULT_MIN_TTL,
             max: DEFAULT_MAX_TTL,
             negative: DEFAULT_NEGATIVE_TTL,
+            response_max: DEFAULT_RESPONSE_MAX_TTL,
+        }
+    }
+}
+
+impl Default for AdaptiveTtlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min: DEFAULT_ADAPTIVE_TTL_MIN,
+            max: DEFAULT_ADAPTIVE_TTL_MAX,
+            max_tracked: DEFAULT_ADAPTIVE_TTL_MAX_TRACKED,
+        }
+    }
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            upstream_groups: Vec::new(),
+            rules: Vec::new(),
+            default_upstream_group: None,
+            default_action: DefaultRouteAction::default(),
+            profiles: Vec::new(),
+            blackhole: BlackholeConfig::default(),
+            blackhole_ttl: DEFAULT_BLACKHOLE_TTL,
+            max_rule_download_bytes: DEFAULT_MAX_RULE_DOWNLOAD_BYTES,
+            max_concurrent_url_updates: DEFAULT_MAX_CONCURRENT_URL_UPDATES,
+            lazy_regex: false,
+            not_ready_policy: GroupNotReadyPolicy::default(),
+            not_ready_wait_ms: DEFAULT_NOT_READY_WAIT_MS,
+            fail_closed_until_loaded: false,
+            fail_closed_action: FailClosedAction::default(),
+            group_override: GroupOverrideConfig::default(),
+        }
+    }
+}
+
+impl Default for BlackholeResponseConfig {
+    fn default() -> Self {
+        Self {
+            type_: BlackholeResponseType::default(),
+            ipv4: None,
+            ipv6: None,
+            ttl: DEFAULT_BLACKHOLE_SINKHOLE_TTL,
         }
     }
 }
@@ -977,8 +3068,23 @@ impl Default for CacheConfig {
         Self {
             enabled: false,
             size: DEFAULT_CACHE_SIZE,
+            shards: DEFAULT_CACHE_SHARDS,
             ttl: TtlConfig::default(),
             persistence: PersistenceCacheConfig::default(),
+            store_compressed: false,
+            compression_min_bytes: DEFAULT_CACHE_COMPRESSION_MIN_BYTES,
+            stale_on_error: false,
+            stale_on_error_ttl: DEFAULT_STALE_ON_ERROR_TTL,
+            stale_on_error_max_age: DEFAULT_STALE_ON_ERROR_MAX_AGE,
+            refresh_failure_grace_secs: 0,
+            override_zero_ttl_secs: 0,
+            companion_prefetch: false,
+            adaptive_ttl: AdaptiveTtlConfig::default(),
+            revalidate_types: Vec::new(),
+            time_to_idle_secs: DEFAULT_CACHE_TIME_TO_IDLE_SECS,
+            max_memory_bytes: 0,
+            isolate_upstream_groups: false,
+            json_answer_cache: false,
         }
     }
 }
@@ -989,6 +3095,10 @@ impl Default for RateLimitConfig {
             enabled: false,
             per_ip_rate: DEFAULT_PER_IP_RATE,
             per_ip_concurrent: DEFAULT_PER_IP_CONCURRENT,
+            per_ip_daily_quota: 0,
+            quota_reset_hour: DEFAULT_QUOTA_RESET_HOUR,
+            quota_max_tracked_ips: DEFAULT_QUOTA_MAX_TRACKED_IPS,
+            quota_trusted_proxies: Vec::new(),
         }
     }
 }
@@ -1017,6 +3127,16 @@ impl Default for HttpClientConfig {
             timeout: DEFAULT_HTTP_CLIENT_TIMEOUT,
             pool: PoolConfig::default(),
             request: RequestConfig::default(),
+            tls: TlsPolicyConfig::default(),
+        }
+    }
+}
+
+impl Default for TlsPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_version: DEFAULT_HTTP_CLIENT_TLS_MIN_VERSION.to_string(),
+            ciphers: Vec::new(),
         }
     }
 }
@@ -1027,6 +3147,23 @@ impl Default for HttpServerConfig {
             listen_addr: default_listen_addr(),
             timeout: DEFAULT_LISTEN_TIMEOUT,
             rate_limit: RateLimitConfig::default(),
+            admin: AdminConfig::default(),
+            nsid: String::new(),
+            base_path: String::new(),
+            get_accept: GetAcceptPolicy::default(),
+            advertise_alt_svc: String::new(),
+            health: HealthConfig::default(),
+        }
+    }
+}
+
+impl Default for HealthProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            name: DEFAULT_HEALTH_PROBE_NAME.to_string(),
+            interval_secs: DEFAULT_HEALTH_PROBE_INTERVAL_SECS,
+            timeout_secs: DEFAULT_HEALTH_PROBE_TIMEOUT_SECS,
         }
     }
 }
@@ -1038,11 +3175,163 @@ impl Default for DnsResolverConfig {
                 resolvers: Vec::new(),
                 enable_dnssec: false,
                 query_timeout: DEFAULT_QUERY_TIMEOUT,
+                connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+                udp_source_port_randomization: true,
+                question_mismatch_policy: QuestionMismatchPolicy::default(),
+                minimize_query: true,
             },
             http_client: HttpClientConfig::default(),
             cache: CacheConfig::default(),
             routing: RoutingConfig::default(),
             ecs_policy: EcsPolicyConfig::default(),
+            emit_ede: false,
+            block_reason_header: false,
+            duplicate_detection: DuplicateDetectionConfig::default(),
+            cname_flatten: CnameFlattenConfig::default(),
+            normalize_response_flags: true,
+            strip_opt_for_non_edns_clients: false,
+            answer_sort: AnswerSort::default(),
+            dedup_answers: false,
+            max_answer_records: 0,
+            answer_ttl_floor: AnswerTtlFloorConfig::default(),
+            response_padding: ResponsePaddingConfig::default(),
+            query_log: QueryLogConfig::default(),
+            dnstap: DnstapConfig::default(),
+            plugins: PluginConfig::default(),
+            log_sample_rate: DEFAULT_LOG_SAMPLE_RATE,
+            log_modified_responses: false,
+            truncated_query_policy: TruncatedQueryPolicy::default(),
+            any_query_policy: AnyQueryPolicy::default(),
+            edns_probe_policy: EdnsProbePolicy::default(),
+            query_limits: QueryLimitsConfig::default(),
+            allow_multi_question: false,
+            partial_results: false,
+            ddr: DdrConfig::default(),
+            special_names: SpecialNamesConfig::default(),
+            loop_protection: LoopProtectionConfig::default(),
+            local_records: LocalRecordsConfig::default(),
+            dnssec: DnssecConfig::default(),
+        }
+    }
+}
+
+impl Default for LocalTrustAnchorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file_path: String::new(),
+            ttl: DEFAULT_TRUST_ANCHOR_TTL,
+        }
+    }
+}
+
+impl Default for LocalRecordsConfig {
+    fn default() -> Self {
+        Self {
+            records: Vec::new(),
+            ttl: DEFAULT_LOCAL_RECORDS_TTL,
+            ipv4_only_aaaa: Ipv4OnlyAaaaPolicy::default(),
+            dns64_prefix: DEFAULT_DNS64_PREFIX,
+        }
+    }
+}
+
+impl Default for LoopProtectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            self_names: Vec::new(),
+            self_addresses: Vec::new(),
+        }
+    }
+}
+
+impl Default for SpecialNamesConfig {
+    fn default() -> Self {
+        Self {
+            localhost: true,
+            invalid: true,
+        }
+    }
+}
+
+impl Default for DdrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: String::new(),
+            port: DEFAULT_DDR_PORT,
+            alpn: default_ddr_alpn(),
+            dohpath: String::new(),
+            priority: DEFAULT_DDR_PRIORITY,
+            ttl: DEFAULT_DDR_TTL,
+        }
+    }
+}
+
+impl Default for QueryLimitsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_query_size: DEFAULT_MAX_QUERY_SIZE,
+            max_edns_options: DEFAULT_MAX_EDNS_OPTIONS,
+            max_questions: DEFAULT_MAX_QUESTIONS,
+            max_json_request_size: DEFAULT_MAX_JSON_REQUEST_SIZE,
+        }
+    }
+}
+
+impl Default for QueryLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: DEFAULT_QUERY_LOG_PATH.to_string(),
+            format: QueryLogFormat::default(),
+            max_size_mb: DEFAULT_QUERY_LOG_MAX_SIZE_MB,
+            max_files: DEFAULT_QUERY_LOG_MAX_FILES,
+            buffer_size: DEFAULT_QUERY_LOG_BUFFER_SIZE,
+        }
+    }
+}
+
+impl Default for DnstapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file_path: String::new(),
+            socket_path: String::new(),
+            identity: String::new(),
+            buffer_size: DEFAULT_DNSTAP_BUFFER_SIZE,
+        }
+    }
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            script_path: String::new(),
+            max_operations: DEFAULT_PLUGIN_MAX_OPERATIONS,
+        }
+    }
+}
+
+impl Default for DuplicateDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: DEFAULT_DUPLICATE_QUERY_WINDOW_SECS,
+            max_tracked: DEFAULT_DUPLICATE_QUERY_MAX_TRACKED,
+        }
+    }
+}
+
+impl Default for CnameFlattenConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            zones: Vec::new(),
+            max_depth: DEFAULT_CNAME_FLATTEN_MAX_DEPTH,
         }
     }
 }
@@ -1056,11 +3345,26 @@ impl Default for PersistenceCacheConfig {
             max_items_to_save: 0,
             skip_expired_on_load: default_cache_skip_expired_on_load(),
             shutdown_save_timeout_secs: default_cache_shutdown_save_timeout(),
+            load_concurrency: default_cache_load_concurrency(),
+            save_concurrency: default_cache_save_concurrency(),
+            load_rate: 0,
+            on_corrupt: CorruptCachePolicy::default(),
+            fail_on_unwritable: false,
+            baseline_path: String::new(),
             periodic: PeriodicSaveConfig::default(),
         }
     }
 }
 
+impl Default for ResponsePaddingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            block_size: DEFAULT_RESPONSE_PADDING_BLOCK_SIZE,
+        }
+    }
+}
+
 impl Default for PeriodicSaveConfig {
     fn default() -> Self {
         Self {
@@ -1085,6 +3389,7 @@ impl Default for EcsPolicyConfig {
             enabled: false,
             strategy: ECS_POLICY_STRIP.to_string(),
             anonymization: EcsAnonymizationConfig::default(),
+            strip_from_response: false,
         }
     }
 }
@@ -1094,6 +3399,17 @@ impl Default for PeriodicUpdateConfig {
         Self {
             enabled: false,
             interval_secs: DEFAULT_URL_RULE_UPDATE_INTERVAL_SECS,
+            retry: UrlRuleRetryConfig::default(),
+        }
+    }
+}
+
+impl Default for UrlRuleRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_URL_RULE_RETRY_MAX_ATTEMPTS,
+            initial_backoff_secs: DEFAULT_URL_RULE_RETRY_INITIAL_BACKOFF_SECS,
+            max_backoff_secs: DEFAULT_URL_RULE_RETRY_MAX_BACKOFF_SECS,
         }
     }
 }
@@ -0,0 +1,98 @@
+// src/server/ddr.rs
+
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use hickory_proto::rr::rdata::NULL;
+use hickory_proto::rr::rdata::svcb::SvcParamKey;
+use hickory_proto::serialize::binary::{BinEncodable, BinEncoder};
+use crate::common::consts::{DDR_RESOLVER_NAME, SVCB_KEY_DOHPATH};
+use crate::server::config::ServerConfig;
+use crate::server::error::{Result, ServerError};
+
+// DDR 应答器：为 _dns.resolver.arpa 的 SVCB 查询合成指向本服务 DoH 端点的记录（RFC 9462）
+pub struct DdrResponder {
+    // DDR 查询名
+    name: Name,
+    // 预编码的 SVCB RDATA
+    rdata: Vec<u8>,
+    // 记录 TTL
+    ttl: u32,
+}
+
+impl DdrResponder {
+    // 根据配置创建 DDR 应答器，未启用时返回 None
+    pub fn new(config: &ServerConfig) -> Result<Option<Self>> {
+        let ddr = &config.dns.ddr;
+        if !ddr.enabled {
+            return Ok(None);
+        }
+
+        let target = Name::from_ascii(&ddr.target)
+            .map_err(|e| ServerError::Config(format!("Invalid DDR target '{}': {}", ddr.target, e)))?;
+        let rdata = encode_svcb_rdata(ddr.priority, &target, &ddr.alpn, ddr.port, &config.ddr_dohpath())?;
+
+        Ok(Some(Self {
+            name: Name::from_ascii(DDR_RESOLVER_NAME)
+                .map_err(|e| ServerError::Config(format!("Invalid DDR name: {}", e)))?,
+            rdata,
+            ttl: ddr.ttl,
+        }))
+    }
+
+    // 若查询为 DDR SVCB 查询，返回合成的应答
+    pub fn respond(&self, query_message: &Message) -> Option<Message> {
+        let query = query_message.queries().first()?;
+        if query.query_type() != RecordType::SVCB || query.name() != &self.name {
+            return None;
+        }
+
+        let mut response = Message::new();
+        response.set_id(query_message.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(query_message.op_code())
+            .set_recursion_desired(query_message.recursion_desired())
+            .set_recursion_available(true)
+            .set_response_code(ResponseCode::NoError)
+            .add_query(query.clone());
+
+        // hickory 的 SVCB 编码会为未知参数附加长度前缀，此处以原始 RDATA 形式写出
+        let rdata = RData::Unknown {
+            code: RecordType::SVCB,
+            rdata: NULL::with(self.rdata.clone()),
+        };
+        response.add_answer(Record::from_rdata(query.name().clone(), self.ttl, rdata));
+
+        Some(response)
+    }
+}
+
+// 编码 SVCB RDATA：优先级、目标名及按键升序排列的 alpn、port、dohpath 参数
+fn encode_svcb_rdata(priority: u16, target: &Name, alpn: &[String], port: u16, dohpath: &str) -> Result<Vec<u8>> {
+    let mut alpn_value = Vec::new();
+    for protocol in alpn {
+        alpn_value.push(protocol.len() as u8);
+        alpn_value.extend_from_slice(protocol.as_bytes());
+    }
+
+    let params: [(u16, Vec<u8>); 3] = [
+        (u16::from(SvcParamKey::Alpn), alpn_value),
+        (u16::from(SvcParamKey::Port), port.to_be_bytes().to_vec()),
+        (SVCB_KEY_DOHPATH, dohpath.as_bytes().to_vec()),
+    ];
+
+    let mut rdata = Vec::new();
+    {
+        let mut encoder = BinEncoder::new(&mut rdata);
+        encoder.set_canonical_names(true);
+        encoder.emit_u16(priority)
+            .and_then(|_| target.emit(&mut encoder))
+            .map_err(|e| ServerError::Config(format!("Failed to encode DDR SVCB record: {}", e)))?;
+    }
+    for (key, value) in params {
+        rdata.extend_from_slice(&key.to_be_bytes());
+        rdata.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        rdata.extend_from_slice(&value);
+    }
+
+    Ok(rdata)
+}
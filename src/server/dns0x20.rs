@@ -0,0 +1,203 @@
+// src/server/dns0x20.rs
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use futures::future::BoxFuture;
+use hickory_proto::op::Message;
+use hickory_proto::rr::{Name, Record};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::timeout;
+use tracing::{debug, warn};
+use crate::server::error::{Result, ServerError};
+use crate::server::metrics::METRICS;
+use crate::server::upstream::UpstreamResolver;
+
+// 协议标签
+const DNS0X20_PROTOCOL: &str = "Udp";
+
+// UDP 应答的最大长度
+const MAX_UDP_RESPONSE_SIZE: usize = 65535;
+
+// 随机翻转名称中 ASCII 字母的大小写（DNS 0x20 编码），其他字节保持不变
+pub fn randomize_case(name: &Name) -> Name {
+    let labels = name.iter().map(|label| {
+        label.iter()
+            .map(|byte| if byte.is_ascii_alphabetic() && fastrand::bool() { byte ^ 0x20 } else { *byte })
+            .collect::<Vec<u8>>()
+    });
+
+    match Name::from_labels(labels) {
+        Ok(mut encoded) => {
+            encoded.set_fqdn(name.is_fqdn());
+            encoded
+        },
+        Err(_) => name.clone(),
+    }
+}
+
+// 应答的问题节是否与发出的查询逐字节一致（标签比较区分大小写）
+pub fn question_matches(response: &Message, sent: &Message) -> bool {
+    match (response.queries().first(), sent.queries().first()) {
+        (Some(received), Some(expected)) => {
+            received.query_type() == expected.query_type()
+                && received.query_class() == expected.query_class()
+                && received.name().iter().eq(expected.name().iter())
+        },
+        _ => false,
+    }
+}
+
+// 启用 DNS 0x20 编码的 UDP 上游：随机化查询名大小写，并丢弃大小写不一致的应答以抵御伪造
+// 应答被截断时改用 TCP 重新查询（同样校验大小写）
+pub struct Dns0x20Resolver {
+    // 上游地址
+    server: SocketAddr,
+    // 解析器标识
+    id: String,
+    // 查询超时
+    query_timeout: Duration,
+    // TCP 连接超时
+    connect_timeout: Duration,
+}
+
+impl Dns0x20Resolver {
+    // 创建新的 0x20 解析器
+    pub fn new(server: SocketAddr, query_timeout: Duration, connect_timeout: Duration) -> Self {
+        Self { server, id: server.to_string(), query_timeout, connect_timeout }
+    }
+
+    // 执行查询：发出随机大小写与随机 ID 的查询，应答恢复为客户端原始的 ID 与问题节
+    async fn query(&self, query_message: &Message) -> Result<Message> {
+        let query = query_message.queries().first().ok_or_else(||
+            ServerError::Upstream("No query in message".to_string())
+        )?;
+
+        let mut encoded_query = query.clone();
+        encoded_query.set_name(randomize_case(query.name()));
+        let mut outbound = query_message.clone();
+        outbound.set_id(fastrand::u16(..));
+        outbound.take_queries();
+        outbound.add_query(encoded_query);
+        let wire = outbound.to_vec()?;
+
+        let mut response = timeout(self.query_timeout, self.exchange_udp(&outbound, &wire))
+            .await
+            .map_err(|_| ServerError::Upstream(format!("DNS 0x20 query to {} timed out", self.server)))??;
+
+        if response.truncated() {
+            debug!(server = %self.server, "Truncated UDP response, retrying over TCP");
+            response = timeout(self.query_timeout, self.exchange_tcp(&outbound, &wire))
+                .await
+                .map_err(|_| ServerError::Upstream(format!("DNS 0x20 TCP query to {} timed out", self.server)))??;
+        }
+
+        Ok(Self::restore_query(query_message, response))
+    }
+
+    // 通过新建的 UDP 套接字（操作系统分配源端口）发送查询，丢弃 ID 或问题节不一致的应答并在超时内继续等待
+    async fn exchange_udp(&self, outbound: &Message, wire: &[u8]) -> Result<Message> {
+        let bind_addr = match self.server.ip() {
+            IpAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            IpAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+        };
+        let socket = UdpSocket::bind(bind_addr).await?;
+        socket.connect(self.server).await?;
+        socket.send(wire).await?;
+
+        let mut buf = vec![0u8; MAX_UDP_RESPONSE_SIZE];
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            let Ok(response) = Message::from_vec(&buf[..len]) else {
+                debug!(server = %self.server, "Discarding unparsable UDP response");
+                continue;
+            };
+            // 应答 ID 可由路径外的伪造者任意构造，仅计数并以 debug 级别记录，避免日志被放大
+            if response.id() != outbound.id() {
+                METRICS.upstream_id_mismatches_total().with_label_values(&[&self.id]).inc();
+                debug!(
+                    server = %self.server,
+                    expected = outbound.id(),
+                    received = response.id(),
+                    "Discarding UDP response with mismatched ID (possible spoofing)"
+                );
+                continue;
+            }
+            if !question_matches(&response, outbound) {
+                METRICS.upstream_0x20_mismatches_total().with_label_values(&[&self.id]).inc();
+                warn!(
+                    server = %self.server,
+                    expected = ?outbound.queries(),
+                    received = ?response.queries(),
+                    "Discarding UDP response whose query name case does not match (possible spoofing)"
+                );
+                continue;
+            }
+            return Ok(response);
+        }
+    }
+
+    // 通过 TCP 发送查询（两字节长度前缀），应答不一致时视为失败
+    async fn exchange_tcp(&self, outbound: &Message, wire: &[u8]) -> Result<Message> {
+        let mut stream = timeout(self.connect_timeout, TcpStream::connect(self.server))
+            .await
+            .map_err(|_| ServerError::Upstream(format!("Connection to {} timed out", self.server)))??;
+
+        let length = u16::try_from(wire.len())
+            .map_err(|_| ServerError::Upstream("DNS query too large for TCP".to_string()))?;
+        stream.write_all(&length.to_be_bytes()).await?;
+        stream.write_all(wire).await?;
+
+        let length = stream.read_u16().await? as usize;
+        let mut buf = vec![0u8; length];
+        stream.read_exact(&mut buf).await?;
+        let response = Message::from_vec(&buf)?;
+
+        if response.id() != outbound.id() {
+            METRICS.upstream_id_mismatches_total().with_label_values(&[&self.id]).inc();
+            return Err(ServerError::Upstream(format!(
+                "TCP response from {} has ID {}, expected {}", self.server, response.id(), outbound.id()
+            )));
+        }
+        if !question_matches(&response, outbound) {
+            METRICS.upstream_0x20_mismatches_total().with_label_values(&[&self.id]).inc();
+            return Err(ServerError::Upstream(format!(
+                "TCP response from {} does not match the query", self.server
+            )));
+        }
+        Ok(response)
+    }
+
+    // 恢复客户端查询的 ID 与问题节，并将与查询名相同的记录名还原为原始大小写
+    fn restore_query(query_message: &Message, mut response: Message) -> Message {
+        response.set_id(query_message.id());
+        let encoded_name = response.take_queries().into_iter().next().map(|q| q.name().clone());
+        response.add_queries(query_message.queries().to_vec());
+
+        if let (Some(encoded_name), Some(query)) = (encoded_name, query_message.queries().first()) {
+            let restore = |records: &mut Vec<Record>| {
+                for record in records.iter_mut().filter(|record| record.name().iter().eq(encoded_name.iter())) {
+                    record.set_name(query.name().clone());
+                }
+            };
+            restore(response.answers_mut());
+            restore(response.name_servers_mut());
+            restore(response.additionals_mut());
+        }
+        response
+    }
+}
+
+impl UpstreamResolver for Dns0x20Resolver {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn protocol(&self) -> &str {
+        DNS0X20_PROTOCOL
+    }
+
+    fn resolve<'a>(&'a self, query_message: &'a Message) -> BoxFuture<'a, Result<Message>> {
+        Box::pin(self.query(query_message))
+    }
+}
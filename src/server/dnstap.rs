@@ -0,0 +1,358 @@
+// src/server/dnstap.rs
+
+use std::fs::{self, OpenOptions};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use hickory_proto::op::Message;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{info, warn};
+use crate::server::config::DnstapConfig;
+use crate::server::error::{Result, ServerError};
+use crate::server::metrics::METRICS;
+
+// Frame Streams 内容类型
+pub const DNSTAP_CONTENT_TYPE: &str = "protobuf:dnstap.Dnstap";
+
+// Frame Streams 控制帧类型
+pub const FSTRM_CONTROL_ACCEPT: u32 = 0x01;
+pub const FSTRM_CONTROL_START: u32 = 0x02;
+pub const FSTRM_CONTROL_STOP: u32 = 0x03;
+pub const FSTRM_CONTROL_READY: u32 = 0x04;
+
+// Frame Streams 控制帧字段：内容类型
+const FSTRM_CONTROL_FIELD_CONTENT_TYPE: u32 = 0x01;
+
+// Frame Streams 控制帧最大长度
+#[cfg(unix)]
+const FSTRM_CONTROL_FRAME_MAX_LEN: usize = 512;
+
+// dnstap.Dnstap.Type：MESSAGE
+const DNSTAP_TYPE_MESSAGE: u64 = 1;
+
+// dnstap.Message.Type
+pub const DNSTAP_MESSAGE_CLIENT_QUERY: u64 = 5;
+pub const DNSTAP_MESSAGE_CLIENT_RESPONSE: u64 = 6;
+
+// dnstap.SocketFamily
+const DNSTAP_SOCKET_FAMILY_INET: u64 = 1;
+const DNSTAP_SOCKET_FAMILY_INET6: u64 = 2;
+
+// dnstap.SocketProtocol：DOH
+const DNSTAP_SOCKET_PROTOCOL_DOH: u64 = 4;
+
+// 套接字输出连接失败后的重连间隔（秒）
+#[cfg(unix)]
+const DNSTAP_RECONNECT_INTERVAL_SECS: u64 = 5;
+
+// dnstap 输出目标
+enum DnstapOutput {
+    // 文件（Frame Streams 单向模式）
+    File(tokio::fs::File),
+    // Unix 套接字（Frame Streams 双向模式）
+    #[cfg(unix)]
+    Socket(String),
+}
+
+// dnstap 查询/应答日志，帧经有界队列交由后台任务写出，队列满时丢弃
+pub struct DnstapLogger {
+    // 帧发送端，禁用时为 None
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    // 写入每帧的 identity 字段
+    identity: Vec<u8>,
+    // 写入每帧的 version 字段
+    version: Vec<u8>,
+}
+
+impl DnstapLogger {
+    // 根据配置创建 dnstap 日志，启用时打开输出并启动后台写出任务
+    pub fn new(config: &DnstapConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+
+        let output = if !config.file_path.is_empty() {
+            info!(path = %config.file_path, "dnstap file output enabled");
+            DnstapOutput::File(tokio::fs::File::from_std(Self::open(&config.file_path)?))
+        } else {
+            Self::socket_output(&config.socket_path)?
+        };
+
+        let (sender, receiver) = mpsc::channel(config.buffer_size);
+        tokio::spawn(run_writer(output, receiver));
+
+        Ok(Self {
+            sender: Some(sender),
+            identity: config.identity.clone().into_bytes(),
+            version: format!("oxide-wdns {}", env!("CARGO_PKG_VERSION")).into_bytes(),
+        })
+    }
+
+    // 创建禁用的 dnstap 日志
+    pub fn disabled() -> Self {
+        Self { sender: None, identity: Vec::new(), version: Vec::new() }
+    }
+
+    // 是否启用
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    // 记录一次已完成的查询：依次输出 CLIENT_QUERY 与 CLIENT_RESPONSE 帧
+    pub fn log(&self, client_ip: IpAddr, query: &Message, response: &Message, query_time: SystemTime) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let response_time = SystemTime::now();
+        let frames = [
+            (DNSTAP_MESSAGE_CLIENT_QUERY, query),
+            (DNSTAP_MESSAGE_CLIENT_RESPONSE, response),
+        ];
+        for (message_type, message) in frames {
+            let wire = match message.to_vec() {
+                Ok(wire) => wire,
+                Err(e) => {
+                    warn!(error = %e, "Failed to encode DNS message for dnstap");
+                    continue;
+                }
+            };
+            let frame = self.encode_frame(message_type, client_ip, &wire, query_time, response_time);
+            match sender.try_send(frame) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) | Err(TrySendError::Closed(_)) => {
+                    METRICS.dnstap_frames_dropped_total().inc();
+                }
+            }
+        }
+    }
+
+    // 编码 dnstap.Dnstap 消息
+    fn encode_frame(
+        &self,
+        message_type: u64,
+        client_ip: IpAddr,
+        wire: &[u8],
+        query_time: SystemTime,
+        response_time: SystemTime,
+    ) -> Vec<u8> {
+        let (family, address) = match client_ip {
+            IpAddr::V4(ip) => (DNSTAP_SOCKET_FAMILY_INET, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (DNSTAP_SOCKET_FAMILY_INET6, ip.octets().to_vec()),
+        };
+        let (query_sec, query_nsec) = unix_time(query_time);
+
+        // dnstap.Message
+        let mut message = Vec::with_capacity(wire.len() + 64);
+        put_varint_field(&mut message, 1, message_type);
+        put_varint_field(&mut message, 2, family);
+        put_varint_field(&mut message, 3, DNSTAP_SOCKET_PROTOCOL_DOH);
+        put_bytes_field(&mut message, 4, &address);
+        put_varint_field(&mut message, 8, query_sec);
+        put_fixed32_field(&mut message, 9, query_nsec);
+        if message_type == DNSTAP_MESSAGE_CLIENT_QUERY {
+            put_bytes_field(&mut message, 10, wire);
+        } else {
+            let (response_sec, response_nsec) = unix_time(response_time);
+            put_varint_field(&mut message, 12, response_sec);
+            put_fixed32_field(&mut message, 13, response_nsec);
+            put_bytes_field(&mut message, 14, wire);
+        }
+
+        // dnstap.Dnstap
+        let mut frame = Vec::with_capacity(message.len() + self.identity.len() + self.version.len() + 16);
+        if !self.identity.is_empty() {
+            put_bytes_field(&mut frame, 1, &self.identity);
+        }
+        put_bytes_field(&mut frame, 2, &self.version);
+        put_bytes_field(&mut frame, 14, &message);
+        put_varint_field(&mut frame, 15, DNSTAP_TYPE_MESSAGE);
+        frame
+    }
+
+    #[cfg(unix)]
+    fn socket_output(path: &str) -> Result<DnstapOutput> {
+        info!(path = %path, "dnstap socket output enabled");
+        Ok(DnstapOutput::Socket(path.to_string()))
+    }
+
+    #[cfg(not(unix))]
+    fn socket_output(path: &str) -> Result<DnstapOutput> {
+        Err(ServerError::Config(format!("dnstap socket output is not supported on this platform: {}", path)))
+    }
+
+    // 以截断模式打开输出文件，必要时创建父目录
+    fn open(path: &str) -> Result<fs::File> {
+        if let Some(parent) = Path::new(path).parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|e| ServerError::Config(format!("Failed to open dnstap file {}: {}", path, e)))
+    }
+}
+
+// 后台写出任务，发送端全部释放后写出 STOP 控制帧并退出
+async fn run_writer(output: DnstapOutput, mut receiver: mpsc::Receiver<Vec<u8>>) {
+    match output {
+        DnstapOutput::File(file) => {
+            let mut writer = BufWriter::new(file);
+            if let Err(e) = write_file_frames(&mut writer, &mut receiver).await {
+                warn!(error = %e, "Failed to write dnstap file, dnstap output stopped");
+            }
+        }
+        #[cfg(unix)]
+        DnstapOutput::Socket(path) => run_socket_writer(&path, &mut receiver).await,
+    }
+}
+
+// 单向模式：START 控制帧 + 数据帧 + STOP 控制帧
+async fn write_file_frames<W: AsyncWrite + Unpin>(writer: &mut W, receiver: &mut mpsc::Receiver<Vec<u8>>) -> std::io::Result<()> {
+    writer.write_all(&control_frame(FSTRM_CONTROL_START, true)).await?;
+    writer.flush().await?;
+
+    while let Some(frame) = receiver.recv().await {
+        write_data_frame(writer, &frame).await?;
+        // 队列已清空时刷新，避免逐帧系统调用
+        if receiver.is_empty() {
+            writer.flush().await?;
+        }
+    }
+
+    writer.write_all(&control_frame(FSTRM_CONTROL_STOP, false)).await?;
+    writer.flush().await
+}
+
+// 双向模式：连接失败或写出失败时丢弃帧，并按间隔重连
+#[cfg(unix)]
+async fn run_socket_writer(path: &str, receiver: &mut mpsc::Receiver<Vec<u8>>) {
+    use tokio::net::UnixStream;
+    use tokio::time::{Duration, Instant};
+
+    let mut stream: Option<BufWriter<UnixStream>> = None;
+    let mut next_attempt = Instant::now();
+
+    while let Some(frame) = receiver.recv().await {
+        if stream.is_none() && Instant::now() >= next_attempt {
+            match connect_socket(path).await {
+                Ok(connected) => {
+                    info!(path = %path, "dnstap socket connected");
+                    stream = Some(BufWriter::new(connected));
+                }
+                Err(e) => {
+                    warn!(path = %path, error = %e, "Failed to connect dnstap socket");
+                    next_attempt = Instant::now() + Duration::from_secs(DNSTAP_RECONNECT_INTERVAL_SECS);
+                }
+            }
+        }
+
+        let Some(writer) = stream.as_mut() else {
+            METRICS.dnstap_frames_dropped_total().inc();
+            continue;
+        };
+
+        let mut result = write_data_frame(writer, &frame).await;
+        if result.is_ok() && receiver.is_empty() {
+            result = writer.flush().await;
+        }
+        if let Err(e) = result {
+            warn!(path = %path, error = %e, "Failed to write dnstap socket, reconnecting");
+            METRICS.dnstap_frames_dropped_total().inc();
+            stream = None;
+            next_attempt = Instant::now() + Duration::from_secs(DNSTAP_RECONNECT_INTERVAL_SECS);
+        }
+    }
+
+    if let Some(mut writer) = stream {
+        let _ = writer.write_all(&control_frame(FSTRM_CONTROL_STOP, false)).await;
+        let _ = writer.flush().await;
+    }
+}
+
+// 连接套接字并完成握手：READY -> ACCEPT -> START
+#[cfg(unix)]
+async fn connect_socket(path: &str) -> std::io::Result<tokio::net::UnixStream> {
+    use std::io::{Error, ErrorKind};
+    use tokio::io::AsyncReadExt;
+
+    let mut stream = tokio::net::UnixStream::connect(path).await?;
+    stream.write_all(&control_frame(FSTRM_CONTROL_READY, true)).await?;
+
+    // 控制帧：转义（0）+ 长度 + 控制类型 + 字段
+    let escape = stream.read_u32().await?;
+    let length = stream.read_u32().await? as usize;
+    if escape != 0 || !(4..=FSTRM_CONTROL_FRAME_MAX_LEN).contains(&length) {
+        return Err(Error::new(ErrorKind::InvalidData, "invalid Frame Streams control frame"));
+    }
+    let mut control = vec![0u8; length];
+    stream.read_exact(&mut control).await?;
+    if u32::from_be_bytes([control[0], control[1], control[2], control[3]]) != FSTRM_CONTROL_ACCEPT {
+        return Err(Error::new(ErrorKind::InvalidData, "dnstap receiver did not accept the stream"));
+    }
+
+    stream.write_all(&control_frame(FSTRM_CONTROL_START, true)).await?;
+    Ok(stream)
+}
+
+// 写出数据帧：长度（大端 u32）+ 负载
+async fn write_data_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(frame.len() as u32).to_be_bytes()).await?;
+    writer.write_all(frame).await
+}
+
+// 构建控制帧，可附带内容类型字段
+fn control_frame(control_type: u32, with_content_type: bool) -> Vec<u8> {
+    let mut control = control_type.to_be_bytes().to_vec();
+    if with_content_type {
+        control.extend_from_slice(&FSTRM_CONTROL_FIELD_CONTENT_TYPE.to_be_bytes());
+        control.extend_from_slice(&(DNSTAP_CONTENT_TYPE.len() as u32).to_be_bytes());
+        control.extend_from_slice(DNSTAP_CONTENT_TYPE.as_bytes());
+    }
+
+    let mut frame = Vec::with_capacity(control.len() + 8);
+    frame.extend_from_slice(&0u32.to_be_bytes());
+    frame.extend_from_slice(&(control.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&control);
+    frame
+}
+
+// Unix 时间（秒，纳秒）
+fn unix_time(time: SystemTime) -> (u64, u32) {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (duration.as_secs(), duration.subsec_nanos())
+}
+
+// Protobuf 编码：varint
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+// Protobuf 编码：varint 字段（wire type 0）
+fn put_varint_field(buf: &mut Vec<u8>, field: u32, value: u64) {
+    put_varint(buf, (field as u64) << 3);
+    put_varint(buf, value);
+}
+
+// Protobuf 编码：length-delimited 字段（wire type 2）
+fn put_bytes_field(buf: &mut Vec<u8>, field: u32, value: &[u8]) {
+    put_varint(buf, ((field as u64) << 3) | 2);
+    put_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+// Protobuf 编码：fixed32 字段（wire type 5）
+fn put_fixed32_field(buf: &mut Vec<u8>, field: u32, value: u32) {
+    put_varint(buf, ((field as u64) << 3) | 5);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
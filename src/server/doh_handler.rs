@@ -2,35 +2,63 @@
 
 use std::net::IpAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
 use axum::{
     extract::{Query, State},
-    http::{header, StatusCode, Request},
-    response::IntoResponse,
+    http::{header, HeaderName, HeaderValue, StatusCode, Request},
+    middleware,
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Router as AxumRouter, Json,
+    Router as AxumRouter,
 };
 use axum::body::to_bytes;
 use serde::{Deserialize, Serialize};
 use tokio::time::Instant;
-use hickory_proto::op::{Message, MessageType, OpCode, ResponseCode};
-use hickory_proto::rr::{DNSClass, Name, RecordType};
-use tracing::{debug, info};
+use futures::stream::{self, StreamExt};
+use hickory_proto::op::{Edns, Message, MessageType, OpCode, ResponseCode};
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_proto::rr::dnssec::rdata::DNSSECRData;
+use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+use hickory_proto::rr::rdata::HINFO;
+use tracing::{debug, info, warn};
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64_ENGINE};
 use crate::server::error::{ServerError, Result};
 use crate::common::consts::{
     CONTENT_TYPE_DNS_JSON, 
     CONTENT_TYPE_DNS_MESSAGE,
+    CONTENT_TYPE_JSON,
     DNS_RECORD_TYPE_A, DNS_CLASS_IN, IP_HEADER_NAMES,
-    MAX_REQUEST_SIZE,
+    MAX_REQUEST_SIZE, MULTI_QUESTION_MAX_CONCURRENCY,
     DOH_JSON_API_PATH, DOH_STANDARD_PATH,
     DOH_FORMAT_JSON, DOH_FORMAT_WIRE,
+    EDE_INFO_CODE_BLOCKED, EDE_INFO_CODE_FILTERED, EDE_INFO_CODE_NO_REACHABLE_AUTHORITY, EDE_INFO_CODE_OTHER, EDE_INFO_CODE_PROHIBITED, EDE_INFO_CODE_STALE_ANSWER,
+    HEADER_BLOCK_REASON, BLOCK_REASON_DEFAULT_ACTION, BLOCK_REASON_RULE_MATCH, HEADER_UPSTREAM_GROUP_OVERRIDE,
+    ANY_HINFO_CPU, ANY_HINFO_TTL, EDNS_PROBE_MAX_PAYLOAD, EDNS_SUPPORTED_VERSION, BLACKHOLE_UPSTREAM_GROUP_NAME, EDNS_NSID_OPTION_CODE, EDNS_PADDING_OPTION_CODE, CACHE_NAMESPACE_GROUP_PREFIX,
 };
-use crate::server::cache::{CacheKey, DnsCache};
-use crate::server::config::ServerConfig;
+use crate::server::admin::constant_time_eq;
+use crate::server::blackhole::IpCidr;
+use crate::server::cache::{CacheKey, CachedJson, DnsCache};
+use crate::server::cname::flatten_cname;
+use crate::server::ddr::DdrResponder;
+use crate::server::trust_anchor::TrustAnchorResponder;
+use crate::server::config::{
+    AnswerSort, AnswerTtlFloorConfig, AnyQueryPolicy, EdnsProbePolicy, GetAcceptPolicy, RoutingProfile, ServerConfig, TruncatedQueryPolicy, TtlFloorAction,
+};
+use crate::server::duplicate::DuplicateQueryDetector;
 use crate::server::routing::{RouteDecision, Router as DnsRouter};
 use crate::server::upstream::{UpstreamManager, UpstreamSelection};
 use crate::server::ecs::{EcsProcessor};
-use crate::server::metrics::METRICS;
+use crate::server::ede::ExtendedError;
+use crate::server::metrics::{query_type_label, METRICS};
+use crate::server::query_log::QueryLogger;
+use crate::server::dnstap::DnstapLogger;
+use crate::server::plugin::ResponsePlugin;
+use crate::server::security::DailyQueryQuota;
+use crate::server::query_limits::{check_json_request_limits, check_query_limits, QueryLimitViolation};
+use crate::server::special_names::respond_special_name;
+use crate::server::local_records::respond_local_record;
+use crate::server::loop_guard::{reject_forwarding_loops, respond_self_name};
 
 // HTTP 方法常量
 const HTTP_METHOD_GET: &str = "GET";
@@ -42,22 +70,71 @@ const DNS_EVENT_PARAMETER_ERROR: &str = "parameter_error";
 const DNS_EVENT_PROCESSING_FAILED: &str = "processing_failed";
 const DNS_EVENT_PARSE_ERROR: &str = "parse_error";
 const DNS_EVENT_BASE64_DECODE_ERROR: &str = "base64_decode_error";
+const DNS_EVENT_SERIALIZE_ERROR: &str = "serialize_error";
+const DNS_EVENT_INVALID_CONTENT_TYPE: &str = "invalid_content_type";
+const DNS_EVENT_READ_ERROR: &str = "read_error";
+const DNS_EVENT_REQUEST_TOO_LARGE: &str = "request_too_large";
+const DNS_EVENT_NOT_ACCEPTABLE: &str = "not_acceptable";
 
 // DNS 查询类型常量
 const DNS_QUERY_TYPE_UNKNOWN: &str = "Unknown";
 
 // DNS 响应相关常量
-const DNS_RESPONSE_NXDOMAIN_BLACKHOLE: &str = "NXDomain_Blackhole";
+const DNS_RESPONSE_BLACKHOLE_SUFFIX: &str = "_Blackhole";
+
+// 应答修改类型标签值
+const RESPONSE_MODIFIED_BLACKHOLE: &str = "blackhole";
+const RESPONSE_MODIFIED_REFUSED: &str = "refused";
+const RESPONSE_MODIFIED_CNAME_FLATTEN: &str = "cname_flatten";
+const RESPONSE_MODIFIED_FILTERED: &str = "filtered";
+const RESPONSE_MODIFIED_TTL_REWRITTEN: &str = "ttl_rewritten";
+const RESPONSE_MODIFIED_PLUGIN: &str = "plugin";
+const RESPONSE_MODIFIED_ECS_STRIPPED: &str = "ecs_stripped";
+const RESPONSE_MODIFIED_ANSWERS_LIMITED: &str = "answers_limited";
+const RESPONSE_MODIFIED_ANY_HINFO: &str = "any_hinfo";
+
+// 全局上游在查询日志中的组名
+const UPSTREAM_GROUP_GLOBAL: &str = "global";
+
+// 调试详情日志采样计数器
+static DETAIL_LOG_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// 生成请求关联 ID（16 位十六进制），附加到单次请求的全部日志上
+pub fn next_request_id() -> String {
+    format!("{:016x}", fastrand::u64(..))
+}
+
+// 按采样率决定是否记录详情日志：每 round(1 / rate) 次记录一次，结果确定且均匀
+pub fn sample_detail_log(counter: &AtomicU64, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    
+    let every = (1.0 / rate).round() as u64;
+    counter.fetch_add(1, Ordering::Relaxed).is_multiple_of(every)
+}
 
 // 路由结果常量
 const ROUTE_RESULT_RULE_MATCH: &str = "rule_match";
 const ROUTE_RESULT_BLACKHOLE: &str = "blackhole";  
 const ROUTE_RESULT_DEFAULT: &str = "default";
+const ROUTE_RESULT_REFUSED: &str = "refused";
+
+// EDE 附加文本常量
+const EDE_TEXT_BLOCKED: &str = "Blocked by routing rule";
+const EDE_TEXT_FILTERED: &str = "Domain not allowed by routing policy";
+const EDE_TEXT_QUOTA_EXCEEDED: &str = "Daily query quota exceeded";
+const EDE_TEXT_UPSTREAM_FAILURE: &str = "Upstream resolvers unreachable";
+const EDE_TEXT_STALE_ANSWER: &str = "Served from expired cache after upstream failure";
 
 // 错误消息常量
 const ERROR_INVALID_DNS_MESSAGE: &str = "Invalid DNS message format";
 const ERROR_INVALID_BASE64: &str = "Invalid base64 encoding";
 const ERROR_SERIALIZE_RESPONSE: &str = "Failed to serialize DNS response";
+const ERROR_NOT_ACCEPTABLE: &str = "Accept header does not allow application/dns-message";
 const ERROR_INVALID_CONTENT_TYPE: &str = "Invalid content type";
 const ERROR_REQUEST_TOO_LARGE: &str = "Request body too large";
 const ERROR_READ_REQUEST_BODY: &str = "Failed to read request body";
@@ -73,6 +150,71 @@ pub struct ServerState {
     pub router: Arc<DnsRouter>,
     // DNS 缓存
     pub cache: Arc<DnsCache>,
+    // 重复查询检测器
+    pub duplicates: Arc<DuplicateQueryDetector>,
+    // 路由配置档案（通过档案路径前缀访问时设置）
+    pub profile: Option<RoutingProfile>,
+    // 查询日志
+    pub query_log: Arc<QueryLogger>,
+    // dnstap 查询/应答日志
+    pub dnstap: Arc<DnstapLogger>,
+    // 每个 IP 的每日查询配额
+    pub quota: Arc<DailyQueryQuota>,
+    // DDR 应答器，未启用时为 None
+    pub ddr: Option<Arc<DdrResponder>>,
+    // 本地信任锚应答器，未启用时为 None
+    pub trust_anchor: Option<Arc<TrustAnchorResponder>>,
+    // 应答修改插件，未启用时为 None
+    pub plugin: Option<Arc<ResponsePlugin>>,
+    // 允许指定上游组的客户端网段（启动时解析）
+    pub group_override_cidrs: Arc<Vec<IpCidr>>,
+}
+
+// 单个请求的上下文，随查询逐层传递，不保存在共享状态中
+struct RequestContext {
+    // 客户端 IP（可来自受信任的代理请求头）
+    client_ip: IpAddr,
+    // 传输层的源 IP（不可由客户端通过请求头伪造）
+    peer_ip: IpAddr,
+    // 请求头指定的上游组（仅对当前请求生效）
+    group_override: Option<String>,
+    // HTTP 方法
+    method: &'static str,
+    // HTTP 版本
+    http_version: String,
+    // 请求大小（查询字符串或请求体的字节数）
+    request_size: usize,
+    // 开始时间
+    start: Instant,
+}
+
+impl RequestContext {
+    // 从请求中提取上下文并记录开始时间
+    fn from_request<T>(state: &ServerState, req: &Request<T>, method: &'static str) -> Self {
+        Self {
+            client_ip: get_client_ip_from_request(req),
+            peer_ip: get_peer_ip_from_request(req),
+            group_override: upstream_group_override(state, req),
+            method,
+            http_version: format!("{:?}", req.version()),
+            request_size: req.uri().query().map_or(0, str::len),
+            start: Instant::now(),
+        }
+    }
+    
+    // 内部合成查询（健康探测等）的上下文
+    fn internal() -> Self {
+        let localhost = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        Self {
+            client_ip: localhost,
+            peer_ip: localhost,
+            group_override: None,
+            method: HTTP_METHOD_GET,
+            http_version: String::new(),
+            request_size: 0,
+            start: Instant::now(),
+        }
+    }
 }
 
 // DNS-over-HTTPS JSON 请求参数
@@ -99,6 +241,9 @@ pub struct DnsJsonRequest {
 pub struct DnsMsgGetRequest {
     // DNS 请求的 Base64url 编码
     pub dns: String,
+    // 客户端附加的随机填充参数，用于隐藏请求长度，服务端忽略其内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_padding: Option<String>,
 }
 
 // DNS-over-HTTPS JSON 响应格式
@@ -154,39 +299,136 @@ pub struct DnsJsonAnswer {
 
 // 创建 DoH 路由
 pub fn doh_routes(state: ServerState) -> AxumRouter {
-    AxumRouter::new()
+    let loop_protection = state.config.dns.loop_protection.enabled;
+    let router = AxumRouter::new()
         // JSON API 路由（兼容性）
-        .route(DOH_JSON_API_PATH, get(handle_dns_json_query))
+        .route(DOH_JSON_API_PATH, get(handle_dns_json_query).post(handle_dns_json_post))
         // RFC 8484 标准路由
         .route(DOH_STANDARD_PATH, get(handle_dns_wire_get))
-        .route(DOH_STANDARD_PATH, post(handle_dns_wire_post))
-        // 添加状态
-        .with_state(state)
+        .route(DOH_STANDARD_PATH, post(handle_dns_wire_post));
+    
+    // 拒绝经 DoH 上游转发回自身的请求
+    let router = if loop_protection {
+        router.route_layer(middleware::from_fn(reject_forwarding_loops))
+    } else {
+        router
+    };
+    
+    // 启用时为 DoH 应答附加 Alt-Svc 头，通告 HTTP/3 等替代服务
+    let router = match HeaderValue::from_str(&state.config.http.advertise_alt_svc) {
+        Ok(alt_svc) if !alt_svc.is_empty() => router.layer(middleware::map_response(move |mut response: Response| {
+            let alt_svc = alt_svc.clone();
+            async move {
+                response.headers_mut().insert(header::ALT_SVC, alt_svc);
+                response
+            }
+        })),
+        _ => router,
+    };
+    
+    // 添加状态
+    router.with_state(state)
 }
 
 // 处理 DNS JSON 查询 (GET 请求，application/dns-json 兼容格式)
 #[axum::debug_handler]
+#[tracing::instrument(name = "doh_request", skip_all, fields(request_id = %next_request_id()))]
 async fn handle_dns_json_query(
     State(state): State<ServerState>,
     Query(params): Query<DnsJsonRequest>,
     req: Request<axum::body::Body>,
 ) -> impl IntoResponse {
-    // 提取客户端 IP
-    let client_ip = get_client_ip_from_request(&req);
+    // 提取请求上下文（客户端 IP、开始时间等）
+    let ctx = RequestContext::from_request(&state, &req, HTTP_METHOD_GET);
     
-    // 记录开始时间
-    let start = Instant::now();
+    answer_json_query(&state, &params, &ctx).await
+}
+
+// 处理 DNS JSON POST 查询（请求体为 DnsJsonRequest 格式的 JSON），避免大查询受 URL 长度限制
+#[axum::debug_handler]
+#[tracing::instrument(name = "doh_request", skip_all, fields(request_id = %next_request_id()))]
+async fn handle_dns_json_post(
+    State(state): State<ServerState>,
+    req: Request<axum::body::Body>,
+) -> impl IntoResponse {
+    // 提取请求上下文（客户端 IP、开始时间等）
+    let mut ctx = RequestContext::from_request(&state, &req, HTTP_METHOD_POST);
+    let client_ip = ctx.client_ip;
+    
+    debug!(client_ip = ?client_ip, "DNS JSON POST request received");
     
+    // 验证内容类型
+    let is_valid_content_type = req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|s| s.starts_with(CONTENT_TYPE_DNS_JSON) || s.starts_with(CONTENT_TYPE_JSON));
+    if !is_valid_content_type {
+        info!(client_ip = ?client_ip, "Invalid content type for DNS JSON POST request");
+        return reject_json_post(&ctx, StatusCode::UNSUPPORTED_MEDIA_TYPE, DNS_EVENT_INVALID_CONTENT_TYPE, ERROR_INVALID_CONTENT_TYPE.to_string());
+    }
+    
+    // 读取并解析请求体
+    let body_bytes = match to_bytes(req.into_body(), MAX_REQUEST_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            info!(client_ip = ?client_ip, error = %e, "Failed to read DNS JSON POST request body");
+            return reject_json_post(&ctx, StatusCode::BAD_REQUEST, DNS_EVENT_READ_ERROR, ERROR_READ_REQUEST_BODY.to_string());
+        },
+    };
+    METRICS.http_request_bytes()
+        .with_label_values(&[HTTP_METHOD_POST, DOH_JSON_API_PATH])
+        .observe(body_bytes.len() as f64);
+    
+    let params: DnsJsonRequest = match serde_json::from_slice(&body_bytes) {
+        Ok(params) => params,
+        Err(e) => {
+            info!(client_ip = ?client_ip, error = %e, "Invalid DNS JSON POST request body");
+            return reject_json_post(&ctx, StatusCode::BAD_REQUEST, DNS_EVENT_PARAMETER_ERROR, format!("Invalid JSON request body: {}", e));
+        },
+    };
+    
+    ctx.request_size = body_bytes.len();
+    answer_json_query(&state, &params, &ctx).await
+}
+
+// 拒绝无法解析的 JSON POST 请求并记录指标
+fn reject_json_post(ctx: &RequestContext, status: StatusCode, event: &str, error_body: String) -> Response {
+    let path = DOH_JSON_API_PATH;
+    let format = DOH_FORMAT_JSON;
+    METRICS.http_requests_total()
+        .with_label_values(&[HTTP_METHOD_POST, path, &status.as_u16().to_string(), format, &ctx.http_version])
+        .inc();
+    METRICS.http_request_duration_seconds()
+        .with_label_values(&[HTTP_METHOD_POST, path, format])
+        .observe(ctx.start.elapsed().as_secs_f64());
+    METRICS.dns_queries_total()
+        .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, event, format])
+        .inc();
+    METRICS.http_response_bytes()
+        .with_label_values(&[HTTP_METHOD_POST, path])
+        .observe(error_body.len() as f64);
+    
+    (status, error_body).into_response()
+}
+
+// 解析 JSON 查询参数并返回 JSON 应答（GET 与 POST 共用）
+async fn answer_json_query(
+    state: &ServerState,
+    params: &DnsJsonRequest,
+    ctx: &RequestContext,
+) -> Response {
+    let RequestContext { client_ip, method, start, .. } = *ctx;
+    let http_version = ctx.http_version.as_str();
     // 相关指标 - 预先提取为常量，避免重复创建
     let path = DOH_JSON_API_PATH;
     let format = DOH_FORMAT_JSON;
-    let http_version = format!("{:?}", req.version());
-    let method = HTTP_METHOD_GET;
     
     debug!(name = %params.name, type_value = params.type_value, client_ip = ?client_ip, "DNS JSON query received");
     
-    // 创建 DNS 查询消息
-    let query_message = match create_dns_message_from_json_request(&params) {
+    // 检查请求大小、域名长度与查询类型后创建 DNS 查询消息
+    let query_message = match check_json_request_limits(&state.config.dns.query_limits, ctx.request_size, &params.name, params.type_value)
+        .and_then(|_| create_dns_message_from_json_request(params))
+    {
         Ok(msg) => msg,
         Err(e) => {
             // 记录请求错误
@@ -207,7 +449,7 @@ async fn handle_dns_json_query(
             // 记录指标
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[method, path, &status_str, format, &http_version])
+                    .with_label_values(&[method, path, &status_str, format, http_version])
                     .inc();
                 
                 // 记录请求持续时间
@@ -218,7 +460,7 @@ async fn handle_dns_json_query(
                 
                 // 记录DNS查询错误
                 METRICS.dns_queries_total()
-                    .with_label_values(&[&params.type_value.to_string(), DNS_EVENT_PARAMETER_ERROR])
+                    .with_label_values(&[&query_type_label(RecordType::from(params.type_value)), DNS_EVENT_PARAMETER_ERROR, format])
                     .inc();
                 
                 METRICS.http_response_bytes()
@@ -233,14 +475,14 @@ async fn handle_dns_json_query(
     
     // 记录DNS查询类型 - 提前计算一次，避免重复计算
     let query_type = if let Some(q) = query_message.queries().first() {
-        format!("{:?}", q.query_type())
+        query_type_label(q.query_type())
     } else {
         DNS_QUERY_TYPE_UNKNOWN.to_string()
     };
     
     {
         METRICS.dns_queries_total()
-            .with_label_values(&[&query_type, DNS_EVENT_RECEIVED])
+            .with_label_values(&[&query_type, DNS_EVENT_RECEIVED, format])
             .inc();
         
         METRICS.dns_query_type_total()
@@ -249,14 +491,12 @@ async fn handle_dns_json_query(
     }
     
     // 发送/接收 DNS 查询响应
-    let (response_message, is_cached) = match process_query(
-        state.upstream.as_ref(),
-        state.router.as_ref(),
-        state.cache.as_ref(),
+    let (response_message, is_cached, block_reason, cached_json) = match process_query_with_json(
+        state,
         &query_message,
-        client_ip,
+        ctx,
     ).await {
-        Ok((msg, cached)) => (msg, cached),
+        Ok(result) => result,
         Err(e) => {
             // 记录处理错误
             info!(
@@ -276,7 +516,7 @@ async fn handle_dns_json_query(
             // 记录指标
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[method, path, &status_str, format, &http_version])
+                    .with_label_values(&[method, path, &status_str, format, http_version])
                     .inc();
                 
                 // 记录请求持续时间
@@ -287,7 +527,7 @@ async fn handle_dns_json_query(
                 
                 // 记录DNS查询错误
                 METRICS.dns_queries_total()
-                    .with_label_values(&[&query_type, DNS_EVENT_PROCESSING_FAILED])
+                    .with_label_values(&[&query_type, DNS_EVENT_PROCESSING_FAILED, format])
                     .inc();
                 
                 METRICS.http_response_bytes()
@@ -300,52 +540,68 @@ async fn handle_dns_json_query(
         }
     };
     
-    // 转换为 JSON 响应
-    let json_response = match dns_message_to_json_response(&response_message) {
-        Ok(resp) => resp,
-        Err(e) => {
-            // 记录响应转换错误
-            info!(
-                name = %params.name,
-                type_value = params.type_value,
-                client_ip = ?client_ip,
-                error = %e,
-                "DNS-over-HTTPS response conversion failed"
-            );
-            
-            // 记录错误状态码 - 提前计算一次，重复使用
-            let status = StatusCode::INTERNAL_SERVER_ERROR;
-            let status_str = status.as_u16().to_string();
-            let error_body = e.to_string();
-            let error_body_len = error_body.len() as f64;
-            
-            // 记录指标
-            {
-                METRICS.http_requests_total()
-                    .with_label_values(&[method, path, &status_str, format, &http_version])
-                    .inc();
+    // 转换为 JSON 响应：启用 JSON 应答缓存且缓存条目已有序列化结果时直接复用，否则转换并序列化
+    let cached_body = cached_json.as_ref().and_then(|slot| slot.get().cloned());
+    let response_body = match cached_body {
+        Some(body) => body.to_vec(),
+        None => match dns_message_to_json_response(&response_message)
+            .and_then(|json_response| serde_json::to_vec(&json_response).map_err(|e| ServerError::Other(e.to_string())))
+        {
+            Ok(body) => {
+                if let Some(slot) = &cached_json {
+                    let _ = slot.set(Arc::from(body.as_slice()));
+                }
+                body
+            },
+            Err(e) => {
+                // 记录响应转换错误
+                info!(
+                    name = %params.name,
+                    type_value = params.type_value,
+                    client_ip = ?client_ip,
+                    error = %e,
+                    "DNS-over-HTTPS response conversion failed"
+                );
                 
-                // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
-                METRICS.http_request_duration_seconds()
-                    .with_label_values(&[method, path, format])
-                    .observe(duration);
+                // 记录错误状态码 - 提前计算一次，重复使用
+                let status = StatusCode::INTERNAL_SERVER_ERROR;
+                let status_str = status.as_u16().to_string();
+                let error_body = e.to_string();
+                let error_body_len = error_body.len() as f64;
                 
-                METRICS.http_response_bytes()
-                    .with_label_values(&[method, path])
-                    .observe(error_body_len);
+                // 记录指标
+                {
+                    METRICS.http_requests_total()
+                        .with_label_values(&[method, path, &status_str, format, http_version])
+                        .inc();
+                
+                    // 记录请求持续时间
+                    let duration = start.elapsed().as_secs_f64();
+                    METRICS.http_request_duration_seconds()
+                        .with_label_values(&[method, path, format])
+                        .observe(duration);
+                
+                    // 记录DNS查询错误
+                    METRICS.dns_queries_total()
+                        .with_label_values(&[&query_type, DNS_EVENT_SERIALIZE_ERROR, format])
+                        .inc();
+                
+                    METRICS.http_response_bytes()
+                        .with_label_values(&[method, path])
+                        .observe(error_body_len);
+                }
+                
+                // 返回错误响应
+                return (status, error_body).into_response();
             }
-            
-            // 返回错误响应
-            return (status, error_body).into_response();
-        }
+        },
     };
     
     // 计算持续时间
     let duration = start.elapsed();
     
     // 记录请求完成的详细日志
-    let answer_count = json_response.answer.len();
+    let answer_count = response_message.answers().len();
     let rcode = response_message.response_code();
     let query_time_ms = duration.as_millis();
     
@@ -361,12 +617,17 @@ async fn handle_dns_json_query(
         "DNS-over-HTTPS request completed"
     );
     
-    // 只在调试级别时记录详细记录信息，减少运行时开销
-    if !json_response.answer.is_empty() && tracing::enabled!(tracing::Level::DEBUG) {
+    // 只在调试级别时按采样率记录详细记录信息，减少运行时开销
+    if answer_count > 0
+        && tracing::enabled!(tracing::Level::DEBUG)
+        && sample_detail_log(&DETAIL_LOG_COUNTER, state.config.dns.log_sample_rate)
+    {
         // 使用迭代器和预分配容量优化字符串收集
-        let mut record_details = Vec::with_capacity(json_response.answer.len());
-        for ans in &json_response.answer {
-            record_details.push(format!("{}({}): {}", ans.name, RecordType::from(ans.type_value), ans.data));
+        let mut record_details = Vec::with_capacity(answer_count);
+        for record in response_message.answers() {
+            if let Some(rdata) = record.data() {
+                record_details.push(format!("{}({}): {}", record.name().to_utf8(), record.record_type(), rdata));
+            }
         }
             
         debug!(
@@ -381,7 +642,7 @@ async fn handle_dns_json_query(
     let status = StatusCode::OK.as_u16().to_string();
     {
         METRICS.http_requests_total()
-            .with_label_values(&[method, path, &status, format, &http_version])
+            .with_label_values(&[method, path, &status, format, http_version])
             .inc();
         
         // 记录请求持续时间
@@ -395,18 +656,16 @@ async fn handle_dns_json_query(
             .inc();
     }
     
-    // 准备JSON响应
-    let json_response_body = Json(json_response.clone());
-    
-    // 提前计算响应大小估计，避免后续借用被移动的值
-    let response_size_estimate = serde_json::to_string(&json_response).map(|s| s.len()).unwrap_or(0);
+    // 响应大小即序列化后的正文长度
+    let response_size_estimate = response_body.len();
     
     // 返回 JSON 响应
-    let response = (
+    let mut response = (
         StatusCode::OK,
         [(header::CONTENT_TYPE, CONTENT_TYPE_DNS_JSON)],
-        json_response_body,
+        response_body,
     ).into_response();
+    attach_block_reason_header(&mut response, &state.config, block_reason);
     
     // 记录响应大小
     {
@@ -420,26 +679,58 @@ async fn handle_dns_json_query(
 
 // 处理 DNS GET 请求（RFC 8484）
 #[axum::debug_handler]
+#[tracing::instrument(name = "doh_request", skip_all, fields(request_id = %next_request_id()))]
 async fn handle_dns_wire_get(
     State(state): State<ServerState>,
     Query(params): Query<DnsMsgGetRequest>,
     req: Request<axum::body::Body>,
 ) -> impl IntoResponse {
-    // 提取客户端 IP
-    let client_ip = get_client_ip_from_request(&req);
-    
-    // 记录开始时间
-    let start = Instant::now();
+    // 提取请求上下文（客户端 IP、开始时间等）
+    let ctx = RequestContext::from_request(&state, &req, HTTP_METHOD_GET);
+    let client_ip = ctx.client_ip;
     
     // 记录请求指标
     let path = DOH_STANDARD_PATH;
-    let format = DOH_FORMAT_WIRE;
-    let http_version = format!("{:?}", req.version());
+    let get_accept = state.config.http.get_accept;
 
     debug!(client_ip = ?client_ip, "DNS-over-HTTPS GET request received");
     
-    // 解码请求参数中的 DNS 消息（Base64url 编码）
-    let query_message = match BASE64_ENGINE.decode(&params.dns) {
+    // 按 Accept 头与策略确定应答格式
+    let Some(format) = negotiate_get_format(&req, get_accept) else {
+        info!(
+            client_ip = ?client_ip,
+            accept = ?req.headers().get(header::ACCEPT),
+            "DNS-over-HTTPS GET request does not accept the wire format"
+        );
+        
+        // 记录错误状态
+        let status = StatusCode::NOT_ACCEPTABLE.as_u16().to_string();
+        {
+            METRICS.http_requests_total()
+                .with_label_values(&[HTTP_METHOD_GET, path, &status, DOH_FORMAT_WIRE, &ctx.http_version])
+                .inc();
+            
+            // 记录请求持续时间
+            let duration = ctx.start.elapsed().as_secs_f64();
+            METRICS.http_request_duration_seconds()
+                .with_label_values(&[HTTP_METHOD_GET, path, DOH_FORMAT_WIRE])
+                .observe(duration);
+            
+            // 记录DNS查询错误
+            METRICS.dns_queries_total()
+                .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_NOT_ACCEPTABLE, DOH_FORMAT_WIRE])
+                .inc();
+            
+            METRICS.http_response_bytes()
+                .with_label_values(&[HTTP_METHOD_GET, path])
+                .observe(ERROR_NOT_ACCEPTABLE.len() as f64);
+        }
+        
+        return (StatusCode::NOT_ACCEPTABLE, ERROR_NOT_ACCEPTABLE).into_response();
+    };
+    
+    // 解码请求参数中的 DNS 消息（Base64url 编码），同时检查查询限制
+    let (query_message, limit_violation) = match BASE64_ENGINE.decode(&params.dns) {
         Ok(data) => {
             // 记录请求大小
             {
@@ -448,9 +739,12 @@ async fn handle_dns_wire_get(
                     .observe(data.len() as f64);
             }
             
-            match Message::from_vec(&data) {
-                Ok(msg) => msg,
-                Err(e) => {
+            // 名称超长的查询无法解析，违反查询限制时以报文头构建查询并返回 FORMERR
+            let limit_violation = check_query_limits(&state.config.dns.query_limits, &data);
+            match (Message::from_vec(&data), limit_violation) {
+                (Ok(msg), violation) => (msg, violation),
+                (Err(_), Some(violation)) => (header_only_query(&data), Some(violation)),
+                (Err(e), None) => {
                     info!(
                         client_ip = ?client_ip,
                         error = %e,
@@ -461,18 +755,18 @@ async fn handle_dns_wire_get(
                     let status = StatusCode::BAD_REQUEST.as_u16().to_string();
                     {
                         METRICS.http_requests_total()
-                            .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &http_version])
+                            .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &ctx.http_version])
                             .inc();
                         
                         // 记录请求持续时间
-                        let duration = start.elapsed().as_secs_f64();
+                        let duration = ctx.start.elapsed().as_secs_f64();
                         METRICS.http_request_duration_seconds()
                             .with_label_values(&[HTTP_METHOD_GET, path, format])
                             .observe(duration);
                         
                         // 记录DNS查询错误
                         METRICS.dns_queries_total()
-                            .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_PARSE_ERROR])
+                            .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_PARSE_ERROR, format])
                             .inc();
                     }
                     
@@ -502,18 +796,18 @@ async fn handle_dns_wire_get(
             let status = StatusCode::BAD_REQUEST.as_u16().to_string();
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &http_version])
+                    .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &ctx.http_version])
                     .inc();
                 
                 // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
+                let duration = ctx.start.elapsed().as_secs_f64();
                 METRICS.http_request_duration_seconds()
                     .with_label_values(&[HTTP_METHOD_GET, path, format])
                     .observe(duration);
                 
                 // 记录DNS查询错误
                 METRICS.dns_queries_total()
-                    .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_BASE64_DECODE_ERROR])
+                    .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_BASE64_DECODE_ERROR, format])
                     .inc();
             }
             
@@ -540,14 +834,14 @@ async fn handle_dns_wire_get(
     
     // 记录DNS查询类型
     let query_type = if let Some(q) = query_message.queries().first() {
-        format!("{:?}", q.query_type())
+        query_type_label(q.query_type())
     } else {
         DNS_QUERY_TYPE_UNKNOWN.to_string()
     };
     
     {
         METRICS.dns_queries_total()
-            .with_label_values(&[&query_type, DNS_EVENT_RECEIVED])
+            .with_label_values(&[&query_type, DNS_EVENT_RECEIVED, format])
             .inc();
         
         METRICS.dns_query_type_total()
@@ -556,14 +850,13 @@ async fn handle_dns_wire_get(
     }
     
     // 处理查询
-    let (response_message, is_cached) = match process_query(
-        state.upstream.as_ref(),
-        state.router.as_ref(),
-        state.cache.as_ref(),
+    let (response_message, is_cached, block_reason) = match process_wire_query(
+        &state,
         &query_message,
-        client_ip,
+        limit_violation,
+        &ctx,
     ).await {
-        Ok((msg, cached)) => (msg, cached),
+        Ok(result) => result,
         Err(e) => {
             info!(
                 domain = %domain,
@@ -576,18 +869,18 @@ async fn handle_dns_wire_get(
             let status = StatusCode::INTERNAL_SERVER_ERROR.as_u16().to_string();
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &http_version])
+                    .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &ctx.http_version])
                     .inc();
                 
                 // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
+                let duration = ctx.start.elapsed().as_secs_f64();
                 METRICS.http_request_duration_seconds()
                     .with_label_values(&[HTTP_METHOD_GET, path, format])
                     .observe(duration);
                 
                 // 记录DNS查询错误
                 METRICS.dns_queries_total()
-                    .with_label_values(&[&query_type, DNS_EVENT_PROCESSING_FAILED])
+                    .with_label_values(&[&query_type, DNS_EVENT_PROCESSING_FAILED, format])
                     .inc();
             }
             
@@ -606,8 +899,14 @@ async fn handle_dns_wire_get(
         }
     };
     
-    // 将响应消息转换为二进制格式
-    let response_bytes = match response_message.to_vec() {
+    // 将响应消息转换为协商的格式（wire 或 JSON）
+    let serialized = if format == DOH_FORMAT_JSON {
+        dns_message_to_json_response(&response_message)
+            .and_then(|json_response| serde_json::to_vec(&json_response).map_err(|e| ServerError::Other(e.to_string())))
+    } else {
+        response_message.to_vec().map_err(ServerError::from)
+    };
+    let response_bytes = match serialized {
         Ok(bytes) => bytes,
         Err(e) => {
             info!(
@@ -621,14 +920,19 @@ async fn handle_dns_wire_get(
             let status = StatusCode::INTERNAL_SERVER_ERROR.as_u16().to_string();
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &http_version])
+                    .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &ctx.http_version])
                     .inc();
                 
                 // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
+                let duration = ctx.start.elapsed().as_secs_f64();
                 METRICS.http_request_duration_seconds()
                     .with_label_values(&[HTTP_METHOD_GET, path, format])
                     .observe(duration);
+                
+                // 记录DNS查询错误
+                METRICS.dns_queries_total()
+                    .with_label_values(&[&query_type, DNS_EVENT_SERIALIZE_ERROR, format])
+                    .inc();
             }
             
             // 返回错误响应
@@ -647,7 +951,7 @@ async fn handle_dns_wire_get(
     };
     
     // 计算持续时间
-    let duration = start.elapsed();
+    let duration = ctx.start.elapsed();
     
     // 记录请求完成
     let qtype = query_message.queries().first().map_or_else(
@@ -675,7 +979,7 @@ async fn handle_dns_wire_get(
     let status = StatusCode::OK.as_u16().to_string();
     {
         METRICS.http_requests_total()
-            .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &http_version])
+            .with_label_values(&[HTTP_METHOD_GET, path, &status, format, &ctx.http_version])
             .inc();
         
         // 记录请求持续时间
@@ -694,30 +998,66 @@ async fn handle_dns_wire_get(
             .observe(response_bytes.len() as f64);
     }
     
-    // 返回响应
-    (
+    // 返回响应，应答格式取决于 Accept 时附加 Vary 头
+    let content_type = if format == DOH_FORMAT_JSON { CONTENT_TYPE_DNS_JSON } else { CONTENT_TYPE_DNS_MESSAGE };
+    let mut response = (
         StatusCode::OK,
-        [(header::CONTENT_TYPE, CONTENT_TYPE_DNS_MESSAGE)],
+        [(header::CONTENT_TYPE, content_type)],
         response_bytes,
-    ).into_response()
+    ).into_response();
+    if get_accept != GetAcceptPolicy::Ignore {
+        response.headers_mut().insert(header::VARY, HeaderValue::from_static("accept"));
+    }
+    attach_block_reason_header(&mut response, &state.config, block_reason);
+    response
+}
+
+// 按 Accept 头与策略确定 GET 请求的应答格式，返回 None 表示应返回 406
+// Accept 缺失或接受 wire 格式（application/dns-message、application/*、*/*）时始终返回 wire 格式，
+// 否则按策略忽略 Accept（wire）、协商（接受 application/dns-json 时返回 JSON）或拒绝
+fn negotiate_get_format<T>(req: &Request<T>, policy: GetAcceptPolicy) -> Option<&'static str> {
+    if policy == GetAcceptPolicy::Ignore || !req.headers().contains_key(header::ACCEPT) {
+        return Some(DOH_FORMAT_WIRE);
+    }
+    
+    // 提取 q 值不为 0 的媒体类型
+    let accepted: Vec<String> = req.headers().get_all(header::ACCEPT).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media_type = parts.next()?.trim().to_ascii_lowercase();
+            let excluded = parts.any(|param| {
+                param.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0)
+            });
+            (!media_type.is_empty() && !excluded).then_some(media_type)
+        })
+        .collect();
+    
+    if accepted.iter().any(|media_type| matches!(media_type.as_str(), CONTENT_TYPE_DNS_MESSAGE | "application/*" | "*/*")) {
+        return Some(DOH_FORMAT_WIRE);
+    }
+    match policy {
+        GetAcceptPolicy::Negotiate if accepted.iter().any(|media_type| media_type == CONTENT_TYPE_DNS_JSON) => Some(DOH_FORMAT_JSON),
+        GetAcceptPolicy::Negotiate | GetAcceptPolicy::Ignore => Some(DOH_FORMAT_WIRE),
+        GetAcceptPolicy::Reject => None,
+    }
 }
 
 // 处理 DNS POST 请求（RFC 8484）
 #[axum::debug_handler]
+#[tracing::instrument(name = "doh_request", skip_all, fields(request_id = %next_request_id()))]
 async fn handle_dns_wire_post(
     State(state): State<ServerState>,
     req: Request<axum::body::Body>,
 ) -> impl IntoResponse {
-    // 提取客户端 IP
-    let client_ip = get_client_ip_from_request(&req);
-    
-    // 记录开始时间
-    let start = Instant::now();
+    // 提取请求上下文（客户端 IP、开始时间等）
+    let mut ctx = RequestContext::from_request(&state, &req, HTTP_METHOD_POST);
+    let client_ip = ctx.client_ip;
     
     // 记录请求指标
     let path = DOH_STANDARD_PATH;
     let format = DOH_FORMAT_WIRE;
-    let http_version = format!("{:?}", req.version());
     
     debug!(client_ip = ?client_ip, "DNS-over-HTTPS POST request received");
     
@@ -738,14 +1078,19 @@ async fn handle_dns_wire_post(
         let status = StatusCode::UNSUPPORTED_MEDIA_TYPE.as_u16().to_string();
         {
             METRICS.http_requests_total()
-                .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &http_version])
+                .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &ctx.http_version])
                 .inc();
             
             // 记录请求持续时间
-            let duration = start.elapsed().as_secs_f64();
+            let duration = ctx.start.elapsed().as_secs_f64();
             METRICS.http_request_duration_seconds()
                 .with_label_values(&[HTTP_METHOD_POST, path, format])
                 .observe(duration);
+            
+            // 记录DNS查询错误
+            METRICS.dns_queries_total()
+                .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_INVALID_CONTENT_TYPE, format])
+                .inc();
         }
         
         // 返回错误响应
@@ -785,14 +1130,19 @@ async fn handle_dns_wire_post(
             let status = StatusCode::BAD_REQUEST.as_u16().to_string();
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &http_version])
+                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &ctx.http_version])
                     .inc();
                 
                 // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
+                let duration = ctx.start.elapsed().as_secs_f64();
                 METRICS.http_request_duration_seconds()
                     .with_label_values(&[HTTP_METHOD_POST, path, format])
                     .observe(duration);
+                
+                // 记录DNS查询错误
+                METRICS.dns_queries_total()
+                    .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_READ_ERROR, format])
+                    .inc();
             }
             
             // 返回错误响应
@@ -823,14 +1173,19 @@ async fn handle_dns_wire_post(
         let status = StatusCode::PAYLOAD_TOO_LARGE.as_u16().to_string();
         {
             METRICS.http_requests_total()
-                .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &http_version])
+                .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &ctx.http_version])
                 .inc();
             
             // 记录请求持续时间
-            let duration = start.elapsed().as_secs_f64();
+            let duration = ctx.start.elapsed().as_secs_f64();
             METRICS.http_request_duration_seconds()
                 .with_label_values(&[HTTP_METHOD_POST, path, format])
                 .observe(duration);
+            
+            // 记录DNS查询错误
+            METRICS.dns_queries_total()
+                .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_REQUEST_TOO_LARGE, format])
+                .inc();
         }
         
         // 返回错误响应
@@ -847,10 +1202,15 @@ async fn handle_dns_wire_post(
         return response;
     }
     
-    // 解析 DNS 消息
-    let query_message = match Message::from_vec(&body_bytes) {
-        Ok(msg) => msg,
-        Err(e) => {
+    // 检查查询大小、EDNS OPT 选项数与问题节名称长度
+    ctx.request_size = body_bytes.len();
+    let limit_violation = check_query_limits(&state.config.dns.query_limits, &body_bytes);
+    
+    // 解析 DNS 消息，名称超长的查询无法解析，违反查询限制时以报文头构建查询并返回 FORMERR
+    let query_message = match (Message::from_vec(&body_bytes), limit_violation) {
+        (Ok(msg), _) => msg,
+        (Err(_), Some(_)) => header_only_query(&body_bytes),
+        (Err(e), None) => {
             info!(
                 client_ip = ?client_ip,
                 error = %e,
@@ -861,18 +1221,18 @@ async fn handle_dns_wire_post(
             let status = StatusCode::BAD_REQUEST.as_u16().to_string();
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &http_version])
+                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &ctx.http_version])
                     .inc();
                 
                 // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
+                let duration = ctx.start.elapsed().as_secs_f64();
                 METRICS.http_request_duration_seconds()
                     .with_label_values(&[HTTP_METHOD_POST, path, format])
                     .observe(duration);
                 
                 // 记录DNS查询错误
                 METRICS.dns_queries_total()
-                    .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_PARSE_ERROR])
+                    .with_label_values(&[DNS_QUERY_TYPE_UNKNOWN, DNS_EVENT_PARSE_ERROR, format])
                     .inc();
             }
             
@@ -899,14 +1259,14 @@ async fn handle_dns_wire_post(
     
     // 记录DNS查询类型
     let query_type = if let Some(q) = query_message.queries().first() {
-        format!("{:?}", q.query_type())
+        query_type_label(q.query_type())
     } else {
         DNS_QUERY_TYPE_UNKNOWN.to_string()
     };
     
     {
         METRICS.dns_queries_total()
-            .with_label_values(&[&query_type, DNS_EVENT_RECEIVED])
+            .with_label_values(&[&query_type, DNS_EVENT_RECEIVED, format])
             .inc();
         
         METRICS.dns_query_type_total()
@@ -915,14 +1275,13 @@ async fn handle_dns_wire_post(
     }
     
     // 处理查询
-    let (response_message, is_cached) = match process_query(
-        state.upstream.as_ref(),
-        state.router.as_ref(),
-        state.cache.as_ref(),
+    let (response_message, is_cached, block_reason) = match process_wire_query(
+        &state,
         &query_message,
-        client_ip,
+        limit_violation,
+        &ctx,
     ).await {
-        Ok((msg, cached)) => (msg, cached),
+        Ok(result) => result,
         Err(e) => {
             info!(
                 domain = %domain,
@@ -935,18 +1294,18 @@ async fn handle_dns_wire_post(
             let status = StatusCode::INTERNAL_SERVER_ERROR.as_u16().to_string();
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &http_version])
+                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &ctx.http_version])
                     .inc();
                 
                 // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
+                let duration = ctx.start.elapsed().as_secs_f64();
                 METRICS.http_request_duration_seconds()
                     .with_label_values(&[HTTP_METHOD_POST, path, format])
                     .observe(duration);
                 
                 // 记录DNS查询错误
                 METRICS.dns_queries_total()
-                    .with_label_values(&[&query_type, DNS_EVENT_PROCESSING_FAILED])
+                    .with_label_values(&[&query_type, DNS_EVENT_PROCESSING_FAILED, format])
                     .inc();
             }
             
@@ -980,14 +1339,19 @@ async fn handle_dns_wire_post(
             let status = StatusCode::INTERNAL_SERVER_ERROR.as_u16().to_string();
             {
                 METRICS.http_requests_total()
-                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &http_version])
+                    .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &ctx.http_version])
                     .inc();
                 
                 // 记录请求持续时间
-                let duration = start.elapsed().as_secs_f64();
+                let duration = ctx.start.elapsed().as_secs_f64();
                 METRICS.http_request_duration_seconds()
                     .with_label_values(&[HTTP_METHOD_POST, path, format])
                     .observe(duration);
+                
+                // 记录DNS查询错误
+                METRICS.dns_queries_total()
+                    .with_label_values(&[&query_type, DNS_EVENT_SERIALIZE_ERROR, format])
+                    .inc();
             }
             
             // 返回错误响应
@@ -1006,7 +1370,7 @@ async fn handle_dns_wire_post(
     };
     
     // 计算持续时间
-    let duration = start.elapsed();
+    let duration = ctx.start.elapsed();
     
     // 记录请求完成
     let qtype = query_message.queries().first().map_or_else(
@@ -1034,7 +1398,7 @@ async fn handle_dns_wire_post(
     let status = StatusCode::OK.as_u16().to_string();
     {
         METRICS.http_requests_total()
-            .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &http_version])
+            .with_label_values(&[HTTP_METHOD_POST, path, &status, format, &ctx.http_version])
             .inc();
         
         // 记录请求持续时间
@@ -1054,11 +1418,13 @@ async fn handle_dns_wire_post(
     }
     
     // 返回响应
-    (
+    let mut response = (
         StatusCode::OK,
         [(header::CONTENT_TYPE, CONTENT_TYPE_DNS_MESSAGE)],
         response_bytes,
-    ).into_response()
+    ).into_response();
+    attach_block_reason_header(&mut response, &state.config, block_reason);
+    response
 }
 
 // 从请求中提取客户端 IP
@@ -1079,25 +1445,775 @@ fn get_client_ip_from_request<T>(req: &Request<T>) -> IpAddr {
     }
     
     // 如果没有找到有效的 IP，使用传输层的源 IP
+    get_peer_ip_from_request(req)
+}
+
+// 从请求中提取传输层的源 IP（不可由客户端通过请求头伪造）
+fn get_peer_ip_from_request<T>(req: &Request<T>) -> IpAddr {
     match req.extensions().get::<axum::extract::ConnectInfo<std::net::SocketAddr>>() {
         Some(connect_info) => connect_info.ip(),
         None => std::net::IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1)), // 默认为本地回环
     }
 }
 
+// 读取请求头指定的上游组：仅在启用且客户端受信任（连接源地址位于 trusted_cidrs 内或携带有效管理令牌）时生效，
+// 受信任判断不使用 X-Forwarded-For 等可伪造的请求头
+fn upstream_group_override<T>(state: &ServerState, req: &Request<T>) -> Option<String> {
+    let config = &state.config.dns.routing.group_override;
+    if !state.config.dns.routing.enabled || !config.enabled {
+        return None;
+    }
+    let group = req.headers().get(HEADER_UPSTREAM_GROUP_OVERRIDE)?.to_str().ok()?.trim();
+    
+    let peer_ip = get_peer_ip_from_request(req);
+    let trusted_peer = state.group_override_cidrs.iter().any(|cidr| cidr.contains(peer_ip));
+    let admin = &state.config.http.admin;
+    let trusted_token = admin.enabled && req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), admin.token.as_bytes()));
+    if !trusted_peer && !trusted_token {
+        warn!(peer_ip = %peer_ip, group = %group, "Ignoring upstream group override from untrusted client");
+        return None;
+    }
+    
+    if !state.config.dns.routing.upstream_groups.iter().any(|g| g.name == group) {
+        warn!(peer_ip = %peer_ip, group = %group, "Ignoring override for unknown upstream group");
+        return None;
+    }
+    
+    info!(peer_ip = %peer_ip, group = %group, "Using upstream group override from request header");
+    Some(group.to_string())
+}
+
+// 为被阻止的查询附加阻止原因响应头
+fn attach_block_reason_header(response: &mut Response, config: &ServerConfig, block_reason: Option<&'static str>) {
+    if !config.dns.block_reason_header {
+        return;
+    }
+    if let Some(reason) = block_reason {
+        response.headers_mut().insert(
+            HeaderName::from_static(HEADER_BLOCK_REASON),
+            HeaderValue::from_static(reason),
+        );
+    }
+}
+
+// 经完整处理流程（路由、上游）解析内部合成查询，供健康探测等使用
+pub async fn resolve_internal_query(state: &ServerState, query_message: &Message) -> Result<Message> {
+    process_query(state, query_message, &RequestContext::internal()).await.map(|(response, _, _)| response)
+}
+
+// 处理线格式查询：超出查询限制（查询大小或 EDNS OPT 选项数）时直接返回 FORMERR
+async fn process_wire_query(
+    state: &ServerState,
+    query_message: &Message,
+    limit_violation: Option<QueryLimitViolation>,
+    ctx: &RequestContext,
+) -> Result<(Message, bool, Option<&'static str>)> {
+    let client_ip = ctx.client_ip;
+    if let Some(violation) = limit_violation {
+        METRICS.oversized_queries_rejected_total()
+            .with_label_values(&[violation.reason()])
+            .inc();
+        info!(
+            client_ip = ?client_ip,
+            query_id = query_message.id(),
+            reason = %violation,
+            "Rejecting client query exceeding query limits"
+        );
+        let response = build_formerr_response(query_message);
+        state.query_log.log(client_ip, query_message, &response, None, false);
+        state.dnstap.log(client_ip, query_message, &response, SystemTime::now());
+        return Ok((response, false, None));
+    }
+    
+    process_query(state, query_message, ctx).await
+}
+
 // 处理 DNS 查询
 async fn process_query(
-    upstream: &UpstreamManager,
+    state: &ServerState,
+    query_message: &Message,
+    ctx: &RequestContext,
+) -> Result<(Message, bool, Option<&'static str>)> {  // 返回元组，第二个参数表示是否缓存命中，第三个参数为阻止原因（未被阻止时为 None）
+    let (response, cached, block_reason, _) = process_query_with_json(state, query_message, ctx).await?;
+    Ok((response, cached, block_reason))
+}
+
+// 处理 DNS 查询，缓存命中时额外返回条目的 JSON 应答缓存槽位（启用 JSON 应答缓存时）
+async fn process_query_with_json(
+    state: &ServerState,
+    query_message: &Message,
+    ctx: &RequestContext,
+) -> Result<(Message, bool, Option<&'static str>, Option<CachedJson>)> {
+    let client_ip = ctx.client_ip;
+    let received_at = SystemTime::now();
+    
+    // 客户端查询不应设置 TC 位，按配置清除后继续处理或直接返回 FORMERR
+    let cleared_query;
+    let query_message = if query_message.truncated() {
+        match state.config.dns.truncated_query_policy {
+            TruncatedQueryPolicy::Clear => {
+                debug!(client_ip = %client_ip, query_id = query_message.id(), "Clearing TC bit set on client query");
+                let mut query = query_message.clone();
+                query.set_truncated(false);
+                cleared_query = query;
+                &cleared_query
+            },
+            TruncatedQueryPolicy::Formerr => {
+                debug!(client_ip = %client_ip, query_id = query_message.id(), "Rejecting client query with TC bit set");
+                let response = build_formerr_response(query_message);
+                state.query_log.log(client_ip, query_message, &response, None, false);
+                state.dnstap.log(client_ip, query_message, &response, received_at);
+                return Ok((response, false, None, None));
+            },
+        }
+    } else {
+        query_message
+    };
+    
+    // 客户端超出每日查询配额时直接返回 REFUSED（按连接源地址计数，仅信任受信任代理的请求头）
+    if !state.quota.consume(ctx.peer_ip, client_ip).await {
+        let mut response = build_refused_response(query_message);
+        if state.config.dns.emit_ede {
+            ExtendedError::new(EDE_INFO_CODE_PROHIBITED, EDE_TEXT_QUOTA_EXCEEDED)
+                .attach_to_message(&mut response);
+        }
+        strip_opt_for_non_edns_client(state, query_message, &mut response);
+        state.query_log.log(client_ip, query_message, &response, None, false);
+        state.dnstap.log(client_ip, query_message, &response, received_at);
+        return Ok((response, false, None, None));
+    }
+    
+    let QueryOutcome { mut response, cached, blocked, group, mut json, authoritative } = resolve_questions(state, query_message, client_ip, ctx.group_override.as_deref()).await?;
+    
+    // A/AAAA 应答来自上游时，在后台预取同名的另一地址类型（指定上游组的查询不预取）
+    if !cached && blocked.is_none() && ctx.group_override.is_none() && response.response_code() == ResponseCode::NoError {
+        spawn_companion_prefetch(state, query_message, client_ip);
+    }
+    
+    // 应答修改插件（作用于缓存条目的副本，缓存不受影响），修改后不再使用缓存的 JSON 应答
+    if let Some(plugin) = &state.plugin {
+        if plugin.apply(query_message, &mut response, client_ip) {
+            record_response_modified(&state.config, query_message, RESPONSE_MODIFIED_PLUGIN);
+            json = None;
+        }
+    }
+    
+    // 限制返回给客户端的应答记录数（响应为缓存条目的副本，缓存不受影响）
+    let max_answer_records = state.config.dns.max_answer_records;
+    if let Some(removed) = limit_answer_records(&mut response, max_answer_records) {
+        if state.config.dns.emit_ede {
+            ExtendedError::new(EDE_INFO_CODE_OTHER, format!("Answer section limited to {} records", max_answer_records))
+                .attach_to_message(&mut response);
+        }
+        record_response_modified(&state.config, query_message, RESPONSE_MODIFIED_ANSWERS_LIMITED);
+        debug!(client_ip = %client_ip, query_id = query_message.id(), removed, "Limited answer records");
+        json = None;
+    }
+    
+    // 限制返回给客户端的记录 TTL，避免下游长期缓存过期记录（缓存中的条目不受影响）
+    if cap_response_ttls(&mut response, state.config.dns.cache.ttl.response_max) {
+        record_response_modified(&state.config, query_message, RESPONSE_MODIFIED_TTL_REWRITTEN);
+        json = None;
+    }
+    
+    // 统一响应头标志：作为递归转发器 RA 始终置位；AA 仅对本地静态记录的应答保留
+    if state.config.dns.normalize_response_flags {
+        response.set_recursion_available(true)
+            .set_authoritative(authoritative);
+    }
+    
+    // 按地址族偏好重排应答记录（响应为缓存条目的副本，缓存不受影响）
+    sort_answers(&mut response, state.config.dns.answer_sort);
+    
+    // 移除上游回显的 ECS 选项，避免向客户端泄露子网作用域（缓存中的条目不受影响）
+    if state.config.dns.ecs_policy.strip_from_response && EcsProcessor::strip_ecs_from_response(&mut response) {
+        record_response_modified(&state.config, query_message, RESPONSE_MODIFIED_ECS_STRIPPED);
+        debug!(client_ip = %client_ip, query_id = query_message.id(), "Stripped ECS option from response");
+    }
+    
+    // 客户端请求 NSID 时附加本实例的服务器标识（RFC 5001）
+    if let Some(nsid) = state.config.http.nsid_identifier() {
+        let requested = query_message.extensions().as_ref()
+            .is_some_and(|edns| edns.option(EdnsCode::from(EDNS_NSID_OPTION_CODE)).is_some());
+        if requested {
+            response.extensions_mut()
+                .get_or_insert_with(Edns::new)
+                .options_mut()
+                .insert(EdnsOption::Unknown(EDNS_NSID_OPTION_CODE, nsid.as_bytes().to_vec()));
+        }
+    }
+    
+    // 客户端未使用 EDNS 时移除应答中的 OPT 记录（含 EDE 等选项）
+    strip_opt_for_non_edns_client(state, query_message, &mut response);
+    
+    // 客户端请求填充时将应答填充至块大小的整数倍（RFC 8467），抵御基于长度的流量分析
+    if state.config.dns.response_padding.enabled {
+        let requested = query_message.extensions().as_ref()
+            .is_some_and(|edns| edns.option(EdnsCode::from(EDNS_PADDING_OPTION_CODE)).is_some());
+        if requested {
+            pad_response(&mut response, state.config.dns.response_padding.block_size);
+        }
+    }
+    
+    // 写入查询日志（审计）与 dnstap 日志
+    state.query_log.log(client_ip, query_message, &response, group.as_deref(), cached);
+    state.dnstap.log(client_ip, query_message, &response, received_at);
+    
+    Ok((response, cached, blocked, json))
+}
+
+// 伴随预取：在后台以另一地址类型（A 对应 AAAA，反之亦然）解析同名查询并写入缓存，不阻塞原始应答
+// 预取经 resolve_query 处理，遵循路由、阻止与上游组的缓存设置；已缓存时不查询上游
+fn spawn_companion_prefetch(state: &ServerState, query_message: &Message, client_ip: IpAddr) {
+    if !state.config.dns.cache.companion_prefetch || !state.cache.is_enabled() {
+        return;
+    }
+    let [query] = query_message.queries() else {
+        return;
+    };
+    let companion_type = match query.query_type() {
+        RecordType::A => RecordType::AAAA,
+        RecordType::AAAA => RecordType::A,
+        _ => return,
+    };
+    if query.query_class() != DNSClass::IN {
+        return;
+    }
+    
+    let mut companion_query = query.clone();
+    companion_query.set_query_type(companion_type);
+    let mut companion = query_message.clone();
+    companion.set_id(fastrand::u16(..));
+    companion.take_queries();
+    companion.add_query(companion_query);
+    
+    let state = state.clone();
+    tokio::spawn(async move {
+        let name = companion.queries()[0].name().clone();
+        match resolve_query(&state, &companion, client_ip, None).await {
+            Ok(outcome) if !outcome.cached => {
+                METRICS.cache_companion_prefetch_total().inc();
+                debug!(name = %name, query_type = ?companion_type, "Companion record prefetched");
+            },
+            Ok(_) => {},
+            Err(e) => debug!(name = %name, query_type = ?companion_type, error = %e, "Companion prefetch failed"),
+        }
+    });
+}
+
+// 记录被本服务修改的应答（阻止、过滤、改写），按配置输出日志
+fn record_response_modified(config: &ServerConfig, query_message: &Message, modification: &'static str) {
+    METRICS.response_modified_total().with_label_values(&[modification]).inc();
+    if config.dns.log_modified_responses {
+        if let Some(query) = query_message.queries().first() {
+            info!(
+                name = %query.name(),
+                query_type = ?query.query_type(),
+                query_id = query_message.id(),
+                modification,
+                "Response modified"
+            );
+        }
+    }
+}
+
+// 按配置移除非 EDNS 客户端应答中的 OPT 记录
+fn strip_opt_for_non_edns_client(state: &ServerState, query_message: &Message, response: &mut Message) {
+    if state.config.dns.strip_opt_for_non_edns_clients
+        && query_message.extensions().is_none()
+        && response.extensions_mut().take().is_some()
+    {
+        debug!(query_id = query_message.id(), "Stripped OPT record from response to non-EDNS client");
+    }
+}
+
+// 附加 EDNS 填充选项，使应答线格式长度为块大小的整数倍
+fn pad_response(response: &mut Message, block_size: u16) {
+    let block_size = block_size as usize;
+    response.extensions_mut()
+        .get_or_insert_with(Edns::new)
+        .options_mut()
+        .insert(EdnsOption::Unknown(EDNS_PADDING_OPTION_CODE, Vec::new()));
+    
+    // 先以空填充选项计算长度，再补足至块边界
+    let Ok(length) = response.to_vec().map(|wire| wire.len()) else {
+        return;
+    };
+    let padding = (block_size - length % block_size) % block_size;
+    if let Some(edns) = response.extensions_mut() {
+        edns.options_mut().insert(EdnsOption::Unknown(EDNS_PADDING_OPTION_CODE, vec![0; padding]));
+    }
+}
+
+// 将应答各节中超过上限的记录 TTL 降至上限，返回是否有记录被修改；上限为 0 时不处理
+fn cap_response_ttls(response: &mut Message, max_ttl: u32) -> bool {
+    if max_ttl == 0 {
+        return false;
+    }
+    
+    let mut capped = false;
+    let mut cap = |records: &mut Vec<Record>| {
+        for record in records.iter_mut().filter(|record| record.ttl() > max_ttl) {
+            record.set_ttl(max_ttl);
+            capped = true;
+        }
+    };
+    cap(response.answers_mut());
+    cap(response.name_servers_mut());
+    cap(response.additionals_mut());
+    capped
+}
+
+// 按 TTL 下限改写或丢弃应答节中 TTL 过低的记录，返回受影响的记录数
+// 丢弃策略下所有记录均低于下限时改为提升 TTL，避免应答被清空
+fn apply_answer_ttl_floor(response: &mut Message, floor: &AnswerTtlFloorConfig) -> usize {
+    let min_ttl = floor.min_ttl;
+    let below = response.answers().iter().filter(|record| record.ttl() < min_ttl).count();
+    if below == 0 {
+        return 0;
+    }
+    
+    if floor.action == TtlFloorAction::Drop && below < response.answers().len() {
+        let kept = response.take_answers().into_iter().filter(|record| record.ttl() >= min_ttl).collect();
+        response.insert_answers(kept);
+    } else {
+        for record in response.answers_mut().iter_mut().filter(|record| record.ttl() < min_ttl) {
+            record.set_ttl(min_ttl);
+        }
+    }
+    below
+}
+
+// 移除应答节中名称、类型、类别与数据均相同的重复记录（TTL 不参与比较），保留首次出现的记录及其顺序，
+// 签名不同的 RRSIG 视为不同记录而保留，返回移除的记录数
+fn dedup_answers(response: &mut Message) -> usize {
+    let answers = response.take_answers();
+    let total = answers.len();
+    let mut unique: Vec<Record> = Vec::with_capacity(total);
+    for record in answers {
+        let duplicate = unique.iter().any(|kept| {
+            kept.record_type() == record.record_type()
+                && kept.dns_class() == record.dns_class()
+                && kept.name() == record.name()
+                && kept.data() == record.data()
+        });
+        if !duplicate {
+            unique.push(record);
+        }
+    }
+    
+    let removed = total - unique.len();
+    response.insert_answers(unique);
+    removed
+}
+
+// 将应答节截至最多 max 条记录（RRSIG 不计数），返回截去的记录数；上限为 0 或未超出时返回 None
+// 已签名的 RRset 整体保留或整体截去（RRSIG 随其覆盖的 RRset），首个 RRset 始终保留，避免签名验证失败
+fn limit_answer_records(response: &mut Message, max: usize) -> Option<usize> {
+    let covered = |record: &Record| match record.data() {
+        Some(RData::DNSSEC(DNSSECRData::RRSIG(sig))) => Some(sig.type_covered()),
+        _ => None,
+    };
+    let counted = response.answers().iter().filter(|record| covered(record).is_none()).count();
+    if max == 0 || counted <= max {
+        return None;
+    }
+    
+    // 已签名的 RRset（名称与覆盖类型）
+    let signed: Vec<(Name, RecordType)> = response.answers().iter()
+        .filter_map(|record| covered(record).map(|type_covered| (record.name().clone(), type_covered)))
+        .collect();
+    let rrset_size = |name: &Name, record_type: RecordType, answers: &[Record]| {
+        answers.iter().filter(|record| covered(record).is_none() && record.record_type() == record_type && record.name() == name).count()
+    };
+    
+    let answers = response.take_answers();
+    let mut kept_signed: Vec<(Name, RecordType)> = Vec::new();
+    let mut decided_signed: Vec<(Name, RecordType)> = Vec::new();
+    let mut kept_count = 0;
+    let mut keep = vec![false; answers.len()];
+    for (index, record) in answers.iter().enumerate() {
+        if covered(record).is_some() {
+            continue;
+        }
+        let key = (record.name().clone(), record.record_type());
+        if signed.contains(&key) {
+            // 已签名的 RRset 在首条记录处整体决定去留
+            if !decided_signed.contains(&key) {
+                let size = rrset_size(&key.0, key.1, &answers);
+                if kept_count == 0 || kept_count + size <= max {
+                    kept_count += size;
+                    kept_signed.push(key.clone());
+                }
+                decided_signed.push(key.clone());
+            }
+            keep[index] = kept_signed.contains(&key);
+        } else if kept_count < max {
+            kept_count += 1;
+            keep[index] = true;
+        }
+    }
+    for (index, record) in answers.iter().enumerate() {
+        if let Some(type_covered) = covered(record) {
+            keep[index] = kept_signed.contains(&(record.name().clone(), type_covered));
+        }
+    }
+    
+    let total = answers.len();
+    let kept: Vec<Record> = answers.into_iter().zip(keep).filter_map(|(record, keep)| keep.then_some(record)).collect();
+    let removed = total - kept.len();
+    response.insert_answers(kept);
+    Some(removed)
+}
+
+// 按地址族偏好重排应答节中的地址记录：A/AAAA 及覆盖它们的 RRSIG 在原有位置间稳定排序，
+// RRSIG 随其覆盖的记录族移动，其他记录（如 CNAME 链）位置不变
+fn sort_answers(response: &mut Message, sort: AnswerSort) {
+    let preferred = match sort {
+        AnswerSort::None => return,
+        AnswerSort::V4First => RecordType::A,
+        AnswerSort::V6First => RecordType::AAAA,
+    };
+    
+    let family = |record: &Record| match record.data() {
+        Some(RData::A(_)) => Some(RecordType::A),
+        Some(RData::AAAA(_)) => Some(RecordType::AAAA),
+        Some(RData::DNSSEC(DNSSECRData::RRSIG(sig))) if matches!(sig.type_covered(), RecordType::A | RecordType::AAAA) => {
+            Some(sig.type_covered())
+        },
+        _ => None,
+    };
+    
+    let mut answers = response.take_answers();
+    let positions: Vec<usize> = answers.iter()
+        .enumerate()
+        .filter(|(_, record)| family(record).is_some())
+        .map(|(index, _)| index)
+        .collect();
+    let mut addresses: Vec<Record> = positions.iter().map(|&index| answers[index].clone()).collect();
+    addresses.sort_by_key(|record| family(record) != Some(preferred));
+    for (index, record) in positions.into_iter().zip(addresses) {
+        answers[index] = record;
+    }
+    response.insert_answers(answers);
+}
+
+// 由无法解析的报文头构建不含问题节的查询（ID、操作码与 RD 标志），用于返回 FORMERR
+fn header_only_query(wire: &[u8]) -> Message {
+    let mut query = Message::new();
+    if let Some(header) = wire.get(..3) {
+        query.set_id(u16::from_be_bytes([header[0], header[1]]))
+            .set_op_code(OpCode::from_u8((header[2] >> 3) & 0x0F).unwrap_or(OpCode::Query))
+            .set_recursion_desired(header[2] & 0x01 != 0);
+    }
+    query
+}
+
+// 构建针对查询的 FORMERR 响应
+fn build_formerr_response(query_message: &Message) -> Message {
+    build_rcode_response(query_message, ResponseCode::FormErr)
+}
+
+// 构建针对查询的 REFUSED 响应
+fn build_refused_response(query_message: &Message) -> Message {
+    build_rcode_response(query_message, ResponseCode::Refused)
+}
+
+// 按策略应答 ANY 查询：hinfo 返回单条 HINFO 记录（RFC 8482），refuse 返回 REFUSED，forward 交由上游解析
+fn respond_any_query(config: &ServerConfig, query_message: &Message) -> Option<Message> {
+    let query = query_message.queries().first()?;
+    if query.query_type() != RecordType::ANY {
+        return None;
+    }
+    
+    match config.dns.any_query_policy {
+        AnyQueryPolicy::Forward => None,
+        AnyQueryPolicy::Hinfo => {
+            debug!(name = %query.name(), "Answering ANY query with RFC 8482 HINFO record");
+            let mut response = build_rcode_response(query_message, ResponseCode::NoError);
+            response.add_answer(Record::from_rdata(
+                query.name().clone(),
+                ANY_HINFO_TTL,
+                RData::HINFO(HINFO::new(ANY_HINFO_CPU.to_string(), String::new())),
+            ));
+            record_response_modified(config, query_message, RESPONSE_MODIFIED_ANY_HINFO);
+            Some(response)
+        },
+        AnyQueryPolicy::Refuse => {
+            debug!(name = %query.name(), "Refusing ANY query");
+            record_response_modified(config, query_message, RESPONSE_MODIFIED_REFUSED);
+            Some(build_refused_response(query_message))
+        },
+    }
+}
+
+// 按策略应答 EDNS 能力探测（无问题节、携带 OPT 记录的查询）：
+// 支持的 EDNS 版本返回 NOERROR，更高版本返回 BADVERS（RFC 6891），应答 OPT 记录回显 DO 位；
+// NSID、填充等选项由后续的应答处理流程按客户端请求附加
+fn respond_edns_probe(config: &ServerConfig, query_message: &Message) -> Option<Message> {
+    if config.dns.edns_probe_policy != EdnsProbePolicy::Answer || !query_message.queries().is_empty() {
+        return None;
+    }
+    let client_edns = query_message.extensions().as_ref()?;
+    
+    let response_code = if client_edns.version() > EDNS_SUPPORTED_VERSION {
+        ResponseCode::BADVERS
+    } else {
+        ResponseCode::NoError
+    };
+    debug!(query_id = query_message.id(), version = client_edns.version(), rcode = %response_code, "Answering EDNS probe");
+    
+    let mut edns = Edns::new();
+    edns.set_version(EDNS_SUPPORTED_VERSION)
+        .set_max_payload(EDNS_PROBE_MAX_PAYLOAD)
+        .set_dnssec_ok(client_edns.dnssec_ok());
+    let mut response = build_rcode_response(query_message, response_code);
+    response.set_edns(edns);
+    Some(response)
+}
+
+// 构建仅包含问题节与指定响应码的响应
+fn build_rcode_response(query_message: &Message, response_code: ResponseCode) -> Message {
+    let mut response = Message::new();
+    response.set_id(query_message.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query_message.op_code())
+        .set_recursion_desired(query_message.recursion_desired())
+        .set_recursion_available(true)
+        .set_response_code(response_code);
+    
+    for q in query_message.queries() {
+        response.add_query(q.clone());
+    }
+    
+    response
+}
+
+// 查询处理结果
+struct QueryOutcome {
+    // 响应消息
+    response: Message,
+    // 是否来自缓存
+    cached: bool,
+    // 阻止原因（未被阻止时为 None）
+    blocked: Option<&'static str>,
+    // 处理查询的上游组（缓存命中时为 None）
+    group: Option<String>,
+    // 缓存条目的 JSON 应答缓存槽位（缓存命中且启用 JSON 应答缓存时）
+    json: Option<CachedJson>,
+    // 是否为本服务持有的权威数据（本地静态记录）
+    authoritative: bool,
+}
+
+// 解析查询中的问题：启用多问题支持且查询包含多个问题时，拆分为单问题查询并发解析后合并应答
+async fn resolve_questions(
+    state: &ServerState,
+    query_message: &Message,
+    client_ip: IpAddr,
+    group_override: Option<&str>,
+) -> Result<QueryOutcome> {
+    if !state.config.dns.allow_multi_question || query_message.queries().len() <= 1 {
+        return resolve_query(state, query_message, client_ip, group_override).await;
+    }
+    
+    // 无论是否启用查询限制，均限制问题总数，避免单个查询展开为大量上游查询（并发度仅限制同时进行的数量）
+    let max_questions = state.config.dns.query_limits.max_questions;
+    let question_count = query_message.queries().len();
+    if question_count > max_questions {
+        let violation = QueryLimitViolation::QuestionCount(question_count);
+        METRICS.oversized_queries_rejected_total()
+            .with_label_values(&[violation.reason()])
+            .inc();
+        info!(client_ip = ?client_ip, query_id = query_message.id(), reason = %violation, "Rejecting multi-question query");
+        let response = build_formerr_response(query_message);
+        return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: false });
+    }
+    
+    let singles: Vec<Message> = query_message.queries().iter().map(|question| {
+        let mut single = query_message.clone();
+        single.take_queries();
+        single.add_query(question.clone());
+        single
+    }).collect();
+    
+    // 限制并发解析的问题数，保持问题顺序以合并应答
+    let resolving: Vec<_> = singles.iter().map(|single| resolve_query(state, single, client_ip, group_override)).collect();
+    let outcomes = stream::iter(resolving)
+        .buffered(MULTI_QUESTION_MAX_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+    
+    merge_question_outcomes(query_message, outcomes, state.config.dns.partial_results, state.config.dns.emit_ede)
+}
+
+// 合并各问题的解析结果
+// 全部失败时返回第一个错误；全部成功时响应码取第一个非 NOERROR 的响应码；
+// 部分失败（解析出错或应答 SERVFAIL）时返回已成功问题的记录：启用 partial_results 时响应码取自成功的问题，
+// 否则为 SERVFAIL；启用 EDE 时附加失败问题数
+fn merge_question_outcomes(
+    query_message: &Message,
+    outcomes: Vec<Result<QueryOutcome>>,
+    partial_results: bool,
+    emit_ede: bool,
+) -> Result<QueryOutcome> {
+    let total = outcomes.len();
+    let mut merged: Option<QueryOutcome> = None;
+    let mut first_error = None;
+    let mut failed = 0;
+    let mut response_code = ResponseCode::NoError;
+    
+    for outcome in outcomes {
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                debug!(query_id = query_message.id(), error = %e, "Failed to resolve one question of a multi-question query");
+                first_error.get_or_insert(e);
+                failed += 1;
+                continue;
+            },
+        };
+        
+        // 以 SERVFAIL 应答的问题（如启用 EDE 时转换的上游失败）同样视为失败
+        let servfail = outcome.response.response_code() == ResponseCode::ServFail;
+        if servfail {
+            failed += 1;
+        }
+        if response_code == ResponseCode::NoError && !(partial_results && servfail) {
+            response_code = outcome.response.response_code();
+        }
+        
+        match &mut merged {
+            None => merged = Some(outcome),
+            Some(merged) => {
+                let mut response = outcome.response;
+                merged.response.add_answers(response.take_answers());
+                merged.response.add_name_servers(response.take_name_servers());
+                merged.response.add_additionals(response.take_additionals());
+                merged.cached &= outcome.cached;
+                merged.blocked = merged.blocked.or(outcome.blocked);
+                merged.authoritative &= outcome.authoritative;
+                if merged.group.is_none() {
+                    merged.group = outcome.group;
+                }
+            },
+        }
+    }
+    
+    let Some(mut merged) = merged else {
+        return Err(first_error.unwrap_or_else(|| ServerError::InvalidQuery("Empty query section".to_string())));
+    };
+    
+    if failed > 0 {
+        if !partial_results || failed == total {
+            response_code = ResponseCode::ServFail;
+        }
+        debug!(query_id = query_message.id(), failed, total, "Returning partial results for a multi-question query");
+        if emit_ede {
+            ExtendedError::new(EDE_INFO_CODE_NO_REACHABLE_AUTHORITY, format!("{} of {} questions could not be resolved", failed, total))
+                .attach_to_message(&mut merged.response);
+        }
+    }
+    
+    // 以原始问题列表与请求 ID 构建合并后的响应
+    merged.response.take_queries();
+    merged.response.add_queries(query_message.queries().to_vec());
+    merged.response.set_id(query_message.id())
+        .set_response_code(response_code);
+    merged.json = None;
+    
+    Ok(merged)
+}
+
+// 使用路由器确定路由决策，并记录路由结果指标
+async fn decide_route(
     router: &DnsRouter,
-    cache: &DnsCache,
+    domain_name: &str,
+    record_type: RecordType,
+    profile_group: Option<&str>,
+) -> RouteDecision {
+    let route_decision = router.match_domain_when_ready(domain_name, record_type, profile_group).await;
+    
+    // 记录路由结果指标
+    match &route_decision {
+        RouteDecision::UseGroup(_) => {
+            METRICS.route_results_total()
+                .with_label_values(&[ROUTE_RESULT_RULE_MATCH])
+                .inc();
+        },
+        RouteDecision::Blackhole => {
+            METRICS.route_results_total()
+                .with_label_values(&[ROUTE_RESULT_BLACKHOLE])
+                .inc();
+        },
+        RouteDecision::UseGlobal => {
+            METRICS.route_results_total()
+                .with_label_values(&[ROUTE_RESULT_DEFAULT])
+                .inc();
+        },
+        RouteDecision::Refuse => {
+            METRICS.route_results_total()
+                .with_label_values(&[ROUTE_RESULT_REFUSED])
+                .inc();
+        },
+    }
+    
+    route_decision
+}
+
+// 按缓存、路由规则和上游解析查询
+async fn resolve_query(
+    state: &ServerState,
     query_message: &Message,
     client_ip: IpAddr,
-) -> Result<(Message, bool)> {  // 返回元组，第二个参数表示是否缓存命中
-    // 检查查询有效性
+    group_override: Option<&str>,
+) -> Result<QueryOutcome> {
+    let ServerState { config, upstream, router, cache, duplicates, profile, ddr, trust_anchor, .. } = state;
+    
+    // 检查查询有效性：无问题节但携带 OPT 记录的 EDNS 能力探测可按策略直接应答
     if query_message.queries().is_empty() {
+        if let Some(response) = respond_edns_probe(config, query_message) {
+            return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: false });
+        }
         return Err(ServerError::InvalidQuery("Empty query section".to_string()));
     }
     
+    // 记录重复/重试查询（仅用于观测，不影响处理）
+    duplicates.observe(client_ip, query_message).await;
+    
+    // DDR 查询由本服务直接应答，不经过缓存与上游
+    if let Some(response) = ddr.as_ref().and_then(|ddr| ddr.respond(query_message)) {
+        return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: false });
+    }
+    
+    // 特殊用途域名（localhost、invalid）由本服务直接应答，避免泄露至上游
+    if let Some(response) = respond_special_name(&config.dns.special_names, query_message) {
+        return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: false });
+    }
+    
+    // 本服务自身的主机名由本服务直接应答，避免经上游解析自身时形成环路
+    if let Some(response) = respond_self_name(&config.dns.loop_protection, query_message) {
+        return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: false });
+    }
+    
+    // 本地静态记录由本服务直接应答，避免内部域名泄露至上游
+    if let Some(response) = respond_local_record(&config.dns.local_records, query_message) {
+        return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: true });
+    }
+    
+    // 信任锚中的 DS/DNSKEY 查询由本服务直接应答，减少上游查询并支持离线验证
+    if let Some(response) = trust_anchor.as_ref().and_then(|anchor| anchor.respond(query_message)) {
+        return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: false });
+    }
+    
+    // ANY 查询按策略直接应答，不经过缓存与上游（RFC 8482）
+    if let Some(response) = respond_any_query(config, query_message) {
+        return Ok(QueryOutcome { response, cached: false, blocked: None, group: None, json: None, authoritative: false });
+    }
+    
     // 获取第一个查询
     let query = &query_message.queries()[0];
     
@@ -1122,96 +2238,270 @@ async fn process_query(
         )
     };
     
-    // 尝试从缓存获取
-    if cache.is_enabled() {
-        if let Some(cached_response) = cache.get_with_ecs(&cache_key, client_ecs.as_ref()).await {
+    // 使用路由器确定上游组 - 提前获取域名UTF8字符串，避免重复转换
+    let domain_name = query.name().to_utf8();
+    // 请求来自路由配置档案路径时，未匹配规则的查询使用档案的默认上游组
+    let profile_group = profile.as_ref().map(|p| p.default_upstream_group.as_str());
+    
+    // 请求头指定上游组时跳过路由规则；按上游组隔离缓存时，需先确定路由决策
+    // 规则加载完成前 fail-closed 时同样先确定路由决策，避免缓存中的应答绕过尚未加载的屏蔽规则
+    let isolate_group = group_override.is_some() || config.dns.cache.isolate_upstream_groups;
+    let early_route_decision = if let Some(group) = group_override {
+        Some(RouteDecision::UseGroup(group.to_string()))
+    } else if config.dns.cache.isolate_upstream_groups || router.failing_closed() {
+        Some(decide_route(router, &domain_name, query.query_type(), profile_group).await)
+    } else {
+        None
+    };
+    
+    // 路由配置档案（及启用隔离时的上游组）使用独立的缓存命名空间，避免应答互相污染
+    let group_namespace = match &early_route_decision {
+        Some(RouteDecision::UseGroup(group_name)) if isolate_group => Some(format!("{}{}", CACHE_NAMESPACE_GROUP_PREFIX, group_name)),
+        _ => None,
+    };
+    let cache_key = match (profile, group_namespace) {
+        (Some(profile), Some(group_namespace)) => cache_key.with_namespace(&format!("{}/{}", profile.name, group_namespace)),
+        (Some(profile), None) => cache_key.with_namespace(&profile.name),
+        (None, Some(group_namespace)) => cache_key.with_namespace(&group_namespace),
+        (None, None) => cache_key,
+    };
+    
+    // DO/CD 标志不同的查询可能得到签名或未签名的应答，分开缓存
+    let dnssec_ok = query_message.extensions().as_ref().is_some_and(|edns| edns.dnssec_ok());
+    let cache_key = cache_key.with_dnssec_flags(dnssec_ok, query_message.checking_disabled());
+    
+    // 配置为重新验证的查询类型（如 DS、DNSKEY）不直接使用缓存应答，每次查询上游，上游失败时再回退到缓存
+    let revalidate = cache.is_enabled() && config.cache_revalidates(query.query_type());
+    
+    // 尝试从缓存获取（已确定为黑洞或拒绝的查询、指定上游组的查询跳过缓存）
+    if cache.is_enabled() && !revalidate && group_override.is_none() && !matches!(early_route_decision, Some(RouteDecision::Blackhole | RouteDecision::Refuse)) {
+        if let Some((cached_response, json)) = cache.get_entry_with_ecs(&cache_key, client_ecs.as_ref()).await {
             // 从缓存构建响应（复制请求 ID 等信息）
             let mut response = cached_response;
             response.set_id(query_message.id());
             
-            return Ok((response, true));
+            return Ok(QueryOutcome { response, cached: true, blocked: None, group: None, json, authoritative: false });
         }
     }
     
     // 缓存未命中，需要查询上游
-    
-    // 使用路由器确定上游组 - 提前获取域名UTF8字符串，避免重复转换
-    let domain_name = query.name().to_utf8();
-    let route_decision = router.match_domain(&domain_name).await;
-    
-    // 记录路由结果指标
-    match &route_decision {
-        RouteDecision::UseGroup(_) => {
-            METRICS.route_results_total()
-                .with_label_values(&[ROUTE_RESULT_RULE_MATCH])
-                .inc();
-        },
-        RouteDecision::Blackhole => {
-            METRICS.route_results_total()
-                .with_label_values(&[ROUTE_RESULT_BLACKHOLE])
-                .inc();
-        },
-        RouteDecision::UseGlobal => {
-            METRICS.route_results_total()
-                .with_label_values(&[ROUTE_RESULT_DEFAULT])
-                .inc();
-        },
-    }
+    let route_decision = match early_route_decision {
+        Some(route_decision) => route_decision,
+        None => decide_route(router, &domain_name, query.query_type(), profile_group).await,
+    };
     
     // 选择上游
     let upstream_selection = match route_decision {
         RouteDecision::UseGroup(group_name) => UpstreamSelection::Group(group_name),
         RouteDecision::Blackhole => {
-            // 黑洞策略 - 创建一个响应，直接重用查询信息
-            let mut response = Message::new();
-            response.set_id(query_message.id())
-                .set_message_type(MessageType::Response)
-                .set_recursion_desired(query_message.recursion_desired())
-                .set_recursion_available(true)
-                .set_response_code(ResponseCode::NXDomain);
+            // 黑洞策略 - 按客户端 IP 选择阻止响应（NXDOMAIN/REFUSED/sinkhole）
+            let mut response = router.blackhole().build_response(query_message, client_ip);
             
-            // 复制查询部分
-            for q in query_message.queries() {
-                response.add_query(q.clone());
+            // 附加 EDE：已阻止
+            if config.dns.emit_ede {
+                ExtendedError::new(EDE_INFO_CODE_BLOCKED, EDE_TEXT_BLOCKED)
+                    .attach_to_message(&mut response);
             }
             
             // 记录DNS响应（黑洞）
             {
                 METRICS.dns_responses_total()
-                    .with_label_values(&[DNS_RESPONSE_NXDOMAIN_BLACKHOLE])
+                    .with_label_values(&[&format!("{:?}{}", response.response_code(), DNS_RESPONSE_BLACKHOLE_SUFFIX)])
                     .inc();
             }
+            record_response_modified(config, query_message, RESPONSE_MODIFIED_BLACKHOLE);
             
             // 不缓存黑洞响应
-            return Ok((response, false));
+            return Ok(QueryOutcome {
+                response,
+                cached: false,
+                blocked: Some(BLOCK_REASON_RULE_MATCH),
+                group: Some(BLACKHOLE_UPSTREAM_GROUP_NAME.to_string()),
+                json: None,
+                authoritative: false,
+            });
+        },
+        RouteDecision::Refuse => {
+            // 未匹配任何规则且 default_action 为 refuse - 仅解析显式路由的域名
+            let mut response = build_refused_response(query_message);
+            
+            // 附加 EDE：已过滤
+            if config.dns.emit_ede {
+                ExtendedError::new(EDE_INFO_CODE_FILTERED, EDE_TEXT_FILTERED)
+                    .attach_to_message(&mut response);
+            }
+            record_response_modified(config, query_message, RESPONSE_MODIFIED_REFUSED);
+            
+            // 不缓存拒绝响应
+            return Ok(QueryOutcome { response, cached: false, blocked: Some(BLOCK_REASON_DEFAULT_ACTION), group: None, json: None, authoritative: false });
         },
         RouteDecision::UseGlobal => UpstreamSelection::Global,
     };
+    let group = match &upstream_selection {
+        UpstreamSelection::Group(group_name) => group_name.clone(),
+        UpstreamSelection::Global => UPSTREAM_GROUP_GLOBAL.to_string(),
+    };
+    
+    // 上游组可关闭缓存或覆盖缓存 TTL 范围，全局上游使用全局设置
+    let (group_cache_enabled, (min_ttl, max_ttl)) = match &upstream_selection {
+        UpstreamSelection::Group(group_name) => {
+            (config.group_cache_enabled(group_name), config.get_effective_cache_ttl(group_name))
+        },
+        UpstreamSelection::Global => (true, (config.dns.cache.ttl.min, config.dns.cache.ttl.max)),
+    };
+    // 指定上游组的应答不写入缓存，避免影响其他客户端
+    let cache_enabled = cache.is_enabled() && group_cache_enabled && group_override.is_none();
+    
+    // 主缓存未命中时查找只读基线缓存：路由已确定使用上游，阻止与拒绝规则优先于基线
+    if cache_enabled && !revalidate {
+        if let Some(mut response) = cache.get_baseline(&cache_key) {
+            response.set_id(query_message.id());
+            
+            return Ok(QueryOutcome { response, cached: true, blocked: None, group: None, json: None, authoritative: false });
+        }
+    }
     
-    // 查询上游，传递客户端 IP 和 ECS 数据 - 避免临时变量
-    let response = upstream.resolve(
+    // 查询上游，传递客户端 IP 和 ECS 数据
+    let resolved = upstream.resolve(
         query_message, 
-        upstream_selection, 
+        upstream_selection.clone(), 
         Some(client_ip), 
         client_ecs.as_ref()
-    ).await?;
+    ).await;
+    
+    // 上游失败（SERVFAIL 或错误/超时）时，尝试返回过期缓存条目
+    let upstream_failed = match &resolved {
+        Ok(response) => response.response_code() == ResponseCode::ServFail,
+        Err(_) => true,
+    };
+    // 指定上游组的查询不读取缓存，直接返回该组的失败，便于排查
+    if upstream_failed && group_override.is_none() {
+        // 重新验证失败时返回未过期的缓存条目
+        if revalidate {
+            if let Some((mut cached_response, json)) = cache.get_entry_with_ecs(&cache_key, client_ecs.as_ref()).await {
+                cached_response.set_id(query_message.id());
+                debug!(domain = %domain_name, "Upstream revalidation failed, answering with cached entry");
+                
+                return Ok(QueryOutcome { response: cached_response, cached: true, blocked: None, group: Some(group), json, authoritative: false });
+            }
+        }
+        
+        // 刷新失败宽限期内保留原条目，平滑上游抖动
+        if let Some(mut retained) = cache.retain_on_refresh_failure(&cache_key).await {
+            retained.set_id(query_message.id());
+            
+            METRICS.cache_refresh_failure_retained_total().inc();
+            debug!(domain = %domain_name, "Upstream refresh failed, retaining cached entry for grace period");
+            
+            return Ok(QueryOutcome { response: retained, cached: true, blocked: None, group: Some(group), json: None, authoritative: false });
+        }
+        
+        if let Some(mut stale) = cache.get_stale(&cache_key).await {
+            stale.set_id(query_message.id());
+            
+            // 附加 EDE：过期应答
+            if config.dns.emit_ede {
+                ExtendedError::new(EDE_INFO_CODE_STALE_ANSWER, EDE_TEXT_STALE_ANSWER)
+                    .attach_to_message(&mut stale);
+            }
+            
+            METRICS.cache_stale_on_error_total().inc();
+            debug!(domain = %domain_name, "Upstream failed, answering with stale cache entry");
+            
+            return Ok(QueryOutcome { response: stale, cached: true, blocked: None, group: Some(group), json: None, authoritative: false });
+        }
+    }
+    
+    let response = match resolved {
+        Ok(mut response) => {
+            // 上游返回 SERVFAIL 且未携带 EDE 时，附加原因
+            if config.dns.emit_ede
+                && response.response_code() == ResponseCode::ServFail
+                && ExtendedError::from_message(&response).is_none()
+            {
+                ExtendedError::new(EDE_INFO_CODE_NO_REACHABLE_AUTHORITY, EDE_TEXT_UPSTREAM_FAILURE)
+                    .attach_to_message(&mut response);
+            }
+            response
+        },
+        Err(e) if config.dns.emit_ede => {
+            // 启用 EDE 时，将上游失败转换为携带原因的 SERVFAIL 响应
+            debug!(error = %e, "Upstream resolution failed, answering SERVFAIL with EDE");
+            let mut response = build_rcode_response(query_message, ResponseCode::ServFail);
+            
+            ExtendedError::new(EDE_INFO_CODE_NO_REACHABLE_AUTHORITY, EDE_TEXT_UPSTREAM_FAILURE)
+                .attach_to_message(&mut response);
+            
+            // 不缓存 SERVFAIL 响应
+            return Ok(QueryOutcome { response, cached: false, blocked: None, group: Some(group), json: None, authoritative: false });
+        },
+        Err(e) => return Err(e),
+    };
+    
+    // CNAME 展平：对配置区域内的查询追踪 CNAME 链并改写应答，展平结果按原查询缓存
+    let mut response = if config.dns.cname_flatten.matches(&domain_name) {
+        let original_answers = response.answers().to_vec();
+        let flattened = flatten_cname(
+            upstream,
+            query_message,
+            response,
+            &upstream_selection,
+            client_ip,
+            client_ecs.as_ref(),
+            config.dns.cname_flatten.max_depth,
+        ).await;
+        if flattened.answers() != original_answers.as_slice() {
+            record_response_modified(config, query_message, RESPONSE_MODIFIED_CNAME_FLATTEN);
+        }
+        flattened
+    } else {
+        response
+    };
+    
+    // 移除应答节中的重复记录，缓存与客户端得到相同的去重结果
+    if config.dns.dedup_answers {
+        let removed = dedup_answers(&mut response);
+        if removed > 0 {
+            record_response_modified(config, query_message, RESPONSE_MODIFIED_FILTERED);
+            debug!(domain = %domain_name, removed, "Removed duplicate answer records");
+        }
+    }
+    
+    // 处理 TTL 低于下限的应答记录，缓存与客户端得到相同的结果
+    if config.dns.answer_ttl_floor.min_ttl > 0 {
+        let answers_before = response.answers().len();
+        let floored = apply_answer_ttl_floor(&mut response, &config.dns.answer_ttl_floor);
+        if floored > 0 {
+            // drop 策略在所有记录均低于下限时改为提升 TTL，按应答节是否减少区分
+            let modification = if response.answers().len() < answers_before {
+                RESPONSE_MODIFIED_FILTERED
+            } else {
+                RESPONSE_MODIFIED_TTL_REWRITTEN
+            };
+            record_response_modified(config, query_message, modification);
+            debug!(domain = %domain_name, floored, action = ?config.dns.answer_ttl_floor.action, "Applied answer TTL floor");
+        }
+    }
     
     // 判断响应代码，避免重复检查
     let response_code = response.response_code();
-    let cache_enabled = cache.is_enabled();
     
     // 缓存响应
     if cache_enabled {
-        if response_code == ResponseCode::NoError {
-            cache.put_with_auto_ttl_and_ecs(&cache_key, &response, client_ecs.as_ref()).await?;
-        } else if response_code == ResponseCode::NXDomain {
-            // 缓存负响应
-            let negative_ttl = cache.negative_ttl();
+        // NODATA：无应答记录但授权段带 SOA，按负响应处理
+        let is_nodata = response_code == ResponseCode::NoError
+            && response.answer_count() == 0
+            && response.name_servers().iter().any(|r| r.record_type() == RecordType::SOA);
+        if response_code == ResponseCode::NXDomain || is_nodata {
+            // 缓存负响应，TTL 取 SOA 与配置值中较小者
+            let negative_ttl = cache.negative_ttl_for(&response);
             cache.put_with_ecs(&cache_key, &response, negative_ttl, client_ecs.as_ref()).await?;
+        } else if response_code == ResponseCode::NoError {
+            cache.put_with_ttl_bounds_and_ecs(&cache_key, &response, min_ttl, max_ttl, client_ecs.as_ref()).await?;
         }
     }
     
-    Ok((response, false))
+    Ok(QueryOutcome { response, cached: false, blocked: None, group: Some(group), json: None, authoritative: false })
 }
 
 // 从 JSON 请求创建 DNS 查询消息
@@ -0,0 +1,63 @@
+// src/server/duplicate.rs
+
+use std::net::IpAddr;
+use std::time::Duration;
+use hickory_proto::op::Message;
+use moka::future::Cache;
+use tracing::debug;
+use crate::server::config::DuplicateDetectionConfig;
+use crate::server::metrics::METRICS;
+
+// 重复查询键：客户端 IP、查询名（小写）、查询类型、查询 ID
+type DuplicateKey = (IpAddr, String, u16, u16);
+
+// 重复/重试查询检测器
+pub struct DuplicateQueryDetector {
+    // 最近查询记录，禁用时为 None
+    recent: Option<Cache<DuplicateKey, ()>>,
+}
+
+impl DuplicateQueryDetector {
+    // 创建新的重复查询检测器
+    pub fn new(config: &DuplicateDetectionConfig) -> Self {
+        let recent = config.enabled.then(|| {
+            Cache::builder()
+                .max_capacity(config.max_tracked)
+                .time_to_live(Duration::from_secs(config.window_secs))
+                .build()
+        });
+
+        Self { recent }
+    }
+
+    // 记录一次查询，若窗口内已见过相同查询则返回 true
+    pub async fn observe(&self, client_ip: IpAddr, query: &Message) -> bool {
+        let Some(recent) = &self.recent else {
+            return false;
+        };
+        let Some(question) = query.queries().first() else {
+            return false;
+        };
+
+        let key = (
+            client_ip,
+            question.name().to_string().to_ascii_lowercase(),
+            u16::from(question.query_type()),
+            query.id(),
+        );
+
+        let duplicate = !recent.entry(key).or_insert(()).await.is_fresh();
+        if duplicate {
+            METRICS.duplicate_queries_total().inc();
+            debug!(
+                client_ip = %client_ip,
+                query_id = query.id(),
+                name = %question.name(),
+                query_type = ?question.query_type(),
+                "Duplicate query detected within window"
+            );
+        }
+
+        duplicate
+    }
+}
@@ -151,9 +151,15 @@ impl EcsData {
     
     // 从 EDNS Option 解析 ECS 数据
     pub fn from_edns_option(option: &EdnsOption) -> Result<Self> {
-        // 获取选项数据
+        // 获取选项数据（解析得到的消息中 ECS 选项为 Subnet 类型，需重新编码）
+        let encoded;
         let data = match option {
             EdnsOption::Unknown(code, data) if *code == EDNS_CLIENT_SUBNET_OPTION_CODE => data,
+            EdnsOption::Subnet(subnet) => {
+                encoded = Vec::<u8>::try_from(subnet)
+                    .map_err(|e| ServerError::Upstream(format!("Failed to encode ECS option: {}", e)))?;
+                &encoded
+            },
             _ => return Err(ServerError::Upstream("Not an ECS EDNS option".to_string())),
         };
         
@@ -234,6 +240,18 @@ pub struct EcsProcessor;
 impl EcsProcessor {
     // 从 DNS 消息中提取 ECS 数据
     pub fn extract_ecs_from_message(message: &Message) -> Option<EcsData> {
+        // 解析得到的消息中 OPT 记录位于 EDNS 扩展中
+        if let Some(edns) = message.extensions() {
+            let option = edns.option(EdnsCode::from(EDNS_CLIENT_SUBNET_OPTION_CODE))?;
+            return match EcsData::from_edns_option(option) {
+                Ok(ecs_data) => Some(ecs_data),
+                Err(err) => {
+                    warn!("Failed to parse ECS data: {}", err);
+                    None
+                }
+            };
+        }
+        
         // 获取 OPT 记录
         if let Some(opt_record) = message.additionals()
             .iter()
@@ -296,7 +314,9 @@ impl EcsProcessor {
             // 剥离策略 - 移除 ECS 信息
             ECS_POLICY_STRIP => {
                 // 如果没有 ECS 数据或没有 OPT 记录，原样返回
-                if ecs_data.is_none() || !query.additionals().iter().any(|r| r.record_type() == RecordType::OPT) {
+                let has_opt = query.extensions().is_some()
+                    || query.additionals().iter().any(|r| r.record_type() == RecordType::OPT);
+                if ecs_data.is_none() || !has_opt {
                     return Ok(None);
                 }
                 
@@ -435,8 +455,29 @@ impl EcsProcessor {
         }
     }
     
+    // 从响应消息的 EDNS 中移除 ECS 选项，返回是否移除
+    pub fn strip_ecs_from_response(message: &mut Message) -> bool {
+        let Some(edns) = message.extensions_mut().as_mut() else {
+            return false;
+        };
+        
+        let code = EdnsCode::from(EDNS_CLIENT_SUBNET_OPTION_CODE);
+        if edns.option(code).is_none() {
+            return false;
+        }
+        
+        edns.options_mut().remove(code);
+        true
+    }
+    
     // 从 DNS 消息中移除 ECS 信息
     pub fn remove_ecs_from_message(message: &mut Message) -> Result<()> {
+        // 解析得到的消息直接修改 EDNS 扩展，保留 DO 标志、UDP 载荷大小与其他选项
+        if let Some(edns) = message.extensions_mut() {
+            edns.options_mut().remove(EdnsCode::from(EDNS_CLIENT_SUBNET_OPTION_CODE));
+            return Ok(());
+        }
+        
         // 查找 OPT 记录索引
         let opt_index = message.additionals()
             .iter()
@@ -522,6 +563,12 @@ impl EcsProcessor {
         // 将 ECS 数据转换为 EDNS 选项
         let ecs_option = ecs_data.to_edns_option()?;
         
+        // 解析得到的消息直接修改 EDNS 扩展，保留 DO 标志、UDP 载荷大小与其他选项
+        if let Some(edns) = message.extensions_mut() {
+            edns.options_mut().insert(ecs_option);
+            return Ok(());
+        }
+        
         // 查找 OPT 记录索引
         let opt_index = message.additionals()
             .iter()
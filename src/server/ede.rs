@@ -0,0 +1,76 @@
+// src/server/ede.rs
+
+use hickory_proto::op::{Edns, Message};
+use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+use crate::common::consts::EDNS_EXTENDED_ERROR_OPTION_CODE;
+use crate::server::error::{Result, ServerError};
+
+// EDNS 扩展错误信息，遵循 RFC 8914
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedError {
+    // 信息码 (INFO-CODE)
+    pub info_code: u16,
+    // 附加文本 (EXTRA-TEXT)，可为空
+    pub extra_text: String,
+}
+
+impl ExtendedError {
+    // 创建新的扩展错误
+    pub fn new(info_code: u16, extra_text: impl Into<String>) -> Self {
+        Self {
+            info_code,
+            extra_text: extra_text.into(),
+        }
+    }
+
+    // 将扩展错误转换为 EDNS Option
+    pub fn to_edns_option(&self) -> EdnsOption {
+        // 格式: INFO-CODE(2) + EXTRA-TEXT(变长, UTF-8)
+        let text_bytes = self.extra_text.as_bytes();
+        let mut wire_format = Vec::with_capacity(2 + text_bytes.len());
+        wire_format.extend_from_slice(&self.info_code.to_be_bytes());
+        wire_format.extend_from_slice(text_bytes);
+
+        EdnsOption::Unknown(EDNS_EXTENDED_ERROR_OPTION_CODE, wire_format)
+    }
+
+    // 从 EDNS Option 解析扩展错误
+    pub fn from_edns_option(option: &EdnsOption) -> Result<Self> {
+        let data = match option {
+            EdnsOption::Unknown(code, data) if *code == EDNS_EXTENDED_ERROR_OPTION_CODE => data,
+            _ => return Err(ServerError::InvalidQuery("Not an EDE EDNS option".to_string())),
+        };
+
+        // 数据必须至少包含 2 字节的信息码
+        if data.len() < 2 {
+            return Err(ServerError::InvalidQuery("EDE option data length insufficient".to_string()));
+        }
+
+        let info_code = u16::from_be_bytes([data[0], data[1]]);
+        // 附加文本可能带有结尾的 NUL，按 RFC 8914 要求容忍并去除
+        let extra_text = String::from_utf8_lossy(&data[2..])
+            .trim_end_matches('\0')
+            .to_string();
+
+        Ok(Self {
+            info_code,
+            extra_text,
+        })
+    }
+
+    // 将扩展错误附加到 DNS 响应消息中（覆盖已有的 EDE 选项）
+    pub fn attach_to_message(&self, message: &mut Message) {
+        message
+            .extensions_mut()
+            .get_or_insert_with(Edns::new)
+            .options_mut()
+            .insert(self.to_edns_option());
+    }
+
+    // 从 DNS 消息中提取扩展错误
+    pub fn from_message(message: &Message) -> Option<Self> {
+        let edns = message.extensions().as_ref()?;
+        let option = edns.option(EdnsCode::from(EDNS_EXTENDED_ERROR_OPTION_CODE))?;
+        Self::from_edns_option(option).ok()
+    }
+}
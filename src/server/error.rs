@@ -65,6 +65,10 @@ pub enum ServerError {
     #[error("Invalid query: {0}")]
     InvalidQuery(String),
     
+    // 插件错误
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+    
     // 其他错误
     #[error("Other error: {0}")]
     Other(String),
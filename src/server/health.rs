@@ -1,9 +1,209 @@
 // src/server/health.rs
 
-use axum::{routing::get, Router};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, RecordType};
+use serde::Serialize;
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use tracing::{debug, info, warn};
+use crate::server::config::HealthProbeConfig;
+use crate::server::doh_handler::{resolve_internal_query, ServerState};
+use crate::server::metrics::METRICS;
+use crate::server::routing::Router as DnsRouter;
 
-// 创建健康检查路由
+// 探测结果指标标签
+const PROBE_RESULT_SUCCESS: &str = "success";
+const PROBE_RESULT_FAILURE: &str = "failure";
+
+// 就绪状态
+const READINESS_READY: &str = "ready";
+const READINESS_NOT_READY: &str = "not_ready";
+
+// 单次探测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    // 是否成功
+    pub success: bool,
+    // 探测耗时（毫秒）
+    pub latency_ms: u64,
+    // 探测完成时间（Unix 时间戳，秒）
+    pub checked_at: u64,
+    // 应答的响应码（探测出错时为 None）
+    pub rcode: Option<String>,
+    // 失败原因
+    pub error: Option<String>,
+}
+
+// 就绪检查响应
+#[derive(Debug, Serialize)]
+struct ReadinessResponse {
+    // 就绪状态
+    status: &'static str,
+    // 最近一次探测结果（未启用探测或尚未完成首次探测时为 None）
+    probe: Option<ProbeResult>,
+    // URL规则尚未完成首次加载的上游组
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pending_upstream_groups: Vec<String>,
+}
+
+// 就绪检查状态
+#[derive(Clone, Default)]
+struct ReadinessState {
+    // 端到端合成探测（未启用时为 None）
+    probe: Option<Arc<HealthProbe>>,
+    // 路由器，用于检查上游组是否就绪
+    router: Option<Arc<DnsRouter>>,
+}
+
+// 端到端合成探测：周期性地经完整处理流程解析探测域名，记录最近一次结果
+pub struct HealthProbe {
+    // 探测配置
+    config: HealthProbeConfig,
+    // 最近一次探测结果
+    last: RwLock<Option<ProbeResult>>,
+}
+
+impl HealthProbe {
+    // 创建新的健康探测
+    pub fn new(config: HealthProbeConfig) -> Self {
+        Self { config, last: RwLock::new(None) }
+    }
+
+    // 最近一次探测结果
+    pub fn last_result(&self) -> Option<ProbeResult> {
+        match self.last.read() {
+            Ok(last) => last.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    // 是否就绪：最近一次探测成功（尚未完成首次探测时视为未就绪）
+    pub fn is_ready(&self) -> bool {
+        self.last_result().is_some_and(|result| result.success)
+    }
+
+    // 启动周期性探测任务，探测对象被释放后任务自动退出
+    // 探测使用独立的禁用缓存，避免缓存命中掩盖上游故障
+    pub fn spawn(self: &Arc<Self>, state: ServerState) {
+        let probe = Arc::downgrade(self);
+        let interval_duration = Duration::from_secs(self.config.interval_secs);
+        info!(
+            name = %self.config.name,
+            interval_secs = self.config.interval_secs,
+            "Health probe enabled"
+        );
+
+        tokio::spawn(async move {
+            let mut interval_timer = interval(interval_duration);
+            interval_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval_timer.tick().await;
+
+                let Some(probe) = Weak::upgrade(&probe) else {
+                    debug!("Health probe task stopped");
+                    break;
+                };
+                probe.run_once(&state).await;
+            }
+        });
+    }
+
+    // 执行一次探测并记录结果
+    pub async fn run_once(&self, state: &ServerState) -> ProbeResult {
+        let start = Instant::now();
+        let outcome = match self.build_query() {
+            Ok(query) => {
+                let timeout = Duration::from_secs(self.config.timeout_secs);
+                match tokio::time::timeout(timeout, resolve_internal_query(state, &query)).await {
+                    Ok(Ok(response)) => Ok(response.response_code()),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(_) => Err(format!("probe timed out after {}s", self.config.timeout_secs)),
+                }
+            },
+            Err(e) => Err(e),
+        };
+        let latency = start.elapsed();
+
+        // 上游给出 NOERROR 或 NXDOMAIN 均说明解析链路可用
+        let result = ProbeResult {
+            success: matches!(outcome, Ok(ResponseCode::NoError | ResponseCode::NXDomain)),
+            latency_ms: latency.as_millis() as u64,
+            checked_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            rcode: outcome.as_ref().ok().map(|rcode| rcode.to_string()),
+            error: match &outcome {
+                Ok(ResponseCode::NoError | ResponseCode::NXDomain) => None,
+                Ok(rcode) => Some(format!("unexpected response code {}", rcode)),
+                Err(e) => Some(e.clone()),
+            },
+        };
+
+        let label = if result.success { PROBE_RESULT_SUCCESS } else { PROBE_RESULT_FAILURE };
+        METRICS.health_probe_success().set(result.success as i64);
+        METRICS.health_probe_duration_seconds()
+            .with_label_values(&[label])
+            .observe(latency.as_secs_f64());
+
+        if result.success {
+            debug!(name = %self.config.name, latency_ms = result.latency_ms, "Health probe succeeded");
+        } else {
+            warn!(name = %self.config.name, latency_ms = result.latency_ms, error = ?result.error, "Health probe failed");
+        }
+
+        match self.last.write() {
+            Ok(mut last) => *last = Some(result.clone()),
+            Err(poisoned) => *poisoned.into_inner() = Some(result.clone()),
+        }
+
+        result
+    }
+
+    // 构建探测查询
+    fn build_query(&self) -> Result<Message, String> {
+        let name = Name::from_utf8(&self.config.name)
+            .map_err(|e| format!("invalid probe name '{}': {}", self.config.name, e))?;
+
+        let mut query = Message::new();
+        query.set_id(fastrand::u16(..))
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(Query::query(name, RecordType::A));
+        Ok(query)
+    }
+}
+
+// 创建健康检查路由（未启用探测，就绪检查始终返回就绪）
 pub fn health_routes() -> Router {
+    health_routes_with_probe(None)
+}
+
+// 创建健康检查路由，启用探测时就绪检查反映最近一次探测结果
+pub fn health_routes_with_probe(probe: Option<Arc<HealthProbe>>) -> Router {
+    health_routes_with_readiness(probe, None)
+}
+
+// 创建健康检查路由，就绪检查同时反映最近一次探测结果与上游组的URL规则加载状态
+pub fn health_routes_with_readiness(probe: Option<Arc<HealthProbe>>, router: Option<Arc<DnsRouter>>) -> Router {
     Router::new()
         .route("/health", get(|| async { "ok!!" }))
-} 
+        .route("/health/ready", get(readiness_handler))
+        .with_state(ReadinessState { probe, router })
+}
+
+// 就绪检查：最近一次探测失败（或尚未完成首次探测）、或存在未就绪的上游组时返回 503
+async fn readiness_handler(State(state): State<ReadinessState>) -> impl IntoResponse {
+    let last = state.probe.as_ref().and_then(|probe| probe.last_result());
+    let probe_ready = state.probe.is_none() || last.as_ref().is_some_and(|result| result.success);
+    let pending_upstream_groups = state.router.as_ref()
+        .map(|router| router.pending_groups())
+        .unwrap_or_default();
+
+    if probe_ready && pending_upstream_groups.is_empty() {
+        (StatusCode::OK, Json(ReadinessResponse { status: READINESS_READY, probe: last, pending_upstream_groups }))
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, Json(ReadinessResponse { status: READINESS_NOT_READY, probe: last, pending_upstream_groups }))
+    }
+}
@@ -0,0 +1,64 @@
+// src/server/http_pool.rs
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use crate::server::metrics::METRICS;
+
+// 全局（共享）HTTP 客户端的连接池名称
+pub const UPSTREAM_POOL_GLOBAL: &str = "global";
+
+// 连接建立计数层：包装 reqwest 的连接器，每新建一个上游连接计数一次，用于观测连接池的连接更替
+// reqwest 不暴露连接池内部状态，新建连接数持续增长说明空闲连接被过早回收或连接池过小
+#[derive(Clone)]
+pub struct ConnectionMetricsLayer {
+    // 连接池名称（global 或上游组名称）
+    pool: Arc<str>,
+}
+
+impl ConnectionMetricsLayer {
+    pub fn new(pool: &str) -> Self {
+        Self { pool: Arc::from(pool) }
+    }
+}
+
+impl<S> Layer<S> for ConnectionMetricsLayer {
+    type Service = ConnectionMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectionMetrics { inner, pool: self.pool.clone() }
+    }
+}
+
+// 连接建立计数服务
+#[derive(Clone)]
+pub struct ConnectionMetrics<S> {
+    inner: S,
+    pool: Arc<str>,
+}
+
+impl<S, R> Service<R> for ConnectionMetrics<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.inner.call(request);
+        let pool = self.pool.clone();
+        Box::pin(async move {
+            let connection = connecting.await?;
+            METRICS.upstream_connections_opened_total().with_label_values(&[&pool]).inc();
+            Ok(connection)
+        })
+    }
+}
@@ -0,0 +1,67 @@
+// src/server/local_records.rs
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::{A, AAAA};
+use hickory_proto::rr::{RData, Record, RecordType};
+use tracing::debug;
+use crate::server::config::{Ipv4OnlyAaaaPolicy, LocalRecordsConfig};
+
+// 若查询命中本地静态记录，返回本地应答，不经过缓存与上游：
+// - A/AAAA 应答对应族的地址
+// - 仅定义 IPv4 地址时的 AAAA 查询按 ipv4_only_aaaa 策略返回 NODATA 或 DNS64 合成地址
+// - 无对应族地址或其他类型返回 NODATA，避免内部域名泄露至公共上游
+pub fn respond_local_record(config: &LocalRecordsConfig, query_message: &Message) -> Option<Message> {
+    if config.records.is_empty() {
+        return None;
+    }
+
+    let query = query_message.queries().first()?;
+    let name = query.name().to_utf8();
+    let name = name.trim_end_matches('.');
+    let entry = config.records.iter()
+        .find(|entry| entry.name.trim_end_matches('.').eq_ignore_ascii_case(name))?;
+
+    let ipv4 = entry.addresses.iter().filter_map(|address| match address {
+        IpAddr::V4(ip) => Some(*ip),
+        IpAddr::V6(_) => None,
+    });
+    let ipv6 = entry.addresses.iter().filter_map(|address| match address {
+        IpAddr::V6(ip) => Some(*ip),
+        IpAddr::V4(_) => None,
+    });
+
+    let rdatas: Vec<RData> = match query.query_type() {
+        RecordType::A => ipv4.map(|ip| RData::A(A(ip))).collect(),
+        RecordType::AAAA if ipv6.clone().next().is_some() => ipv6.map(|ip| RData::AAAA(AAAA(ip))).collect(),
+        RecordType::AAAA => match config.ipv4_only_aaaa {
+            Ipv4OnlyAaaaPolicy::Nodata => Vec::new(),
+            Ipv4OnlyAaaaPolicy::Synthesize => ipv4
+                .map(|ip| RData::AAAA(AAAA(synthesize_ipv6(config.dns64_prefix, ip))))
+                .collect(),
+        },
+        _ => Vec::new(),
+    };
+
+    debug!(name = %query.name(), query_type = ?query.query_type(), answers = rdatas.len(), "Answering local record");
+
+    let mut response = Message::new();
+    response.set_id(query_message.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query_message.op_code())
+        .set_recursion_desired(query_message.recursion_desired())
+        .set_recursion_available(true)
+        .set_authoritative(true)
+        .set_response_code(ResponseCode::NoError)
+        .add_query(query.clone())
+        .add_answers(rdatas.into_iter().map(|rdata| Record::from_rdata(query.name().clone(), config.ttl, rdata)));
+
+    Some(response)
+}
+
+// 按 RFC 6052 将 IPv4 地址嵌入 /96 前缀的低 32 位
+pub fn synthesize_ipv6(prefix: Ipv6Addr, ipv4: Ipv4Addr) -> Ipv6Addr {
+    let mut octets = prefix.octets();
+    octets[12..].copy_from_slice(&ipv4.octets());
+    Ipv6Addr::from(octets)
+}
@@ -0,0 +1,87 @@
+// src/server/loop_guard.rs
+
+use std::net::IpAddr;
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::{A, AAAA};
+use hickory_proto::rr::{RData, Record, RecordType};
+use once_cell::sync::Lazy;
+use tracing::{debug, warn};
+use crate::common::consts::{HEADER_LOOP_MARKER, SPECIAL_NAME_TTL};
+use crate::server::config::LoopProtectionConfig;
+use crate::server::metrics::METRICS;
+
+// 错误消息常量
+const ERROR_FORWARDING_LOOP: &str = "Forwarding loop detected";
+
+// 本进程的实例标识，随发往 DoH 上游的请求一同发送，用于识别转发回自身的请求
+static INSTANCE_ID: Lazy<String> = Lazy::new(|| format!("{:016x}", fastrand::u64(..)));
+
+// 获取本进程的实例标识
+pub fn instance_id() -> &'static str {
+    INSTANCE_ID.as_str()
+}
+
+// 拒绝携带本实例标识的请求（即本服务经 DoH 上游转发回自身），返回 508 以打断转发环路
+pub async fn reject_forwarding_loops(req: Request, next: Next) -> Response {
+    let looped = req.headers()
+        .get(HEADER_LOOP_MARKER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value == instance_id());
+
+    if looped {
+        METRICS.forwarding_loops_detected_total().inc();
+        warn!(path = %req.uri().path(), "Rejected request forwarded back to this server, check upstream and routing configuration");
+        return (StatusCode::LOOP_DETECTED, ERROR_FORWARDING_LOOP).into_response();
+    }
+
+    next.run(req).await
+}
+
+// 若查询为本服务自身的主机名，返回本地应答，不转发至上游：
+// 配置了自身地址时 A/AAAA 应答对应族的地址，未配置时返回 REFUSED
+pub fn respond_self_name(config: &LoopProtectionConfig, query_message: &Message) -> Option<Message> {
+    if !config.enabled || config.self_names.is_empty() {
+        return None;
+    }
+
+    let query = query_message.queries().first()?;
+    let name = query.name().to_lowercase().to_utf8();
+    let name = name.trim_end_matches('.');
+    if !config.self_names.iter().any(|self_name| self_name.trim_end_matches('.').eq_ignore_ascii_case(name)) {
+        return None;
+    }
+
+    let mut response = Message::new();
+    response.set_id(query_message.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query_message.op_code())
+        .set_recursion_desired(query_message.recursion_desired())
+        .set_recursion_available(true)
+        .add_query(query.clone());
+
+    if config.self_addresses.is_empty() {
+        debug!(name = %query.name(), "Refusing query for this server's own name");
+        response.set_response_code(ResponseCode::Refused);
+        return Some(response);
+    }
+
+    let answers = config.self_addresses.iter().filter_map(|address| {
+        let rdata = match (query.query_type(), address) {
+            (RecordType::A, IpAddr::V4(ip)) => RData::A(A(*ip)),
+            (RecordType::AAAA, IpAddr::V6(ip)) => RData::AAAA(AAAA(*ip)),
+            _ => return None,
+        };
+        Some(Record::from_rdata(query.name().clone(), SPECIAL_NAME_TTL, rdata))
+    });
+    response.set_response_code(ResponseCode::NoError)
+        .add_answers(answers);
+
+    debug!(name = %query.name(), answers = response.answer_count(), "Answering query for this server's own name locally");
+    Some(response)
+}
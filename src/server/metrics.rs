@@ -3,14 +3,52 @@
 use axum::{routing::get, Router};
 use prometheus::{
     GaugeVec, HistogramVec, 
-    IntCounter, IntCounterVec, IntGauge, Registry,
+    IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Registry,
     opts,
 };
 use once_cell::sync::Lazy;
+use hickory_proto::rr::RecordType;
 
 // 全局静态指标实例
 pub static METRICS: Lazy<DnsMetrics> = Lazy::new(DnsMetrics::new);
 
+// 未在允许列表中的查询类型统一使用的标签值
+pub const QUERY_TYPE_LABEL_OTHER: &str = "OTHER";
+
+// 单独标记的查询类型允许列表，其余类型归入 OTHER，避免随机查询类型造成标签基数膨胀
+const KNOWN_QUERY_TYPES: &[RecordType] = &[
+    RecordType::A,
+    RecordType::AAAA,
+    RecordType::ANY,
+    RecordType::CAA,
+    RecordType::CNAME,
+    RecordType::DNSKEY,
+    RecordType::DS,
+    RecordType::HTTPS,
+    RecordType::MX,
+    RecordType::NAPTR,
+    RecordType::NS,
+    RecordType::NSEC,
+    RecordType::NSEC3,
+    RecordType::PTR,
+    RecordType::RRSIG,
+    RecordType::SOA,
+    RecordType::SRV,
+    RecordType::SSHFP,
+    RecordType::SVCB,
+    RecordType::TLSA,
+    RecordType::TXT,
+];
+
+// 查询类型指标标签：允许列表内的类型使用类型名，其余归入 OTHER
+pub fn query_type_label(record_type: RecordType) -> String {
+    if KNOWN_QUERY_TYPES.contains(&record_type) {
+        format!("{:?}", record_type)
+    } else {
+        QUERY_TYPE_LABEL_OTHER.to_string()
+    }
+}
+
 // DNS 服务器性能指标
 pub struct DnsMetrics {
     registry: Registry,
@@ -21,27 +59,47 @@ pub struct DnsMetrics {
     http_request_bytes: HistogramVec,
     http_response_bytes: HistogramVec,
     rate_limit_rejected_total: IntCounterVec,
+    query_quota_exceeded_total: IntCounter,
     
     // 2. 缓存效率和状态指标
     cache_entries: IntGauge, 
     cache_capacity: IntGauge,
+    cache_memory_bytes: IntGauge,
     cache_operations_total: IntCounterVec,
     cache_ttl_seconds: HistogramVec,
+    cache_hit_age_seconds: HistogramVec,
+    cache_stale_on_error_total: IntCounter,
+    cache_baseline_hits_total: IntCounter,
+    cache_refresh_failure_retained_total: IntCounter,
+    cache_companion_prefetch_total: IntCounter,
     
     // 3. DNS 查询统计指标
     dns_queries_total: IntCounterVec,
     dns_responses_total: IntCounterVec,
     dns_query_type_total: IntCounterVec,
     dns_query_duration_seconds: HistogramVec,
+    duplicate_queries_total: IntCounter,
+    oversized_queries_rejected_total: IntCounterVec,
+    forwarding_loops_detected_total: IntCounter,
+    dnstap_frames_dropped_total: IntCounter,
+    query_log_records_dropped_total: IntCounter,
+    plugin_invocations_total: IntCounterVec,
+    response_modified_total: IntCounterVec,
     
     // 4. 上游 DNS 解析器指标
     upstream_requests_total: IntCounterVec,
     upstream_failures_total: IntCounterVec,
+    upstream_0x20_mismatches_total: IntCounterVec,
+    upstream_id_mismatches_total: IntCounterVec,
     upstream_duration_seconds: HistogramVec,
+    upstream_transport_served_total: IntCounterVec,
+    upstream_active_requests: IntGaugeVec,
+    upstream_connections_opened_total: IntCounterVec,
     
     // 5. DNS 路由/拆分功能指标
     route_results_total: IntCounterVec,
     route_rules: GaugeVec,
+    route_group_not_ready_total: IntCounterVec,
     
     // 6. DNSSEC 验证指标
     dnssec_validations_total: IntCounterVec,
@@ -53,9 +111,16 @@ pub struct DnsMetrics {
     // 8. 持久化缓存功能指标
     cache_persist_operations_total: IntCounterVec,
     cache_persist_duration_seconds: HistogramVec,
+    cache_load_total_entries: IntGauge,
+    cache_load_loaded_entries: IntGauge,
     
     // 9. URL规则更新指标
     url_rule_update_duration_seconds: HistogramVec,
+    url_rule_update_retries_total: IntCounterVec,
+    
+    // 10. 健康探测指标
+    health_probe_success: IntGauge,
+    health_probe_duration_seconds: HistogramVec,
 }
 
 impl Default for DnsMetrics {
@@ -107,6 +172,10 @@ impl DnsMetrics {
             &["client_ip"]
         ).unwrap();
         
+        let query_quota_exceeded_total = IntCounter::new(
+            "owdns_query_quota_exceeded_total", "Total queries refused because the client IP exceeded its daily query quota"
+        ).unwrap();
+        
         // 2. 缓存效率和状态指标
         let cache_entries = IntGauge::new(
             "owdns_cache_entries", "Current number of DNS cache entries"
@@ -116,6 +185,10 @@ impl DnsMetrics {
             "owdns_cache_capacity", "Maximum capacity of the DNS cache"
         ).unwrap();
         
+        let cache_memory_bytes = IntGauge::new(
+            "owdns_cache_memory_bytes", "Estimated memory used by DNS cache entries in bytes"
+        ).unwrap();
+        
         let cache_operations_total = IntCounterVec::new(
             opts!("owdns_cache_operations_total", "Total cache operations, classified by operation type (hit, miss, insert, evict, expire)"),
             &["operation"]
@@ -130,10 +203,35 @@ impl DnsMetrics {
             &[]
         ).unwrap();
         
+        let cache_hit_age_seconds = HistogramVec::new(
+            prometheus::histogram_opts!(
+                "owdns_cache_hit_age_seconds", 
+                "Age distribution of cache entries served on cache hits (time since stored) in seconds",
+                vec![1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 600.0, 1800.0, 3600.0, 7200.0, 86400.0]
+            ),
+            &[]
+        ).unwrap();
+        
+        let cache_stale_on_error_total = IntCounter::new(
+            "owdns_cache_stale_on_error_total", "Total expired cache entries served because the upstream failed"
+        ).unwrap();
+        
+        let cache_baseline_hits_total = IntCounter::new(
+            "owdns_cache_baseline_hits_total", "Total queries answered from the read-only baseline cache after a main cache miss"
+        ).unwrap();
+        
+        let cache_refresh_failure_retained_total = IntCounter::new(
+            "owdns_cache_refresh_failure_retained_total", "Total expired cache entries retained for the grace period because their refresh failed"
+        ).unwrap();
+        
+        let cache_companion_prefetch_total = IntCounter::new(
+            "owdns_cache_companion_prefetch_total", "Total companion A/AAAA queries resolved from upstream in the background to warm the cache"
+        ).unwrap();
+        
         // 3. DNS 查询统计指标
         let dns_queries_total = IntCounterVec::new(
-            opts!("owdns_dns_queries_total", "Total DNS queries received, classified by query type and status"),
-            &["query_type", "status"]
+            opts!("owdns_dns_queries_total", "Total DNS queries received, classified by query type, status and request format (wire/json)"),
+            &["query_type", "status", "format"]
         ).unwrap();
         
         let dns_responses_total = IntCounterVec::new(
@@ -155,6 +253,37 @@ impl DnsMetrics {
             &["query_type"]
         ).unwrap();
         
+        let duplicate_queries_total = IntCounter::new(
+            "owdns_duplicate_queries_total", "Total duplicate (retransmitted) client queries with the same client IP, name, type and ID seen within the detection window"
+        ).unwrap();
+        
+        let oversized_queries_rejected_total = IntCounterVec::new(
+            opts!("owdns_oversized_queries_rejected_total", "Total client queries rejected with FORMERR for exceeding query size or EDNS option limits, classified by reason"),
+            &["reason"]
+        ).unwrap();
+        
+        let forwarding_loops_detected_total = IntCounter::new(
+            "owdns_forwarding_loops_detected_total", "Total DoH requests rejected because they were forwarded back to this server by itself"
+        ).unwrap();
+        
+        let dnstap_frames_dropped_total = IntCounter::new(
+            "owdns_dnstap_frames_dropped_total", "Total dnstap frames dropped because the output queue was full or the output was unavailable"
+        ).unwrap();
+        
+        let query_log_records_dropped_total = IntCounter::new(
+            "owdns_query_log_records_dropped_total", "Total query log records dropped because the write queue was full or the log file could not be written"
+        ).unwrap();
+        
+        let plugin_invocations_total = IntCounterVec::new(
+            opts!("owdns_plugin_invocations_total", "Total response plugin invocations, classified by result (modified, unchanged, error)"),
+            &["result"]
+        ).unwrap();
+        
+        let response_modified_total = IntCounterVec::new(
+            opts!("owdns_response_modified_total", "Total responses altered by the server instead of being passed through, classified by modification type"),
+            &["modification"]
+        ).unwrap();
+        
         // 4. 上游 DNS 解析器指标
         let upstream_requests_total = IntCounterVec::new(
             opts!("owdns_upstream_requests_total", "Total requests sent to upstream DNS resolvers, classified by resolver address, protocol and upstream group"),
@@ -166,6 +295,16 @@ impl DnsMetrics {
             &["type", "resolver", "upstream_group"]
         ).unwrap();
         
+        let upstream_0x20_mismatches_total = IntCounterVec::new(
+            opts!("owdns_upstream_0x20_mismatches_total", "Total upstream responses discarded because the query name case did not match the DNS 0x20 encoded query, classified by resolver address"),
+            &["resolver"]
+        ).unwrap();
+        
+        let upstream_id_mismatches_total = IntCounterVec::new(
+            opts!("owdns_upstream_id_mismatches_total", "Total upstream responses discarded because their message ID did not match the query, classified by resolver address"),
+            &["resolver"]
+        ).unwrap();
+        
         let upstream_duration_seconds = HistogramVec::new(
             prometheus::histogram_opts!(
                 "owdns_upstream_duration_seconds", 
@@ -175,9 +314,24 @@ impl DnsMetrics {
             &["resolver", "protocol", "upstream_group"]
         ).unwrap();
         
+        let upstream_transport_served_total = IntCounterVec::new(
+            opts!("owdns_upstream_transport_served_total", "Total queries answered by resolvers with a protocol fallback list, classified by the transport that served the query and upstream group"),
+            &["protocol", "upstream_group"]
+        ).unwrap();
+        
+        let upstream_active_requests = IntGaugeVec::new(
+            opts!("owdns_upstream_active_requests", "Number of DoH requests currently in flight to upstream resolvers, classified by resolver address"),
+            &["resolver"]
+        ).unwrap();
+        
+        let upstream_connections_opened_total = IntCounterVec::new(
+            opts!("owdns_upstream_connections_opened_total", "Total new connections opened by the upstream HTTP client pools, classified by pool (global or upstream group)"),
+            &["pool"]
+        ).unwrap();
+        
         // 5. DNS 路由/拆分功能指标
         let route_results_total = IntCounterVec::new(
-            opts!("owdns_route_results_total", "Total routing results, classified by result type (rule_match, blackhole, default)"),
+            opts!("owdns_route_results_total", "Total routing results, classified by result type (rule_match, blackhole, default, refused)"),
             &["result"]
         ).unwrap();
         
@@ -186,6 +340,11 @@ impl DnsMetrics {
             &["type"]
         ).unwrap();
         
+        let route_group_not_ready_total = IntCounterVec::new(
+            opts!("owdns_route_group_not_ready_total", "Total queries handled while their upstream group's URL rules were still loading, classified by action (waited, global)"),
+            &["action"]
+        ).unwrap();
+        
         // 6. DNSSEC 验证指标
         let dnssec_validations_total = IntCounterVec::new(
             opts!("owdns_dnssec_validations_total", "Total DNSSEC validations performed, classified by validation status (success, failure)"),
@@ -217,6 +376,14 @@ impl DnsMetrics {
             &["operation"]
         ).unwrap();
         
+        let cache_load_total_entries = IntGauge::new(
+            "owdns_cache_load_total_entries", "Total entries read from the persisted cache file to be loaded on startup"
+        ).unwrap();
+        
+        let cache_load_loaded_entries = IntGauge::new(
+            "owdns_cache_load_loaded_entries", "Entries from the persisted cache file loaded into memory so far"
+        ).unwrap();
+        
         // 9. URL规则更新指标
         let url_rule_update_duration_seconds = HistogramVec::new(
             prometheus::histogram_opts!(
@@ -226,6 +393,25 @@ impl DnsMetrics {
             ),
             &["status", "upstream_group"]
         ).unwrap();
+        
+        let url_rule_update_retries_total = IntCounterVec::new(
+            opts!("owdns_url_rule_update_retries_total", "Total URL rule fetch retry attempts after a failed update, classified by status (success, failed) and upstream group"),
+            &["status", "upstream_group"]
+        ).unwrap();
+
+        // 10. 健康探测指标
+        let health_probe_success = IntGauge::new(
+            "owdns_health_probe_success", "Whether the last end-to-end synthetic health probe succeeded (1) or failed (0)"
+        ).unwrap();
+        
+        let health_probe_duration_seconds = HistogramVec::new(
+            prometheus::histogram_opts!(
+                "owdns_health_probe_duration_seconds", 
+                "End-to-end synthetic health probe duration in seconds, classified by result (success, failure)",
+                vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+            ),
+            &["result"]
+        ).unwrap();
 
         // 创建指标实例
         let metrics = DnsMetrics {
@@ -235,25 +421,50 @@ impl DnsMetrics {
             http_request_bytes,
             http_response_bytes,
             rate_limit_rejected_total,
+            query_quota_exceeded_total,
             cache_entries,
             cache_capacity,
+            cache_memory_bytes,
             cache_operations_total,
             cache_ttl_seconds,
+            cache_hit_age_seconds,
+            cache_stale_on_error_total,
+            cache_baseline_hits_total,
+            cache_refresh_failure_retained_total,
+            cache_companion_prefetch_total,
             dns_queries_total,
             dns_responses_total,
             dns_query_type_total,
             dns_query_duration_seconds,
+            duplicate_queries_total,
+            oversized_queries_rejected_total,
+            forwarding_loops_detected_total,
+            dnstap_frames_dropped_total,
+            query_log_records_dropped_total,
+            plugin_invocations_total,
+            response_modified_total,
             upstream_requests_total,
             upstream_failures_total,
+            upstream_0x20_mismatches_total,
+            upstream_id_mismatches_total,
             upstream_duration_seconds,
+            upstream_transport_served_total,
+            upstream_active_requests,
+            upstream_connections_opened_total,
             route_results_total,
             route_rules,
+            route_group_not_ready_total,
             dnssec_validations_total,
             ecs_processed_total,
             ecs_cache_matches_total,
             cache_persist_operations_total,
             cache_persist_duration_seconds,
+            cache_load_total_entries,
+            cache_load_loaded_entries,
             url_rule_update_duration_seconds,
+            url_rule_update_retries_total,
+            health_probe_success,
+            health_probe_duration_seconds,
         };
         
         // 集中注册所有指标
@@ -270,27 +481,47 @@ impl DnsMetrics {
         self.registry.register(Box::new(self.http_request_bytes.clone())).unwrap();
         self.registry.register(Box::new(self.http_response_bytes.clone())).unwrap();
         self.registry.register(Box::new(self.rate_limit_rejected_total.clone())).unwrap();
+        self.registry.register(Box::new(self.query_quota_exceeded_total.clone())).unwrap();
         
         // 2. 缓存效率和状态指标
         self.registry.register(Box::new(self.cache_entries.clone())).unwrap();
         self.registry.register(Box::new(self.cache_capacity.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_memory_bytes.clone())).unwrap();
         self.registry.register(Box::new(self.cache_operations_total.clone())).unwrap();
         self.registry.register(Box::new(self.cache_ttl_seconds.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_hit_age_seconds.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_stale_on_error_total.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_baseline_hits_total.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_refresh_failure_retained_total.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_companion_prefetch_total.clone())).unwrap();
         
         // 3. DNS 查询统计指标
         self.registry.register(Box::new(self.dns_queries_total.clone())).unwrap();
         self.registry.register(Box::new(self.dns_responses_total.clone())).unwrap();
         self.registry.register(Box::new(self.dns_query_type_total.clone())).unwrap();
         self.registry.register(Box::new(self.dns_query_duration_seconds.clone())).unwrap();
+        self.registry.register(Box::new(self.duplicate_queries_total.clone())).unwrap();
+        self.registry.register(Box::new(self.oversized_queries_rejected_total.clone())).unwrap();
+        self.registry.register(Box::new(self.forwarding_loops_detected_total.clone())).unwrap();
+        self.registry.register(Box::new(self.dnstap_frames_dropped_total.clone())).unwrap();
+        self.registry.register(Box::new(self.query_log_records_dropped_total.clone())).unwrap();
+        self.registry.register(Box::new(self.plugin_invocations_total.clone())).unwrap();
+        self.registry.register(Box::new(self.response_modified_total.clone())).unwrap();
         
         // 4. 上游 DNS 解析器指标
         self.registry.register(Box::new(self.upstream_requests_total.clone())).unwrap();
         self.registry.register(Box::new(self.upstream_failures_total.clone())).unwrap();
+        self.registry.register(Box::new(self.upstream_0x20_mismatches_total.clone())).unwrap();
+        self.registry.register(Box::new(self.upstream_id_mismatches_total.clone())).unwrap();
         self.registry.register(Box::new(self.upstream_duration_seconds.clone())).unwrap();
+        self.registry.register(Box::new(self.upstream_transport_served_total.clone())).unwrap();
+        self.registry.register(Box::new(self.upstream_active_requests.clone())).unwrap();
+        self.registry.register(Box::new(self.upstream_connections_opened_total.clone())).unwrap();
         
         // 5. DNS 路由/拆分功能指标
         self.registry.register(Box::new(self.route_results_total.clone())).unwrap();
         self.registry.register(Box::new(self.route_rules.clone())).unwrap();
+        self.registry.register(Box::new(self.route_group_not_ready_total.clone())).unwrap();
         
         // 6. DNSSEC 验证指标
         self.registry.register(Box::new(self.dnssec_validations_total.clone())).unwrap();
@@ -302,9 +533,16 @@ impl DnsMetrics {
         // 8. 持久化缓存功能指标
         self.registry.register(Box::new(self.cache_persist_operations_total.clone())).unwrap();
         self.registry.register(Box::new(self.cache_persist_duration_seconds.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_load_total_entries.clone())).unwrap();
+        self.registry.register(Box::new(self.cache_load_loaded_entries.clone())).unwrap();
         
         // 注册URL规则更新指标
         self.registry.register(Box::new(self.url_rule_update_duration_seconds.clone())).unwrap();
+        self.registry.register(Box::new(self.url_rule_update_retries_total.clone())).unwrap();
+        
+        // 10. 健康探测指标
+        self.registry.register(Box::new(self.health_probe_success.clone())).unwrap();
+        self.registry.register(Box::new(self.health_probe_duration_seconds.clone())).unwrap();
     }
     
     // 获取 Prometheus 注册表
@@ -344,6 +582,10 @@ impl DnsMetrics {
         &self.rate_limit_rejected_total
     }
     
+    pub fn query_quota_exceeded_total(&self) -> &IntCounter {
+        &self.query_quota_exceeded_total
+    }
+    
     // 2. 缓存效率和状态指标
     pub fn cache_entries(&self) -> &IntGauge {
         &self.cache_entries
@@ -353,6 +595,10 @@ impl DnsMetrics {
         &self.cache_capacity
     }
     
+    pub fn cache_memory_bytes(&self) -> &IntGauge {
+        &self.cache_memory_bytes
+    }
+    
     pub fn cache_operations_total(&self) -> &IntCounterVec {
         &self.cache_operations_total
     }
@@ -361,6 +607,26 @@ impl DnsMetrics {
         &self.cache_ttl_seconds
     }
     
+    pub fn cache_hit_age_seconds(&self) -> &HistogramVec {
+        &self.cache_hit_age_seconds
+    }
+    
+    pub fn cache_stale_on_error_total(&self) -> &IntCounter {
+        &self.cache_stale_on_error_total
+    }
+    
+    pub fn cache_baseline_hits_total(&self) -> &IntCounter {
+        &self.cache_baseline_hits_total
+    }
+    
+    pub fn cache_refresh_failure_retained_total(&self) -> &IntCounter {
+        &self.cache_refresh_failure_retained_total
+    }
+    
+    pub fn cache_companion_prefetch_total(&self) -> &IntCounter {
+        &self.cache_companion_prefetch_total
+    }
+    
     // 3. DNS 查询统计指标
     pub fn dns_queries_total(&self) -> &IntCounterVec {
         &self.dns_queries_total
@@ -378,6 +644,34 @@ impl DnsMetrics {
         &self.dns_query_duration_seconds
     }
     
+    pub fn duplicate_queries_total(&self) -> &IntCounter {
+        &self.duplicate_queries_total
+    }
+    
+    pub fn oversized_queries_rejected_total(&self) -> &IntCounterVec {
+        &self.oversized_queries_rejected_total
+    }
+    
+    pub fn forwarding_loops_detected_total(&self) -> &IntCounter {
+        &self.forwarding_loops_detected_total
+    }
+    
+    pub fn dnstap_frames_dropped_total(&self) -> &IntCounter {
+        &self.dnstap_frames_dropped_total
+    }
+    
+    pub fn query_log_records_dropped_total(&self) -> &IntCounter {
+        &self.query_log_records_dropped_total
+    }
+    
+    pub fn plugin_invocations_total(&self) -> &IntCounterVec {
+        &self.plugin_invocations_total
+    }
+    
+    pub fn response_modified_total(&self) -> &IntCounterVec {
+        &self.response_modified_total
+    }
+    
     // 4. 上游 DNS 解析器指标
     pub fn upstream_requests_total(&self) -> &IntCounterVec {
         &self.upstream_requests_total
@@ -387,10 +681,30 @@ impl DnsMetrics {
         &self.upstream_failures_total
     }
     
+    pub fn upstream_0x20_mismatches_total(&self) -> &IntCounterVec {
+        &self.upstream_0x20_mismatches_total
+    }
+    
+    pub fn upstream_id_mismatches_total(&self) -> &IntCounterVec {
+        &self.upstream_id_mismatches_total
+    }
+    
     pub fn upstream_duration_seconds(&self) -> &HistogramVec {
         &self.upstream_duration_seconds
     }
     
+    pub fn upstream_transport_served_total(&self) -> &IntCounterVec {
+        &self.upstream_transport_served_total
+    }
+    
+    pub fn upstream_active_requests(&self) -> &IntGaugeVec {
+        &self.upstream_active_requests
+    }
+    
+    pub fn upstream_connections_opened_total(&self) -> &IntCounterVec {
+        &self.upstream_connections_opened_total
+    }
+    
     // 5. DNS 路由/拆分功能指标
     pub fn route_results_total(&self) -> &IntCounterVec {
         &self.route_results_total
@@ -400,6 +714,10 @@ impl DnsMetrics {
         &self.route_rules
     }
     
+    pub fn route_group_not_ready_total(&self) -> &IntCounterVec {
+        &self.route_group_not_ready_total
+    }
+    
     // 6. DNSSEC 验证指标
     pub fn dnssec_validations_total(&self) -> &IntCounterVec {
         &self.dnssec_validations_total
@@ -423,10 +741,32 @@ impl DnsMetrics {
         &self.cache_persist_duration_seconds
     }
     
+    pub fn cache_load_total_entries(&self) -> &IntGauge {
+        &self.cache_load_total_entries
+    }
+    
+    pub fn cache_load_loaded_entries(&self) -> &IntGauge {
+        &self.cache_load_loaded_entries
+    }
+    
     // URL规则更新耗时指标
     pub fn url_rule_update_duration_seconds(&self) -> &HistogramVec {
         &self.url_rule_update_duration_seconds
     }
+    
+    // URL规则重试次数指标
+    pub fn url_rule_update_retries_total(&self) -> &IntCounterVec {
+        &self.url_rule_update_retries_total
+    }
+    
+    // 10. 健康探测指标
+    pub fn health_probe_success(&self) -> &IntGauge {
+        &self.health_probe_success
+    }
+    
+    pub fn health_probe_duration_seconds(&self) -> &HistogramVec {
+        &self.health_probe_duration_seconds
+    }
 }
 
 // 提供指标导出路由
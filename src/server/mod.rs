@@ -1,40 +1,84 @@
 // src/server/mod.rs
 
+pub mod admin;
+pub mod blackhole;
 pub mod cache;
+pub mod cname;
+pub mod ddr;
+pub mod dns0x20;
+pub mod dnstap;
 pub mod config;
 pub mod doh_handler;
+pub mod duplicate;
 pub mod error;
 pub mod health;
+pub mod http_pool;
+pub mod local_records;
+pub mod loop_guard;
 pub mod metrics;
+pub mod plugin;
+pub mod query_log;
+pub mod query_limits;
 pub mod routing;
 pub mod security;
+pub mod self_test;
+pub mod special_names;
+pub mod trust_anchor;
 pub mod upstream;
 pub mod args;
 pub mod ecs;
+pub mod ede;
 pub mod scalar;
 
 use std::sync::Arc;
+use std::time::Duration;
 use axum::Router as AxumRouter;
 use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderValue};
 use tracing::info;
 
+use crate::common::consts::HEADER_LOOP_MARKER;
 use crate::server::error::{Result, ServerError};
+use crate::server::admin::{admin_routes, AdminState};
+use crate::server::blackhole::parse_cidrs;
 use crate::server::cache::DnsCache;
-use crate::server::config::ServerConfig;
+use crate::server::config::{DuplicateDetectionConfig, PoolConfig, ServerConfig};
+use crate::server::ddr::DdrResponder;
+use crate::server::trust_anchor::TrustAnchorResponder;
+use crate::server::dnstap::DnstapLogger;
 use crate::server::doh_handler::{doh_routes, ServerState};
-use crate::server::health::health_routes;
+use crate::server::duplicate::DuplicateQueryDetector;
+use crate::server::health::{health_routes_with_readiness, HealthProbe};
+use crate::server::http_pool::{ConnectionMetricsLayer, UPSTREAM_POOL_GLOBAL};
 use crate::server::metrics::metrics_routes;
+use crate::server::query_log::QueryLogger;
 use crate::server::routing::Router as DnsRouter;
-use crate::server::security::{apply_rate_limiting, calculate_period_duration};
-use crate::server::upstream::UpstreamManager;
+use crate::server::plugin::ResponsePlugin;
+use crate::server::security::{apply_rate_limiting, calculate_period_duration, DailyQueryQuota};
+use crate::server::upstream::{UpstreamManager, UpstreamResolver, UpstreamSelection};
 
 // 创建 HTTP 客户端的公共函数
 pub fn create_http_client(config: &ServerConfig) -> Result<Client> {
+    create_pooled_http_client(config, &config.dns.http_client.pool, UPSTREAM_POOL_GLOBAL)
+}
+
+// 按指定的连接池参数创建 HTTP 客户端，新建连接按连接池名称计入指标
+pub fn create_pooled_http_client(config: &ServerConfig, pool: &PoolConfig, pool_name: &str) -> Result<Client> {
+    // 启用转发环路防护时，所有上游请求携带本实例标识
+    let mut default_headers = HeaderMap::new();
+    if config.dns.loop_protection.enabled {
+        default_headers.insert(HEADER_LOOP_MARKER, HeaderValue::from_static(loop_guard::instance_id()));
+    }
+    
     reqwest::ClientBuilder::new()
+        .default_headers(default_headers)
         .timeout(config.http_client_timeout())
-        .pool_idle_timeout(config.http_client_pool_idle_timeout())
+        .connect_timeout(config.connect_timeout())
+        .pool_idle_timeout(Duration::from_secs(pool.idle_timeout))
         .user_agent(&config.dns.http_client.request.user_agent)
-        .pool_max_idle_per_host(config.dns.http_client.pool.max_idle_connections as usize)
+        .pool_max_idle_per_host(pool.max_idle_connections as usize)
+        .connector_layer(ConnectionMetricsLayer::new(pool_name))
+        .use_preconfigured_tls(config.dns.http_client.tls.client_config()?)
         .build()
         .map_err(|e| error::ServerError::Http(format!("Failed to create HTTP client: {}", e)))
 }
@@ -45,12 +89,20 @@ pub struct DoHServer {
     config: ServerConfig,
     // 是否启用调试模式
     debug: bool,
+    // 自定义上游解析器（目标上游 -> 解析器）
+    custom_resolvers: Vec<(UpstreamSelection, Arc<dyn UpstreamResolver>)>,
 }
 
 impl DoHServer {
     // 创建新的 DoH 服务器
     pub fn new(config: ServerConfig, debug: bool) -> Self {
-        Self { config, debug }
+        Self { config, debug, custom_resolvers: Vec::new() }
+    }
+    
+    // 为全局上游或指定上游组注册自定义解析器，优先于内置传输
+    pub fn with_upstream_resolver(mut self, selection: UpstreamSelection, resolver: Arc<dyn UpstreamResolver>) -> Self {
+        self.custom_resolvers.push((selection, resolver));
+        self
     }
 
     // 此方法构建 Axum 应用和相关资源，但不启动服务器。
@@ -61,19 +113,61 @@ impl DoHServer {
         AxumRouter,
         Arc<DnsCache>,
     )> {
-        let cache = Arc::new(DnsCache::new(self.config.dns.cache.clone()));
+        let cache = Arc::new(DnsCache::try_new(self.config.dns.cache.clone())?);
         let client = create_http_client(&self.config)?;
         let router_manager = Arc::new(DnsRouter::new(self.config.dns.routing.clone(), Some(client.clone())).await?);
-        let upstream_manager = Arc::new(UpstreamManager::new(Arc::new(self.config.clone()), client.clone()).await?);
+        let mut upstream_manager = UpstreamManager::new(Arc::new(self.config.clone()), client.clone()).await?;
+        for (selection, resolver) in &self.custom_resolvers {
+            upstream_manager.register_resolver(selection, resolver.clone())?;
+        }
+        let upstream_manager = Arc::new(upstream_manager);
 
         let state = ServerState {
             config: self.config.clone(),
             upstream: upstream_manager,
-            router: router_manager,
+            router: router_manager.clone(),
             cache: cache.clone(),
+            duplicates: Arc::new(DuplicateQueryDetector::new(&self.config.dns.duplicate_detection)),
+            profile: None,
+            query_log: Arc::new(QueryLogger::new(&self.config.dns.query_log)?),
+            dnstap: Arc::new(DnstapLogger::new(&self.config.dns.dnstap)?),
+            quota: Arc::new(DailyQueryQuota::new(&self.config.http.rate_limit)?),
+            ddr: DdrResponder::new(&self.config)?.map(Arc::new),
+            trust_anchor: TrustAnchorResponder::new(&self.config.dns.dnssec.local_trust_anchor)?.map(Arc::new),
+            plugin: ResponsePlugin::new(&self.config.dns.plugins)?,
+            group_override_cidrs: Arc::new(parse_cidrs(&self.config.dns.routing.group_override.trusted_cidrs)?),
         };
 
-        let mut doh_specific_routes = doh_routes(state);
+        // 启用时启动端到端合成探测：经路由与上游解析，绕过缓存与查询日志
+        let health_probe = self.config.http.health.probe.enabled.then(|| {
+            let probe = Arc::new(HealthProbe::new(self.config.http.health.probe.clone()));
+            probe.spawn(ServerState {
+                cache: Arc::new(DnsCache::disabled()),
+                duplicates: Arc::new(DuplicateQueryDetector::new(&DuplicateDetectionConfig { enabled: false, ..Default::default() })),
+                query_log: Arc::new(QueryLogger::disabled()),
+                dnstap: Arc::new(DnstapLogger::disabled()),
+                quota: Arc::new(DailyQueryQuota::disabled()),
+                ..state.clone()
+            });
+            probe
+        });
+
+        let mut doh_specific_routes = doh_routes(state.clone());
+        
+        // 为每个路由配置档案挂载独立的 DoH 路径，共享缓存与上游，仅默认上游组不同
+        for profile in &self.config.dns.routing.profiles {
+            info!(
+                profile = %profile.name,
+                path_prefix = %profile.path_prefix,
+                upstream_group = %profile.default_upstream_group,
+                "Routing profile enabled"
+            );
+            let profile_state = ServerState {
+                profile: Some(profile.clone()),
+                ..state.clone()
+            };
+            doh_specific_routes = doh_specific_routes.nest(&profile.path_prefix, doh_routes(profile_state));
+        }
         
         let rate_limit_config = &self.config.http.rate_limit;
         if rate_limit_config.enabled {
@@ -100,17 +194,35 @@ impl DoHServer {
         if self.debug {
             // info!("Debug mode enabled: Swagger UI available at /swagger");
             // app = app.merge(swagger::create_swagger_routes());
-            info!("Debug mode enabled: Scalar UI available at /scalar");
-            app = app.merge(scalar::create_scalar_routes());
+            info!("Debug mode enabled: Scalar UI available at {}/scalar", self.config.http.base_path);
+            app = app.merge(scalar::create_scalar_routes(&self.config.http.base_path));
         }
 
         // 添加健康检查和指标路由
         // 放在doh_specific_routes之前，放置被限速
-        app = app.merge(health_routes()).merge(metrics_routes());
+        app = app.merge(health_routes_with_readiness(health_probe, Some(router_manager.clone()))).merge(metrics_routes());
+
+        // 启用时添加管理接口路由（不受速率限制，依赖令牌认证）
+        if self.config.http.admin.enabled {
+            info!("Admin API enabled");
+            app = app.merge(admin_routes(AdminState {
+                token: Arc::new(self.config.http.admin.token.clone()),
+                cache: cache.clone(),
+                router: router_manager,
+                config: Arc::new(self.config.clone()),
+            }));
+        }
 
         // 添加doh_specific_routes
         app = app.merge(doh_specific_routes);
 
+        // 配置了公共路径前缀时，将整个应用挂载到该前缀下
+        let base_path = &self.config.http.base_path;
+        if !base_path.is_empty() {
+            info!(base_path = %base_path, "All routes mounted under base path");
+            app = AxumRouter::new().nest(base_path, app);
+        }
+
         Ok((app, cache))
     }
 }
@@ -0,0 +1,238 @@
+// src/server/plugin.rs
+
+// 应答修改插件：返回客户端前以查询与应答调用 Rhai 脚本的 on_response 函数
+//
+// 脚本接口：
+//   fn on_response(query, response)
+//   - query:    #{ name, type, class, client_ip }
+//   - response: #{ rcode, answers: [#{ name, type, ttl, data }] }
+// 返回修改后的 response 映射（可附加 ede: #{ code, text }），返回 () 表示不修改。
+// A、AAAA、CNAME、NS、PTR、TXT 记录可由脚本新建或改写，其他类型仅可原样保留（可修改 TTL）。
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use hickory_proto::op::Message;
+#[cfg(feature = "plugins")]
+use tracing::{info, warn};
+use crate::server::config::PluginConfig;
+use crate::server::error::{Result, ServerError};
+#[cfg(feature = "plugins")]
+use crate::server::metrics::METRICS;
+
+// 插件调用结果标签值
+#[cfg(feature = "plugins")]
+const PLUGIN_RESULT_MODIFIED: &str = "modified";
+#[cfg(feature = "plugins")]
+const PLUGIN_RESULT_UNCHANGED: &str = "unchanged";
+#[cfg(feature = "plugins")]
+const PLUGIN_RESULT_ERROR: &str = "error";
+
+// 脚本入口函数名
+#[cfg(feature = "plugins")]
+const PLUGIN_ENTRY_FUNCTION: &str = "on_response";
+
+// 应答修改插件
+pub struct ResponsePlugin {
+    // 脚本引擎（限制单次调用的操作数）
+    #[cfg(feature = "plugins")]
+    engine: rhai::Engine,
+    // 编译后的脚本
+    #[cfg(feature = "plugins")]
+    ast: rhai::AST,
+}
+
+impl ResponsePlugin {
+    // 从配置加载插件，未启用时返回 None
+    pub fn new(config: &PluginConfig) -> Result<Option<Arc<Self>>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        Self::load(config).map(|plugin| Some(Arc::new(plugin)))
+    }
+
+    #[cfg(feature = "plugins")]
+    fn load(config: &PluginConfig) -> Result<Self> {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(config.max_operations);
+
+        let ast = engine
+            .compile_file(config.script_path.clone().into())
+            .map_err(|e| ServerError::Plugin(format!("Failed to compile plugin script {}: {}", config.script_path, e)))?;
+        if !ast.iter_functions().any(|f| f.name == PLUGIN_ENTRY_FUNCTION && f.params.len() == 2) {
+            return Err(ServerError::Plugin(format!(
+                "Plugin script {} does not define {}(query, response)", config.script_path, PLUGIN_ENTRY_FUNCTION
+            )));
+        }
+
+        info!(script = %config.script_path, max_operations = config.max_operations, "Response plugin loaded");
+        Ok(Self { engine, ast })
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    fn load(_config: &PluginConfig) -> Result<Self> {
+        Err(ServerError::Plugin(
+            "plugins are enabled but this build does not include the 'plugins' feature".to_string()
+        ))
+    }
+
+    // 以查询与应答调用脚本，脚本返回修改后的应答时替换应答码与应答节，返回是否修改
+    // 脚本出错时记录警告并保留原应答
+    #[cfg(feature = "plugins")]
+    pub fn apply(&self, query: &Message, response: &mut Message, client_ip: IpAddr) -> bool {
+        let (result, modified) = match script::invoke(&self.engine, &self.ast, query, response, client_ip) {
+            Ok(modified) => (if modified { PLUGIN_RESULT_MODIFIED } else { PLUGIN_RESULT_UNCHANGED }, modified),
+            Err(e) => {
+                warn!(query_id = query.id(), error = %e, "Response plugin failed, returning the original response");
+                (PLUGIN_RESULT_ERROR, false)
+            },
+        };
+        METRICS.plugin_invocations_total().with_label_values(&[result]).inc();
+        modified
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn apply(&self, _query: &Message, _response: &mut Message, _client_ip: IpAddr) -> bool {
+        false
+    }
+}
+
+// 查询/应答与脚本值之间的转换
+#[cfg(feature = "plugins")]
+mod script {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::str::FromStr;
+    use hickory_proto::op::{Message, ResponseCode};
+    use hickory_proto::rr::rdata::{A, AAAA, CNAME, NS, PTR, TXT};
+    use hickory_proto::rr::{Name, RData, Record, RecordType};
+    use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+    use crate::server::ede::ExtendedError;
+    use crate::server::error::{Result, ServerError};
+    use super::PLUGIN_ENTRY_FUNCTION;
+
+    // 调用脚本并将返回值写回应答，返回是否修改
+    pub(super) fn invoke(engine: &Engine, ast: &AST, query: &Message, response: &mut Message, client_ip: IpAddr) -> Result<bool> {
+        let output: Dynamic = engine
+            .call_fn(&mut Scope::new(), ast, PLUGIN_ENTRY_FUNCTION, (query_map(query, client_ip), response_map(response)))
+            .map_err(|e| ServerError::Plugin(e.to_string()))?;
+        if output.is_unit() {
+            return Ok(false);
+        }
+
+        let output = output.try_cast::<Map>()
+            .ok_or_else(|| ServerError::Plugin(format!("{} must return a map or ()", PLUGIN_ENTRY_FUNCTION)))?;
+
+        let response_code: ResponseCode = match output.get("rcode") {
+            Some(rcode) => u16::try_from(int_value(rcode, "rcode")?).map_err(|_| invalid("rcode"))?.into(),
+            None => response.response_code(),
+        };
+        let answers = match output.get("answers") {
+            Some(answers) => {
+                let answers = answers.clone().try_cast::<Array>().ok_or_else(|| invalid("answers"))?;
+                answers.into_iter()
+                    .map(|answer| record_from_value(answer, response.answers()))
+                    .collect::<Result<Vec<_>>>()?
+            },
+            None => response.answers().to_vec(),
+        };
+        let ede = match output.get("ede") {
+            Some(ede) => {
+                let ede = ede.clone().try_cast::<Map>().ok_or_else(|| invalid("ede"))?;
+                let code = ede.get("code").ok_or_else(|| invalid("ede.code"))?;
+                let code = u16::try_from(int_value(code, "ede.code")?).map_err(|_| invalid("ede.code"))?;
+                let text = ede.get("text").map(|text| string_value(text, "ede.text")).transpose()?;
+                Some(ExtendedError::new(code, text.unwrap_or_default()))
+            },
+            None => None,
+        };
+
+        response.set_response_code(response_code);
+        response.take_answers();
+        response.insert_answers(answers);
+        if let Some(ede) = ede {
+            ede.attach_to_message(response);
+        }
+        Ok(true)
+    }
+
+    fn query_map(query: &Message, client_ip: IpAddr) -> Dynamic {
+        let mut map = Map::new();
+        if let Some(question) = query.queries().first() {
+            map.insert("name".into(), question.name().to_utf8().into());
+            map.insert("type".into(), question.query_type().to_string().into());
+            map.insert("class".into(), question.query_class().to_string().into());
+        }
+        map.insert("client_ip".into(), client_ip.to_string().into());
+        Dynamic::from_map(map)
+    }
+
+    fn response_map(response: &Message) -> Dynamic {
+        let mut map = Map::new();
+        map.insert("rcode".into(), (u16::from(response.response_code()) as i64).into());
+        let answers: Array = response.answers().iter().map(record_map).collect();
+        map.insert("answers".into(), Dynamic::from_array(answers));
+        Dynamic::from_map(map)
+    }
+
+    fn record_map(record: &Record) -> Dynamic {
+        let mut map = Map::new();
+        map.insert("name".into(), record.name().to_utf8().into());
+        map.insert("type".into(), record.record_type().to_string().into());
+        map.insert("ttl".into(), (record.ttl() as i64).into());
+        map.insert("data".into(), rdata_text(record).into());
+        Dynamic::from_map(map)
+    }
+
+    // 记录数据的文本形式：TXT 为拼接后的文本，其他类型使用标准表示
+    fn rdata_text(record: &Record) -> String {
+        match record.data() {
+            Some(RData::TXT(txt)) => txt.txt_data().iter().map(|part| String::from_utf8_lossy(part)).collect(),
+            Some(data) => data.to_string(),
+            None => String::new(),
+        }
+    }
+
+    // 由脚本返回的记录构建应答记录：支持的类型直接构建，其他类型需与原应答中的记录一致
+    fn record_from_value(value: Dynamic, originals: &[Record]) -> Result<Record> {
+        let map = value.try_cast::<Map>().ok_or_else(|| invalid("answers[]"))?;
+        let name = string_value(map.get("name").ok_or_else(|| invalid("answers[].name"))?, "answers[].name")?;
+        let name = Name::from_utf8(&name).map_err(|_| invalid("answers[].name"))?;
+        let record_type = string_value(map.get("type").ok_or_else(|| invalid("answers[].type"))?, "answers[].type")?;
+        let record_type = RecordType::from_str(&record_type.to_uppercase()).map_err(|_| invalid("answers[].type"))?;
+        let ttl = int_value(map.get("ttl").ok_or_else(|| invalid("answers[].ttl"))?, "answers[].ttl")?;
+        let ttl = u32::try_from(ttl).map_err(|_| invalid("answers[].ttl"))?;
+        let data = string_value(map.get("data").ok_or_else(|| invalid("answers[].data"))?, "answers[].data")?;
+
+        let parse_name = |data: &str| Name::from_utf8(data).map_err(|_| invalid("answers[].data"));
+        let rdata = match record_type {
+            RecordType::A => RData::A(A(Ipv4Addr::from_str(&data).map_err(|_| invalid("answers[].data"))?)),
+            RecordType::AAAA => RData::AAAA(AAAA(Ipv6Addr::from_str(&data).map_err(|_| invalid("answers[].data"))?)),
+            RecordType::CNAME => RData::CNAME(CNAME(parse_name(&data)?)),
+            RecordType::NS => RData::NS(NS(parse_name(&data)?)),
+            RecordType::PTR => RData::PTR(PTR(parse_name(&data)?)),
+            RecordType::TXT => RData::TXT(TXT::new(vec![data])),
+            _ => {
+                let original = originals.iter()
+                    .find(|record| record.record_type() == record_type && record.name() == &name && rdata_text(record) == data)
+                    .ok_or_else(|| ServerError::Plugin(format!(
+                        "{} records can only be kept from the original response", record_type
+                    )))?;
+                let mut record = original.clone();
+                record.set_ttl(ttl);
+                return Ok(record);
+            },
+        };
+        Ok(Record::from_rdata(name, ttl, rdata))
+    }
+
+    fn int_value(value: &Dynamic, field: &str) -> Result<i64> {
+        value.as_int().map_err(|_| invalid(field))
+    }
+
+    fn string_value(value: &Dynamic, field: &str) -> Result<String> {
+        value.clone().into_string().map_err(|_| invalid(field))
+    }
+
+    fn invalid(field: &str) -> ServerError {
+        ServerError::Plugin(format!("Invalid value for '{}' returned by {}", field, PLUGIN_ENTRY_FUNCTION))
+    }
+}
@@ -0,0 +1,220 @@
+// src/server/query_limits.rs
+
+use std::fmt;
+use crate::common::consts::{
+    DNS_HEADER_SIZE, DNS_RECORD_TYPE_OPT, MAX_DOMAIN_NAME_LENGTH, MAX_LABEL_LENGTH, MAX_WIRE_NAME_LENGTH,
+};
+use crate::server::config::QueryLimitsConfig;
+use crate::server::error::{Result, ServerError};
+
+// 查询限制违规原因（指标标签）
+const VIOLATION_QUERY_SIZE: &str = "query_size";
+const VIOLATION_EDNS_OPTIONS: &str = "edns_options";
+const VIOLATION_QUESTION_COUNT: &str = "question_count";
+const VIOLATION_NAME_LENGTH: &str = "name_length";
+const VIOLATION_LABEL_LENGTH: &str = "label_length";
+
+// JSON API 不接受的查询类型：保留类型 0、OPT、TKEY、TSIG、IXFR、AXFR、MAILB、MAILA
+// 这些类型仅用于报文或区域传送，不是可经 JSON API 解析的记录
+const JSON_REJECTED_QUERY_TYPES: [u16; 8] = [0, DNS_RECORD_TYPE_OPT, 249, 250, 251, 252, 253, 254];
+
+// 客户端查询违反的限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryLimitViolation {
+    // 查询大小超出限制（实际字节数）
+    QuerySize(usize),
+    // EDNS OPT 选项数超出限制（实际选项数）
+    EdnsOptions(usize),
+    // 问题数超出限制（报文头 QDCOUNT）
+    QuestionCount(usize),
+    // 问题节名称超出 255 字节（实际线格式长度）
+    NameLength(usize),
+    // 问题节标签超出 63 字节（标签长度字节的值）
+    LabelLength(usize),
+}
+
+impl QueryLimitViolation {
+    // 指标标签
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::QuerySize(_) => VIOLATION_QUERY_SIZE,
+            Self::EdnsOptions(_) => VIOLATION_EDNS_OPTIONS,
+            Self::QuestionCount(_) => VIOLATION_QUESTION_COUNT,
+            Self::NameLength(_) => VIOLATION_NAME_LENGTH,
+            Self::LabelLength(_) => VIOLATION_LABEL_LENGTH,
+        }
+    }
+}
+
+impl fmt::Display for QueryLimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::QuerySize(size) => write!(f, "query size {} bytes exceeds limit", size),
+            Self::EdnsOptions(count) => write!(f, "EDNS OPT record carries {} options, exceeding limit", count),
+            Self::QuestionCount(count) => write!(f, "query carries {} questions, exceeding limit", count),
+            Self::NameLength(length) => write!(f, "query name length {} bytes exceeds limit of {} bytes", length, MAX_WIRE_NAME_LENGTH),
+            Self::LabelLength(length) => write!(f, "query name label length {} exceeds limit of {} bytes", length, MAX_LABEL_LENGTH),
+        }
+    }
+}
+
+// 检查线格式查询是否超出限制，未启用或未超出时返回 None
+pub fn check_query_limits(config: &QueryLimitsConfig, wire: &[u8]) -> Option<QueryLimitViolation> {
+    if !config.enabled {
+        return None;
+    }
+
+    if wire.len() > config.max_query_size {
+        return Some(QueryLimitViolation::QuerySize(wire.len()));
+    }
+
+    // 压缩指针使每个问题仅占数字节，需在解析前按 QDCOUNT 拒绝，避免单个请求展开为大量上游查询
+    if wire.len() >= DNS_HEADER_SIZE {
+        let question_count = u16::from_be_bytes([wire[4], wire[5]]) as usize;
+        if question_count > config.max_questions {
+            return Some(QueryLimitViolation::QuestionCount(question_count));
+        }
+    }
+
+    if let Some(violation) = check_question_names(wire) {
+        return Some(violation);
+    }
+
+    // 解析后的 EDNS 选项以选项码去重，需在线格式上计数才能发现重复填充
+    match count_edns_options(wire) {
+        Some(count) if count > config.max_edns_options => Some(QueryLimitViolation::EdnsOptions(count)),
+        _ => None,
+    }
+}
+
+// 检查 JSON API 请求：请求大小、域名长度与查询类型，在构建查询前拒绝滥用的输入
+// 未启用查询限制时仅检查域名长度与查询类型
+pub fn check_json_request_limits(config: &QueryLimitsConfig, request_size: usize, name: &str, type_value: u16) -> Result<()> {
+    if config.enabled && request_size > config.max_json_request_size {
+        return Err(ServerError::Http(format!(
+            "Request size {} bytes exceeds limit of {} bytes", request_size, config.max_json_request_size
+        )));
+    }
+
+    let name_length = name.strip_suffix('.').unwrap_or(name).len();
+    if name_length > MAX_DOMAIN_NAME_LENGTH {
+        return Err(ServerError::Http(format!(
+            "Domain name length {} exceeds limit of {} characters", name_length, MAX_DOMAIN_NAME_LENGTH
+        )));
+    }
+
+    if let Some(label_length) = name.split('.').map(str::len).find(|length| *length > MAX_LABEL_LENGTH) {
+        return Err(ServerError::Http(format!(
+            "Domain name label length {} exceeds limit of {} bytes", label_length, MAX_LABEL_LENGTH
+        )));
+    }
+
+    if JSON_REJECTED_QUERY_TYPES.contains(&type_value) {
+        return Err(ServerError::Http(format!("Unsupported query type: {}", type_value)));
+    }
+
+    Ok(())
+}
+
+// 统计线格式报文中 OPT 记录的选项数（含重复选项），无 OPT 记录或报文格式异常时返回 None
+pub fn count_edns_options(wire: &[u8]) -> Option<usize> {
+    if wire.len() < DNS_HEADER_SIZE {
+        return None;
+    }
+
+    let section_count = |index: usize| u16::from_be_bytes([wire[4 + index * 2], wire[5 + index * 2]]) as usize;
+    let question_count = section_count(0);
+    let record_count = section_count(1) + section_count(2) + section_count(3);
+
+    let mut offset = DNS_HEADER_SIZE;
+    for _ in 0..question_count {
+        // QNAME + QTYPE(2) + QCLASS(2)
+        offset = skip_name(wire, offset)? + 4;
+    }
+
+    for _ in 0..record_count {
+        // NAME + TYPE(2) + CLASS(2) + TTL(4) + RDLENGTH(2) + RDATA
+        offset = skip_name(wire, offset)?;
+        let header = wire.get(offset..offset + 10)?;
+        let record_type = u16::from_be_bytes([header[0], header[1]]);
+        let rdlength = u16::from_be_bytes([header[8], header[9]]) as usize;
+        offset += 10;
+        let rdata = wire.get(offset..offset + rdlength)?;
+        offset += rdlength;
+
+        if record_type == DNS_RECORD_TYPE_OPT {
+            return count_options(rdata);
+        }
+    }
+
+    None
+}
+
+// 检查问题节名称的标签与总长度（RFC 1035），报文格式异常时交由解析器处理
+// 超长的名称无法被解析，需在线格式上检查才能以 FORMERR 应答
+fn check_question_names(wire: &[u8]) -> Option<QueryLimitViolation> {
+    if wire.len() < DNS_HEADER_SIZE {
+        return None;
+    }
+
+    let question_count = u16::from_be_bytes([wire[4], wire[5]]) as usize;
+    let mut offset = DNS_HEADER_SIZE;
+    for _ in 0..question_count {
+        let mut name_length = 0;
+        loop {
+            let length = *wire.get(offset)? as usize;
+            match length {
+                0 => {
+                    name_length += 1;
+                    offset += 1;
+                    break;
+                },
+                // 压缩指针：指向的名称已在之前的问题中检查
+                l if l & 0xC0 == 0xC0 => {
+                    offset += 2;
+                    break;
+                },
+                // 长度字节高两位非 00 且非压缩指针时，标签长度超出 63 字节
+                l if l > MAX_LABEL_LENGTH => return Some(QueryLimitViolation::LabelLength(l)),
+                l => {
+                    name_length += 1 + l;
+                    offset += 1 + l;
+                },
+            }
+        }
+        if name_length > MAX_WIRE_NAME_LENGTH {
+            return Some(QueryLimitViolation::NameLength(name_length));
+        }
+        // QTYPE(2) + QCLASS(2)
+        offset += 4;
+    }
+
+    None
+}
+
+// 统计 OPT RDATA 中的选项数：每个选项为 CODE(2) + LENGTH(2) + DATA
+fn count_options(rdata: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    let mut count = 0;
+    while offset < rdata.len() {
+        let length = rdata.get(offset + 2..offset + 4)?;
+        offset += 4 + u16::from_be_bytes([length[0], length[1]]) as usize;
+        count += 1;
+    }
+
+    (offset == rdata.len()).then_some(count)
+}
+
+// 跳过报文中的域名，返回域名之后的偏移
+fn skip_name(wire: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let length = *wire.get(offset)? as usize;
+        match length {
+            // 根标签，域名结束
+            0 => return Some(offset + 1),
+            // 压缩指针（2 字节），域名结束
+            l if l & 0xC0 == 0xC0 => return Some(offset + 2),
+            l => offset += 1 + l,
+        }
+    }
+}
@@ -0,0 +1,212 @@
+// src/server/query_log.rs
+
+use std::fs::{self, File, OpenOptions};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use hickory_proto::op::Message;
+use serde::Serialize;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{info, warn};
+use crate::server::config::{QueryLogConfig, QueryLogFormat};
+use crate::server::error::{Result, ServerError};
+use crate::server::metrics::METRICS;
+
+// 查询日志缓存状态
+pub const QUERY_LOG_CACHE_HIT: &str = "hit";
+pub const QUERY_LOG_CACHE_MISS: &str = "miss";
+
+// 单条查询日志记录
+#[derive(Debug, Serialize)]
+struct QueryLogRecord<'a> {
+    // 记录时间（Unix 时间戳，毫秒）
+    timestamp_ms: u128,
+    // 客户端 IP
+    client: IpAddr,
+    // 查询名
+    name: String,
+    // 查询类型
+    #[serde(rename = "type")]
+    record_type: String,
+    // 响应码
+    rcode: String,
+    // 处理查询的上游组（缓存命中时为 None）
+    group: Option<&'a str>,
+    // 缓存状态
+    cache: &'a str,
+}
+
+// 查询日志（审计），独立于 tracing 运行日志
+// 记录经有界队列交由后台任务写出，队列满时丢弃，请求处理不等待磁盘写入
+pub struct QueryLogger {
+    // 记录格式
+    format: QueryLogFormat,
+    // 记录发送端，禁用时为 None
+    sender: Option<mpsc::Sender<String>>,
+}
+
+impl QueryLogger {
+    // 根据配置创建查询日志，启用时打开（追加）日志文件并启动后台写出任务
+    pub fn new(config: &QueryLogConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+
+        let file = open(&config.path)?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        info!(path = %config.path, format = ?config.format, "Query log enabled");
+
+        let (sender, receiver) = mpsc::channel(config.buffer_size);
+        let writer = QueryLogWriter {
+            file: BufWriter::new(tokio::fs::File::from_std(file)),
+            written,
+            path: config.path.clone(),
+            max_bytes: config.max_size_mb * 1024 * 1024,
+            max_files: config.max_files,
+        };
+        tokio::spawn(run_writer(writer, receiver));
+
+        Ok(Self {
+            format: config.format,
+            sender: Some(sender),
+        })
+    }
+
+    // 创建禁用的查询日志
+    pub fn disabled() -> Self {
+        Self {
+            format: QueryLogFormat::default(),
+            sender: None,
+        }
+    }
+
+    // 是否启用
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    // 记录一次已完成的查询
+    pub fn log(&self, client_ip: IpAddr, query: &Message, response: &Message, group: Option<&str>, cached: bool) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let (name, record_type) = match query.queries().first() {
+            Some(q) => (q.name().to_utf8(), q.query_type().to_string()),
+            None => (String::new(), String::new()),
+        };
+        let record = QueryLogRecord {
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0),
+            client: client_ip,
+            name,
+            record_type,
+            rcode: response.response_code().to_string(),
+            group,
+            cache: if cached { QUERY_LOG_CACHE_HIT } else { QUERY_LOG_CACHE_MISS },
+        };
+
+        let mut line = match self.format {
+            QueryLogFormat::Json => serde_json::to_string(&record).unwrap_or_default(),
+            QueryLogFormat::Text => format!(
+                "{} {} {} {} {} group={} cache={}",
+                record.timestamp_ms,
+                record.client,
+                record.name,
+                record.record_type,
+                record.rcode,
+                record.group.unwrap_or("-"),
+                record.cache,
+            ),
+        };
+        line.push('\n');
+
+        match sender.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) | Err(TrySendError::Closed(_)) => {
+                METRICS.query_log_records_dropped_total().inc();
+            }
+        }
+    }
+}
+
+// 查询日志文件写入器，由后台任务独占，按大小轮转
+struct QueryLogWriter {
+    // 当前日志文件
+    file: BufWriter<tokio::fs::File>,
+    // 当前文件已写入字节数
+    written: u64,
+    // 日志文件路径
+    path: String,
+    // 单个文件最大字节数，0 表示不轮转
+    max_bytes: u64,
+    // 保留的轮转文件数
+    max_files: u32,
+}
+
+impl QueryLogWriter {
+    // 写入一行，超出大小限制时先轮转
+    async fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if self.max_bytes > 0 && self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            self.rotate().await?;
+        }
+
+        self.file.write_all(line).await?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    // 轮转日志文件：path.N-1 -> path.N，...，path -> path.1，超出 max_files 的最旧文件被删除
+    async fn rotate(&mut self) -> Result<()> {
+        let path = &self.path;
+        self.file.flush().await?;
+
+        let _ = tokio::fs::remove_file(format!("{}.{}", path, self.max_files)).await;
+        for index in (1..self.max_files).rev() {
+            let from = format!("{}.{}", path, index);
+            if tokio::fs::try_exists(&from).await.unwrap_or(false) {
+                tokio::fs::rename(&from, format!("{}.{}", path, index + 1)).await?;
+            }
+        }
+        tokio::fs::rename(path, format!("{}.1", path)).await?;
+
+        self.file = BufWriter::new(tokio::fs::File::from_std(open(path)?));
+        self.written = 0;
+        info!(path = %path, "Query log rotated");
+        Ok(())
+    }
+}
+
+// 后台写出任务：队列清空时刷新，发送端全部释放后刷新并退出
+async fn run_writer(mut writer: QueryLogWriter, mut receiver: mpsc::Receiver<String>) {
+    while let Some(line) = receiver.recv().await {
+        let mut result = writer.write_line(line.as_bytes()).await;
+        // 队列已清空时刷新，避免逐条系统调用
+        if result.is_ok() && receiver.is_empty() {
+            result = writer.file.flush().await.map_err(Into::into);
+        }
+        if let Err(e) = result {
+            METRICS.query_log_records_dropped_total().inc();
+            warn!(path = %writer.path, error = %e, "Failed to write query log record");
+        }
+    }
+
+    if let Err(e) = writer.file.flush().await {
+        warn!(path = %writer.path, error = %e, "Failed to flush query log");
+    }
+}
+
+// 以追加模式打开日志文件，必要时创建父目录
+fn open(path: &str) -> Result<File> {
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| ServerError::Config(format!("Failed to open query log {}: {}", path, e)))
+}
@@ -3,16 +3,21 @@
 use std::collections::{HashMap, HashSet, BTreeMap};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use hickory_proto::rr::RecordType;
 use lazy_static::lazy_static;
 use regex::Regex;
-use tokio::sync::RwLock as AsyncRwLock;
+use serde::Serialize;
+use tokio::sync::{Notify, RwLock as AsyncRwLock, Semaphore};
 use tracing::{debug, error, info, warn};
 use reqwest::Client;
-use tokio::time::{Duration, interval};
+use tokio::time::{Duration, Instant, interval, sleep, timeout_at};
 use xxhash_rust::xxh64::xxh64;
 
-use crate::server::config::{RoutingConfig, MatchType};
+use crate::server::config::{RoutingConfig, MatchType, MatchCondition, GroupNotReadyPolicy, DefaultRouteAction, FailClosedAction};
+use crate::server::blackhole::BlackholeResponder;
 use crate::server::error::{ServerError, Result};
 use crate::common::consts::{
     BLACKHOLE_UPSTREAM_GROUP_NAME,
@@ -32,12 +37,60 @@ const ROUTE_RESULT_BLACKHOLE: &str = "blackhole";
 const ROUTE_RESULT_RULE_MATCH: &str = "rule_match";
 const ROUTE_RESULT_DEFAULT: &str = "default";
 const ROUTE_RESULT_GLOBAL: &str = "global";
+const ROUTE_RESULT_REFUSED: &str = "refused";
+
+// 上游组未就绪时的处理结果标签值
+const NOT_READY_ACTION_WAITED: &str = "waited";
+const NOT_READY_ACTION_GLOBAL: &str = "global";
+const NOT_READY_ACTION_FAIL_CLOSED: &str = "fail_closed";
 
 // URL规则更新相关常量
 const URL_RULE_UPDATE_STATUS_SUCCESS: &str = "success";
 const URL_RULE_UPDATE_STATUS_FAILED: &str = "failed";
 const URL_RULE_UPDATE_STATUS_UNCHANGED: &str = "unchanged";
 
+// 规则刷新来源类型
+pub const RULE_SOURCE_FILE: &str = "file";
+pub const RULE_SOURCE_URL: &str = "url";
+
+// 单个规则来源的刷新结果
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleRefreshSummary {
+    // 来源类型：file 或 url
+    pub source_type: &'static str,
+    // 文件路径或 URL
+    pub source: String,
+    // 上游组名
+    pub upstream_group: String,
+    // 是否成功加载（URL 内容未变化也视为成功）
+    pub success: bool,
+    // 当前生效的精确匹配规则数
+    pub exact: usize,
+    // 当前生效的通配符规则数
+    pub wildcard: usize,
+    // 当前生效的正则表达式规则数
+    pub regex: usize,
+}
+
+// 将域名规范化为小写的 A-label（punycode）形式并去除尾部的点，保证 Unicode 与 punycode 写法一致匹配
+// 无法进行 IDNA 转换时退回小写形式
+pub fn normalize_domain(domain: &str) -> String {
+    let domain = domain.trim().trim_end_matches('.');
+    if domain.is_ascii() {
+        return domain.to_ascii_lowercase();
+    }
+    
+    idna::domain_to_ascii(domain).unwrap_or_else(|_| domain.to_lowercase())
+}
+
+// 校验正则表达式语法而不编译，用于延迟编译的规则在加载阶段即拒绝无效模式
+pub fn check_regex_syntax(pattern: &str) -> std::result::Result<(), String> {
+    regex_syntax::Parser::new()
+        .parse(pattern)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
 // 路由决策结果
 #[derive(Debug, Clone, PartialEq)]
 pub enum RouteDecision {
@@ -47,6 +100,8 @@ pub enum RouteDecision {
     UseGlobal,
     // 黑洞（阻止查询）
     Blackhole,
+    // 拒绝查询（返回 REFUSED）
+    Refuse,
 }
 
 // 优化的路由引擎核心数据结构
@@ -61,12 +116,62 @@ struct RouterCore {
     global_wildcard: Option<String>,
     
     // 正则表达式规则 - (正则表达式, 上游组名, 原始模式)
-    regex_rules: Vec<(Regex, String, String)>,
+    regex_rules: Vec<(RuleRegex, String, String)>,
     
     // 正则预筛选 - 特征 -> 规则索引集合
     regex_prefilter: HashMap<String, HashSet<usize>>,
 }
 
+// 规则正则表达式 - 预编译，或延迟到首次成为匹配候选时编译并缓存
+struct RuleRegex {
+    // 正则表达式源码
+    source: String,
+    // 编译结果，编译失败（如超出编译大小限制）时为 None（规则不再匹配）
+    compiled: OnceLock<Option<Regex>>,
+}
+
+impl RuleRegex {
+    // 使用已编译的正则表达式
+    fn compiled(regex: Regex) -> Self {
+        Self {
+            source: regex.as_str().to_string(),
+            compiled: OnceLock::from(Some(regex)),
+        }
+    }
+    
+    // 延迟编译，仅保存源码
+    fn lazy(source: String) -> Self {
+        Self {
+            source,
+            compiled: OnceLock::new(),
+        }
+    }
+    
+    // 匹配域名，尚未编译时先编译
+    fn is_match(&self, domain: &str) -> bool {
+        self.compiled
+            .get_or_init(|| {
+                let start = std::time::Instant::now();
+                match Regex::new(&self.source) {
+                    Ok(regex) => {
+                        debug!(
+                            pattern = %self.source,
+                            compile_us = start.elapsed().as_micros() as u64,
+                            "Lazily compiled regex rule"
+                        );
+                        Some(regex)
+                    },
+                    Err(e) => {
+                        warn!(pattern = %self.source, error = %e, "Failed to compile regex rule, rule disabled");
+                        None
+                    },
+                }
+            })
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(domain))
+    }
+}
+
 // URL规则数据结构 - 与之前相同
 #[derive(Debug, Default)]
 struct UrlRules {
@@ -88,12 +193,24 @@ struct WildcardPattern {
     suffix: Option<String>,
 }
 
-// 文件规则数据
-struct FileRuleData {
+// 带查询类型过滤的内联规则数据
+struct TypedRuleData {
     // 规则内容
     core: RouterCore,
+    // 适用的查询类型
+    record_types: HashSet<RecordType>,
+}
+
+// 文件规则数据
+struct FileRuleData {
+    // 文件路径
+    path: String,
+    // 规则内容 - 使用RwLock以支持按需重新加载
+    core: AsyncRwLock<RouterCore>,
     // 上游组名
     upstream_group: String,
+    // 适用的查询类型（None 表示所有类型）
+    record_types: Option<HashSet<RecordType>>,
 }
 
 // URL规则数据
@@ -106,13 +223,26 @@ struct UrlRuleData {
     upstream_group: String,
     // 周期性更新配置
     periodic: Option<PeriodicConfig>,
+    // 适用的查询类型（None 表示所有类型）
+    record_types: Option<HashSet<RecordType>>,
+    // 是否已完成首次加载（未启用周期性更新的规则不会自动加载，始终视为就绪）
+    ready: Arc<AtomicBool>,
 }
 
-// 周期性更新配置 - 与之前相同
+// 周期性更新配置
 #[derive(Debug, Clone)]
 struct PeriodicConfig {
     enabled: bool,
     interval_secs: u64,
+    retry: RetryConfig,
+}
+
+// 获取失败重试配置
+#[derive(Debug, Clone, Copy)]
+struct RetryConfig {
+    max_attempts: u32,
+    initial_backoff_secs: u64,
+    max_backoff_secs: u64,
 }
 
 // DNS 路由器 - 优化重构版
@@ -123,6 +253,9 @@ pub struct Router {
     // 核心路由规则 - 不包括文件和URL规则
     core: RouterCore,
     
+    // 带查询类型过滤的内联规则列表
+    typed_rules: Vec<TypedRuleData>,
+    
     // 文件规则列表
     file_rules: Vec<FileRuleData>,
     
@@ -132,8 +265,35 @@ pub struct Router {
     // 默认上游组名称
     default_upstream_group: Option<String>,
     
+    // 未匹配任何规则且没有默认上游组时的处理方式
+    default_action: DefaultRouteAction,
+    
+    // 黑洞响应生成器
+    blackhole: BlackholeResponder,
+    
     // HTTP客户端（用于URL规则）
     http_client: Option<Client>,
+    
+    // URL规则下载的最大字节数
+    max_rule_download_bytes: u64,
+    
+    // URL规则更新许可，限制同时下载与编译规则的任务数
+    url_update_permits: Arc<Semaphore>,
+    
+    // 是否延迟编译正则规则（内联及文件规则）
+    lazy_regex: bool,
+    
+    // 上游组未就绪时的处理策略
+    not_ready_policy: GroupNotReadyPolicy,
+    
+    // wait 策略下的最长等待时间
+    not_ready_wait: Duration,
+    
+    // URL规则完成首次加载时通知等待中的查询
+    ready_notify: Arc<Notify>,
+    
+    // URL规则完成首次加载前的应答方式（None 表示不拦截）
+    fail_closed_action: Option<FailClosedAction>,
 }
 
 impl Router {
@@ -144,16 +304,35 @@ impl Router {
             return Ok(Self {
                 enabled: false,
                 core: RouterCore::new(),
+                typed_rules: Vec::new(),
                 file_rules: Vec::new(),
                 url_rules: Vec::new(),
                 default_upstream_group: None,
+                default_action: DefaultRouteAction::Global,
+                blackhole: BlackholeResponder::default(),
                 http_client: None,
+                max_rule_download_bytes: routing_config.max_rule_download_bytes,
+                url_update_permits: Arc::new(Semaphore::new(routing_config.max_concurrent_url_updates)),
+                lazy_regex: routing_config.lazy_regex,
+                not_ready_policy: routing_config.not_ready_policy,
+                not_ready_wait: Duration::from_millis(routing_config.not_ready_wait_ms),
+                ready_notify: Arc::new(Notify::new()),
+                fail_closed_action: None,
             });
         }
         
+        // 记录规则编译耗时，用于评估延迟编译正则规则的收益
+        let build_start = std::time::Instant::now();
+        
+        // 创建黑洞响应生成器
+        let blackhole = BlackholeResponder::new(&routing_config.blackhole, routing_config.blackhole_ttl)?;
+        
         // 创建主核心路由结构
         let mut core = RouterCore::new();
         
+        // 带查询类型过滤的内联规则列表
+        let mut typed_rules = Vec::new();
+        
         // 文件规则列表
         let mut file_rules = Vec::new();
         
@@ -169,12 +348,17 @@ impl Router {
         
         // 编译所有规则
         for rule in routing_config.rules {
+            // 解析查询类型过滤，带过滤的内联规则使用独立的核心结构
+            let record_types = Self::parse_record_types(&rule.match_)?;
+            let mut typed_core = RouterCore::new();
+            let target_core = if record_types.is_some() { &mut typed_core } else { &mut core };
+            
             match &rule.match_ {
                 condition if condition.type_ == MatchType::Exact => {
                     // 处理精确匹配规则
                     if let Some(values) = &condition.values {
                         for domain in values {
-                            core.add_exact_rule(domain.clone(), rule.upstream_group.clone());
+                            target_core.add_exact_rule(domain.clone(), rule.upstream_group.clone());
                             exact_count += 1;
                         }
                     }
@@ -184,7 +368,7 @@ impl Router {
                     // 处理通配符规则
                     if let Some(values) = &condition.values {
                         for pattern in values {
-                            core.add_wildcard_rule(pattern.clone(), rule.upstream_group.clone());
+                            target_core.add_wildcard_rule(pattern.clone(), rule.upstream_group.clone());
                             wildcard_count += 1;
                         }
                     }
@@ -194,18 +378,9 @@ impl Router {
                     // 处理正则表达式规则
                     if let Some(values) = &condition.values {
                         for pattern in values {
-                            match Regex::new(pattern) {
-                                Ok(regex) => {
-                                    core.add_regex_rule(pattern.clone(), regex, rule.upstream_group.clone());
-                                    regex_count += 1;
-                                },
-                                Err(e) => {
-                                    return Err(ServerError::RegexCompilation(format!(
-                                        "Failed to compile regex '{}': {}", 
-                                        pattern, e
-                                    )));
-                                }
-                            }
+                            let regex = Self::build_rule_regex(pattern, routing_config.lazy_regex)?;
+                            target_core.add_regex_rule(pattern.clone(), regex, rule.upstream_group.clone());
+                            regex_count += 1;
                         }
                     }
                 },
@@ -213,11 +388,13 @@ impl Router {
                 condition if condition.type_ == MatchType::File => {
                     // 处理文件规则
                     if let Some(path) = &condition.path {
-                        let file_rule_core = Self::load_rules_from_file(path)?;
+                        let file_rule_core = Self::load_rules_from_file(path, routing_config.lazy_regex)?;
                         
                         file_rules.push(FileRuleData {
-                            core: file_rule_core,
+                            path: path.clone(),
+                            core: AsyncRwLock::new(file_rule_core),
                             upstream_group: rule.upstream_group.clone(),
+                            record_types: record_types.clone(),
                         });
                         
                         file_count += 1;
@@ -234,13 +411,22 @@ impl Router {
                         let periodic = condition.periodic.as_ref().map(|p| PeriodicConfig {
                             enabled: p.enabled,
                             interval_secs: p.interval_secs,
+                            retry: RetryConfig {
+                                max_attempts: p.retry.max_attempts,
+                                initial_backoff_secs: p.retry.initial_backoff_secs,
+                                max_backoff_secs: p.retry.max_backoff_secs,
+                            },
                         });
                         
+                        // 仅由周期性更新任务自动加载的规则在首次加载前未就绪
+                        let auto_load = periodic.as_ref().is_some_and(|p| p.enabled);
                         url_rules.push(UrlRuleData {
                             url: url.clone(),
                             rules,
                             upstream_group: rule.upstream_group.clone(),
                             periodic,
+                            record_types: record_types.clone(),
+                            ready: Arc::new(AtomicBool::new(!auto_load)),
                         });
                         
                         url_count += 1;
@@ -251,6 +437,16 @@ impl Router {
                     return Err(ServerError::InvalidRuleFormat("Unknown match type".to_string()));
                 }
             }
+            
+            // 保存带查询类型过滤的内联规则
+            if let Some(record_types) = record_types {
+                if matches!(rule.match_.type_, MatchType::Exact | MatchType::Wildcard | MatchType::Regex) {
+                    typed_rules.push(TypedRuleData {
+                        core: typed_core,
+                        record_types,
+                    });
+                }
+            }
         }
         
         // 记录规则计数指标 - 确保所有类型的计数都被更新
@@ -262,14 +458,32 @@ impl Router {
             METRICS.route_rules().with_label_values(&[ROUTE_RULE_TYPE_URL]).set(url_count as f64);
         }
         
+        info!(
+            regex_rules = regex_count,
+            file_rules = file_count,
+            lazy_regex = routing_config.lazy_regex,
+            build_ms = build_start.elapsed().as_millis() as u64,
+            "Routing rules compiled"
+        );
+        
         // 创建路由器实例
         let router = Self {
             enabled: true,
             core,
+            typed_rules,
             file_rules,
             url_rules,
             default_upstream_group: routing_config.default_upstream_group,
+            default_action: routing_config.default_action,
+            blackhole,
             http_client,
+            max_rule_download_bytes: routing_config.max_rule_download_bytes,
+            url_update_permits: Arc::new(Semaphore::new(routing_config.max_concurrent_url_updates)),
+            lazy_regex: routing_config.lazy_regex,
+            not_ready_policy: routing_config.not_ready_policy,
+            not_ready_wait: Duration::from_millis(routing_config.not_ready_wait_ms),
+            ready_notify: Arc::new(Notify::new()),
+            fail_closed_action: routing_config.fail_closed_until_loaded.then_some(routing_config.fail_closed_action),
         };
         
         // 启动URL规则更新任务
@@ -278,8 +492,97 @@ impl Router {
         Ok(router)
     }
     
-    // 匹配域名，返回路由决策 - 主要入口方法
-    pub async fn match_domain(&self, domain: &str) -> RouteDecision {
+    // 获取黑洞响应生成器
+    pub fn blackhole(&self) -> &BlackholeResponder {
+        &self.blackhole
+    }
+    
+    // 上游组是否就绪：指向该组的URL规则均已完成首次加载
+    pub fn group_ready(&self, group: &str) -> bool {
+        self.url_rules.iter()
+            .filter(|rule| rule.upstream_group == group)
+            .all(|rule| rule.ready.load(Ordering::Acquire))
+    }
+    
+    // 尚未就绪的上游组名称（已排序、去重）
+    pub fn pending_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self.url_rules.iter()
+            .filter(|rule| !rule.ready.load(Ordering::Acquire))
+            .map(|rule| rule.upstream_group.clone())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
+    
+    // 是否处于 fail-closed 窗口期：启用 fail_closed_until_loaded 且仍有URL规则未完成首次加载
+    pub fn failing_closed(&self) -> bool {
+        self.fail_closed_action.is_some() && !self.pending_groups().is_empty()
+    }
+    
+    // 等待所有URL规则完成首次加载，超时返回 false
+    async fn wait_until_ready(&self) -> bool {
+        let deadline = Instant::now() + self.not_ready_wait;
+        loop {
+            // 先注册通知再检查状态，避免错过检查与等待之间的就绪通知
+            let notified = self.ready_notify.notified();
+            if self.url_rules.iter().all(|rule| rule.ready.load(Ordering::Acquire)) {
+                return true;
+            }
+            if timeout_at(deadline, notified).await.is_err() {
+                return false;
+            }
+        }
+    }
+    
+    // 匹配域名并处理上游组未就绪的窗口期（启动或规则加载期间）：
+    // wait 策略先短暂等待URL规则完成首次加载再匹配；启用 fail_closed_until_loaded 时仍未加载完成则拒绝或阻止查询，
+    // 否则仍命中未就绪的上游组时改用全局上游
+    pub async fn match_domain_when_ready(
+        &self,
+        domain: &str,
+        record_type: RecordType,
+        profile_group: Option<&str>,
+    ) -> RouteDecision {
+        if self.not_ready_policy == GroupNotReadyPolicy::Wait && !self.pending_groups().is_empty() {
+            let ready = self.wait_until_ready().await;
+            METRICS.route_group_not_ready_total().with_label_values(&[NOT_READY_ACTION_WAITED]).inc();
+            debug!(domain = %domain, ready = ready, "Waited for URL rules to finish loading before routing");
+        }
+        
+        if let Some(action) = self.fail_closed_action {
+            if !self.pending_groups().is_empty() {
+                METRICS.route_group_not_ready_total().with_label_values(&[NOT_READY_ACTION_FAIL_CLOSED]).inc();
+                debug!(domain = %domain, action = ?action, "URL rules still loading, failing closed");
+                return match action {
+                    FailClosedAction::Refuse => RouteDecision::Refuse,
+                    FailClosedAction::Blackhole => RouteDecision::Blackhole,
+                };
+            }
+        }
+        
+        match self.match_domain_with_default(domain, record_type, profile_group).await {
+            RouteDecision::UseGroup(group) if !self.group_ready(&group) => {
+                METRICS.route_group_not_ready_total().with_label_values(&[NOT_READY_ACTION_GLOBAL]).inc();
+                debug!(domain = %domain, upstream_group = %group, "Upstream group not ready, falling back to global upstream");
+                RouteDecision::UseGlobal
+            },
+            decision => decision,
+        }
+    }
+    
+    // 匹配域名及查询类型，返回路由决策 - 主要入口方法
+    pub async fn match_domain(&self, domain: &str, record_type: RecordType) -> RouteDecision {
+        self.match_domain_with_default(domain, record_type, None).await
+    }
+    
+    // 匹配域名及查询类型，未匹配任何规则时优先使用 profile_group（路由配置档案的默认上游组）
+    pub async fn match_domain_with_default(
+        &self,
+        domain: &str,
+        record_type: RecordType,
+        profile_group: Option<&str>,
+    ) -> RouteDecision {
         // 如果路由未启用，返回使用全局上游
         if !self.enabled {
             {
@@ -288,11 +591,44 @@ impl Router {
             return RouteDecision::UseGlobal;
         }
         
-        // 规范化域名（转换为小写，去除尾部的点）
-        let domain_lower = domain.to_lowercase();
-        let domain_normalized = domain_lower.trim_end_matches('.');
+        // 规范化域名（转换为小写 A-label 形式，去除尾部的点）
+        let domain_normalized = normalize_domain(domain);
+        let domain_normalized = domain_normalized.as_str();
+        
+        // 0. 优先匹配带查询类型过滤的内联规则（比不带过滤的规则更具体）
+        for typed_rule in &self.typed_rules {
+            if !typed_rule.record_types.contains(&record_type) {
+                continue;
+            }
+            
+            if let Some((upstream_group, pattern, rule_type)) = typed_rule.core.match_domain(domain_normalized) {
+                // 如果是黑洞，返回黑洞决策
+                if upstream_group == BLACKHOLE_UPSTREAM_GROUP_NAME {
+                    {
+                        METRICS.route_results_total().with_label_values(&[ROUTE_RESULT_BLACKHOLE]).inc();
+                    }
+                    return RouteDecision::Blackhole;
+                }
+                
+                // 记录匹配
+                {
+                    METRICS.route_results_total().with_label_values(&[ROUTE_RESULT_RULE_MATCH]).inc();
+                }
+                
+                debug!(
+                    domain = %domain_normalized,
+                    record_type = %record_type,
+                    pattern = %pattern,
+                    rule_type = %rule_type,
+                    upstream_group = %upstream_group,
+                    "Domain matched typed core rule"
+                );
+                
+                return RouteDecision::UseGroup(upstream_group);
+            }
+        }
         
-        // 1. 首先尝试匹配核心规则 (高效的数据结构)
+        // 1. 然后尝试匹配核心规则 (高效的数据结构)
         if let Some((upstream_group, pattern, rule_type)) = self.core.match_domain(domain_normalized) {
             // 如果是黑洞，返回黑洞决策
             if upstream_group == BLACKHOLE_UPSTREAM_GROUP_NAME {
@@ -320,7 +656,12 @@ impl Router {
         
         // 2. 然后尝试匹配文件规则 (文件规则也使用高效数据结构)
         for file_rule in &self.file_rules {
-            if let Some((_, pattern, rule_type)) = file_rule.core.match_domain(domain_normalized) {
+            if !Self::record_type_allowed(&file_rule.record_types, record_type) {
+                continue;
+            }
+            
+            let file_rule_match = file_rule.core.read().await.match_domain(domain_normalized);
+            if let Some((_, pattern, rule_type)) = file_rule_match {
                 let upstream_group = &file_rule.upstream_group;
                 
                 // 如果是黑洞，返回黑洞决策
@@ -350,6 +691,10 @@ impl Router {
         
         // 3. 最后尝试匹配URL规则 (需要异步读取)
         for url_rule in &self.url_rules {
+            if !Self::record_type_allowed(&url_rule.record_types, record_type) {
+                continue;
+            }
+            
             // 读取URL规则
             let url_rules = url_rule.rules.read().await;
             
@@ -440,23 +785,76 @@ impl Router {
             }
         }
         
-        // 如果没有规则匹配，检查默认上游组
-        if let Some(default_group) = &self.default_upstream_group {
+        // 如果没有规则匹配，检查默认上游组（配置档案优先于全局默认组）
+        if let Some(default_group) = profile_group.or(self.default_upstream_group.as_deref()) {
             {
                 METRICS.route_results_total().with_label_values(&[ROUTE_RESULT_DEFAULT]).inc();
             }
-            return RouteDecision::UseGroup(default_group.clone());
+            return RouteDecision::UseGroup(default_group.to_string());
         }
         
-        // 没有匹配规则且没有默认组，使用全局上游
-        {
-            METRICS.route_results_total().with_label_values(&[ROUTE_RESULT_GLOBAL]).inc();
+        // 没有匹配规则且没有默认组，按 default_action 使用全局上游、拒绝或阻止查询
+        match self.default_action {
+            DefaultRouteAction::Global => {
+                METRICS.route_results_total().with_label_values(&[ROUTE_RESULT_GLOBAL]).inc();
+                RouteDecision::UseGlobal
+            },
+            DefaultRouteAction::Refuse => {
+                METRICS.route_results_total().with_label_values(&[ROUTE_RESULT_REFUSED]).inc();
+                debug!(domain = %domain_normalized, "Domain matched no rule, refusing query");
+                RouteDecision::Refuse
+            },
+            DefaultRouteAction::Blackhole => {
+                METRICS.route_results_total().with_label_values(&[ROUTE_RESULT_BLACKHOLE]).inc();
+                debug!(domain = %domain_normalized, "Domain matched no rule, blocking query");
+                RouteDecision::Blackhole
+            },
+        }
+    }
+    
+    // 解析规则的查询类型过滤
+    fn parse_record_types(condition: &MatchCondition) -> Result<Option<HashSet<RecordType>>> {
+        let Some(values) = &condition.record_types else {
+            return Ok(None);
+        };
+        
+        let mut record_types = HashSet::with_capacity(values.len());
+        for value in values {
+            let record_type = RecordType::from_str(&value.to_uppercase()).map_err(|e| {
+                ServerError::InvalidRuleFormat(format!("Invalid record type '{}': {}", value, e))
+            })?;
+            record_types.insert(record_type);
         }
-        RouteDecision::UseGlobal
+        
+        Ok(Some(record_types))
+    }
+    
+    // 检查查询类型是否满足规则的类型过滤
+    #[inline]
+    fn record_type_allowed(record_types: &Option<HashSet<RecordType>>, record_type: RecordType) -> bool {
+        record_types.as_ref().is_none_or(|types| types.contains(&record_type))
+    }
+    
+    // 构建规则正则表达式，延迟编译时仅校验语法并保存源码
+    fn build_rule_regex(pattern: &str, lazy: bool) -> Result<RuleRegex> {
+        if lazy {
+            check_regex_syntax(pattern).map_err(|e| ServerError::RegexCompilation(format!(
+                "Invalid regex '{}': {}",
+                pattern, e
+            )))?;
+            return Ok(RuleRegex::lazy(pattern.to_string()));
+        }
+        
+        Regex::new(pattern)
+            .map(RuleRegex::compiled)
+            .map_err(|e| ServerError::RegexCompilation(format!(
+                "Failed to compile regex '{}': {}", 
+                pattern, e
+            )))
     }
     
     // 从文件加载规则
-    fn load_rules_from_file(path: &str) -> Result<RouterCore> {
+    fn load_rules_from_file(path: &str, lazy_regex: bool) -> Result<RouterCore> {
         // 打开文件
         let file = match File::open(path) {
             Ok(f) => f,
@@ -491,7 +889,7 @@ impl Router {
             };
             
             // 处理规则行
-            if let Err(e) = Self::process_rule_line(&line, &mut exact, &mut regex, &mut wildcard) {
+            if let Err(e) = Self::process_rule_line(&line, &mut exact, &mut regex, &mut wildcard, lazy_regex) {
                 error!("Error in file '{}' at line {}: {}", path, line_num + 1, e);
                 return Err(ServerError::RuleLoad(format!(
                     "Error in file '{}' at line {}: {}", 
@@ -528,9 +926,9 @@ impl Router {
         }
         
         // 添加正则表达式规则
-        for (i, re) in regex.iter().enumerate() {
+        for (i, re) in regex.into_iter().enumerate() {
             let pattern = format!("regex_pattern_{}", i);
-            core.add_regex_rule(pattern, re.clone(), "file_rule".to_string());
+            core.add_regex_rule(pattern, re, "file_rule".to_string());
         }
         
         Ok(core)
@@ -540,8 +938,9 @@ impl Router {
     fn process_rule_line(
         line: &str, 
         exact: &mut HashSet<String>, 
-        regex: &mut Vec<Regex>, 
-        wildcard: &mut Vec<WildcardPattern>
+        regex: &mut Vec<RuleRegex>, 
+        wildcard: &mut Vec<WildcardPattern>,
+        lazy_regex: bool,
     ) -> Result<()> {
         // 去除前后空白
         let line = line.trim();
@@ -555,20 +954,14 @@ impl Router {
         if let Some(pattern) = line.strip_prefix("regex:") {
             // 提取正则表达式
             let pattern = pattern.trim();
-            match Regex::new(pattern) {
-                Ok(re) => regex.push(re),
-                Err(e) => return Err(ServerError::RegexCompilation(format!(
-                    "Failed to compile regex '{}': {}", 
-                    pattern, e
-                ))),
-            }
+            regex.push(Self::build_rule_regex(pattern, lazy_regex)?);
         } else if let Some(pattern) = line.strip_prefix("wildcard:") {
             // 提取通配符模式
             let pattern = pattern.trim();
             wildcard.push(Self::parse_wildcard_pattern(pattern));
         } else {
-            // 默认为精确匹配（转换为小写）
-            exact.insert(line.to_lowercase().trim_end_matches('.').to_string());
+            // 默认为精确匹配（转换为小写 A-label 形式）
+            exact.insert(normalize_domain(line));
         }
         
         Ok(())
@@ -593,14 +986,14 @@ impl Router {
             return WildcardPattern {
                 pattern: pattern_lower.clone(),
                 prefix: None,
-                suffix: Some(suffix.to_string()),
+                suffix: Some(normalize_domain(suffix)),
             };
         }
         
         // 处理特殊情况：prefix.*
         if pattern_lower.ends_with(".*") {
             let prefix_len = pattern_lower.len() - 2;
-            let prefix = pattern_lower[..prefix_len].to_string();
+            let prefix = normalize_domain(&pattern_lower[..prefix_len]);
             return WildcardPattern {
                 pattern: pattern_lower.clone(),
                 prefix: Some(prefix),
@@ -742,9 +1135,9 @@ impl Router {
     }
     
     // 从URL加载规则
-    async fn load_rules_from_url(client: &Client, url: &str) -> Result<(String, UrlRules)> {
+    async fn load_rules_from_url(client: &Client, url: &str, max_download_bytes: u64) -> Result<(String, UrlRules)> {
         // 发送 HTTP 请求
-        let response = match client.get(url).send().await {
+        let mut response = match client.get(url).send().await {
             Ok(resp) => resp,
             Err(e) => {
                 error!("Failed to fetch rules from {}: {}", url, e);
@@ -761,14 +1154,39 @@ impl Router {
             )));
         }
         
-        // 获取响应文本
-        let text = match response.text().await {
-            Ok(t) => t,
-            Err(e) => {
-                error!("Failed to read response body from {}: {}", url, e);
-                return Err(ServerError::Http(e.to_string()));
+        // 根据 Content-Length 提前拒绝过大的响应
+        if let Some(content_length) = response.content_length() {
+            if content_length > max_download_bytes {
+                error!("Rules from {} exceed download limit: {} > {} bytes", url, content_length, max_download_bytes);
+                return Err(ServerError::RuleFetch(format!(
+                    "Rules from URL '{}' exceed download limit of {} bytes",
+                    url, max_download_bytes
+                )));
             }
-        };
+        }
+        
+        // 流式读取响应体，超出大小限制时中止
+        let mut body = Vec::new();
+        loop {
+            let chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to read response body from {}: {}", url, e);
+                    return Err(ServerError::Http(e.to_string()));
+                }
+            };
+            
+            if (body.len() + chunk.len()) as u64 > max_download_bytes {
+                error!("Rules from {} exceed download limit of {} bytes, aborting", url, max_download_bytes);
+                return Err(ServerError::RuleFetch(format!(
+                    "Rules from URL '{}' exceed download limit of {} bytes",
+                    url, max_download_bytes
+                )));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let text = String::from_utf8_lossy(&body).into_owned();
         
         // 初始化URL规则
         let mut url_rules = UrlRules::default();
@@ -802,8 +1220,8 @@ impl Router {
                 let pattern = pattern.trim();
                 url_rules.wildcard.push(Self::parse_wildcard_pattern(pattern));
             } else {
-                // 默认为精确匹配
-                url_rules.exact.insert(line.to_lowercase().trim_end_matches('.').to_string());
+                // 默认为精确匹配（转换为小写 A-label 形式）
+                url_rules.exact.insert(normalize_domain(line));
             }
         }
         
@@ -826,6 +1244,79 @@ impl Router {
         Ok((text, url_rules))
     }
     
+    // 立即重新加载所有文件规则并重新获取所有URL规则，返回每个来源的加载结果
+    pub async fn refresh_rules(&self) -> Vec<RuleRefreshSummary> {
+        let mut summaries = Vec::with_capacity(self.file_rules.len() + self.url_rules.len());
+        
+        // 重新加载文件规则，失败时保留原有规则
+        for file_rule in &self.file_rules {
+            let success = match Self::load_rules_from_file(&file_rule.path, self.lazy_regex) {
+                Ok(new_core) => {
+                    // 写锁仅用于交换规则，旧规则在锁外释放
+                    let old_core = std::mem::replace(&mut *file_rule.core.write().await, new_core);
+                    drop(old_core);
+                    true
+                },
+                Err(e) => {
+                    error!(path = %file_rule.path, error = %e, "Failed to reload rules from file");
+                    false
+                }
+            };
+            
+            let core = file_rule.core.read().await;
+            summaries.push(RuleRefreshSummary {
+                source_type: RULE_SOURCE_FILE,
+                source: file_rule.path.clone(),
+                upstream_group: file_rule.upstream_group.clone(),
+                success,
+                exact: core.exact_rules.len(),
+                wildcard: core.wildcard_rules.len() + usize::from(core.global_wildcard.is_some()),
+                regex: core.regex_rules.len(),
+            });
+        }
+        
+        // 重新获取URL规则
+        for url_rule in &self.url_rules {
+            let success = match &self.http_client {
+                Some(client) => Self::update_single_url_rule(
+                    client,
+                    &url_rule.url,
+                    &url_rule.rules,
+                    &url_rule.upstream_group,
+                    self.max_rule_download_bytes,
+                    &self.url_update_permits,
+                ).await,
+                None => {
+                    warn!(url = %url_rule.url, "HTTP client not available, cannot refresh URL rule");
+                    false
+                }
+            };
+            
+            if success && !url_rule.ready.swap(true, Ordering::AcqRel) {
+                self.ready_notify.notify_waiters();
+            }
+            
+            let rules = url_rule.rules.read().await;
+            summaries.push(RuleRefreshSummary {
+                source_type: RULE_SOURCE_URL,
+                source: url_rule.url.clone(),
+                upstream_group: url_rule.upstream_group.clone(),
+                success,
+                exact: rules.exact.len(),
+                wildcard: rules.wildcard.len(),
+                regex: rules.regex.len(),
+            });
+        }
+        
+        info!(
+            sources = summaries.len(),
+            failed = summaries.iter().filter(|s| !s.success).count(),
+            "Routing rules refreshed on demand"
+        );
+        
+        summaries
+    }
+    
     // 启动所有URL规则更新任务
     async fn start_url_updaters(&self) {
         // 如果没有HTTP客户端，无法更新URL规则
@@ -844,7 +1335,12 @@ impl Router {
                     let url_clone = rule.url.clone();
                     let rules_clone = Arc::clone(&rule.rules);
                     let interval_secs = config.interval_secs;
+                    let retry = config.retry;
+                    let max_download_bytes = self.max_rule_download_bytes;
+                    let permits = Arc::clone(&self.url_update_permits);
                     let upstream_group = rule.upstream_group.clone();
+                    let ready = Arc::clone(&rule.ready);
+                    let ready_notify = Arc::clone(&self.ready_notify);
                     
                     // 启动独立的更新任务
                     tokio::spawn(async move {
@@ -859,13 +1355,19 @@ impl Router {
                             "Started URL rule periodic updater"
                         );
                         
-                        // 立即执行第一次更新
-                        Self::update_single_url_rule(&client_clone, &url_clone, &rules_clone, &upstream_group).await;
+                        // 立即执行第一次更新，完成（含重试）后上游组视为就绪，加载失败时不再阻塞查询
+                        if !Self::update_single_url_rule(&client_clone, &url_clone, &rules_clone, &upstream_group, max_download_bytes, &permits).await {
+                            Self::retry_url_rule_update(&client_clone, &url_clone, &rules_clone, &upstream_group, max_download_bytes, &permits, retry).await;
+                        }
+                        ready.store(true, Ordering::Release);
+                        ready_notify.notify_waiters();
                         
                         // 定期更新
                         loop {
                             interval_timer.tick().await;
-                            Self::update_single_url_rule(&client_clone, &url_clone, &rules_clone, &upstream_group).await;
+                            if !Self::update_single_url_rule(&client_clone, &url_clone, &rules_clone, &upstream_group, max_download_bytes, &permits).await {
+                                Self::retry_url_rule_update(&client_clone, &url_clone, &rules_clone, &upstream_group, max_download_bytes, &permits, retry).await;
+                            }
                         }
                     });
                 } else {
@@ -877,14 +1379,61 @@ impl Router {
         }
     }
     
-    // 更新单个URL规则
-    async fn update_single_url_rule(client: &Client, url: &str, rules: &Arc<AsyncRwLock<UrlRules>>, upstream_group: &str) {
+    // 获取失败后按指数退避重试，重试耗尽后回到正常的周期性更新
+    async fn retry_url_rule_update(
+        client: &Client,
+        url: &str,
+        rules: &Arc<AsyncRwLock<UrlRules>>,
+        upstream_group: &str,
+        max_download_bytes: u64,
+        permits: &Semaphore,
+        retry: RetryConfig,
+    ) {
+        let mut backoff_secs = retry.initial_backoff_secs;
+        
+        for attempt in 1..=retry.max_attempts {
+            debug!(url = url, attempt = attempt, backoff_secs = backoff_secs, "Retrying URL rule update after backoff");
+            sleep(Duration::from_secs(backoff_secs)).await;
+            
+            let succeeded = Self::update_single_url_rule(client, url, rules, upstream_group, max_download_bytes, permits).await;
+            let status = if succeeded { URL_RULE_UPDATE_STATUS_SUCCESS } else { URL_RULE_UPDATE_STATUS_FAILED };
+            METRICS.url_rule_update_retries_total().with_label_values(&[status, upstream_group]).inc();
+            
+            if succeeded {
+                info!(url = url, attempt = attempt, "URL rule update recovered after retry");
+                return;
+            }
+            
+            backoff_secs = backoff_secs.saturating_mul(2).min(retry.max_backoff_secs);
+        }
+        
+        if retry.max_attempts > 0 {
+            warn!(
+                url = url,
+                max_attempts = retry.max_attempts,
+                "URL rule update retries exhausted, waiting for next periodic update"
+            );
+        }
+    }
+    
+    // 更新单个URL规则，返回是否成功获取（内容未变化也视为成功）
+    async fn update_single_url_rule(
+        client: &Client,
+        url: &str,
+        rules: &Arc<AsyncRwLock<UrlRules>>,
+        upstream_group: &str,
+        max_download_bytes: u64,
+        permits: &Semaphore,
+    ) -> bool {
+        // 限制同时下载与编译规则的任务数，信号量不会被关闭
+        let _permit = permits.acquire().await.ok();
+        
         let start_time = std::time::Instant::now();
         let mut status = URL_RULE_UPDATE_STATUS_FAILED;
         
-        // 尝试获取规则内容并计算哈希
-        match Self::load_rules_from_url(client, url).await {
-            Ok((content, new_rules)) => {
+        // 尝试获取规则内容并计算哈希（下载与正则编译均在锁外完成）
+        match Self::load_rules_from_url(client, url, max_download_bytes).await {
+            Ok((content, mut new_rules)) => {
                 // 计算内容哈希
                 let new_hash = xxh64(content.as_bytes(), 0);
                 
@@ -904,30 +1453,35 @@ impl Router {
                 
                 // 内容有变化或首次加载，需要更新规则
                 if need_update {
-                    // 获取写锁
-                    let mut rules_write = rules.write().await;
+                    new_rules.last_updated = Some(std::time::Instant::now());
+                    new_rules.last_hash = Some(new_hash);
+                    let (exact_count, regex_count, wildcard_count) =
+                        (new_rules.exact.len(), new_rules.regex.len(), new_rules.wildcard.len());
                     
-                    // 更新规则
-                    rules_write.exact = new_rules.exact;
-                    rules_write.regex = new_rules.regex;
-                    rules_write.wildcard = new_rules.wildcard;
-                    rules_write.last_updated = Some(std::time::Instant::now());
-                    rules_write.last_hash = Some(new_hash);
+                    // 写锁仅用于交换规则集，旧规则集在锁外释放
+                    let lock_start = std::time::Instant::now();
+                    let old_rules = {
+                        let mut rules_write = rules.write().await;
+                        std::mem::replace(&mut *rules_write, new_rules)
+                    };
+                    let lock_hold_us = lock_start.elapsed().as_micros();
+                    drop(old_rules);
                     
                     status = URL_RULE_UPDATE_STATUS_SUCCESS;
                     info!(
                         url = url,
-                        exact_rules = rules_write.exact.len(),
-                        regex_rules = rules_write.regex.len(),
-                        wildcard_rules = rules_write.wildcard.len(),
+                        exact_rules = exact_count,
+                        regex_rules = regex_count,
+                        wildcard_rules = wildcard_count,
                         elapsed_ms = start_time.elapsed().as_millis(),
+                        lock_hold_us = lock_hold_us,
                         "Updated URL rules successfully"
                     );
                     
                     // 更新指标统计 - 使用统一的标签值进行计数
-                    METRICS.route_rules().with_label_values(&[ROUTE_RULE_TYPE_EXACT]).set(rules_write.exact.len() as f64);
-                    METRICS.route_rules().with_label_values(&[ROUTE_RULE_TYPE_REGEX]).set(rules_write.regex.len() as f64);
-                    METRICS.route_rules().with_label_values(&[ROUTE_RULE_TYPE_WILDCARD]).set(rules_write.wildcard.len() as f64);
+                    METRICS.route_rules().with_label_values(&[ROUTE_RULE_TYPE_EXACT]).set(exact_count as f64);
+                    METRICS.route_rules().with_label_values(&[ROUTE_RULE_TYPE_REGEX]).set(regex_count as f64);
+                    METRICS.route_rules().with_label_values(&[ROUTE_RULE_TYPE_WILDCARD]).set(wildcard_count as f64);
                 }
             },
             Err(e) => {
@@ -938,6 +1492,8 @@ impl Router {
         // 更新指标
         let elapsed = start_time.elapsed().as_secs_f64();
         METRICS.url_rule_update_duration_seconds().with_label_values(&[status, upstream_group]).observe(elapsed);
+        
+        status != URL_RULE_UPDATE_STATUS_FAILED
     }
 }
 
@@ -956,7 +1512,7 @@ impl RouterCore {
     
     // 添加精确匹配规则
     fn add_exact_rule(&mut self, domain: String, upstream_group: String) {
-        self.exact_rules.insert(domain.to_lowercase().trim_end_matches('.').to_string(), upstream_group);
+        self.exact_rules.insert(normalize_domain(&domain), upstream_group);
     }
     
     // 添加通配符规则
@@ -969,7 +1525,7 @@ impl RouterCore {
         
         // 处理标准通配符格式: *.domain.com
         if let Some(suffix) = pattern.strip_prefix("*.") {
-            let reversed_suffix = Self::reverse_domain_labels(suffix);
+            let reversed_suffix = Self::reverse_domain_labels(&normalize_domain(suffix));
             self.wildcard_rules.insert(reversed_suffix, (upstream_group, pattern));
             return;
         }
@@ -977,7 +1533,7 @@ impl RouterCore {
         // 将其他通配符格式转换为正则表达式
         if let Ok(regex) = Router::wildcard_to_regex(&pattern) {
             let index = self.regex_rules.len();
-            self.regex_rules.push((regex, upstream_group, pattern.clone()));
+            self.regex_rules.push((RuleRegex::compiled(regex), upstream_group, pattern.clone()));
             
             // 添加到预筛选映射
             self.add_to_prefilter(index, &pattern);
@@ -985,7 +1541,7 @@ impl RouterCore {
     }
     
     // 添加正则表达式规则
-    fn add_regex_rule(&mut self, pattern: String, regex: Regex, upstream_group: String) {
+    fn add_regex_rule(&mut self, pattern: String, regex: RuleRegex, upstream_group: String) {
         let index = self.regex_rules.len();
         let pattern_clone = pattern.clone();
         self.regex_rules.push((regex, upstream_group, pattern));
@@ -1103,7 +1659,7 @@ impl RouterCore {
         
         // 尝试匹配候选正则表达式
         for &index in &candidate_indices {
-            let (regex, upstream_group, pattern): &(Regex, String, String) = &self.regex_rules[index];
+            let (regex, upstream_group, pattern): &(RuleRegex, String, String) = &self.regex_rules[index];
             if regex.is_match(domain) {
                 return Some((upstream_group.clone(), pattern.clone(), ROUTE_RULE_TYPE_REGEX));
             }
@@ -1,5 +1,6 @@
 use axum::Router;
 use utoipa::OpenApi;
+use utoipa::openapi::server::Server;
 use utoipa_scalar::{Scalar, Servable};
 use crate::server::doh_handler::{DnsJsonRequest, DnsMsgGetRequest, DnsJsonResponse};
 
@@ -8,6 +9,7 @@ use crate::server::doh_handler::{DnsJsonRequest, DnsMsgGetRequest, DnsJsonRespon
 #[openapi(
     paths(
         get_dns_json_query,
+        post_dns_json_query,
         get_dns_wire_query,
         post_dns_wire_query,
     ),
@@ -20,10 +22,15 @@ use crate::server::doh_handler::{DnsJsonRequest, DnsMsgGetRequest, DnsJsonRespon
 )]
 pub struct ApiDoc;
 
-// 创建 API 文档路由
-pub fn create_scalar_routes() -> Router {
+// 创建 API 文档路由，配置了公共路径前缀时将其作为文档的服务器地址
+pub fn create_scalar_routes(base_path: &str) -> Router {
+    let mut openapi = ApiDoc::openapi();
+    if !base_path.is_empty() {
+        openapi.servers = Some(vec![Server::new(base_path)]);
+    }
+
     Router::new()
-        .merge(Scalar::with_url("/scalar", ApiDoc::openapi()))
+        .merge(Scalar::with_url("/scalar", openapi))
 }
 
 /// Query DNS records in JSON format (GET)
@@ -47,6 +54,22 @@ pub fn create_scalar_routes() -> Router {
 )]
 pub fn get_dns_json_query() {}
 
+/// Query DNS records in JSON format (POST)
+#[utoipa::path(
+    post,
+    path = "/resolve",
+    operation_id = "postDnsJsonQuery",
+    tag = "DoH",
+    request_body(content = DnsJsonRequest, content_type = "application/dns-json", description = "JSON query with the same fields as the GET parameters"),
+    responses(
+        (status = 200, description = "DNS query successful", body = DnsJsonResponse),
+        (status = 400, description = "Invalid request body", body = String),
+        (status = 415, description = "Unsupported media type", body = String),
+        (status = 500, description = "Internal server error", body = String)
+    )
+)]
+pub fn post_dns_json_query() {}
+
 /// Query DNS records in binary format (GET)
 #[utoipa::path(
     get,
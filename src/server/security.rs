@@ -1,10 +1,12 @@
 // src/server/security.rs
 
+use std::net::{IpAddr, Ipv6Addr};
 use std::num::NonZeroU32;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use axum::{Router, http::StatusCode, response::Response};
 use axum::body::Body;
+use moka::future::Cache;
 use tokio::time;
 use tracing::{info, warn, debug};
 use tower_governor::{
@@ -14,10 +16,14 @@ use tower_governor::{
     errors::GovernorError,
 };
 
+use crate::server::blackhole::{parse_cidrs, IpCidr};
 use crate::server::config::RateLimitConfig;
+use crate::server::error::Result;
 use crate::common::consts::{MIN_PER_IP_RATE, MAX_PER_IP_RATE, MIN_PER_IP_CONCURRENT, MAX_PER_IP_CONCURRENT};
 use crate::server::metrics::METRICS;
 
+// 一天的秒数
+const SECONDS_PER_DAY: u64 = 86400;
 
 // 返回应用了速率限制的路由或者错误
 pub fn apply_rate_limiting(routes: Router, config: &RateLimitConfig) -> Router {
@@ -132,4 +138,119 @@ pub fn calculate_period_duration(rate: u32) -> Option<Duration> {
     // 周期 (纳秒) = 1,000,000,000 / 速率
     let period_nanos = 1000000000 / rate;
     Some(Duration::from_nanos(period_nanos.into()))
-} 
\ No newline at end of file
+}
+
+// 单个 IP 在当前配额周期内的查询计数
+struct QuotaCounter {
+    // 配额周期编号（自重置时刻起的天数）
+    period: u64,
+    // 当前周期内的查询数
+    count: u64,
+}
+
+// 每个 IP 的每日查询配额：按 UTC 重置时刻划分周期计数，超出配额的查询被拒绝
+// 计数器空闲一天后淘汰（此时配额已重置），跟踪的 IP 数受 quota_max_tracked_ips 限制
+// 按连接源地址计数，仅信任来自 quota_trusted_proxies 的 X-Forwarded-For 等请求头；IPv6 地址按 /64 聚合
+pub struct DailyQueryQuota {
+    // 各 IP 的计数器，未启用时为 None
+    counters: Option<Cache<IpAddr, Arc<Mutex<QuotaCounter>>>>,
+    // 每日最大查询数
+    limit: u64,
+    // 重置时刻相对 UTC 零点的偏移（秒）
+    reset_offset: u64,
+    // 允许通过请求头传递客户端地址的代理网段（启动时解析）
+    trusted_proxies: Vec<IpCidr>,
+}
+
+impl DailyQueryQuota {
+    // 从速率限制配置创建每日配额
+    pub fn new(config: &RateLimitConfig) -> Result<Self> {
+        if !config.enabled || config.per_ip_daily_quota == 0 {
+            return Ok(Self::disabled());
+        }
+
+        info!(
+            per_ip_daily_quota = config.per_ip_daily_quota,
+            reset_hour = config.quota_reset_hour,
+            max_tracked_ips = config.quota_max_tracked_ips,
+            "Per-IP daily query quota enabled",
+        );
+
+        let counters = Cache::builder()
+            .max_capacity(config.quota_max_tracked_ips)
+            .time_to_idle(Duration::from_secs(SECONDS_PER_DAY))
+            .build();
+
+        Ok(Self {
+            counters: Some(counters),
+            limit: config.per_ip_daily_quota,
+            reset_offset: config.quota_reset_hour as u64 * 3600,
+            trusted_proxies: parse_cidrs(&config.quota_trusted_proxies)?,
+        })
+    }
+
+    // 创建未启用的每日配额
+    pub fn disabled() -> Self {
+        Self { counters: None, limit: 0, reset_offset: 0, trusted_proxies: Vec::new() }
+    }
+
+    // 记录客户端的一次查询，超出当日配额时返回 false
+    // peer_ip 为连接源地址，forwarded_ip 为从请求头解析的客户端地址（仅当连接来自受信任代理时采用）
+    pub async fn consume(&self, peer_ip: IpAddr, forwarded_ip: IpAddr) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.consume_at(self.client_ip(peer_ip, forwarded_ip), now).await
+    }
+
+    // 确定计数的客户端地址：请求头可被任意客户端伪造，仅信任受信任代理转发的地址
+    pub fn client_ip(&self, peer_ip: IpAddr, forwarded_ip: IpAddr) -> IpAddr {
+        if self.trusted_proxies.iter().any(|cidr| cidr.contains(peer_ip)) {
+            forwarded_ip
+        } else {
+            peer_ip
+        }
+    }
+
+    // 按指定时间（Unix 时间戳，秒）记录客户端的一次查询，超出所在周期的配额时返回 false
+    pub async fn consume_at(&self, client_ip: IpAddr, now: u64) -> bool {
+        let Some(counters) = &self.counters else {
+            return true;
+        };
+
+        let client_ip = quota_key(client_ip);
+        let period = now.saturating_sub(self.reset_offset) / SECONDS_PER_DAY;
+        let counter = counters
+            .get_with(client_ip, async move { Arc::new(Mutex::new(QuotaCounter { period, count: 0 })) })
+            .await;
+
+        let mut counter = counter.lock().unwrap_or_else(|e| e.into_inner());
+        // 进入新周期时重置计数
+        if counter.period != period {
+            counter.period = period;
+            counter.count = 0;
+        }
+        if counter.count >= self.limit {
+            METRICS.query_quota_exceeded_total().inc();
+            debug!(client_ip = %client_ip, limit = self.limit, "Daily query quota exceeded by client");
+            return false;
+        }
+        counter.count += 1;
+        true
+    }
+}
+
+// 配额计数键：IPv6 地址按 /64 聚合，避免单个主机轮换地址绕过配额（IPv4 映射地址按 IPv4 处理）
+fn quota_key(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => {
+                let segments = v6.segments();
+                IpAddr::V6(Ipv6Addr::new(segments[0], segments[1], segments[2], segments[3], 0, 0, 0, 0))
+            },
+        },
+        v4 => v4,
+    }
+}
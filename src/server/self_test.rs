@@ -0,0 +1,294 @@
+// src/server/self_test.rs
+
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use axum::body::{to_bytes, Body};
+use axum::extract::ConnectInfo;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router as AxumRouter;
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, RecordType};
+use tokio::time::{sleep, timeout, Instant};
+use tower::util::ServiceExt;
+use tracing::{debug, info};
+use crate::common::consts::{
+    CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE, DOH_JSON_API_PATH, DOH_STANDARD_PATH,
+    SELF_TEST_MAX_BODY_BYTES, SELF_TEST_ROUTING_WAIT_SECS,
+};
+use crate::server::metrics::METRICS;
+use crate::server::DoHServer;
+
+// 缓存命中指标标签值
+const CACHE_OP_HIT: &str = "hit";
+
+// 就绪检查路径
+const READINESS_PATH: &str = "/health/ready";
+
+// 自检项名称
+pub const CHECK_CONFIGURATION: &str = "configuration";
+pub const CHECK_COMPONENTS: &str = "application components";
+pub const CHECK_ROUTING: &str = "routing";
+pub const CHECK_UPSTREAM: &str = "upstream";
+pub const CHECK_CACHE: &str = "cache";
+pub const CHECK_JSON_API: &str = "json api";
+
+// 自检项结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    // 通过
+    Pass,
+    // 失败
+    Fail,
+    // 未执行（功能未启用或依赖的检查失败）
+    Skip,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "PASS"),
+            Self::Fail => write!(f, "FAIL"),
+            Self::Skip => write!(f, "SKIP"),
+        }
+    }
+}
+
+// 单个自检项
+#[derive(Debug, Clone)]
+pub struct SelfTestCheck {
+    // 子系统名称
+    pub name: &'static str,
+    // 结果
+    pub status: CheckStatus,
+    // 结果说明
+    pub detail: String,
+}
+
+// 自检报告：按执行顺序记录各子系统的结果
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    // 没有失败项时自检通过
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.status != CheckStatus::Fail)
+    }
+
+    // 按名称查找自检项
+    pub fn check(&self, name: &str) -> Option<&SelfTestCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    fn record(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        let detail = detail.into();
+        debug!(check = name, %status, detail = %detail, "Self-test check finished");
+        self.checks.push(SelfTestCheck { name, status, detail });
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Self-test results:")?;
+        for check in &self.checks {
+            writeln!(f, "  [{}] {}: {}", check.status, check.name, check.detail)?;
+        }
+        let failed = self.checks.iter().filter(|check| check.status == CheckStatus::Fail).count();
+        if failed == 0 {
+            write!(f, "Self-test passed")
+        } else {
+            write!(f, "Self-test failed ({} of {} checks failed)", failed, self.checks.len())
+        }
+    }
+}
+
+impl DoHServer {
+    // 在进程内构建应用组件并经完整处理流程发送合成查询，检查配置、路由、上游与缓存是否可用
+    // 自检不监听端口，也不读写持久化缓存文件
+    pub async fn self_test(&self) -> SelfTestReport {
+        let mut report = SelfTestReport::default();
+
+        if let Err(e) = self.config.test() {
+            report.record(CHECK_CONFIGURATION, CheckStatus::Fail, e.to_string());
+            return report;
+        }
+        report.record(CHECK_CONFIGURATION, CheckStatus::Pass, "valid");
+
+        let mut config = self.config.clone();
+        config.dns.cache.persistence.enabled = false;
+        config.dns.cache.persistence.load_on_startup = false;
+        config.dns.cache.persistence.periodic.enabled = false;
+        let server = DoHServer {
+            config,
+            debug: false,
+            custom_resolvers: self.custom_resolvers.clone(),
+        };
+        let (app, cache) = match server.build_application_components().await {
+            Ok(components) => components,
+            Err(e) => {
+                report.record(CHECK_COMPONENTS, CheckStatus::Fail, e.to_string());
+                return report;
+            },
+        };
+        report.record(CHECK_COMPONENTS, CheckStatus::Pass, "built");
+
+        let probe = SelfTestProbe {
+            app,
+            base_path: server.config.http.base_path.clone(),
+            timeout: Duration::from_secs(server.config.http.health.probe.timeout_secs),
+        };
+        let name = &server.config.http.health.probe.name;
+        info!(name = %name, "Running self-test");
+
+        // 路由：等待所有上游组的URL规则完成首次加载
+        if server.config.dns.routing.enabled {
+            let (status, detail) = probe.wait_routing_ready().await;
+            report.record(CHECK_ROUTING, status, detail);
+        } else {
+            report.record(CHECK_ROUTING, CheckStatus::Skip, "routing is disabled");
+        }
+
+        // 上游：缓存为空，首次查询经路由与上游解析
+        let upstream_ok = match probe.query_wire(name).await {
+            Ok((rcode, answers, elapsed)) => {
+                let detail = format!("{} A -> {}, {} answers in {} ms", name, rcode, answers, elapsed.as_millis());
+                if matches!(rcode, ResponseCode::NoError | ResponseCode::NXDomain) {
+                    report.record(CHECK_UPSTREAM, CheckStatus::Pass, detail);
+                    true
+                } else {
+                    report.record(CHECK_UPSTREAM, CheckStatus::Fail, detail);
+                    false
+                }
+            },
+            Err(e) => {
+                report.record(CHECK_UPSTREAM, CheckStatus::Fail, e);
+                false
+            },
+        };
+
+        // 缓存：重复同一查询应命中缓存
+        if !server.config.dns.cache.enabled {
+            report.record(CHECK_CACHE, CheckStatus::Skip, "cache is disabled");
+        } else if !upstream_ok {
+            report.record(CHECK_CACHE, CheckStatus::Skip, "upstream check failed");
+        } else {
+            let hits_before = METRICS.cache_operations_total().with_label_values(&[CACHE_OP_HIT]).get();
+            let repeated = probe.query_wire(name).await;
+            let hits = METRICS.cache_operations_total().with_label_values(&[CACHE_OP_HIT]).get() - hits_before;
+            match repeated {
+                Ok(_) if hits > 0 => {
+                    report.record(CHECK_CACHE, CheckStatus::Pass, format!("repeated query served from cache ({} entries)", cache.len().await));
+                },
+                Ok(_) => report.record(CHECK_CACHE, CheckStatus::Fail, "repeated query was not served from cache"),
+                Err(e) => report.record(CHECK_CACHE, CheckStatus::Fail, e),
+            }
+        }
+
+        // JSON API：经 /resolve 查询 AAAA 记录
+        match probe.query_json(name).await {
+            Ok(status) => report.record(CHECK_JSON_API, CheckStatus::Pass, format!("{} AAAA -> status {}", name, status)),
+            Err(e) => report.record(CHECK_JSON_API, CheckStatus::Fail, e),
+        }
+
+        report
+    }
+}
+
+// 向进程内应用发送合成请求
+struct SelfTestProbe {
+    // 应用路由
+    app: AxumRouter,
+    // 公共路径前缀
+    base_path: String,
+    // 单次请求超时
+    timeout: Duration,
+}
+
+impl SelfTestProbe {
+    // 发送请求，附带回环地址的连接信息（速率限制与访问控制依赖客户端地址）
+    async fn send(&self, mut request: Request<Body>) -> Result<(StatusCode, Vec<u8>), String> {
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))));
+        let response = timeout(self.timeout, self.app.clone().oneshot(request))
+            .await
+            .map_err(|_| format!("request timed out after {}s", self.timeout.as_secs()))?
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let body = to_bytes(response.into_body(), SELF_TEST_MAX_BODY_BYTES)
+            .await
+            .map_err(|e| format!("failed to read response body: {}", e))?;
+        Ok((status, body.to_vec()))
+    }
+
+    // 经 RFC 8484 POST 接口查询 A 记录，返回响应码、应答数与耗时
+    async fn query_wire(&self, name: &str) -> Result<(ResponseCode, usize, Duration), String> {
+        let name = Name::from_utf8(name).map_err(|e| format!("invalid probe name '{}': {}", name, e))?;
+        let mut query = Message::new();
+        query.set_id(fastrand::u16(..))
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(Query::query(name, RecordType::A));
+        let wire = query.to_vec().map_err(|e| e.to_string())?;
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}{}", self.base_path, DOH_STANDARD_PATH))
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_DNS_MESSAGE)
+            .body(Body::from(wire))
+            .map_err(|e| e.to_string())?;
+
+        let start = Instant::now();
+        let (status, body) = self.send(request).await?;
+        if status != StatusCode::OK {
+            return Err(format!("HTTP {}", status));
+        }
+        let response = Message::from_vec(&body).map_err(|e| format!("invalid DNS response: {}", e))?;
+        Ok((response.response_code(), response.answers().len(), start.elapsed()))
+    }
+
+    // 经 JSON API 查询 AAAA 记录，返回应答中的响应码
+    async fn query_json(&self, name: &str) -> Result<u64, String> {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!("{}{}?name={}&type=AAAA", self.base_path, DOH_JSON_API_PATH, name))
+            .header(header::ACCEPT, CONTENT_TYPE_DNS_JSON)
+            .body(Body::empty())
+            .map_err(|e| e.to_string())?;
+
+        let (status, body) = self.send(request).await?;
+        if status != StatusCode::OK {
+            return Err(format!("HTTP {}", status));
+        }
+        let json: serde_json::Value = serde_json::from_slice(&body).map_err(|e| format!("invalid JSON response: {}", e))?;
+        json.get("status").and_then(|status| status.as_u64()).ok_or_else(|| "JSON response has no status".to_string())
+    }
+
+    // 轮询就绪检查，直到没有未完成URL规则加载的上游组或等待超时
+    async fn wait_routing_ready(&self) -> (CheckStatus, String) {
+        let deadline = Instant::now() + Duration::from_secs(SELF_TEST_ROUTING_WAIT_SECS);
+        loop {
+            let request = match Request::builder().uri(format!("{}{}", self.base_path, READINESS_PATH)).body(Body::empty()) {
+                Ok(request) => request,
+                Err(e) => return (CheckStatus::Fail, e.to_string()),
+            };
+            let pending = match self.send(request).await {
+                Ok((_, body)) => serde_json::from_slice::<serde_json::Value>(&body)
+                    .ok()
+                    .and_then(|json| json.get("pending_upstream_groups").cloned())
+                    .and_then(|groups| serde_json::from_value::<Vec<String>>(groups).ok())
+                    .unwrap_or_default(),
+                Err(e) => return (CheckStatus::Fail, e),
+            };
+
+            if pending.is_empty() {
+                return (CheckStatus::Pass, "all upstream group rules loaded".to_string());
+            }
+            if Instant::now() >= deadline {
+                return (CheckStatus::Fail, format!("rules not loaded for upstream groups: {}", pending.join(", ")));
+            }
+            sleep(Duration::from_millis(200)).await;
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// src/server/special_names.rs
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::{A, AAAA};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use once_cell::sync::Lazy;
+use tracing::debug;
+use crate::common::consts::{SPECIAL_NAME_INVALID, SPECIAL_NAME_LOCALHOST, SPECIAL_NAME_TTL};
+use crate::server::config::SpecialNamesConfig;
+
+// 特殊用途域名（RFC 6761）
+static LOCALHOST_ZONE: Lazy<Name> = Lazy::new(|| Name::from_ascii(SPECIAL_NAME_LOCALHOST).unwrap());
+static INVALID_ZONE: Lazy<Name> = Lazy::new(|| Name::from_ascii(SPECIAL_NAME_INVALID).unwrap());
+
+// 若查询为特殊用途域名，返回本地合成的应答，不经过缓存与上游：
+// - localhost 及其子域：A 应答 127.0.0.1，AAAA 应答 ::1，其他类型返回空应答
+// - invalid 及其子域：返回 NXDOMAIN
+pub fn respond_special_name(config: &SpecialNamesConfig, query_message: &Message) -> Option<Message> {
+    let query = query_message.queries().first()?;
+    let name = query.name();
+
+    let (response_code, answer) = if config.localhost && LOCALHOST_ZONE.zone_of(name) {
+        let rdata = match query.query_type() {
+            RecordType::A => Some(RData::A(A(Ipv4Addr::LOCALHOST))),
+            RecordType::AAAA => Some(RData::AAAA(AAAA(Ipv6Addr::LOCALHOST))),
+            _ => None,
+        };
+        (ResponseCode::NoError, rdata.map(|rdata| Record::from_rdata(name.clone(), SPECIAL_NAME_TTL, rdata)))
+    } else if config.invalid && INVALID_ZONE.zone_of(name) {
+        (ResponseCode::NXDomain, None)
+    } else {
+        return None;
+    };
+
+    debug!(name = %name, query_type = ?query.query_type(), response_code = ?response_code, "Answering special-use name locally");
+
+    let mut response = Message::new();
+    response.set_id(query_message.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query_message.op_code())
+        .set_recursion_desired(query_message.recursion_desired())
+        .set_recursion_available(true)
+        .set_response_code(response_code)
+        .add_query(query.clone());
+    if let Some(answer) = answer {
+        response.add_answer(answer);
+    }
+
+    Some(response)
+}
@@ -0,0 +1,191 @@
+// src/server/trust_anchor.rs
+
+use std::fs;
+use std::str::FromStr;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::dnssec::rdata::{DNSSECRData, DNSKEY, DS};
+use hickory_proto::rr::dnssec::{Algorithm, DigestType};
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use tracing::{debug, info};
+use crate::server::config::LocalTrustAnchorConfig;
+use crate::server::error::{Result, ServerError};
+
+// DNSKEY 记录的协议字段固定为 3（RFC 4034）
+const DNSKEY_PROTOCOL: u8 = 3;
+
+// DNSKEY 标志位
+const DNSKEY_FLAG_ZONE_KEY: u16 = 0x0100;
+const DNSKEY_FLAG_REVOKE: u16 = 0x0080;
+const DNSKEY_FLAG_SEP: u16 = 0x0001;
+
+// 本地信任锚应答器：由本服务直接应答信任锚文件中名称的 DS/DNSKEY 查询，不经过缓存与上游
+pub struct TrustAnchorResponder {
+    // 信任锚记录
+    records: Vec<Record>,
+}
+
+impl TrustAnchorResponder {
+    // 根据配置加载信任锚文件，未启用时返回 None
+    pub fn new(config: &LocalTrustAnchorConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&config.file_path).map_err(|e| ServerError::Config(format!(
+            "Failed to read trust anchor file {}: {}", config.file_path, e
+        )))?;
+        let records = parse_trust_anchors(&content, config.ttl).map_err(|e| ServerError::Config(format!(
+            "Invalid trust anchor file {}: {}", config.file_path, e
+        )))?;
+        if records.is_empty() {
+            return Err(ServerError::Config(format!(
+                "Trust anchor file {} contains no DS or DNSKEY records", config.file_path
+            )));
+        }
+
+        info!(file = %config.file_path, records = records.len(), "Local trust anchor loaded");
+        Ok(Some(Self { records }))
+    }
+
+    // 若查询为信任锚中名称的 DS/DNSKEY 查询，返回本地应答；文件中无对应类型的记录时交由上游解析
+    pub fn respond(&self, query_message: &Message) -> Option<Message> {
+        let query = query_message.queries().first()?;
+        if !matches!(query.query_type(), RecordType::DS | RecordType::DNSKEY) || query.query_class() != DNSClass::IN {
+            return None;
+        }
+
+        let answers: Vec<Record> = self.records.iter()
+            .filter(|record| record.record_type() == query.query_type() && record.name() == query.name())
+            .cloned()
+            .collect();
+        if answers.is_empty() {
+            return None;
+        }
+
+        debug!(name = %query.name(), query_type = ?query.query_type(), answers = answers.len(), "Answering from local trust anchor");
+
+        let mut response = Message::new();
+        response.set_id(query_message.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(query_message.op_code())
+            .set_recursion_desired(query_message.recursion_desired())
+            .set_recursion_available(true)
+            .set_response_code(ResponseCode::NoError)
+            .add_query(query.clone())
+            .add_answers(answers);
+
+        Some(response)
+    }
+}
+
+// 解析区文件格式的信任锚：每条记录为 "<名称> [TTL] [IN] DS|DNSKEY <RDATA>"，
+// 支持 ; 注释与括号跨行，其他类型的记录被忽略
+pub fn parse_trust_anchors(content: &str, default_ttl: u32) -> std::result::Result<Vec<Record>, String> {
+    let mut records = Vec::new();
+    let mut pending = String::new();
+    let mut depth = 0i32;
+
+    for line in content.lines() {
+        let line = line.split(';').next().unwrap_or_default();
+        depth += line.matches('(').count() as i32 - line.matches(')').count() as i32;
+        if depth < 0 {
+            return Err("unbalanced parentheses".to_string());
+        }
+        pending.push(' ');
+        pending.push_str(&line.replace(['(', ')'], " "));
+        if depth > 0 {
+            continue;
+        }
+
+        let entry = std::mem::take(&mut pending);
+        if let Some(record) = parse_record(entry.split_whitespace().collect(), default_ttl)? {
+            records.push(record);
+        }
+    }
+
+    if depth > 0 {
+        return Err("unbalanced parentheses".to_string());
+    }
+    Ok(records)
+}
+
+// 解析单条记录，非 DS/DNSKEY 记录返回 None
+fn parse_record(tokens: Vec<&str>, default_ttl: u32) -> std::result::Result<Option<Record>, String> {
+    let Some((owner, mut rest)) = tokens.split_first() else {
+        return Ok(None);
+    };
+    let name = Name::from_str(owner).map_err(|e| format!("invalid owner name '{}': {}", owner, e))?;
+
+    // TTL 与类别可按任意顺序出现
+    let mut ttl = default_ttl;
+    while let Some((token, tail)) = rest.split_first() {
+        if let Ok(value) = token.parse::<u32>() {
+            ttl = value;
+        } else if !token.eq_ignore_ascii_case("IN") {
+            break;
+        }
+        rest = tail;
+    }
+
+    let Some((record_type, rdata)) = rest.split_first() else {
+        return Err(format!("missing record type for '{}'", owner));
+    };
+    let rdata = match record_type.to_ascii_uppercase().as_str() {
+        "DS" => parse_ds(rdata)?,
+        "DNSKEY" => parse_dnskey(rdata)?,
+        _ => return Ok(None),
+    };
+
+    let mut record = Record::from_rdata(name, ttl, rdata);
+    record.set_dns_class(DNSClass::IN);
+    Ok(Some(record))
+}
+
+// 解析 DS 记录数据：<key tag> <algorithm> <digest type> <digest (hex)>
+fn parse_ds(fields: &[&str]) -> std::result::Result<RData, String> {
+    let [key_tag, algorithm, digest_type, digest @ ..] = fields else {
+        return Err(format!("invalid DS record data: {}", fields.join(" ")));
+    };
+    let key_tag = key_tag.parse::<u16>().map_err(|_| format!("invalid DS key tag: {}", key_tag))?;
+    let algorithm = parse_algorithm(algorithm)?;
+    let digest_type = digest_type.parse::<u8>().ok()
+        .and_then(|value| DigestType::from_u8(value).ok())
+        .ok_or_else(|| format!("unsupported DS digest type: {}", digest_type))?;
+    let digest = hex::decode(digest.concat()).map_err(|e| format!("invalid DS digest: {}", e))?;
+    if digest.is_empty() {
+        return Err("DS digest is empty".to_string());
+    }
+
+    Ok(RData::DNSSEC(DNSSECRData::DS(DS::new(key_tag, algorithm, digest_type, digest))))
+}
+
+// 解析 DNSKEY 记录数据：<flags> <protocol> <algorithm> <public key (base64)>
+fn parse_dnskey(fields: &[&str]) -> std::result::Result<RData, String> {
+    let [flags, protocol, algorithm, public_key @ ..] = fields else {
+        return Err(format!("invalid DNSKEY record data: {}", fields.join(" ")));
+    };
+    let flags = flags.parse::<u16>().map_err(|_| format!("invalid DNSKEY flags: {}", flags))?;
+    if protocol.parse::<u8>() != Ok(DNSKEY_PROTOCOL) {
+        return Err(format!("invalid DNSKEY protocol: {}", protocol));
+    }
+    let algorithm = parse_algorithm(algorithm)?;
+    let public_key = STANDARD.decode(public_key.concat()).map_err(|e| format!("invalid DNSKEY public key: {}", e))?;
+    if public_key.is_empty() {
+        return Err("DNSKEY public key is empty".to_string());
+    }
+
+    Ok(RData::DNSSEC(DNSSECRData::DNSKEY(DNSKEY::new(
+        flags & DNSKEY_FLAG_ZONE_KEY != 0,
+        flags & DNSKEY_FLAG_SEP != 0,
+        flags & DNSKEY_FLAG_REVOKE != 0,
+        algorithm,
+        public_key,
+    ))))
+}
+
+fn parse_algorithm(value: &str) -> std::result::Result<Algorithm, String> {
+    value.parse::<u8>()
+        .map(Algorithm::from_u8)
+        .map_err(|_| format!("invalid DNSSEC algorithm: {}", value))
+}
@@ -1,27 +1,47 @@
 // src/server/upstream.rs
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::net::{SocketAddr, IpAddr};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures::future::BoxFuture;
 use reqwest::{Client, header};
-use tracing::{debug, info};
-use hickory_resolver::TokioAsyncResolver;
-use hickory_resolver::proto::op::{Message, MessageType, OpCode, ResponseCode};
+use tracing::{debug, info, warn};
+use hickory_resolver::AsyncResolver;
+use hickory_resolver::name_server::{GenericConnector, RuntimeProvider, TokioHandle, TokioRuntimeProvider};
+use hickory_resolver::proto::TokioTime;
+use hickory_resolver::proto::iocompat::AsyncIoTokioAsStd;
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::proto::op::{Edns, Message, MessageType, OpCode, ResponseCode};
+use hickory_resolver::proto::rr::Record;
+use hickory_resolver::proto::rr::rdata::opt::EdnsCode;
 use hickory_resolver::config::{
     NameServerConfig, Protocol, ResolverConfig, ResolverOpts,
 };
 use tokio::time::Instant;
 
-use crate::server::config::{ServerConfig, UpstreamConfig, ResolverProtocol};
+use crate::server::config::{
+    QuestionMismatchPolicy, ServerConfig, UpstreamConfig, ResolverProtocol, ResolverConfig as UpstreamResolverConfig,
+};
 use crate::server::error::{Result, ServerError};
 use crate::server::ecs::{EcsProcessor, EcsData};
-use crate::common::consts::CONTENT_TYPE_DNS_MESSAGE;
-use crate::server::metrics::METRICS;
+use crate::common::consts::{
+    CONTENT_TYPE_DNS_MESSAGE, DNS_STANDARD_PORT, DOH_STANDARD_PATH, DOH_STANDARD_PORT, DOT_STANDARD_PORT,
+    EDNS_CLIENT_SUBNET_OPTION_CODE,
+};
+use crate::server::metrics::{query_type_label, METRICS};
+use crate::server::dns0x20::Dns0x20Resolver;
+use crate::server::create_pooled_http_client;
 
 // Metrics 标签常量
 const DNS_QUERY_DESTINATION_UPSTREAM: &str = "sent_to_upstream";
+// 上游查询不区分客户端请求格式
+const DNS_QUERY_FORMAT_NONE: &str = "";
 const UPSTREAM_PROTOCOL_DOH: &str = "DoH";
+const STANDARD_RESOLVER_ID: &str = "hickory-resolver";
 const UPSTREAM_FAILURE_REASON_ERROR: &str = "error";
 const DNSSEC_VALIDATION_SUCCESS: &str = "success";
 const DNSSEC_VALIDATION_FAILURE: &str = "failure";
@@ -38,6 +58,20 @@ pub enum UpstreamSelection {
     Global,
 }
 
+// 上游解析器扩展点：接收 DNS 查询消息并返回应答消息
+// 内置的 DoH 与 hickory-resolver（UDP/TCP/DoT）传输均实现此 trait，
+// 下游 crate 可实现自定义协议（如 gRPC 内部解析服务）并通过 UpstreamManager::register_resolver 注册
+pub trait UpstreamResolver: Send + Sync {
+    // 解析器标识，用于指标与日志
+    fn id(&self) -> &str;
+    
+    // 协议名称，用于指标与日志
+    fn protocol(&self) -> &str;
+    
+    // 执行查询
+    fn resolve<'a>(&'a self, query_message: &'a Message) -> BoxFuture<'a, Result<Message>>;
+}
+
 // DoH查询客户端
 struct DoHClient {
     // HTTP客户端
@@ -57,6 +91,9 @@ impl DoHClient {
         // 将DNS消息转换为二进制格式
         let dns_wire = dns_message.to_vec()?;
         
+        // 记录进行中的上游请求数（请求结束或被取消时减少）
+        let _active = ActiveRequestGuard::new(&self.url);
+        
         // 构建请求 - 提前创建内容类型变量避免重复创建
         let content_type = CONTENT_TYPE_DNS_MESSAGE;
         
@@ -102,12 +139,173 @@ impl DoHClient {
     }
 }
 
+// 进行中的上游请求计数守卫：创建时增加、释放时减少
+struct ActiveRequestGuard<'a> {
+    resolver: &'a str,
+}
+
+impl<'a> ActiveRequestGuard<'a> {
+    fn new(resolver: &'a str) -> Self {
+        METRICS.upstream_active_requests().with_label_values(&[resolver]).inc();
+        Self { resolver }
+    }
+}
+
+impl Drop for ActiveRequestGuard<'_> {
+    fn drop(&mut self) {
+        METRICS.upstream_active_requests().with_label_values(&[self.resolver]).dec();
+    }
+}
+
+impl UpstreamResolver for DoHClient {
+    fn id(&self) -> &str {
+        &self.url
+    }
+    
+    fn protocol(&self) -> &str {
+        UPSTREAM_PROTOCOL_DOH
+    }
+    
+    fn resolve<'a>(&'a self, query_message: &'a Message) -> BoxFuture<'a, Result<Message>> {
+        Box::pin(self.query(query_message))
+    }
+}
+
+// 上游查询使用的运行时：UDP 源端口可选择由操作系统分配，TCP 连接受连接超时限制
+#[derive(Clone)]
+struct UpstreamRuntimeProvider {
+    // 内部 Tokio 运行时
+    inner: TokioRuntimeProvider,
+    // 是否使用 hickory 的随机源端口（每次查询新建套接字并随机选择端口）
+    randomize_udp_port: bool,
+    // TCP 连接超时（TCP 与 DoT 传输）
+    connect_timeout: Duration,
+}
+
+impl RuntimeProvider for UpstreamRuntimeProvider {
+    type Handle = TokioHandle;
+    type Timer = TokioTime;
+    type Udp = tokio::net::UdpSocket;
+    type Tcp = AsyncIoTokioAsStd<tokio::net::TcpStream>;
+
+    fn create_handle(&self) -> Self::Handle {
+        self.inner.create_handle()
+    }
+
+    fn connect_tcp(
+        &self,
+        server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = std::io::Result<Self::Tcp>>>> {
+        // 连接阶段单独限时，不可达的解析器尽快失败以便切换到下一个
+        let connect = self.inner.connect_tcp(server_addr);
+        let connect_timeout = self.connect_timeout;
+        Box::pin(async move {
+            tokio::time::timeout(connect_timeout, connect)
+                .await
+                .map_err(|_| std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("connection to {} timed out after {:?}", server_addr, connect_timeout),
+                ))?
+        })
+    }
+
+    fn bind_udp(
+        &self,
+        local_addr: SocketAddr,
+        server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = std::io::Result<Self::Udp>>>> {
+        // 关闭随机化时忽略 hickory 选择的端口，交由操作系统分配
+        let local_addr = if self.randomize_udp_port {
+            local_addr
+        } else {
+            SocketAddr::new(local_addr.ip(), 0)
+        };
+        self.inner.bind_udp(local_addr, server_addr)
+    }
+}
+
+// 使用上游运行时的 hickory 解析器
+type UpstreamAsyncResolver = AsyncResolver<GenericConnector<UpstreamRuntimeProvider>>;
+
+// 创建 hickory 解析器，按配置决定 UDP 源端口策略与连接超时
+fn new_async_resolver(
+    resolver_config: ResolverConfig,
+    resolver_opts: ResolverOpts,
+    config: &UpstreamConfig,
+) -> UpstreamAsyncResolver {
+    let provider = UpstreamRuntimeProvider {
+        inner: TokioRuntimeProvider::new(),
+        randomize_udp_port: config.udp_source_port_randomization,
+        connect_timeout: Duration::from_secs(config.connect_timeout),
+    };
+    AsyncResolver::new(resolver_config, resolver_opts, GenericConnector::new(provider))
+}
+
+// 由 hickory-resolver 处理的传输（UDP/TCP/DoT）
+struct StandardResolver {
+    // 内部 hickory 解析器
+    resolver: UpstreamAsyncResolver,
+    // 解析器标识
+    id: String,
+    // 协议名称
+    protocol: String,
+    // 上游明确给出的否定应答（NXDOMAIN/NODATA）是否作为应答返回，否则视为查询失败
+    answer_negative: bool,
+}
+
+impl StandardResolver {
+    // 通过 hickory-resolver 执行查询
+    async fn query(&self, query_message: &Message) -> Result<Message> {
+        let query = query_message.queries().first().ok_or_else(||
+            ServerError::Upstream("No query in message".to_string())
+        )?;
+
+        match self.resolver.lookup(query.name().clone(), query.query_type()).await {
+            Ok(lookup) => Ok(build_lookup_response(query_message, ResponseCode::NoError, lookup.record_iter())),
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { response_code, .. } if self.answer_negative => {
+                    Ok(build_lookup_response(query_message, *response_code, std::iter::empty()))
+                },
+                _ => Err(ServerError::Upstream(format!("DNS query failed: {}", e))),
+            },
+        }
+    }
+}
+
+impl UpstreamResolver for StandardResolver {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    
+    fn protocol(&self) -> &str {
+        &self.protocol
+    }
+    
+    fn resolve<'a>(&'a self, query_message: &'a Message) -> BoxFuture<'a, Result<Message>> {
+        Box::pin(self.query(query_message))
+    }
+}
+
+// 配置了协议回退链的解析器，按顺序尝试各传输直至成功
+struct FallbackResolver {
+    // 解析器地址
+    address: String,
+    // 按优先级排列的传输
+    transports: Vec<Arc<dyn UpstreamResolver>>,
+}
+
 // 上游组解析配置
 struct UpstreamGroupConfig {
-    // 内部 TokioAsyncResolver
-    resolver: TokioAsyncResolver,
+    // 由 hickory-resolver 处理的标准解析器（UDP/TCP/DoT）
+    standard_resolver: Arc<dyn UpstreamResolver>,
     // DoH客户端
-    doh_clients: Vec<Arc<DoHClient>>,
+    doh_clients: Vec<Arc<dyn UpstreamResolver>>,
+    // 通过 register_resolver 注册的自定义解析器，优先于内置传输
+    custom_resolvers: Vec<Arc<dyn UpstreamResolver>>,
+    // 配置了协议回退链的解析器
+    fallback_resolvers: Vec<FallbackResolver>,
+    // 是否存在未配置协议回退链的解析器
+    has_standard_resolvers: bool,
     // 上游配置 - 使用引用代替克隆整个配置
     config: Arc<UpstreamConfig>,
 }
@@ -138,8 +336,22 @@ impl UpstreamManager {
                 // 获取此组的有效配置（继承与覆盖全局配置）
                 let effective_config = Arc::new(config.get_effective_upstream_config(&group.name)?);
                 
+                // 覆盖了连接池参数的组使用独立的 HTTP 客户端
+                let group_client = match config.get_group_pool_config(&group.name) {
+                    Some(pool) => {
+                        info!(
+                            group_name = &group.name,
+                            pool_idle_timeout = pool.idle_timeout,
+                            pool_max_idle_connections = pool.max_idle_connections,
+                            "Using dedicated HTTP connection pool for upstream group"
+                        );
+                        create_pooled_http_client(&config, &pool, &group.name)?
+                    },
+                    None => http_client.clone(),
+                };
+                
                 // 创建上游组配置
-                let group_config = Self::create_upstream_group_config(&config, effective_config.clone(), http_client.clone())?;
+                let group_config = Self::create_upstream_group_config(&config, effective_config.clone(), group_client)?;
                 
                 // 添加到映射
                 group_configs.insert(group.name.clone(), group_config);
@@ -176,14 +388,22 @@ impl UpstreamManager {
         // 构建 hickory-resolver 配置（用于非DoH协议）
         let (resolver_config, resolver_opts) = Self::build_resolver_config(&upstream_config)?;
         
-        // 创建异步解析器
-        let resolver = TokioAsyncResolver::tokio(resolver_config, resolver_opts);
+        // 创建标准解析器，协议标签取第一个解析器的协议
+        let standard_resolver = StandardResolver {
+            resolver: new_async_resolver(resolver_config, resolver_opts, &upstream_config),
+            id: STANDARD_RESOLVER_ID.to_string(),
+            protocol: match upstream_config.resolvers.first() {
+                Some(r) => format!("{:?}", r.protocol),
+                None => "Unknown".to_string(),
+            },
+            answer_negative: false,
+        };
         
         // 创建DoH客户端列表
-        let mut doh_clients = Vec::new();
+        let mut doh_clients: Vec<Arc<dyn UpstreamResolver>> = Vec::new();
         
         for resolver_config in &upstream_config.resolvers {
-            if resolver_config.protocol == ResolverProtocol::Doh {
+            if resolver_config.protocols.is_none() && resolver_config.protocol == ResolverProtocol::Doh {
                 // 使用共享的 HTTP 客户端
                 let client = DoHClient::new(resolver_config.address.clone(), http_client.clone());
                 doh_clients.push(Arc::new(client));
@@ -194,13 +414,54 @@ impl UpstreamManager {
             }
         }
         
+        // 创建协议回退链解析器，启用 DNS 0x20 的 UDP 解析器同样按顺序单独查询
+        let fallback_resolvers = upstream_config.resolvers
+            .iter()
+            .filter(|r| !Self::is_standard_resolver(r))
+            .map(|r| if r.dns_0x20 {
+                Self::build_dns0x20_resolver(r, &upstream_config)
+            } else {
+                Self::build_fallback_resolver(r, &upstream_config, http_client.clone())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let has_standard_resolvers = upstream_config.resolvers.iter().any(Self::is_standard_resolver);
+        
         Ok(UpstreamGroupConfig {
-            resolver,
+            standard_resolver: Arc::new(standard_resolver),
             doh_clients,
+            custom_resolvers: Vec::new(),
+            fallback_resolvers,
+            has_standard_resolvers,
             config: upstream_config,
         })
     }
     
+    // 为全局上游或指定上游组注册自定义解析器
+    // 注册的解析器优先于内置传输，同一目标注册多个时使用最先注册的
+    pub fn register_resolver(
+        &mut self,
+        selection: &UpstreamSelection,
+        resolver: Arc<dyn UpstreamResolver>,
+    ) -> Result<()> {
+        let (target_config, group_name) = match selection {
+            UpstreamSelection::Group(group_name) => match self.group_configs.get_mut(group_name) {
+                Some(config) => (config, group_name.as_str()),
+                None => return Err(ServerError::Upstream(format!("Unknown upstream group: {}", group_name))),
+            },
+            UpstreamSelection::Global => (&mut self.global_config, "global"),
+        };
+        
+        info!(
+            resolver = resolver.id(),
+            protocol = resolver.protocol(),
+            upstream_group = group_name,
+            "Registered custom upstream resolver"
+        );
+        target_config.custom_resolvers.push(resolver);
+        
+        Ok(())
+    }
+    
     // 执行 DNS 查询
     pub async fn resolve(
         &self, 
@@ -240,9 +501,14 @@ impl UpstreamManager {
         // 获取 ECS 策略
         let ecs_policy = self.server_config.get_effective_ecs_policy(group_name)?;
         
+        // 精简查询，避免将客户端附加节中的记录转发至上游
+        let minimized_query = self.server_config.dns.upstream.minimize_query
+            .then(|| minimize_query(query_message));
+        let outbound_query = minimized_query.as_ref().unwrap_or(query_message);
+        
         // 处理 ECS，根据策略和 client_ecs 参数修改查询
         let processed_query = match EcsProcessor::process_ecs_for_query(
-            query_message, 
+            outbound_query, 
             &ecs_policy,
             client_ip,
             client_ecs
@@ -255,7 +521,7 @@ impl UpstreamManager {
                     
                 new_query
             },
-            None => query_message.clone(), // 这里的克隆是必要的
+            None => minimized_query.unwrap_or_else(|| query_message.clone()),
         };
         
         // 记录查询信息
@@ -271,170 +537,51 @@ impl UpstreamManager {
         // 记录DNS查询统计
         {
             METRICS.dns_queries_total().with_label_values(&[
-                &format!("{:?}", query.query_type()), 
-                DNS_QUERY_DESTINATION_UPSTREAM
+                &query_type_label(query.query_type()), 
+                DNS_QUERY_DESTINATION_UPSTREAM,
+                DNS_QUERY_FORMAT_NONE
             ]).inc();
             
             METRICS.dns_query_type_total().with_label_values(&[
-                &format!("{:?}", query.query_type())
+                &query_type_label(query.query_type())
             ]).inc();
         }
         
         // 记录查询开始时间，用于计算查询时间
         let query_start = Instant::now();
         
-        // 执行查询
-        let response = if !target_config.doh_clients.is_empty() {
-            // 有 DoH 客户端，优先使用
-            let client = &target_config.doh_clients[0]; // 简单选择第一个，后续可以实现更复杂的负载均衡
-            
-            // 记录上游请求
-            {
-                METRICS.upstream_requests_total().with_label_values(&[
-                    &client.url, UPSTREAM_PROTOCOL_DOH, group_name
-                ]).inc();
-            }
-            
-            // 开始计时
-            let upstream_start = Instant::now();
-            
-            // 执行查询
-            match client.query(&processed_query).await {
-                Ok(resp) => {
-                    // 计算查询时间
-                    let upstream_duration = upstream_start.elapsed().as_secs_f64();
-                    
-                    // 记录上游查询时间
-                    {
-                        METRICS.upstream_duration_seconds().with_label_values(&[
-                            &client.url, UPSTREAM_PROTOCOL_DOH, group_name
-                        ]).observe(upstream_duration);
-                    }
-                    
-                    // 如果启用了DNSSEC，记录验证结果
-                    if target_config.config.enable_dnssec {
-                        let is_validated = resp.authentic_data();
-                        let status = if is_validated { DNSSEC_VALIDATION_SUCCESS } else { DNSSEC_VALIDATION_FAILURE };
-                        METRICS.dnssec_validations_total().with_label_values(&[status]).inc();
-                    }
-                    
-                    resp
-                }
-                Err(e) => {
-                    // 计算查询时间
-                    let upstream_duration = upstream_start.elapsed().as_secs_f64();
-                    
-                    // 记录查询失败
-                    {
-                        METRICS.upstream_failures_total().with_label_values(&[
-                            UPSTREAM_FAILURE_REASON_ERROR, &client.url, group_name
-                        ]).inc();
-                        
-                        METRICS.upstream_duration_seconds().with_label_values(&[
-                            &client.url, UPSTREAM_PROTOCOL_DOH, group_name
-                        ]).observe(upstream_duration);
-                    }
-                    
-                    return Err(e);
-                }
+        // 优先尝试配置了协议回退链的解析器（注册了自定义解析器时跳过内置传输）
+        let fallback_response = if target_config.custom_resolvers.is_empty() {
+            match self.resolve_with_fallback(target_config, &processed_query, group_name).await {
+                Some(Ok(resp)) => Some(resp),
+                Some(Err(e)) if !target_config.has_standard_resolvers => return Err(e),
+                _ => None,
             }
         } else {
-            // 没有 DoH 客户端，使用标准解析器
-            let query = processed_query.queries().first().ok_or_else(|| 
-                ServerError::Upstream("No query in message".to_string())
-            )?;
-            
-            // 记录上游请求（使用通用标识）
-            let resolver_id = "hickory-resolver";
-            let protocol = match target_config.config.resolvers.first() {
-                Some(r) => format!("{:?}", r.protocol),
-                None => "Unknown".to_string(),
-            };
-            
-            {
-                METRICS.upstream_requests_total().with_label_values(&[
-                    resolver_id, &protocol, group_name
-                ]).inc();
-            }
-            
-            // 开始计时
-            let upstream_start = Instant::now();
-            
-            // 使用lookup方法进行查询
-            let lookup_result = target_config.resolver.lookup(
-                query.name().clone(),
-                query.query_type()
-            ).await;
-            
-            // 计算查询时间
-            let upstream_duration = upstream_start.elapsed().as_secs_f64();
-            
-            // 记录查询时间
-            {
-                METRICS.upstream_duration_seconds().with_label_values(&[
-                    resolver_id, &protocol, group_name
-                ]).observe(upstream_duration);
-            }
-            
-            // 处理查询结果
-            let response = match lookup_result {
-                Ok(lookup) => {
-                    // 构建DNS响应消息
-                    let mut message = Message::new();
-                    message.set_id(processed_query.id())
-                        .set_message_type(MessageType::Response)
-                        .set_op_code(processed_query.op_code())
-                        .set_response_code(ResponseCode::NoError)
-                        .set_recursion_desired(processed_query.recursion_desired())
-                        .set_recursion_available(true);
-                    
-                    // 添加原始查询
-                    for q in processed_query.queries() {
-                        message.add_query(q.clone());
-                    }
-                    
-                    // 添加记录
-                    for record in lookup.record_iter() {
-                        message.add_answer(record.clone());
-                    }
-                    
-                    // 如果启用了DNSSEC，记录验证统计
-                    if target_config.config.enable_dnssec {
-                        // lookup 对象没有 dnssec_status 方法，直接设置 AD 标志
-                        // Trust-DNS 解析器会在验证成功时自动设置消息的AD标志
-                        let is_validated = message.authentic_data();
-                        
-                        // 记录DNSSEC验证结果
-                        {
-                            let status = if is_validated { DNSSEC_VALIDATION_SUCCESS } else { DNSSEC_VALIDATION_FAILURE };
-                            METRICS.dnssec_validations_total().with_label_values(&[status]).inc();
-                        }
-                    }
-                    
-                    message
-                },
-                Err(e) => {
-                    // 记录查询失败
-                    {
-                        METRICS.upstream_failures_total().with_label_values(&[
-                            UPSTREAM_FAILURE_REASON_ERROR, resolver_id, group_name
-                        ]).inc();
-                    }
-                    
-                    return Err(ServerError::Upstream(format!("DNS query failed: {}", e)));
-                }
-            };
-            
-            response
+            None
+        };
+        
+        // 执行查询：自定义解析器优先，其次 DoH 客户端，最后标准解析器
+        let response = match fallback_response {
+            Some(resp) => resp,
+            None => {
+                let resolver = target_config.custom_resolvers.first()
+                    .or_else(|| target_config.doh_clients.first()) // 简单选择第一个，后续可以实现更复杂的负载均衡
+                    .unwrap_or(&target_config.standard_resolver);
+                Self::query_resolver(target_config, resolver.as_ref(), &processed_query, group_name).await?
+            },
         };
         
+        // 校验应答的问题节，缺失或不一致时按策略修正或拒绝，避免客户端混淆及缓存错配
+        let response = self.check_response_question(query_message, response, group_name);
+        
         // 计算总查询时间
         let query_duration = query_start.elapsed().as_secs_f64();
         
         // 记录总查询时间
         {
             METRICS.dns_query_duration_seconds().with_label_values(&[
-                &format!("{:?}", query.query_type())
+                &query_type_label(query.query_type())
             ]).observe(query_duration);
         }
         
@@ -449,6 +596,276 @@ impl UpstreamManager {
         Ok(response)
     }
     
+    // 校验应答的问题节是否与查询一致（名称比较不区分大小写）
+    fn check_response_question(&self, query_message: &Message, mut response: Message, group_name: &str) -> Message {
+        if response.queries() == query_message.queries() {
+            return response;
+        }
+        
+        let policy = self.server_config.dns.upstream.question_mismatch_policy;
+        warn!(
+            id = query_message.id(),
+            expected = ?query_message.queries(),
+            received = ?response.queries(),
+            upstream_group = group_name,
+            policy = ?policy,
+            "Upstream response question section is missing or does not match the query"
+        );
+        
+        match policy {
+            QuestionMismatchPolicy::Fixup => {
+                response.take_queries();
+                response.add_queries(query_message.queries().to_vec());
+                response
+            },
+            QuestionMismatchPolicy::Servfail => {
+                let mut servfail = Message::new();
+                servfail.set_id(query_message.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(query_message.op_code())
+                    .set_recursion_desired(query_message.recursion_desired())
+                    .set_recursion_available(true)
+                    .set_response_code(ResponseCode::ServFail)
+                    .add_queries(query_message.queries().to_vec());
+                servfail
+            },
+        }
+    }
+    
+    // 通过单个解析器执行查询并记录上游指标
+    async fn query_resolver(
+        target_config: &UpstreamGroupConfig,
+        resolver: &dyn UpstreamResolver,
+        query_message: &Message,
+        group_name: &str,
+    ) -> Result<Message> {
+        // 记录上游请求
+        METRICS.upstream_requests_total().with_label_values(&[
+            resolver.id(), resolver.protocol(), group_name
+        ]).inc();
+        
+        // 开始计时
+        let upstream_start = Instant::now();
+        let result = resolver.resolve(query_message).await;
+        
+        // 记录上游查询时间
+        METRICS.upstream_duration_seconds().with_label_values(&[
+            resolver.id(), resolver.protocol(), group_name
+        ]).observe(upstream_start.elapsed().as_secs_f64());
+        
+        match result {
+            Ok(resp) => {
+                // 如果启用了DNSSEC，记录验证结果
+                if target_config.config.enable_dnssec {
+                    let status = if resp.authentic_data() { DNSSEC_VALIDATION_SUCCESS } else { DNSSEC_VALIDATION_FAILURE };
+                    METRICS.dnssec_validations_total().with_label_values(&[status]).inc();
+                }
+                
+                Ok(resp)
+            },
+            Err(e) => {
+                // 记录查询失败
+                METRICS.upstream_failures_total().with_label_values(&[
+                    UPSTREAM_FAILURE_REASON_ERROR, resolver.id(), group_name
+                ]).inc();
+                
+                Err(e)
+            },
+        }
+    }
+    
+    // 按协议回退链依次查询，未配置回退链时返回 None
+    async fn resolve_with_fallback(
+        &self,
+        target_config: &UpstreamGroupConfig,
+        query_message: &Message,
+        group_name: &str,
+    ) -> Option<Result<Message>> {
+        if target_config.fallback_resolvers.is_empty() {
+            return None;
+        }
+        
+        let mut last_error = None;
+        for fallback in &target_config.fallback_resolvers {
+            for transport in &fallback.transports {
+                let protocol_label = transport.protocol();
+                
+                METRICS.upstream_requests_total().with_label_values(&[
+                    &fallback.address, protocol_label, group_name
+                ]).inc();
+                
+                let upstream_start = Instant::now();
+                let result = transport.resolve(query_message).await;
+                
+                METRICS.upstream_duration_seconds().with_label_values(&[
+                    &fallback.address, protocol_label, group_name
+                ]).observe(upstream_start.elapsed().as_secs_f64());
+                
+                match result {
+                    Ok(resp) => {
+                        // 记录最终提供应答的传输协议
+                        METRICS.upstream_transport_served_total().with_label_values(&[
+                            protocol_label, group_name
+                        ]).inc();
+                        
+                        if target_config.config.enable_dnssec {
+                            let status = if resp.authentic_data() { DNSSEC_VALIDATION_SUCCESS } else { DNSSEC_VALIDATION_FAILURE };
+                            METRICS.dnssec_validations_total().with_label_values(&[status]).inc();
+                        }
+                        
+                        debug!(
+                            resolver = %fallback.address,
+                            protocol = protocol_label,
+                            upstream_group = group_name,
+                            "Query served by fallback transport"
+                        );
+                        return Some(Ok(resp));
+                    },
+                    Err(e) => {
+                        METRICS.upstream_failures_total().with_label_values(&[
+                            UPSTREAM_FAILURE_REASON_ERROR, &fallback.address, group_name
+                        ]).inc();
+                        
+                        warn!(
+                            resolver = %fallback.address,
+                            protocol = protocol_label,
+                            upstream_group = group_name,
+                            error = %e,
+                            "Upstream transport failed, trying next protocol"
+                        );
+                        last_error = Some(e);
+                    }
+                }
+            }
+        }
+        
+        Some(Err(last_error.unwrap_or_else(|| {
+            ServerError::Upstream("All fallback transports failed".to_string())
+        })))
+    }
+    
+    // 协议的指标标签
+    fn protocol_label(protocol: ResolverProtocol) -> &'static str {
+        match protocol {
+            ResolverProtocol::Udp => "Udp",
+            ResolverProtocol::Tcp => "Tcp",
+            ResolverProtocol::Dot => "Dot",
+            ResolverProtocol::Doh => UPSTREAM_PROTOCOL_DOH,
+        }
+    }
+    
+    // 是否由标准传输处理（未配置协议回退链且未启用 DNS 0x20）
+    fn is_standard_resolver(resolver: &UpstreamResolverConfig) -> bool {
+        resolver.protocols.is_none() && !resolver.dns_0x20
+    }
+    
+    // 构建启用 DNS 0x20 编码的 UDP 解析器
+    fn build_dns0x20_resolver(resolver: &UpstreamResolverConfig, config: &UpstreamConfig) -> Result<FallbackResolver> {
+        let socket_addr = Self::parse_socket_addr(&resolver.address)?;
+        debug!(address = %resolver.address, "Added UDP upstream resolver with DNS 0x20 encoding");
+        
+        Ok(FallbackResolver {
+            address: resolver.address.clone(),
+            transports: vec![Arc::new(Dns0x20Resolver::new(
+                socket_addr,
+                Duration::from_secs(config.query_timeout),
+                Duration::from_secs(config.connect_timeout),
+            ))],
+        })
+    }
+    
+    // 构建协议回退链解析器：根据 IP（及可选端口）推导各协议的标准端点
+    fn build_fallback_resolver(
+        resolver: &UpstreamResolverConfig,
+        config: &UpstreamConfig,
+        http_client: Client,
+    ) -> Result<FallbackResolver> {
+        // 地址可为 IP 或 IP:端口，显式端口将用于所有传输
+        let (ip, port) = match resolver.address.parse::<IpAddr>() {
+            Ok(ip) => (ip, None),
+            Err(_) => {
+                let socket_addr = Self::parse_socket_addr(&resolver.address)?;
+                (socket_addr.ip(), Some(socket_addr.port()))
+            }
+        };
+        
+        let protocols = resolver.protocols.as_deref().unwrap_or_default();
+        let mut transports: Vec<Arc<dyn UpstreamResolver>> = Vec::with_capacity(protocols.len());
+        for protocol in protocols {
+            let transport: Arc<dyn UpstreamResolver> = match protocol {
+                ResolverProtocol::Doh => {
+                    let socket_addr = SocketAddr::new(ip, port.unwrap_or(DOH_STANDARD_PORT));
+                    let url = format!("https://{}{}", socket_addr, DOH_STANDARD_PATH);
+                    Arc::new(DoHClient::new(url, http_client.clone()))
+                },
+                ResolverProtocol::Dot => {
+                    let tls_name = resolver.tls_name.clone().unwrap_or_else(|| ip.to_string());
+                    let name_server = NameServerConfig {
+                        socket_addr: SocketAddr::new(ip, port.unwrap_or(DOT_STANDARD_PORT)),
+                        protocol: Protocol::Tls,
+                        tls_dns_name: Some(tls_name),
+                        trust_negative_responses: true,
+                        bind_addr: None,
+                    };
+                    Arc::new(Self::single_server_resolver(name_server, *protocol, config))
+                },
+                ResolverProtocol::Udp | ResolverProtocol::Tcp => {
+                    let name_server = NameServerConfig {
+                        socket_addr: SocketAddr::new(ip, port.unwrap_or(DNS_STANDARD_PORT)),
+                        protocol: if *protocol == ResolverProtocol::Udp { Protocol::Udp } else { Protocol::Tcp },
+                        tls_dns_name: None,
+                        trust_negative_responses: true,
+                        bind_addr: None,
+                    };
+                    Arc::new(Self::single_server_resolver(name_server, *protocol, config))
+                },
+            };
+            transports.push(transport);
+        }
+        
+        debug!(
+            address = %resolver.address,
+            protocols = ?protocols,
+            "Added upstream resolver with protocol fallback"
+        );
+        
+        Ok(FallbackResolver {
+            address: resolver.address.clone(),
+            transports,
+        })
+    }
+    
+    // 创建仅包含单个名称服务器的解析器
+    // 上游明确给出的否定应答（NXDOMAIN/NODATA）视为成功，不再回退
+    fn single_server_resolver(
+        name_server: NameServerConfig,
+        protocol: ResolverProtocol,
+        config: &UpstreamConfig,
+    ) -> StandardResolver {
+        let id = name_server.socket_addr.to_string();
+        let mut resolver_config = ResolverConfig::new();
+        resolver_config.add_name_server(name_server);
+        StandardResolver {
+            resolver: new_async_resolver(resolver_config, Self::build_resolver_opts(config), config),
+            id,
+            protocol: Self::protocol_label(protocol).to_string(),
+            answer_negative: true,
+        }
+    }
+    
+    // 构建 hickory-resolver 选项
+    fn build_resolver_opts(config: &UpstreamConfig) -> ResolverOpts {
+        let mut resolver_opts = ResolverOpts::default();
+        
+        // 设置查询超时
+        resolver_opts.timeout = std::time::Duration::from_secs(config.query_timeout);
+        
+        // 设置是否启用DNSSEC
+        resolver_opts.validate = config.enable_dnssec;
+        
+        resolver_opts
+    }
+    
     // 构建 hickory-resolver 配置
     fn build_resolver_config(
         config: &UpstreamConfig,
@@ -456,8 +873,8 @@ impl UpstreamManager {
         // 创建解析器配置
         let mut resolver_config = ResolverConfig::new();
         
-        // 添加解析器
-        for resolver in &config.resolvers {
+        // 添加解析器（配置了协议回退链或启用 DNS 0x20 的解析器单独处理）
+        for resolver in config.resolvers.iter().filter(|r| Self::is_standard_resolver(r)) {
             match resolver.protocol {
                 // UDP/TCP 协议
                 ResolverProtocol::Udp | ResolverProtocol::Tcp => {
@@ -511,15 +928,7 @@ impl UpstreamManager {
         }
         
         // 创建解析器选项
-        let mut resolver_opts = ResolverOpts::default();
-        
-        // 设置查询超时
-        resolver_opts.timeout = std::time::Duration::from_secs(config.query_timeout);
-        
-        // 设置是否启用DNSSEC
-        resolver_opts.validate = config.enable_dnssec;
-        
-        // 在此可以设置其他选项...
+        let resolver_opts = Self::build_resolver_opts(config);
         
         Ok((resolver_config, resolver_opts))
     }
@@ -533,3 +942,56 @@ impl UpstreamManager {
             )))
     }
 } 
+
+// 根据 lookup 结果构建 DNS 响应消息
+fn build_lookup_response<'a>(
+    query_message: &Message,
+    response_code: ResponseCode,
+    records: impl Iterator<Item = &'a Record>,
+) -> Message {
+    let mut message = Message::new();
+    message.set_id(query_message.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query_message.op_code())
+        .set_response_code(response_code)
+        .set_recursion_desired(query_message.recursion_desired())
+        .set_recursion_available(true);
+    
+    // 添加原始查询
+    for q in query_message.queries() {
+        message.add_query(q.clone());
+    }
+    
+    // 添加记录
+    for record in records {
+        message.add_answer(record.clone());
+    }
+    
+    message
+}
+
+// 构建发往上游的精简查询：仅保留 ID、操作码、RD/CD/AD 位与问题节，
+// 客户端携带 EDNS 时以新的 OPT 记录保留 UDP 载荷大小、DO 位与 ECS 选项，丢弃其他附加记录与 EDNS 选项
+pub fn minimize_query(query_message: &Message) -> Message {
+    let mut minimized = Message::new();
+    minimized.set_id(query_message.id())
+        .set_message_type(MessageType::Query)
+        .set_op_code(query_message.op_code())
+        .set_recursion_desired(query_message.recursion_desired())
+        .set_checking_disabled(query_message.checking_disabled())
+        .set_authentic_data(query_message.authentic_data())
+        .add_queries(query_message.queries().to_vec());
+
+    if let Some(client_edns) = query_message.extensions() {
+        let mut edns = Edns::new();
+        edns.set_max_payload(client_edns.max_payload())
+            .set_version(client_edns.version())
+            .set_dnssec_ok(client_edns.dnssec_ok());
+        if let Some(ecs) = client_edns.option(EdnsCode::from(EDNS_CLIENT_SUBNET_OPTION_CODE)) {
+            edns.options_mut().insert(ecs.clone());
+        }
+        minimized.set_edns(edns);
+    }
+
+    minimized
+}
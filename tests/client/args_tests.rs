@@ -183,11 +183,17 @@ mod tests {
             method: None,
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: oxide_wdns::client::args::PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         let result = args.validate();
@@ -208,11 +214,17 @@ mod tests {
             method: None,
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: oxide_wdns::client::args::PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         let result = args.validate();
@@ -233,11 +245,17 @@ mod tests {
             method: None,
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: Some("GZ".to_string()), // 包含非十六进制字符
+            payload_format: oxide_wdns::client::args::PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         let result = args.validate();
@@ -258,11 +276,17 @@ mod tests {
             method: None,
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: oxide_wdns::client::args::PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         let result = args.validate();
@@ -283,11 +307,17 @@ mod tests {
             method: None,
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: oxide_wdns::client::args::PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         let result = args.validate();
@@ -336,4 +366,36 @@ mod tests {
         assert!(args2.insecure);
         info!("Test finished: test_insecure_flag");
     }
+
+    #[test]
+    fn test_edns_option_flag() {
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_edns_option_flag");
+        // 测试：可重复的 --edns-option 参数
+        let args = CliArgs::parse_from([
+            "owdns-cli",
+            "https://dns.google/dns-query",
+            "example.com",
+            "--edns-option", "15:0017",
+            "--edns-option", "65001:",
+        ]);
+        
+        assert_eq!(args.edns_options.len(), 2);
+        assert_eq!(args.edns_options[0].code, 15);
+        assert_eq!(args.edns_options[0].data, vec![0x00, 0x17]);
+        assert_eq!(args.edns_options[1].code, 65001);
+        assert!(args.edns_options[1].data.is_empty());
+        
+        // 代码超出范围、保留代码、无效十六进制及缺少分隔符均应被拒绝
+        for invalid in ["70000:00", "0:00", "65535:00", "15:zz", "15:123", "15"] {
+            let result = CliArgs::try_parse_from([
+                "owdns-cli",
+                "https://dns.google/dns-query",
+                "example.com",
+                "--edns-option", invalid,
+            ]);
+            assert!(result.is_err(), "EDNS option {} should be rejected", invalid);
+        }
+        info!("Test finished: test_edns_option_flag");
+    }
 } 
\ No newline at end of file
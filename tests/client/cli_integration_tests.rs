@@ -1517,4 +1517,73 @@ mod tests {
         assert!(stdout.contains("93.184.216.34"));
         info!("Test completed: test_cli_get_base64url_encoding");
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_cli_payload_from_stdin() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cli_payload_from_stdin");
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("content-type", CONTENT_TYPE_DNS_MESSAGE)
+                .set_body_bytes(create_dns_response()))
+            .mount(&mock_server)
+            .await;
+
+        // 已知的查询报文：stdin.example.com A
+        let mut query = Message::new();
+        query.set_id(4321);
+        query.set_message_type(MessageType::Query);
+        query.set_op_code(OpCode::Query);
+        query.set_recursion_desired(true);
+        query.add_query(hickory_proto::op::Query::query(
+            Name::from_ascii("stdin.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        let wire = query.to_vec().unwrap();
+
+        // 二进制与十六进制文本（含换行）两种格式
+        let inputs = [
+            ("binary", wire.clone()),
+            ("hex", format!("{}\n", hex::encode(&wire)).into_bytes()),
+        ];
+        for (format, input) in inputs {
+            info!(format, "Piping query payload to CLI...");
+            let output = Command::cargo_bin("owdns-cli")
+                .unwrap()
+                .args([
+                    &mock_server.uri(),
+                    "ignored.example.com",
+                    "--payload", "-",
+                    "--payload-format", format,
+                    "--method", "post",
+                    "--no-color",
+                    "-k",
+                ])
+                .write_stdin(input)
+                .output()
+                .expect("Failed to execute command");
+            info!(success = output.status.success(), "Command execution completed");
+            assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+        }
+
+        // 服务器收到的请求体与管道输入的报文一致
+        let requests = mock_server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        for request in requests {
+            assert_eq!(request.body, wire);
+        }
+
+        // 二进制格式不能用于命令行内联载荷
+        let output = Command::cargo_bin("owdns-cli")
+            .unwrap()
+            .args([&mock_server.uri(), "example.com", "--payload", "00", "--payload-format", "binary"])
+            .output()
+            .expect("Failed to execute command");
+        assert!(!output.status.success());
+
+        info!("Test completed: test_cli_payload_from_stdin");
+    }
+}
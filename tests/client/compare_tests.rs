@@ -0,0 +1,102 @@
+// tests/client/compare_tests.rs
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use clap::Parser;
+    use oxide_wdns::client::args::{CompareArgs, DohFormat};
+    use oxide_wdns::client::compare::run_compare;
+    use oxide_wdns::common::consts::CONTENT_TYPE_DNS_MESSAGE;
+    use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+    use hickory_proto::rr::{Name, RData, Record, RecordType};
+    use hickory_proto::rr::rdata::A;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::method;
+    use tracing::info;
+
+    // 辅助函数 - 创建包含指定 A 记录的 DNS 响应
+    fn create_dns_response(ip: Ipv4Addr) -> Vec<u8> {
+        let name = Name::from_ascii("example.com.").unwrap();
+        let mut message = Message::new();
+        message.set_id(1234)
+            .set_message_type(MessageType::Response)
+            .set_op_code(OpCode::Query)
+            .set_response_code(ResponseCode::NoError)
+            .set_recursion_desired(true)
+            .set_recursion_available(true);
+        message.add_query(Query::query(name.clone(), RecordType::A));
+        message.add_answer(Record::from_rdata(name, 300, RData::A(A(ip))));
+        message.to_vec().unwrap()
+    }
+
+    // 辅助函数 - 启动返回指定地址的模拟 DoH 服务器
+    async fn start_doh_server(ip: Ipv4Addr) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("content-type", CONTENT_TYPE_DNS_MESSAGE)
+                .set_body_bytes(create_dns_response(ip)))
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[test]
+    fn test_compare_args_parse() {
+        info!("Starting test: test_compare_args_parse");
+
+        let args = CompareArgs::parse_from([
+            "compare", "--servers", "https://a.example/dns-query,https://b.example/dns-query",
+            "example.com", "-r", "AAAA", "--json",
+        ]);
+        assert_eq!(args.servers.len(), 2);
+        assert_eq!(args.domain, "example.com");
+        assert_eq!(args.record_type, "AAAA");
+        assert!(args.json);
+        assert!(args.validate().is_ok());
+
+        // 至少需要两个服务器
+        let args = CompareArgs::parse_from(["compare", "--servers", "https://a.example/dns-query", "example.com"]);
+        assert!(args.validate().is_err());
+
+        info!("Test completed: test_compare_args_parse");
+    }
+
+    #[tokio::test]
+    async fn test_run_compare_detects_discrepancy() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_run_compare_detects_discrepancy");
+
+        // 两个服务器返回相同地址，第三个返回不同地址
+        let server_a = start_doh_server(Ipv4Addr::new(192, 0, 2, 1)).await;
+        let server_b = start_doh_server(Ipv4Addr::new(192, 0, 2, 1)).await;
+        let server_c = start_doh_server(Ipv4Addr::new(198, 51, 100, 1)).await;
+
+        let args = CompareArgs {
+            servers: vec![server_a.uri(), server_b.uri(), server_c.uri()],
+            domain: "example.com".to_string(),
+            record_type: "A".to_string(),
+            format: DohFormat::Wire,
+            method: None,
+            dnssec: false,
+            insecure: true,
+            json: false,
+            no_color: true,
+        };
+        args.validate().unwrap();
+
+        let report = run_compare(&args).await.unwrap();
+        info!(?report, "Comparison finished");
+
+        assert!(!report.agree, "Servers should be reported as disagreeing");
+        assert_eq!(report.distinct_answer_sets, 2);
+        assert_eq!(report.results.len(), 3);
+        assert!(report.results[0].matches_majority);
+        assert!(report.results[1].matches_majority);
+        assert!(!report.results[2].matches_majority, "Third server deviates from the majority");
+        assert_eq!(report.results[2].answers, vec!["A 198.51.100.1".to_string()]);
+
+        info!("Test completed: test_run_compare_detects_discrepancy");
+    }
+}
@@ -2,8 +2,8 @@
 
 #[cfg(test)]
 mod tests {
-    use oxide_wdns::client::args::{CliArgs, DohFormat, HttpMethod};
-    use oxide_wdns::client::core::{ValidationCondition, run_query};
+    use oxide_wdns::client::args::{CliArgs, DohFormat, HttpMethod, PayloadFormat};
+    use oxide_wdns::client::core::{ValidationCondition, run_query, run_watch};
     use oxide_wdns::client::error::ClientError;
     use oxide_wdns::common::consts::{CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE};
     use std::str::FromStr;
@@ -75,6 +75,11 @@ mod tests {
 
     // 辅助函数 - 创建 DNS 响应消息
     fn create_dns_response() -> Vec<u8> {
+        create_dns_response_with_ip(std::net::Ipv4Addr::new(93, 184, 216, 34))
+    }
+
+    // 辅助函数 - 创建包含指定 A 记录的 DNS 响应消息
+    fn create_dns_response_with_ip(ip: std::net::Ipv4Addr) -> Vec<u8> {
         let mut message = Message::new();
         message.set_id(1234);
         message.set_message_type(MessageType::Response);
@@ -98,7 +103,7 @@ mod tests {
         record.set_ttl(3600);
         record.set_record_type(RecordType::A);
         record.set_dns_class(DNSClass::IN);
-        record.set_data(Some(RData::A(A(std::net::Ipv4Addr::new(93, 184, 216, 34)))));
+        record.set_data(Some(RData::A(A(ip))));
         message.add_answer(record);
         
         // 编码为二进制
@@ -145,11 +150,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: true, // 允许自签名证书
             verbose: 0,
             no_color: true,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.domain, ?args.record_type, ?args.format, ?args.method, "CLI arguments created");
         
@@ -226,11 +237,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: true, // 允许自签名证书
             verbose: 0,
             no_color: true,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.domain, ?args.record_type, ?args.format, ?args.method, "CLI arguments created");
         
@@ -286,11 +303,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: Some("rcode=NOERROR,min-answers=1".to_string()), // 验证条件
             insecure: true, // 允许自签名证书
             verbose: 0,
             no_color: true,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.domain, ?args.record_type, ?args.validate, "CLI arguments with validation created");
         
@@ -369,11 +392,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: Some("rcode=NOERROR,min-answers=1".to_string()), // 期望成功的验证条件
             insecure: true,
             verbose: 0,
             no_color: true,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.domain, ?args.validate, "CLI arguments created with validation expecting success");
         
@@ -432,11 +461,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: true,
             verbose: 0,
             no_color: true,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.domain, ?args.record_type, "CLI arguments created");
         
@@ -467,4 +502,59 @@ mod tests {
         }
         info!("Test completed: test_run_query_server_error");
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_run_watch_detects_answer_change() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_run_watch_detects_answer_change");
+
+        // 第一次查询返回旧地址，之后返回新地址，模拟 DNS 迁移
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("content-type", CONTENT_TYPE_DNS_MESSAGE)
+                .set_body_bytes(create_dns_response_with_ip(std::net::Ipv4Addr::new(192, 0, 2, 1))))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("content-type", CONTENT_TYPE_DNS_MESSAGE)
+                .set_body_bytes(create_dns_response_with_ip(std::net::Ipv4Addr::new(192, 0, 2, 2))))
+            .mount(&mock_server)
+            .await;
+
+        let args = CliArgs {
+            server_url: mock_server.uri(),
+            domain: "example.com".to_string(),
+            record_type: "A".to_string(),
+            format: DohFormat::Wire,
+            method: Some(HttpMethod::Get),
+            http_version: None,
+            dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
+            payload: None,
+            payload_format: PayloadFormat::Hex,
+            validate: Some("has-ip=192.0.2.1".to_string()),
+            insecure: true,
+            verbose: 0,
+            no_color: true,
+            watch: Some(1),
+            watch_count: Some(1),
+            follow_cname: false,
+        };
+
+        // 检测到一次变化后停止
+        let summary = run_watch(args).await.unwrap();
+        info!(?summary, "Watch finished");
+
+        assert_eq!(summary.queries, 2, "Watch should stop right after the first change");
+        assert_eq!(summary.changes, 1);
+        assert_eq!(summary.alerts, 1, "New answer violates the has-ip expectation");
+        assert_eq!(summary.exit_code(), 3);
+
+        info!("Test completed: test_run_watch_detects_answer_change");
+    }
+}
@@ -3,7 +3,7 @@
 #[cfg(test)]
 mod tests {
     use oxide_wdns::client::error::{ClientError, ClientResult};
-    use oxide_wdns::client::args::{CliArgs, DohFormat, HttpMethod};
+    use oxide_wdns::client::args::{CliArgs, DohFormat, HttpMethod, PayloadFormat};
     
     
     use hickory_proto::op::{Header, ResponseCode};
@@ -184,11 +184,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.domain, "Invalid domain format created");
         
@@ -224,11 +230,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.record_type, "Invalid record type created");
         
@@ -263,11 +275,17 @@ mod tests {
             method: Some(HttpMethod::Post),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: Some("ZZ".to_string()), // 包含非十六进制字符
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.payload, "Invalid hex payload created");
         
@@ -315,11 +333,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         info!("Building DoH request...");
@@ -371,11 +395,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         info!("Building DoH request...");
@@ -428,11 +458,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         info!("Building DoH request...");
@@ -492,11 +528,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         info!("Building DoH request...");
@@ -559,11 +601,17 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         
         info!("Building DoH request...");
@@ -638,12 +686,18 @@ mod tests {
             method: Some(HttpMethod::Get),
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             // 添加验证条件：期望响应码为 NOERROR
             validate: Some("rcode=NOERROR".to_string()),
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         };
         info!(?args.validate, "Validation condition configured");
         
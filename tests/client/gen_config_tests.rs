@@ -0,0 +1,93 @@
+// tests/client/gen_config_tests.rs
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use clap::Parser;
+    use oxide_wdns::client::args::GenConfigArgs;
+    use oxide_wdns::client::gen_config::{generate_config, prompt_options, run_gen_config};
+    use oxide_wdns::common::consts::DEFAULT_GEN_CONFIG_UPSTREAM;
+    use oxide_wdns::server::config::ServerConfig;
+    use tracing::info;
+
+    #[test]
+    fn test_gen_config_defaults_round_trip() {
+        info!("Starting test: test_gen_config_defaults_round_trip");
+
+        let args = GenConfigArgs::parse_from(["gen-config"]);
+        assert_eq!(args.upstream, vec![DEFAULT_GEN_CONFIG_UPSTREAM.to_string()]);
+        assert!(!args.cache && !args.routing);
+
+        // 生成的配置带注释，且可被服务器配置解析
+        let yaml = generate_config(&args).unwrap();
+        assert!(yaml.lines().any(|line| line.trim_start().starts_with('#')));
+        let config: ServerConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config.http.listen_addr, args.listen);
+        assert_eq!(config.dns.upstream.resolvers.len(), 1);
+        assert_eq!(config.dns.upstream.resolvers[0].address, DEFAULT_GEN_CONFIG_UPSTREAM);
+        assert!(!config.dns.cache.enabled);
+        assert!(!config.dns.routing.enabled);
+
+        info!("Test completed: test_gen_config_defaults_round_trip");
+    }
+
+    #[test]
+    fn test_gen_config_with_cache_and_routing() {
+        info!("Starting test: test_gen_config_with_cache_and_routing");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("config.yaml");
+        let args = GenConfigArgs::parse_from([
+            "gen-config",
+            "--listen", "0.0.0.0:8053",
+            "--upstream", "https://dns.google/dns-query,https://dns.quad9.net/dns-query",
+            "--cache", "--routing",
+            "--output", output.to_str().unwrap(),
+        ]);
+        run_gen_config(args).unwrap();
+
+        // 写出的文件可直接由服务器加载（包含验证）
+        let config = ServerConfig::from_file(&output).unwrap();
+        assert_eq!(config.http.listen_addr, "0.0.0.0:8053".parse().unwrap());
+        assert_eq!(config.dns.upstream.resolvers.len(), 2);
+        assert!(config.dns.cache.enabled);
+        assert!(config.dns.routing.enabled);
+        assert_eq!(config.dns.routing.upstream_groups.len(), 1);
+        assert_eq!(config.dns.routing.upstream_groups[0].resolvers.len(), 2);
+        assert_eq!(config.dns.routing.default_upstream_group.as_deref(), Some(config.dns.routing.upstream_groups[0].name.as_str()));
+
+        // 无效的上游地址在生成时即被拒绝
+        let args = GenConfigArgs::parse_from(["gen-config", "--upstream", "http://insecure.example/dns-query"]);
+        assert!(generate_config(&args).is_err());
+
+        info!("Test completed: test_gen_config_with_cache_and_routing");
+    }
+
+    #[test]
+    fn test_gen_config_interactive_prompts() {
+        info!("Starting test: test_gen_config_interactive_prompts");
+
+        // 回车保留监听地址，其余逐项输入
+        let mut args = GenConfigArgs::parse_from(["gen-config", "--interactive"]);
+        let listen = args.listen;
+        let mut input = Cursor::new("\nhttps://a.example/dns-query, https://b.example/dns-query\ny\nn\n");
+        let mut prompts = Vec::new();
+        prompt_options(&mut args, &mut input, &mut prompts).unwrap();
+
+        assert_eq!(args.listen, listen);
+        assert_eq!(args.upstream, vec!["https://a.example/dns-query", "https://b.example/dns-query"]);
+        assert!(args.cache);
+        assert!(!args.routing);
+        let prompts = String::from_utf8(prompts).unwrap();
+        assert!(prompts.contains("Listen address"));
+        assert!(prompts.contains("Enable routing (y/n) [n]"));
+        assert!(generate_config(&args).is_ok());
+
+        // 无法识别的是/否输入返回错误
+        let mut args = GenConfigArgs::parse_from(["gen-config"]);
+        let mut input = Cursor::new("\n\nmaybe\n");
+        assert!(prompt_options(&mut args, &mut input, &mut Vec::new()).is_err());
+
+        info!("Test completed: test_gen_config_interactive_prompts");
+    }
+}
@@ -8,6 +8,8 @@ mod request_tests;
 mod response_tests;
 mod core_tests;
 mod error_tests;
+mod compare_tests;
+mod gen_config_tests;
 mod cli_integration_tests; 
 
 // 注意：在Rust测试中，不需要使用pub use语句导出测试模块
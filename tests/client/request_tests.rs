@@ -2,12 +2,14 @@
 
 #[cfg(test)]
 mod tests {
-    use oxide_wdns::client::args::{CliArgs, DohFormat, HttpMethod};
+    use oxide_wdns::client::args::{CliArgs, DohFormat, EdnsOptionArg, HttpMethod, PayloadFormat};
     
     
     use std::str::FromStr;
     use hickory_proto::op::Message;
     use hickory_proto::rr::RecordType;
+    use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+    use oxide_wdns::common::consts::EDNS_NSID_OPTION_CODE;
     
     use base64::engine::general_purpose::URL_SAFE_NO_PAD;
     use base64::Engine;
@@ -24,11 +26,17 @@ mod tests {
             method,
             http_version: None,
             dnssec: false,
+            nsid: false,
+            edns_options: Vec::new(),
             payload: None,
+            payload_format: PayloadFormat::Hex,
             validate: None,
             insecure: false,
             verbose: 0,
             no_color: false,
+            watch: None,
+            watch_count: None,
+            follow_cname: false,
         }
     }
 
@@ -549,4 +557,66 @@ mod tests {
             assert_eq!(query.query_type(), expected_record_type);
         }
     }
+    
+    #[tokio::test]
+    async fn test_build_doh_request_nsid_option() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_build_doh_request_nsid_option");
+
+        let client = reqwest::Client::new();
+
+        // 启用 --nsid 时附加空的 NSID 选项
+        let mut args = create_test_args(DohFormat::Wire, Some(HttpMethod::Post));
+        args.nsid = true;
+        let request = oxide_wdns::client::request::build_doh_request(&args, &client).await.unwrap();
+        let bytes = request.body().and_then(|body| body.as_bytes()).expect("Body should be available as bytes");
+        let message = parse_dns_query(bytes).unwrap();
+        let edns = message.extensions().as_ref().expect("Message should have EDNS extensions");
+        match edns.option(EdnsCode::from(EDNS_NSID_OPTION_CODE)) {
+            Some(EdnsOption::Unknown(_, data)) => assert!(data.is_empty(), "NSID request must carry empty data"),
+            other => panic!("Expected NSID option, got {:?}", other),
+        }
+        assert!(!edns.dnssec_ok(), "NSID alone should not set the DO bit");
+
+        // 未启用时不附加 EDNS
+        args.nsid = false;
+        let request = oxide_wdns::client::request::build_doh_request(&args, &client).await.unwrap();
+        let bytes = request.body().and_then(|body| body.as_bytes()).unwrap();
+        let message = parse_dns_query(bytes).unwrap();
+        assert!(message.extensions().is_none());
+
+        info!("Test completed: test_build_doh_request_nsid_option");
+    }
+
+    #[tokio::test]
+    async fn test_build_doh_request_custom_edns_options() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_build_doh_request_custom_edns_options");
+
+        let client = reqwest::Client::new();
+
+        // 自定义选项附加到 OPT 记录中
+        let mut args = create_test_args(DohFormat::Wire, Some(HttpMethod::Post));
+        args.edns_options = vec![
+            EdnsOptionArg { code: 15, data: vec![0x00, 0x17] },
+            EdnsOptionArg { code: 65001, data: b"probe".to_vec() },
+        ];
+        let request = oxide_wdns::client::request::build_doh_request(&args, &client).await.unwrap();
+        let bytes = request.body().and_then(|body| body.as_bytes()).expect("Body should be available as bytes");
+        let message = parse_dns_query(bytes).unwrap();
+        let edns = message.extensions().as_ref().expect("Message should have EDNS extensions");
+        match edns.option(EdnsCode::from(15)) {
+            Some(EdnsOption::Unknown(15, data)) => assert_eq!(data, &vec![0x00, 0x17]),
+            other => panic!("Expected EDNS option 15, got {:?}", other),
+        }
+        match edns.option(EdnsCode::from(65001)) {
+            Some(EdnsOption::Unknown(65001, data)) => assert_eq!(data, &b"probe".to_vec()),
+            other => panic!("Expected EDNS option 65001, got {:?}", other),
+        }
+        assert!(!edns.dnssec_ok(), "Custom options alone should not set the DO bit");
+
+        info!("Test completed: test_build_doh_request_custom_edns_options");
+    }
 } 
\ No newline at end of file
@@ -3,13 +3,20 @@
 #[cfg(test)]
 mod tests {
     
-    use oxide_wdns::client::response::parse_doh_response;
-    use oxide_wdns::common::consts::{CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE};
+    use oxide_wdns::client::response::{
+        block_reason, ede_info_code_name, extract_extended_error, extract_server_identity,
+        format_resolution_chain, parse_doh_response, resolution_chain, ServerIdentity,
+    };
+    use oxide_wdns::common::consts::{
+        CONTENT_TYPE_DNS_JSON, CONTENT_TYPE_DNS_MESSAGE,
+        EDNS_EXTENDED_ERROR_OPTION_CODE, EDNS_NSID_OPTION_CODE, HEADER_BLOCK_REASON,
+    };
     use reqwest::StatusCode;
     
-    use hickory_proto::op::{Message, MessageType, OpCode, ResponseCode};
+    use hickory_proto::op::{Edns, Message, MessageType, OpCode, ResponseCode};
     use hickory_proto::rr::{Name, Record, RecordType, RData, DNSClass};
     use hickory_proto::rr::rdata::A;
+    use hickory_proto::rr::rdata::opt::EdnsOption;
     use hickory_proto::serialize::binary::{BinEncodable, BinEncoder};
     use wiremock::{Mock, MockServer, ResponseTemplate};
     use wiremock::matchers::{method, path};
@@ -430,4 +437,150 @@ mod tests {
         
         info!("Test completed: test_parse_doh_response_unknown_content_type");
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_parse_doh_response_block_reason_and_ede() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_parse_doh_response_block_reason_and_ede");
+
+        // 创建携带 EDE "Blocked" 的 NXDOMAIN 响应
+        let mut message = Message::new();
+        message.set_id(1234);
+        message.set_message_type(MessageType::Response);
+        message.set_response_code(ResponseCode::NXDomain);
+        let mut ede_data = 15u16.to_be_bytes().to_vec();
+        ede_data.extend_from_slice(b"Blocked by routing rule");
+        let mut edns = Edns::new();
+        edns.options_mut().insert(EdnsOption::Unknown(EDNS_EXTENDED_ERROR_OPTION_CODE, ede_data));
+        message.set_edns(edns);
+        let body = message.to_vec().unwrap();
+
+        // 模拟服务器同时返回阻止原因响应头
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/dns-query"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("content-type", CONTENT_TYPE_DNS_MESSAGE)
+                .insert_header(HEADER_BLOCK_REASON, "rule_match")
+                .set_body_bytes(body))
+            .mount(&mock_server)
+            .await;
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/dns-query", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+        let doh_response = parse_doh_response(response).await.unwrap();
+
+        // 验证阻止原因和扩展错误均可被识别
+        assert_eq!(block_reason(&doh_response), Some("rule_match"));
+        let ede = extract_extended_error(&doh_response.message).expect("EDE should be present");
+        assert_eq!(ede.info_code, 15);
+        assert_eq!(ede.extra_text, "Blocked by routing rule");
+        assert_eq!(ede_info_code_name(ede.info_code), "Blocked");
+
+        info!("Test completed: test_parse_doh_response_block_reason_and_ede");
+    }
+
+    #[tokio::test]
+    async fn test_parse_doh_response_server_identity() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_parse_doh_response_server_identity");
+
+        // 创建携带 NSID 的响应
+        let mut message = Message::new();
+        message.set_id(1234);
+        message.set_message_type(MessageType::Response);
+        message.set_response_code(ResponseCode::NoError);
+        let mut edns = Edns::new();
+        edns.options_mut().insert(EdnsOption::Unknown(EDNS_NSID_OPTION_CODE, b"ns1-fra".to_vec()));
+        message.set_edns(edns);
+
+        // 模拟负载均衡后端返回的标识响应头
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/dns-query"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("content-type", CONTENT_TYPE_DNS_MESSAGE)
+                .insert_header("server", "owdns")
+                .insert_header("x-backend", "backend-2")
+                .set_body_bytes(message.to_vec().unwrap()))
+            .mount(&mock_server)
+            .await;
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/dns-query", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+        let doh_response = parse_doh_response(response).await.unwrap();
+
+        let identity = extract_server_identity(&doh_response);
+        assert_eq!(identity, ServerIdentity {
+            server: Some("owdns".to_string()),
+            backend: Some("backend-2".to_string()),
+            nsid: Some("6e73312d667261 (\"ns1-fra\")".to_string()),
+        });
+
+        // 不可打印的 NSID 仅显示十六进制
+        let mut binary = Message::new();
+        let mut edns = Edns::new();
+        edns.options_mut().insert(EdnsOption::Unknown(EDNS_NSID_OPTION_CODE, vec![0x00, 0xff]));
+        binary.set_edns(edns);
+        let binary = Message::from_vec(&binary.to_vec().unwrap()).unwrap();
+        assert_eq!(oxide_wdns::client::response::extract_nsid(&binary).as_deref(), Some("00ff"));
+
+        info!("Test completed: test_parse_doh_response_server_identity");
+    }
+
+    #[test]
+    fn test_resolution_chain() {
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_resolution_chain");
+
+        let record = |name: &str, ttl: u32, rdata: RData| {
+            Record::from_rdata(Name::from_ascii(name).unwrap(), ttl, rdata)
+        };
+        let cname = |target: &str| RData::CNAME(hickory_proto::rr::rdata::CNAME(Name::from_ascii(target).unwrap()));
+
+        let mut message = Message::new();
+        message.set_message_type(MessageType::Response);
+        message.add_query(hickory_proto::op::Query::query(Name::from_ascii("www.example.com.").unwrap(), RecordType::A));
+        // 记录顺序与链顺序无关，名称比较不区分大小写
+        message.add_answer(record("LB.example.net.", 60, RData::A(A(std::net::Ipv4Addr::new(1, 2, 3, 4)))));
+        message.add_answer(record("www.example.com.", 300, cname("cdn.example.org.")));
+        message.add_answer(record("cdn.example.org.", 120, cname("lb.example.net.")));
+        message.add_answer(record("lb.example.net.", 60, RData::A(A(std::net::Ipv4Addr::new(1, 2, 3, 5)))));
+
+        assert_eq!(
+            format_resolution_chain(&message),
+            "www.example.com. → CNAME → cdn.example.org. → CNAME → lb.example.net. → A → 1.2.3.4, 1.2.3.5"
+        );
+
+        // JSON 输出为按解析顺序排列的数组
+        let chain = serde_json::to_value(resolution_chain(&message)).unwrap();
+        assert_eq!(chain, serde_json::json!([
+            { "name": "www.example.com.", "type": "CNAME", "ttl": 300, "data": "cdn.example.org." },
+            { "name": "cdn.example.org.", "type": "CNAME", "ttl": 120, "data": "lb.example.net." },
+            { "name": "LB.example.net.", "type": "A", "ttl": 60, "data": "1.2.3.4" },
+            { "name": "lb.example.net.", "type": "A", "ttl": 60, "data": "1.2.3.5" },
+        ]));
+
+        // 链末无记录时附加响应码，CNAME 环路不会无限跟随
+        let mut dangling = Message::new();
+        dangling.set_response_code(ResponseCode::NXDomain);
+        dangling.add_query(hickory_proto::op::Query::query(Name::from_ascii("a.example.com.").unwrap(), RecordType::A));
+        dangling.add_answer(record("a.example.com.", 60, cname("b.example.com.")));
+        dangling.add_answer(record("b.example.com.", 60, cname("a.example.com.")));
+        assert_eq!(
+            format_resolution_chain(&dangling),
+            "a.example.com. → CNAME → b.example.com. → CNAME → a.example.com. → Non-Existent Domain"
+        );
+        assert_eq!(resolution_chain(&dangling).len(), 2);
+
+        info!("Test completed: test_resolution_chain");
+    }
+}
@@ -124,4 +124,37 @@ dns_resolver:
             .success()
             .stdout(predicatesStr::contains("Debug logging enabled"));
     }
+    
+    #[test]
+    fn test_self_test_flag() {
+        // 上游不可达：自检输出检查清单并以非零状态退出
+        let tmp_file = NamedTempFile::new().expect("Failed to create temp file");
+        let config_content = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+  timeout: 10
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "127.0.0.1:1"
+        protocol: udp
+    query_timeout: 1
+    enable_dnssec: false
+  cache:
+    enabled: true
+        "#;
+        fs::write(&tmp_file, config_content).expect("Failed to write temp config file");
+        
+        let mut cmd = Command::cargo_bin("owdns").expect("Failed to find binary");
+        
+        cmd.arg("--config")
+            .arg(tmp_file.path())
+            .arg("--self-test")
+            .timeout(std::time::Duration::from_secs(30))
+            .assert()
+            .failure()
+            .stdout(predicatesStr::contains("[PASS] configuration"))
+            .stdout(predicatesStr::contains("[FAIL] upstream"))
+            .stdout(predicatesStr::contains("Self-test failed"));
+    }
 }
@@ -2,15 +2,16 @@
 
 #[cfg(test)]
 mod tests {
-    use oxide_wdns::server::cache::{DnsCache, CacheKey};
-    use oxide_wdns::server::config::{CacheConfig, TtlConfig, PersistenceCacheConfig};
+    use oxide_wdns::server::cache::{DnsCache, CacheDumpFormat, CacheKey, CachedMessage};
+    use oxide_wdns::server::config::{AdaptiveTtlConfig, CacheConfig, CorruptCachePolicy, TtlConfig, PersistenceCacheConfig, PeriodicSaveConfig};
     use std::time::Duration;
     use tokio::time::sleep;
     use hickory_proto::op::{Message, ResponseCode};
     use hickory_proto::rr::{Record, Name, RecordType, RData, DNSClass};
     use hickory_proto::op::Query;
-    use hickory_proto::rr::rdata::A;
+    use hickory_proto::rr::rdata::{A, SOA, TXT};
     use tracing::info;
+    use oxide_wdns::common::consts::{CACHE_FILE_MAGIC, CACHE_FILE_PREVIOUS_VERSION, CACHE_FILE_VERSION};
     
     use std::fs;
     use std::path::Path;
@@ -25,12 +26,28 @@ mod tests {
         let config = CacheConfig {
             enabled: true,
             size,
+            shards: 1,
             ttl: TtlConfig {
                 min: min_ttl,
                 max: max_ttl,
                 negative: negative_ttl,
+                response_max: 604800,
             },
             persistence: PersistenceCacheConfig::default(),
+            store_compressed: false,
+            compression_min_bytes: 512,
+            stale_on_error: false,
+            stale_on_error_ttl: 30,
+            stale_on_error_max_age: 86400,
+            refresh_failure_grace_secs: 0,
+            override_zero_ttl_secs: 0,
+            companion_prefetch: false,
+            adaptive_ttl: AdaptiveTtlConfig::default(),
+            revalidate_types: Vec::new(),
+            time_to_idle_secs: 300,
+            max_memory_bytes: 0,
+            isolate_upstream_groups: false,
+            json_answer_cache: false,
         };
         DnsCache::new(config)
     }
@@ -43,6 +60,9 @@ mod tests {
             record_class: 1, // IN 类
             ecs_network: None,
             ecs_scope_prefix_length: None,
+            namespace: None,
+            dnssec_ok: false,
+            checking_disabled: false,
         }
     }
     
@@ -441,12 +461,28 @@ mod tests {
         let config = CacheConfig {
             enabled: false,
             size: 100,
+            shards: 1,
             ttl: TtlConfig {
                 min: 60,
                 max: 3600,
                 negative: 60,
+                response_max: 604800,
             },
             persistence: PersistenceCacheConfig::default(),
+            store_compressed: false,
+            compression_min_bytes: 512,
+            stale_on_error: false,
+            stale_on_error_ttl: 30,
+            stale_on_error_max_age: 86400,
+            refresh_failure_grace_secs: 0,
+            override_zero_ttl_secs: 0,
+            companion_prefetch: false,
+            adaptive_ttl: AdaptiveTtlConfig::default(),
+            revalidate_types: Vec::new(),
+            time_to_idle_secs: 300,
+            max_memory_bytes: 0,
+            isolate_upstream_groups: false,
+            json_answer_cache: false,
         };
         info!("Creating DnsCache instance with disabled config...");
         let cache = DnsCache::new(config);
@@ -994,10 +1030,12 @@ mod tests {
         let config = CacheConfig {
             enabled: true,
             size: 100,
+            shards: 1,
             ttl: TtlConfig {
                 min: 60,
                 max: 3600,
                 negative: 60,
+                response_max: 604800,
             },
             persistence: PersistenceCacheConfig {
                 enabled: true,
@@ -1006,8 +1044,28 @@ mod tests {
                 skip_expired_on_load: true,
                 max_items_to_save: 1000,
                 shutdown_save_timeout_secs: 5,
+                load_concurrency: 4,
+                save_concurrency: 4,
+                load_rate: 0,
+                on_corrupt: Default::default(),
+                fail_on_unwritable: false,
+                baseline_path: String::new(),
                 periodic: Default::default(),
             },
+            store_compressed: false,
+            compression_min_bytes: 512,
+            stale_on_error: false,
+            stale_on_error_ttl: 30,
+            stale_on_error_max_age: 86400,
+            refresh_failure_grace_secs: 0,
+            override_zero_ttl_secs: 0,
+            companion_prefetch: false,
+            adaptive_ttl: AdaptiveTtlConfig::default(),
+            revalidate_types: Vec::new(),
+            time_to_idle_secs: 300,
+            max_memory_bytes: 0,
+            isolate_upstream_groups: false,
+            json_answer_cache: false,
         };
         let cache = DnsCache::new(config);
         
@@ -1048,4 +1106,862 @@ mod tests {
         info!("Test finished: test_file_format_compatibility");
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_persistent_cache_concurrent_warm_load() {
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_persistent_cache_concurrent_warm_load");
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_file_path = temp_dir.path().join("warm_cache.dat");
+        
+        // 创建支持持久化的缓存配置，使用多批次并发加载
+        let mut config = CacheConfig {
+            enabled: true,
+            size: 5000,
+            ..CacheConfig::default()
+        };
+        config.persistence.enabled = true;
+        config.persistence.path = cache_file_path.to_str().unwrap().to_string();
+        config.persistence.load_on_startup = true;
+        config.persistence.load_concurrency = 2;
+        config.persistence.load_rate = 0;
+        // 保存时分块并行序列化（条目数不能被线程数整除）
+        config.persistence.save_concurrency = 3;
+        
+        // 写入跨越多个批次的条目
+        let entry_count = 2500;
+        let cache = DnsCache::new(config.clone());
+        for i in 0..entry_count {
+            let name = format!("warm-{}.example.com.", i);
+            let message = create_test_message(&name, RecordType::A, 3600, Some("10.0.0.1"));
+            let key = CacheKey::new(Name::from_str(&name).unwrap(), RecordType::A, DNSClass::IN);
+            cache.put(&key, &message, 3600).await.unwrap();
+        }
+        let saved_count = cache.save_to_file().await.expect("Failed to save cache");
+        assert_eq!(saved_count, entry_count);
+        
+        // 从磁盘加载，等待后台预热完成
+        let new_cache = DnsCache::new(config);
+        let mut loaded = 0;
+        for _ in 0..50 {
+            sleep(Duration::from_millis(100)).await;
+            loaded = new_cache.len().await;
+            if loaded as usize == entry_count {
+                break;
+            }
+        }
+        info!(loaded, "Cache warm load finished");
+        assert_eq!(loaded as usize, entry_count, "All persisted entries should be loaded");
+        
+        // 验证任意批次中的条目均可读取
+        let key = CacheKey::new(Name::from_str("warm-2499.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+        assert!(new_cache.get(&key).await.is_some());
+        
+        temp_dir.close().unwrap();
+        info!("Test finished: test_persistent_cache_concurrent_warm_load");
+    }
+
+    #[tokio::test]
+    async fn test_cache_store_compressed() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_store_compressed");
+
+        let config = CacheConfig {
+            enabled: true,
+            size: 100,
+            shards: 1,
+            store_compressed: true,
+            compression_min_bytes: 512,
+            ..CacheConfig::default()
+        };
+
+        // 构造包含大量重复文本的 TXT 响应
+        let name = Name::from_ascii("txt.example.com.").unwrap();
+        let mut message = create_test_message("txt.example.com.", RecordType::A, 300, None);
+        message.set_response_code(ResponseCode::NoError);
+        for i in 0..20 {
+            let text = format!("v=spf1 include:_spf{}.example.com ~all {}", i, "x".repeat(64));
+            message.add_answer(Record::from_rdata(name.clone(), 300, RData::TXT(TXT::new(vec![text]))));
+        }
+        let wire_len = message.to_vec().unwrap().len();
+
+        // 大条目以压缩形式存储且占用更少字节
+        let stored = CachedMessage::encode(&message, &config);
+        assert!(matches!(stored, CachedMessage::Compressed(_)), "Large entry should be stored compressed");
+        assert!(stored.stored_bytes().unwrap() < wire_len, "Compressed entry should be smaller than wire format");
+
+        // 小条目仅保存线格式
+        let small = create_test_message("small.example.com.", RecordType::A, 300, Some("192.0.2.1"));
+        assert!(matches!(CachedMessage::encode(&small, &config), CachedMessage::Wire(_)));
+
+        // 命中时还原的消息与原消息一致
+        let cache = DnsCache::new(config);
+        let key = create_cache_key("txt.example.com.", RecordType::TXT.into());
+        cache.put(&key, &message, 300).await.unwrap();
+        let cached = cache.get(&key).await.expect("Compressed entry should be retrievable");
+        assert_eq!(cached.answers(), message.answers());
+
+        info!("Test completed: test_cache_store_compressed");
+    }
+    
+    #[tokio::test]
+    async fn test_cache_time_to_idle_configurable() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_time_to_idle_configurable");
+
+        let key = create_cache_key("idle.example.com.", RecordType::A.into());
+        let message = create_test_message("idle.example.com.", RecordType::A, 3600, Some("192.0.2.10"));
+
+        // 较短的空闲时间：长 TTL 条目在空闲后被移除
+        let short_idle = DnsCache::new(CacheConfig {
+            enabled: true,
+            size: 100,
+            time_to_idle_secs: 1,
+            ..CacheConfig::default()
+        });
+        short_idle.put(&key, &message, 3600).await.unwrap();
+
+        // 禁用空闲过期：条目仅受 TTL 和容量约束
+        let no_idle = DnsCache::new(CacheConfig {
+            enabled: true,
+            size: 100,
+            time_to_idle_secs: 0,
+            ..CacheConfig::default()
+        });
+        no_idle.put(&key, &message, 3600).await.unwrap();
+
+        sleep(Duration::from_millis(1500)).await;
+
+        assert!(short_idle.get(&key).await.is_none(), "Idle entry should be evicted after time_to_idle_secs");
+        assert!(no_idle.get(&key).await.is_some(), "Entry should survive idle periods when time_to_idle is disabled");
+
+        info!("Test completed: test_cache_time_to_idle_configurable");
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_persistent_cache_corrupt_file() {
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_persistent_cache_corrupt_file");
+        let cache_path = format!("./test_cache_corrupt_{}.dat", std::process::id());
+        let quarantine_path = format!("{}.corrupt", cache_path);
+        
+        let config_with_policy = |on_corrupt: CorruptCachePolicy| CacheConfig {
+            enabled: true,
+            size: 100,
+            persistence: PersistenceCacheConfig {
+                enabled: true,
+                path: cache_path.clone(),
+                load_on_startup: true,
+                on_corrupt,
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        };
+        
+        // 先保存一个有效的缓存文件，再截断为一半模拟写入中断
+        let cache = DnsCache::new(config_with_policy(CorruptCachePolicy::Ignore));
+        let key = create_cache_key("corrupt.example.com.", RecordType::A.into());
+        let message = create_test_message("corrupt.example.com.", RecordType::A, 300, Some("192.0.2.20"));
+        cache.put(&key, &message, 300).await.unwrap();
+        cache.save_to_file().await.unwrap();
+        let bytes = fs::read(&cache_path).unwrap();
+        fs::write(&cache_path, &bytes[..bytes.len() / 2]).unwrap();
+        
+        // fail：启动报错，但 new() 仍以空缓存继续
+        assert!(DnsCache::try_new(config_with_policy(CorruptCachePolicy::Fail)).is_err());
+        let fallback = DnsCache::new(config_with_policy(CorruptCachePolicy::Fail));
+        assert!(fallback.get(&key).await.is_none());
+        
+        // ignore：以空缓存启动，文件保留原位
+        let ignored = DnsCache::try_new(config_with_policy(CorruptCachePolicy::Ignore))
+            .expect("Corrupt cache file should be ignored");
+        assert!(ignored.get(&key).await.is_none());
+        assert!(Path::new(&cache_path).exists());
+        
+        // quarantine：以空缓存启动，损坏文件被移到 .corrupt
+        let quarantined = DnsCache::try_new(config_with_policy(CorruptCachePolicy::Quarantine))
+            .expect("Corrupt cache file should be quarantined");
+        assert!(quarantined.get(&key).await.is_none());
+        assert!(!Path::new(&cache_path).exists(), "Corrupt file should be moved aside");
+        assert_eq!(fs::read(&quarantine_path).unwrap().len(), bytes.len() / 2);
+        
+        // 测试结束后清理
+        for path in [&cache_path, &quarantine_path] {
+            if Path::new(path).exists() {
+                fs::remove_file(path).expect("Failed to delete cache file");
+            }
+        }
+        info!("Test finished: test_persistent_cache_corrupt_file");
+    }
+    
+    #[tokio::test]
+    async fn test_cache_max_memory_bytes_eviction() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_max_memory_bytes_eviction");
+
+        // 构造约 2KB 的大 TXT 响应
+        let large_message = |domain: &str| {
+            let name = Name::from_ascii(domain).unwrap();
+            let mut message = create_test_message(domain, RecordType::TXT, 300, None);
+            message.set_response_code(ResponseCode::NoError);
+            for i in 0..20 {
+                let text = format!("{}-{}", i, "x".repeat(96));
+                message.add_answer(Record::from_rdata(name.clone(), 300, RData::TXT(TXT::new(vec![text]))));
+            }
+            message
+        };
+
+        let max_memory_bytes = 8 * 1024;
+        let bounded = DnsCache::new(CacheConfig {
+            enabled: true,
+            size: 1000,
+            max_memory_bytes,
+            ..CacheConfig::default()
+        });
+        let unbounded = DnsCache::new(CacheConfig {
+            enabled: true,
+            size: 1000,
+            ..CacheConfig::default()
+        });
+
+        let total = 20;
+        for i in 0..total {
+            let domain = format!("big{}.example.com.", i);
+            let key = create_cache_key(&domain, RecordType::TXT.into());
+            let message = large_message(&domain);
+            bounded.put(&key, &message, 300).await.unwrap();
+            unbounded.put(&key, &message, 300).await.unwrap();
+        }
+
+        // 条目数远低于容量，但估算内存超出上限，应按内存淘汰
+        let bounded_bytes = bounded.memory_bytes().await;
+        assert!(bounded_bytes <= max_memory_bytes, "Estimated memory {} should stay within {}", bounded_bytes, max_memory_bytes);
+        assert!(bounded.len().await < total, "Large entries should be evicted once the memory bound is reached");
+
+        // 未配置内存上限时仅按条目数限制，但仍统计估算内存
+        assert_eq!(unbounded.len().await, total);
+        assert!(unbounded.memory_bytes().await > max_memory_bytes, "Memory estimate should account for all entries");
+
+        info!("Test completed: test_cache_max_memory_bytes_eviction");
+    }
+    
+    #[tokio::test]
+    async fn test_cache_key_idn_normalization() {
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_key_idn_normalization");
+        
+        let cache = create_test_cache(100, 60, 3600, 300);
+        
+        // 以 Unicode 形式的查询写入缓存
+        let unicode_name = Name::from_utf8("例え.テスト.").unwrap();
+        let mut message = Message::new();
+        message.add_query(Query::query(unicode_name.clone(), RecordType::A));
+        message.add_answer(Record::from_rdata(unicode_name, 300, RData::A(A::from_str("192.0.2.10").unwrap())));
+        let unicode_key = CacheKey::from(&message);
+        cache.put(&unicode_key, &message, 300).await.unwrap();
+        
+        // 以 punycode 形式的查询命中同一条目
+        let punycode_key = CacheKey::new(Name::from_ascii("xn--r8jz45g.xn--zckzah.").unwrap(), RecordType::A, DNSClass::IN);
+        assert_eq!(unicode_key, punycode_key);
+        assert_eq!(punycode_key.name.as_str(), "xn--r8jz45g.xn--zckzah.");
+        assert!(cache.get(&punycode_key).await.is_some(), "Punycode query should hit the entry cached for the Unicode query");
+        
+        info!("Test completed: test_cache_key_idn_normalization");
+    }
+    
+    #[tokio::test]
+    async fn test_negative_ttl_from_soa_minimum() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_negative_ttl_from_soa_minimum");
+        
+        let cache = create_test_cache(100, 60, 3600, 300);
+        let mut message = create_test_message("missing.example.org", RecordType::A, 300, None);
+        
+        // 无 SOA 时回落到配置的负缓存 TTL
+        assert_eq!(cache.negative_ttl_for(&message), 300);
+        
+        // SOA MINIMUM 小于配置值时取 SOA MINIMUM
+        let zone = Name::from_ascii("example.org.").unwrap();
+        let soa = SOA::new(
+            Name::from_ascii("ns1.example.org.").unwrap(),
+            Name::from_ascii("hostmaster.example.org.").unwrap(),
+            2024010101, 7200, 3600, 1209600, 60,
+        );
+        message.add_name_server(Record::from_rdata(zone.clone(), 900, RData::SOA(soa.clone())));
+        assert_eq!(cache.negative_ttl_for(&message), 60);
+        
+        // SOA 记录 TTL 小于 MINIMUM 时取记录 TTL
+        message.take_name_servers();
+        message.add_name_server(Record::from_rdata(zone.clone(), 30, RData::SOA(soa)));
+        assert_eq!(cache.negative_ttl_for(&message), 30);
+        
+        // SOA 值大于配置值时不超过配置的负缓存 TTL
+        let long_soa = SOA::new(
+            Name::from_ascii("ns1.example.org.").unwrap(),
+            Name::from_ascii("hostmaster.example.org.").unwrap(),
+            2024010101, 7200, 3600, 1209600, 86400,
+        );
+        message.take_name_servers();
+        message.add_name_server(Record::from_rdata(zone, 86400, RData::SOA(long_soa)));
+        assert_eq!(cache.negative_ttl_for(&message), 300);
+        
+        info!("Test completed: test_negative_ttl_from_soa_minimum");
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_persistent_cache_unwritable_path() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_persistent_cache_unwritable_path");
+        
+        // 父路径是普通文件，无法创建目录，确保即使以 root 运行也不可写
+        let blocker = tempfile::NamedTempFile::new().unwrap();
+        let cache_path = blocker.path().join("cache.dat").to_string_lossy().to_string();
+        let config_with_flag = |fail_on_unwritable: bool| CacheConfig {
+            enabled: true,
+            size: 100,
+            persistence: PersistenceCacheConfig {
+                enabled: true,
+                path: cache_path.clone(),
+                fail_on_unwritable,
+                periodic: PeriodicSaveConfig { enabled: true, interval_secs: 1 },
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        };
+        
+        // 默认：记录错误并禁用持久化，保存操作不再报错
+        let cache = DnsCache::try_new(config_with_flag(false))
+            .expect("Unwritable path should not fail startup by default");
+        let key = create_cache_key("unwritable.example.com.", RecordType::A.into());
+        let message = create_test_message("unwritable.example.com.", RecordType::A, 300, Some("192.0.2.30"));
+        cache.put(&key, &message, 300).await.unwrap();
+        assert_eq!(cache.save_to_file().await.unwrap(), 0, "Persistence should be disabled");
+        assert!(cache.get(&key).await.is_some(), "Cache should keep working in memory");
+        
+        // fail_on_unwritable：启动失败，new() 仍降级继续
+        assert!(DnsCache::try_new(config_with_flag(true)).is_err());
+        let fallback = DnsCache::new(config_with_flag(true));
+        assert_eq!(fallback.save_to_file().await.unwrap(), 0);
+        
+        // 可写路径不受影响，探测文件不残留
+        let dir = tempfile::tempdir().unwrap();
+        let writable_path = dir.path().join("nested").join("cache.dat").to_string_lossy().to_string();
+        let writable = DnsCache::try_new(CacheConfig {
+            enabled: true,
+            persistence: PersistenceCacheConfig {
+                enabled: true,
+                path: writable_path.clone(),
+                fail_on_unwritable: true,
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        }).expect("Writable path should pass the startup check");
+        writable.put(&key, &message, 300).await.unwrap();
+        assert_eq!(writable.save_to_file().await.unwrap(), 1);
+        assert!(!Path::new(&format!("{}.probe", writable_path)).exists());
+        
+        info!("Test completed: test_persistent_cache_unwritable_path");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cache_shards_aggregate() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_shards_aggregate");
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_file_path = temp_dir.path().join("sharded_cache.dat");
+        
+        let mut config = CacheConfig {
+            enabled: true,
+            size: 1000,
+            shards: 8,
+            ..CacheConfig::default()
+        };
+        config.persistence.enabled = true;
+        config.persistence.path = cache_file_path.to_str().unwrap().to_string();
+        config.persistence.load_on_startup = true;
+        config.persistence.load_rate = 0;
+        
+        // 写入的条目分布到各分片，均可读取
+        let entry_count = 400;
+        let cache = DnsCache::new(config.clone());
+        for i in 0..entry_count {
+            let name = format!("shard-{}.example.com.", i);
+            let message = create_test_message(&name, RecordType::A, 3600, Some("10.0.0.1"));
+            let key = CacheKey::new(Name::from_str(&name).unwrap(), RecordType::A, DNSClass::IN);
+            cache.put(&key, &message, 3600).await.unwrap();
+        }
+        for i in 0..entry_count {
+            let key = CacheKey::new(Name::from_str(&format!("shard-{}.example.com.", i)).unwrap(), RecordType::A, DNSClass::IN);
+            assert!(cache.get(&key).await.is_some(), "Entry {} should be cached", i);
+        }
+        
+        // 条目数、内存估算与导出汇总所有分片
+        assert_eq!(cache.len().await, entry_count as u64);
+        assert!(cache.memory_bytes().await > 0);
+        let mut dump = cache.dump(CacheDumpFormat::Json);
+        let mut dumped = 0;
+        while dump.recv().await.is_some() {
+            dumped += 1;
+        }
+        assert_eq!(dumped, entry_count);
+        
+        // 持久化保存所有分片，可由不同分片数的实例加载
+        assert_eq!(cache.save_to_file().await.unwrap(), entry_count);
+        config.shards = 3;
+        let reloaded = DnsCache::new(config);
+        let mut loaded = 0;
+        for _ in 0..50 {
+            sleep(Duration::from_millis(100)).await;
+            loaded = reloaded.len().await;
+            if loaded as usize == entry_count {
+                break;
+            }
+        }
+        assert_eq!(loaded as usize, entry_count);
+        let key = CacheKey::new(Name::from_str("shard-399.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+        assert!(reloaded.get(&key).await.is_some());
+        
+        // 清空作用于所有分片
+        cache.clear().await;
+        assert_eq!(cache.len().await, 0);
+        
+        temp_dir.close().unwrap();
+        info!("Test completed: test_cache_shards_aggregate");
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_cache_shards_benchmark() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("info").try_init();
+        info!("Starting test: test_cache_shards_benchmark");
+        
+        // 高并发读写混合负载：16 个任务，每个任务 2000 次操作（90% 读，10% 写）
+        let tasks = 16;
+        let operations = 2000;
+        let key_space = 2000;
+        for shards in [1, 16] {
+            let cache = Arc::new(DnsCache::new(CacheConfig {
+                enabled: true,
+                size: key_space * 2,
+                shards,
+                ..CacheConfig::default()
+            }));
+            let message = create_test_message("bench.example.com.", RecordType::A, 3600, Some("10.0.0.1"));
+            let keys: Arc<Vec<CacheKey>> = Arc::new((0..key_space).map(|i| {
+                CacheKey::new(Name::from_str(&format!("bench-{}.example.com.", i)).unwrap(), RecordType::A, DNSClass::IN)
+            }).collect());
+            for key in keys.iter() {
+                cache.put(key, &message, 3600).await.unwrap();
+            }
+            
+            let start = std::time::Instant::now();
+            let mut handles = Vec::new();
+            for task in 0..tasks {
+                let cache = cache.clone();
+                let keys = keys.clone();
+                let message = message.clone();
+                handles.push(tokio::spawn(async move {
+                    let mut hits = 0;
+                    for i in 0..operations {
+                        let key = &keys[(task * 7919 + i * 31) % key_space];
+                        if i % 10 == 0 {
+                            cache.put(key, &message, 3600).await.unwrap();
+                        } else if cache.get(key).await.is_some() {
+                            hits += 1;
+                        }
+                    }
+                    hits
+                }));
+            }
+            let mut hits = 0;
+            for handle in handles {
+                hits += handle.await.unwrap();
+            }
+            let elapsed = start.elapsed();
+            
+            let total_operations = tasks * operations;
+            info!(
+                shards,
+                total_operations,
+                elapsed_ms = elapsed.as_millis() as u64,
+                ops_per_sec = (total_operations as f64 / elapsed.as_secs_f64()) as u64,
+                "Cache shard benchmark finished"
+            );
+            // 读操作全部命中（键空间小于容量）
+            assert_eq!(hits, tasks * operations * 9 / 10);
+            assert_eq!(cache.len().await, key_space as u64);
+        }
+        
+        info!("Test completed: test_cache_shards_benchmark");
+    }
+    
+    #[tokio::test]
+    async fn test_cache_hit_age_histogram() {
+        use oxide_wdns::server::metrics::METRICS;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_hit_age_histogram");
+        
+        let cache = create_test_cache(100, 1, 3600, 60);
+        let message = create_test_message("age.example.com", RecordType::A, 300, Some("192.0.2.1"));
+        let key = CacheKey::new(Name::from_str("age.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+        cache.put(&key, &message, 300).await.unwrap();
+        
+        let histogram = METRICS.cache_hit_age_seconds().with_label_values(&[]);
+        let count_before = histogram.get_sample_count();
+        let sum_before = histogram.get_sample_sum();
+        
+        // 存储后约 2 秒命中，记录的时长不小于 1 秒
+        sleep(Duration::from_secs(3)).await;
+        assert!(cache.get(&key).await.is_some());
+        assert!(histogram.get_sample_count() > count_before, "Cache hit should be observed");
+        assert!(histogram.get_sample_sum() - sum_before >= 1.0, "Observed age should reflect the time since stored");
+        
+        info!("Test completed: test_cache_hit_age_histogram");
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_baseline_cache_on_cold_cache() {
+        use oxide_wdns::server::metrics::METRICS;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_baseline_cache_on_cold_cache");
+        
+        // 由持久化缓存生成基线文件：一条长 TTL 条目与一条即将过期的条目
+        let dir = tempfile::tempdir().unwrap();
+        let baseline_path = dir.path().join("baseline.dat").to_string_lossy().to_string();
+        let source = DnsCache::new(CacheConfig {
+            enabled: true,
+            persistence: PersistenceCacheConfig {
+                enabled: true,
+                path: baseline_path.clone(),
+                load_on_startup: false,
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        });
+        let stable_key = create_cache_key("baseline.example.com.", RecordType::A.into());
+        let stable = create_test_message("baseline.example.com.", RecordType::A, 300, Some("192.0.2.40"));
+        source.put(&stable_key, &stable, 300).await.unwrap();
+        let short_key = create_cache_key("short.example.com.", RecordType::A.into());
+        let short = create_test_message("short.example.com.", RecordType::A, 2, Some("192.0.2.41"));
+        source.put(&short_key, &short, 2).await.unwrap();
+        assert_eq!(source.save_to_file().await.unwrap(), 2);
+        
+        // 未启用持久化的冷缓存仍可从基线应答
+        let cache = DnsCache::new(CacheConfig {
+            enabled: true,
+            persistence: PersistenceCacheConfig {
+                baseline_path: baseline_path.clone(),
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        });
+        assert!(cache.is_empty().await);
+        
+        // 基线与主缓存分开查找：主缓存未命中，基线命中
+        let hits_before = METRICS.cache_baseline_hits_total().get();
+        assert!(cache.get(&stable_key).await.is_none());
+        let message = cache.get_baseline(&stable_key).expect("Baseline entry should be served on a cold cache");
+        assert_eq!(message.answers()[0].data(), Some(&RData::A(A::new(192, 0, 2, 40))));
+        assert!(cache.get_baseline(&short_key).is_some());
+        assert!(METRICS.cache_baseline_hits_total().get() >= hits_before + 2);
+        
+        // 基线文件不含命名空间，带命名空间的键（路由配置档案、隔离上游组）不使用基线
+        assert!(cache.get_baseline(&stable_key.clone().with_namespace("profile")).is_none());
+        
+        // 基线只读：命中不写入主缓存，文件保持不变
+        assert!(cache.is_empty().await);
+        let modified = fs::metadata(&baseline_path).unwrap().modified().unwrap();
+        assert_eq!(cache.save_to_file().await.unwrap(), 0);
+        assert_eq!(fs::metadata(&baseline_path).unwrap().modified().unwrap(), modified);
+        
+        // 基线条目遵循 TTL，过期后不再应答
+        sleep(Duration::from_millis(3100)).await;
+        assert!(cache.get_baseline(&short_key).is_none());
+        assert!(cache.get_baseline(&stable_key).is_some());
+        assert!(cache.get_baseline(&create_cache_key("other.example.com.", RecordType::A.into())).is_none());
+        
+        info!("Test completed: test_baseline_cache_on_cold_cache");
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_baseline_cache_generated_in_the_past() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_baseline_cache_generated_in_the_past");
+        
+        // 按当前文件格式构造两小时前生成的基线文件，条目的绝对过期时间均已过去
+        #[derive(serde::Serialize)]
+        struct Header { magic: String, version: u64, timestamp: u64, entry_count: usize }
+        #[derive(serde::Serialize)]
+        struct Key {
+            name: String,
+            record_type: u16,
+            record_class: u16,
+            ecs_network: Option<String>,
+            ecs_scope_prefix_length: Option<u8>,
+            dnssec_ok: bool,
+            checking_disabled: bool,
+        }
+        #[derive(serde::Serialize)]
+        struct Entry { message_bytes: Vec<u8>, expires_at: u64, stored_at: u64, access_count: u64, last_accessed: u64 }
+        
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let generated_at = now - 7200;
+        let records = [("old.example.com.", 300, "192.0.2.50"), ("brief.example.com.", 2, "192.0.2.51")];
+        let header = Header {
+            magic: CACHE_FILE_MAGIC.to_string(),
+            version: CACHE_FILE_VERSION,
+            timestamp: generated_at,
+            entry_count: records.len(),
+        };
+        let keys: Vec<Key> = records.iter().map(|(name, _, _)| Key {
+            name: name.to_string(),
+            record_type: RecordType::A.into(),
+            record_class: DNSClass::IN.into(),
+            ecs_network: None,
+            ecs_scope_prefix_length: None,
+            dnssec_ok: false,
+            checking_disabled: false,
+        }).collect();
+        let entries: Vec<Entry> = records.iter().map(|(name, ttl, ip)| Entry {
+            message_bytes: create_test_message(name, RecordType::A, *ttl, Some(ip)).to_vec().unwrap(),
+            expires_at: generated_at + *ttl as u64,
+            stored_at: generated_at,
+            access_count: 1,
+            last_accessed: generated_at,
+        }).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let baseline_path = dir.path().join("baseline.dat").to_string_lossy().to_string();
+        let mut bytes = bincode::serialize(&header).unwrap();
+        bytes.extend(bincode::serialize(&(keys, entries)).unwrap());
+        fs::write(&baseline_path, bytes).unwrap();
+        
+        let cache = DnsCache::new(CacheConfig {
+            enabled: true,
+            persistence: PersistenceCacheConfig {
+                baseline_path: baseline_path.clone(),
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        });
+        
+        // 过期时间按原始 TTL 从加载时起重新计算，旧文件中的条目仍可应答
+        let old_key = create_cache_key("old.example.com.", RecordType::A.into());
+        let brief_key = create_cache_key("brief.example.com.", RecordType::A.into());
+        let message = cache.get_baseline(&old_key).expect("Baseline entry from an old file should be served");
+        assert_eq!(message.answers()[0].data(), Some(&RData::A(A::new(192, 0, 2, 50))));
+        assert!(cache.get_baseline(&brief_key).is_some());
+        
+        // 原始 TTL 过后不再应答
+        sleep(Duration::from_millis(3100)).await;
+        assert!(cache.get_baseline(&brief_key).is_none());
+        assert!(cache.get_baseline(&old_key).is_some());
+        
+        info!("Test completed: test_baseline_cache_generated_in_the_past");
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_persistent_cache_migrates_previous_version() {
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_persistent_cache_migrates_previous_version");
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_path = temp_dir.path().join("cache_v1.dat").to_string_lossy().to_string();
+        
+        // 按 v1 文件格式构造缓存文件（缓存键不含 DO/CD 标志）
+        #[derive(serde::Serialize)]
+        struct HeaderV1 { magic: String, version: u64, timestamp: u64, entry_count: usize }
+        #[derive(serde::Serialize)]
+        struct KeyV1 {
+            name: String,
+            record_type: u16,
+            record_class: u16,
+            ecs_network: Option<String>,
+            ecs_scope_prefix_length: Option<u8>,
+        }
+        #[derive(serde::Serialize)]
+        struct EntryV1 { message_bytes: Vec<u8>, expires_at: u64, stored_at: u64, access_count: u64, last_accessed: u64 }
+        
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let message = create_test_message("migrate.example.com.", RecordType::A, 300, Some("192.0.2.30"));
+        let header = HeaderV1 {
+            magic: CACHE_FILE_MAGIC.to_string(),
+            version: CACHE_FILE_PREVIOUS_VERSION,
+            timestamp: now,
+            entry_count: 1,
+        };
+        let keys = vec![KeyV1 {
+            name: "migrate.example.com.".to_string(),
+            record_type: RecordType::A.into(),
+            record_class: DNSClass::IN.into(),
+            ecs_network: None,
+            ecs_scope_prefix_length: None,
+        }];
+        let entries = vec![EntryV1 {
+            message_bytes: message.to_vec().unwrap(),
+            expires_at: now + 300,
+            stored_at: now,
+            access_count: 1,
+            last_accessed: now,
+        }];
+        let mut bytes = bincode::serialize(&header).unwrap();
+        bytes.extend(bincode::serialize(&(keys, entries)).unwrap());
+        fs::write(&cache_path, bytes).unwrap();
+        
+        let config = CacheConfig {
+            enabled: true,
+            size: 100,
+            persistence: PersistenceCacheConfig {
+                enabled: true,
+                path: cache_path.clone(),
+                load_on_startup: true,
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        };
+        let cache = DnsCache::try_new(config).expect("Previous cache file version should be migrated");
+        
+        // 等待缓存加载完成
+        sleep(Duration::from_millis(100)).await;
+        
+        // 迁移后的条目以未设置 DO/CD 的键提供服务
+        let key = create_cache_key("migrate.example.com.", RecordType::A.into());
+        let cached = cache.get(&key).await.expect("Migrated entry should be served from cache");
+        assert_eq!(cached.answers().len(), 1);
+        assert_eq!(cached.answers()[0].data(), Some(&RData::A(A::from_str("192.0.2.30").unwrap())));
+        
+        // 未知版本仍视为无效文件
+        let mut unknown = bincode::serialize(&HeaderV1 {
+            magic: CACHE_FILE_MAGIC.to_string(),
+            version: CACHE_FILE_VERSION + 1,
+            timestamp: now,
+            entry_count: 0,
+        }).unwrap();
+        unknown.extend(bincode::serialize(&(Vec::<KeyV1>::new(), Vec::<EntryV1>::new())).unwrap());
+        fs::write(&cache_path, unknown).unwrap();
+        let config = CacheConfig {
+            enabled: true,
+            size: 100,
+            persistence: PersistenceCacheConfig {
+                enabled: true,
+                path: cache_path.clone(),
+                load_on_startup: true,
+                on_corrupt: CorruptCachePolicy::Fail,
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        };
+        assert!(DnsCache::try_new(config).is_err());
+        
+        info!("Test completed: test_persistent_cache_migrates_previous_version");
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cache_override_zero_ttl() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_override_zero_ttl");
+
+        // 上游返回 TTL=0 的应答（经线格式往返，与真实上游应答一致）
+        let message = create_test_message("zero-ttl.example.com", RecordType::A, 0, Some("192.0.2.10"));
+        let message = Message::from_vec(&message.to_vec().unwrap()).unwrap();
+        let key = create_cache_key("zero-ttl.example.com", 1);
+
+        // 未启用时 TTL=0 与其他应答一样受 ttl.min 约束
+        let cache = create_test_cache(100, 60, 3600, 60);
+        assert_eq!(cache.calculate_ttl(&message), 60);
+
+        // 启用后按配置时长缓存，不受 ttl.min 影响
+        let cache = DnsCache::new(CacheConfig {
+            enabled: true,
+            override_zero_ttl_secs: 2,
+            companion_prefetch: false,
+            ..CacheConfig::default()
+        });
+        assert_eq!(cache.calculate_ttl(&message), 2);
+
+        // 非零 TTL 的应答不受影响
+        let regular = create_test_message("regular.example.com", RecordType::A, 300, Some("192.0.2.11"));
+        let regular = Message::from_vec(&regular.to_vec().unwrap()).unwrap();
+        assert_eq!(cache.calculate_ttl(&regular), 300);
+
+        // 在覆盖时长内命中缓存，之后过期
+        cache.put_with_auto_ttl(&key, &message).await.unwrap();
+        assert!(cache.get(&key).await.is_some(), "TTL=0 answer should be cached for the override duration");
+        sleep(Duration::from_secs(3)).await;
+        assert!(cache.get(&key).await.is_none(), "TTL=0 answer should expire after the override duration");
+
+        info!("Test completed: test_cache_override_zero_ttl");
+    }
+    
+    // 从缓存导出中读取指定域名条目的剩余 TTL
+    async fn dumped_ttl(cache: &DnsCache, name: &str) -> Option<u64> {
+        let mut dump = cache.dump(CacheDumpFormat::Json);
+        while let Some(line) = dump.recv().await {
+            let entry: serde_json::Value = serde_json::from_str(&line).unwrap();
+            if entry["name"] == name {
+                return entry["ttl_remaining"].as_u64();
+            }
+        }
+        None
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cache_adaptive_ttl() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_cache_adaptive_ttl");
+
+        let config = CacheConfig {
+            enabled: true,
+            ttl: TtlConfig { min: 1, ..TtlConfig::default() },
+            adaptive_ttl: AdaptiveTtlConfig { enabled: true, min: 10, max: 3600, max_tracked: 100 },
+            ..CacheConfig::default()
+        };
+        let cache = DnsCache::new(config);
+
+        // 稳定的记录：每次在缓存过期后刷新且应答不变，有效 TTL 逐级翻倍直至上限
+        let stable = create_test_message("stable.example.com", RecordType::A, 1, Some("192.0.2.20"));
+        let stable = Message::from_vec(&stable.to_vec().unwrap()).unwrap();
+        let key = create_cache_key("stable.example.com", 1);
+
+        cache.put_with_auto_ttl(&key, &stable).await.unwrap();
+        sleep(Duration::from_millis(1100)).await;
+        cache.put_with_auto_ttl(&key, &stable).await.unwrap();
+        let ttl = dumped_ttl(&cache, "stable.example.com").await.expect("Stable entry should be cached");
+        assert!((1..=2).contains(&ttl), "TTL should double after the first unchanged refresh, got {}", ttl);
+
+        // 过期前的重复写入不计为刷新
+        cache.put_with_auto_ttl(&key, &stable).await.unwrap();
+        let ttl = dumped_ttl(&cache, "stable.example.com").await.unwrap();
+        assert!((1..=2).contains(&ttl), "Writes before expiry should not extend the TTL, got {}", ttl);
+
+        sleep(Duration::from_millis(2100)).await;
+        cache.put_with_auto_ttl(&key, &stable).await.unwrap();
+        let ttl = dumped_ttl(&cache, "stable.example.com").await.unwrap();
+        assert!((3..=4).contains(&ttl), "TTL should keep growing over unchanged refreshes, got {}", ttl);
+
+        // 频繁变化的记录：每次应答变化 TTL 减半，不低于下限
+        let key = create_cache_key("volatile.example.com", 1);
+        let mut ttls = Vec::new();
+        for ip in ["192.0.2.30", "192.0.2.31", "192.0.2.32", "192.0.2.33"] {
+            let volatile = create_test_message("volatile.example.com", RecordType::A, 60, Some(ip));
+            let volatile = Message::from_vec(&volatile.to_vec().unwrap()).unwrap();
+            cache.put_with_auto_ttl(&key, &volatile).await.unwrap();
+            ttls.push(dumped_ttl(&cache, "volatile.example.com").await.unwrap());
+        }
+        for (ttl, expected) in ttls.iter().zip([60, 30, 15, 10]) {
+            assert!(*ttl + 1 >= expected && *ttl <= expected, "Expected TTL {} for a volatile record, got {:?}", expected, ttls);
+        }
+
+        info!("Test completed: test_cache_adaptive_ttl");
+    }
 }
@@ -3,7 +3,8 @@
 #[cfg(test)]
 mod tests {
     use oxide_wdns::server::config::{ServerConfig, ResolverProtocol, MatchType};
-    use oxide_wdns::common::consts::{DEFAULT_CACHE_SIZE,DEFAULT_HTTP_CLIENT_AGENT};
+    use oxide_wdns::server::create_http_client;
+    use oxide_wdns::common::consts::{DEFAULT_CACHE_SIZE,DEFAULT_HTTP_CLIENT_AGENT,DEFAULT_RESPONSE_MAX_TTL};
     use std::path::PathBuf;
     use std::fs::File;
     use std::io::Write;
@@ -377,6 +378,8 @@ dns_resolver:
         info!(config.dns.cache.ttl.max, "Validated cache.ttl.max default value.");
         assert!(config.dns.cache.ttl.negative > 0, "Negative cache TTL should have a default value > 0");
         info!(config.dns.cache.ttl.negative, "Validated cache.ttl.negative default value.");
+        assert_eq!(config.dns.cache.ttl.response_max, DEFAULT_RESPONSE_MAX_TTL, "Response TTL cap should default to 7 days");
+        info!(config.dns.cache.ttl.response_max, "Validated cache.ttl.response_max default value.");
 
         // HTTP客户端默认值
         assert!(config.dns.http_client.timeout > 0, "HTTP client timeout should have a default value > 0");
@@ -617,6 +620,168 @@ dns_resolver:
         }
         info!("Test finished: test_config_validate_regex_compile");
     }
+
+    #[test]
+    fn test_config_validate_admin_requires_token() {
+        let _guard = setup_test_tracing();
+        info!("Starting test: test_config_validate_admin_requires_token");
+        
+        // 启用管理接口但未配置令牌
+        let invalid_config = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+  admin:
+    enabled: true
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+        "#;
+        
+        let (_temp_dir, config_path) = create_temp_config_file(invalid_config);
+        let config_result = ServerConfig::from_file(&config_path);
+        assert!(config_result.is_err(), "Admin API without token should fail validation");
+        assert!(config_result.err().unwrap().to_string().contains("token"));
+        
+        // 配置令牌后应通过验证
+        let valid_config = invalid_config.replace("    enabled: true", "    enabled: true\n    token: \"secret\"");
+        let (_temp_dir, config_path) = create_temp_config_file(&valid_config);
+        let config = ServerConfig::from_file(&config_path).expect("Admin API with token should be valid");
+        assert!(config.http.admin.enabled);
+        assert_eq!(config.http.admin.token, "secret");
+        
+        info!("Test finished: test_config_validate_admin_requires_token");
+    }
+
+    #[test]
+    fn test_config_validate_cname_flatten() {
+        let _guard = setup_test_tracing();
+        info!("Starting test: test_config_validate_cname_flatten");
+        
+        // 启用 CNAME 展平但未配置区域
+        let invalid_config = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  cname_flatten:
+    enabled: true
+        "#;
+        
+        let (_temp_dir, config_path) = create_temp_config_file(invalid_config);
+        let config_result = ServerConfig::from_file(&config_path);
+        assert!(config_result.is_err(), "CNAME flattening without zones should fail validation");
+        assert!(config_result.err().unwrap().to_string().contains("zones"));
+        
+        // 配置区域后应通过验证，并匹配区域本身及其子域
+        let valid_config = invalid_config.replace("    enabled: true", "    enabled: true\n    zones: [\"Example.com.\"]");
+        let (_temp_dir, config_path) = create_temp_config_file(&valid_config);
+        let config = ServerConfig::from_file(&config_path).expect("CNAME flattening with zones should be valid");
+        let flatten = &config.dns.cname_flatten;
+        assert!(flatten.matches("example.com."));
+        assert!(flatten.matches("www.EXAMPLE.com"));
+        assert!(!flatten.matches("notexample.com"));
+        
+        info!("Test finished: test_config_validate_cname_flatten");
+    }
+
+    #[test]
+    fn test_config_validate_tls_min_version() {
+        let _guard = setup_test_tracing();
+        info!("Starting test: test_config_validate_tls_min_version");
+        
+        let base_config = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  http_client:
+    tls:
+      min_version: "VERSION"
+        "#;
+        
+        // 默认最低版本为 TLS 1.2
+        let (_temp_dir, config_path) = create_temp_config_file(&base_config.replace("    tls:\n      min_version: \"VERSION\"\n", ""));
+        let config = ServerConfig::from_file(&config_path).expect("Default TLS policy should be valid");
+        assert_eq!(config.dns.http_client.tls.min_version, "1.2");
+        assert!(create_http_client(&config).is_ok());
+        
+        // 合法版本（包括仅允许 TLS 1.3）可用于构建 HTTP 客户端
+        for version in ["1.1", "1.3"] {
+            let (_temp_dir, config_path) = create_temp_config_file(&base_config.replace("VERSION", version));
+            let config = ServerConfig::from_file(&config_path).expect("Valid TLS minimum should pass validation");
+            assert!(create_http_client(&config).is_ok());
+        }
+        
+        // 非法版本字符串无法通过验证
+        for version in ["1.4", "tls12"] {
+            let (_temp_dir, config_path) = create_temp_config_file(&base_config.replace("VERSION", version));
+            let config_result = ServerConfig::from_file(&config_path);
+            assert!(config_result.is_err(), "TLS min_version {} should fail validation", version);
+            assert!(config_result.err().unwrap().to_string().contains("min_version"));
+        }
+        
+        info!("Test finished: test_config_validate_tls_min_version");
+    }
+    
+    #[test]
+    fn test_config_validate_tls_ciphers() {
+        let _guard = setup_test_tracing();
+        info!("Starting test: test_config_validate_tls_ciphers");
+        
+        let base_config = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  http_client:
+    tls:
+      min_version: "VERSION"
+      ciphers: CIPHERS
+        "#;
+        let build = |version: &str, ciphers: &str| {
+            let (_temp_dir, config_path) = create_temp_config_file(
+                &base_config.replace("VERSION", version).replace("CIPHERS", ciphers)
+            );
+            ServerConfig::from_file(&config_path)
+        };
+        
+        // 默认不限制加密套件
+        let config = build("1.2", "[]").expect("Empty cipher list should be valid");
+        assert!(config.dns.http_client.tls.ciphers.is_empty());
+        
+        // 显式加密套件列表（名称不区分大小写）可用于构建 HTTP 客户端
+        let config = build("1.3", r#"["TLS13_AES_256_GCM_SHA384", "tls13_chacha20_poly1305_sha256"]"#)
+            .expect("TLS 1.3 cipher suites should be valid");
+        assert_eq!(config.dns.http_client.tls.ciphers.len(), 2);
+        assert!(create_http_client(&config).is_ok());
+        
+        let config = build("1.2", r#"["TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256", "TLS13_AES_128_GCM_SHA256"]"#)
+            .expect("Mixed TLS 1.2/1.3 cipher suites should be valid");
+        assert!(create_http_client(&config).is_ok());
+        
+        // 未知的加密套件名称无法通过验证
+        let result = build("1.2", r#"["TLS_RSA_WITH_RC4_128_SHA"]"#);
+        assert!(result.is_err(), "Unknown cipher suite should fail validation");
+        assert!(result.err().unwrap().to_string().contains("ciphers"));
+        
+        // 仅包含 TLS 1.2 加密套件时无法要求 TLS 1.3
+        let result = build("1.3", r#"["TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"]"#);
+        assert!(result.is_err(), "TLS 1.2 only cipher suites should not satisfy a TLS 1.3 minimum");
+        assert!(result.err().unwrap().to_string().contains("ciphers"));
+        
+        info!("Test finished: test_config_validate_tls_ciphers");
+    }
 }
 
 #[cfg(test)]
@@ -638,6 +803,9 @@ mod persistence_cache_config_tests {
         assert_eq!(config.max_items_to_save, 0);
         assert!(config.skip_expired_on_load);
         assert_eq!(config.shutdown_save_timeout_secs, 30);
+        assert_eq!(config.load_concurrency, 4);
+        assert_eq!(config.save_concurrency, 4);
+        assert_eq!(config.load_rate, 0);
         assert!(!config.periodic.enabled);
         assert_eq!(config.periodic.interval_secs, 3600);
         info!("Test finished: test_persistence_cache_default_config");
@@ -0,0 +1,294 @@
+// tests/server/dnstap_tests.rs
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Method, Request, StatusCode};
+    use tower::util::ServiceExt;
+    use hickory_proto::op::{Message, MessageType};
+    use hickory_proto::rr::RecordType;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{method, path};
+    use tracing::info;
+    use oxide_wdns::common::consts::CONTENT_TYPE_DNS_MESSAGE;
+    use oxide_wdns::server::config::ServerConfig;
+    use oxide_wdns::server::dnstap::{
+        DNSTAP_CONTENT_TYPE, DNSTAP_MESSAGE_CLIENT_QUERY, DNSTAP_MESSAGE_CLIENT_RESPONSE, FSTRM_CONTROL_START,
+    };
+    use oxide_wdns::server::DoHServer;
+    use crate::server::mock_http_server::{create_test_query, create_test_response};
+
+    // 解析后的 Protobuf 字段值
+    #[derive(Debug)]
+    enum FieldValue {
+        Varint(u64),
+        Bytes(Vec<u8>),
+        Fixed32(u32),
+    }
+
+    // 读取 varint，返回值与新偏移
+    fn read_varint(buf: &[u8], mut offset: usize) -> (u64, usize) {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = buf[offset];
+            offset += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return (value, offset);
+            }
+            shift += 7;
+        }
+    }
+
+    // 解析单层 Protobuf 消息
+    fn decode_fields(buf: &[u8]) -> Vec<(u64, FieldValue)> {
+        let mut fields = Vec::new();
+        let mut offset = 0;
+        while offset < buf.len() {
+            let (key, next) = read_varint(buf, offset);
+            offset = next;
+            let value = match key & 0x07 {
+                0 => {
+                    let (value, next) = read_varint(buf, offset);
+                    offset = next;
+                    FieldValue::Varint(value)
+                },
+                2 => {
+                    let (length, next) = read_varint(buf, offset);
+                    let end = next + length as usize;
+                    offset = end;
+                    FieldValue::Bytes(buf[next..end].to_vec())
+                },
+                5 => {
+                    let value = u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap());
+                    offset += 4;
+                    FieldValue::Fixed32(value)
+                },
+                wire_type => panic!("Unexpected wire type {}", wire_type),
+            };
+            fields.push((key >> 3, value));
+        }
+        fields
+    }
+
+    fn varint_field(fields: &[(u64, FieldValue)], number: u64) -> Option<u64> {
+        fields.iter().find_map(|(field, value)| match value {
+            FieldValue::Varint(v) if *field == number => Some(*v),
+            _ => None,
+        })
+    }
+
+    fn bytes_field(fields: &[(u64, FieldValue)], number: u64) -> Option<&[u8]> {
+        fields.iter().find_map(|(field, value)| match value {
+            FieldValue::Bytes(v) if *field == number => Some(v.as_slice()),
+            _ => None,
+        })
+    }
+
+    // 控制帧：控制类型与字段
+    type ControlFrame = (u32, Vec<u8>);
+
+    // 解析 Frame Streams 字节流：返回控制帧与数据帧列表
+    fn split_frames(stream: &[u8]) -> (Vec<ControlFrame>, Vec<Vec<u8>>) {
+        let mut controls = Vec::new();
+        let mut frames = Vec::new();
+        let mut offset = 0;
+        while offset + 4 <= stream.len() {
+            let length = u32::from_be_bytes(stream[offset..offset + 4].try_into().unwrap()) as usize;
+            offset += 4;
+            if length == 0 {
+                let control_length = u32::from_be_bytes(stream[offset..offset + 4].try_into().unwrap()) as usize;
+                let control = &stream[offset + 4..offset + 4 + control_length];
+                controls.push((u32::from_be_bytes(control[..4].try_into().unwrap()), control[4..].to_vec()));
+                offset += 4 + control_length;
+            } else {
+                frames.push(stream[offset..offset + length].to_vec());
+                offset += length;
+            }
+        }
+        (controls, frames)
+    }
+
+    // 校验一对 CLIENT_QUERY / CLIENT_RESPONSE 帧
+    fn assert_query_response_frames(frames: &[Vec<u8>], domain: &str) {
+        assert_eq!(frames.len(), 2, "Expected one query and one response frame");
+
+        let expected = [
+            (DNSTAP_MESSAGE_CLIENT_QUERY, 10, MessageType::Query),
+            (DNSTAP_MESSAGE_CLIENT_RESPONSE, 14, MessageType::Response),
+        ];
+        for (frame, (message_type, wire_field, dns_type)) in frames.iter().zip(expected) {
+            let dnstap = decode_fields(frame);
+            // Dnstap.type = MESSAGE
+            assert_eq!(varint_field(&dnstap, 15), Some(1));
+            assert!(bytes_field(&dnstap, 2).unwrap().starts_with(b"oxide-wdns"));
+
+            let message = decode_fields(bytes_field(&dnstap, 14).expect("Missing dnstap message"));
+            assert_eq!(varint_field(&message, 1), Some(message_type));
+            // socket_family = INET，socket_protocol = DOH
+            assert_eq!(varint_field(&message, 2), Some(1));
+            assert_eq!(varint_field(&message, 3), Some(4));
+            assert_eq!(bytes_field(&message, 4).map(|a| a.len()), Some(4));
+            // query_time_sec / query_time_nsec
+            assert!(varint_field(&message, 8).unwrap() > 0);
+            assert!(message.iter().any(|(field, value)| *field == 9 && matches!(value, FieldValue::Fixed32(nsec) if *nsec < 1_000_000_000)));
+
+            let dns = Message::from_vec(bytes_field(&message, wire_field).expect("Missing DNS message")).unwrap();
+            assert_eq!(dns.message_type(), dns_type);
+            assert_eq!(dns.queries()[0].name().to_utf8(), format!("{}.", domain));
+        }
+    }
+
+    // 创建模拟上游与启用 dnstap 的应用
+    async fn build_app(mock_upstream: &MockServer, dnstap_yaml: &str) -> axum::Router {
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+          dnstap:
+            enabled: true
+        {}
+        "#, mock_upstream.uri(), dnstap_yaml);
+
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        app
+    }
+
+    async fn start_mock_upstream() -> MockServer {
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let response = create_test_response(&query, Ipv4Addr::new(1, 1, 1, 1));
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        mock_upstream
+    }
+
+    async fn send_query(app: &axum::Router, domain: &str) {
+        let query = create_test_query(domain, RecordType::A);
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/dns-query")
+            .header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+            .body(Body::from(query.to_vec().unwrap()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _ = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dnstap_file_output() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_dnstap_file_output");
+
+        let mock_upstream = start_mock_upstream().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dnstap_path = temp_dir.path().join("dnstap").join("queries.fstrm");
+        let dnstap_yaml = format!("    file_path: \"{}\"", dnstap_path.display());
+        let app = build_app(&mock_upstream, &dnstap_yaml).await;
+
+        send_query(&app, "dnstap.example.com").await;
+
+        // 等待后台任务写出两帧
+        let mut stream = Vec::new();
+        for _ in 0..50 {
+            stream = std::fs::read(&dnstap_path).unwrap();
+            if split_frames(&stream).1.len() >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        // 单向模式以携带内容类型的 START 控制帧开头
+        let (controls, frames) = split_frames(&stream);
+        assert_eq!(controls[0].0, FSTRM_CONTROL_START);
+        assert!(controls[0].1.ends_with(DNSTAP_CONTENT_TYPE.as_bytes()));
+        assert_query_response_frames(&frames, "dnstap.example.com");
+
+        info!("Test completed: test_dnstap_file_output");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dnstap_socket_output() {
+        use oxide_wdns::server::dnstap::{FSTRM_CONTROL_ACCEPT, FSTRM_CONTROL_READY};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_dnstap_socket_output");
+
+        // 模拟 dnstap 接收端：完成 READY/ACCEPT/START 握手后接收两个数据帧
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("dnstap.sock");
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+        let receiver = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let read_control = |control: Vec<u8>| u32::from_be_bytes(control[..4].try_into().unwrap());
+
+            let mut controls = Vec::new();
+            let mut frames = Vec::new();
+            while frames.len() < 2 {
+                let length = stream.read_u32().await.unwrap() as usize;
+                if length == 0 {
+                    let control_length = stream.read_u32().await.unwrap() as usize;
+                    let mut control = vec![0u8; control_length];
+                    stream.read_exact(&mut control).await.unwrap();
+                    let control_type = read_control(control);
+                    if control_type == FSTRM_CONTROL_READY {
+                        let accept = [0u32, 4, FSTRM_CONTROL_ACCEPT];
+                        let bytes: Vec<u8> = accept.iter().flat_map(|v| v.to_be_bytes()).collect();
+                        stream.write_all(&bytes).await.unwrap();
+                    }
+                    controls.push(control_type);
+                } else {
+                    let mut frame = vec![0u8; length];
+                    stream.read_exact(&mut frame).await.unwrap();
+                    frames.push(frame);
+                }
+            }
+            (controls, frames)
+        });
+
+        let mock_upstream = start_mock_upstream().await;
+        let dnstap_yaml = format!("    socket_path: \"{}\"\n            identity: \"test-node\"", socket_path.display());
+        let app = build_app(&mock_upstream, &dnstap_yaml).await;
+
+        send_query(&app, "socket.example.com").await;
+
+        let (controls, frames) = tokio::time::timeout(Duration::from_secs(5), receiver)
+            .await
+            .expect("dnstap receiver timed out")
+            .unwrap();
+        assert_eq!(controls, vec![FSTRM_CONTROL_READY, FSTRM_CONTROL_START]);
+        assert_query_response_frames(&frames, "socket.example.com");
+        assert_eq!(bytes_field(&decode_fields(&frames[0]), 1), Some(&b"test-node"[..]));
+
+        info!("Test completed: test_dnstap_socket_output");
+    }
+}
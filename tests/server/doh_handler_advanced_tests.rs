@@ -8,18 +8,30 @@ mod tests {
     use axum::body::{Body, to_bytes};
     use axum::http::{Method, Request, header, StatusCode};
     use tower::util::ServiceExt; // 用于oneshot方法的trait
-    use hickory_proto::op::{Message, MessageType, OpCode};
-    use hickory_proto::rr::{Name, RecordType};
+    use hickory_proto::op::{Edns, Message, MessageType, OpCode, ResponseCode};
+    use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+    use hickory_proto::rr::{DNSClass, Name, RecordType};
     use wiremock::MockServer;
     use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64_ENGINE};
-    use oxide_wdns::common::consts::CONTENT_TYPE_DNS_MESSAGE;
-    use oxide_wdns::server::config::ServerConfig;
+    use oxide_wdns::common::consts::{
+        CONTENT_TYPE_DNS_MESSAGE, CONTENT_TYPE_DNS_JSON, EDE_INFO_CODE_BLOCKED, EDE_INFO_CODE_FILTERED, EDE_INFO_CODE_PROHIBITED, HEADER_BLOCK_REASON, BLOCK_REASON_DEFAULT_ACTION, BLOCK_REASON_RULE_MATCH,
+        EDNS_NSID_OPTION_CODE, EDNS_PADDING_OPTION_CODE, DEFAULT_TRUST_ANCHOR_TTL,
+    };
+    use oxide_wdns::server::ede::ExtendedError;
+    use oxide_wdns::server::ecs::EcsData;
+    use oxide_wdns::server::config::{ServerConfig, TruncatedQueryPolicy};
     use oxide_wdns::server::upstream::UpstreamManager;
-    use oxide_wdns::server::cache::DnsCache;
+    use oxide_wdns::server::cache::{CacheKey, DnsCache};
     use oxide_wdns::server::metrics::METRICS;
-    use oxide_wdns::server::doh_handler::{ServerState, doh_routes};
+    use oxide_wdns::server::duplicate::DuplicateQueryDetector;
+    use oxide_wdns::server::query_log::QueryLogger;
+    use oxide_wdns::server::dnstap::DnstapLogger;
+    use oxide_wdns::server::security::DailyQueryQuota;
+    use oxide_wdns::server::plugin::ResponsePlugin;
+    use oxide_wdns::server::doh_handler::{ServerState, doh_routes, sample_detail_log};
     use tracing::info;
     use oxide_wdns::server::routing::Router;
+    use oxide_wdns::server::DoHServer;
 
     // === 辅助函数 / 模拟 ===
     
@@ -60,11 +72,21 @@ mod tests {
         let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), http_client).await.unwrap());
         let cache = Arc::new(DnsCache::new(config.dns.cache.clone())); // 移除unwrap并传递值而非引用
         
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
         ServerState {
             config,
             upstream,
             router,
             cache,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
         }
     }
     
@@ -539,11 +561,21 @@ mod tests {
         let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), http_client).await.unwrap());
         let cache = Arc::new(DnsCache::new(config.dns.cache.clone()));
         
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
         let state = ServerState {
             config,
             upstream,
             cache,
             router,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
         };
         
         // 创建测试应用
@@ -730,11 +762,21 @@ mod tests {
         let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), http_client).await.unwrap());
         let cache = Arc::new(DnsCache::new(config.dns.cache.clone()));
         
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
         let state = ServerState {
             config,
             upstream,
             cache,
             router,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
         };
         
         // 创建测试应用
@@ -803,4 +845,4502 @@ mod tests {
         
         info!("Test completed: test_doh_handler_multiple_upstream_groups");
     }
-} 
\ No newline at end of file
+    
+    // 测试启用 emit_ede 时黑洞响应携带 EDE "Blocked" 选项
+    #[tokio::test]
+    async fn test_doh_handler_blackhole_emits_ede() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_blackhole_emits_ede");
+
+        // 创建包含黑洞规则并启用 EDE 的配置
+        let config_str = r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "8.8.8.8:53"
+                protocol: udp
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+          emit_ede: true
+          block_reason_header: true
+          routing:
+            enabled: true
+            rules:
+              - match:
+                  type: exact
+                  values: ["blocked.example.com"]
+                upstream_group: "__blackhole__"
+        "#;
+        let config: ServerConfig = serde_yaml::from_str(config_str).unwrap();
+        assert!(config.dns.emit_ede);
+        
+        // 创建服务器状态
+        let router = Arc::new(Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap());
+        let http_client = Client::new();
+        let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), http_client).await.unwrap());
+        let cache = Arc::new(DnsCache::new(config.dns.cache.clone()));
+        
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
+        let state = ServerState {
+            config,
+            upstream,
+            cache,
+            router,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
+        };
+        let app = doh_routes(state);
+        
+        // 发送被黑洞的域名查询
+        let query = create_test_query("blocked.example.com", RecordType::A);
+        let request = build_http_request(
+            Method::POST, 
+            "/dns-query", 
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)], 
+            query.to_vec().unwrap()
+        );
+        
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        // 验证阻止原因响应头
+        assert_eq!(
+            response.headers().get(HEADER_BLOCK_REASON).and_then(|v| v.to_str().ok()),
+            Some(BLOCK_REASON_RULE_MATCH)
+        );
+        
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+        assert_eq!(dns_response.response_code(), hickory_proto::op::ResponseCode::NXDomain);
+        
+        // 验证 EDE 选项存在且信息码为 Blocked
+        let ede = ExtendedError::from_message(&dns_response)
+            .expect("Blackhole response should carry an EDE option");
+        info!(info_code = ede.info_code, extra_text = %ede.extra_text, "EDE option found");
+        assert_eq!(ede.info_code, EDE_INFO_CODE_BLOCKED);
+        assert!(!ede.extra_text.is_empty());
+        
+        info!("Test completed: test_doh_handler_blackhole_emits_ede");
+    }
+    
+    #[tokio::test]
+    async fn test_duplicate_query_detection() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_duplicate_query_detection");
+
+        let config = create_test_config();
+        let detector = DuplicateQueryDetector::new(&config.dns.duplicate_detection);
+        let client_ip: std::net::IpAddr = "192.0.2.10".parse().unwrap();
+        let before = METRICS.duplicate_queries_total().get();
+
+        // 首次查询不是重复查询
+        let query = create_test_query("dup.example.com", RecordType::A);
+        assert!(!detector.observe(client_ip, &query).await);
+
+        // 相同 IP、名称、类型和 ID 的重传被识别为重复（名称大小写不敏感）
+        let retransmit = create_test_query("DUP.example.com", RecordType::A);
+        assert!(detector.observe(client_ip, &retransmit).await);
+        assert!(METRICS.duplicate_queries_total().get() > before);
+
+        // 不同 ID、类型或客户端 IP 不视为重复
+        let mut new_id = create_test_query("dup.example.com", RecordType::A);
+        new_id.set_id(4321);
+        assert!(!detector.observe(client_ip, &new_id).await);
+        let other_type = create_test_query("dup.example.com", RecordType::AAAA);
+        assert!(!detector.observe(client_ip, &other_type).await);
+        assert!(!detector.observe("192.0.2.11".parse().unwrap(), &query).await);
+
+        // 禁用时始终返回 false
+        let mut disabled_config = config.dns.duplicate_detection.clone();
+        disabled_config.enabled = false;
+        let disabled = DuplicateQueryDetector::new(&disabled_config);
+        assert!(!disabled.observe(client_ip, &query).await);
+        assert!(!disabled.observe(client_ip, &query).await);
+
+        info!("Test completed: test_duplicate_query_detection");
+    }
+    
+    // 构建带 CNAME 链的上游响应
+    fn create_cname_chain_response(query: &Message) -> Message {
+        use hickory_proto::rr::{RData, Record, rdata::{A, CNAME}};
+        
+        let mut response = Message::new();
+        response.set_id(query.id())
+                .set_message_type(MessageType::Response)
+                .set_op_code(OpCode::Query)
+                .set_recursion_desired(true)
+                .set_recursion_available(true);
+        for q in query.queries() {
+            response.add_query(q.clone());
+        }
+        
+        let name = query.queries()[0].name().clone();
+        let cname = |owner: &str, target: &str, ttl: u32| {
+            Record::from_rdata(
+                Name::from_ascii(owner).unwrap(),
+                ttl,
+                RData::CNAME(CNAME(Name::from_ascii(target).unwrap())),
+            )
+        };
+        
+        match name.to_ascii().trim_end_matches('.') {
+            // 顶点域名仅返回 CNAME，需要继续追踪目标
+            "apex.example.com" | "other.example.net" => {
+                response.add_answer(cname(&name.to_ascii(), "edge.cdn.test.", 600));
+            },
+            // 目标继续指向源站，并附带最终 A 记录
+            "edge.cdn.test" => {
+                response.add_answer(cname("edge.cdn.test.", "origin.cdn.test.", 120));
+                response.add_answer(Record::from_rdata(
+                    Name::from_ascii("origin.cdn.test.").unwrap(),
+                    300,
+                    RData::A(A::new(203, 0, 113, 7)),
+                ));
+            },
+            _ => {},
+        }
+        
+        response
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_cname_flattening() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_cname_flattening");
+        
+        // 模拟上游：按查询名返回 CNAME 链
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = create_cname_chain_response(&query);
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          cname_flatten:
+            enabled: true
+            zones: ["example.com"]
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        
+        let router = Arc::new(Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap());
+        let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), Client::new()).await.unwrap());
+        let cache = Arc::new(DnsCache::new(config.dns.cache.clone()));
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
+        let state = ServerState {
+            config,
+            upstream,
+            cache: cache.clone(),
+            router,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
+        };
+        let app = doh_routes(state);
+        
+        // 区域内的查询：CNAME 链被展平为查询名下的 A 记录
+        let query = create_test_query("apex.example.com", RecordType::A);
+        let request = build_http_request(
+            Method::POST,
+            "/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+        
+        let answers = dns_response.answers();
+        info!(answers = ?answers, "Flattened answers");
+        assert_eq!(answers.len(), 1);
+        assert_eq!(answers[0].record_type(), RecordType::A);
+        assert_eq!(answers[0].name(), &Name::from_ascii("apex.example.com").unwrap());
+        assert_eq!(answers[0].data().unwrap().to_string(), "203.0.113.7");
+        // TTL 取链上最小值
+        assert_eq!(answers[0].ttl(), 120);
+        
+        // 展平结果按原查询缓存
+        assert!(cache.len().await > 0);
+        
+        // 区域外的查询保持上游原始应答
+        let query = create_test_query("other.example.net", RecordType::A);
+        let request = build_http_request(
+            Method::POST,
+            "/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = app.oneshot(request).await.unwrap();
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+        assert_eq!(dns_response.answers().len(), 1);
+        assert_eq!(dns_response.answers()[0].record_type(), RecordType::CNAME);
+        
+        info!("Test completed: test_doh_handler_cname_flattening");
+    }
+    
+    // 挂载按固定 IP 应答的模拟 DoH 上游
+    async fn mount_fixed_ip_upstream(server: &MockServer, ip: std::net::Ipv4Addr) {
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(move |req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let response = crate::server::mock_http_server::create_test_response(&query, ip);
+                
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(server)
+            .await;
+    }
+    
+    // 通过指定路径发送查询并返回首条应答数据
+    async fn query_first_answer(app: &axum::Router, uri: &str, domain: &str) -> String {
+        let query = create_test_query(domain, RecordType::A);
+        let request = build_http_request(
+            Method::POST,
+            uri,
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "Unexpected status for {}", uri);
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+        dns_response.answers()[0].data().unwrap().to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_routing_profiles() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_routing_profiles");
+        
+        let mock_public = MockServer::start().await;
+        let mock_internal = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_public, std::net::Ipv4Addr::new(1, 1, 1, 1)).await;
+        mount_fixed_ip_upstream(&mock_internal, std::net::Ipv4Addr::new(10, 0, 0, 1)).await;
+        
+        // 内部档案默认使用 internal 组，域名规则仍优先生效
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{public}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          routing:
+            enabled: true
+            upstream_groups:
+              - name: "public"
+                resolvers:
+                  - address: "{public}/dns-query"
+                    protocol: doh
+              - name: "internal"
+                resolvers:
+                  - address: "{internal}/dns-query"
+                    protocol: doh
+            rules:
+              - match:
+                  type: exact
+                  values: ["forced.example.com"]
+                upstream_group: "public"
+            profiles:
+              - name: "internal"
+                path_prefix: "/internal"
+                default_upstream_group: "internal"
+        "#, public = mock_public.uri(), internal = mock_internal.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 标准路径使用全局上游
+        assert_eq!(query_first_answer(&app, "/dns-query", "example.com").await, "1.1.1.1");
+        
+        // 档案路径使用档案默认组，且不会命中标准路径的缓存
+        assert_eq!(query_first_answer(&app, "/internal/dns-query", "example.com").await, "10.0.0.1");
+        
+        // 档案路径下域名规则优先于档案默认组
+        assert_eq!(query_first_answer(&app, "/internal/dns-query", "forced.example.com").await, "1.1.1.1");
+        
+        // 标准路径缓存未被档案应答污染
+        assert_eq!(query_first_answer(&app, "/dns-query", "example.com").await, "1.1.1.1");
+        
+        info!("Test completed: test_doh_handler_routing_profiles");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_stale_on_error() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_stale_on_error");
+        
+        // 模拟上游：首次正常应答，之后返回 HTTP 500
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .up_to_n_times(1)
+                .mount(&mock_upstream)
+                .await;
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(ResponseTemplate::new(500))
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        // 缓存条目 1 秒后过期
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+            ttl:
+              min: 1
+              max: 1
+            stale_on_error: true
+            stale_on_error_ttl: 5
+          emit_ede: true
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 首次查询写入缓存
+        assert_eq!(query_first_answer(&app, "/dns-query", "stale.example.com").await, "1.1.1.1");
+        
+        // 等待缓存条目过期
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        let stale_before = METRICS.cache_stale_on_error_total().get();
+        
+        // 上游失败时返回过期条目，TTL 改写为 stale_on_error_ttl 并附带 EDE
+        let query = create_test_query("stale.example.com", RecordType::A);
+        let request = build_http_request(
+            Method::POST,
+            "/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+        
+        assert_eq!(dns_response.id(), query.id());
+        assert_eq!(dns_response.answers().len(), 1);
+        assert_eq!(dns_response.answers()[0].data().unwrap().to_string(), "1.1.1.1");
+        assert_eq!(dns_response.answers()[0].ttl(), 5);
+        assert_eq!(ExtendedError::from_message(&dns_response).map(|e| e.info_code), Some(3));
+        assert!(METRICS.cache_stale_on_error_total().get() > stale_before);
+        
+        // 没有过期条目的查询仍按上游失败处理
+        let query = create_test_query("fresh.example.com", RecordType::A);
+        let request = build_http_request(
+            Method::POST,
+            "/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = app.oneshot(request).await.unwrap();
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+        assert_eq!(dns_response.response_code(), hickory_proto::op::ResponseCode::ServFail);
+        
+        info!("Test completed: test_doh_handler_stale_on_error");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_normalizes_response_flags() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_normalizes_response_flags");
+        
+        // 模拟上游：应答未置位 RA 且置位 AA
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let mut response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    response.set_recursion_available(false)
+                        .set_authoritative(true);
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          routing:
+            enabled: true
+            rules:
+              - match:
+                  type: exact
+                  values: ["blocked.example.com"]
+                upstream_group: "__blackhole__"
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        assert!(config.dns.normalize_response_flags);
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 发送查询并返回解码后的 DNS 响应
+        let send = |domain: &'static str| {
+            let app = app.clone();
+            async move {
+                let query = create_test_query(domain, RecordType::A);
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        // 上游应答：RA 置位，AA 清除
+        let upstream_response = send("flags.example.com").await;
+        assert!(upstream_response.recursion_available());
+        assert!(!upstream_response.authoritative());
+        
+        // 缓存命中：标志与上游应答一致
+        let cached_response = send("flags.example.com").await;
+        assert_eq!(cached_response.answers().len(), 1);
+        assert!(cached_response.recursion_available(), "Cache hits should have RA set");
+        assert!(!cached_response.authoritative(), "Cache hits should not claim authority");
+        
+        // 黑洞应答：RA 置位，AA 清除
+        let blocked_response = send("blocked.example.com").await;
+        assert!(blocked_response.recursion_available(), "Blackhole responses should have RA set");
+        assert!(!blocked_response.authoritative());
+        
+        info!("Test completed: test_doh_handler_normalizes_response_flags");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_cache_key_includes_dnssec_flags() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_cache_key_includes_dnssec_flags");
+        
+        // 模拟上游：DO=0 与 DO=1 查询各应转发一次
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .expect(2)
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 发送查询，dnssec_ok 控制 EDNS DO 标志
+        let send = |dnssec_ok: bool| {
+            let app = app.clone();
+            async move {
+                let mut query = create_test_query("do.example.com", RecordType::A);
+                if dnssec_ok {
+                    let mut edns = hickory_proto::op::Edns::new();
+                    edns.set_dnssec_ok(true);
+                    query.set_edns(edns);
+                }
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+        };
+        
+        // DO=0 写入缓存，DO=1 不应命中该条目
+        send(false).await;
+        send(true).await;
+        assert_eq!(cache.len().await, 2, "DO=0 and DO=1 answers should be cached separately");
+        
+        // 再次查询均命中各自的缓存条目，不再访问上游
+        send(false).await;
+        send(true).await;
+        assert_eq!(cache.len().await, 2);
+        
+        info!("Test completed: test_doh_handler_cache_key_includes_dnssec_flags");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_get_ignores_random_padding() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_get_ignores_random_padding");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(1, 1, 1, 1)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 带随机填充参数的 GET 请求（参数可位于 dns 前后）
+        let query = create_test_query("padding.example.com", RecordType::A);
+        let query_base64 = encode_dns_message_base64url(&query);
+        let padding = "x".repeat(37);
+        for uri in [
+            format!("/dns-query?dns={}&random_padding={}", query_base64, padding),
+            format!("/dns-query?random_padding={}&dns={}", padding, query_base64),
+        ] {
+            let request = build_http_request(Method::GET, &uri, vec![], vec![]);
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "Padded GET request should succeed: {}", uri);
+            
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+            assert_eq!(dns_response.id(), query.id());
+            assert_eq!(dns_response.answers()[0].data().unwrap().to_string(), "1.1.1.1");
+        }
+        
+        info!("Test completed: test_doh_handler_get_ignores_random_padding");
+    }
+    
+    #[test]
+    fn test_detail_log_sampling() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_detail_log_sampling");
+        
+        let sampled = |rate: f64, total: usize| {
+            let counter = std::sync::atomic::AtomicU64::new(0);
+            (0..total).filter(|_| sample_detail_log(&counter, rate)).count()
+        };
+        
+        // 默认记录所有查询，0 表示不记录
+        assert_eq!(create_test_config().dns.log_sample_rate, 1.0);
+        assert_eq!(sampled(1.0, 50), 50);
+        assert_eq!(sampled(0.0, 50), 0);
+        
+        // 1% 采样：每 100 个查询记录一次，且首个查询被记录
+        assert_eq!(sampled(0.01, 1000), 10);
+        assert_eq!(sampled(0.25, 16), 4);
+        assert_eq!(sampled(0.01, 1), 1);
+        
+        // 超出范围的采样率无法通过验证
+        let mut config = create_test_config();
+        config.dns.log_sample_rate = 1.5;
+        assert!(config.test().is_err());
+        config.dns.log_sample_rate = 0.01;
+        assert!(config.test().is_ok());
+        
+        info!("Test completed: test_detail_log_sampling");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_nsid_round_trip() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_nsid_round_trip");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(1, 1, 1, 1)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+          nsid: "doh-node-1"
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        assert_eq!(config.http.nsid_identifier(), Some("doh-node-1"));
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let send = |query: Message| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        let nsid_of = |message: &Message| match message.extensions().as_ref()
+            .and_then(|edns| edns.option(EdnsCode::from(EDNS_NSID_OPTION_CODE)))
+        {
+            Some(EdnsOption::Unknown(_, data)) => Some(data.clone()),
+            _ => None,
+        };
+        
+        // 携带空 NSID 选项的查询获得服务器标识
+        let mut query = create_test_query("nsid.example.com", RecordType::A);
+        let mut edns = Edns::new();
+        edns.options_mut().insert(EdnsOption::Unknown(EDNS_NSID_OPTION_CODE, Vec::new()));
+        query.set_edns(edns);
+        let response = send(query).await;
+        assert_eq!(nsid_of(&response).as_deref(), Some(&b"doh-node-1"[..]));
+        
+        // 未请求 NSID 的查询（包括缓存命中）不附加
+        let response = send(create_test_query("nsid.example.com", RecordType::A)).await;
+        assert_eq!(nsid_of(&response), None);
+        
+        // "hostname" 使用本机主机名
+        let mut config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        config.http.nsid = "hostname".to_string();
+        let hostname = config.http.nsid_identifier().expect("Hostname should be available");
+        assert!(!hostname.is_empty());
+        assert_ne!(hostname, "hostname");
+        
+        info!("Test completed: test_doh_handler_nsid_round_trip");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_cache_isolates_upstream_groups() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_cache_isolates_upstream_groups");
+        
+        // 两个上游组对同一域名返回不同的地址
+        let alpha_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&alpha_upstream, std::net::Ipv4Addr::new(10, 0, 0, 1)).await;
+        let beta_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&beta_upstream, std::net::Ipv4Addr::new(10, 0, 0, 2)).await;
+        
+        // 同一份上游配置，规则分别将域名路由到 alpha 或 beta 组
+        let build_config = |group: &str| -> ServerConfig {
+            serde_yaml::from_str(&format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "8.8.8.8:53"
+                    protocol: udp
+                query_timeout: 3
+                enable_dnssec: false
+              cache:
+                enabled: true
+                isolate_upstream_groups: true
+              routing:
+                enabled: true
+                upstream_groups:
+                  - name: "alpha"
+                    resolvers:
+                      - address: "{}/dns-query"
+                        protocol: doh
+                  - name: "beta"
+                    resolvers:
+                      - address: "{}/dns-query"
+                        protocol: doh
+                rules:
+                  - match:
+                      type: exact
+                      values: ["split.example.com"]
+                    upstream_group: "{}"
+            "#, alpha_upstream.uri(), beta_upstream.uri(), group)).unwrap()
+        };
+        
+        // 两个状态共享缓存与上游管理器，仅路由规则不同
+        let alpha_config = build_config("alpha");
+        let cache = Arc::new(DnsCache::new(alpha_config.dns.cache.clone()));
+        let upstream = Arc::new(UpstreamManager::new(Arc::new(alpha_config.clone()), Client::new()).await.unwrap());
+        let build_app = |config: ServerConfig| {
+            let cache = cache.clone();
+            let upstream = upstream.clone();
+            async move {
+                let router = Arc::new(Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap());
+                doh_routes(ServerState {
+                    duplicates: Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection)),
+                    config,
+                    upstream,
+                    cache,
+                    router,
+                    profile: None,
+                    query_log: Arc::new(QueryLogger::disabled()),
+                    dnstap: Arc::new(DnstapLogger::disabled()),
+                    quota: Arc::new(DailyQueryQuota::disabled()),
+                    plugin: None,
+                    group_override_cidrs: Arc::new(Vec::new()),
+                    ddr: None,
+                    trust_anchor: None,
+                })
+            }
+        };
+        let alpha_app = build_app(alpha_config).await;
+        let beta_app = build_app(build_config("beta")).await;
+        
+        // 各组先后两次查询（第二次命中缓存），应答不互相污染
+        for _ in 0..2 {
+            assert_eq!(query_first_answer(&alpha_app, "/dns-query", "split.example.com").await, "10.0.0.1");
+            assert_eq!(query_first_answer(&beta_app, "/dns-query", "split.example.com").await, "10.0.0.2");
+        }
+        
+        // 两个组各自维护独立的缓存条目
+        assert_eq!(cache.len().await, 2);
+        
+        info!("Test completed: test_doh_handler_cache_isolates_upstream_groups");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_server_base_path() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_server_base_path");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(1, 1, 1, 1)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          base_path: "/owdns"
+          rate_limit:
+            enabled: false
+          admin:
+            enabled: true
+            token: "base-path-token"
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          routing:
+            enabled: true
+            upstream_groups:
+              - name: "internal"
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+            profiles:
+              - name: "internal"
+                path_prefix: "/internal"
+                default_upstream_group: "internal"
+        "#, mock_upstream.uri(), mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config.clone(), true).build_application_components().await.unwrap();
+        
+        // DoH 与档案路径均挂载在公共前缀下
+        assert_eq!(query_first_answer(&app, "/owdns/dns-query", "example.com").await, "1.1.1.1");
+        assert_eq!(query_first_answer(&app, "/owdns/internal/dns-query", "example.com").await, "1.1.1.1");
+        
+        // 健康检查、指标、Scalar UI 与管理接口同样带前缀
+        let get_status = |uri: &str, token: Option<&str>| {
+            let mut builder = Request::builder().method(Method::GET).uri(uri);
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            let request = builder.body(Body::empty()).unwrap();
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+        assert_eq!(get_status("/owdns/health", None).await, StatusCode::OK);
+        assert_eq!(get_status("/owdns/metrics", None).await, StatusCode::OK);
+        assert_eq!(get_status("/owdns/scalar", None).await, StatusCode::OK);
+        assert_eq!(get_status("/owdns/admin/cache/dump", Some("base-path-token")).await, StatusCode::OK);
+        
+        // 未带前缀的路径不再可用
+        assert_eq!(get_status("/health", None).await, StatusCode::NOT_FOUND);
+        assert_eq!(get_status("/dns-query?dns=AAABAAABAAAAAAAAB2V4YW1wbGUDY29tAAABAAE", None).await, StatusCode::NOT_FOUND);
+        
+        // 非法前缀在配置验证阶段被拒绝
+        for invalid in ["owdns", "/owdns/", "/", "/ow dns", "//owdns"] {
+            let mut invalid_config = config.clone();
+            invalid_config.http.base_path = invalid.to_string();
+            let err = invalid_config.test().expect_err("Invalid base_path should be rejected");
+            assert!(err.to_string().contains("base_path"), "Unexpected error for {:?}: {}", invalid, err);
+        }
+        
+        info!("Test completed: test_doh_server_base_path");
+    }
+    
+    // 收集日志输出的写入器
+    #[derive(Clone, Default)]
+    struct CaptureWriter(Arc<std::sync::Mutex<Vec<u8>>>);
+    
+    impl std::io::Write for CaptureWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_request_id_correlates_logs() {
+        info!("Starting test: test_doh_handler_request_id_correlates_logs");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(1, 1, 1, 1)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 单线程运行时下为当前线程设置捕获日志的订阅者
+        let writer = CaptureWriter::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer({
+                let writer = writer.clone();
+                move || writer.clone()
+            })
+            .finish();
+        let guard = tracing::subscriber::set_default(subscriber);
+        
+        for domain in ["first.example.com", "second.example.com"] {
+            assert_eq!(query_first_answer(&app, "/dns-query", domain).await, "1.1.1.1");
+        }
+        drop(guard);
+        
+        // 提取包含指定消息的日志行中的请求 ID
+        let output = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+        let request_ids = |message: &str| -> Vec<String> {
+            output
+                .lines()
+                .filter(|line| line.contains(message))
+                .map(|line| {
+                    let start = line.find("request_id=").expect("Log line should carry request_id") + "request_id=".len();
+                    line[start..start + 16].to_string()
+                })
+                .collect()
+        };
+        let received = request_ids("DNS-over-HTTPS POST request received");
+        let completed = request_ids("DNS-over-HTTPS wire POST request completed");
+        
+        // 同一请求的接收与完成日志共享 ID，不同请求的 ID 不同
+        assert_eq!(received.len(), 2);
+        assert_eq!(received, completed);
+        assert_ne!(received[0], received[1]);
+        assert!(received.iter().all(|id| id.chars().all(|c| c.is_ascii_hexdigit())));
+        
+        info!("Test completed: test_doh_handler_request_id_correlates_logs");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_ddr_svcb_response() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_ddr_svcb_response");
+        
+        // 上游不应收到 DDR 查询
+        let mock_upstream = MockServer::start().await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          base_path: "/owdns"
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          ddr:
+            enabled: true
+            target: "doh.example.net"
+            alpn: ["h2", "h3"]
+            ttl: 600
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        assert_eq!(config.ddr_dohpath(), "/owdns/dns-query{?dns}");
+        let (app, _cache) = DoHServer::new(config.clone(), false).build_application_components().await.unwrap();
+        
+        let query = create_test_query("_dns.resolver.arpa", RecordType::SVCB);
+        let request = build_http_request(
+            Method::POST,
+            "/owdns/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+        assert_eq!(dns_response.response_code(), hickory_proto::op::ResponseCode::NoError);
+        assert_eq!(dns_response.answer_count(), 1);
+        
+        // 合成的 SVCB 记录携带目标名、alpn、port 与 dohpath
+        let record = &dns_response.answers()[0];
+        assert_eq!(record.ttl(), 600);
+        let Some(hickory_proto::rr::RData::SVCB(svcb)) = record.data() else {
+            panic!("Expected SVCB record, got {:?}", record.data());
+        };
+        assert_eq!(svcb.svc_priority(), 1);
+        assert_eq!(svcb.target_name(), &Name::from_ascii("doh.example.net.").unwrap());
+        
+        use hickory_proto::rr::rdata::svcb::{Alpn, SvcParamKey, SvcParamValue, Unknown};
+        let params = svcb.svc_params();
+        assert_eq!(params.len(), 3);
+        assert_eq!(params[0], (SvcParamKey::Alpn, SvcParamValue::Alpn(Alpn(vec!["h2".to_string(), "h3".to_string()]))));
+        assert_eq!(params[1], (SvcParamKey::Port, SvcParamValue::Port(443)));
+        assert_eq!(params[2], (SvcParamKey::Unknown(7), SvcParamValue::Unknown(Unknown(b"/owdns/dns-query{?dns}".to_vec()))));
+        assert!(mock_upstream.received_requests().await.unwrap().is_empty());
+        
+        // 启用 DDR 时必须配置有效的目标名
+        let mut invalid_config = config.clone();
+        invalid_config.dns.upstream.resolvers[0].address = "https://dns.example.com/dns-query".to_string();
+        invalid_config.test().expect("Valid DDR configuration should pass validation");
+        invalid_config.dns.ddr.target = String::new();
+        let err = invalid_config.test().expect_err("Missing DDR target should be rejected");
+        assert!(err.to_string().contains("DDR"), "Unexpected error: {}", err);
+        
+        info!("Test completed: test_doh_handler_ddr_svcb_response");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_truncated_query_policy() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_truncated_query_policy");
+        
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        // 按指定策略构建应用并发送一个设置了 TC 位的查询
+        let send_truncated = |policy: &'static str| {
+            let upstream_uri = mock_upstream.uri();
+            async move {
+                let config_str = format!(r#"
+                http_server:
+                  listen_addr: "127.0.0.1:8053"
+                  timeout: 10
+                  rate_limit:
+                    enabled: false
+                dns_resolver:
+                  upstream:
+                    resolvers:
+                      - address: "{}/dns-query"
+                        protocol: doh
+                    query_timeout: 3
+                    enable_dnssec: false
+                  http_client:
+                    timeout: 5
+                  cache:
+                    enabled: false
+                  truncated_query_policy: "{}"
+                "#, upstream_uri, policy);
+                let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+                let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+                
+                let mut query = create_test_query("truncated.example.com", RecordType::A);
+                query.set_truncated(true);
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        // clear：清除 TC 位后转发至上游并正常应答
+        let cleared = send_truncated("clear").await;
+        assert_eq!(cleared.response_code(), ResponseCode::NoError);
+        assert_eq!(cleared.id(), 1234);
+        assert_eq!(cleared.answers().len(), 1);
+        let forwarded = mock_upstream.received_requests().await.unwrap();
+        assert_eq!(forwarded.len(), 1);
+        let forwarded_query = Message::from_vec(&forwarded[0].body).unwrap();
+        assert!(!forwarded_query.truncated(), "TC bit should be cleared before forwarding upstream");
+        
+        // formerr：直接返回 FORMERR，不查询上游
+        let rejected = send_truncated("formerr").await;
+        assert_eq!(rejected.response_code(), ResponseCode::FormErr);
+        assert_eq!(rejected.id(), 1234);
+        assert_eq!(rejected.queries().len(), 1);
+        assert!(rejected.answers().is_empty());
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 1, "Rejected query should not reach upstream");
+        
+        // 默认策略为 clear
+        assert_eq!(create_test_config().dns.truncated_query_policy, TruncatedQueryPolicy::Clear);
+        
+        info!("Test completed: test_doh_handler_truncated_query_policy");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_json_answer_cache() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_json_answer_cache");
+        
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+            json_answer_cache: true
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        assert!(!create_test_config().dns.cache.json_answer_cache, "JSON answer cache should be opt-in");
+        let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 发送 JSON API 查询并返回响应正文
+        let send = || {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(Method::GET, "/resolve?name=json.example.com&type=1", vec![], vec![]);
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), CONTENT_TYPE_DNS_JSON);
+                to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec()
+            }
+        };
+        
+        // 首次查询：上游应答写入缓存，JSON 序列化结果尚未缓存
+        let first = send().await;
+        let key = CacheKey::new(Name::from_ascii("json.example.com").unwrap(), RecordType::A, DNSClass::IN)
+            .with_dnssec_flags(false, false);
+        let (_, slot) = cache.get_entry_with_ecs(&key, None).await.expect("Answer should be cached");
+        let slot = slot.expect("JSON answer cache slot should exist when enabled");
+        
+        // 第二次查询：缓存命中，填充并返回 JSON 序列化结果
+        let second = send().await;
+        assert_eq!(first, second, "Cached JSON should match the freshly serialized response");
+        assert_eq!(slot.get().map(|body| body.to_vec()), Some(second.clone()));
+        
+        // 第三次查询：直接复用已缓存的 JSON
+        let third = send().await;
+        assert_eq!(third, second);
+        let json: serde_json::Value = serde_json::from_slice(&third).unwrap();
+        assert_eq!(json["status"], 0);
+        assert_eq!(json["answer"][0]["data"], "1.1.1.1");
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 1);
+        
+        info!("Test completed: test_doh_handler_json_answer_cache");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_multi_question() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_multi_question");
+        
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        // 按指定开关构建应用并发送一个包含多个问题的查询
+        let send_with_limits = |allow: bool, query_limits: bool, domains: &'static [&'static str]| {
+            let upstream_uri = mock_upstream.uri();
+            async move {
+                let config_str = format!(r#"
+                http_server:
+                  listen_addr: "127.0.0.1:8053"
+                  timeout: 10
+                  rate_limit:
+                    enabled: false
+                dns_resolver:
+                  upstream:
+                    resolvers:
+                      - address: "{}/dns-query"
+                        protocol: doh
+                    query_timeout: 3
+                    enable_dnssec: false
+                  http_client:
+                    timeout: 5
+                  cache:
+                    enabled: false
+                  allow_multi_question: {}
+                  query_limits:
+                    enabled: {}
+                  routing:
+                    enabled: true
+                    rules:
+                      - match:
+                          type: exact
+                          values: ["blocked.example.com"]
+                        upstream_group: "__blackhole__"
+                "#, upstream_uri, allow, query_limits);
+                let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+                let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+                
+                let mut query = create_test_query(domains[0], RecordType::A);
+                for domain in &domains[1..] {
+                    query.add_query(hickory_proto::op::Query::query(Name::from_ascii(domain).unwrap(), RecordType::A));
+                }
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        let send = |allow: bool, domains: &'static [&'static str]| send_with_limits(allow, true, domains);
+        
+        // 启用：两个问题分别解析，应答合并到同一响应
+        let merged = send(true, &["one.example.com.", "two.example.com."]).await;
+        assert_eq!(merged.response_code(), ResponseCode::NoError);
+        assert_eq!(merged.id(), 1234);
+        assert_eq!(merged.queries().len(), 2);
+        let mut answer_names: Vec<String> = merged.answers().iter().map(|r| r.name().to_string()).collect();
+        answer_names.sort();
+        assert_eq!(answer_names, vec!["one.example.com.", "two.example.com."]);
+        
+        // 启用：其中一个问题被阻止时返回其响应码，并保留另一个问题的应答
+        let partial = send(true, &["three.example.com.", "blocked.example.com."]).await;
+        assert_eq!(partial.response_code(), ResponseCode::NXDomain);
+        assert_eq!(partial.queries().len(), 2);
+        assert_eq!(partial.answers().len(), 1);
+        assert_eq!(partial.answers()[0].name().to_string(), "three.example.com.");
+        
+        // 默认关闭：仅解析第一个问题
+        let first_only = send(false, &["four.example.com.", "five.example.com."]).await;
+        assert_eq!(first_only.answers().len(), 1);
+        assert_eq!(first_only.answers()[0].name().to_string(), "four.example.com.");
+        assert!(!create_test_config().dns.allow_multi_question, "Multi-question support should be opt-in");
+        
+        // 问题数达到默认上限（8）时全部解析，应答顺序与问题顺序一致
+        let at_limit = send(true, &[
+            "q1.example.com.", "q2.example.com.", "q3.example.com.", "q4.example.com.",
+            "q5.example.com.", "q6.example.com.", "q7.example.com.", "q8.example.com.",
+        ]).await;
+        assert_eq!(at_limit.response_code(), ResponseCode::NoError);
+        let answer_names: Vec<String> = at_limit.answers().iter().map(|r| r.name().to_string()).collect();
+        assert_eq!(answer_names, (1..=8).map(|i| format!("q{}.example.com.", i)).collect::<Vec<_>>());
+        
+        // 超出问题数上限时返回 FORMERR，不向上游展开
+        let upstream_requests = mock_upstream.received_requests().await.unwrap().len();
+        let over_limit = send(true, &[
+            "r1.example.com.", "r2.example.com.", "r3.example.com.", "r4.example.com.", "r5.example.com.",
+            "r6.example.com.", "r7.example.com.", "r8.example.com.", "r9.example.com.",
+        ]).await;
+        assert_eq!(over_limit.response_code(), ResponseCode::FormErr);
+        assert!(over_limit.answers().is_empty());
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), upstream_requests);
+        
+        // 关闭查询限制时问题数上限仍然生效
+        let over_limit = send_with_limits(true, false, &[
+            "s1.example.com.", "s2.example.com.", "s3.example.com.", "s4.example.com.", "s5.example.com.",
+            "s6.example.com.", "s7.example.com.", "s8.example.com.", "s9.example.com.",
+        ]).await;
+        assert_eq!(over_limit.response_code(), ResponseCode::FormErr);
+        assert!(over_limit.answers().is_empty());
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), upstream_requests);
+        
+        
+        info!("Test completed: test_doh_handler_multi_question");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_strip_ecs_from_response() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_strip_ecs_from_response");
+        
+        // 模拟上游：应答中回显 ECS 选项
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let mut response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    let ecs = EcsData::new("192.0.2.0".parse().unwrap(), 24, 24).to_edns_option().unwrap();
+                    let mut edns = Edns::new();
+                    edns.options_mut().insert(ecs);
+                    response.set_edns(edns);
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        // 按指定开关构建应用并返回响应是否携带 ECS 选项
+        let response_has_ecs = |strip: bool| {
+            let upstream_uri = mock_upstream.uri();
+            async move {
+                let config_str = format!(r#"
+                http_server:
+                  listen_addr: "127.0.0.1:8053"
+                  timeout: 10
+                  rate_limit:
+                    enabled: false
+                dns_resolver:
+                  upstream:
+                    resolvers:
+                      - address: "{}/dns-query"
+                        protocol: doh
+                    query_timeout: 3
+                    enable_dnssec: false
+                  http_client:
+                    timeout: 5
+                  cache:
+                    enabled: true
+                  ecs_policy:
+                    strip_from_response: {}
+                "#, upstream_uri, strip);
+                let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+                let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+                
+                // 第一次为上游应答，第二次为缓存命中
+                let mut results = Vec::new();
+                for _ in 0..2 {
+                    let query = create_test_query("ecs-echo.example.com.", RecordType::A);
+                    let request = build_http_request(
+                        Method::POST,
+                        "/dns-query",
+                        vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                        query.to_vec().unwrap()
+                    );
+                    let response = app.clone().oneshot(request).await.unwrap();
+                    assert_eq!(response.status(), StatusCode::OK);
+                    let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                    let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+                    assert_eq!(dns_response.answers().len(), 1);
+                    results.push(dns_response.extensions().as_ref()
+                        .is_some_and(|edns| edns.option(EdnsCode::Subnet).is_some()));
+                }
+                results
+            }
+        };
+        
+        // 默认行为：原样转发上游的 ECS 选项
+        assert_eq!(response_has_ecs(false).await, vec![true, true]);
+        assert!(!create_test_config().dns.ecs_policy.strip_from_response);
+        
+        // 启用后：上游应答与缓存命中均不携带 ECS 选项
+        assert_eq!(response_has_ecs(true).await, vec![false, false]);
+        
+        info!("Test completed: test_doh_handler_strip_ecs_from_response");
+    }
+    
+    // 在线格式查询末尾追加携带指定选项的 OPT 记录（不经 hickory 编码，可构造重复选项）
+    fn append_opt_record(mut wire: Vec<u8>, options: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut rdata = Vec::new();
+        for (code, data) in options {
+            rdata.extend_from_slice(&code.to_be_bytes());
+            rdata.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            rdata.extend_from_slice(data);
+        }
+        
+        // 根域名 + TYPE=OPT + CLASS=UDP 负载大小 + TTL + RDLENGTH + RDATA
+        wire.push(0);
+        wire.extend_from_slice(&41u16.to_be_bytes());
+        wire.extend_from_slice(&1232u16.to_be_bytes());
+        wire.extend_from_slice(&0u32.to_be_bytes());
+        wire.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        wire.extend_from_slice(&rdata);
+        
+        // ARCOUNT + 1
+        let arcount = u16::from_be_bytes([wire[10], wire[11]]) + 1;
+        wire[10..12].copy_from_slice(&arcount.to_be_bytes());
+        wire
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_query_limits() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_query_limits");
+        
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+          query_limits:
+            max_query_size: 512
+            max_edns_options: 4
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let send = |wire: Vec<u8>| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    wire
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        let base_query = create_test_query("limits.example.com", RecordType::A).to_vec().unwrap();
+        let rejected = |reason: &str| METRICS.oversized_queries_rejected_total().with_label_values(&[reason]).get();
+        let size_rejected_before = rejected("query_size");
+        let options_rejected_before = rejected("edns_options");
+        
+        // 选项数在限制内的查询正常处理
+        let allowed = append_opt_record(base_query.clone(), &[(12, vec![0; 16]), (10, vec![0; 8])]);
+        assert_eq!(oxide_wdns::server::query_limits::count_edns_options(&allowed), Some(2));
+        let response = send(allowed).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        
+        // 重复的填充选项按线格式逐个计数，超出限制返回 FORMERR
+        let padded = append_opt_record(base_query.clone(), &vec![(12, vec![0; 4]); 8]);
+        assert_eq!(oxide_wdns::server::query_limits::count_edns_options(&padded), Some(8));
+        let response = send(padded).await;
+        assert_eq!(response.response_code(), ResponseCode::FormErr);
+        assert_eq!(response.id(), 1234);
+        assert!(response.answers().is_empty());
+        
+        // 超出最大查询大小的查询返回 FORMERR
+        let oversized = append_opt_record(base_query.clone(), &[(12, vec![0; 600])]);
+        let response = send(oversized).await;
+        assert_eq!(response.response_code(), ResponseCode::FormErr);
+        
+        assert_eq!(rejected("query_size"), size_rejected_before + 1);
+        assert_eq!(rejected("edns_options"), options_rejected_before + 1);
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 1, "Rejected queries should not reach upstream");
+        
+        info!("Test completed: test_doh_handler_query_limits");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_special_names() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_special_names");
+        
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, 1)
+                    );
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        // 按指定的特殊域名开关构建应用
+        let build_app = |localhost: bool, invalid: bool| {
+            let upstream_uri = mock_upstream.uri();
+            async move {
+                let config_str = format!(r#"
+                http_server:
+                  listen_addr: "127.0.0.1:8053"
+                  timeout: 10
+                  rate_limit:
+                    enabled: false
+                dns_resolver:
+                  upstream:
+                    resolvers:
+                      - address: "{}/dns-query"
+                        protocol: doh
+                    query_timeout: 3
+                    enable_dnssec: false
+                  http_client:
+                    timeout: 5
+                  cache:
+                    enabled: false
+                  special_names:
+                    localhost: {}
+                    invalid: {}
+                "#, upstream_uri, localhost, invalid);
+                let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+                let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+                app
+            }
+        };
+        let send = |app: axum::Router, name: &'static str, record_type: RecordType| async move {
+            let query = create_test_query(name, record_type);
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            decode_dns_response(&body_bytes).await.unwrap()
+        };
+        
+        let app = build_app(true, true).await;
+        
+        // localhost 解析为 127.0.0.1
+        let response = send(app.clone(), "localhost", RecordType::A).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.id(), 1234);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].data().and_then(|d| d.as_a()).map(|a| a.0), Some(std::net::Ipv4Addr::LOCALHOST));
+        
+        // localhost 子域的 AAAA 查询解析为 ::1
+        let response = send(app.clone(), "foo.localhost", RecordType::AAAA).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].name(), &Name::from_ascii("foo.localhost.").unwrap());
+        assert_eq!(response.answers()[0].data().and_then(|d| d.as_aaaa()).map(|a| a.0), Some(std::net::Ipv6Addr::LOCALHOST));
+        
+        // localhost 的其他类型返回空应答
+        let response = send(app.clone(), "localhost", RecordType::MX).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(response.answers().is_empty());
+        
+        // invalid 子域返回 NXDOMAIN
+        let response = send(app.clone(), "something.invalid", RecordType::A).await;
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+        assert!(response.answers().is_empty());
+        
+        assert!(mock_upstream.received_requests().await.unwrap().is_empty(), "Special-use names should not reach upstream");
+        
+        // 关闭后按普通域名转发至上游
+        let app = build_app(false, false).await;
+        let response = send(app.clone(), "localhost", RecordType::A).await;
+        assert_eq!(response.answers()[0].data().and_then(|d| d.as_a()).map(|a| a.0), Some(std::net::Ipv4Addr::new(1, 1, 1, 1)));
+        let response = send(app, "something.invalid", RecordType::A).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 2);
+        
+        info!("Test completed: test_doh_handler_special_names");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_loop_protection() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_loop_protection");
+        
+        // 上游指向服务自身，模拟错误配置导致的转发环路
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "http://127.0.0.1:{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+          loop_protection:
+            self_names:
+              - "doh.example.com"
+            self_addresses:
+              - "192.0.2.53"
+              - "2001:db8::53"
+        "#, port);
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        let server_app = app.clone();
+        tokio::spawn(async move {
+            axum::serve(listener, server_app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await.unwrap();
+        });
+        
+        let send = |app: axum::Router, name: &'static str, record_type: RecordType| async move {
+            let query = create_test_query(name, record_type);
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            decode_dns_response(&body_bytes).await.unwrap()
+        };
+        
+        // 转发回自身的请求被识别并拒绝，原始查询快速失败而非无限递归
+        let loops_before = METRICS.forwarding_loops_detected_total().get();
+        let query = create_test_query("loop.example.com", RecordType::A);
+        let request = build_http_request(
+            Method::POST,
+            "/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = tokio::time::timeout(Duration::from_secs(10), app.clone().oneshot(request))
+            .await
+            .expect("Forwarding loop must not hang")
+            .unwrap();
+        assert!(response.status().is_server_error());
+        assert!(METRICS.forwarding_loops_detected_total().get() > loops_before);
+        
+        // 自身主机名由本服务直接应答对应族的地址
+        let response = send(app.clone(), "DOH.example.com", RecordType::A).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].data().and_then(|d| d.as_a()).map(|a| a.0), Some(std::net::Ipv4Addr::new(192, 0, 2, 53)));
+        let response = send(app.clone(), "doh.example.com", RecordType::AAAA).await;
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(
+            response.answers()[0].data().and_then(|d| d.as_aaaa()).map(|a| a.0),
+            Some("2001:db8::53".parse::<std::net::Ipv6Addr>().unwrap())
+        );
+        
+        // 未配置自身地址时，对自身主机名的查询返回 REFUSED
+        let mut config = create_test_config();
+        config.dns.loop_protection.self_names = vec!["doh.example.com.".to_string()];
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        let response = send(app, "doh.example.com", RecordType::A).await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        assert!(response.answers().is_empty());
+        
+        info!("Test completed: test_doh_handler_loop_protection");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_local_records() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_local_records");
+        
+        // 上游不可达，任何泄露至上游的查询都会失败
+        let build_app = |policy: &'static str| async move {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "http://127.0.0.1:1/dns-query"
+                    protocol: doh
+                query_timeout: 1
+                enable_dnssec: false
+              http_client:
+                timeout: 1
+              cache:
+                enabled: false
+              local_records:
+                ttl: 60
+                ipv4_only_aaaa: {}
+                records:
+                  - name: "nas.home.lan"
+                    addresses: ["192.168.1.10"]
+                  - name: "printer.home.lan."
+                    addresses: ["192.168.1.20", "fd00::20"]
+            "#, policy);
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            app
+        };
+        let send = |app: axum::Router, name: &'static str, record_type: RecordType| async move {
+            let query = create_test_query(name, record_type);
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            decode_dns_response(&body_bytes).await.unwrap()
+        };
+        
+        let app = build_app("nodata").await;
+        
+        // A 查询返回配置的 IPv4 地址
+        let response = send(app.clone(), "NAS.home.lan", RecordType::A).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].ttl(), 60);
+        assert_eq!(response.answers()[0].data().and_then(|d| d.as_a()).map(|a| a.0), Some(std::net::Ipv4Addr::new(192, 168, 1, 10)));
+        // 默认统一响应头标志时本地记录的应答仍保留 AA 位
+        assert!(response.authoritative());
+        assert!(response.recursion_available());
+        
+        // 仅定义 IPv4 地址时 AAAA 查询返回 NODATA，不转发至上游
+        let response = send(app.clone(), "nas.home.lan", RecordType::AAAA).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(response.answers().is_empty());
+        
+        // 定义了 IPv6 地址时 AAAA 查询返回该地址
+        let response = send(app.clone(), "printer.home.lan", RecordType::AAAA).await;
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(
+            response.answers()[0].data().and_then(|d| d.as_aaaa()).map(|a| a.0),
+            Some("fd00::20".parse::<std::net::Ipv6Addr>().unwrap())
+        );
+        
+        // 其他类型同样返回 NODATA
+        let response = send(app.clone(), "nas.home.lan", RecordType::MX).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(response.answers().is_empty());
+        
+        // synthesize 策略以默认 DNS64 前缀合成 AAAA 记录
+        let app = build_app("synthesize").await;
+        let response = send(app.clone(), "nas.home.lan", RecordType::AAAA).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(
+            response.answers()[0].data().and_then(|d| d.as_aaaa()).map(|a| a.0),
+            Some("64:ff9b::c0a8:10a".parse::<std::net::Ipv6Addr>().unwrap())
+        );
+        
+        // 非 /96 前缀被配置校验拒绝
+        let mut config = create_test_config();
+        assert!(config.test().is_ok());
+        config.dns.local_records.dns64_prefix = "64:ff9b::1:2".parse().unwrap();
+        assert!(config.test().is_err());
+        
+        info!("Test completed: test_doh_handler_local_records");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_refresh_failure_grace() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_refresh_failure_grace");
+        
+        // 模拟抖动的上游：可用时按成功次数应答 1.1.1.N，不可用时返回 HTTP 500
+        use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+        let upstream_up = Arc::new(AtomicBool::new(true));
+        let successes = Arc::new(AtomicU8::new(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            let upstream_up = upstream_up.clone();
+            let successes = successes.clone();
+            let calls = calls.clone();
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(move |req: &wiremock::Request| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    if !upstream_up.load(Ordering::SeqCst) {
+                        return ResponseTemplate::new(500);
+                    }
+                    
+                    let octet = successes.fetch_add(1, Ordering::SeqCst) + 1;
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let response = crate::server::mock_http_server::create_test_response(
+                        &query, std::net::Ipv4Addr::new(1, 1, 1, octet)
+                    );
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        // 缓存条目 1 秒后过期，刷新失败后保留 2 秒（时间以秒计，等待时长按边界留出余量）
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+            ttl:
+              min: 1
+              max: 1
+            refresh_failure_grace_secs: 2
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        let domain = "flapping.example.com";
+        
+        // 首次查询写入缓存
+        assert_eq!(query_first_answer(&app, "/dns-query", domain).await, "1.1.1.1");
+        
+        // 条目过期后刷新失败：保留原条目并应答
+        upstream_up.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        let retained_before = METRICS.cache_refresh_failure_retained_total().get();
+        assert_eq!(query_first_answer(&app, "/dns-query", domain).await, "1.1.1.1");
+        assert!(METRICS.cache_refresh_failure_retained_total().get() > retained_before);
+        
+        // 宽限期内的查询直接命中缓存，不再冲击失败的上游
+        let calls_during_grace = calls.load(Ordering::SeqCst);
+        assert_eq!(query_first_answer(&app, "/dns-query", domain).await, "1.1.1.1");
+        assert_eq!(calls.load(Ordering::SeqCst), calls_during_grace);
+        
+        // 上游恢复后宽限期结束，刷新成功得到新应答
+        upstream_up.store(true, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(3100)).await;
+        assert_eq!(query_first_answer(&app, "/dns-query", domain).await, "1.1.1.2");
+        
+        // 再次刷新失败：成功刷新后宽限期重新计时，新条目同样被保留
+        upstream_up.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        assert_eq!(query_first_answer(&app, "/dns-query", domain).await, "1.1.1.2");
+        
+        // 持续失败超过宽限期后不再保留，按上游失败处理
+        tokio::time::sleep(Duration::from_millis(3100)).await;
+        let query = create_test_query(domain, RecordType::A);
+        let request = build_http_request(
+            Method::POST,
+            "/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            query.to_vec().unwrap()
+        );
+        let response = app.oneshot(request).await.unwrap();
+        assert!(response.status().is_server_error());
+        
+        info!("Test completed: test_doh_handler_refresh_failure_grace");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_answer_sort() {
+        use std::net::{Ipv4Addr, Ipv6Addr};
+        use hickory_proto::rr::{RData, Record};
+        use hickory_proto::rr::rdata::{A, AAAA, CNAME};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_answer_sort");
+        
+        // 模拟上游：CNAME 之后 A 与 AAAA 交错排列
+        let mock_upstream = MockServer::start().await;
+        {
+            use wiremock::{Mock, ResponseTemplate};
+            use wiremock::matchers::{method, path};
+            
+            Mock::given(method("POST"))
+                .and(path("/dns-query"))
+                .respond_with(|req: &wiremock::Request| {
+                    let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                    let name = query.queries()[0].name().clone();
+                    let target = Name::from_ascii("mixed.cdn.example.net.").unwrap();
+                    let mut response = Message::new();
+                    response.set_id(query.id())
+                        .set_message_type(MessageType::Response)
+                        .set_recursion_available(true)
+                        .add_queries(query.queries().to_vec())
+                        .add_answer(Record::from_rdata(name, 300, RData::CNAME(CNAME(target.clone()))))
+                        .add_answer(Record::from_rdata(target.clone(), 300, RData::AAAA(AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))))
+                        .add_answer(Record::from_rdata(target.clone(), 300, RData::A(A(Ipv4Addr::new(192, 0, 2, 1)))))
+                        .add_answer(Record::from_rdata(target.clone(), 300, RData::AAAA(AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2)))))
+                        .add_answer(Record::from_rdata(target, 300, RData::A(A(Ipv4Addr::new(192, 0, 2, 2)))));
+                    
+                    ResponseTemplate::new(200)
+                        .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                        .set_body_bytes(response.to_vec().unwrap())
+                })
+                .mount(&mock_upstream)
+                .await;
+        }
+        
+        // 应答节记录类型序列
+        let record_types = |message: &Message| message.answers().iter().map(|r| r.record_type()).collect::<Vec<_>>();
+        let upstream_order = vec![RecordType::CNAME, RecordType::AAAA, RecordType::A, RecordType::AAAA, RecordType::A];
+        
+        for (answer_sort, expected) in [
+            ("v4_first", vec![RecordType::CNAME, RecordType::A, RecordType::A, RecordType::AAAA, RecordType::AAAA]),
+            ("v6_first", vec![RecordType::CNAME, RecordType::AAAA, RecordType::AAAA, RecordType::A, RecordType::A]),
+            ("none", upstream_order.clone()),
+        ] {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: true
+              answer_sort: "{}"
+            "#, mock_upstream.uri(), answer_sort);
+            
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let query = create_test_query("mixed.example.com", RecordType::A);
+            for round in ["upstream", "cache hit"] {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.clone().oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                let response = decode_dns_response(&body_bytes).await.unwrap();
+                assert_eq!(record_types(&response), expected, "{} ({})", answer_sort, round);
+                
+                // 同族记录保持上游的相对顺序
+                let first_a = response.answers().iter().find_map(|r| match r.data() {
+                    Some(RData::A(a)) => Some(a.0),
+                    _ => None,
+                });
+                assert_eq!(first_a, Some(Ipv4Addr::new(192, 0, 2, 1)));
+            }
+            
+            // 缓存中的条目保持上游顺序
+            let key = CacheKey::new(Name::from_ascii("mixed.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+            let cached = cache.get(&key).await.expect("Response should be cached");
+            assert_eq!(record_types(&cached), upstream_order, "Cache entry must not be reordered");
+        }
+        
+        info!("Test completed: test_doh_handler_answer_sort");
+    }
+    
+    // 测试 default_action 为 refuse 时未匹配的域名返回 REFUSED（仅解析显式路由的域名）
+    #[tokio::test]
+    async fn test_doh_handler_default_action_refuse() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_default_action_refuse");
+        
+        // 模拟允许列表上游组
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 10)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "127.0.0.1:9"
+                protocol: udp
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          emit_ede: true
+          block_reason_header: true
+          routing:
+            enabled: true
+            default_action: refuse
+            upstream_groups:
+              - name: "allowed"
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+            rules:
+              - match:
+                  type: exact
+                  values: ["allowed.example.com"]
+                upstream_group: "allowed"
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let send = |domain: &'static str| {
+            let app = app.clone();
+            async move {
+                let query = create_test_query(domain, RecordType::A);
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let block_reason = response.headers().get(HEADER_BLOCK_REASON)
+                    .map(|v| v.to_str().unwrap().to_string());
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                (block_reason, decode_dns_response(&body_bytes).await.unwrap())
+            }
+        };
+        
+        // 显式路由的域名正常解析
+        let (block_reason, response) = send("allowed.example.com").await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        assert!(block_reason.is_none());
+        
+        // 未匹配的域名不经全局上游，直接返回 REFUSED 并附带 EDE "Filtered"，阻止原因区别于规则阻止
+        for _ in 0..2 {
+            let (block_reason, response) = send("unlisted.example.org").await;
+            assert_eq!(block_reason.as_deref(), Some(BLOCK_REASON_DEFAULT_ACTION));
+            assert_eq!(response.response_code(), ResponseCode::Refused);
+            assert!(response.answers().is_empty());
+            let ede = ExtendedError::from_message(&response)
+                .expect("Refused response should carry an EDE option");
+            assert_eq!(ede.info_code, EDE_INFO_CODE_FILTERED);
+        }
+        
+        info!("Test completed: test_doh_handler_default_action_refuse");
+    }
+    
+    // 测试每个 IP 的每日查询配额：超出后返回 REFUSED 并附带 EDE，不影响其他 IP，跨重置时刻后恢复
+    // 按连接源地址计数，仅信任受信任代理的请求头，IPv6 地址按 /64 聚合
+    #[tokio::test]
+    async fn test_doh_handler_daily_query_quota() {
+        use axum::extract::ConnectInfo;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_daily_query_quota");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 20)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: true
+            per_ip_rate: 1000
+            per_ip_concurrent: 100
+            per_ip_daily_quota: 2
+            quota_reset_hour: 4
+            quota_trusted_proxies: ["10.0.0.1/32"]
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+          emit_ede: true
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let rate_limit = config.http.rate_limit.clone();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let send_via = |peer_ip: &'static str, forwarded_for: &'static str| {
+            let app = app.clone();
+            async move {
+                let query = create_test_query("quota.example.com", RecordType::A);
+                let mut request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE), ("X-Forwarded-For", forwarded_for)],
+                    query.to_vec().unwrap()
+                );
+                let peer = std::net::SocketAddr::new(peer_ip.parse().unwrap(), 40000);
+                request.extensions_mut().insert(ConnectInfo(peer));
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        let send = |client_ip: &'static str| send_via(client_ip, client_ip);
+        
+        // 配额内的查询正常解析
+        for _ in 0..2 {
+            let response = send("198.51.100.7").await;
+            assert_eq!(response.response_code(), ResponseCode::NoError);
+            assert_eq!(response.answers().len(), 1);
+        }
+        
+        // 超出配额后返回 REFUSED 并附带 EDE，同时记录指标
+        let exceeded_before = METRICS.query_quota_exceeded_total().get();
+        let response = send("198.51.100.7").await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        assert!(response.answers().is_empty());
+        let ede = ExtendedError::from_message(&response)
+            .expect("Quota refusal should carry an EDE option");
+        assert_eq!(ede.info_code, EDE_INFO_CODE_PROHIBITED);
+        assert!(METRICS.query_quota_exceeded_total().get() > exceeded_before);
+        
+        // 非受信任代理的连接无法通过伪造 X-Forwarded-For 绕过配额
+        let response = send_via("198.51.100.7", "192.0.2.99").await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        
+        // 其他 IP 的配额独立计算，且伪造请求头不会消耗被冒充 IP 的配额
+        let response = send_via("198.51.100.9", "198.51.100.8").await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        let response = send("198.51.100.8").await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        
+        // 来自受信任代理的请求按请求头中的客户端地址计数
+        for _ in 0..2 {
+            let response = send_via("10.0.0.1", "203.0.113.50").await;
+            assert_eq!(response.response_code(), ResponseCode::NoError);
+        }
+        let response = send_via("10.0.0.1", "203.0.113.50").await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        let response = send_via("10.0.0.1", "203.0.113.51").await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        
+        // 同一 /64 内的 IPv6 地址共享配额
+        for peer_ip in ["2001:db8::1", "2001:db8::2"] {
+            let response = send(peer_ip).await;
+            assert_eq!(response.response_code(), ResponseCode::NoError);
+        }
+        let response = send("2001:db8::3").await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        let response = send("2001:db8:0:1::1").await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        
+        // 非法的受信任代理网段被配置校验拒绝
+        let mut invalid: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        invalid.http.rate_limit.quota_trusted_proxies = vec!["not-a-cidr".to_string()];
+        assert!(invalid.test().is_err());
+        
+        // 计数在每日重置时刻（UTC 04:00）后清零
+        let quota = DailyQueryQuota::new(&rate_limit).unwrap();
+        let client_ip: std::net::IpAddr = "203.0.113.9".parse().unwrap();
+        let day = 20_000 * 86400;
+        let before_reset = day + 4 * 3600 - 1;
+        assert!(quota.consume_at(client_ip, before_reset - 3600).await);
+        assert!(quota.consume_at(client_ip, before_reset).await);
+        assert!(!quota.consume_at(client_ip, before_reset).await);
+        assert!(quota.consume_at(client_ip, before_reset + 1).await, "Quota should reset at the configured hour");
+        
+        // 未启用速率限制或配额为 0 时不限制
+        assert!(DailyQueryQuota::disabled().consume_at(client_ip, before_reset).await);
+        
+        info!("Test completed: test_doh_handler_daily_query_quota");
+    }
+    
+    // 测试应答修改插件：示例脚本改写指定域名的应答，其他查询不受影响，缓存条目保持上游应答
+    #[cfg(feature = "plugins")]
+    #[tokio::test]
+    async fn test_doh_handler_response_plugin() {
+        use hickory_proto::rr::RData;
+        use oxide_wdns::server::config::PluginConfig;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_response_plugin");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 30)).await;
+        
+        let build_app = |script_path: String| {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: true
+              plugins:
+                enabled: true
+                script_path: "{}"
+            "#, mock_upstream.uri(), script_path);
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            async move { DoHServer::new(config, false).build_application_components().await.unwrap() }
+        };
+        let send = |app: axum::Router, domain: &'static str| async move {
+            let query = create_test_query(domain, RecordType::A);
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            decode_dns_response(&body_bytes).await.unwrap()
+        };
+        let first_a = |response: &Message| response.answers().iter().find_map(|r| match r.data() {
+            Some(RData::A(a)) => Some(a.0),
+            _ => None,
+        });
+        let plugin_results = |result: &str| METRICS.plugin_invocations_total().with_label_values(&[result]).get();
+        
+        // 示例插件：改写 intranet.example.com 的 A 记录并附带 EDE，首次查询与缓存命中均生效
+        let sample = format!("{}/examples/plugins/rewrite.rhai", env!("CARGO_MANIFEST_DIR"));
+        let (app, cache) = build_app(sample).await;
+        let modified_before = plugin_results("modified");
+        for _ in 0..2 {
+            let response = send(app.clone(), "intranet.example.com").await;
+            assert_eq!(response.response_code(), ResponseCode::NoError);
+            assert_eq!(response.answers().len(), 1);
+            assert_eq!(first_a(&response), Some(std::net::Ipv4Addr::new(10, 0, 0, 10)));
+            let ede = ExtendedError::from_message(&response).expect("Rewritten response should carry an EDE option");
+            assert_eq!(ede.info_code, 4);
+        }
+        assert!(plugin_results("modified") >= modified_before + 2);
+        
+        // 缓存中的条目保持上游应答
+        let key = CacheKey::new(Name::from_ascii("intranet.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+        let cached = cache.get(&key).await.expect("Response should be cached");
+        assert_eq!(first_a(&cached), Some(std::net::Ipv4Addr::new(192, 0, 2, 30)));
+        
+        // 未列出的域名不受影响
+        let response = send(app.clone(), "public.example.com").await;
+        assert_eq!(first_a(&response), Some(std::net::Ipv4Addr::new(192, 0, 2, 30)));
+        assert!(ExtendedError::from_message(&response).is_none());
+        
+        // 脚本运行出错时返回原应答并记录错误
+        let temp_dir = tempfile::tempdir().unwrap();
+        let broken = temp_dir.path().join("broken.rhai");
+        std::fs::write(&broken, "fn on_response(query, response) { response.answers = [#{ name: query.name, type: \"A\", ttl: 60, data: \"not-an-ip\" }]; response }").unwrap();
+        let (app, _cache) = build_app(broken.display().to_string()).await;
+        let errors_before = plugin_results("error");
+        let response = send(app, "broken.example.com").await;
+        assert_eq!(first_a(&response), Some(std::net::Ipv4Addr::new(192, 0, 2, 30)));
+        assert!(plugin_results("error") > errors_before);
+        
+        // 未定义 on_response 的脚本在启动时报错
+        let missing = temp_dir.path().join("missing.rhai");
+        std::fs::write(&missing, "fn on_query(query) { () }").unwrap();
+        let config = PluginConfig { enabled: true, script_path: missing.display().to_string(), ..Default::default() };
+        assert!(ResponsePlugin::new(&config).is_err());
+        
+        info!("Test completed: test_doh_handler_response_plugin");
+    }
+    
+    // 测试未包含 plugins 编译特性时启用插件的配置验证失败
+    #[cfg(not(feature = "plugins"))]
+    #[test]
+    fn test_plugins_require_feature() {
+        let config_str = r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "8.8.8.8:53"
+                protocol: udp
+          plugins:
+            enabled: true
+            script_path: "./examples/plugins/rewrite.rhai"
+        "#;
+        let config: ServerConfig = serde_yaml::from_str(config_str).unwrap();
+        assert!(config.test().is_err(), "Enabling plugins without the feature should fail validation");
+        assert!(ResponsePlugin::new(&config.dns.plugins).is_err());
+    }
+    
+    // 测试 GET 请求的 Accept 头与 get_accept 策略的组合：路径优先、协商 JSON 或返回 406
+    #[tokio::test]
+    async fn test_doh_handler_get_accept_policy() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_get_accept_policy");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 40)).await;
+        
+        let dns_param = encode_dns_message_base64url(&create_test_query("accept.example.com", RecordType::A));
+        let uri = format!("/dns-query?dns={}", dns_param);
+        
+        // (策略, Accept 头, 期望状态码, 期望内容类型)
+        let cases: Vec<(&str, Option<&str>, StatusCode, Option<&str>)> = vec![
+            ("ignore", None, StatusCode::OK, Some(CONTENT_TYPE_DNS_MESSAGE)),
+            ("ignore", Some(CONTENT_TYPE_DNS_JSON), StatusCode::OK, Some(CONTENT_TYPE_DNS_MESSAGE)),
+            ("negotiate", None, StatusCode::OK, Some(CONTENT_TYPE_DNS_MESSAGE)),
+            ("negotiate", Some(CONTENT_TYPE_DNS_JSON), StatusCode::OK, Some(CONTENT_TYPE_DNS_JSON)),
+            ("negotiate", Some("application/dns-json, application/dns-message;q=0.5"), StatusCode::OK, Some(CONTENT_TYPE_DNS_MESSAGE)),
+            ("negotiate", Some("application/dns-message;q=0, application/dns-json"), StatusCode::OK, Some(CONTENT_TYPE_DNS_JSON)),
+            ("negotiate", Some("*/*"), StatusCode::OK, Some(CONTENT_TYPE_DNS_MESSAGE)),
+            ("negotiate", Some("text/html"), StatusCode::OK, Some(CONTENT_TYPE_DNS_MESSAGE)),
+            ("reject", Some(CONTENT_TYPE_DNS_MESSAGE), StatusCode::OK, Some(CONTENT_TYPE_DNS_MESSAGE)),
+            ("reject", Some(CONTENT_TYPE_DNS_JSON), StatusCode::NOT_ACCEPTABLE, None),
+            ("reject", Some("text/html"), StatusCode::NOT_ACCEPTABLE, None),
+        ];
+        
+        for (policy, accept, expected_status, expected_content_type) in cases {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              get_accept: {}
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: false
+            "#, policy, mock_upstream.uri());
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let headers = accept.map(|accept| vec![("Accept", accept)]).unwrap_or_default();
+            let request = build_http_request(Method::GET, &uri, headers, vec![]);
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected_status, "policy={} accept={:?}", policy, accept);
+            
+            let content_type = response.headers().get(header::CONTENT_TYPE).map(|v| v.to_str().unwrap().to_string());
+            let vary = response.headers().get(header::VARY).map(|v| v.to_str().unwrap().to_string());
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            let Some(expected_content_type) = expected_content_type else {
+                continue;
+            };
+            assert_eq!(content_type.as_deref(), Some(expected_content_type), "policy={} accept={:?}", policy, accept);
+            assert_eq!(vary.is_some(), policy != "ignore");
+            
+            // 两种格式携带相同的应答
+            if expected_content_type == CONTENT_TYPE_DNS_JSON {
+                let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+                assert_eq!(json["status"], 0);
+                assert_eq!(json["answer"][0]["data"], "192.0.2.40");
+            } else {
+                let message = decode_dns_response(&body_bytes).await.unwrap();
+                assert_eq!(message.answers().len(), 1);
+            }
+        }
+        
+        info!("Test completed: test_doh_handler_get_accept_policy");
+    }
+    
+    // 测试 DNS 查询错误计数携带请求格式标签，可按格式计算错误率
+    #[tokio::test]
+    async fn test_doh_handler_error_metrics_format_label() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_error_metrics_format_label");
+        
+        let config = create_test_config();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        let queries = |status: &str, format: &str| {
+            METRICS.dns_queries_total().with_label_values(&["A", status, format]).get()
+        };
+        let unknown_queries = |status: &str, format: &str| {
+            METRICS.dns_queries_total().with_label_values(&["Unknown", status, format]).get()
+        };
+        
+        // JSON API 参数错误计入 json 格式
+        let json_errors_before = queries("parameter_error", "json");
+        let wire_errors_before = queries("parameter_error", "wire");
+        let request = build_http_request(Method::GET, "/resolve?name=bad..example.com", vec![], vec![]);
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(queries("parameter_error", "json"), json_errors_before + 1);
+        assert_eq!(queries("parameter_error", "wire"), wire_errors_before);
+        
+        // wire 格式的解析错误与内容类型错误计入 wire 格式
+        let parse_errors_before = unknown_queries("parse_error", "wire");
+        let request = build_http_request(
+            Method::POST,
+            "/dns-query",
+            vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+            vec![0x12, 0x34]
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(unknown_queries("parse_error", "wire"), parse_errors_before + 1);
+        
+        let content_type_errors_before = unknown_queries("invalid_content_type", "wire");
+        let request = build_http_request(Method::POST, "/dns-query", vec![("Content-Type", "text/plain")], vec![0x00]);
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(unknown_queries("invalid_content_type", "wire"), content_type_errors_before + 1);
+        
+        info!("Test completed: test_doh_handler_error_metrics_format_label");
+    }
+    
+    // 测试启用 advertise_alt_svc 时 DoH 应答携带 Alt-Svc 头，健康检查等非 DoH 路由不携带
+    #[tokio::test]
+    async fn test_doh_handler_advertise_alt_svc() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_advertise_alt_svc");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 41)).await;
+        
+        let build_app = |advertise_alt_svc: &str| {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              advertise_alt_svc: '{}'
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: false
+            "#, advertise_alt_svc, mock_upstream.uri());
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            DoHServer::new(config, false)
+        };
+        let alt_svc = |response: &axum::response::Response| {
+            response.headers().get(header::ALT_SVC).map(|v| v.to_str().unwrap().to_string())
+        };
+        
+        let dns_param = encode_dns_message_base64url(&create_test_query("altsvc.example.com", RecordType::A));
+        let wire_get = || build_http_request(Method::GET, &format!("/dns-query?dns={}", dns_param), vec![], vec![]);
+        
+        // 启用时 wire 与 JSON 格式的 DoH 应答均携带 Alt-Svc
+        let (app, _cache) = build_app(r#"h3=":443"; ma=86400"#).build_application_components().await.unwrap();
+        let response = app.clone().oneshot(wire_get()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(alt_svc(&response).as_deref(), Some(r#"h3=":443"; ma=86400"#));
+        
+        let request = build_http_request(Method::GET, "/resolve?name=altsvc.example.com&type=A", vec![], vec![]);
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(alt_svc(&response).as_deref(), Some(r#"h3=":443"; ma=86400"#));
+        
+        let request = build_http_request(Method::GET, "/health", vec![], vec![]);
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(alt_svc(&response), None);
+        
+        // 默认不通告
+        let (app, _cache) = build_app("").build_application_components().await.unwrap();
+        let response = app.oneshot(wire_get()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(alt_svc(&response), None);
+        
+        // 非法的头值在配置校验时被拒绝
+        let mut config = create_test_config();
+        config.http.advertise_alt_svc = "h3=\":443\"\n".to_string();
+        let err = config.test().expect_err("Invalid advertise_alt_svc should be rejected");
+        assert!(err.to_string().contains("advertise_alt_svc"), "Unexpected error: {}", err);
+        
+        info!("Test completed: test_doh_handler_advertise_alt_svc");
+    }
+    
+    // 测试返回给客户端的记录 TTL 被限制在 ttl.response_max 以内（默认 7 天），0 表示不限制
+    #[tokio::test]
+    async fn test_doh_handler_response_max_ttl() {
+        use hickory_proto::rr::{RData, Record};
+        use hickory_proto::rr::rdata::{A, NS};
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_response_max_ttl");
+        
+        // 上游应答携带 10 年 TTL 的 A 记录与授权节 NS 记录，以及 60 秒 TTL 的 A 记录
+        const TEN_YEARS: u32 = 10 * 365 * 86400;
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let name = query.queries()[0].name().clone();
+                let mut response = query.clone();
+                response.set_message_type(MessageType::Response);
+                response.add_answer(Record::from_rdata(name.clone(), TEN_YEARS, RData::A(A::new(192, 0, 2, 50))));
+                response.add_answer(Record::from_rdata(name.clone(), 60, RData::A(A::new(192, 0, 2, 51))));
+                response.add_name_server(Record::from_rdata(
+                    Name::from_ascii("example.com.").unwrap(),
+                    TEN_YEARS,
+                    RData::NS(NS(Name::from_ascii("ns.example.com.").unwrap())),
+                ));
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        // (ttl 配置, 期望的长 TTL)
+        let cases = [("", 604800), ("response_max: 3600", 3600), ("response_max: 0", TEN_YEARS)];
+        for (ttl_yaml, expected_ttl) in cases {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: false
+                ttl:
+                  negative: 60
+                  {}
+            "#, mock_upstream.uri(), ttl_yaml);
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let query = create_test_query("ttl-cap.example.com", RecordType::A);
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+            let message = decode_dns_response(&body_bytes).await.unwrap();
+            
+            let ttls: Vec<u32> = message.answers().iter().map(|record| record.ttl()).collect();
+            assert_eq!(ttls, vec![expected_ttl, 60], "ttl config: {:?}", ttl_yaml);
+            assert_eq!(message.name_servers()[0].ttl(), expected_ttl, "ttl config: {:?}", ttl_yaml);
+            
+            // JSON API 同样受限
+            let request = build_http_request(Method::GET, "/resolve?name=ttl-cap.example.com&type=A", vec![], vec![]);
+            let response = app.oneshot(request).await.unwrap();
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+            assert_eq!(json["answer"][0]["ttl"], expected_ttl, "ttl config: {:?}", ttl_yaml);
+        }
+        
+        info!("Test completed: test_doh_handler_response_max_ttl");
+    }
+    
+    // 测试启用 dedup_answers 时移除上游应答中的重复记录，保留顺序，缓存存储去重后的应答
+    #[tokio::test]
+    async fn test_doh_handler_dedup_answers() {
+        use std::net::Ipv4Addr;
+        use hickory_proto::rr::{RData, Record};
+        use hickory_proto::rr::rdata::A;
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_dedup_answers");
+        
+        // 模拟上游：重复的 A 记录（TTL 或名称大小写不同仍视为重复）
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let name = query.queries()[0].name().clone();
+                let upper = Name::from_ascii(name.to_ascii().to_uppercase()).unwrap();
+                let mut response = Message::new();
+                response.set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_available(true)
+                    .add_queries(query.queries().to_vec())
+                    .add_answer(Record::from_rdata(name.clone(), 300, RData::A(A(Ipv4Addr::new(192, 0, 2, 1)))))
+                    .add_answer(Record::from_rdata(name.clone(), 300, RData::A(A(Ipv4Addr::new(192, 0, 2, 2)))))
+                    .add_answer(Record::from_rdata(name.clone(), 120, RData::A(A(Ipv4Addr::new(192, 0, 2, 1)))))
+                    .add_answer(Record::from_rdata(upper, 300, RData::A(A(Ipv4Addr::new(192, 0, 2, 2)))))
+                    .add_answer(Record::from_rdata(name, 300, RData::A(A(Ipv4Addr::new(192, 0, 2, 3)))));
+                
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        let addresses = |message: &Message| message.answers().iter().filter_map(|r| match r.data() {
+            Some(RData::A(a)) => Some(a.0.octets()[3]),
+            _ => None,
+        }).collect::<Vec<_>>();
+        
+        for (dedup_answers, expected) in [(true, vec![1, 2, 3]), (false, vec![1, 2, 1, 2, 3])] {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: true
+              dedup_answers: {}
+            "#, mock_upstream.uri(), dedup_answers);
+            
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let query = create_test_query("dedup.example.com", RecordType::A);
+            for round in ["upstream", "cache hit"] {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.clone().oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                let response = decode_dns_response(&body_bytes).await.unwrap();
+                assert_eq!(addresses(&response), expected, "dedup_answers={} ({})", dedup_answers, round);
+            }
+            
+            // 缓存中存储的是相同的（去重后的）应答
+            let key = CacheKey::new(Name::from_ascii("dedup.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+            let cached = cache.get(&key).await.expect("Response should be cached");
+            assert_eq!(addresses(&cached), expected, "dedup_answers={} (cache entry)", dedup_answers);
+        }
+        
+        info!("Test completed: test_doh_handler_dedup_answers");
+    }
+    
+    // 测试 JSON API 在构建查询前拒绝超长域名、不支持的查询类型与超大请求
+    #[tokio::test]
+    async fn test_doh_handler_json_request_limits() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_json_request_limits");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 60)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+          query_limits:
+            max_json_request_size: 512
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let get = |uri: String| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(build_http_request(Method::GET, &uri, vec![], vec![])).await.unwrap();
+                let status = response.status();
+                let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+                (status, String::from_utf8_lossy(&body).to_string())
+            }
+        };
+        
+        // 正常请求不受影响
+        let (status, _) = get("/resolve?name=limits.example.com&type_value=1".to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        
+        // 253 个字符的域名可用，超出时返回 400
+        let longest = format!("{0}.{0}.{0}.{0}.{1}", "a".repeat(60), "b".repeat(9));
+        assert_eq!(longest.len(), 253);
+        let (status, _) = get(format!("/resolve?name={}.", longest)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = get(format!("/resolve?name=c{}", longest)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("Domain name length 254"), "Unexpected body: {}", body);
+        
+        // 区域传送与元类型被拒绝
+        for type_value in [0, 41, 250, 251, 252, 254] {
+            let (status, body) = get(format!("/resolve?name=limits.example.com&type_value={}", type_value)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "type_value={}", type_value);
+            assert!(body.contains("Unsupported query type"), "Unexpected body: {}", body);
+        }
+        
+        // 查询字符串超出 max_json_request_size 时返回 400
+        let (status, body) = get(format!("/resolve?name=limits.example.com&padding={}", "x".repeat(512))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("exceeds limit of 512 bytes"), "Unexpected body: {}", body);
+        
+        info!("Test completed: test_doh_handler_json_request_limits");
+    }
+    
+    // 测试多问题查询中部分问题的上游失败时仍返回成功问题的记录，并以响应码与 EDE 指示部分结果
+    #[tokio::test]
+    async fn test_doh_handler_multi_question_partial_results() {
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_multi_question_partial_results");
+        
+        // 模拟上游：名称以 fail 开头的查询返回 HTTP 500
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                if query.queries()[0].name().to_ascii().starts_with("fail") {
+                    return ResponseTemplate::new(500);
+                }
+                let response = crate::server::mock_http_server::create_test_response(&query, std::net::Ipv4Addr::new(192, 0, 2, 70));
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        // (partial_results, emit_ede, 期望响应码)
+        let cases = [
+            (true, true, ResponseCode::NoError),
+            (false, true, ResponseCode::ServFail),
+            (true, false, ResponseCode::NoError),
+            (false, false, ResponseCode::ServFail),
+        ];
+        for (partial_results, emit_ede, expected_rcode) in cases {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: false
+              allow_multi_question: true
+              partial_results: {}
+              emit_ede: {}
+            "#, mock_upstream.uri(), partial_results, emit_ede);
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let mut query = create_test_query("ok.example.com.", RecordType::A);
+            query.add_query(hickory_proto::op::Query::query(Name::from_ascii("fail.example.com.").unwrap(), RecordType::A));
+            query.add_query(hickory_proto::op::Query::query(Name::from_ascii("also-ok.example.com.").unwrap(), RecordType::A));
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            let response = decode_dns_response(&body_bytes).await.unwrap();
+            
+            let case = format!("partial_results={} emit_ede={}", partial_results, emit_ede);
+            assert_eq!(response.response_code(), expected_rcode, "{}", case);
+            assert_eq!(response.queries().len(), 3, "{}", case);
+            let mut answer_names: Vec<String> = response.answers().iter().map(|r| r.name().to_string()).collect();
+            answer_names.sort();
+            assert_eq!(answer_names, vec!["also-ok.example.com.", "ok.example.com."], "{}", case);
+            
+            let ede = ExtendedError::from_message(&response);
+            if emit_ede {
+                let ede = ede.expect("Partial results should carry an EDE");
+                assert_eq!(ede.info_code, 22);
+                assert_eq!(ede.extra_text, "1 of 3 questions could not be resolved");
+            } else {
+                assert!(ede.is_none(), "{}", case);
+            }
+        }
+        
+        info!("Test completed: test_doh_handler_multi_question_partial_results");
+    }
+    
+    // 测试启用 strip_opt_for_non_edns_clients 时，未携带 OPT 的查询得到不含 OPT 的应答
+    #[tokio::test]
+    async fn test_doh_handler_strip_opt_for_non_edns_clients() {
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_strip_opt_for_non_edns_clients");
+        
+        // 模拟上游：应答始终携带 OPT 记录
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let mut response = crate::server::mock_http_server::create_test_response(&query, std::net::Ipv4Addr::new(192, 0, 2, 80));
+                let mut edns = Edns::new();
+                edns.set_max_payload(1232);
+                response.set_edns(edns);
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        // (启用剥离, 查询携带 OPT, 期望应答携带 OPT)
+        let cases = [(true, false, false), (true, true, true), (false, false, true)];
+        for (strip, client_edns, expect_opt) in cases {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: false
+              strip_opt_for_non_edns_clients: {}
+            "#, mock_upstream.uri(), strip);
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let mut query = create_test_query("legacy.example.com", RecordType::A);
+            if client_edns {
+                query.set_edns(Edns::new());
+            }
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            let response = decode_dns_response(&body_bytes).await.unwrap();
+            
+            assert_eq!(response.answers().len(), 1);
+            assert_eq!(
+                response.extensions().is_some(), expect_opt,
+                "strip={} client_edns={}", strip, client_edns
+            );
+        }
+        
+        info!("Test completed: test_doh_handler_strip_opt_for_non_edns_clients");
+    }
+    
+    // 测试问题节标签与名称长度限制（RFC 1035）
+    #[tokio::test]
+    async fn test_doh_handler_name_length_limits() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_name_length_limits");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 61)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 按给定标签直接构造线格式查询（ID 4321，RD，A/IN），绕过名称构建时的校验
+        let raw_query = |labels: &[Vec<u8>]| {
+            let mut wire = vec![0x10, 0xE1, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+            for label in labels {
+                wire.push(label.len() as u8);
+                wire.extend_from_slice(label);
+            }
+            wire.extend_from_slice(&[0, 0, 1, 0, 1]);
+            wire
+        };
+        let post = |wire: Vec<u8>| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    wire
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        let rejected = |reason: &str| METRICS.oversized_queries_rejected_total().with_label_values(&[reason]).get();
+        let label_rejected_before = rejected("label_length");
+        let name_rejected_before = rejected("name_length");
+        
+        // 63 字节的标签正常解析
+        let response = post(raw_query(&[vec![b'a'; 63], b"example".to_vec(), b"com".to_vec()])).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        
+        // 超过 63 字节的标签返回 FORMERR，保留查询 ID
+        let response = post(raw_query(&[vec![b'a'; 64], b"example".to_vec(), b"com".to_vec()])).await;
+        assert_eq!(response.response_code(), ResponseCode::FormErr);
+        assert_eq!(response.id(), 4321);
+        assert!(response.recursion_desired());
+        
+        // 超过 255 字节的名称返回 FORMERR（GET 请求同样适用）
+        let long_name = raw_query(&vec![vec![b'a'; 63]; 5]);
+        let response = post(long_name.clone()).await;
+        assert_eq!(response.response_code(), ResponseCode::FormErr);
+        let request = build_http_request(
+            Method::GET,
+            &format!("/dns-query?dns={}", BASE64_ENGINE.encode(&long_name)),
+            vec![("Accept", CONTENT_TYPE_DNS_MESSAGE)],
+            vec![]
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+        let response = decode_dns_response(&body_bytes).await.unwrap();
+        assert_eq!(response.response_code(), ResponseCode::FormErr);
+        assert_eq!(response.id(), 4321);
+        
+        assert_eq!(rejected("label_length"), label_rejected_before + 1);
+        assert_eq!(rejected("name_length"), name_rejected_before + 2);
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 1, "Rejected queries should not reach upstream");
+        
+        // JSON API 拒绝超过 63 字节的标签
+        let request = build_http_request(
+            Method::GET,
+            &format!("/resolve?name={}.example.com", "a".repeat(64)),
+            vec![],
+            vec![]
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("label length 64"));
+        
+        info!("Test completed: test_doh_handler_name_length_limits");
+    }
+    
+    // 测试上游组级别的缓存开关与 TTL 覆盖
+    #[tokio::test]
+    async fn test_doh_handler_per_group_cache() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_per_group_cache");
+        
+        let dynamic_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&dynamic_upstream, std::net::Ipv4Addr::new(10, 0, 1, 1)).await;
+        let stable_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&stable_upstream, std::net::Ipv4Addr::new(10, 0, 1, 2)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{0}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          routing:
+            enabled: true
+            upstream_groups:
+              - name: "dynamic"
+                cache: false
+                resolvers:
+                  - address: "{0}/dns-query"
+                    protocol: doh
+              - name: "stable"
+                cache_max_ttl: 60
+                resolvers:
+                  - address: "{1}/dns-query"
+                    protocol: doh
+            rules:
+              - match:
+                  type: exact
+                  values: ["dynamic.example.com"]
+                upstream_group: "dynamic"
+              - match:
+                  type: exact
+                  values: ["stable.example.com"]
+                upstream_group: "stable"
+        "#, dynamic_upstream.uri(), stable_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        assert_eq!(config.get_effective_cache_ttl("stable"), (config.dns.cache.ttl.min, 60));
+        assert!(!config.group_cache_enabled("dynamic"));
+        let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let query_ttl = |domain: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    create_test_query(domain, RecordType::A).to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                let response = decode_dns_response(&body_bytes).await.unwrap();
+                assert_eq!(response.answers().len(), 1);
+                response.answers()[0].ttl()
+            }
+        };
+        
+        // 关闭缓存的组：每次查询都转发至上游，应答不写入缓存
+        for _ in 0..3 {
+            query_ttl("dynamic.example.com").await;
+        }
+        assert_eq!(dynamic_upstream.received_requests().await.unwrap().len(), 3);
+        assert_eq!(cache.len().await, 0, "No-cache group responses must never be stored");
+        
+        // 覆盖 TTL 的组：应答按组的最大 TTL 缓存，第二次查询命中缓存
+        for _ in 0..2 {
+            query_ttl("stable.example.com").await;
+        }
+        assert_eq!(stable_upstream.received_requests().await.unwrap().len(), 1);
+        assert_eq!(cache.len().await, 1);
+        let mut dump = cache.dump(oxide_wdns::server::cache::CacheDumpFormat::Json);
+        let entry: serde_json::Value = serde_json::from_str(&dump.recv().await.unwrap()).unwrap();
+        assert!(entry["ttl_remaining"].as_u64().unwrap() <= 60, "Unexpected entry: {}", entry);
+        
+        info!("Test completed: test_doh_handler_per_group_cache");
+    }
+    
+    // 测试应答记录 TTL 下限的改写与丢弃策略
+    #[tokio::test]
+    async fn test_doh_handler_answer_ttl_floor() {
+        use std::net::Ipv4Addr;
+        use hickory_proto::rr::{RData, Record};
+        use hickory_proto::rr::rdata::A;
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_answer_ttl_floor");
+        
+        // 模拟上游：mixed.example.com 返回 TTL 混合的记录，low.example.com 的记录 TTL 均低于下限
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let name = query.queries()[0].name().clone();
+                let ttls: &[u32] = if name.to_ascii().starts_with("mixed") { &[0, 300, 5] } else { &[0, 10] };
+                let mut response = Message::new();
+                response.set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_available(true)
+                    .add_queries(query.queries().to_vec());
+                for (index, ttl) in ttls.iter().enumerate() {
+                    let ip = Ipv4Addr::new(192, 0, 2, index as u8 + 1);
+                    response.add_answer(Record::from_rdata(name.clone(), *ttl, RData::A(A(ip))));
+                }
+                
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        let answers = |message: &Message| message.answers().iter().filter_map(|r| match r.data() {
+            Some(RData::A(a)) => Some((a.0.octets()[3], r.ttl())),
+            _ => None,
+        }).collect::<Vec<_>>();
+        
+        let cases = [
+            ("rewrite", "mixed.example.com", vec![(1, 60), (2, 300), (3, 60)]),
+            ("drop", "mixed.example.com", vec![(2, 300)]),
+            // 所有记录均低于下限时改为提升 TTL，应答不会被清空
+            ("drop", "low.example.com", vec![(1, 60), (2, 60)]),
+        ];
+        for (action, domain, expected) in cases {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: false
+              answer_ttl_floor:
+                min_ttl: 60
+                action: "{}"
+            "#, mock_upstream.uri(), action);
+            
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                create_test_query(domain, RecordType::A).to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            let dns_response = decode_dns_response(&body_bytes).await.unwrap();
+            assert_eq!(answers(&dns_response), expected, "action={}, domain={}", action, domain);
+        }
+        
+        info!("Test completed: test_doh_handler_answer_ttl_floor");
+    }
+    
+    // 测试客户端请求填充时应答按块大小填充（RFC 8467）
+    #[tokio::test]
+    async fn test_doh_handler_response_padding() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_response_padding");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(1, 1, 1, 1)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          response_padding:
+            enabled: true
+            block_size: 128
+        "#, mock_upstream.uri());
+        
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let send = |query: Message| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec()
+            }
+        };
+        let padding_of = |message: &Message| match message.extensions().as_ref()
+            .and_then(|edns| edns.option(EdnsCode::from(EDNS_PADDING_OPTION_CODE)))
+        {
+            Some(EdnsOption::Unknown(_, data)) => Some(data.clone()),
+            _ => None,
+        };
+        
+        // 携带填充选项的查询：应答长度为块大小的整数倍，填充内容为零
+        for domain in ["padding.example.com", "a-much-longer-name-for-padding.example.com"] {
+            let mut query = create_test_query(domain, RecordType::A);
+            let mut edns = Edns::new();
+            edns.options_mut().insert(EdnsOption::Unknown(EDNS_PADDING_OPTION_CODE, vec![0; 16]));
+            query.set_edns(edns);
+            let body_bytes = send(query).await;
+            assert_eq!(body_bytes.len() % 128, 0, "Response for {} should be padded to the block size", domain);
+            let response = decode_dns_response(&body_bytes).await.unwrap();
+            assert_eq!(response.answers().len(), 1);
+            let padding = padding_of(&response).expect("Response should carry a padding option");
+            assert!(padding.iter().all(|byte| *byte == 0));
+        }
+        
+        // 未请求填充的查询不附加填充选项
+        let body_bytes = send(create_test_query("padding.example.com", RecordType::A)).await;
+        let response = decode_dns_response(&body_bytes).await.unwrap();
+        assert_eq!(padding_of(&response), None);
+        
+        info!("Test completed: test_doh_handler_response_padding");
+    }
+    
+    #[tokio::test]
+    async fn test_doh_handler_local_trust_anchor() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_local_trust_anchor");
+        
+        // 区文件格式的根区信任锚（DNSKEY 跨行书写）
+        let temp_dir = tempfile::tempdir().unwrap();
+        let anchor_path = temp_dir.path().join("root.key");
+        std::fs::write(&anchor_path, r#"
+; 根区 KSK-2017
+.  172800  IN  DNSKEY  257 3 8 (
+        AwEAAaz/tAm8yTn4Mfeh5eyI96WSVexTBAvkMgJzkKTOiW1vkIbzxeF3
+        +/4RgWOq7HrxRixHlFlExOLAJr5emLvN7SWXgnLh4+B5xQlNVz8Og8kv
+        ArMtNROxVQuCaSnIDdD5LKyWbRd2n9WGe2R8PzgCmr3EgVLrjyBxWezF
+        0jLHwVN8efS3rCj/EWgvIWgb9tarpVUDK/b58Da+sqqls3eNbuv7pr+e
+        oZG+SrDK6nWeL3c6H5Apxz7LjVc1uTIdsIXxuOLYA4/ilBmSVIzuDWfd
+        RUfhHdY6+cn8HFRm+2hM8AnXGXws9555KrUB5qihylGa8subX2Nn6UwN
+        R1AkUTV74bU= ) ; KSK; alg = RSASHA256
+.  IN  DS  20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D
+"#).unwrap();
+        
+        // 上游不可达，任何泄露至上游的查询都会失败
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "https://127.0.0.1:1/dns-query"
+                protocol: doh
+            query_timeout: 1
+            enable_dnssec: true
+          http_client:
+            timeout: 1
+          cache:
+            enabled: false
+          dnssec:
+            local_trust_anchor:
+              enabled: true
+              file_path: "{}"
+        "#, anchor_path.display());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        config.test().unwrap();
+        let (app, _cache) = DoHServer::new(config.clone(), false).build_application_components().await.unwrap();
+        
+        let send = |app: axum::Router, name: &'static str, record_type: RecordType| async move {
+            let query = create_test_query(name, record_type);
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                query.to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            (status, body_bytes)
+        };
+        
+        // 根区 DNSKEY 查询由本地信任锚直接应答
+        let (status, body) = send(app.clone(), ".", RecordType::DNSKEY).await;
+        assert_eq!(status, StatusCode::OK);
+        let response = decode_dns_response(&body).await.unwrap();
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        let answer = &response.answers()[0];
+        assert_eq!(answer.record_type(), RecordType::DNSKEY);
+        assert!(answer.name().is_root());
+        assert_eq!(answer.ttl(), 172800);
+        
+        // 未指定 TTL 的 DS 记录使用配置的默认 TTL
+        let (status, body) = send(app.clone(), ".", RecordType::DS).await;
+        assert_eq!(status, StatusCode::OK);
+        let response = decode_dns_response(&body).await.unwrap();
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].record_type(), RecordType::DS);
+        assert_eq!(response.answers()[0].ttl(), DEFAULT_TRUST_ANCHOR_TTL);
+        
+        // 信任锚之外的名称仍转发至（不可达的）上游
+        let (status, _) = send(app.clone(), "example.com", RecordType::DNSKEY).await;
+        assert_ne!(status, StatusCode::OK);
+        
+        // 信任锚文件不存在时配置校验失败
+        let mut missing = config.clone();
+        missing.dns.dnssec.local_trust_anchor.file_path = temp_dir.path().join("missing.key").display().to_string();
+        assert!(missing.test().is_err());
+        
+        info!("Test completed: test_doh_handler_local_trust_anchor");
+    }
+    
+    // 测试受信任客户端通过请求头指定上游组
+    #[tokio::test]
+    async fn test_doh_handler_upstream_group_override() {
+        use axum::extract::ConnectInfo;
+        use oxide_wdns::common::consts::HEADER_UPSTREAM_GROUP_OVERRIDE;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_upstream_group_override");
+        
+        let default_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&default_upstream, std::net::Ipv4Addr::new(10, 0, 2, 1)).await;
+        let cn_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&cn_upstream, std::net::Ipv4Addr::new(10, 0, 2, 2)).await;
+        
+        let config_str = r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+          admin:
+            enabled: true
+            token: "override-secret"
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "https://127.0.0.1:1/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          routing:
+            enabled: true
+            upstream_groups:
+              - name: "default_doh"
+                resolvers:
+                  - address: "https://127.0.0.1:1/dns-query"
+                    protocol: doh
+              - name: "cn_doh"
+                resolvers:
+                  - address: "https://127.0.0.1:1/dns-query"
+                    protocol: doh
+            default_upstream_group: "default_doh"
+            group_override:
+              enabled: true
+              trusted_cidrs: ["127.0.0.1/32"]
+        "#;
+        let config: ServerConfig = serde_yaml::from_str(config_str).unwrap();
+        config.test().unwrap();
+        
+        // 未配置受信任网段且未启用管理接口时配置无效
+        let mut invalid = config.clone();
+        invalid.dns.routing.group_override.trusted_cidrs.clear();
+        invalid.http.admin.enabled = false;
+        assert!(invalid.test().is_err());
+        
+        // 测试时将上游地址替换为模拟服务器
+        let mut config = config;
+        config.dns.routing.upstream_groups[0].resolvers[0].address = format!("{}/dns-query", default_upstream.uri());
+        config.dns.routing.upstream_groups[1].resolvers[0].address = format!("{}/dns-query", cn_upstream.uri());
+        let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let query_ip = |peer: &'static str, headers: Vec<(&'static str, &'static str)>| {
+            let app = app.clone();
+            async move {
+                let mut request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    [vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)], headers].concat(),
+                    create_test_query("override.example.com", RecordType::A).to_vec().unwrap()
+                );
+                request.extensions_mut().insert(ConnectInfo(peer.parse::<std::net::SocketAddr>().unwrap()));
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                let response = decode_dns_response(&body_bytes).await.unwrap();
+                response.answers()[0].data().unwrap().to_string()
+            }
+        };
+        
+        // 受信任网段内的客户端：使用指定的上游组，应答不写入缓存
+        let ip = query_ip("127.0.0.1:40000", vec![(HEADER_UPSTREAM_GROUP_OVERRIDE, "cn_doh")]).await;
+        assert_eq!(ip, "10.0.2.2");
+        assert_eq!(cn_upstream.received_requests().await.unwrap().len(), 1);
+        assert_eq!(cache.len().await, 0, "Overridden responses must not be cached");
+        
+        // 不受信任的客户端：忽略请求头，按路由规则使用默认上游组
+        let ip = query_ip("192.0.2.10:40000", vec![(HEADER_UPSTREAM_GROUP_OVERRIDE, "cn_doh")]).await;
+        assert_eq!(ip, "10.0.2.1");
+        assert_eq!(cn_upstream.received_requests().await.unwrap().len(), 1);
+        
+        // 携带有效管理令牌的客户端：即使不在受信任网段内也可指定上游组
+        let ip = query_ip("192.0.2.10:40000", vec![
+            (HEADER_UPSTREAM_GROUP_OVERRIDE, "cn_doh"),
+            ("Authorization", "Bearer override-secret"),
+        ]).await;
+        assert_eq!(ip, "10.0.2.2");
+        
+        // 未知的上游组：忽略请求头
+        let ip = query_ip("127.0.0.1:40000", vec![(HEADER_UPSTREAM_GROUP_OVERRIDE, "missing")]).await;
+        assert_eq!(ip, "10.0.2.1");
+        
+        info!("Test completed: test_doh_handler_upstream_group_override");
+    }
+    
+    // 测试 A 查询在后台预取同名 AAAA 记录并写入缓存
+    #[tokio::test]
+    async fn test_doh_handler_companion_prefetch() {
+        use std::net::Ipv6Addr;
+        use hickory_proto::rr::rdata::AAAA;
+        use hickory_proto::rr::{RData, Record};
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_companion_prefetch");
+        
+        // 模拟上游：A 查询返回 IPv4 地址，AAAA 查询返回 IPv6 地址
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let mut response = crate::server::mock_http_server::create_test_response(&query, std::net::Ipv4Addr::new(10, 0, 3, 1));
+                let question = &query.queries()[0];
+                if question.query_type() == RecordType::AAAA {
+                    response.add_answer(Record::from_rdata(
+                        question.name().clone(), 300, RData::AAAA(AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))
+                    ));
+                }
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+            companion_prefetch: true
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        
+        // 未启用缓存时配置无效
+        let mut invalid = config.clone();
+        invalid.dns.cache.enabled = false;
+        assert!(invalid.test().is_err());
+        
+        let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let query = |record_type: RecordType| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    create_test_query("companion.example.com", record_type).to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        // A 查询立即返回，AAAA 记录在后台预取并写入缓存
+        let response = query(RecordType::A).await;
+        assert_eq!(response.answers()[0].record_type(), RecordType::A);
+        for _ in 0..50 {
+            if cache.len().await >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(cache.len().await, 2, "Companion AAAA record should be cached");
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 2);
+        
+        // 随后的 AAAA 查询命中缓存，不再查询上游，也不再触发预取
+        let response = query(RecordType::AAAA).await;
+        assert_eq!(response.answers()[0].record_type(), RecordType::AAAA);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 2);
+        
+        info!("Test completed: test_doh_handler_companion_prefetch");
+    }
+    
+    // 测试应答记录数上限：超出部分被截去，已签名的 RRset 不被拆分，缓存条目不受影响
+    #[tokio::test]
+    async fn test_doh_handler_max_answer_records() {
+        use std::net::Ipv4Addr;
+        use hickory_proto::rr::dnssec::Algorithm;
+        use hickory_proto::rr::dnssec::rdata::{DNSSECRData, RRSIG};
+        use hickory_proto::rr::rdata::A;
+        use hickory_proto::rr::{RData, Record};
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_max_answer_records");
+        
+        // 模拟上游：many.example.com 返回 20 条 A 记录，signed.example.com 返回 3 条已签名的 A 记录
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let name = query.queries()[0].name().clone();
+                let signed = name.to_ascii().starts_with("signed");
+                let count = if signed { 3 } else { 20 };
+                let mut response = Message::new();
+                response.set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_available(true)
+                    .add_queries(query.queries().to_vec());
+                for i in 1..=count {
+                    response.add_answer(Record::from_rdata(name.clone(), 300, RData::A(A(Ipv4Addr::new(192, 0, 2, i)))));
+                }
+                if signed {
+                    let sig = RRSIG::new(
+                        RecordType::A, Algorithm::ECDSAP256SHA256, 3, 300, u32::MAX, 0, 12345,
+                        Name::from_ascii("example.com.").unwrap(), vec![0xab; 64],
+                    );
+                    response.add_answer(Record::from_rdata(name, 300, RData::DNSSEC(DNSSECRData::RRSIG(sig))));
+                }
+                
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          emit_ede: true
+          max_answer_records: 2
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let query = |domain: &'static str| {
+            let app = app.clone();
+            async move {
+                let mut query = create_test_query(domain, RecordType::A);
+                query.set_edns(Edns::new());
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    query.to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        // 未签名的应答按上限截断（上游应答与缓存命中一致），并附加 EDE 说明
+        for round in ["upstream", "cache hit"] {
+            let response = query("many.example.com").await;
+            assert_eq!(response.answers().len(), 2, "Answers should be capped ({})", round);
+            assert!(ExtendedError::from_message(&response).is_some(), "Capped response should carry an EDE ({})", round);
+        }
+        
+        // 缓存中存储的是完整应答
+        let key = CacheKey::new(Name::from_ascii("many.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+        let cached = cache.get(&key).await.expect("Response should be cached");
+        assert_eq!(cached.answers().len(), 20);
+        
+        // 已签名的 RRset 不被拆分：记录与 RRSIG 整体保留
+        let response = query("signed.example.com").await;
+        assert_eq!(response.answers().iter().filter(|r| r.record_type() == RecordType::A).count(), 3);
+        assert_eq!(response.answers().iter().filter(|r| r.record_type() == RecordType::RRSIG).count(), 1);
+        
+        info!("Test completed: test_doh_handler_max_answer_records");
+    }
+    
+    // 测试通过 POST 提交 JSON 请求体查询 JSON API
+    #[tokio::test]
+    async fn test_doh_handler_json_post() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_json_post");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 70)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: false
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let post = |content_type: &'static str, body: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/resolve",
+                    vec![("Content-Type", content_type)],
+                    body.as_bytes().to_vec()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let content_type = response.headers().get(header::CONTENT_TYPE).cloned();
+                let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+                (status, content_type, String::from_utf8_lossy(&body).to_string())
+            }
+        };
+        
+        // 有效的 JSON 请求体：返回与 GET 相同格式的 JSON 应答
+        let (status, content_type, body) = post(CONTENT_TYPE_DNS_JSON, r#"{"name": "post.example.com", "type_value": 1}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.unwrap(), CONTENT_TYPE_DNS_JSON);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["status"], 0);
+        assert_eq!(json["question"][0]["name"], "post.example.com.");
+        assert_eq!(json["answer"][0]["data"], "192.0.2.70");
+        
+        // 同样接受 application/json，省略的字段使用默认值
+        let (status, _, _) = post("application/json", r#"{"name": "post.example.com"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        
+        // 无效的请求体返回 400，不支持的内容类型返回 415
+        let (status, _, body) = post(CONTENT_TYPE_DNS_JSON, r#"{"type_value": 1}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("Invalid JSON request body"), "Unexpected body: {}", body);
+        let (status, _, _) = post(CONTENT_TYPE_DNS_MESSAGE, r#"{"name": "post.example.com"}"#).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        
+        info!("Test completed: test_doh_handler_json_post");
+    }
+    
+    // 测试 ANY 查询的处理策略（RFC 8482）
+    #[tokio::test]
+    async fn test_doh_handler_any_query_policy() {
+        use hickory_proto::rr::RData;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_any_query_policy");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(192, 0, 2, 80)).await;
+        
+        for policy in ["hinfo", "refuse", "forward"] {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: false
+              http_client:
+                timeout: 5
+              cache:
+                enabled: true
+              any_query_policy: {}
+            "#, mock_upstream.uri(), policy);
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            
+            let requests_before = mock_upstream.received_requests().await.unwrap().len();
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                create_test_query("any.example.com", RecordType::ANY).to_vec().unwrap()
+            );
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            let response = decode_dns_response(&body_bytes).await.unwrap();
+            let forwarded = mock_upstream.received_requests().await.unwrap().len() - requests_before;
+            
+            match policy {
+                "hinfo" => {
+                    // 单条 HINFO 记录，CPU 为 "RFC8482"，OS 为空，不查询上游也不写入缓存
+                    assert_eq!(response.response_code(), ResponseCode::NoError);
+                    assert_eq!(response.answers().len(), 1);
+                    let answer = &response.answers()[0];
+                    assert_eq!(answer.name().to_utf8(), "any.example.com.");
+                    match answer.data() {
+                        Some(RData::HINFO(hinfo)) => {
+                            assert_eq!(hinfo.cpu(), b"RFC8482");
+                            assert!(hinfo.os().is_empty());
+                        },
+                        other => panic!("Expected HINFO record, got {:?}", other),
+                    }
+                    assert_eq!(forwarded, 0);
+                    assert_eq!(cache.len().await, 0);
+                },
+                "refuse" => {
+                    assert_eq!(response.response_code(), ResponseCode::Refused);
+                    assert!(response.answers().is_empty());
+                    assert_eq!(forwarded, 0);
+                },
+                _ => {
+                    assert_eq!(response.response_code(), ResponseCode::NoError);
+                    assert_eq!(forwarded, 1, "ANY query should be forwarded upstream");
+                },
+            }
+        }
+        
+        info!("Test completed: test_doh_handler_any_query_policy");
+    }
+    
+    // 测试 cache.revalidate_types：DS 查询每次向上游重新验证，A 查询命中缓存，上游失败时 DS 回退到缓存
+    #[tokio::test]
+    async fn test_doh_handler_cache_revalidate_types() {
+        use hickory_proto::rr::dnssec::rdata::{DNSSECRData, DS};
+        use hickory_proto::rr::dnssec::{Algorithm, DigestType};
+        use hickory_proto::rr::{RData, Record};
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_cache_revalidate_types");
+        
+        // 模拟上游：前 3 次查询正常应答（DS 查询返回 DS 记录），之后返回 HTTP 500
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let mut response = crate::server::mock_http_server::create_test_response(&query, std::net::Ipv4Addr::new(10, 0, 4, 1));
+                let question = &query.queries()[0];
+                if question.query_type() == RecordType::DS {
+                    let ds = DS::new(12345, Algorithm::RSASHA256, DigestType::SHA256, vec![0xab; 32]);
+                    response.add_answer(Record::from_rdata(question.name().clone(), 300, RData::DNSSEC(DNSSECRData::DS(ds))));
+                }
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .up_to_n_times(3)
+            .with_priority(1)
+            .mount(&mock_upstream)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(ResponseTemplate::new(500))
+            .with_priority(2)
+            .mount(&mock_upstream)
+            .await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+            revalidate_types: ["ds", "DNSKEY"]
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        assert!(config.cache_revalidates(RecordType::DS));
+        assert!(!config.cache_revalidates(RecordType::A));
+        
+        // 配置校验（校验要求 DoH 上游使用 HTTPS）：未知类型或未启用缓存时配置无效
+        let mut valid = config.clone();
+        valid.dns.upstream.resolvers[0].address = "https://127.0.0.1:1/dns-query".to_string();
+        assert!(valid.test().is_ok());
+        let mut invalid = valid.clone();
+        invalid.dns.cache.revalidate_types.push("NOTATYPE".to_string());
+        assert!(invalid.test().is_err());
+        let mut invalid = valid.clone();
+        invalid.dns.cache.enabled = false;
+        assert!(invalid.test().is_err());
+        
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let query = |record_type: RecordType| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    create_test_query("revalidate.example.com", record_type).to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        // A 查询第二次命中缓存，DS 查询每次都查询上游
+        query(RecordType::A).await;
+        query(RecordType::A).await;
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 1);
+        query(RecordType::DS).await;
+        query(RecordType::DS).await;
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 3);
+        
+        // 上游失败时，DS 查询回退到未过期的缓存条目
+        let response = query(RecordType::DS).await;
+        assert!(mock_upstream.received_requests().await.unwrap().len() > 3, "DS query should still be revalidated upstream");
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers()[0].record_type(), RecordType::DS, "Cached DS answer should be served when revalidation fails");
+        
+        info!("Test completed: test_doh_handler_cache_revalidate_types");
+    }
+    
+    // 测试 edns_probe_policy：无问题节但携带 OPT 记录的 EDNS 能力探测
+    #[tokio::test]
+    async fn test_doh_handler_edns_probe_policy() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_edns_probe_policy");
+        
+        // 构建无问题节的查询，可选携带指定版本的 OPT 记录
+        let probe = |edns_version: Option<u8>| {
+            let mut query = Message::new();
+            query.set_id(4321)
+                .set_message_type(MessageType::Query)
+                .set_op_code(OpCode::Query)
+                .set_recursion_desired(true);
+            if let Some(version) = edns_version {
+                let mut edns = Edns::new();
+                edns.set_version(version).set_max_payload(4096).set_dnssec_ok(true);
+                query.set_edns(edns);
+            }
+            query.to_vec().unwrap()
+        };
+        
+        let build_app = |policy: &'static str| async move {
+            let config_str = format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "https://127.0.0.1:1/dns-query"
+                    protocol: doh
+                query_timeout: 1
+                enable_dnssec: false
+              http_client:
+                timeout: 1
+              cache:
+                enabled: false
+              edns_probe_policy: "{}"
+            "#, policy);
+            let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+            assert!(config.test().is_ok());
+            let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+            app
+        };
+        
+        let send = |app: axum::Router, body: Vec<u8>| async move {
+            let request = build_http_request(
+                Method::POST,
+                "/dns-query",
+                vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                body
+            );
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+            (status, body_bytes)
+        };
+        
+        // 默认策略：EDNS 探测与无问题节的查询一样被拒绝
+        let app = build_app("reject").await;
+        let (status, _) = send(app, probe(Some(0))).await;
+        assert_ne!(status, StatusCode::OK);
+        
+        // answer 策略：返回携带 OPT 记录的 NOERROR 应答，回显 DO 位，不查询上游
+        let app = build_app("answer").await;
+        let (status, body) = send(app.clone(), probe(Some(0))).await;
+        assert_eq!(status, StatusCode::OK);
+        let response = decode_dns_response(&body).await.unwrap();
+        assert_eq!(response.id(), 4321);
+        assert_eq!(response.message_type(), MessageType::Response);
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(response.queries().is_empty());
+        let edns = response.extensions().as_ref().expect("EDNS probe response should carry an OPT record");
+        assert_eq!(edns.version(), 0);
+        assert!(edns.dnssec_ok());
+        assert!(edns.max_payload() >= 512);
+        
+        // 不支持的 EDNS 版本返回 BADVERS（扩展响应码 16，与 BADSIG 同值）
+        let (status, body) = send(app.clone(), probe(Some(1))).await;
+        assert_eq!(status, StatusCode::OK);
+        let response = decode_dns_response(&body).await.unwrap();
+        assert_eq!(u16::from(response.response_code()), u16::from(ResponseCode::BADVERS));
+        assert_eq!(response.extensions().as_ref().unwrap().version(), 0);
+        
+        // 不携带 OPT 记录的空查询仍被拒绝
+        let (status, _) = send(app, probe(None)).await;
+        assert_ne!(status, StatusCode::OK);
+        
+        info!("Test completed: test_doh_handler_edns_probe_policy");
+    }
+    
+    // 测试只读基线缓存在路由决策之后查找：阻止规则与关闭缓存的上游组优先于基线
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_doh_handler_baseline_cache_after_routing() {
+        use hickory_proto::rr::{RData, Record};
+        use hickory_proto::rr::rdata::A;
+        use oxide_wdns::server::config::{CacheConfig, PersistenceCacheConfig};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_baseline_cache_after_routing");
+        
+        // 由持久化缓存生成基线文件，三个域名均有基线条目
+        let dir = tempfile::tempdir().unwrap();
+        let baseline_path = dir.path().join("baseline.dat").to_string_lossy().to_string();
+        let source = DnsCache::new(CacheConfig {
+            enabled: true,
+            persistence: PersistenceCacheConfig {
+                enabled: true,
+                path: baseline_path.clone(),
+                load_on_startup: false,
+                ..PersistenceCacheConfig::default()
+            },
+            ..CacheConfig::default()
+        });
+        for domain in ["base.example.com.", "blocked.example.com.", "nocache.example.com."] {
+            let query = create_test_query(domain, RecordType::A);
+            let mut response = query.clone();
+            response.set_message_type(MessageType::Response);
+            response.add_answer(Record::from_rdata(Name::from_ascii(domain).unwrap(), 300, RData::A(A::new(192, 0, 2, 60))));
+            let key = CacheKey::new(Name::from_ascii(domain).unwrap(), RecordType::A, DNSClass::IN);
+            source.put(&key, &response, 300).await.unwrap();
+        }
+        assert_eq!(source.save_to_file().await.unwrap(), 3);
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(10, 0, 5, 1)).await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{0}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+            persistence:
+              baseline_path: "{1}"
+          routing:
+            enabled: true
+            upstream_groups:
+              - name: "nocache"
+                cache: false
+                resolvers:
+                  - address: "{0}/dns-query"
+                    protocol: doh
+            rules:
+              - match:
+                  type: exact
+                  values: ["blocked.example.com"]
+                upstream_group: "__blackhole__"
+              - match:
+                  type: exact
+                  values: ["nocache.example.com"]
+                upstream_group: "nocache"
+        "#, mock_upstream.uri(), baseline_path);
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        let query = |domain: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    create_test_query(domain, RecordType::A).to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        // 全局上游：主缓存未命中时由基线应答，不查询上游
+        let hits_before = METRICS.cache_baseline_hits_total().get();
+        let response = query("base.example.com").await;
+        assert_eq!(response.answers()[0].data(), Some(&RData::A(A::new(192, 0, 2, 60))));
+        assert!(METRICS.cache_baseline_hits_total().get() > hits_before);
+        assert!(mock_upstream.received_requests().await.unwrap().is_empty());
+        
+        // 阻止规则优先于基线
+        let response = query("blocked.example.com").await;
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+        assert!(response.answers().is_empty());
+        
+        // 关闭缓存的上游组不使用基线，始终查询上游
+        let response = query("nocache.example.com").await;
+        assert_eq!(response.answers()[0].data(), Some(&RData::A(A::new(10, 0, 5, 1))));
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 1);
+        
+        info!("Test completed: test_doh_handler_baseline_cache_after_routing");
+    }
+    
+    // 测试规则加载完成前 fail-closed 时缓存中的应答同样被拒绝，不绕过尚未加载的屏蔽规则
+    #[tokio::test]
+    async fn test_doh_handler_fail_closed_skips_cache() {
+        use wiremock::{Mock, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_doh_handler_fail_closed_skips_cache");
+        
+        let mock_upstream = MockServer::start().await;
+        mount_fixed_ip_upstream(&mock_upstream, std::net::Ipv4Addr::new(10, 0, 6, 1)).await;
+        Mock::given(method("GET"))
+            .and(path("/blocklist.txt"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_string("ads.example.net\n")
+                .set_delay(Duration::from_millis(800)))
+            .mount(&mock_upstream)
+            .await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{0}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          routing:
+            enabled: true
+            fail_closed_until_loaded: true
+            fail_closed_action: "refuse"
+            rules:
+              - match:
+                  type: url
+                  url: "{0}/blocklist.txt"
+                  periodic:
+                    enabled: true
+                    interval_secs: 3600
+                upstream_group: "__blackhole__"
+        "#, mock_upstream.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 规则加载前缓存中已有屏蔽域名的应答（如启动时从持久化文件预热）
+        let cached = crate::server::mock_http_server::create_test_response(
+            &create_test_query("ads.example.net", RecordType::A), std::net::Ipv4Addr::new(192, 0, 2, 70)
+        );
+        let key = CacheKey::new(Name::from_ascii("ads.example.net.").unwrap(), RecordType::A, DNSClass::IN);
+        cache.put(&key, &cached, 300).await.unwrap();
+        
+        let query = |domain: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = build_http_request(
+                    Method::POST,
+                    "/dns-query",
+                    vec![("Content-Type", CONTENT_TYPE_DNS_MESSAGE)],
+                    create_test_query(domain, RecordType::A).to_vec().unwrap()
+                );
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body_bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap().to_vec();
+                decode_dns_response(&body_bytes).await.unwrap()
+            }
+        };
+        
+        // 加载完成前不使用缓存，按 fail_closed_action 拒绝
+        let response = query("ads.example.net").await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        assert!(response.answers().is_empty());
+        let response = query("www.example.com").await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        assert!(mock_upstream.received_requests().await.unwrap().iter().all(|request| request.url.path() != "/dns-query"));
+        
+        // 加载完成后恢复正常路由
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let response = query("www.example.com").await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        
+        info!("Test completed: test_doh_handler_fail_closed_skips_cache");
+    }
+}
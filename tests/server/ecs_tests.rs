@@ -190,6 +190,7 @@ fn test_strip_policy() {
         enabled: true,
         strategy: ECS_POLICY_STRIP.to_string(),
         anonymization: EcsAnonymizationConfig::default(),
+        strip_from_response: false,
     };
     
     // 应用策略
@@ -226,6 +227,7 @@ fn test_forward_policy() {
         enabled: true,
         strategy: ECS_POLICY_FORWARD.to_string(),
         anonymization: EcsAnonymizationConfig::default(),
+        strip_from_response: false,
     };
     
     // 应用策略
@@ -274,6 +276,7 @@ fn test_anonymize_policy() {
             ipv4_prefix_length: 24,
             ipv6_prefix_length: 48,
         },
+        strip_from_response: false,
     };
     
     // 应用策略
@@ -318,6 +321,7 @@ fn test_respect_client_privacy() {
         enabled: true,
         strategy: ECS_POLICY_FORWARD.to_string(),
         anonymization: EcsAnonymizationConfig::default(),
+        strip_from_response: false,
     };
     
     // 应用策略 - 这里我们没有提供客户端IP地址，因为查询已包含ECS
@@ -370,6 +374,7 @@ fn test_create_ecs_from_client_ip() {
             ipv4_prefix_length: 24,
             ipv6_prefix_length: 56,
         },
+        strip_from_response: false,
     };
     
     // 应用策略，使用客户端IP
@@ -662,6 +667,7 @@ fn test_ecs_enabled_flag() {
         enabled: false,
         strategy: ECS_POLICY_STRIP.to_string(),
         anonymization: EcsAnonymizationConfig::default(),
+        strip_from_response: false,
     };
     
     // 应用禁用的策略
@@ -680,6 +686,7 @@ fn test_ecs_enabled_flag() {
         enabled: true,
         strategy: ECS_POLICY_STRIP.to_string(),
         anonymization: EcsAnonymizationConfig::default(),
+        strip_from_response: false,
     };
     
     // 应用启用的策略
@@ -10,6 +10,13 @@ mod tests {
     use std::sync::Arc;
     use tokio::sync::Mutex;
     use tracing::info;
+    use std::net::Ipv4Addr;
+    use tower::util::ServiceExt;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{method, path};
+    use oxide_wdns::server::config::ServerConfig;
+    use oxide_wdns::server::DoHServer;
+    use crate::server::mock_http_server::setup_mock_doh_server;
     
     // 定义一个辅助结构体来表示健康状态
     struct MockHealthState {
@@ -172,4 +179,163 @@ mod tests {
         info!("Validated second response body.");
         info!("Test completed: test_health_check_upstream_dependency");
     }
+
+    // 构建启用端到端探测的应用，上游为指定 DoH 地址
+    async fn build_probe_app(upstream_url: &str, probe_enabled: bool) -> Router {
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+          health:
+            probe:
+              enabled: {}
+              name: "canary.example.com"
+              interval_secs: 1
+              timeout_secs: 2
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}"
+                protocol: doh
+            query_timeout: 2
+          cache:
+            enabled: true
+        "#, probe_enabled, upstream_url);
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        app
+    }
+    
+    // 请求就绪检查，返回状态码与 JSON 正文
+    async fn get_readiness(app: &Router) -> (StatusCode, serde_json::Value) {
+        let request = axum::http::Request::builder()
+            .uri("/health/ready")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+        let body = axum::body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+    
+    // 轮询就绪检查直到返回期望状态码（最多约 5 秒）
+    async fn wait_for_readiness(app: &Router, expected: StatusCode) -> serde_json::Value {
+        for _ in 0..50 {
+            let (status, body) = get_readiness(app).await;
+            if status == expected && !body["probe"].is_null() {
+                return body;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("Readiness did not become {}", expected);
+    }
+
+    #[tokio::test]
+    async fn test_health_ready_reflects_synthetic_probe() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_health_ready_reflects_synthetic_probe");
+        
+        // 1. 未启用探测：就绪检查始终就绪
+        let app = build_probe_app("https://127.0.0.1:1/dns-query", false).await;
+        let (status, body) = get_readiness(&app).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert!(body["probe"].is_null());
+        
+        // 2. 上游可用：探测成功后就绪
+        let (healthy_upstream, counter) = setup_mock_doh_server(Ipv4Addr::new(192, 0, 2, 1)).await;
+        let app = build_probe_app(&format!("{}/dns-query", healthy_upstream.uri()), true).await;
+        let body = wait_for_readiness(&app, StatusCode::OK).await;
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["probe"]["success"], true);
+        assert_eq!(body["probe"]["rcode"], "No Error");
+        assert!(*counter.lock().unwrap() >= 1, "Probe should reach the upstream");
+        
+        // 3. 上游返回错误：探测失败后返回 503
+        let failing_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&failing_upstream)
+            .await;
+        let app = build_probe_app(&format!("{}/dns-query", failing_upstream.uri()), true).await;
+        let body = wait_for_readiness(&app, StatusCode::SERVICE_UNAVAILABLE).await;
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["probe"]["success"], false);
+        assert!(body["probe"]["error"].is_string());
+        
+        info!("Test completed: test_health_ready_reflects_synthetic_probe");
+    }
+    
+    #[tokio::test]
+    async fn test_health_ready_waits_for_url_rules() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_health_ready_waits_for_url_rules");
+        
+        // URL规则列表延迟返回，首次加载完成前上游组未就绪
+        let rule_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/internal.txt"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_string("listed.corp.example\n")
+                .set_delay(std::time::Duration::from_millis(800)))
+            .mount(&rule_server)
+            .await;
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "8.8.8.8:53"
+                protocol: udp
+          routing:
+            enabled: true
+            upstream_groups:
+              - name: "internal_group"
+                resolvers:
+                  - address: "10.0.0.53:53"
+                    protocol: udp
+            rules:
+              - match:
+                  type: url
+                  url: "{}/internal.txt"
+                  periodic:
+                    enabled: true
+                    interval_secs: 3600
+                upstream_group: "internal_group"
+        "#, rule_server.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+        
+        // 加载期间返回 503 并列出未就绪的上游组
+        let (status, body) = get_readiness(&app).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["pending_upstream_groups"], serde_json::json!(["internal_group"]));
+        
+        // 加载完成后就绪
+        let mut ready_body = None;
+        for _ in 0..50 {
+            let (status, body) = get_readiness(&app).await;
+            if status == StatusCode::OK {
+                ready_body = Some(body);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        let body = ready_body.expect("Readiness did not become OK after URL rules loaded");
+        assert_eq!(body["status"], "ready");
+        assert!(body.get("pending_upstream_groups").is_none());
+        
+        info!("Test completed: test_health_ready_waits_for_url_rules");
+    }
 } 
\ No newline at end of file
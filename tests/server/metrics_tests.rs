@@ -1 +1,163 @@
 // tests/server/metrics_tests.rs
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use hickory_proto::op::{Message, MessageType};
+    use hickory_proto::rr::rdata::A;
+    use hickory_proto::rr::{RData, Record, RecordType};
+    use tower::ServiceExt;
+    use tracing::info;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use oxide_wdns::common::consts::CONTENT_TYPE_DNS_MESSAGE;
+    use oxide_wdns::server::config::ServerConfig;
+    use oxide_wdns::server::metrics::{query_type_label, METRICS, QUERY_TYPE_LABEL_OTHER};
+    use oxide_wdns::server::DoHServer;
+
+    use crate::server::mock_http_server::{create_test_query, setup_mock_doh_server};
+
+    #[tokio::test]
+    async fn test_metrics_query_type_label_cardinality() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_metrics_query_type_label_cardinality");
+
+        // 允许列表内的类型单独标记，未知及罕见类型归入 OTHER
+        assert_eq!(query_type_label(RecordType::A), "A");
+        assert_eq!(query_type_label(RecordType::HTTPS), "HTTPS");
+        assert_eq!(query_type_label(RecordType::Unknown(65280)), QUERY_TYPE_LABEL_OTHER);
+        assert_eq!(query_type_label(RecordType::NULL), QUERY_TYPE_LABEL_OTHER);
+
+        // 经完整处理流程的未知类型查询计入 OTHER 标签
+        let (mock_server, _counter) = setup_mock_doh_server(Ipv4Addr::new(192, 168, 1, 1)).await;
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+          cache:
+            enabled: false
+        "#, mock_server.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+
+        let other_before = METRICS.dns_query_type_total().with_label_values(&[QUERY_TYPE_LABEL_OTHER]).get();
+        let query = create_test_query("scanner.example.com", RecordType::Unknown(65280));
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/dns-query")
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_DNS_MESSAGE)
+            .body(Body::from(query.to_vec().unwrap()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert!(METRICS.dns_query_type_total().with_label_values(&[QUERY_TYPE_LABEL_OTHER]).get() > other_before);
+        let exported = METRICS.registry().gather();
+        let unknown_labels = exported.iter()
+            .filter(|family| family.get_name() == "owdns_dns_query_type_total")
+            .flat_map(|family| family.get_metric())
+            .flat_map(|metric| metric.get_label())
+            // 无问题节的查询使用固定的 Unknown 标签，仅检查按类型值生成的 Unknown(N) 标签
+            .filter(|label| label.get_value().starts_with("Unknown("))
+            .count();
+        assert_eq!(unknown_labels, 0, "Unknown query types must not create individual labels");
+
+        info!("Test completed: test_metrics_query_type_label_cardinality");
+    }
+
+    #[tokio::test]
+    async fn test_metrics_response_modified_total() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_metrics_response_modified_total");
+
+        // 模拟上游：应答节包含重复记录
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let name = query.queries()[0].name().clone();
+                let record = Record::from_rdata(name, 300, RData::A(A(Ipv4Addr::new(192, 0, 2, 1))));
+                let mut response = Message::new();
+                response.set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_available(true)
+                    .add_queries(query.queries().to_vec())
+                    .add_answers(vec![record.clone(), record]);
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_server)
+            .await;
+
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+          cache:
+            enabled: false
+          dedup_answers: true
+          log_modified_responses: true
+          routing:
+            enabled: true
+            rules:
+              - match:
+                  type: exact
+                  values: ["blocked.example.com"]
+                upstream_group: "__blackhole__"
+        "#, mock_server.uri());
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+
+        let modified = |modification: &str| METRICS.response_modified_total().with_label_values(&[modification]).get();
+        let send = |domain: &'static str| {
+            let app = app.clone();
+            async move {
+                let query = create_test_query(domain, RecordType::A);
+                let request = Request::builder()
+                    .method(Method::POST)
+                    .uri("/dns-query")
+                    .header(header::CONTENT_TYPE, CONTENT_TYPE_DNS_MESSAGE)
+                    .body(Body::from(query.to_vec().unwrap()))
+                    .unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+        };
+
+        // 黑洞阻止的查询计入 blackhole
+        let blackhole_before = modified("blackhole");
+        send("blocked.example.com").await;
+        assert!(modified("blackhole") > blackhole_before);
+
+        // 去除重复记录的应答计入 filtered
+        let filtered_before = modified("filtered");
+        send("duplicate.example.com").await;
+        assert!(modified("filtered") > filtered_before);
+
+        info!("Test completed: test_metrics_response_modified_total");
+    }
+}
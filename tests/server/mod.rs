@@ -4,14 +4,18 @@
 pub mod mock_http_server;
 
 // 声明测试模块
+mod admin_tests;
 mod args_tests;
 mod cache_tests;
 mod config_tests;
+mod dnstap_tests;
 mod doh_handler_advanced_tests;
 mod health_tests;
 mod metrics_tests;
+mod query_log_tests;
 mod routing_tests; // 新增的DNS分流测试模块
 mod server_integration_tests;
+mod self_test_tests;
 // mod signal_tests;
 mod upstream_tests;
 mod ecs_tests;
@@ -0,0 +1,193 @@
+// tests/server/query_log_tests.rs
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Method, Request, StatusCode};
+    use tower::util::ServiceExt;
+    use hickory_proto::op::ResponseCode;
+    use hickory_proto::rr::RecordType;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{method, path};
+    use tracing::info;
+    use oxide_wdns::common::consts::CONTENT_TYPE_DNS_MESSAGE;
+    use oxide_wdns::server::config::{QueryLogConfig, QueryLogFormat, ServerConfig};
+    use oxide_wdns::server::query_log::QueryLogger;
+    use oxide_wdns::server::DoHServer;
+    use crate::server::mock_http_server::{create_test_query, create_test_response};
+
+    #[tokio::test]
+    async fn test_query_log_records_resolved_queries() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_query_log_records_resolved_queries");
+
+        // 模拟上游：固定返回 1.1.1.1
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = hickory_proto::op::Message::from_vec(&req.body).expect("Invalid DNS query");
+                let response = create_test_response(&query, Ipv4Addr::new(1, 1, 1, 1));
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("audit").join("query.log");
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "{}/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+          routing:
+            enabled: true
+            rules:
+              - match:
+                  type: exact
+                  values: ["blocked.example.com"]
+                upstream_group: "__blackhole__"
+          query_log:
+            enabled: true
+            path: "{}"
+            format: json
+        "#, mock_upstream.uri(), log_path.display());
+
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        let (app, _cache) = DoHServer::new(config, false).build_application_components().await.unwrap();
+
+        // 未命中缓存、命中缓存、被规则阻止各一次
+        for domain in ["audit.example.com", "audit.example.com", "blocked.example.com"] {
+            let query = create_test_query(domain, RecordType::A);
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/dns-query")
+                .header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                .body(Body::from(query.to_vec().unwrap()))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let _ = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+        }
+
+        // 每个查询一条 JSON 记录（由后台任务写出）
+        let content = wait_for_lines(&log_path, 3).await;
+        let records: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+
+        assert_eq!(records[0]["name"], "audit.example.com.");
+        assert_eq!(records[0]["type"], "A");
+        assert_eq!(records[0]["rcode"], "No Error");
+        assert_eq!(records[0]["group"], "global");
+        assert_eq!(records[0]["cache"], "miss");
+        assert!(records[0]["client"].is_string());
+        assert!(records[0]["timestamp_ms"].as_u64().unwrap() > 0);
+
+        assert_eq!(records[1]["cache"], "hit");
+        assert!(records[1]["group"].is_null());
+
+        assert_eq!(records[2]["name"], "blocked.example.com.");
+        assert_eq!(records[2]["group"], "__blackhole__");
+        assert_eq!(records[2]["rcode"], "Non-Existent Domain");
+
+        info!("Test completed: test_query_log_records_resolved_queries");
+    }
+
+    #[tokio::test]
+    async fn test_query_log_text_format_and_rotation() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_query_log_text_format_and_rotation");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("query.log");
+        let logger = Arc::new(QueryLogger::new(&QueryLogConfig {
+            enabled: true,
+            path: log_path.display().to_string(),
+            format: QueryLogFormat::Text,
+            max_size_mb: 1,
+            max_files: 2,
+            // 容纳全部记录，测试中不丢弃
+            buffer_size: 65536,
+        }).unwrap());
+        assert!(logger.is_enabled());
+
+        let client = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7));
+        let query = create_test_query("rotate.example.com", RecordType::A);
+        let mut response = create_test_response(&query, Ipv4Addr::new(1, 1, 1, 1));
+        response.set_response_code(ResponseCode::NoError);
+
+        // 文本格式：时间戳 客户端 查询名 类型 响应码 group= cache=
+        logger.log(client, &query, &response, Some("global"), false);
+        let first_line = wait_for_lines(&log_path, 1).await;
+        let fields: Vec<&str> = first_line.trim_end().split(' ').collect();
+        assert_eq!(&fields[1..], ["192.0.2.7", "rotate.example.com", "A", "No", "Error", "group=global", "cache=miss"]);
+
+        // 写入超过 3MB 的记录，触发多次轮转，仅保留 max_files 个历史文件
+        let line_len = first_line.len() as u64;
+        for _ in 0..(3 * 1024 * 1024 / line_len + 1) {
+            logger.log(client, &query, &response, None, true);
+        }
+
+        // 最后一条记录写出后，之前的记录均已写出
+        let last_query = create_test_query("last.example.com", RecordType::A);
+        logger.log(client, &last_query, &response, None, true);
+        let mut written = false;
+        for _ in 0..100 {
+            let content = std::fs::read_to_string(&log_path).unwrap_or_default();
+            if content.lines().last().is_some_and(|line| line.contains("last.example.com")) {
+                written = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(written, "Query log records were not written by the background task");
+
+        let max_bytes = 1024 * 1024;
+        assert!(std::fs::metadata(&log_path).unwrap().len() <= max_bytes);
+        assert!(std::fs::metadata(format!("{}.1", log_path.display())).unwrap().len() <= max_bytes);
+        assert!(std::path::Path::new(&format!("{}.2", log_path.display())).exists());
+        assert!(!std::path::Path::new(&format!("{}.3", log_path.display())).exists(), "Rotation should keep at most max_files files");
+
+        // 禁用时不写入任何内容
+        let disabled = QueryLogger::disabled();
+        assert!(!disabled.is_enabled());
+        disabled.log(client, &query, &response, None, false);
+
+        info!("Test completed: test_query_log_text_format_and_rotation");
+    }
+
+    // 等待后台任务写出至少 expected 行，返回文件内容
+    async fn wait_for_lines(path: &std::path::Path, expected: usize) -> String {
+        let mut content = String::new();
+        for _ in 0..100 {
+            content = std::fs::read_to_string(path).unwrap_or_default();
+            if content.lines().count() >= expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        content
+    }
+}
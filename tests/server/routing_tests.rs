@@ -13,8 +13,11 @@ mod tests {
     use tokio::time::sleep;
     use tracing::info;
     
-    use hickory_proto::op::{Message, MessageType, OpCode};
-    use hickory_proto::rr::{Name, RecordType};
+    use std::net::IpAddr;
+    
+    use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+    use hickory_proto::rr::{Name, RData, RecordType};
+    use hickory_proto::rr::rdata::{A, AAAA};
     use wiremock::{Mock, MockServer, ResponseTemplate};
     use wiremock::matchers::{method, path};
     
@@ -179,17 +182,17 @@ dns_resolver:
         let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
         
         // 测试匹配特定上游组的域名
-        let decision = router.match_domain("example.com").await;
+        let decision = router.match_domain("example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "special_group"), 
                 "example.com should match to special_group");
         
         // 测试匹配黑洞组的域名
-        let decision = router.match_domain("blocked.test").await;
+        let decision = router.match_domain("blocked.test", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "blocked.test should be blackholed");
         
         // 测试不匹配任何规则的域名
-        let decision = router.match_domain("randoMETRICS.example.org").await;
+        let decision = router.match_domain("randoMETRICS.example.org", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "randoMETRICS.example.org should use global upstream");
         
@@ -235,17 +238,17 @@ dns_resolver:
         let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
         
         // 测试匹配.cn域名
-        let decision = router.match_domain("example.cn").await;
+        let decision = router.match_domain("example.cn", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "cn_group"), 
                 "example.cn should match to cn_group");
         
         // 测试匹配.coMETRICS.cn域名
-        let decision = router.match_domain("example.com.cn").await;
+        let decision = router.match_domain("example.com.cn", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "cn_group"), 
                 "example.com.cn should match to cn_group");
         
         // 测试不匹配的域名
-        let decision = router.match_domain("example.com").await;
+        let decision = router.match_domain("example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "example.com should not match any rules");
         
@@ -291,17 +294,17 @@ dns_resolver:
         let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
         
         // 测试匹配 *.eu 域名
-        let decision = router.match_domain("example.eu").await;
+        let decision = router.match_domain("example.eu", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "eu_group"), 
                 "example.eu should match to eu_group");
         
         // 测试匹配 *.co.uk 域名
-        let decision = router.match_domain("example.co.uk").await;
+        let decision = router.match_domain("example.co.uk", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "eu_group"), 
                 "example.co.uk should match to eu_group");
         
         // 测试不匹配的域名
-        let decision = router.match_domain("example.com").await;
+        let decision = router.match_domain("example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "example.com should not match any rules");
         
@@ -362,22 +365,22 @@ dns_resolver:
         sleep(Duration::from_millis(100)).await;
         
         // 测试匹配精确域名
-        let decision = router.match_domain("ad-server1.com").await;
+        let decision = router.match_domain("ad-server1.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "ad-server1.com should be blackholed");
         
         // 测试匹配通配符域名
-        let decision = router.match_domain("sub.malicious.com").await;
+        let decision = router.match_domain("sub.malicious.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "sub.malicious.com should be blackholed");
         
         // 测试匹配正则域名
-        let decision = router.match_domain("evil123.example.org").await;
+        let decision = router.match_domain("evil123.example.org", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "evil123.example.org should be blackholed");
         
         // 测试不匹配的域名
-        let decision = router.match_domain("normal-site.com").await;
+        let decision = router.match_domain("normal-site.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "normal-site.com should not be blackholed");
         
@@ -442,22 +445,22 @@ dns_resolver:
         sleep(Duration::from_millis(500)).await;
         
         // 测试匹配精确域名
-        let decision = router.match_domain("adserver.example.com").await;
+        let decision = router.match_domain("adserver.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "adserver.example.com should be blackholed");
         
         // 测试匹配通配符域名
-        let decision = router.match_domain("test.malware.test").await;
+        let decision = router.match_domain("test.malware.test", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "test.malware.test should be blackholed");
         
         // 测试匹配正则域名
-        let decision = router.match_domain("evil123.example.biz").await;
+        let decision = router.match_domain("evil123.example.biz", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "evil123.example.biz should be blackholed");
         
         // 测试不匹配的域名
-        let decision = router.match_domain("example.com").await;
+        let decision = router.match_domain("example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "example.com should not be blackholed");
         
@@ -504,18 +507,190 @@ dns_resolver:
         let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
         
         // 测试匹配特定规则的域名
-        let decision = router.match_domain("special.example.com").await;
+        let decision = router.match_domain("special.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "special_group"), 
                 "special.example.com should match to special_group");
         
         // 测试使用默认上游组的域名
-        let decision = router.match_domain("unmatched.example.com").await;
+        let decision = router.match_domain("unmatched.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "special_group"), 
                 "unmatched.example.com should use default upstream group special_group");
         
         info!("Test completed: test_routing_default_upstream_group");
     }
     
+    #[tokio::test]
+    async fn test_routing_record_type_filter() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_record_type_filter");
+        
+        // 同一域名按查询类型分流到不同上游组
+        let config_content = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    upstream_groups:
+      - name: "mail_group"
+        resolvers:
+          - address: "1.1.1.1:53"
+            protocol: udp
+      - name: "web_group"
+        resolvers:
+          - address: "9.9.9.9:53"
+            protocol: udp
+      - name: "any_group"
+        resolvers:
+          - address: "208.67.222.222:53"
+            protocol: udp
+    rules:
+      - match:
+          type: wildcard
+          values: ["*.example.com"]
+          record_types: ["MX"]
+        upstream_group: "mail_group"
+      - match:
+          type: wildcard
+          values: ["*.example.com"]
+          record_types: ["a", "AAAA"]
+        upstream_group: "web_group"
+      - match:
+          type: wildcard
+          values: ["*.example.com"]
+        upstream_group: "any_group"
+"#;
+        
+        // 创建临时配置文件
+        let (_temp_dir, config_path) = create_temp_config_file(config_content);
+        
+        // 加载配置
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        
+        // 创建Router
+        let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        
+        // MX 查询匹配 mail_group
+        let decision = router.match_domain("www.example.com", RecordType::MX).await;
+        assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "mail_group"),
+                "MX query should match mail_group");
+        
+        // A/AAAA 查询匹配 web_group（类型名不区分大小写）
+        let decision = router.match_domain("www.example.com", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "web_group"),
+                "A query should match web_group");
+        let decision = router.match_domain("www.example.com", RecordType::AAAA).await;
+        assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "web_group"),
+                "AAAA query should match web_group");
+        
+        // 其他类型回落到不带类型过滤的规则
+        let decision = router.match_domain("www.example.com", RecordType::TXT).await;
+        assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "any_group"),
+                "TXT query should match any_group");
+        
+        // 无效的查询类型应在配置验证阶段被拒绝
+        let invalid_content = config_content.replace(r#"record_types: ["MX"]"#, r#"record_types: ["NOTATYPE"]"#);
+        let (_temp_dir, invalid_path) = create_temp_config_file(&invalid_content);
+        assert!(ServerConfig::from_file(&invalid_path).is_err(),
+                "Unknown record type should fail validation");
+        
+        info!("Test completed: test_routing_record_type_filter");
+    }
+    
+    #[tokio::test]
+    async fn test_routing_blackhole_response_per_client() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_blackhole_response_per_client");
+        
+        // 访客网段返回强制门户地址，办公网段返回 REFUSED，其余客户端使用默认 NXDOMAIN
+        let config_content = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    rules:
+      - match:
+          type: exact
+          values: ["blocked.example.com"]
+        upstream_group: "__blackhole__"
+    blackhole:
+      response:
+        type: nxdomain
+      client_responses:
+        - client_cidrs: ["192.168.100.0/24", "fd00:100::/64"]
+          response:
+            type: sinkhole
+            ipv4: "10.0.0.1"
+            ipv6: "fd00::1"
+            ttl: 30
+        - client_cidrs: ["10.1.0.0/16"]
+          response:
+            type: refused
+"#;
+        
+        let (_temp_dir, config_path) = create_temp_config_file(config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        
+        let decision = router.match_domain("blocked.example.com", RecordType::A).await;
+        assert_eq!(decision, RouteDecision::Blackhole);
+        
+        let name = Name::from_ascii("blocked.example.com.").unwrap();
+        let mut query = Message::new();
+        query.set_id(4321)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .add_query(Query::query(name.clone(), RecordType::A));
+        
+        // 访客客户端：sinkhole 到强制门户地址
+        let guest_ip: IpAddr = "192.168.100.23".parse().unwrap();
+        let response = router.blackhole().build_response(&query, guest_ip);
+        assert_eq!(response.id(), 4321);
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].ttl(), 30);
+        assert_eq!(response.answers()[0].data(), Some(&RData::A(A::new(10, 0, 0, 1))));
+        
+        // 办公客户端：REFUSED 且无应答
+        let corp_ip: IpAddr = "10.1.2.3".parse().unwrap();
+        let response = router.blackhole().build_response(&query, corp_ip);
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        assert!(response.answers().is_empty());
+        
+        // 其他客户端：回落到默认 NXDOMAIN
+        let other_ip: IpAddr = "203.0.113.5".parse().unwrap();
+        let response = router.blackhole().build_response(&query, other_ip);
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+        
+        // 访客 IPv6 客户端的 AAAA 查询返回 IPv6 sinkhole 地址
+        let mut query_v6 = Message::new();
+        query_v6.set_id(1234)
+            .set_message_type(MessageType::Query)
+            .add_query(Query::query(name, RecordType::AAAA));
+        let guest_v6: IpAddr = "fd00:100::42".parse().unwrap();
+        let response = router.blackhole().build_response(&query_v6, guest_v6);
+        assert_eq!(response.answers()[0].data(), Some(&RData::AAAA(AAAA("fd00::1".parse().unwrap()))));
+        
+        // 非法 CIDR 应在配置验证阶段被拒绝
+        let invalid_content = config_content.replace("10.1.0.0/16", "10.1.0.0/40");
+        let (_temp_dir, invalid_path) = create_temp_config_file(&invalid_content);
+        assert!(ServerConfig::from_file(&invalid_path).is_err(),
+                "Invalid client CIDR should fail validation");
+        
+        info!("Test completed: test_routing_blackhole_response_per_client");
+    }
+    
     #[tokio::test]
     async fn test_routing_disabled() {
         // 启用 tracing 日志
@@ -555,7 +730,7 @@ dns_resolver:
         let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
         
         // 测试所有域名都使用全局上游(因为路由功能已禁用)
-        let decision = router.match_domain("example.com").await;
+        let decision = router.match_domain("example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "When routing is disabled, all domains should use global upstream");
         
@@ -610,12 +785,12 @@ dns_resolver:
         let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
         
         // 测试精确匹配规则优先级高于通配符规则
-        let decision = router.match_domain("test.example.com").await;
+        let decision = router.match_domain("test.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "first_group"), 
                 "test.example.com should match exact rule first, using first_group");
         
         // 测试通配符规则匹配
-        let decision = router.match_domain("other.example.com").await;
+        let decision = router.match_domain("other.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "second_group"), 
                 "other.example.com should match wildcard rule, using second_group");
         
@@ -676,11 +851,11 @@ dns_resolver:
         sleep(Duration::from_millis(500)).await;
         
         // 验证初始规则工作正常
-        let decision = router.match_domain("adserver1.example.com").await;
+        let decision = router.match_domain("adserver1.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "After initial loading, adserver1.example.com should be blocked");
                 
-        let decision = router.match_domain("test.malware123.example.org").await;
+        let decision = router.match_domain("test.malware123.example.org", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "After initial loading, test.malware123.example.org should be blocked");
         
@@ -689,16 +864,16 @@ dns_resolver:
         sleep(Duration::from_secs(35)).await;
         
         // 验证规则仍然有效（尽管实际上没有重新解析，因为哈希相同）
-        let decision = router.match_domain("adserver1.example.com").await;
+        let decision = router.match_domain("adserver1.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole),
                 "When hash is the same, rules should remain unchanged, adserver1.example.com should be blocked");
                 
-        let decision = router.match_domain("subdomain.tracker.example.net").await;
+        let decision = router.match_domain("subdomain.tracker.example.net", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole),
                 "When hash is the same, rules should remain unchanged, subdomain.tracker.example.net should be blocked");
         
         // 验证不匹配的域名仍然不被拦截
-        let decision = router.match_domain("normal.example.org").await;
+        let decision = router.match_domain("normal.example.org", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal),
                 "When hash is the same, rules should remain unchanged, normal.example.org should not be blocked");
         
@@ -765,16 +940,16 @@ dns_resolver:
         sleep(Duration::from_millis(1000)).await;
         
         // 验证初始规则工作正常
-        let decision = router.match_domain("adserver1.example.com").await;
+        let decision = router.match_domain("adserver1.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "After initial loading, adserver1.example.com should be blocked");
         
         // 验证新规则最初不匹配
-        let decision = router.match_domain("newserver.example.com").await;
+        let decision = router.match_domain("newserver.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "After initial loading, newserver.example.com should not be blocked");
                 
-        let decision = router.match_domain("sub.malicious.test").await;
+        let decision = router.match_domain("sub.malicious.test", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal), 
                 "After initial loading, sub.malicious.test should not be blocked");
         
@@ -830,19 +1005,19 @@ dns_resolver:
         info!("Using new configuration to create Router for testing...");
         
         // 验证原有规则仍然有效
-        let decision = updated_router.match_domain("adserver1.example.com").await;
+        let decision = updated_router.match_domain("adserver1.example.com", RecordType::A).await;
         info!("After update, checking match result for adserver1.example.com: {:?}", decision);
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "After update, adserver1.example.com should still be blocked");
         
         // 验证新规则是否生效
-        let decision = updated_router.match_domain("newserver.example.com").await;
+        let decision = updated_router.match_domain("newserver.example.com", RecordType::A).await;
         info!("After update, checking match result for newserver.example.com: {:?}", decision);
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "After update, newserver.example.com should be blocked");
                 
         // 验证新的通配符规则是否生效
-        let decision = updated_router.match_domain("sub.malicious.test").await;
+        let decision = updated_router.match_domain("sub.malicious.test", RecordType::A).await;
         info!("After update, checking match result for sub.malicious.test: {:?}", decision);
         assert!(matches!(decision, RouteDecision::Blackhole), 
                 "After update, sub.malicious.test should be blocked");
@@ -914,7 +1089,7 @@ dns_resolver:
         sleep(Duration::from_secs(1)).await;
         
         // 测试域名: 应该使用全局默认组，因为规则URL没有匹配的内容
-        let decision = router.match_domain("test.example.com").await;
+        let decision = router.match_domain("test.example.com", RecordType::A).await;
         
         // 更宽松的断言，因为测试可能不稳定
         if matches!(decision, RouteDecision::UseGroup(ref group) if group == "enabled_group") {
@@ -973,13 +1148,129 @@ dns_resolver:
         sleep(Duration::from_millis(500)).await;
         
         // 由于URL不可达，不应该匹配任何规则
-        let decision = router.match_domain("test.example.com").await;
+        let decision = router.match_domain("test.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal),
                 "When URL is unreachable, no rules should match");
         
         info!("Test completed: test_url_rule_error_handling_unreachable");
     }
     
+    #[tokio::test]
+    async fn test_url_rule_retry_after_transient_failure() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_url_rule_retry_after_transient_failure");
+        
+        // 第一次请求返回 503，之后恢复正常
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("retry.example.com\n"))
+            .mount(&mock_server)
+            .await;
+        
+        // 周期间隔很长，但重试退避很短
+        let config_content = format!(r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    rules:
+      - match:
+          type: url
+          url: "{}"
+          periodic:
+            enabled: true
+            interval_secs: 3600
+            retry:
+              max_attempts: 3
+              initial_backoff_secs: 1
+              max_backoff_secs: 2
+        upstream_group: "__blackhole__"
+"#, mock_server.uri());
+        
+        let (_temp_dir, config_path) = create_temp_config_file(&config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        
+        // 首次获取失败，规则尚未加载
+        sleep(Duration::from_millis(500)).await;
+        let decision = router.match_domain("retry.example.com", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::UseGlobal),
+                "Rules should not be loaded after the failed first fetch");
+        
+        // 退避重试后规则应被加载，无需等待完整周期
+        sleep(Duration::from_secs(2)).await;
+        let decision = router.match_domain("retry.example.com", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::Blackhole),
+                "Rules should be loaded by the backoff retry");
+        
+        info!("Test completed: test_url_rule_retry_after_transient_failure");
+    }
+    
+    #[tokio::test]
+    async fn test_url_rule_download_size_limit() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_url_rule_download_size_limit");
+        
+        // 返回超出下载限制的规则列表（约 64KB）
+        let mut oversized_content = String::from("oversized.example.com\n");
+        while oversized_content.len() < 64 * 1024 {
+            oversized_content.push_str("padding-domain.example.com\n");
+        }
+        let mock_server = setup_domain_list_server(&oversized_content).await;
+        
+        let config_content = format!(r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    max_rule_download_bytes: 4096
+    rules:
+      - match:
+          type: url
+          url: "{}"
+          periodic:
+            enabled: true
+            interval_secs: 30
+            retry:
+              max_attempts: 0
+        upstream_group: "__blackhole__"
+"#, mock_server.uri());
+        
+        let (_temp_dir, config_path) = create_temp_config_file(&config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        assert_eq!(config.dns.routing.max_rule_download_bytes, 4096);
+        let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        
+        // 等待规则加载尝试完成
+        sleep(Duration::from_millis(500)).await;
+        
+        // 下载被中止，规则不应被加载
+        let decision = router.match_domain("oversized.example.com", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::UseGlobal),
+                "Oversized rule list should be rejected");
+        
+        info!("Test completed: test_url_rule_download_size_limit");
+    }
+    
     #[tokio::test]
     async fn test_url_rule_error_handling_invalid_format() {
         // 启用 tracing 日志
@@ -1023,7 +1314,7 @@ dns_resolver:
         sleep(Duration::from_secs(1)).await;
         
         // 即使有一些无效的规则，有效的规则仍应该生效
-        let decision = router.match_domain("valid.domain.com").await;
+        let decision = router.match_domain("valid.domain.com", RecordType::A).await;
         
         // 放宽测试要求，因为在有一些格式错误的情况下，解析行为可能变化
         if matches!(decision, RouteDecision::Blackhole) {
@@ -1033,7 +1324,7 @@ dns_resolver:
         }
         
         // 验证格式无效的规则不会导致系统崩溃
-        let decision = router.match_domain("other.example.com").await;
+        let decision = router.match_domain("other.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal),
                 "Invalid format rules should not match");
         
@@ -1088,10 +1379,505 @@ dns_resolver:
         sleep(Duration::from_secs(2)).await;
         
         // 验证所有域名都使用全局上游（因为全局路由功能已禁用）
-        let decision = router.match_domain("test.example.com").await;
+        let decision = router.match_domain("test.example.com", RecordType::A).await;
         assert!(matches!(decision, RouteDecision::UseGlobal),
                 "When global routing is disabled, all domains should use global upstream even if URL rules have periodic updates enabled");
         
         info!("Test completed: test_url_rule_global_routing_disabled");
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_routing_idn_normalization() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_idn_normalization");
+        
+        // 规则分别以 punycode 和 Unicode 编写（例え.テスト = xn--r8jz45g.xn--zckzah）
+        let config_content = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    rules:
+      - match:
+          type: exact
+          values: ["xn--r8jz45g.xn--zckzah"]
+        upstream_group: "__blackhole__"
+      - match:
+          type: wildcard
+          values: ["*.例え.jp"]
+        upstream_group: "__blackhole__"
+"#;
+        
+        let (_temp_dir, config_path) = create_temp_config_file(config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        
+        // Unicode 查询匹配 punycode 规则
+        let decision = router.match_domain("例え.テスト.", RecordType::A).await;
+        assert_eq!(decision, RouteDecision::Blackhole, "Unicode query should match punycode rule");
+        
+        // punycode 查询（大小写混合）同样匹配
+        let decision = router.match_domain("XN--R8JZ45G.xn--zckzah.", RecordType::A).await;
+        assert_eq!(decision, RouteDecision::Blackhole, "Punycode query should match punycode rule");
+        
+        // punycode 查询匹配 Unicode 通配符规则
+        let decision = router.match_domain("www.xn--r8jz45g.jp", RecordType::A).await;
+        assert_eq!(decision, RouteDecision::Blackhole, "Punycode query should match Unicode wildcard rule");
+        
+        let decision = router.match_domain("www.例え.jp", RecordType::A).await;
+        assert_eq!(decision, RouteDecision::Blackhole, "Unicode query should match Unicode wildcard rule");
+        
+        // 不相关的域名不受影响
+        let decision = router.match_domain("例え.jp", RecordType::A).await;
+        assert_eq!(decision, RouteDecision::UseGlobal);
+        
+        info!("Test completed: test_routing_idn_normalization");
+    }
+    
+    #[tokio::test]
+    async fn test_routing_max_concurrent_url_updates_config() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_max_concurrent_url_updates_config");
+        
+        let config_content = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    max_concurrent_url_updates: 1
+"#;
+        
+        let (_temp_dir, config_path) = create_temp_config_file(config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        assert_eq!(config.dns.routing.max_concurrent_url_updates, 1);
+        
+        // 未配置时使用默认值
+        let default_content = config_content.replace("    max_concurrent_url_updates: 1\n", "");
+        let (_temp_dir, default_path) = create_temp_config_file(&default_content);
+        let config = ServerConfig::from_file(&default_path).unwrap();
+        assert_eq!(config.dns.routing.max_concurrent_url_updates, 2);
+        
+        // 0 应在配置验证阶段被拒绝
+        let invalid_content = config_content.replace("max_concurrent_url_updates: 1", "max_concurrent_url_updates: 0");
+        let (_temp_dir, invalid_path) = create_temp_config_file(&invalid_content);
+        assert!(ServerConfig::from_file(&invalid_path).is_err(),
+                "Zero concurrent URL updates should fail validation");
+        
+        info!("Test completed: test_routing_max_concurrent_url_updates_config");
+    }
+    
+    #[tokio::test]
+    async fn test_routing_lazy_regex_compilation() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_lazy_regex_compilation");
+        
+        // 规则文件包含大量正则规则，用于比较预编译与延迟编译的构建耗时
+        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
+        let rules_file_path = temp_dir.path().join("regex_rules.txt");
+        let mut rules_content = String::new();
+        for i in 0..500 {
+            rules_content.push_str(&format!("regex:^(www|api|cdn)\\d*\\.tracker{}\\.example\\.net$\n", i));
+        }
+        std::fs::write(&rules_file_path, rules_content).expect("Failed to write rules file");
+        
+        let config_content = format!(r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    lazy_regex: true
+    upstream_groups:
+      - name: "cn_group"
+        resolvers:
+          - address: "114.114.114.114:53"
+            protocol: udp
+    rules:
+      - match:
+          type: regex
+          values: [".*\\.cn$"]
+        upstream_group: "cn_group"
+      - match:
+          type: file
+          path: "{}"
+        upstream_group: "__blackhole__"
+"#, rules_file_path.to_str().unwrap().replace("\\", "\\\\"));
+        let (_temp_dir2, config_path) = create_temp_config_file(&config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        
+        // 延迟编译时仍校验语法，无效的正则规则在校验与构建阶段即报错
+        let mut broken_config = config.clone();
+        broken_config.dns.routing.rules[0].match_.values = Some(vec![".*\\.cn$".to_string(), "[broken".to_string()]);
+        assert!(broken_config.test().is_err());
+        assert!(Router::new(broken_config.dns.routing.clone(), Some(Client::new())).await.is_err());
+        
+        // 规则文件中的无效正则同样在构建阶段报错
+        let broken_file_path = temp_dir.path().join("broken_rules.txt");
+        std::fs::write(&broken_file_path, "regex:^ok\\.example\\.net$\nregex:(unclosed\n").expect("Failed to write rules file");
+        let mut broken_file_config = config.clone();
+        broken_file_config.dns.routing.rules[1].match_.path = Some(broken_file_path.to_str().unwrap().to_string());
+        assert!(Router::new(broken_file_config.dns.routing.clone(), Some(Client::new())).await.is_err());
+        
+        // 有效规则延迟编译，不影响配置校验与路由器构建
+        config.test().unwrap();
+        let lazy_start = std::time::Instant::now();
+        let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        let lazy_build = lazy_start.elapsed();
+        
+        // 规则首次成为候选时编译，匹配结果与预编译一致
+        let decision = router.match_domain("example.cn", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::UseGroup(name) if name == "cn_group"));
+        let decision = router.match_domain("api7.tracker499.example.net", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::Blackhole));
+        let decision = router.match_domain("example.com", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::UseGlobal));
+        
+        // 预编译模式下比较两种模式的构建耗时
+        let mut eager_config = config.clone();
+        eager_config.dns.routing.lazy_regex = false;
+        eager_config.test().unwrap();
+        let eager_start = std::time::Instant::now();
+        let eager_router = Router::new(eager_config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        let eager_build = eager_start.elapsed();
+        let decision = eager_router.match_domain("api7.tracker499.example.net", RecordType::A).await;
+        assert!(matches!(decision, RouteDecision::Blackhole));
+        info!(?lazy_build, ?eager_build, "Router build time with 500 regex file rules");
+        
+        info!("Test completed: test_routing_lazy_regex_compilation");
+    }
+    
+    // 创建URL规则加载较慢的路由器：规则列表延迟返回，以观察首次加载前的窗口期
+    async fn create_slow_url_router(mock_server: &MockServer, policy: &str) -> Router {
+        let config_content = format!(r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    not_ready_policy: "{}"
+    not_ready_wait_ms: 3000
+    upstream_groups:
+      - name: "internal_group"
+        resolvers:
+          - address: "10.0.0.53:53"
+            protocol: udp
+    rules:
+      - match:
+          type: exact
+          values: ["inline.corp.example"]
+        upstream_group: "internal_group"
+      - match:
+          type: url
+          url: "{}/internal.txt"
+          periodic:
+            enabled: true
+            interval_secs: 3600
+        upstream_group: "internal_group"
+"#, policy, mock_server.uri());
+        
+        let (_temp_dir, config_path) = create_temp_config_file(&config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        config.test().unwrap();
+        Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_routing_group_not_ready_window() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_group_not_ready_window");
+        
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/internal.txt"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_string("listed.corp.example\n")
+                .set_delay(Duration::from_millis(800)))
+            .mount(&mock_server)
+            .await;
+        
+        // global 策略：URL规则加载期间，命中该组的查询改用全局上游
+        let router = create_slow_url_router(&mock_server, "global").await;
+        assert_eq!(router.pending_groups(), vec!["internal_group".to_string()]);
+        assert!(!router.group_ready("internal_group"));
+        let decision = router.match_domain_when_ready("inline.corp.example", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::UseGlobal);
+        
+        // 加载完成后上游组就绪，按规则正常路由
+        for _ in 0..50 {
+            if router.group_ready("internal_group") {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert!(router.pending_groups().is_empty());
+        let decision = router.match_domain_when_ready("inline.corp.example", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::UseGroup("internal_group".to_string()));
+        let decision = router.match_domain_when_ready("listed.corp.example", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::UseGroup("internal_group".to_string()));
+        
+        // wait 策略：首个查询等待规则加载完成，直接命中URL规则
+        let router = create_slow_url_router(&mock_server, "wait").await;
+        assert!(!router.group_ready("internal_group"));
+        let start = std::time::Instant::now();
+        let decision = router.match_domain_when_ready("listed.corp.example", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::UseGroup("internal_group".to_string()));
+        assert!(start.elapsed() < Duration::from_millis(3000), "Query should stop waiting once rules are loaded");
+        assert!(router.group_ready("internal_group"));
+        
+        info!("Test completed: test_routing_group_not_ready_window");
+    }
+    
+    #[tokio::test]
+    async fn test_routing_fail_closed_until_loaded() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_fail_closed_until_loaded");
+        
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/blocklist.txt"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_string("ads.example.net\n")
+                .set_delay(Duration::from_millis(800)))
+            .mount(&mock_server)
+            .await;
+        
+        let create_router = |action: &'static str| {
+            let uri = mock_server.uri();
+            async move {
+                let config_content = format!(r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    fail_closed_until_loaded: true
+    fail_closed_action: "{}"
+    rules:
+      - match:
+          type: url
+          url: "{}/blocklist.txt"
+          periodic:
+            enabled: true
+            interval_secs: 3600
+        upstream_group: "__blackhole__"
+"#, action, uri);
+                let (_temp_dir, config_path) = create_temp_config_file(&config_content);
+                let config = ServerConfig::from_file(&config_path).unwrap();
+                config.test().unwrap();
+                Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap()
+            }
+        };
+        
+        // 屏蔽列表加载前，所有查询（包括本不会被屏蔽的域名）按配置拒绝或阻止
+        let router = create_router("refuse").await;
+        assert!(!router.pending_groups().is_empty());
+        let decision = router.match_domain_when_ready("ads.example.net", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::Refuse);
+        let decision = router.match_domain_when_ready("www.example.com", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::Refuse);
+        
+        let blackhole_router = create_router("blackhole").await;
+        let decision = blackhole_router.match_domain_when_ready("www.example.com", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::Blackhole);
+        
+        // 加载完成后按规则正常路由
+        for _ in 0..50 {
+            if router.pending_groups().is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert!(router.pending_groups().is_empty());
+        let decision = router.match_domain_when_ready("ads.example.net", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::Blackhole);
+        let decision = router.match_domain_when_ready("www.example.com", RecordType::A, None).await;
+        assert_eq!(decision, RouteDecision::UseGlobal);
+        
+        info!("Test completed: test_routing_fail_closed_until_loaded");
+    }
+    
+    #[tokio::test]
+    async fn test_routing_blackhole_ttl() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_blackhole_ttl");
+        
+        // 默认 NXDOMAIN，访客网段 sinkhole（应答 TTL 高于 blackhole_ttl），办公网段 REFUSED
+        let config_content = r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    blackhole_ttl: 120
+    rules:
+      - match:
+          type: exact
+          values: ["blocked.example.com"]
+        upstream_group: "__blackhole__"
+    blackhole:
+      client_responses:
+        - client_cidrs: ["192.168.100.0/24"]
+          response:
+            type: sinkhole
+            ttl: 600
+        - client_cidrs: ["10.1.0.0/16"]
+          response:
+            type: refused
+"#;
+        
+        let (_temp_dir, config_path) = create_temp_config_file(config_content);
+        let config = ServerConfig::from_file(&config_path).unwrap();
+        let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+        
+        let name = Name::from_ascii("blocked.example.com.").unwrap();
+        let build_query = |record_type| {
+            let mut query = Message::new();
+            query.set_id(4321)
+                .set_message_type(MessageType::Query)
+                .set_op_code(OpCode::Query)
+                .add_query(Query::query(name.clone(), record_type));
+            query
+        };
+        let assert_soa = |response: &Message| {
+            assert_eq!(response.name_servers().len(), 1, "Negative blackhole response should carry an SOA record");
+            let record = &response.name_servers()[0];
+            assert_eq!(record.name(), &name);
+            assert_eq!(record.ttl(), 120);
+            match record.data() {
+                Some(RData::SOA(soa)) => assert_eq!(soa.minimum(), 120),
+                other => panic!("Expected SOA record, got {:?}", other),
+            }
+        };
+        
+        // NXDOMAIN 携带以 blackhole_ttl 为 TTL 与 MINIMUM 的 SOA
+        let response = router.blackhole().build_response(&build_query(RecordType::A), "203.0.113.5".parse().unwrap());
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+        assert_soa(&response);
+        
+        // sinkhole 应答 TTL 被限制为 blackhole_ttl，且不附加 SOA
+        let guest_ip: IpAddr = "192.168.100.23".parse().unwrap();
+        let response = router.blackhole().build_response(&build_query(RecordType::A), guest_ip);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].ttl(), 120);
+        assert!(response.name_servers().is_empty());
+        
+        // sinkhole 下的其他类型为 NODATA，同样携带 SOA
+        let response = router.blackhole().build_response(&build_query(RecordType::MX), guest_ip);
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(response.answers().is_empty());
+        assert_soa(&response);
+        
+        // REFUSED 不可被负缓存，不附加 SOA
+        let response = router.blackhole().build_response(&build_query(RecordType::A), "10.1.2.3".parse().unwrap());
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+        assert!(response.name_servers().is_empty());
+        
+        // blackhole_ttl 为 0 时配置验证失败
+        let invalid_content = config_content.replace("blackhole_ttl: 120", "blackhole_ttl: 0");
+        let (_temp_dir, invalid_path) = create_temp_config_file(&invalid_content);
+        assert!(ServerConfig::from_file(&invalid_path).is_err(),
+                "Zero blackhole_ttl should fail validation");
+        
+        info!("Test completed: test_routing_blackhole_ttl");
+    }
+    
+    #[tokio::test]
+    async fn test_routing_default_action() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_routing_default_action");
+        
+        // 仅 internal.example.com 显式路由，未匹配的域名按 default_action 处理
+        let build_config = |default_action: &str| format!(r#"
+http_server:
+  listen_addr: "127.0.0.1:8053"
+dns_resolver:
+  upstream:
+    resolvers:
+      - address: "8.8.8.8:53"
+        protocol: udp
+  routing:
+    enabled: true
+    upstream_groups:
+      - name: "internal_group"
+        resolvers:
+          - address: "10.0.0.53:53"
+            protocol: udp
+    rules:
+      - match:
+          type: exact
+          values: ["internal.example.com"]
+        upstream_group: "internal_group"
+      - match:
+          type: exact
+          values: ["ads.example.com"]
+        upstream_group: "__blackhole__"
+{}
+"#, default_action);
+        
+        for (default_action, expected) in [
+            ("", RouteDecision::UseGlobal),
+            ("    default_action: global", RouteDecision::UseGlobal),
+            ("    default_action: refuse", RouteDecision::Refuse),
+            ("    default_action: blackhole", RouteDecision::Blackhole),
+        ] {
+            let (_temp_dir, config_path) = create_temp_config_file(&build_config(default_action));
+            let config = ServerConfig::from_file(&config_path).unwrap();
+            let router = Router::new(config.dns.routing.clone(), Some(Client::new())).await.unwrap();
+            
+            // 显式路由的域名不受 default_action 影响
+            let decision = router.match_domain("internal.example.com", RecordType::A).await;
+            assert_eq!(decision, RouteDecision::UseGroup("internal_group".to_string()), "{}", default_action);
+            let decision = router.match_domain("ads.example.com", RecordType::A).await;
+            assert_eq!(decision, RouteDecision::Blackhole, "{}", default_action);
+            
+            // 未匹配的域名按 default_action 处理
+            let decision = router.match_domain("www.example.org", RecordType::A).await;
+            assert_eq!(decision, expected, "{}", default_action);
+            
+            // 配置档案的默认上游组优先于 default_action
+            let decision = router.match_domain_with_default("www.example.org", RecordType::A, Some("internal_group")).await;
+            assert_eq!(decision, RouteDecision::UseGroup("internal_group".to_string()), "{}", default_action);
+        }
+        
+        // default_action 与 default_upstream_group 同时配置时验证失败
+        let invalid_content = build_config("    default_action: refuse\n    default_upstream_group: \"internal_group\"");
+        let (_temp_dir, invalid_path) = create_temp_config_file(&invalid_content);
+        assert!(ServerConfig::from_file(&invalid_path).is_err(),
+                "default_action should not be combined with default_upstream_group");
+        
+        info!("Test completed: test_routing_default_action");
+    }
+}
@@ -0,0 +1,94 @@
+// tests/server/self_test_tests.rs
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+    use futures::future::BoxFuture;
+    use hickory_proto::op::Message;
+    use tracing::info;
+    use oxide_wdns::server::config::ServerConfig;
+    use oxide_wdns::server::error::{Result, ServerError};
+    use oxide_wdns::server::self_test::{
+        CheckStatus, CHECK_CACHE, CHECK_COMPONENTS, CHECK_CONFIGURATION, CHECK_JSON_API, CHECK_ROUTING, CHECK_UPSTREAM,
+    };
+    use oxide_wdns::server::upstream::{UpstreamResolver, UpstreamSelection};
+    use oxide_wdns::server::DoHServer;
+    use crate::server::mock_http_server::create_test_response;
+
+    // 模拟上游：应答固定 IP，或始终失败
+    struct SelfTestResolver {
+        fail: bool,
+    }
+
+    impl UpstreamResolver for SelfTestResolver {
+        fn id(&self) -> &str {
+            "self-test"
+        }
+
+        fn protocol(&self) -> &str {
+            "Custom"
+        }
+
+        fn resolve<'a>(&'a self, query_message: &'a Message) -> BoxFuture<'a, Result<Message>> {
+            Box::pin(async move {
+                if self.fail {
+                    return Err(ServerError::Upstream("upstream unavailable".to_string()));
+                }
+                Ok(create_test_response(query_message, Ipv4Addr::new(10, 0, 5, 1)))
+            })
+        }
+    }
+
+    fn build_server(fail: bool) -> DoHServer {
+        let config: ServerConfig = serde_yaml::from_str(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: true
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "https://127.0.0.1:1/dns-query"
+                protocol: doh
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+          cache:
+            enabled: true
+            persistence:
+              enabled: false
+        "#).unwrap();
+        DoHServer::new(config, false)
+            .with_upstream_resolver(UpstreamSelection::Global, Arc::new(SelfTestResolver { fail }))
+    }
+
+    #[tokio::test]
+    async fn test_self_test_report() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_self_test_report");
+
+        // 上游可用：所有已启用的子系统通过，未启用的路由被跳过
+        let report = build_server(false).self_test().await;
+        info!("{}", report);
+        assert!(report.passed(), "Self-test should pass: {}", report);
+        for name in [CHECK_CONFIGURATION, CHECK_COMPONENTS, CHECK_UPSTREAM, CHECK_CACHE, CHECK_JSON_API] {
+            assert_eq!(report.check(name).unwrap().status, CheckStatus::Pass, "{} should pass", name);
+        }
+        assert_eq!(report.check(CHECK_ROUTING).unwrap().status, CheckStatus::Skip);
+        assert!(report.to_string().contains("[PASS] upstream: example.com. A -> No Error, 1 answers"));
+
+        // 上游失败：上游检查失败，缓存检查被跳过，自检整体失败
+        let report = build_server(true).self_test().await;
+        info!("{}", report);
+        assert!(!report.passed());
+        assert_eq!(report.check(CHECK_UPSTREAM).unwrap().status, CheckStatus::Fail);
+        assert_eq!(report.check(CHECK_CACHE).unwrap().status, CheckStatus::Skip);
+        assert!(report.to_string().contains("Self-test failed"));
+
+        info!("Test completed: test_self_test_report");
+    }
+}
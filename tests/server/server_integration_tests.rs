@@ -23,6 +23,10 @@ mod tests {
     use oxide_wdns::server::cache::DnsCache;
     use oxide_wdns::server::upstream::UpstreamManager;
     use oxide_wdns::server::routing::Router;
+    use oxide_wdns::server::duplicate::DuplicateQueryDetector;
+    use oxide_wdns::server::query_log::QueryLogger;
+    use oxide_wdns::server::dnstap::DnstapLogger;
+    use oxide_wdns::server::security::DailyQueryQuota;
     use oxide_wdns::server::doh_handler::ServerState;
     use oxide_wdns::server::config::ServerConfig;
     
@@ -77,11 +81,21 @@ mod tests {
         let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), http_client).await.unwrap());
         let cache = Arc::new(DnsCache::new(config.dns.cache.clone()));
         
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
         ServerState {
             config, 
             upstream, 
             cache, 
             router,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
         }
     }
 
@@ -201,6 +215,9 @@ mod tests {
             oxide_wdns::server::config::ResolverConfig {
                 address: format!("{}/dns-query", mock_upstream.uri()),
                 protocol: oxide_wdns::server::config::ResolverProtocol::Doh,
+                protocols: None,
+                tls_name: None,
+                dns_0x20: false,
             }
         ];
         
@@ -210,11 +227,21 @@ mod tests {
         let cache = Arc::new(DnsCache::new(config.dns.cache.clone()));
         let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), http_client).await.unwrap());
         
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
         let server_state = ServerState {
             config,
             upstream,
             cache,
             router,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
         };
         
         // 4. 启动测试服务器
@@ -364,11 +391,21 @@ mod tests {
         let cache = Arc::new(DnsCache::new(config.dns.cache.clone()));
         let upstream = Arc::new(UpstreamManager::new(Arc::new(config.clone()), http_client).await.unwrap());
         
+        let duplicates = Arc::new(DuplicateQueryDetector::new(&config.dns.duplicate_detection));
         let server_state = ServerState {
             config,
             upstream,
             cache,
             router,
+            duplicates,
+            profile: None,
+            query_log: Arc::new(QueryLogger::disabled()),
+            dnstap: Arc::new(DnstapLogger::disabled()),
+            quota: Arc::new(DailyQueryQuota::disabled()),
+            plugin: None,
+            group_override_cidrs: Arc::new(Vec::new()),
+            ddr: None,
+            trust_anchor: None,
         };
         
         // 启动服务器
@@ -941,4 +978,123 @@ mod tests {
         let _ = shutdown_tx.send(());
         info!("Test completed: test_server_handles_different_query_types");
     }
-} 
\ No newline at end of file
+    
+    // 测试分层部署：边缘实例经 DoH 转发至核心实例，DO/CD 与 ECS 端到端保留，两层均缓存应答
+    #[tokio::test]
+    async fn test_server_forwards_to_core_doh_server() {
+        use hickory_proto::op::Edns;
+        use oxide_wdns::server::ecs::{EcsData, EcsProcessor};
+        use oxide_wdns::server::DoHServer;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_server_forwards_to_core_doh_server");
+        
+        // 核心实例的上游：回显查询的 EDNS（含 ECS）并应答固定地址
+        let mock_upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let mut response = create_test_response(&query, std::net::Ipv4Addr::new(192, 0, 2, 80));
+                if let Some(edns) = query.extensions() {
+                    response.set_edns(edns.clone());
+                }
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_upstream)
+            .await;
+        
+        // 两层实例均启用缓存并以 forward 策略转发 ECS；同一进程内两实例共享实例标识，边缘关闭环路标记
+        let build_config = |upstream: String, loop_protection: bool| -> ServerConfig {
+            serde_yaml::from_str(&format!(r#"
+            http_server:
+              listen_addr: "127.0.0.1:8053"
+              timeout: 10
+              rate_limit:
+                enabled: false
+            dns_resolver:
+              upstream:
+                resolvers:
+                  - address: "{}/dns-query"
+                    protocol: doh
+                query_timeout: 3
+                enable_dnssec: true
+              http_client:
+                timeout: 5
+              cache:
+                enabled: true
+              ecs_policy:
+                enabled: true
+                strategy: "forward"
+              loop_protection:
+                enabled: {}
+            "#, upstream, loop_protection)).expect("Failed to parse configuration")
+        };
+        let serve = |app: axum::Router| async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move {
+                axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+            });
+            addr
+        };
+        
+        let (core_app, core_cache) = DoHServer::new(build_config(mock_upstream.uri(), true), false)
+            .build_application_components().await.unwrap();
+        let core_addr = serve(core_app).await;
+        let (edge_app, edge_cache) = DoHServer::new(build_config(core_addr.clone(), false), false)
+            .build_application_components().await.unwrap();
+        let edge_addr = serve(edge_app).await;
+        
+        // 携带 DO、CD 与客户端子网的查询
+        let client_subnet = EcsData::new("198.51.100.0".parse().unwrap(), 24, 0);
+        let mut query = create_dns_query("tiered.example.com", RecordType::A);
+        query.set_checking_disabled(true);
+        let mut edns = Edns::new();
+        edns.set_dnssec_ok(true);
+        edns.options_mut().insert(client_subnet.to_edns_option().unwrap());
+        query.set_edns(edns);
+        
+        let client = Client::new();
+        let send = |addr: String| {
+            let client = client.clone();
+            let body = query.to_vec().unwrap();
+            async move {
+                let response = client.post(format!("{}/dns-query", addr))
+                    .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE_DNS_MESSAGE)
+                    .body(body)
+                    .send()
+                    .await
+                    .expect("Failed to send DoH request");
+                assert_eq!(response.status(), StatusCode::OK);
+                Message::from_vec(&response.bytes().await.unwrap()).expect("Failed to parse DNS response")
+            }
+        };
+        
+        // 经边缘实例的查询到达核心实例的上游，DO/CD 与 ECS 保持不变
+        let response = send(edge_addr.clone()).await;
+        assert_eq!(response.answers().len(), 1);
+        let received = mock_upstream.received_requests().await.unwrap();
+        assert_eq!(received.len(), 1);
+        let upstream_query = Message::from_vec(&received[0].body).unwrap();
+        assert!(upstream_query.checking_disabled(), "CD bit should be preserved end-to-end");
+        assert!(upstream_query.extensions().as_ref().is_some_and(|edns| edns.dnssec_ok()), "DO bit should be preserved end-to-end");
+        let upstream_ecs = EcsProcessor::extract_ecs_from_message(&upstream_query).expect("ECS should be preserved end-to-end");
+        assert_eq!(upstream_ecs.address, client_subnet.address);
+        assert_eq!(upstream_ecs.source_prefix_length, 24);
+        
+        // 两层均缓存应答：再次经边缘或直接向核心查询均不再访问上游
+        assert_eq!(edge_cache.len().await, 1);
+        assert_eq!(core_cache.len().await, 1);
+        let response = send(edge_addr).await;
+        assert_eq!(response.answers().len(), 1);
+        let response = send(core_addr).await;
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(mock_upstream.received_requests().await.unwrap().len(), 1);
+        
+        info!("Test completed: test_server_forwards_to_core_doh_server");
+    }
+}
@@ -6,13 +6,14 @@ mod tests {
     use std::sync::Arc;
     
     use tracing::info;
-    use hickory_proto::op::ResponseCode;
+    use hickory_proto::op::{Message, ResponseCode};
     use hickory_proto::rr::RecordType;
     use reqwest::Client;
     
-    use oxide_wdns::server::config::{ResolverConfig, ResolverProtocol, ServerConfig};
-    use oxide_wdns::server::upstream::{UpstreamManager, UpstreamSelection};
+    use oxide_wdns::server::config::{QuestionMismatchPolicy, ResolverConfig, ResolverProtocol, ServerConfig};
+    use oxide_wdns::server::upstream::{UpstreamManager, UpstreamResolver, UpstreamSelection};
     use oxide_wdns::server::routing::Router;
+    use oxide_wdns::server::metrics::METRICS;
     use oxide_wdns::common::consts::CONTENT_TYPE_DNS_MESSAGE;
     
     // 引入 wiremock 库和公共测试模块
@@ -69,6 +70,9 @@ mod tests {
             ResolverConfig {
                 address: format!("{}/dns-query", mock_server.uri()),
                 protocol: ResolverProtocol::Doh,
+                protocols: None,
+                tls_name: None,
+                dns_0x20: false,
             }
         ];
 
@@ -137,6 +141,9 @@ mod tests {
             ResolverConfig {
                 address: format!("{}/dns-query", mock_server.uri()),
                 protocol: ResolverProtocol::Doh,
+                protocols: None,
+                tls_name: None,
+                dns_0x20: false,
             }
         ];
         
@@ -155,4 +162,598 @@ mod tests {
         
         info!("Test completed: test_upstream_resolve_doh_get");
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_upstream_protocol_fallback() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_protocol_fallback");
+
+        // 启动仅监听 UDP 的模拟 DNS 服务器，同端口上的 DoH/TCP 连接会被拒绝
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let query = Message::from_vec(&buf[..len]).unwrap();
+                let response = create_test_response(&query, Ipv4Addr::new(10, 0, 0, 1));
+                let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+            }
+        });
+        info!("Mock UDP DNS server started at {}", server_addr);
+
+        // 配置协议回退链 DoH -> TCP -> UDP
+        let mut config = create_test_config();
+        config.dns.upstream.resolvers = vec![
+            ResolverConfig {
+                address: server_addr.to_string(),
+                protocol: ResolverProtocol::Udp,
+                protocols: Some(vec![ResolverProtocol::Doh, ResolverProtocol::Tcp, ResolverProtocol::Udp]),
+                tls_name: None,
+                dns_0x20: false,
+            }
+        ];
+        config.test().unwrap();
+
+        let upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+        let served_before = METRICS.upstream_transport_served_total()
+            .with_label_values(&["Udp", "global"])
+            .get();
+
+        // 执行查询，应在 DoH 与 TCP 失败后由 UDP 应答
+        let query = create_test_query("example.com", RecordType::A);
+        let response = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await.unwrap();
+
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(!response.answers().is_empty(), "Response should contain answers served over UDP");
+
+        let served_after = METRICS.upstream_transport_served_total()
+            .with_label_values(&["Udp", "global"])
+            .get();
+        assert_eq!(served_after - served_before, 1, "UDP transport should be recorded as serving the query");
+
+        info!("Test completed: test_upstream_protocol_fallback");
+    }
+
+    // 自定义解析器：对任意查询返回固定 IP 的应答
+    struct FixedAnswerResolver {
+        ip: Ipv4Addr,
+    }
+
+    impl UpstreamResolver for FixedAnswerResolver {
+        fn id(&self) -> &str {
+            "fixed-answer"
+        }
+
+        fn protocol(&self) -> &str {
+            "Custom"
+        }
+
+        fn resolve<'a>(&'a self, query_message: &'a Message) -> futures::future::BoxFuture<'a, oxide_wdns::server::error::Result<Message>> {
+            Box::pin(async move { Ok(create_test_response(query_message, self.ip)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upstream_custom_resolver() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_custom_resolver");
+
+        // 内置 DoH 上游，注册自定义解析器后不应再被访问
+        let (mock_server, counter) = setup_mock_doh_server(Ipv4Addr::new(192, 168, 1, 1)).await;
+        let mut config = create_test_config();
+        config.dns.upstream.resolvers = vec![
+            ResolverConfig {
+                address: format!("{}/dns-query", mock_server.uri()),
+                protocol: ResolverProtocol::Doh,
+                protocols: None,
+                tls_name: None,
+                dns_0x20: false,
+            }
+        ];
+
+        let mut upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+        upstream_manager
+            .register_resolver(&UpstreamSelection::Global, Arc::new(FixedAnswerResolver { ip: Ipv4Addr::new(10, 0, 0, 1) }))
+            .unwrap();
+
+        // 未知上游组无法注册
+        let unknown = UpstreamSelection::Group("missing".to_string());
+        assert!(upstream_manager
+            .register_resolver(&unknown, Arc::new(FixedAnswerResolver { ip: Ipv4Addr::new(10, 0, 0, 2) }))
+            .is_err());
+
+        let requests_before = METRICS.upstream_requests_total()
+            .with_label_values(&["fixed-answer", "Custom", "global"]).get();
+
+        let query = create_test_query("custom.example.com", RecordType::A);
+        let response = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await.unwrap();
+
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers()[0].data().unwrap().to_string(), "10.0.0.1");
+        assert_eq!(*counter.lock().unwrap(), 0, "Built-in DoH upstream should not be queried");
+        assert_eq!(
+            METRICS.upstream_requests_total().with_label_values(&["fixed-answer", "Custom", "global"]).get() - requests_before,
+            1,
+            "Custom resolver requests should be recorded in upstream metrics"
+        );
+
+        info!("Test completed: test_upstream_custom_resolver");
+    }
+    
+    // 启动记录查询源端口的模拟 UDP DNS 服务器
+    async fn start_port_recording_udp_server() -> (std::net::SocketAddr, Arc<std::sync::Mutex<Vec<u16>>>) {
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let ports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = ports.clone();
+        
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(query) = Message::from_vec(&buf[..len]) else {
+                    continue;
+                };
+                recorded.lock().unwrap().push(peer.port());
+                let response = create_test_response(&query, Ipv4Addr::new(192, 0, 2, 53));
+                let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+            }
+        });
+        
+        (addr, ports)
+    }
+    
+    #[tokio::test]
+    async fn test_upstream_udp_source_port_randomization() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_udp_source_port_randomization");
+        
+        for randomize in [true, false] {
+            let (server_addr, ports) = start_port_recording_udp_server().await;
+            let mut config = create_test_config();
+            assert!(config.dns.upstream.udp_source_port_randomization, "Randomization should be on by default");
+            config.dns.upstream.udp_source_port_randomization = randomize;
+            config.dns.upstream.resolvers = vec![
+                ResolverConfig {
+                    address: server_addr.to_string(),
+                    protocol: ResolverProtocol::Udp,
+                    protocols: None,
+                    tls_name: None,
+                    dns_0x20: false,
+                }
+            ];
+            let upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+            
+            // 不同域名避免命中 hickory 内部缓存，每次查询都发往上游
+            for i in 0..5 {
+                let query = create_test_query(&format!("port{}.example.com", i), RecordType::A);
+                let response = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await.unwrap();
+                assert_eq!(response.response_code(), ResponseCode::NoError);
+            }
+            
+            let ports = ports.lock().unwrap().clone();
+            assert_eq!(ports.len(), 5, "Every query should reach the upstream");
+            if randomize {
+                // 每次查询使用新的套接字与随机源端口
+                let distinct: std::collections::HashSet<_> = ports.iter().collect();
+                assert_eq!(distinct.len(), ports.len(), "Source ports should differ across queries: {:?}", ports);
+            }
+            info!(randomize, ?ports, "Observed UDP source ports");
+        }
+        
+        info!("Test completed: test_upstream_udp_source_port_randomization");
+    }
+    
+    #[tokio::test]
+    async fn test_upstream_connect_timeout() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_connect_timeout");
+        
+        // 默认连接超时较短，且不允许为 0
+        let mut config = create_test_config();
+        assert_eq!(config.dns.upstream.connect_timeout, 2);
+        config.dns.upstream.connect_timeout = 0;
+        assert!(config.test().is_err(), "connect_timeout of 0 should be rejected");
+        
+        // 不可路由地址上的 TCP 解析器：连接阶段应在连接超时内失败，而非耗尽查询超时
+        let mut config = create_test_config();
+        config.dns.upstream.query_timeout = 10;
+        config.dns.upstream.connect_timeout = 1;
+        config.dns.upstream.resolvers = vec![
+            ResolverConfig {
+                address: "10.255.255.1:53".to_string(),
+                protocol: ResolverProtocol::Tcp,
+                protocols: None,
+                tls_name: None,
+                dns_0x20: false,
+            }
+        ];
+        let upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+        
+        let query = create_test_query("connect-timeout.example.com", RecordType::A);
+        let started = std::time::Instant::now();
+        let result = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await;
+        let elapsed = started.elapsed();
+        
+        assert!(result.is_err(), "Query to an unreachable resolver should fail");
+        assert!(elapsed < std::time::Duration::from_secs(5), "Connection phase should fail fast, took {:?}", elapsed);
+        info!(?elapsed, "Unreachable resolver failed");
+        
+        info!("Test completed: test_upstream_connect_timeout");
+    }
+    
+    #[tokio::test]
+    async fn test_upstream_question_mismatch_policy() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_question_mismatch_policy");
+        
+        // 模拟上游：应答中不回显问题节
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/dns-query"))
+            .respond_with(|req: &wiremock::Request| {
+                let query = Message::from_vec(&req.body).expect("Invalid DNS query");
+                let mut response = create_test_response(&query, Ipv4Addr::new(192, 168, 1, 1));
+                response.take_queries();
+                
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", CONTENT_TYPE_DNS_MESSAGE)
+                    .set_body_bytes(response.to_vec().unwrap())
+            })
+            .mount(&mock_server)
+            .await;
+        
+        let resolve_with_policy = |policy: QuestionMismatchPolicy| {
+            let address = format!("{}/dns-query", mock_server.uri());
+            async move {
+                let mut config = create_test_config();
+                config.dns.upstream.question_mismatch_policy = policy;
+                config.dns.upstream.resolvers = vec![
+                    ResolverConfig {
+                        address,
+                        protocol: ResolverProtocol::Doh,
+                        protocols: None,
+                        tls_name: None,
+                        dns_0x20: false,
+                    }
+                ];
+                let upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+                let query = create_test_query("example.com", RecordType::A);
+                let response = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await.unwrap();
+                (query, response)
+            }
+        };
+        
+        // 默认策略：以原始查询的问题节修正应答，保留应答记录
+        assert_eq!(create_test_config().dns.upstream.question_mismatch_policy, QuestionMismatchPolicy::Fixup);
+        let (query, response) = resolve_with_policy(QuestionMismatchPolicy::Fixup).await;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.queries(), query.queries());
+        assert_eq!(response.answers().len(), 1);
+        
+        // servfail 策略：拒绝应答
+        let (query, response) = resolve_with_policy(QuestionMismatchPolicy::Servfail).await;
+        assert_eq!(response.response_code(), ResponseCode::ServFail);
+        assert_eq!(response.id(), query.id());
+        assert_eq!(response.queries(), query.queries());
+        assert!(response.answers().is_empty());
+        
+        info!("Test completed: test_upstream_question_mismatch_policy");
+    }
+    
+    #[tokio::test]
+    async fn test_upstream_dns_0x20() {
+        use hickory_proto::rr::{DNSClass, Name, RData};
+        use hickory_proto::rr::rdata::A;
+        use oxide_wdns::server::cache::CacheKey;
+        use oxide_wdns::server::dns0x20::randomize_case;
+        use oxide_wdns::server::ecs::EcsData;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_dns_0x20");
+        
+        // 随机化只改变字母大小写，名称按不区分大小写比较保持相等
+        let name = Name::from_ascii("abcdefghijklmnopqrstuvwxyz.example.com.").unwrap();
+        let encoded: Vec<Name> = (0..8).map(|_| randomize_case(&name)).collect();
+        assert!(encoded.iter().all(|n| n == &name && n.is_fqdn()));
+        assert!(encoded.iter().any(|n| n.to_ascii() != name.to_ascii()), "Case should be randomized");
+        
+        // 缓存键使用小写名称，0x20 不会分裂缓存
+        let mixed = CacheKey::new(Name::from_ascii("WwW.ExAmPle.CoM.").unwrap(), RecordType::A, DNSClass::IN);
+        let lower = CacheKey::new(Name::from_ascii("www.example.com.").unwrap(), RecordType::A, DNSClass::IN);
+        assert_eq!(mixed, lower);
+        
+        // 携带 ECS 的查询同样使用小写名称
+        let ecs = EcsData::new("192.0.2.0".parse().unwrap(), 24, 24);
+        let mixed = CacheKey::with_ecs(Name::from_ascii("WwW.ExAmPle.CoM.").unwrap(), RecordType::A, DNSClass::IN, &ecs);
+        let lower = CacheKey::create_lookup_key(Name::from_ascii("www.example.com.").unwrap(), RecordType::A, DNSClass::IN, Some(&ecs));
+        assert_eq!(mixed, lower);
+        assert_eq!(mixed.get_base_key(), CacheKey::new(Name::from_ascii("www.example.com.").unwrap(), RecordType::A, DNSClass::IN));
+        
+        // 模拟 UDP 上游：先发送问题节为小写的伪造应答，再发送回显原始大小写的真实应答
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = socket.local_addr().unwrap();
+        let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let received_clone = Arc::clone(&received);
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 4096];
+            loop {
+                let Ok((len, peer)) = socket.recv_from(&mut buf).await else { break };
+                let query = Message::from_vec(&buf[..len]).unwrap();
+                received_clone.lock().unwrap().push(query.queries()[0].name().to_ascii());
+                
+                let mut spoofed_query = query.clone();
+                let lowered = spoofed_query.queries()[0].name().to_lowercase();
+                spoofed_query.queries_mut()[0].set_name(lowered);
+                let spoofed = create_test_response(&spoofed_query, Ipv4Addr::new(6, 6, 6, 6));
+                let _ = socket.send_to(&spoofed.to_vec().unwrap(), peer).await;
+                
+                let genuine = create_test_response(&query, Ipv4Addr::new(192, 0, 2, 20));
+                let _ = socket.send_to(&genuine.to_vec().unwrap(), peer).await;
+            }
+        });
+        
+        let mut config = create_test_config();
+        config.dns.upstream.resolvers = vec![
+            ResolverConfig {
+                address: server_addr.to_string(),
+                protocol: ResolverProtocol::Udp,
+                protocols: None,
+                tls_name: None,
+                dns_0x20: true,
+            }
+        ];
+        config.test().unwrap();
+        let upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+        
+        let resolver_label = server_addr.to_string();
+        let mismatches_before = METRICS.upstream_0x20_mismatches_total().with_label_values(&[&resolver_label]).get();
+        
+        let domain = "abcdefghijklmnopqrstuvwxyz.example.com";
+        let query = create_test_query(domain, RecordType::A);
+        let response = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await.unwrap();
+        
+        // 上游收到的是随机大小写的名称
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].eq_ignore_ascii_case(&format!("{}.", domain)));
+        assert_ne!(received[0], format!("{}.", domain), "Outbound query name should use randomized case");
+        
+        // 伪造应答被丢弃，客户端得到真实应答，ID 与问题节恢复为原始查询
+        assert_eq!(response.id(), query.id());
+        let original_name = query.queries()[0].name().to_ascii();
+        assert_eq!(response.queries()[0].name().to_ascii(), original_name);
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].data(), Some(&RData::A(A::new(192, 0, 2, 20))));
+        assert_eq!(response.answers()[0].name().to_ascii(), original_name);
+        assert_eq!(
+            METRICS.upstream_0x20_mismatches_total().with_label_values(&[&resolver_label]).get(),
+            mismatches_before + 1
+        );
+        
+        // 0x20 仅适用于 UDP 解析器
+        let mut invalid = create_test_config();
+        invalid.dns.upstream.resolvers[0].protocol = ResolverProtocol::Tcp;
+        invalid.dns.upstream.resolvers[0].dns_0x20 = true;
+        assert!(invalid.test().is_err());
+        
+        info!("Test completed: test_upstream_dns_0x20");
+    }
+    
+    #[tokio::test]
+    async fn test_upstream_discards_mismatched_id() {
+        use hickory_proto::rr::RData;
+        use hickory_proto::rr::rdata::A;
+        
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_discards_mismatched_id");
+        
+        // 模拟 UDP 上游：先发送 ID 不一致的伪造应答，再发送真实应答
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 4096];
+            loop {
+                let Ok((len, peer)) = socket.recv_from(&mut buf).await else { break };
+                let query = Message::from_vec(&buf[..len]).unwrap();
+                
+                let mut spoofed = create_test_response(&query, Ipv4Addr::new(6, 6, 6, 6));
+                spoofed.set_id(query.id().wrapping_add(1));
+                let _ = socket.send_to(&spoofed.to_vec().unwrap(), peer).await;
+                
+                let genuine = create_test_response(&query, Ipv4Addr::new(192, 0, 2, 21));
+                let _ = socket.send_to(&genuine.to_vec().unwrap(), peer).await;
+            }
+        });
+        
+        // 标准 UDP 传输与 0x20 UDP 传输均丢弃伪造应答并继续等待
+        for dns_0x20 in [false, true] {
+            let mut config = create_test_config();
+            config.dns.upstream.resolvers = vec![
+                ResolverConfig {
+                    address: server_addr.to_string(),
+                    protocol: ResolverProtocol::Udp,
+                    protocols: None,
+                    tls_name: None,
+                    dns_0x20,
+                }
+            ];
+            config.test().unwrap();
+            let upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+            let mismatches_before = METRICS.upstream_id_mismatches_total().with_label_values(&[&server_addr.to_string()]).get();
+            
+            let query = create_test_query("id-mismatch.example.com", RecordType::A);
+            let response = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await.unwrap();
+            
+            assert_eq!(response.id(), query.id());
+            assert_eq!(response.answers().len(), 1, "dns_0x20={}", dns_0x20);
+            assert_eq!(response.answers()[0].data(), Some(&RData::A(A::new(192, 0, 2, 21))), "dns_0x20={}", dns_0x20);
+            
+            // 0x20 传输统计被丢弃的伪造应答
+            if dns_0x20 {
+                assert!(METRICS.upstream_id_mismatches_total().with_label_values(&[&server_addr.to_string()]).get() > mismatches_before);
+            }
+        }
+        
+        info!("Test completed: test_upstream_discards_mismatched_id");
+    }
+    
+    // 自定义解析器：记录收到的查询并返回固定 IP 的应答
+    struct CapturingResolver {
+        captured: Arc<std::sync::Mutex<Vec<Message>>>,
+    }
+
+    impl UpstreamResolver for CapturingResolver {
+        fn id(&self) -> &str {
+            "capturing"
+        }
+
+        fn protocol(&self) -> &str {
+            "Custom"
+        }
+
+        fn resolve<'a>(&'a self, query_message: &'a Message) -> futures::future::BoxFuture<'a, oxide_wdns::server::error::Result<Message>> {
+            self.captured.lock().unwrap().push(query_message.clone());
+            Box::pin(async move { Ok(create_test_response(query_message, Ipv4Addr::new(10, 0, 0, 2))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upstream_minimize_query() {
+        use hickory_proto::op::Edns;
+        use hickory_proto::rr::{Name, RData, Record};
+        use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_minimize_query");
+
+        // 客户端查询：附加节携带多余的 A 记录，EDNS 携带 DO 位与 Cookie 选项，并设置 CD 位
+        let mut query = create_test_query("minimize.example.com", RecordType::A);
+        query.set_checking_disabled(true);
+        query.add_additional(Record::from_rdata(
+            Name::from_ascii("extra.example.com.").unwrap(),
+            60,
+            RData::A(hickory_proto::rr::rdata::A(Ipv4Addr::new(192, 0, 2, 99))),
+        ));
+        let mut edns = Edns::new();
+        edns.set_max_payload(1232).set_dnssec_ok(true);
+        edns.options_mut().insert(EdnsOption::Unknown(10, vec![1, 2, 3, 4, 5, 6, 7, 8]));
+        query.set_edns(edns);
+        let query = Message::from_vec(&query.to_vec().unwrap()).unwrap();
+
+        let forwarded_query = |minimize: bool| {
+            let query = query.clone();
+            async move {
+                let mut config = create_test_config();
+                config.dns.upstream.minimize_query = minimize;
+                let captured = Arc::new(std::sync::Mutex::new(Vec::new()));
+                let mut upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+                upstream_manager
+                    .register_resolver(&UpstreamSelection::Global, Arc::new(CapturingResolver { captured: captured.clone() }))
+                    .unwrap();
+                let response = upstream_manager.resolve(&query, UpstreamSelection::Global, None, None).await.unwrap();
+                assert_eq!(response.response_code(), ResponseCode::NoError);
+                let forwarded = captured.lock().unwrap().pop().expect("Query should reach the upstream");
+                // 经线路格式往返，与上游实际收到的报文一致
+                Message::from_vec(&forwarded.to_vec().unwrap()).unwrap()
+            }
+        };
+
+        // 默认精简：仅保留问题节、标志位与自身 OPT 记录
+        let forwarded = forwarded_query(true).await;
+        assert_eq!(forwarded.id(), query.id());
+        assert_eq!(forwarded.queries(), query.queries());
+        assert_eq!(forwarded.recursion_desired(), query.recursion_desired());
+        assert!(forwarded.checking_disabled());
+        assert!(forwarded.additionals().is_empty(), "Client additional records should not be forwarded");
+        let edns = forwarded.extensions().as_ref().expect("Forwarded query should carry an OPT record");
+        assert!(edns.dnssec_ok());
+        assert_eq!(edns.max_payload(), 1232);
+        assert!(edns.option(EdnsCode::from(10)).is_none(), "Client EDNS options should not be forwarded");
+
+        // 关闭精简时原样转发
+        let forwarded = forwarded_query(false).await;
+        assert_eq!(forwarded.additionals().len(), 1);
+        assert!(forwarded.extensions().as_ref().unwrap().option(EdnsCode::from(10)).is_some());
+
+        info!("Test completed: test_upstream_minimize_query");
+    }
+    
+    // 测试上游组独立连接池的参数继承与连接指标
+    #[tokio::test]
+    async fn test_upstream_group_connection_pool() {
+        // 启用 tracing 日志
+        let _ = tracing_subscriber::fmt().with_env_filter("debug").try_init();
+        info!("Starting test: test_upstream_group_connection_pool");
+        
+        let (mock_server, counter) = setup_mock_doh_server(Ipv4Addr::new(192, 168, 1, 20)).await;
+        let resolver_url = format!("{}/dns-query", mock_server.uri());
+        
+        let config_str = format!(r#"
+        http_server:
+          listen_addr: "127.0.0.1:8053"
+          timeout: 10
+          rate_limit:
+            enabled: false
+        dns_resolver:
+          upstream:
+            resolvers:
+              - address: "8.8.8.8:53"
+                protocol: udp
+            query_timeout: 3
+            enable_dnssec: false
+          http_client:
+            timeout: 5
+            pool:
+              idle_timeout: 60
+              max_idle_connections: 20
+          cache:
+            enabled: false
+          routing:
+            enabled: true
+            upstream_groups:
+              - name: "pooled_group"
+                pool_idle_timeout: 30
+                resolvers:
+                  - address: "{0}"
+                    protocol: doh
+              - name: "shared_group"
+                resolvers:
+                  - address: "{0}"
+                    protocol: doh
+        "#, resolver_url);
+        let config: ServerConfig = serde_yaml::from_str(&config_str).unwrap();
+        
+        // 覆盖连接池参数的组继承未覆盖的全局参数，未覆盖的组使用共享客户端
+        let pool = config.get_group_pool_config("pooled_group").expect("Group should have its own pool");
+        assert_eq!(pool.idle_timeout, 30);
+        assert_eq!(pool.max_idle_connections, 20);
+        assert!(config.get_group_pool_config("shared_group").is_none());
+        
+        let upstream_manager = UpstreamManager::new(Arc::new(config), Client::new()).await.unwrap();
+        let opened = || METRICS.upstream_connections_opened_total().with_label_values(&["pooled_group"]).get();
+        assert_eq!(opened(), 0);
+        
+        // 同一组的连续查询复用连接池中的空闲连接
+        for _ in 0..3 {
+            let query = create_test_query("pool.example.com", RecordType::A);
+            let selection = UpstreamSelection::Group("pooled_group".to_string());
+            let response = upstream_manager.resolve(&query, selection, None, None).await.unwrap();
+            assert_eq!(response.response_code(), ResponseCode::NoError);
+        }
+        assert_eq!(*counter.lock().unwrap(), 3);
+        assert_eq!(opened(), 1, "Idle connection should be reused");
+        
+        // 请求完成后进行中的请求数归零
+        assert_eq!(METRICS.upstream_active_requests().with_label_values(&[&resolver_url]).get(), 0);
+        
+        info!("Test completed: test_upstream_group_connection_pool");
+    }
+}